@@ -7,7 +7,7 @@ mod service;
 mod cli;
 mod command;
 
-fn main() -> sc_cli::Result<()> {
+fn main() {
 	let version = sc_cli::VersionInfo {
 		name: "Substrate Node",
 		commit: env!("VERGEN_SHA_SHORT"),
@@ -19,5 +19,8 @@ fn main() -> sc_cli::Result<()> {
 		copyright_start_year: 2017,
 	};
 
-	command::run(version)
+	if let Err(error) = command::run(version) {
+		eprintln!("Error: {}", error);
+		std::process::exit(error.exit_code());
+	}
 }
@@ -160,7 +160,8 @@ pub fn new_full(config: Configuration)
 			inherent_data_providers: inherent_data_providers.clone(),
 			telemetry_on_connect: Some(service.telemetry_on_connect_stream()),
 			voting_rule: grandpa::VotingRulesBuilder::default().build(),
-			prometheus_registry: service.prometheus_registry()
+			prometheus_registry: service.prometheus_registry(),
+			health_sink: service.health_sink(),
 		};
 
 		// the GRANDPA voter task is considered infallible, i.e.
@@ -18,7 +18,7 @@
 
 #![warn(missing_docs)]
 
-fn main() -> sc_cli::Result<()> {
+fn main() {
 	let version = sc_cli::VersionInfo {
 		name: "Substrate Node",
 		commit: env!("VERGEN_SHA_SHORT"),
@@ -30,5 +30,9 @@ fn main() -> sc_cli::Result<()> {
 		copyright_start_year: 2017,
 	};
 
-	node_cli::run(std::env::args(), version)
+	// exit with the code of the error category, for the automation supervising the node.
+	if let Err(error) = node_cli::run(std::env::args(), version) {
+		eprintln!("Error: {}", error);
+		std::process::exit(error.exit_code());
+	}
 }
@@ -53,6 +53,10 @@ pub struct Extensions {
 	pub fork_blocks: sc_client::ForkBlocks<Block>,
 	/// Known bad block hashes.
 	pub bad_blocks: sc_client::BadBlocks<Block>,
+	/// Whether the secondary slots of BABE are claimed, unless overridden on the command line.
+	pub babe_secondary_slots: Option<sc_consensus_babe::SecondarySlots>,
+	/// Proportion of the slot given to build a block, unless overridden on the command line.
+	pub babe_proposal_slot_proportion: Option<sc_consensus_babe::SlotProportion>,
 }
 
 /// Specialized `ChainSpec`.
@@ -428,4 +432,36 @@ pub(crate) mod tests {
 	fn test_staging_test_net_chain_spec() {
 		staging_testnet_config().build_storage().unwrap();
 	}
+
+	#[test]
+	fn test_babe_extensions() {
+		let extensions: Extensions = serde_json::from_str(r#"{
+			"forkBlocks": null,
+			"badBlocks": null,
+			"babeSecondarySlots": "skip",
+			"babeProposalSlotProportion": 0.8
+		}"#).unwrap();
+		assert_eq!(
+			sc_chain_spec::get_extension::<Option<sc_consensus_babe::SecondarySlots>>(&extensions),
+			Some(&Some(sc_consensus_babe::SecondarySlots::Skip)),
+		);
+		assert_eq!(
+			extensions.babe_proposal_slot_proportion.map(|proportion| proportion.get()),
+			Some(0.8),
+		);
+
+		// the proportions outside (0, 1] are rejected, as on the command line.
+		assert!(serde_json::from_str::<Extensions>(r#"{
+			"forkBlocks": null,
+			"badBlocks": null,
+			"babeProposalSlotProportion": 1.5
+		}"#).is_err());
+
+		// the chain specs without the extensions claim the secondary slots.
+		let extensions: Extensions = serde_json::from_str(
+			r#"{ "forkBlocks": null, "badBlocks": null }"#
+		).unwrap();
+		assert_eq!(extensions.babe_secondary_slots, None);
+		assert_eq!(extensions.babe_proposal_slot_proportion, None);
+	}
 }
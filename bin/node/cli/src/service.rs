@@ -88,21 +88,38 @@ macro_rules! new_full_start {
 				import_setup = Some((block_import, grandpa_link, babe_link));
 				Ok(import_queue)
 			})?
-			.with_rpc_extensions(|builder| -> Result<RpcExtension, _> {
+			.with_rpc_extensions_builder(|builder| {
 				let babe_link = import_setup.as_ref().map(|s| &s.2)
 					.expect("BabeLink is present for full services or set up failed; qed.");
-				let deps = node_rpc::FullDeps {
-					client: builder.client().clone(),
-					pool: builder.pool(),
-					select_chain: builder.select_chain().cloned()
-						.expect("SelectChain is present for full services or set up failed; qed."),
-					babe: node_rpc::BabeDeps {
-						keystore: builder.keystore(),
-						babe_config: sc_consensus_babe::BabeLink::config(babe_link).clone(),
-						shared_epoch_changes: sc_consensus_babe::BabeLink::epoch_changes(babe_link).clone()
-					}
-				};
-				Ok(node_rpc::create_full(deps))
+				let client = builder.client().clone();
+				let pool = builder.pool();
+				let select_chain = builder.select_chain().cloned()
+					.expect("SelectChain is present for full services or set up failed; qed.");
+				let keystore = builder.keystore();
+				let babe_config = sc_consensus_babe::BabeLink::config(babe_link).clone();
+				let shared_epoch_changes = sc_consensus_babe::BabeLink::epoch_changes(babe_link).clone();
+				let missed_slots = sc_consensus_babe::BabeLink::missed_slots(babe_link).clone();
+				let round_state = import_setup.as_ref().map(|s| s.1.round_state().clone())
+					.expect("GRANDPA LinkHalf is present for full services or set up failed; qed.");
+
+				Ok(move |deny_unsafe: sc_rpc::DenyUnsafe| -> RpcExtension {
+					let deps = node_rpc::FullDeps {
+						client: client.clone(),
+						pool: pool.clone(),
+						select_chain: select_chain.clone(),
+						deny_unsafe,
+						babe: node_rpc::BabeDeps {
+							keystore: keystore.clone(),
+							babe_config: babe_config.clone(),
+							shared_epoch_changes: shared_epoch_changes.clone(),
+							missed_slots: missed_slots.clone(),
+						},
+						grandpa: node_rpc::GrandpaDeps {
+							round_state: round_state.clone(),
+						},
+					};
+					node_rpc::create_full(deps)
+				})
 			})?;
 
 		(builder, import_setup, inherent_data_providers)
@@ -133,6 +150,26 @@ macro_rules! new_full {
 			$config.network.sentry_nodes.clone(),
 		);
 
+		let secondary_slots = $config.secondary_slots.unwrap_or_else(|| {
+			sc_chain_spec::get_extension::<Option<sc_consensus_babe::SecondarySlots>>(
+				$config.expect_chain_spec().extensions()
+			)
+				.cloned()
+				.flatten()
+				.unwrap_or_default()
+		});
+		let max_unfinalized_blocks = $config.max_unfinalized_blocks;
+		let proposal_slot_proportion = match $config.proposal_slot_proportion {
+			Some(proportion) => sc_consensus_babe::SlotProportion::new(proportion)
+				.map_err(ServiceError::Other)?,
+			None => sc_chain_spec::get_extension::<Option<sc_consensus_babe::SlotProportion>>(
+				$config.expect_chain_spec().extensions()
+			)
+				.cloned()
+				.flatten()
+				.unwrap_or_default(),
+		};
+
 		// sentry nodes announce themselves as authorities to the network
 		// and should run the same protocols authorities do, but it should
 		// never actively participate in any consensus process.
@@ -153,6 +190,13 @@ macro_rules! new_full {
 
 		($with_startup_data)(&block_import, &babe_link);
 
+		let equivocation_reports = sc_consensus_babe::submit_equivocation_reports(
+			service.client(),
+			service.transaction_pool(),
+			babe_link.equivocation_reports().subscribe(),
+		);
+		service.spawn_task("babe-equivocation-reports", equivocation_reports);
+
 		if participates_in_consensus {
 			let proposer = sc_basic_authorship::ProposerFactory::new(
 				service.client(),
@@ -175,8 +219,13 @@ macro_rules! new_full {
 				sync_oracle: service.network(),
 				inherent_data_providers: inherent_data_providers.clone(),
 				force_authoring,
+				secondary_slots,
+				max_unfinalized_blocks,
+				proposal_slot_proportion,
 				babe_link,
 				can_author_with,
+				prometheus_registry: service.prometheus_registry(),
+				health_sink: service.health_sink(),
 			};
 
 			let babe = sc_consensus_babe::start_babe(babe_config)?;
@@ -233,6 +282,7 @@ macro_rules! new_full {
 				telemetry_on_connect: Some(service.telemetry_on_connect_stream()),
 				voting_rule: grandpa::VotingRulesBuilder::default().build(),
 				prometheus_registry: service.prometheus_registry(),
+				health_sink: service.health_sink(),
 			};
 
 			// the GRANDPA voter task is considered infallible, i.e.
@@ -0,0 +1,47 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+#![cfg(unix)]
+
+use assert_cmd::cargo::cargo_bin;
+use std::process::Command;
+use tempfile::tempdir;
+
+mod common;
+
+#[test]
+fn audit_works() {
+	let base_path = tempdir().expect("could not create a temp dir");
+	let attestation = base_path.path().join("attestation.json");
+
+	common::run_dev_node_for_a_while(base_path.path());
+
+	let status = Command::new(cargo_bin("substrate"))
+		.args(&["audit", "--dev", "--pruning", "archive", "--blocks", "2", "--suri", "//Alice"])
+		.arg("-d")
+		.arg(base_path.path())
+		.arg("--output")
+		.arg(&attestation)
+		.status()
+		.unwrap();
+	assert!(status.success());
+
+	let attestation: serde_json::Value = serde_json::from_slice(
+		&std::fs::read(&attestation).unwrap()
+	).unwrap();
+	assert!(attestation["signature"].is_string());
+	assert!(attestation["audit"]["blocks"].as_array().map_or(false, |blocks| !blocks.is_empty()));
+}
@@ -0,0 +1,53 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+#![cfg(unix)]
+
+use assert_cmd::cargo::cargo_bin;
+use std::process::Command;
+use tempfile::tempdir;
+
+mod common;
+
+#[test]
+fn chain_info_works() {
+	let base_path = tempdir().expect("could not create a temp dir");
+
+	common::run_dev_node_for_a_while(base_path.path());
+
+	let output = Command::new(cargo_bin("substrate"))
+		.args(&["chain-info", "--dev", "--json", "-d"])
+		.arg(base_path.path())
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+
+	let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert!(info["bestNumber"].as_u64().unwrap() > 0);
+	assert_eq!(info["pruning"], "archive");
+	assert!(info["lastPruned"].is_null());
+	assert!(info["columns"].as_array().unwrap().iter().any(|column| column["name"] == "header"));
+
+	// The database is opened read-only, so a missing one isn't created.
+	let missing = base_path.path().join("missing");
+	let status = Command::new(cargo_bin("substrate"))
+		.args(&["chain-info", "--dev", "-d"])
+		.arg(&missing)
+		.status()
+		.unwrap();
+	assert!(!status.success());
+	assert!(!missing.join("chains").join("dev").join("db").exists());
+}
@@ -36,3 +36,29 @@ fn check_block_works() {
 		.unwrap();
 	assert!(status.success());
 }
+
+#[test]
+fn check_block_from_file_works() {
+	let base_path = tempdir().expect("could not create a temp dir");
+	let exported_block = base_path.path().join("exported_block");
+
+	common::run_dev_node_for_a_while(base_path.path());
+
+	let status = Command::new(cargo_bin("substrate"))
+		.args(&["export-blocks", "--dev", "--pruning", "archive", "--from", "1", "--to", "1"])
+		.args(&["--binary", "true", "-d"])
+		.arg(base_path.path())
+		.arg(&exported_block)
+		.status()
+		.unwrap();
+	assert!(status.success());
+
+	let status = Command::new(cargo_bin("substrate"))
+		.args(&["check-block", "--dev", "--pruning", "archive", "-d"])
+		.arg(base_path.path())
+		.arg("--file")
+		.arg(&exported_block)
+		.status()
+		.unwrap();
+	assert!(status.success());
+}
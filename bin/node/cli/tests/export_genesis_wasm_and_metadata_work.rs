@@ -0,0 +1,47 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+#![cfg(unix)]
+
+use assert_cmd::cargo::cargo_bin;
+use std::process::Command;
+use tempfile::tempdir;
+
+#[test]
+fn export_genesis_wasm_and_metadata_work() {
+	let base_path = tempdir().expect("could not create a temp dir");
+	let wasm = base_path.path().join("genesis.wasm");
+	let metadata = base_path.path().join("metadata.scale");
+
+	let status = Command::new(cargo_bin("substrate"))
+		.args(&["export-genesis-wasm", "--dev", "--raw"])
+		.arg(&wasm)
+		.status()
+		.unwrap();
+	assert!(status.success());
+	assert!(std::fs::read(&wasm).unwrap().starts_with(b"\0asm"));
+
+	let status = Command::new(cargo_bin("substrate"))
+		.args(&["export-metadata", "--dev", "--raw"])
+		.arg("-d")
+		.arg(base_path.path())
+		.arg(&metadata)
+		.status()
+		.unwrap();
+	assert!(status.success());
+	// Metadata starts with the `meta` magic number.
+	assert!(std::fs::read(&metadata).unwrap().starts_with(b"meta"));
+}
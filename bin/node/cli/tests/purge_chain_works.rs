@@ -26,6 +26,7 @@ fn purge_chain_works() {
 	let base_path = tempdir().expect("could not create a temp dir");
 
 	common::run_dev_node_for_a_while(base_path.path());
+	std::fs::create_dir_all(base_path.path().join("chains/dev/keystore")).unwrap();
 
 	let status = Command::new(cargo_bin("substrate"))
 		.args(&["purge-chain", "--dev", "-d"])
@@ -38,4 +39,40 @@ fn purge_chain_works() {
 	// Make sure that the `dev` chain folder exists, but the `db` is deleted.
 	assert!(base_path.path().join("chains/dev/").exists());
 	assert!(!base_path.path().join("chains/dev/db").exists());
+	assert!(base_path.path().join("chains/dev/keystore").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn purge_chain_keeping_identity_works() {
+	let base_path = tempdir().expect("could not create a temp dir");
+	let chain_path = base_path.path().join("chains/dev");
+
+	common::run_dev_node_for_a_while(base_path.path());
+	std::fs::create_dir_all(chain_path.join("keystore")).unwrap();
+	std::fs::create_dir_all(chain_path.join("network")).unwrap();
+
+	let status = Command::new(cargo_bin("substrate"))
+		.args(&["purge-chain", "--dev", "--all", "--keep-keystore", "--keep-network-key", "-d"])
+		.arg(base_path.path())
+		.arg("-y")
+		.status()
+		.unwrap();
+	assert!(status.success());
+
+	assert!(!chain_path.join("db").exists());
+	assert!(chain_path.join("keystore").exists());
+	assert!(chain_path.join("network").exists());
+
+	let status = Command::new(cargo_bin("substrate"))
+		.args(&["purge-chain", "--dev", "--all", "-d"])
+		.arg(base_path.path())
+		.arg("-y")
+		.status()
+		.unwrap();
+	assert!(status.success());
+
+	assert!(chain_path.exists());
+	assert!(!chain_path.join("keystore").exists());
+	assert!(!chain_path.join("network").exists());
 }
@@ -0,0 +1,48 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+#![cfg(unix)]
+
+use assert_cmd::cargo::cargo_bin;
+use std::{path::Path, process::Command};
+use tempfile::tempdir;
+
+mod common;
+
+fn best_number(base_path: &Path) -> u64 {
+	let output = Command::new(cargo_bin("substrate"))
+		.args(&["chain-info", "--dev", "--json", "-d"])
+		.arg(base_path)
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+
+	let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	info["bestNumber"].as_u64().unwrap()
+}
+
+#[test]
+fn restart_after_shutdown_works() {
+	let base_path = tempdir().expect("could not create a temp dir");
+
+	common::run_dev_node_for_a_while(base_path.path());
+	let first_best = best_number(base_path.path());
+	assert!(first_best > 0);
+
+	// The node must start again on the database it flushed, and build on top of its blocks.
+	common::run_dev_node_for_a_while(base_path.path());
+	assert!(best_number(base_path.path()) > first_best);
+}
@@ -31,7 +31,7 @@
 
 use std::{sync::Arc, fmt};
 
-use node_primitives::{Block, BlockNumber, AccountId, Index, Balance};
+use node_primitives::{Block, BlockNumber, AccountId, Index, Balance, Hash};
 use node_runtime::UncheckedExtrinsic;
 use sp_api::ProvideRuntimeApi;
 use sp_transaction_pool::TransactionPool;
@@ -40,8 +40,11 @@ use sp_consensus::SelectChain;
 use sc_keystore::KeyStorePtr;
 use sp_consensus_babe::BabeApi;
 use sc_consensus_epochs::SharedEpochChanges;
-use sc_consensus_babe::{Config, Epoch};
+use sc_consensus_babe::{Config, Epoch, MissedSlots};
 use sc_consensus_babe_rpc::BabeRPCHandler;
+use sc_finality_grandpa::SharedRoundState;
+use sc_finality_grandpa_rpc::GrandpaRpcHandler;
+use sc_rpc_api::DenyUnsafe;
 
 /// Light client extra dependencies.
 pub struct LightDeps<C, F, P> {
@@ -63,6 +66,14 @@ pub struct BabeDeps {
 	pub shared_epoch_changes: SharedEpochChanges<Block, Epoch>,
 	/// The keystore that manages the keys of the node.
 	pub keystore: KeyStorePtr,
+	/// The slots the node was scheduled for but didn't author.
+	pub missed_slots: MissedSlots,
+}
+
+/// Extra dependencies for GRANDPA.
+pub struct GrandpaDeps {
+	/// The votes seen by the voter in its recent rounds.
+	pub round_state: SharedRoundState<Block>,
 }
 
 /// Full client dependencies.
@@ -73,8 +84,12 @@ pub struct FullDeps<C, P, SC> {
 	pub pool: Arc<P>,
 	/// The SelectChain Strategy
 	pub select_chain: SC,
+	/// Whether to deny unsafe calls
+	pub deny_unsafe: DenyUnsafe,
 	/// BABE specific dependencies.
 	pub babe: BabeDeps,
+	/// GRANDPA specific dependencies.
+	pub grandpa: GrandpaDeps,
 }
 
 /// Instantiate all Full RPC extensions.
@@ -83,35 +98,46 @@ pub fn create_full<C, P, M, SC>(
 ) -> jsonrpc_core::IoHandler<M> where
 	C: ProvideRuntimeApi<Block>,
 	C: HeaderBackend<Block> + HeaderMetadata<Block, Error=BlockChainError> + 'static,
+	C: sc_client_api::BlockBackend<Block>,
 	C: Send + Sync + 'static,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
+	C::Api: substrate_frame_rpc_system::EventsApi<Block>,
+	C::Api: sp_block_builder::BlockBuilder<Block>,
 	C::Api: pallet_contracts_rpc::ContractsRuntimeApi<Block, AccountId, Balance, BlockNumber>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance, UncheckedExtrinsic>,
+	C::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
 	C::Api: BabeApi<Block>,
 	<C::Api as sp_api::ApiErrorExt>::Error: fmt::Debug,
-	P: TransactionPool + 'static,
+	P: TransactionPool<Block = Block> + 'static,
 	M: jsonrpc_core::Metadata + Default,
 	SC: SelectChain<Block> +'static,
 {
 	use substrate_frame_rpc_system::{FullSystem, SystemApi};
 	use pallet_contracts_rpc::{Contracts, ContractsApi};
-	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
+	use pallet_transaction_payment_rpc::{
+		TransactionPayment, TransactionPaymentApi, BlockTemplate, BlockTemplateApi, BlockLimits,
+		FeeStatistics, FeeStatisticsApi,
+	};
+	use node_runtime::{MaximumBlockWeight, MaximumBlockLength, AvailableBlockRatio};
 
 	let mut io = jsonrpc_core::IoHandler::default();
 	let FullDeps {
 		client,
 		pool,
 		select_chain,
-		babe
+		deny_unsafe,
+		babe,
+		grandpa,
 	} = deps;
 	let BabeDeps {
 		keystore,
 		babe_config,
 		shared_epoch_changes,
+		missed_slots,
 	} = babe;
 
 	io.extend_with(
-		SystemApi::to_delegate(FullSystem::new(client.clone(), pool))
+		SystemApi::to_delegate(FullSystem::new(client.clone(), pool.clone(), deny_unsafe))
 	);
 	// Making synchronous calls in light client freezes the browser currently,
 	// more context: https://github.com/paritytech/substrate/pull/3480
@@ -122,9 +148,34 @@ pub fn create_full<C, P, M, SC>(
 	io.extend_with(
 		TransactionPaymentApi::to_delegate(TransactionPayment::new(client.clone()))
 	);
+	io.extend_with(
+		FeeStatisticsApi::to_delegate(FeeStatistics::new(client.clone(), pool.clone(), deny_unsafe))
+	);
+	// Transactions of the normal dispatch class may only fill the available part of the block.
+	let block_limits = BlockLimits {
+		max_weight: AvailableBlockRatio::get() * MaximumBlockWeight::get(),
+		max_length: AvailableBlockRatio::get() * MaximumBlockLength::get(),
+	};
+	io.extend_with(
+		BlockTemplateApi::to_delegate(
+			BlockTemplate::<_, _, UncheckedExtrinsic>::new(client.clone(), pool, block_limits, deny_unsafe)
+		)
+	);
 	io.extend_with(
 		sc_consensus_babe_rpc::BabeApi::to_delegate(
-			BabeRPCHandler::new(client, shared_epoch_changes, keystore, babe_config, select_chain)
+			BabeRPCHandler::new(
+				client,
+				shared_epoch_changes,
+				keystore,
+				babe_config,
+				select_chain,
+				missed_slots,
+			)
+		)
+	);
+	io.extend_with(
+		sc_finality_grandpa_rpc::GrandpaApi::to_delegate(
+			GrandpaRpcHandler::new(grandpa.round_state)
 		)
 	);
 
@@ -151,7 +202,9 @@ pub fn create_light<C, P, M, F>(
 	} = deps;
 	let mut io = jsonrpc_core::IoHandler::default();
 	io.extend_with(
-		SystemApi::<AccountId, Index>::to_delegate(LightSystem::new(client, remote_blockchain, fetcher, pool))
+		SystemApi::<Hash, AccountId, Index>::to_delegate(
+			LightSystem::new(client, remote_blockchain, fetcher, pool)
+		)
 	);
 
 	io
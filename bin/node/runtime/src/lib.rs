@@ -21,6 +21,7 @@
 #![recursion_limit="256"]
 
 use sp_std::prelude::*;
+use codec::Encode;
 use frame_support::{
 	construct_runtime, parameter_types, debug,
 	weights::Weight,
@@ -83,7 +84,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	// and set impl_version to 0. If only runtime
 	// implementation changes and behavior does not, then leave spec_version as
 	// is and increment impl_version.
-	spec_version: 239,
+	spec_version: 242,
 	impl_version: 0,
 	apis: RUNTIME_API_VERSIONS,
 };
@@ -171,6 +172,7 @@ impl pallet_babe::Trait for Runtime {
 	type EpochDuration = EpochDuration;
 	type ExpectedBlockTime = ExpectedBlockTime;
 	type EpochChangeTrigger = pallet_babe::ExternalTrigger;
+	type HandleEquivocation = pallet_babe::EquivocationHandler<Offences>;
 }
 
 parameter_types! {
@@ -633,7 +635,7 @@ construct_runtime!(
 	{
 		System: frame_system::{Module, Call, Config, Storage, Event<T>},
 		Utility: pallet_utility::{Module, Call, Storage, Event<T>},
-		Babe: pallet_babe::{Module, Call, Storage, Config, Inherent(Timestamp)},
+		Babe: pallet_babe::{Module, Call, Storage, Config, Inherent(Timestamp), ValidateUnsigned},
 		Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
 		Authorship: pallet_authorship::{Module, Call, Storage, Inherent},
 		Indices: pallet_indices::{Module, Call, Storage, Config<T>, Event<T>},
@@ -734,6 +736,12 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl sp_block_builder::BlockWeight<Block> for Runtime {
+		fn extrinsics_weight(extrinsics: Vec<<Block as BlockT>::Extrinsic>) -> u64 {
+			Executive::extrinsics_weight(&extrinsics)
+		}
+	}
+
 	impl sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block> for Runtime {
 		fn validate_transaction(tx: <Block as BlockT>::Extrinsic) -> TransactionValidity {
 			Executive::validate_transaction(tx)
@@ -772,6 +780,13 @@ impl_runtime_apis! {
 		fn current_epoch_start() -> sp_consensus_babe::SlotNumber {
 			Babe::current_epoch_start()
 		}
+
+		fn report_equivocation_extrinsic(
+			equivocation_proof: sp_consensus_babe::EquivocationProof<Header>,
+		) -> Option<<Block as BlockT>::Extrinsic> {
+			let call = pallet_babe::Call::report_equivocation(equivocation_proof);
+			Some(UncheckedExtrinsic::new_unsigned(call.into()))
+		}
 	}
 
 	impl sp_authority_discovery::AuthorityDiscoveryApi<Block> for Runtime {
@@ -786,6 +801,12 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl frame_system_rpc_runtime_api::EventsApi<Block> for Runtime {
+		fn events() -> Vec<u8> {
+			System::events().encode()
+		}
+	}
+
 	impl pallet_contracts_rpc_runtime_api::ContractsApi<Block, AccountId, Balance, BlockNumber>
 		for Runtime
 	{
@@ -268,13 +268,15 @@ fn get_app<'a, 'b>(usage: &'a str) -> App<'a, 'b> {
 				"),
 			SubCommand::with_name("sign-transaction")
 				.about("Sign transaction from encoded Call. Returns a signed and encoded \
-						UncheckedMortalCompactExtrinsic as hex.")
+						immortal UncheckedExtrinsic as hex, ready to be submitted with \
+						`author_submitExtrinsic`.")
 				.args_from_usage("
 					-c, --call <call> 'The call, hex-encoded.'
 					-n, --nonce <nonce> 'The nonce.'
-					-p, --password <password> 'The password for the key.'
-					-h, --prior-block-hash <prior-block-hash> 'The prior block hash, hex-encoded.'
-					-s, --suri <suri> 'The secret key URI.'
+					-g, --genesis <genesis> 'The genesis hash or a recognized \
+											chain identifier (elm, alex).'
+					-s, --suri <suri> 'The secret key URI. \
+						If the value is a file, the file content is used as URI.'
 				"),
 			SubCommand::with_name("transfer")
 				.about("Author and sign a Node pallet_balances::Transfer transaction with a given (secret) key")
@@ -468,15 +470,13 @@ where
 			print_extrinsic(extrinsic);
 		}
 		("sign-transaction", Some(matches)) => {
-			let signer = read_pair::<C>(matches.value_of("suri"), password)?;
+			let suri = get_uri("suri", &matches)?;
+			let signer = read_pair::<C>(Some(&suri), password)?;
 			let index = read_required_parameter::<Index>(matches, "nonce")?;
 			let genesis_hash = read_genesis_hash(matches)?;
 
 			let call = matches.value_of("call").expect("call is required; qed");
-			let function: Call = hex::decode(&call)
-				.ok()
-				.and_then(|x| Decode::decode(&mut &x[..]).ok())
-				.unwrap();
+			let function = decode_call(call)?;
 
 			let extrinsic = create_extrinsic::<C>(function, index, signer, genesis_hash);
 
@@ -573,13 +573,20 @@ fn read_genesis_hash(matches: &ArgMatches) -> Result<H256, Error> {
 		h => Decode::decode(&mut &decode_hex(h)?[..])
 			.expect("Invalid genesis hash or unrecognized chain identifier"),
 	};
-	println!(
+	// Printed to stderr, to keep the encoded extrinsic alone on stdout.
+	eprintln!(
 		"Using a genesis hash of {}",
 		HexDisplay::from(&genesis_hash.as_ref())
 	);
 	Ok(genesis_hash)
 }
 
+fn decode_call(call: &str) -> Result<Call, Error> {
+	let call = if call.starts_with("0x") { &call[2..] } else { call };
+	Decode::decode(&mut &decode_hex(call)?[..])
+		.map_err(|e| Error::Formatted(format!("Invalid call ({})", e.what())))
+}
+
 fn read_signature<C: Crypto>(matches: &ArgMatches) -> Result<SignatureOf<C>, Error>
 where
 	SignatureOf<C>: SignatureT,
@@ -767,6 +774,22 @@ mod tests {
 		test_generate_sign_verify::<Sr25519>();
 	}
 
+	#[test]
+	fn sign_transaction_decodes_call() {
+		let call = Call::Balances(BalancesCall::transfer(AccountId::default().into(), 42));
+		let encoded = hex::encode(call.encode());
+
+		assert_eq!(decode_call(&encoded).unwrap(), call);
+		assert_eq!(decode_call(&format!("0x{}", encoded)).unwrap(), call);
+		assert!(decode_call("0x00").is_err());
+
+		let pair = Sr25519::pair_from_suri("//Alice", None);
+		let extrinsic = create_extrinsic::<Sr25519>(call.clone(), 7, pair, Default::default());
+		let decoded = UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..]).unwrap();
+		assert_eq!(decoded.function, call);
+		assert!(decoded.signature.is_some());
+	}
+
 	#[test]
 	fn should_work() {
 		let s = "0123456789012345678901234567890123456789012345678901234567890123";
@@ -32,7 +32,7 @@ use crate::{
 		Backend as BlockchainBackend, well_known_cache_keys
 	},
 	light::RemoteBlockchain,
-	UsageInfo,
+	UsageInfo, DatabaseColumnInfo,
 };
 use sp_blockchain;
 use sp_consensus::BlockOrigin;
@@ -406,6 +406,19 @@ pub trait Backend<Block: BlockT>: AuxStore + Send + Sync {
 	/// Returns current usage statistics.
 	fn usage_info(&self) -> Option<UsageInfo>;
 
+	/// Returns the size of each column of the database, or `None` if the backend isn't backed by
+	/// a database.
+	///
+	/// The sizes of a RocksDB database are estimates, which don't read the whole database.
+	fn database_columns(&self) -> Option<Vec<DatabaseColumnInfo>> {
+		None
+	}
+
+	/// Writes the buffered changes of the database to disk, if the backend is backed by one.
+	fn flush(&self) -> sp_blockchain::Result<()> {
+		Ok(())
+	}
+
 	/// Returns reference to changes trie storage.
 	fn changes_trie_storage(&self) -> Option<&dyn PrunableStateChangesTrieStorage<Block>>;
 
@@ -447,6 +460,19 @@ pub trait Backend<Block: BlockT>: AuxStore + Send + Sync {
 		AuxStore::get_aux(self, key)
 	}
 
+	/// Query the state the network keeps across restarts, such as its address book.
+	///
+	/// Backends with a dedicated storage for it should override this, the default uses the
+	/// auxiliary data.
+	fn get_network_state(&self, key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+		AuxStore::get_aux(self, key)
+	}
+
+	/// Store a value of the network state, replacing the previous one.
+	fn insert_network_state(&self, key: &[u8], value: &[u8]) -> sp_blockchain::Result<()> {
+		AuxStore::insert_aux(self, &[(key, value)], &[])
+	}
+
 	/// Gain access to the import lock around this backend.
 	///
 	/// _Note_ Backend isn't expected to acquire the lock by itself ever. Rather
@@ -16,7 +16,7 @@
 
 //! A set of APIs supported by the client along with their primitives.
 
-use std::{fmt, collections::HashSet};
+use std::{fmt, collections::HashSet, time::Duration};
 use futures::channel::mpsc;
 use sp_core::storage::StorageKey;
 use sp_runtime::{
@@ -90,6 +90,13 @@ pub trait BlockBackend<Block: BlockT> {
 
 	/// Get block justification set by id.
 	fn justification(&self, id: &BlockId<Block>) -> sp_blockchain::Result<Option<Justification>>;
+
+	/// Get the hashes of the leaves of the block tree, i.e. the heads of all known forks.
+	fn leaves(&self) -> sp_blockchain::Result<Vec<Block::Hash>>;
+
+	/// Get the time the execution of a recently imported block took. Returns `None` for the
+	/// blocks not executed since the client started, e.g. the ones authored locally.
+	fn import_time(&self, hash: &Block::Hash) -> Option<Duration>;
 }
 
 /// Provide a list of potential uncle headers for a given block.
@@ -219,6 +226,17 @@ impl fmt::Display for UsageInfo {
 	}
 }
 
+/// Size of a column of the database.
+#[derive(Clone, Debug)]
+pub struct DatabaseColumnInfo {
+	/// Name of the column.
+	pub name: &'static str,
+	/// Number of entries in the column, possibly estimated.
+	pub entries: u64,
+	/// Total size of the keys and values of the column, in bytes, possibly estimated.
+	pub bytes: u64,
+}
+
 /// Summary of an imported block
 #[derive(Clone, Debug)]
 pub struct BlockImportNotification<Block: BlockT> {
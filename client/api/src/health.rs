@@ -0,0 +1,33 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Events the consensus engines report to the health gauges of the service.
+
+use futures::channel::mpsc;
+
+/// An event of the consensus engines, from which the service computes its health gauges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthEvent {
+	/// One of our keys claimed a slot to author a block.
+	SlotClaimed,
+	/// We started voting in the given GRANDPA round.
+	RoundStarted(u64),
+	/// The given GRANDPA round was completed.
+	RoundCompleted(u64),
+}
+
+/// Sender of the events of the consensus engines to the health task of the service.
+pub type HealthSink = mpsc::UnboundedSender<HealthEvent>;
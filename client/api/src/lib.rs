@@ -21,6 +21,7 @@ pub mod backend;
 pub mod call_executor;
 pub mod client;
 pub mod execution_extensions;
+pub mod health;
 pub mod light;
 pub mod notifications;
 pub mod proof_provider;
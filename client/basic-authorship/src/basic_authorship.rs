@@ -18,9 +18,9 @@
 
 // FIXME #1021 move this into sp-consensus
 
-use std::{time, sync::Arc};
+use std::{time, sync::Arc, collections::HashMap};
 use sc_client_api::backend;
-use codec::Decode;
+use codec::{Decode, Encode};
 use sp_consensus::{evaluation, Proposal, RecordProof};
 use sp_inherents::InherentData;
 use log::{error, info, debug, trace};
@@ -28,6 +28,7 @@ use sp_core::ExecutionContext;
 use sp_runtime::{
 	generic::BlockId,
 	traits::{Block as BlockT, Hash as HashT, Header as HeaderT, DigestFor, BlakeTwo256},
+	transaction_validity::TransactionTag,
 };
 use sp_transaction_pool::{TransactionPool, InPoolTransaction};
 use sc_telemetry::{telemetry, CONSENSUS_INFO};
@@ -37,12 +38,31 @@ use futures::{executor, future, future::Either};
 use sp_blockchain::{HeaderBackend, ApplyExtrinsicFailed};
 use std::marker::PhantomData;
 
+/// Clock measuring the time left to build a block.
+pub type Clock = Arc<dyn Fn() -> time::Instant + Send + Sync>;
+
+/// Order in which the ready transactions of the pool are pushed to the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionOrdering {
+	/// The order of the pool: by priority, then by time of submission.
+	Pool,
+	/// By priority, then by encoding, so the block only depends on the set of ready
+	/// transactions and not on the order they were submitted in.
+	///
+	/// Transactions are still pushed after the transactions providing the tags they require.
+	Deterministic,
+}
+
 /// Proposer factory.
 pub struct ProposerFactory<A, B, C> {
 	/// The client instance.
 	client: Arc<C>,
 	/// The transaction pool.
 	transaction_pool: Arc<A>,
+	/// The order the transactions are pushed to blocks in.
+	ordering: TransactionOrdering,
+	/// The clock of the proposers, the system clock if `None`.
+	clock: Option<Clock>,
 	/// phantom member to pin the `Backend` type.
 	_phantom: PhantomData<B>,
 }
@@ -52,9 +72,26 @@ impl<A, B, C> ProposerFactory<A, B, C> {
 		ProposerFactory {
 			client,
 			transaction_pool,
+			ordering: TransactionOrdering::Pool,
+			clock: None,
 			_phantom: PhantomData,
 		}
 	}
+
+	/// Set the order the transactions are pushed to blocks in.
+	pub fn with_ordering(mut self, ordering: TransactionOrdering) -> Self {
+		self.ordering = ordering;
+		self
+	}
+
+	/// Set the clock the proposers measure their deadline with.
+	///
+	/// A clock that doesn't advance makes the proposers push every ready transaction that fits
+	/// in the block, however long it takes.
+	pub fn with_clock(mut self, clock: Clock) -> Self {
+		self.clock = Some(clock);
+		self
+	}
 }
 
 impl<B, Block, C, A> ProposerFactory<A, B, C>
@@ -85,6 +122,7 @@ impl<B, Block, C, A> ProposerFactory<A, B, C>
 				parent_id: id,
 				parent_number: *parent_header.number(),
 				transaction_pool: self.transaction_pool.clone(),
+				ordering: self.ordering,
 				now,
 				_phantom: PhantomData,
 			}),
@@ -113,7 +151,14 @@ impl<A, B, Block, C> sp_consensus::Environment<Block> for
 		&mut self,
 		parent_header: &<Block as BlockT>::Header,
 	) -> Self::CreateProposer {
-		future::ready(Ok(self.init_with_now(parent_header, Box::new(time::Instant::now))))
+		let now: Box<dyn Fn() -> time::Instant + Send + Sync> = match &self.clock {
+			Some(clock) => {
+				let clock = clock.clone();
+				Box::new(move || clock())
+			},
+			None => Box::new(time::Instant::now),
+		};
+		future::ready(Ok(self.init_with_now(parent_header, now)))
 	}
 }
 
@@ -129,6 +174,7 @@ struct ProposerInner<B, Block: BlockT, C, A: TransactionPool> {
 	parent_id: BlockId<Block>,
 	parent_number: <<Block as BlockT>::Header as HeaderT>::Number,
 	transaction_pool: Arc<A>,
+	ordering: TransactionOrdering,
 	now: Box<dyn Fn() -> time::Instant + Send + Sync>,
 	_phantom: PhantomData<B>,
 }
@@ -159,8 +205,9 @@ impl<A, B, Block, C> sp_consensus::Proposer<Block> for
 	) -> Self::Proposal {
 		let inner = self.inner.clone();
 		tokio_executor::blocking::run(move || {
-			// leave some time for evaluation and block finalization (33%)
-			let deadline = (inner.now)() + max_duration - max_duration / 3;
+			// the slot worker already leaves the rest of the slot for evaluation and block
+			// finalization, see `SlotProportion`.
+			let deadline = (inner.now)() + max_duration;
 			inner.propose_with(inherent_data, inherent_digests, deadline, record_proof)
 		})
 	}
@@ -222,6 +269,11 @@ impl<A, B, Block, C> ProposerInner<B, Block, C, A>
 				self.transaction_pool.ready()
 			}
 		};
+		let pending_iterator: Box<dyn Iterator<Item = _>> = match self.ordering {
+			TransactionOrdering::Pool => pending_iterator,
+			TransactionOrdering::Deterministic =>
+				Box::new(deterministic_order(pending_iterator).into_iter()),
+		};
 
 		debug!("Attempting to push transactions from the pool.");
 		debug!("Pool status: {:?}", self.transaction_pool.status());
@@ -305,6 +357,55 @@ impl<A, B, Block, C> ProposerInner<B, Block, C, A>
 	}
 }
 
+/// Order the given ready transactions by priority, then by encoding, keeping every transaction
+/// after the transactions providing the tags it requires.
+fn deterministic_order<T, I>(ready: I) -> Vec<Arc<T>> where
+	T: InPoolTransaction,
+	T::Transaction: Encode,
+	I: Iterator<Item = Arc<T>>,
+{
+	let mut pending: Vec<_> = ready.map(|tx| (tx.data().encode(), tx)).collect();
+
+	// Number of pending transactions providing each tag.
+	let mut providers = HashMap::<TransactionTag, usize>::new();
+	for (_, tx) in &pending {
+		for tag in tx.provides() {
+			*providers.entry(tag.clone()).or_default() += 1;
+		}
+	}
+
+	let mut ordered = Vec::with_capacity(pending.len());
+	while !pending.is_empty() {
+		let next = pending.iter()
+			.enumerate()
+			.filter(|(_, (_, tx))| tx.requires().iter().all(|tag| !providers.contains_key(tag)))
+			.max_by(|(_, (a_encoded, a)), (_, (b_encoded, b))| a.priority().cmp(b.priority())
+				.then_with(|| b_encoded.cmp(a_encoded))
+			)
+			.map(|(index, _)| index);
+		// The tags required by ready transactions can't form a cycle, but keep the rest in the
+		// pool order rather than dropping them if they do.
+		let index = match next {
+			Some(index) => index,
+			None => break,
+		};
+
+		let (_, tx) = pending.remove(index);
+		for tag in tx.provides() {
+			if let Some(count) = providers.get_mut(tag) {
+				*count -= 1;
+				if *count == 0 {
+					providers.remove(tag);
+				}
+			}
+		}
+		ordered.push(tx);
+	}
+
+	ordered.extend(pending.into_iter().map(|(_, tx)| tx));
+	ordered
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -323,10 +424,14 @@ mod tests {
 	use sp_runtime::traits::NumberFor;
 
 	fn extrinsic(nonce: u64) -> Extrinsic {
+		transfer(AccountKeyring::Alice, nonce)
+	}
+
+	fn transfer(from: AccountKeyring, nonce: u64) -> Extrinsic {
 		Transfer {
 			amount: Default::default(),
 			nonce,
-			from: AccountKeyring::Alice.into(),
+			from: from.into(),
 			to: Default::default(),
 		}.into_signed_tx()
 	}
@@ -523,4 +628,52 @@ mod tests {
 		let block = propose_block(&client, 1, 2, 5);
 		client.import(BlockOrigin::Own, block).unwrap();
 	}
+
+	#[test]
+	fn deterministic_ordering_does_not_depend_on_submission_order() {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let start = time::Instant::now();
+		let clock: Clock = Arc::new(move || start);
+
+		let propose = |extrinsics: Vec<Extrinsic>| {
+			let txpool = Arc::new(
+				BasicPool::new(Default::default(), Arc::new(FullChainApi::new(client.clone()))).0
+			);
+			for xt in extrinsics {
+				futures::executor::block_on(txpool.submit_one(&BlockId::number(0), xt)).unwrap();
+			}
+			futures::executor::block_on(
+				txpool.maintain(chain_event(
+					0,
+					client.header(&BlockId::Number(0u64)).expect("header get error").expect("there should be header")
+				))
+			);
+
+			let mut proposer_factory = ProposerFactory::new(client.clone(), txpool)
+				.with_ordering(TransactionOrdering::Deterministic)
+				.with_clock(clock.clone());
+			let mut proposer = futures::executor::block_on(
+				proposer_factory.init(&client.header(&BlockId::number(0)).unwrap().unwrap())
+			).unwrap();
+
+			let deadline = time::Duration::from_secs(1);
+			futures::executor::block_on(
+				proposer.propose(Default::default(), Default::default(), deadline, RecordProof::No)
+			).map(|r| r.block).unwrap()
+		};
+
+		// Signatures are randomized, so each transaction is only signed once.
+		let alice_0 = transfer(AccountKeyring::Alice, 0);
+		let alice_1 = transfer(AccountKeyring::Alice, 1);
+		let bob = transfer(AccountKeyring::Bob, 0);
+		let charlie = transfer(AccountKeyring::Charlie, 0);
+
+		let first = propose(vec![alice_0.clone(), alice_1.clone(), bob.clone(), charlie.clone()]);
+		let second = propose(vec![charlie, bob, alice_1.clone(), alice_0.clone()]);
+
+		assert_eq!(first.extrinsics().len(), 4);
+		assert_eq!(first, second);
+		let position = |xt: &Extrinsic| first.extrinsics().iter().position(|x| x == xt);
+		assert!(position(&alice_0) < position(&alice_1));
+	}
 }
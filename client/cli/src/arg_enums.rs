@@ -56,6 +56,25 @@ impl Into<sc_service::config::WasmExecutionMethod> for WasmExecutionMethod {
 	}
 }
 
+arg_enum! {
+	/// How the node is reached through the NAT of its local network.
+	#[allow(missing_docs)]
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	pub enum NatMode {
+		None,
+		Upnp,
+	}
+}
+
+impl Into<sc_network::config::NatTraversal> for NatMode {
+	fn into(self) -> sc_network::config::NatTraversal {
+		match self {
+			NatMode::None => sc_network::config::NatTraversal::None,
+			NatMode::Upnp => sc_network::config::NatTraversal::Upnp,
+		}
+	}
+}
+
 arg_enum! {
 	#[allow(missing_docs)]
 	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -74,6 +93,41 @@ impl Into<sc_tracing::TracingReceiver> for TracingReceiver {
 	}
 }
 
+arg_enum! {
+	/// Format of the log output.
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	pub enum LogFormat {
+		// Human readable lines, colored when writing to a terminal.
+		Text,
+		// One JSON object per line.
+		Json,
+	}
+}
+
+arg_enum! {
+	/// Available RPC methods.
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	pub enum RpcMethods {
+		// Expose every RPC method only when RPC is listening on `localhost`,
+		// otherwise serve only safe RPC methods.
+		Auto,
+		// Allow only a safe subset of RPC methods.
+		Safe,
+		// Expose every RPC method (even potentially unsafe ones).
+		Unsafe,
+	}
+}
+
+impl Into<sc_service::config::RpcMethods> for RpcMethods {
+	fn into(self) -> sc_service::config::RpcMethods {
+		match self {
+			RpcMethods::Auto => sc_service::config::RpcMethods::Auto,
+			RpcMethods::Safe => sc_service::config::RpcMethods::Safe,
+			RpcMethods::Unsafe => sc_service::config::RpcMethods::Unsafe,
+		}
+	}
+}
+
 arg_enum! {
 	#[allow(missing_docs)]
 	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -82,6 +136,28 @@ arg_enum! {
 	}
 }
 
+arg_enum! {
+	/// Cryptography scheme of a key.
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	pub enum CryptoScheme {
+		// Schnorr signatures over Ristretto25519.
+		Sr25519,
+		// EdDSA signatures over Curve25519.
+		Ed25519,
+	}
+}
+
+arg_enum! {
+	/// Format of the information printed by a command.
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	pub enum OutputType {
+		// Human readable text.
+		Text,
+		// Pretty printed JSON.
+		Json,
+	}
+}
+
 arg_enum! {
 	/// How to execute blocks
 	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -0,0 +1,246 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::Debug;
+use std::time::Duration;
+use futures::{future, TryFutureExt};
+use structopt::StructOpt;
+use sc_network::{Multiaddr, PeerId, config::parse_str_addr};
+use sc_service::{Configuration, ServiceBuilderCommand, ChainSpec, Roles};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+
+use crate::error;
+use crate::VersionInfo;
+use crate::params::{SharedParams, PruningParams, NodeKeyParams};
+use crate::runtime::run_until_exit;
+
+/// Requests that can be sent to an administrated node.
+#[derive(Debug, StructOpt, Clone, PartialEq)]
+pub enum AdminAction {
+	/// Print the health of the node.
+	Health,
+
+	/// Print the peers the node is connected to.
+	Peers,
+
+	/// Print the progress of the sync of the chain.
+	SyncState,
+
+	/// Change the maximum log level of the node.
+	SetLogLevel {
+		/// One of `off`, `error`, `warn`, `info`, `debug` or `trace`.
+		#[structopt(value_name = "LEVEL")]
+		level: String,
+	},
+
+	/// Add a reserved peer, given by its address ending with its peer id.
+	AddReservedPeer {
+		#[allow(missing_docs)]
+		#[structopt(value_name = "ADDRESS")]
+		address: String,
+	},
+
+	/// Remove a reserved peer, given by its peer id.
+	RemoveReservedPeer {
+		#[allow(missing_docs)]
+		#[structopt(value_name = "PEER_ID")]
+		peer_id: String,
+	},
+}
+
+impl AdminAction {
+	/// The JSON-RPC request of the action.
+	fn request(&self) -> serde_json::Value {
+		let (method, params) = match self {
+			AdminAction::Health => ("system_health", vec![]),
+			AdminAction::Peers => ("system_peers", vec![]),
+			AdminAction::SyncState => ("system_syncState", vec![]),
+			AdminAction::SetLogLevel { level } => ("admin_setLogLevel", vec![level.clone()]),
+			AdminAction::AddReservedPeer { address } =>
+				("system_addReservedPeer", vec![address.clone()]),
+			AdminAction::RemoveReservedPeer { peer_id } =>
+				("system_removeReservedPeer", vec![peer_id.clone()]),
+		};
+
+		serde_json::json!({
+			"jsonrpc": "2.0",
+			"method": method,
+			"params": params,
+			"id": 1,
+		})
+	}
+}
+
+/// The `admin` command used to administrate a node over the p2p network.
+///
+/// The request is sent with the network identity given by the node key parameters, which must be
+/// one of the `--admin-peer`s of the node.
+#[derive(Debug, StructOpt, Clone)]
+pub struct AdminCmd {
+	/// Address of the node, ending with its peer id, e.g.
+	/// `/ip4/198.51.100.19/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV`.
+	#[structopt(long = "node", value_name = "ADDRESS", parse(try_from_str = parse_node_addr))]
+	pub node: (PeerId, Multiaddr),
+
+	/// Seconds to wait for the response of the node.
+	#[structopt(long = "timeout", value_name = "SECS", default_value = "30")]
+	pub timeout: u64,
+
+	#[allow(missing_docs)]
+	#[structopt(subcommand)]
+	pub action: AdminAction,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub node_key_params: NodeKeyParams,
+}
+
+impl AdminCmd {
+	/// Run the admin command
+	pub fn run<B, BC, BB>(
+		self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: sp_runtime::traits::Block + Debug,
+		<<<BB as BlockT>::Header as HeaderT>::Number as std::str::FromStr>::Err: std::fmt::Debug,
+		<BB as BlockT>::Hash: std::str::FromStr,
+	{
+		let (peer_id, address) = self.node;
+		let request = self.action.request().to_string();
+		let timeout = Duration::from_secs(self.timeout);
+
+		run_until_exit(config, |config| {
+			Ok(builder(config)?
+				.admin_request(peer_id, address, request, timeout)
+				.and_then(|response| future::ready(print_response(&response))))
+		})
+	}
+
+	/// Update and prepare a `Configuration` with command line parameters
+	pub fn update_config<F>(
+		&self,
+		mut config: &mut Configuration,
+		spec_factory: F,
+		version: &VersionInfo,
+	) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Box<dyn ChainSpec>, String>,
+	{
+		self.shared_params.update_config(&mut config, spec_factory, version)?;
+		self.pruning_params.update_config(&mut config, Roles::FULL, true)?;
+		config.use_in_memory_keystore()?;
+
+		let network_path = config
+			.in_chain_config_dir(crate::commands::DEFAULT_NETWORK_CONFIG_PATH)
+			.expect("We provided a basepath");
+		self.node_key_params.update_config(&mut config, Some(&network_path))?;
+		config.network.net_config_path = Some(network_path);
+		config.network.client_version = config.client_id();
+
+		Ok(())
+	}
+}
+
+fn parse_node_addr(s: &str) -> Result<(PeerId, Multiaddr), String> {
+	parse_str_addr(s).map_err(|e| format!("Invalid node address: {}", e))
+}
+
+/// Print the result of the JSON-RPC `response`, or fail with its error.
+fn print_response(response: &str) -> Result<(), sc_service::error::Error> {
+	let response: serde_json::Value = serde_json::from_str(response)
+		.map_err(|e| format!("Invalid response from the node: {}", e))?;
+
+	if let Some(error) = response.get("error") {
+		return Err(format!("The node returned an error: {}", error).into());
+	}
+	let result = response.get("result").unwrap_or(&serde_json::Value::Null);
+	println!("{}", serde_json::to_string_pretty(result).expect("Values are serializable; qed"));
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn method_and_params(action: AdminAction) -> (serde_json::Value, serde_json::Value) {
+		let request = action.request();
+		(request["method"].clone(), request["params"].clone())
+	}
+
+	#[test]
+	fn actions_are_mapped_to_rpc_requests() {
+		let peer_id = "QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV";
+		let peer = format!("/ip4/198.51.100.19/tcp/30333/p2p/{}", peer_id);
+
+		assert_eq!(
+			method_and_params(AdminAction::Health),
+			(serde_json::json!("system_health"), serde_json::json!([])),
+		);
+		assert_eq!(
+			method_and_params(AdminAction::Peers),
+			(serde_json::json!("system_peers"), serde_json::json!([])),
+		);
+		assert_eq!(
+			method_and_params(AdminAction::SyncState),
+			(serde_json::json!("system_syncState"), serde_json::json!([])),
+		);
+		assert_eq!(
+			method_and_params(AdminAction::SetLogLevel { level: "debug".into() }),
+			(serde_json::json!("admin_setLogLevel"), serde_json::json!(["debug"])),
+		);
+		assert_eq!(
+			method_and_params(AdminAction::AddReservedPeer { address: peer.clone() }),
+			(serde_json::json!("system_addReservedPeer"), serde_json::json!([peer])),
+		);
+		assert_eq!(
+			method_and_params(AdminAction::RemoveReservedPeer { peer_id: peer_id.into() }),
+			(serde_json::json!("system_removeReservedPeer"), serde_json::json!([peer_id])),
+		);
+	}
+
+	#[test]
+	fn errors_of_the_node_are_reported() {
+		assert!(print_response(r#"{"jsonrpc":"2.0","result":null,"id":1}"#).is_ok());
+
+		let unsafe_call = serde_json::json!({
+			"jsonrpc": "2.0",
+			"error": { "code": -32601, "message": "RPC call is unsafe to be called externally" },
+			"id": 1,
+		});
+		assert!(print_response(&unsafe_call.to_string()).is_err());
+		assert!(print_response("not json").is_err());
+	}
+
+	#[test]
+	fn node_address_must_end_with_the_peer_id() {
+		assert!(parse_node_addr("/ip4/198.51.100.19/tcp/30333").is_err());
+		assert!(parse_node_addr(
+			"/ip4/198.51.100.19/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV",
+		).is_ok());
+	}
+}
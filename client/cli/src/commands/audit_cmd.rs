@@ -0,0 +1,134 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{fmt::Debug, fs, path::PathBuf};
+use structopt::StructOpt;
+use sc_service::{Configuration, ServiceBuilderCommand, ChainSpec, Roles};
+use sp_core::{crypto::{Pair, Ss58Codec}, hexdisplay::HexDisplay, sr25519};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+
+use crate::error;
+use crate::VersionInfo;
+use crate::params::{SharedParams, PruningParams};
+use super::key_cmd::input_suri;
+
+/// The `audit` command used to re-execute the last finalized blocks and attest the result.
+#[derive(Debug, StructOpt, Clone)]
+pub struct AuditCmd {
+	/// Number of finalized blocks to re-execute, ending with the last finalized block.
+	#[structopt(long = "blocks", value_name = "COUNT", default_value = "100")]
+	pub blocks: u32,
+
+	/// File to write the attestation to. Written to stdout if not given.
+	#[structopt(long = "output", short = "o", value_name = "PATH", parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	/// Secret URI of the sr25519 key signing the attestation. Read from the terminal if not given.
+	#[structopt(long = "suri", value_name = "SURI")]
+	pub suri: Option<String>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+impl AuditCmd {
+	/// Run the audit command
+	pub fn run<B, BC, BB>(
+		self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: sp_runtime::traits::Block + Debug,
+		<<<BB as BlockT>::Header as HeaderT>::Number as std::str::FromStr>::Err: std::fmt::Debug,
+		<BB as BlockT>::Hash: std::str::FromStr,
+	{
+		let suri = match self.suri {
+			Some(suri) => suri,
+			None => input_suri()?,
+		};
+		let pair = sr25519::Pair::from_string(&suri, None)
+			.map_err(|_| error::Error::Input("Invalid secret URI".into()))?;
+
+		let builder = builder(config)?;
+		let genesis_hash = builder.database_info()?.chain.genesis_hash;
+		let audits = builder.audit_blocks(self.blocks.into())?;
+
+		let failed = audits.iter().filter(|audit| audit.error.is_some()).count();
+		let blocks = audits.iter()
+			.map(|audit| serde_json::json!({
+				"number": audit.number,
+				"hash": audit.hash,
+				"stateRoot": audit.state_root,
+				"error": audit.error,
+			}))
+			.collect::<Vec<_>>();
+		let audit = serde_json::json!({
+			"genesisHash": genesis_hash,
+			"time": chrono::Utc::now().to_rfc3339(),
+			"blocks": blocks,
+		});
+
+		// The keys of JSON objects are sorted, so the signed payload can be serialized again
+		// from the attestation file to verify the signature.
+		let payload = serde_json::to_vec(&audit)
+			.map_err(|e| error::Error::Other(format!("Failed to serialize audit: {}", e)))?;
+		let signature = pair.sign(&payload);
+		let attestation = serde_json::json!({
+			"audit": audit,
+			"signer": pair.public().to_ss58check(),
+			"signature": format!("0x{}", HexDisplay::from(&signature.as_ref())),
+		});
+
+		let attestation = serde_json::to_string_pretty(&attestation)
+			.map_err(|e| error::Error::Other(format!("Failed to serialize attestation: {}", e)))?;
+		match &self.output {
+			Some(path) => fs::write(path, attestation)?,
+			None => println!("{}", attestation),
+		}
+
+		if failed > 0 {
+			return Err(error::Error::Other(
+				format!("Re-execution of {} out of {} blocks failed", failed, audits.len())
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Update and prepare a `Configuration` with command line parameters
+	pub fn update_config<F>(
+		&self,
+		mut config: &mut Configuration,
+		spec_factory: F,
+		version: &VersionInfo,
+	) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Box<dyn ChainSpec>, String>,
+	{
+		self.shared_params.update_config(&mut config, spec_factory, version)?;
+		self.pruning_params.update_config(&mut config, Roles::FULL, true)?;
+		config.use_in_memory_keystore()?;
+
+		Ok(())
+	}
+}
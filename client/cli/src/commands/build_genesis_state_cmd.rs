@@ -0,0 +1,102 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{fs, path::PathBuf};
+use log::info;
+use structopt::StructOpt;
+use sc_service::{Configuration, ChainSpec};
+use sp_core::storage::well_known_keys;
+
+use crate::error;
+use crate::VersionInfo;
+use crate::params::SharedParams;
+use crate::arg_enums::WasmExecutionMethod;
+
+/// The `build-genesis-state` command used to build the genesis storage of a Wasm runtime from a
+/// JSON config, into a raw chain spec.
+#[derive(Debug, StructOpt, Clone)]
+pub struct BuildGenesisStateCmd {
+	/// JSON genesis config, passed as is to the `GenesisBuilder` api of the runtime.
+	#[structopt(value_name = "CONFIG", parse(from_os_str))]
+	pub config: PathBuf,
+
+	/// Wasm runtime to build the genesis storage with. Defaults to the runtime code of the
+	/// genesis of the chain spec.
+	#[structopt(long = "runtime", value_name = "PATH", parse(from_os_str))]
+	pub runtime: Option<PathBuf>,
+
+	/// Method for executing Wasm runtime code.
+	#[structopt(
+		long = "wasm-execution",
+		value_name = "METHOD",
+		possible_values = &WasmExecutionMethod::enabled_variants(),
+		case_insensitive = true,
+		default_value = "Interpreted"
+	)]
+	pub wasm_method: WasmExecutionMethod,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl BuildGenesisStateCmd {
+	/// Run the build-genesis-state command
+	pub fn run(self, config: Configuration) -> error::Result<()> {
+		let mut spec = config.expect_chain_spec().cloned_box();
+		let code = match &self.runtime {
+			Some(path) => fs::read(path)?,
+			None => spec.as_storage_builder()
+				.build_storage()
+				.map_err(error::Error::Other)?
+				.top
+				.remove(well_known_keys::CODE)
+				.ok_or_else(|| error::Error::Input(
+					"The genesis of the chain spec has no runtime code".into(),
+				))?,
+		};
+		let genesis_config = fs::read(&self.config)?;
+
+		info!("Building genesis state...");
+		let storage = sc_service::chain_ops::build_genesis_state(
+			&code,
+			&genesis_config,
+			self.wasm_method.into(),
+		)?;
+		spec.set_storage(storage);
+
+		info!("Generating new chain spec...");
+		let json = sc_service::chain_ops::build_spec(&*spec, true)?;
+
+		print!("{}", json);
+
+		Ok(())
+	}
+
+	/// Update and prepare a `Configuration` with command line parameters
+	pub fn update_config<F>(
+		&self,
+		mut config: &mut Configuration,
+		spec_factory: F,
+		version: &VersionInfo,
+	) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Box<dyn ChainSpec>, String>,
+	{
+		self.shared_params.update_config(&mut config, spec_factory, version)?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,131 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::Debug;
+use structopt::StructOpt;
+use sc_service::{Configuration, ServiceBuilderCommand, ChainSpec, PruningMode};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+
+use crate::error;
+use crate::VersionInfo;
+use crate::params::SharedParams;
+
+/// The `chain-info` command used to print information about the chain stored in the database.
+///
+/// The database is opened read-only, and the pruning mode stored in it is used.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ChainInfoCmd {
+	/// Print the information as JSON.
+	#[structopt(long = "json")]
+	pub json: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl ChainInfoCmd {
+	/// Run the chain-info command
+	pub fn run<B, BC, BB>(
+		self,
+		mut config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: sp_runtime::traits::Block + Debug,
+		<<<BB as BlockT>::Header as HeaderT>::Number as std::str::FromStr>::Err: std::fmt::Debug,
+		<BB as BlockT>::Hash: std::str::FromStr,
+	{
+		let stored_pruning = config.use_read_only_database()?;
+		let pruning = pruning_mode_name(&stored_pruning.mode);
+		let last_pruned = stored_pruning.last_pruned;
+		let info = builder(config)?.database_info()?;
+		let chain = &info.chain;
+
+		if self.json {
+			let columns = info.columns.as_ref().map(|columns| columns.iter()
+				.map(|column| serde_json::json!({
+					"name": column.name,
+					"entries": column.entries,
+					"bytes": column.bytes,
+				}))
+				.collect::<Vec<_>>()
+			);
+			let json = serde_json::json!({
+				"genesisHash": chain.genesis_hash,
+				"bestHash": chain.best_hash,
+				"bestNumber": chain.best_number,
+				"finalizedHash": chain.finalized_hash,
+				"finalizedNumber": chain.finalized_number,
+				"pruning": pruning,
+				"lastPruned": last_pruned,
+				"runtimeVersion": info.runtime_version,
+				"columns": columns,
+			});
+			println!("{}", json);
+		} else {
+			println!("Genesis hash: {:?}", chain.genesis_hash);
+			println!("Best block: #{} ({:?})", chain.best_number, chain.best_hash);
+			println!("Finalized block: #{} ({:?})", chain.finalized_number, chain.finalized_hash);
+			println!("Pruning: {}", pruning);
+			if let Some(last_pruned) = last_pruned {
+				println!("State pruned up to block: #{}", last_pruned);
+			}
+			println!("Runtime: {}", info.runtime_version);
+			if let Some(columns) = &info.columns {
+				println!("Database columns:");
+				for column in columns {
+					println!(
+						"  {:<14} {:>10} entries {:>14} bytes",
+						column.name,
+						column.entries,
+						column.bytes,
+					);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Update and prepare a `Configuration` with command line parameters
+	pub fn update_config<F>(
+		&self,
+		mut config: &mut Configuration,
+		spec_factory: F,
+		version: &VersionInfo,
+	) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Box<dyn ChainSpec>, String>,
+	{
+		self.shared_params.update_config(&mut config, spec_factory, version)?;
+		config.use_in_memory_keystore()?;
+
+		Ok(())
+	}
+}
+
+fn pruning_mode_name(mode: &PruningMode) -> String {
+	match mode {
+		PruningMode::ArchiveAll => "archive".into(),
+		PruningMode::ArchiveCanonical => "archive canonical".into(),
+		PruningMode::Constrained(constraints) => match constraints.max_blocks {
+			Some(blocks) => format!("keep {} blocks", blocks),
+			None => "constrained".into(),
+		},
+	}
+}
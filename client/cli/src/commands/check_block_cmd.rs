@@ -15,26 +15,35 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::fmt::Debug;
-use std::str::FromStr;
+use std::fs;
+use std::path::PathBuf;
 use structopt::StructOpt;
 use sc_service::{
 	Configuration, ServiceBuilderCommand, Roles, ChainSpec,
 };
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
-use sp_runtime::generic::BlockId;
 
 use crate::error;
 use crate::VersionInfo;
 use crate::runtime::run_until_exit;
 use crate::params::SharedParams;
 use crate::params::ImportParams;
+use super::parse_block_id;
 
 /// The `check-block` command used to validate blocks.
+///
+/// The block is imported again, going through consensus verification and execution, and the
+/// stage at which it is rejected, if any, is reported.
 #[derive(Debug, StructOpt, Clone)]
 pub struct CheckBlockCmd {
 	/// Block hash or number
-	#[structopt(value_name = "HASH or NUMBER")]
-	pub input: String,
+	#[structopt(value_name = "HASH or NUMBER", required_unless = "file")]
+	pub input: Option<String>,
+
+	/// Check the blocks of a file written by `export-blocks --binary` instead of a block of the
+	/// database.
+	#[structopt(long = "file", value_name = "PATH", parse(from_os_str), conflicts_with = "input")]
+	pub file: Option<PathBuf>,
 
 	/// The default number of 64KB pages to ever allocate for Wasm execution.
 	///
@@ -65,19 +74,22 @@ impl CheckBlockCmd {
 		<<<BB as BlockT>::Header as HeaderT>::Number as std::str::FromStr>::Err: std::fmt::Debug,
 		<BB as BlockT>::Hash: std::str::FromStr,
 	{
-		let input = if self.input.starts_with("0x") { &self.input[2..] } else { &self.input[..] };
-		let block_id = match FromStr::from_str(input) {
-			Ok(hash) => BlockId::hash(hash),
-			Err(_) => match self.input.parse::<u32>() {
-				Ok(n) => BlockId::number((n as u32).into()),
-				Err(_) => return Err(error::Error::Input("Invalid hash or number specified".into())),
-			}
-		};
-
 		let start = std::time::Instant::now();
-		run_until_exit(config, |config| {
-			Ok(builder(config)?.check_block(block_id))
-		})?;
+		match (&self.input, &self.file) {
+			(_, Some(path)) => {
+				let file = fs::File::open(path)?;
+				run_until_exit(config, |config| {
+					Ok(builder(config)?.import_blocks(file, true))
+				})?;
+			},
+			(Some(input), None) => {
+				let block_id = parse_block_id(input)?;
+				run_until_exit(config, |config| {
+					Ok(builder(config)?.check_block(block_id))
+				})?;
+			},
+			(None, None) => return Err(error::Error::Input("No block specified".into())),
+		}
 		println!("Completed in {} ms.", start.elapsed().as_millis());
 
 		Ok(())
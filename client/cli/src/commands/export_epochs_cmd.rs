@@ -0,0 +1,102 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{fmt::Debug, fs, path::PathBuf};
+use codec::Encode;
+use log::info;
+use structopt::StructOpt;
+use sc_service::{Configuration, ServiceBuilderCommand, ChainSpec, Roles};
+use sc_service::chain_ops::SignedEpochSnapshot;
+use sp_core::{crypto::{Pair, Ss58Codec}, sr25519};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+
+use crate::error;
+use crate::VersionInfo;
+use crate::params::{SharedParams, PruningParams};
+use super::key_cmd::input_suri;
+
+/// The `export-epochs` command used to write a signed snapshot of the epoch and authority set
+/// transitions, to be imported by light clients.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportEpochsCmd {
+	/// File to write the SCALE-encoded snapshot to.
+	#[structopt(parse(from_os_str))]
+	pub output: PathBuf,
+
+	/// Secret URI of the sr25519 key signing the snapshot. Read from the terminal if not given.
+	#[structopt(long = "suri", value_name = "SURI")]
+	pub suri: Option<String>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+impl ExportEpochsCmd {
+	/// Run the export-epochs command
+	pub fn run<B, BC, BB>(
+		self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: sp_runtime::traits::Block + Debug,
+		<<<BB as BlockT>::Header as HeaderT>::Number as std::str::FromStr>::Err: std::fmt::Debug,
+		<BB as BlockT>::Hash: std::str::FromStr,
+	{
+		let suri = match self.suri {
+			Some(suri) => suri,
+			None => input_suri()?,
+		};
+		let pair = sr25519::Pair::from_string(&suri, None)
+			.map_err(|_| error::Error::Input("Invalid secret URI".into()))?;
+
+		let snapshot = builder(config)?.export_epochs()?;
+		let signed = SignedEpochSnapshot::sign(&snapshot, &pair);
+		fs::write(&self.output, signed.encode())?;
+
+		info!(
+			"Epoch snapshot written to {}, import it with --epoch-snapshot {} \
+			--epoch-snapshot-signer {}",
+			self.output.display(),
+			self.output.display(),
+			pair.public().to_ss58check(),
+		);
+		Ok(())
+	}
+
+	/// Update and prepare a `Configuration` with command line parameters
+	pub fn update_config<F>(
+		&self,
+		mut config: &mut Configuration,
+		spec_factory: F,
+		version: &VersionInfo,
+	) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Box<dyn ChainSpec>, String>,
+	{
+		self.shared_params.update_config(&mut config, spec_factory, version)?;
+		self.pruning_params.update_config(&mut config, Roles::FULL, true)?;
+		config.use_in_memory_keystore()?;
+
+		Ok(())
+	}
+}
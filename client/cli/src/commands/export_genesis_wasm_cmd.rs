@@ -0,0 +1,86 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{fs, io::{self, Write}, path::{Path, PathBuf}};
+use structopt::StructOpt;
+use sc_service::{Configuration, ChainSpec};
+use sp_core::{hexdisplay::HexDisplay, storage::well_known_keys};
+
+use crate::error;
+use crate::VersionInfo;
+use crate::params::SharedParams;
+
+/// The `export-genesis-wasm` command used to extract the runtime code of the genesis of the
+/// chain spec.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportGenesisWasmCmd {
+	/// File to write the runtime code to. Written to stdout if not given.
+	#[structopt(value_name = "PATH", parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	/// Write the runtime code as binary instead of hex.
+	#[structopt(long = "raw")]
+	pub raw: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl ExportGenesisWasmCmd {
+	/// Run the export-genesis-wasm command
+	pub fn run(self, config: Configuration) -> error::Result<()> {
+		let storage = config.expect_chain_spec()
+			.as_storage_builder()
+			.build_storage()
+			.map_err(error::Error::Other)?;
+		let code = storage.top.get(well_known_keys::CODE)
+			.ok_or_else(|| error::Error::Input("The genesis of the chain spec has no runtime code".into()))?;
+
+		write_output(code, self.output.as_ref().map(PathBuf::as_path), self.raw)
+	}
+
+	/// Update and prepare a `Configuration` with command line parameters
+	pub fn update_config<F>(
+		&self,
+		mut config: &mut Configuration,
+		spec_factory: F,
+		version: &VersionInfo,
+	) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Box<dyn ChainSpec>, String>,
+	{
+		self.shared_params.update_config(&mut config, spec_factory, version)?;
+
+		Ok(())
+	}
+}
+
+/// Write `data` to the file at `output`, or to stdout if `None`, as binary if `raw` and as hex
+/// otherwise.
+pub(crate) fn write_output(data: &[u8], output: Option<&Path>, raw: bool) -> error::Result<()> {
+	let data = if raw {
+		data.to_vec()
+	} else {
+		format!("0x{}", HexDisplay::from(&data)).into_bytes()
+	};
+
+	match output {
+		Some(path) => fs::write(path, data)?,
+		None => io::stdout().write_all(&data)?,
+	}
+
+	Ok(())
+}
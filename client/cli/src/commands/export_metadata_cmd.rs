@@ -0,0 +1,92 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::Debug;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use sc_service::{Configuration, ServiceBuilderCommand, ChainSpec, Roles};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+
+use crate::error;
+use crate::VersionInfo;
+use crate::params::{SharedParams, PruningParams};
+use super::export_genesis_wasm_cmd::write_output;
+use super::parse_block_id;
+
+/// The `export-metadata` command used to extract the metadata of the runtime of a block.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportMetadataCmd {
+	/// File to write the metadata to. Written to stdout if not given.
+	#[structopt(value_name = "PATH", parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	/// Block hash or number. Defaults to the best block, which is the genesis of the chain spec
+	/// if the database is empty.
+	#[structopt(long = "at", value_name = "HASH or NUMBER")]
+	pub at: Option<String>,
+
+	/// Write the metadata as binary instead of hex.
+	#[structopt(long = "raw")]
+	pub raw: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+impl ExportMetadataCmd {
+	/// Run the export-metadata command
+	pub fn run<B, BC, BB>(
+		self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: sp_runtime::traits::Block + Debug,
+		<<<BB as BlockT>::Header as HeaderT>::Number as std::str::FromStr>::Err: std::fmt::Debug,
+		<BB as BlockT>::Hash: std::str::FromStr,
+	{
+		let block_id = match &self.at {
+			Some(at) => Some(parse_block_id(at)?),
+			None => None,
+		};
+
+		let metadata = builder(config)?.export_metadata(block_id)?;
+		write_output(&metadata, self.output.as_ref().map(PathBuf::as_path), self.raw)
+	}
+
+	/// Update and prepare a `Configuration` with command line parameters
+	pub fn update_config<F>(
+		&self,
+		mut config: &mut Configuration,
+		spec_factory: F,
+		version: &VersionInfo,
+	) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Box<dyn ChainSpec>, String>,
+	{
+		self.shared_params.update_config(&mut config, spec_factory, version)?;
+		self.pruning_params.update_config(&mut config, Roles::FULL, true)?;
+		config.use_in_memory_keystore()?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,137 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs;
+use std::fmt::Debug;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use sc_service::{
+	Configuration, ServiceBuilderCommand, ChainSpec, Roles,
+};
+use sp_core::{H256, hashing::blake2_256};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+
+use crate::error;
+use crate::VersionInfo;
+use crate::params::{SharedParams, PruningParams};
+use super::parse_block_id;
+
+/// The `export-runtime` command used to extract the runtime code of a block.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportRuntimeCmd {
+	/// Block hash or number
+	#[structopt(value_name = "HASH or NUMBER")]
+	pub input: String,
+
+	/// Write the runtime code to the given file.
+	#[structopt(long = "output", value_name = "PATH", parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	/// Compare the runtime code with the given Wasm file.
+	///
+	/// Fails if they differ.
+	#[structopt(long = "compare", value_name = "PATH", parse(from_os_str))]
+	pub compare: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+impl ExportRuntimeCmd {
+	/// Run the export-runtime command
+	pub fn run<B, BC, BB>(
+		self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: sp_runtime::traits::Block + Debug,
+		<<<BB as BlockT>::Header as HeaderT>::Number as std::str::FromStr>::Err: std::fmt::Debug,
+		<BB as BlockT>::Hash: std::str::FromStr,
+	{
+		let block_id = parse_block_id(&self.input)?;
+
+		let (code, version) = builder(config)?.export_runtime(block_id)?;
+		println!("Runtime: {} (spec version {})", version.spec_name, version.spec_version);
+		println!("Code hash: {:?}", H256::from(blake2_256(&code)));
+		println!("Code size: {} bytes", code.len());
+
+		if let Some(output) = &self.output {
+			fs::write(output, &code)?;
+			println!("Code written to {}", output.display());
+		}
+
+		if let Some(compare) = &self.compare {
+			let other = fs::read(compare)?;
+			match first_difference(&code, &other) {
+				None => println!("Code matches {}", compare.display()),
+				Some(offset) => return Err(error::Error::Other(format!(
+					"Code differs from {} ({} bytes, hash {:?}): first difference at offset {}",
+					compare.display(),
+					other.len(),
+					H256::from(blake2_256(&other)),
+					offset,
+				))),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Update and prepare a `Configuration` with command line parameters
+	pub fn update_config<F>(
+		&self,
+		mut config: &mut Configuration,
+		spec_factory: F,
+		version: &VersionInfo,
+	) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Box<dyn ChainSpec>, String>,
+	{
+		self.shared_params.update_config(&mut config, spec_factory, version)?;
+		self.pruning_params.update_config(&mut config, Roles::FULL, true)?;
+		config.use_in_memory_keystore()?;
+
+		Ok(())
+	}
+}
+
+/// Returns the offset of the first byte that differs between `a` and `b`, if any.
+fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+	a.iter()
+		.zip(b.iter())
+		.position(|(a, b)| a != b)
+		.or_else(|| if a.len() != b.len() { Some(a.len().min(b.len())) } else { None })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn first_difference_works() {
+		assert_eq!(first_difference(b"wasm", b"wasm"), None);
+		assert_eq!(first_difference(b"wasm", b"wisp"), Some(1));
+		assert_eq!(first_difference(b"wasm", b"was"), Some(3));
+		assert_eq!(first_difference(b"", b"wasm"), Some(0));
+	}
+}
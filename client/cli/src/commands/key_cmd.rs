@@ -0,0 +1,769 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+	convert::TryFrom, iter, path::PathBuf, thread, time::{Duration, Instant},
+	sync::{Arc, mpsc, atomic::{AtomicBool, AtomicU64, Ordering}},
+};
+use structopt::StructOpt;
+use regex::Regex;
+use sc_service::{Configuration, ChainSpec, config::KeystoreConfig};
+use sc_keystore::{Store, KeyStorePtr};
+use sc_network::config::{NodeKeyConfig, Secret, identity::{self, Keypair}};
+use sp_core::{
+	crypto::{KeyTypeId, Pair, Public, Ss58Codec, Ss58AddressFormat, DeriveJunction},
+	hexdisplay::HexDisplay, ed25519, sr25519,
+};
+use sp_runtime::{MultiSigner, traits::IdentifyAccount};
+
+use crate::error;
+use crate::VersionInfo;
+use crate::params::{SharedParams, KeystoreParams, NODE_KEY_ED25519_FILE};
+use crate::arg_enums::{CryptoScheme, OutputType};
+use crate::commands::DEFAULT_NETWORK_CONFIG_PATH;
+
+/// The `key` command used to manage keys and the keystore of a node.
+#[derive(Debug, StructOpt, Clone)]
+pub enum KeySubcommand {
+	/// Generate a random key, and print its secret phrase, public key and SS58 address.
+	Generate(GenerateKeyCmd),
+
+	/// Print the public key and SS58 address of a secret URI or SS58 address, and the keys
+	/// derived along the junctions of the secret URI.
+	Inspect(InspectKeyCmd),
+
+	/// Insert a key into the keystore of the node.
+	Insert(InsertKeyCmd),
+
+	/// List the keys stored in the keystore of the node.
+	List(ListKeysCmd),
+
+	/// Generate an Ed25519 network key, and print the peer id of the node using it.
+	GenerateNodeKey(GenerateNodeKeyCmd),
+
+	/// Search for a key whose SS58 address starts with a pattern, and print its secret phrase.
+	Vanity(VanityCmd),
+}
+
+/// The `key generate` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct GenerateKeyCmd {
+	/// Cryptography scheme of the key.
+	#[structopt(
+		long = "scheme",
+		value_name = "SCHEME",
+		possible_values = &CryptoScheme::variants(),
+		case_insensitive = true,
+		default_value = "Sr25519"
+	)]
+	pub scheme: CryptoScheme,
+
+	/// Password protecting the secret phrase.
+	#[structopt(long = "password")]
+	pub password: Option<String>,
+
+	/// SS58 network of the printed address.
+	#[structopt(long = "network", value_name = "NETWORK", parse(try_from_str = parse_network))]
+	pub network: Option<Ss58AddressFormat>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// The `key inspect` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct InspectKeyCmd {
+	/// Secret URI (phrase, seed or `//Dev` style derivation) or SS58 address of the key.
+	#[structopt(value_name = "URI")]
+	pub uri: String,
+
+	/// Cryptography scheme of the key.
+	#[structopt(
+		long = "scheme",
+		value_name = "SCHEME",
+		possible_values = &CryptoScheme::variants(),
+		case_insensitive = true,
+		default_value = "Sr25519"
+	)]
+	pub scheme: CryptoScheme,
+
+	/// Password protecting the secret URI.
+	#[structopt(long = "password")]
+	pub password: Option<String>,
+
+	/// SS58 network of the printed address.
+	#[structopt(long = "network", value_name = "NETWORK", parse(try_from_str = parse_network))]
+	pub network: Option<Ss58AddressFormat>,
+
+	/// Format of the printed information.
+	#[structopt(
+		long = "output",
+		value_name = "FORMAT",
+		possible_values = &OutputType::variants(),
+		case_insensitive = true,
+		default_value = "Text"
+	)]
+	pub output: OutputType,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// The `key insert` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct InsertKeyCmd {
+	/// Secret URI of the key. Asked for interactively if not given.
+	#[structopt(long = "suri", value_name = "SURI")]
+	pub suri: Option<String>,
+
+	/// Four letter key type ID, e.g. `babe`, `gran`, `imon` or `audi`.
+	#[structopt(long = "key-type", value_name = "KEY_TYPE", parse(try_from_str = parse_key_type))]
+	pub key_type: KeyTypeId,
+
+	/// Cryptography scheme of the key.
+	#[structopt(
+		long = "scheme",
+		value_name = "SCHEME",
+		possible_values = &CryptoScheme::variants(),
+		case_insensitive = true,
+		default_value = "Sr25519"
+	)]
+	pub scheme: CryptoScheme,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub keystore_params: KeystoreParams,
+}
+
+/// The `key list` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ListKeysCmd {
+	/// Only list the keys of the given key type ID.
+	#[structopt(long = "key-type", value_name = "KEY_TYPE", parse(try_from_str = parse_key_type))]
+	pub key_type: Option<KeyTypeId>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub keystore_params: KeystoreParams,
+}
+
+/// The `key generate-node-key` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct GenerateNodeKeyCmd {
+	/// Write the secret key to the given file, to be given to the node with `--node-key-file`.
+	#[structopt(long = "file", value_name = "PATH", parse(from_os_str), conflicts_with = "persist")]
+	pub file: Option<PathBuf>,
+
+	/// Write the secret key to the file the node loads its network key from by default, in the
+	/// directory of the chain.
+	#[structopt(long = "persist")]
+	pub persist: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// The `key vanity` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct VanityCmd {
+	/// Start of the SS58 address to search for. The first characters of an address are
+	/// determined by its network, e.g. `5` for Substrate.
+	#[structopt(long = "pattern", value_name = "PATTERN", parse(try_from_str = parse_vanity_pattern))]
+	pub pattern: String,
+
+	/// Number of threads searching. Defaults to the number of CPUs.
+	#[structopt(long = "threads", value_name = "COUNT")]
+	pub threads: Option<usize>,
+
+	/// Cryptography scheme of the key.
+	#[structopt(
+		long = "scheme",
+		value_name = "SCHEME",
+		possible_values = &CryptoScheme::variants(),
+		case_insensitive = true,
+		default_value = "Sr25519"
+	)]
+	pub scheme: CryptoScheme,
+
+	/// SS58 network of the address.
+	#[structopt(long = "network", value_name = "NETWORK", parse(try_from_str = parse_network))]
+	pub network: Option<Ss58AddressFormat>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl KeySubcommand {
+	/// Get the shared parameters of the key command.
+	pub fn get_shared_params(&self) -> &SharedParams {
+		match self {
+			KeySubcommand::Generate(cmd) => &cmd.shared_params,
+			KeySubcommand::Inspect(cmd) => &cmd.shared_params,
+			KeySubcommand::Insert(cmd) => &cmd.shared_params,
+			KeySubcommand::List(cmd) => &cmd.shared_params,
+			KeySubcommand::GenerateNodeKey(cmd) => &cmd.shared_params,
+			KeySubcommand::Vanity(cmd) => &cmd.shared_params,
+		}
+	}
+
+	/// Run the key command
+	pub fn run(self, config: Configuration) -> error::Result<()> {
+		match self {
+			KeySubcommand::Generate(cmd) => cmd.run(),
+			KeySubcommand::Inspect(cmd) => cmd.run(),
+			KeySubcommand::Insert(cmd) => cmd.run(config),
+			KeySubcommand::List(cmd) => cmd.run(config),
+			KeySubcommand::GenerateNodeKey(cmd) => cmd.run(config),
+			KeySubcommand::Vanity(cmd) => cmd.run(),
+		}
+	}
+
+	/// Update and prepare a `Configuration` with command line parameters
+	///
+	/// Only the commands working on the keystore or the network key of the node need the chain
+	/// spec, to locate them.
+	pub fn update_config<F>(
+		&self,
+		mut config: &mut Configuration,
+		spec_factory: F,
+		version: &VersionInfo,
+	) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Box<dyn ChainSpec>, String>,
+	{
+		let (shared_params, keystore_params) = match self {
+			KeySubcommand::Generate(_) | KeySubcommand::Inspect(_) | KeySubcommand::Vanity(_) =>
+				return Ok(()),
+			KeySubcommand::Insert(cmd) => (&cmd.shared_params, &cmd.keystore_params),
+			KeySubcommand::List(cmd) => (&cmd.shared_params, &cmd.keystore_params),
+			KeySubcommand::GenerateNodeKey(cmd) => {
+				if cmd.persist {
+					cmd.shared_params.update_config(&mut config, spec_factory, version)?;
+				}
+				return Ok(());
+			},
+		};
+
+		shared_params.update_config(&mut config, spec_factory, version)?;
+		keystore_params.update_config(&mut config)?;
+
+		Ok(())
+	}
+}
+
+impl GenerateKeyCmd {
+	/// Run the key generate command
+	pub fn run(self) -> error::Result<()> {
+		let password = self.password.as_ref().map(String::as_str);
+		let phrase = match self.scheme {
+			CryptoScheme::Sr25519 => sr25519::Pair::generate_with_phrase(password).1,
+			CryptoScheme::Ed25519 => ed25519::Pair::generate_with_phrase(password).1,
+		};
+
+		print_key(&phrase, self.scheme, password, self.network, OutputType::Text)
+	}
+}
+
+impl InspectKeyCmd {
+	/// Run the key inspect command
+	pub fn run(self) -> error::Result<()> {
+		print_key(
+			&self.uri,
+			self.scheme,
+			self.password.as_ref().map(String::as_str),
+			self.network,
+			self.output,
+		)
+	}
+}
+
+impl InsertKeyCmd {
+	/// Run the key insert command
+	pub fn run(self, config: Configuration) -> error::Result<()> {
+		let keystore = open_keystore(&config)?;
+		let suri = match self.suri {
+			Some(suri) => suri,
+			None => input_suri()?,
+		};
+
+		let public = insert_key(&keystore, self.key_type, &suri, self.scheme)?;
+		println!(
+			"Inserted key 0x{} of type `{}`",
+			HexDisplay::from(&public),
+			String::from_utf8_lossy(&self.key_type.0),
+		);
+
+		Ok(())
+	}
+}
+
+impl ListKeysCmd {
+	/// Run the key list command
+	pub fn run(self, config: Configuration) -> error::Result<()> {
+		let keystore = open_keystore(&config)?;
+
+		for (key_type, public) in list_keys(&keystore, self.key_type)? {
+			println!("{} 0x{}", String::from_utf8_lossy(&key_type.0), HexDisplay::from(&public));
+		}
+
+		Ok(())
+	}
+}
+
+impl GenerateNodeKeyCmd {
+	/// Run the key generate-node-key command
+	pub fn run(self, config: Configuration) -> error::Result<()> {
+		let path = if self.persist {
+			let path = config.in_chain_config_dir(DEFAULT_NETWORK_CONFIG_PATH)
+				.expect("We provided a base_path/config_dir.");
+			Some(path.join(NODE_KEY_ED25519_FILE))
+		} else {
+			self.file
+		};
+
+		let (keypair, secret) = match path {
+			Some(path) => {
+				if path.exists() {
+					return Err(error::Error::Input(
+						format!("A node key already exists at {}", path.display())
+					));
+				}
+				// Generated and written the same way as the node does on first start.
+				let keypair = NodeKeyConfig::Ed25519(Secret::File(path.clone())).into_keypair()?;
+				(keypair, format!("Secret key written to {}", path.display()))
+			},
+			None => {
+				let pair = identity::ed25519::Keypair::generate();
+				let secret = format!("Secret key: 0x{}", HexDisplay::from(&pair.secret().as_ref()));
+				(Keypair::Ed25519(pair), secret)
+			},
+		};
+
+		println!("Peer ID: {}", keypair.public().into_peer_id());
+		println!("{}", secret);
+
+		Ok(())
+	}
+}
+
+impl VanityCmd {
+	/// Run the key vanity command
+	pub fn run(self) -> error::Result<()> {
+		let threads = self.threads.unwrap_or_else(num_cpus::get);
+		if threads == 0 {
+			return Err(error::Error::Input("At least one thread is required".into()));
+		}
+
+		let network = self.network.unwrap_or_default();
+		let phrase = match self.scheme {
+			CryptoScheme::Sr25519 => find_vanity_phrase::<sr25519::Pair>(&self.pattern, network, threads),
+			CryptoScheme::Ed25519 => find_vanity_phrase::<ed25519::Pair>(&self.pattern, network, threads),
+		};
+
+		print_key(&phrase, self.scheme, None, Some(network), OutputType::Text)
+	}
+}
+
+/// Interval between two reports of the progress of the vanity search.
+const VANITY_PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Generate secret phrases on `threads` threads until the SS58 address of the key of one of them
+/// starts with `pattern`, and return it.
+fn find_vanity_phrase<P: Pair>(
+	pattern: &str,
+	network: Ss58AddressFormat,
+	threads: usize,
+) -> String {
+	let found = Arc::new(AtomicBool::new(false));
+	let searched = Arc::new(AtomicU64::new(0));
+	let (sender, receiver) = mpsc::channel();
+
+	eprintln!("Searching for an address starting with `{}` on {} threads", pattern, threads);
+	for _ in 0..threads {
+		let pattern = pattern.to_string();
+		let found = found.clone();
+		let searched = searched.clone();
+		let sender = sender.clone();
+		thread::spawn(move || {
+			while !found.load(Ordering::Relaxed) {
+				let (pair, phrase, _) = P::generate_with_phrase(None);
+				searched.fetch_add(1, Ordering::Relaxed);
+				if pair.public().to_ss58check_with_version(network).starts_with(&pattern) {
+					found.store(true, Ordering::Relaxed);
+					let _ = sender.send(phrase);
+				}
+			}
+		});
+	}
+	drop(sender);
+
+	let start = Instant::now();
+	loop {
+		match receiver.recv_timeout(VANITY_PROGRESS_INTERVAL) {
+			Ok(phrase) => return phrase,
+			Err(mpsc::RecvTimeoutError::Timeout) => {
+				let searched = searched.load(Ordering::Relaxed);
+				eprintln!(
+					"{} keys searched ({:.0} keys/s)",
+					searched,
+					searched as f64 / start.elapsed().as_secs_f64(),
+				);
+			},
+			Err(mpsc::RecvTimeoutError::Disconnected) =>
+				unreachable!("The threads only stop once a phrase is sent; qed"),
+		}
+	}
+}
+
+/// Print the secret seed, public key, account ID and SS58 address of the given secret URI, and
+/// of every key derived along its junctions.
+///
+/// Only the public key, account ID and address are printed if the URI is an SS58 address.
+fn print_key(
+	uri: &str,
+	scheme: CryptoScheme,
+	password: Option<&str>,
+	network: Option<Ss58AddressFormat>,
+	output: OutputType,
+) -> error::Result<()> {
+	match scheme {
+		CryptoScheme::Sr25519 => print_key_with::<sr25519::Pair>(uri, password, network, output),
+		CryptoScheme::Ed25519 => print_key_with::<ed25519::Pair>(uri, password, network, output),
+	}
+}
+
+fn print_key_with<P: Pair>(
+	uri: &str,
+	password: Option<&str>,
+	network: Option<Ss58AddressFormat>,
+	output: OutputType,
+) -> error::Result<()> where
+	P::Public: Into<MultiSigner>,
+{
+	if let Some(steps) = derivation_steps::<P>(uri, password) {
+		let network = network.unwrap_or_default();
+		let key = steps.last().expect("There is at least the root key; qed");
+		match output {
+			OutputType::Json => {
+				let mut json = key_json(key, network);
+				json["secretKeyUri"] = uri.into();
+				json["derivation"] = steps.iter()
+					.map(|step| {
+						let mut json = key_json(step, network);
+						json["junction"] = serde_json::json!(step.junction);
+						json
+					})
+					.collect::<Vec<_>>()
+					.into();
+				print_json(&json)?;
+			},
+			OutputType::Text => {
+				println!("Secret Key URI `{}` is account:", uri);
+				print_key_text(key, network, "  ");
+				if steps.len() > 1 {
+					println!("  Derivation:");
+					for step in &steps {
+						match &step.junction {
+							None => println!("    Root key:"),
+							Some(junction) if junction.starts_with("//") =>
+								println!("    Hard junction `{}`:", junction),
+							Some(junction) => println!("    Soft junction `{}`:", junction),
+						}
+						print_key_text(step, network, "      ");
+					}
+				}
+			},
+		}
+	} else if let Ok((public, version)) = P::Public::from_string_with_version(uri) {
+		let network = network.unwrap_or(version);
+		let account = account_id(public.clone());
+		let address = public.to_ss58check_with_version(network);
+		match output {
+			OutputType::Json => print_json(&serde_json::json!({
+				"publicKeyUri": uri,
+				"networkId": String::from(version),
+				"publicKey": format!("0x{}", HexDisplay::from(&public.as_ref())),
+				"accountId": account,
+				"ss58Address": address,
+			}))?,
+			OutputType::Text => println!(
+				"Public Key URI `{}` is account:\n  \
+				Network ID/version: {}\n  \
+				Public key (hex):   0x{}\n  \
+				Account ID:         {}\n  \
+				SS58 Address:       {}",
+				uri,
+				String::from(version),
+				HexDisplay::from(&public.as_ref()),
+				account,
+				address,
+			),
+		}
+	} else {
+		return Err(error::Error::Input(format!("Invalid secret URI or SS58 address `{}`", uri)));
+	}
+
+	Ok(())
+}
+
+/// A key derived from a secret URI.
+struct DerivationStep<P: Pair> {
+	/// The junction the key is derived with, as written in the URI. `None` for the root key.
+	junction: Option<String>,
+	/// The secret seed of the key, if it has one.
+	seed: Option<P::Seed>,
+	/// The public key.
+	public: P::Public,
+}
+
+/// Derive the key of the given secret URI one junction at a time.
+///
+/// Returns the root key followed by the key derived with each junction, or `None` if the URI
+/// isn't a valid secret URI.
+fn derivation_steps<P: Pair>(uri: &str, password: Option<&str>) -> Option<Vec<DerivationStep<P>>> {
+	// Same grammar as `Pair::from_string_with_seed`.
+	let re = Regex::new(r"^(?P<phrase>[\d\w ]+)?(?P<path>(//?[^/]+)*)(///(?P<password>.*))?$")
+		.expect("constructed from known-good static value; qed");
+	let re_junction = Regex::new(r"/(/?[^/]+)")
+		.expect("constructed from known-good static value; qed");
+
+	let cap = re.captures(uri)?;
+	let path = cap.name("path")?;
+	let root_uri = format!("{}{}", &uri[..path.start()], &uri[path.end()..]);
+	let (mut pair, mut seed) = P::from_string_with_seed(&root_uri, password).ok()?;
+
+	let mut steps = vec![DerivationStep { junction: None, seed: seed.clone(), public: pair.public() }];
+	for junction in re_junction.captures_iter(path.as_str()) {
+		let (derived, derived_seed) = pair
+			.derive(iter::once(DeriveJunction::from(&junction[1])), seed)
+			.ok()?;
+		pair = derived;
+		seed = derived_seed;
+		steps.push(DerivationStep {
+			junction: Some(junction[0].to_string()),
+			seed: seed.clone(),
+			public: pair.public(),
+		});
+	}
+
+	Some(steps)
+}
+
+fn account_id<P: Public + Into<MultiSigner>>(public: P) -> String {
+	let account = public.into().into_account();
+	let account: &[u8] = account.as_ref();
+	format!("0x{}", HexDisplay::from(&account))
+}
+
+fn format_seed<P: Pair>(seed: &Option<P::Seed>) -> String {
+	seed.as_ref()
+		.map_or_else(|| "n/a".into(), |seed| format!("0x{}", HexDisplay::from(&seed.as_ref())))
+}
+
+fn key_json<P: Pair>(key: &DerivationStep<P>, network: Ss58AddressFormat) -> serde_json::Value where
+	P::Public: Into<MultiSigner>,
+{
+	serde_json::json!({
+		"secretSeed": format_seed::<P>(&key.seed),
+		"publicKey": format!("0x{}", HexDisplay::from(&key.public.as_ref())),
+		"accountId": account_id(key.public.clone()),
+		"ss58Address": key.public.to_ss58check_with_version(network),
+	})
+}
+
+fn print_key_text<P: Pair>(key: &DerivationStep<P>, network: Ss58AddressFormat, indent: &str) where
+	P::Public: Into<MultiSigner>,
+{
+	println!("{}Secret seed:      {}", indent, format_seed::<P>(&key.seed));
+	println!("{}Public key (hex): 0x{}", indent, HexDisplay::from(&key.public.as_ref()));
+	println!("{}Account ID:       {}", indent, account_id(key.public.clone()));
+	println!("{}SS58 Address:     {}", indent, key.public.to_ss58check_with_version(network));
+}
+
+fn print_json(json: &serde_json::Value) -> error::Result<()> {
+	let json = serde_json::to_string_pretty(json)
+		.map_err(|e| error::Error::Other(format!("Failed to serialize key: {}", e)))?;
+	println!("{}", json);
+	Ok(())
+}
+
+/// Insert the key of the given secret URI into the keystore, and return its public key.
+fn insert_key(
+	keystore: &KeyStorePtr,
+	key_type: KeyTypeId,
+	suri: &str,
+	scheme: CryptoScheme,
+) -> error::Result<Vec<u8>> {
+	let keystore = keystore.read();
+	let public = match scheme {
+		CryptoScheme::Sr25519 =>
+			keystore.insert_by_type::<sr25519::Pair>(key_type, suri)?.public().to_raw_vec(),
+		CryptoScheme::Ed25519 =>
+			keystore.insert_by_type::<ed25519::Pair>(key_type, suri)?.public().to_raw_vec(),
+	};
+
+	Ok(public)
+}
+
+/// Returns the key types and public keys stored in the keystore, sorted by key type.
+fn list_keys(
+	keystore: &KeyStorePtr,
+	key_type: Option<KeyTypeId>,
+) -> error::Result<Vec<(KeyTypeId, Vec<u8>)>> {
+	let mut keys: Vec<_> = keystore.read().keys()?
+		.into_iter()
+		.filter(|(ty, _)| key_type.map_or(true, |key_type| *ty == key_type))
+		.collect();
+	keys.sort();
+
+	Ok(keys)
+}
+
+fn open_keystore(config: &Configuration) -> error::Result<KeyStorePtr> {
+	match &config.keystore {
+		KeystoreConfig::Path { path, password } => Ok(Store::open(path.clone(), password.clone())?),
+		_ => Err(error::Error::Input("A keystore path is required".into())),
+	}
+}
+
+fn parse_key_type(key_type: &str) -> Result<KeyTypeId, String> {
+	KeyTypeId::try_from(key_type)
+		.map_err(|_| format!("Invalid key type `{}`, expected four characters", key_type))
+}
+
+fn parse_vanity_pattern(pattern: &str) -> Result<String, String> {
+	const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+	if pattern.is_empty() {
+		return Err("The pattern must not be empty".into());
+	}
+	match pattern.chars().find(|c| !BASE58_ALPHABET.contains(*c)) {
+		Some(c) => Err(format!("`{}` can't appear in an SS58 address", c)),
+		None => Ok(pattern.to_string()),
+	}
+}
+
+fn parse_network(network: &str) -> Result<Ss58AddressFormat, String> {
+	Ss58AddressFormat::try_from(network)
+		.map_err(|_| format!("Unknown SS58 network `{}`", network))
+}
+
+#[cfg(not(target_os = "unknown"))]
+pub(crate) fn input_suri() -> error::Result<String> {
+	rpassword::read_password_from_tty(Some("Secret URI: "))
+		.map_err(|e| error::Error::Input(format!("{:?}", e)))
+}
+
+#[cfg(target_os = "unknown")]
+pub(crate) fn input_suri() -> error::Result<String> {
+	Err(error::Error::Input("The secret URI must be given with `--suri`".into()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	#[test]
+	fn inserted_keys_are_listed() {
+		let temp_dir = TempDir::new().unwrap();
+		let keystore = Store::open(temp_dir.path(), None).unwrap();
+		let babe = KeyTypeId(*b"babe");
+		let gran = KeyTypeId(*b"gran");
+
+		let babe_public = insert_key(&keystore, babe, "//Alice", CryptoScheme::Sr25519).unwrap();
+		let gran_public = insert_key(&keystore, gran, "//Alice", CryptoScheme::Ed25519).unwrap();
+
+		assert_eq!(babe_public, sr25519::Pair::from_string("//Alice", None).unwrap().public().to_raw_vec());
+		assert_eq!(gran_public, ed25519::Pair::from_string("//Alice", None).unwrap().public().to_raw_vec());
+		assert_eq!(
+			list_keys(&keystore, None).unwrap(),
+			vec![(babe, babe_public.clone()), (gran, gran_public)],
+		);
+		assert_eq!(list_keys(&keystore, Some(babe)).unwrap(), vec![(babe, babe_public)]);
+	}
+
+	#[test]
+	fn derivation_steps_end_with_derived_key() {
+		let uri = "//Alice/stash//0///password";
+		let steps = derivation_steps::<sr25519::Pair>(uri, None).unwrap();
+
+		let junctions: Vec<_> = steps.iter().map(|step| step.junction.clone()).collect();
+		assert_eq!(junctions, vec![
+			None,
+			Some("//Alice".to_string()),
+			Some("/stash".to_string()),
+			Some("//0".to_string()),
+		]);
+		assert_eq!(steps[0].public, sr25519::Pair::from_string("///password", None).unwrap().public());
+		assert_eq!(
+			steps[1].public,
+			sr25519::Pair::from_string("//Alice///password", None).unwrap().public(),
+		);
+		assert_eq!(steps[3].public, sr25519::Pair::from_string(uri, None).unwrap().public());
+		// Soft derivation loses the seed.
+		assert!(steps[1].seed.is_some());
+		assert!(steps[2].seed.is_none());
+
+		let steps = derivation_steps::<ed25519::Pair>("//Alice//0", None).unwrap();
+		assert_eq!(steps[2].public, ed25519::Pair::from_string("//Alice//0", None).unwrap().public());
+		assert!(derivation_steps::<ed25519::Pair>("//Alice/soft", None).is_none());
+	}
+
+	#[test]
+	fn vanity_address_starts_with_pattern() {
+		let network = Ss58AddressFormat::default();
+		let address = sr25519::Pair::generate().0.public().to_ss58check_with_version(network);
+		let pattern = &address[..2];
+
+		let phrase = find_vanity_phrase::<sr25519::Pair>(pattern, network, 2);
+		let public = sr25519::Pair::from_phrase(&phrase, None).unwrap().0.public();
+		assert!(public.to_ss58check_with_version(network).starts_with(pattern));
+
+		assert!(parse_vanity_pattern("5Abc").is_ok());
+		assert!(parse_vanity_pattern("").is_err());
+		assert!(parse_vanity_pattern("5l").is_err());
+	}
+
+	#[test]
+	fn generated_node_key_is_not_overwritten() {
+		let temp_dir = TempDir::new().unwrap();
+		let path = temp_dir.path().join("network").join(NODE_KEY_ED25519_FILE);
+		let cmd = GenerateNodeKeyCmd::from_iter(&[
+			"generate-node-key",
+			"--file",
+			path.to_str().unwrap(),
+		]);
+
+		cmd.clone().run(Configuration::default()).unwrap();
+		let secret = std::fs::read(&path).unwrap();
+		assert_eq!(secret.len(), 32);
+
+		assert!(cmd.run(Configuration::default()).is_err());
+		assert_eq!(std::fs::read(&path).unwrap(), secret);
+	}
+}
@@ -21,12 +21,25 @@ mod import_blocks_cmd;
 mod check_block_cmd;
 mod revert_cmd;
 mod purge_chain_cmd;
+mod export_runtime_cmd;
+mod key_cmd;
+mod export_state_cmd;
+mod chain_info_cmd;
+mod audit_cmd;
+mod export_genesis_wasm_cmd;
+mod export_metadata_cmd;
+mod export_epochs_cmd;
+mod build_genesis_state_cmd;
+mod network_state_cmd;
+mod admin_cmd;
 
 use std::fmt::Debug;
+use std::str::FromStr;
 use structopt::StructOpt;
 
 use sc_service::{ Configuration, ServiceBuilderCommand, ChainSpec };
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+use sp_runtime::generic::BlockId;
 
 use crate::error;
 use crate::VersionInfo;
@@ -39,6 +52,20 @@ pub use crate::commands::import_blocks_cmd::ImportBlocksCmd;
 pub use crate::commands::check_block_cmd::CheckBlockCmd;
 pub use crate::commands::revert_cmd::RevertCmd;
 pub use crate::commands::purge_chain_cmd::PurgeChainCmd;
+pub use crate::commands::export_runtime_cmd::ExportRuntimeCmd;
+pub use crate::commands::export_state_cmd::ExportStateCmd;
+pub use crate::commands::chain_info_cmd::ChainInfoCmd;
+pub use crate::commands::audit_cmd::AuditCmd;
+pub use crate::commands::export_genesis_wasm_cmd::ExportGenesisWasmCmd;
+pub use crate::commands::export_metadata_cmd::ExportMetadataCmd;
+pub use crate::commands::export_epochs_cmd::ExportEpochsCmd;
+pub use crate::commands::build_genesis_state_cmd::BuildGenesisStateCmd;
+pub use crate::commands::network_state_cmd::NetworkStateCmd;
+pub use crate::commands::admin_cmd::{AdminCmd, AdminAction};
+pub use crate::commands::key_cmd::{
+	KeySubcommand, GenerateKeyCmd, InspectKeyCmd, InsertKeyCmd, ListKeysCmd, GenerateNodeKeyCmd,
+	VanityCmd,
+};
 
 /// default sub directory to store network config
 const DEFAULT_NETWORK_CONFIG_PATH : &'static str = "network";
@@ -67,6 +94,39 @@ pub enum Subcommand {
 
 	/// Remove the whole chain data.
 	PurgeChain(purge_chain_cmd::PurgeChainCmd),
+
+	/// Extract the runtime code of a block, and optionally compare it with a Wasm file.
+	ExportRuntime(export_runtime_cmd::ExportRuntimeCmd),
+
+	/// Export the state of a given block into a chain spec.
+	ExportState(export_state_cmd::ExportStateCmd),
+
+	/// Generate and inspect keys, and manage the keystore of the node.
+	Key(key_cmd::KeySubcommand),
+
+	/// Print information about the chain stored in the database.
+	ChainInfo(chain_info_cmd::ChainInfoCmd),
+
+	/// Re-execute the last finalized blocks and write a signed attestation of the result.
+	Audit(audit_cmd::AuditCmd),
+
+	/// Extract the runtime code of the genesis of the chain spec.
+	ExportGenesisWasm(export_genesis_wasm_cmd::ExportGenesisWasmCmd),
+
+	/// Extract the SCALE-encoded metadata of the runtime of a block.
+	ExportMetadata(export_metadata_cmd::ExportMetadataCmd),
+
+	/// Write a signed snapshot of the epoch and authority set transitions, for light clients.
+	ExportEpochs(export_epochs_cmd::ExportEpochsCmd),
+
+	/// Build the genesis storage of a Wasm runtime from a JSON config into a raw chain spec.
+	BuildGenesisState(build_genesis_state_cmd::BuildGenesisStateCmd),
+
+	/// Print the network address book stored in the database.
+	NetworkState(network_state_cmd::NetworkStateCmd),
+
+	/// Query and manage a node over the p2p network, as one of its admin peers.
+	Admin(admin_cmd::AdminCmd),
 }
 
 impl Subcommand {
@@ -81,6 +141,17 @@ impl Subcommand {
 			CheckBlock(params) => &params.shared_params,
 			Revert(params) => &params.shared_params,
 			PurgeChain(params) => &params.shared_params,
+			ExportRuntime(params) => &params.shared_params,
+			ExportState(params) => &params.shared_params,
+			Key(cmd) => cmd.get_shared_params(),
+			ChainInfo(params) => &params.shared_params,
+			Audit(params) => &params.shared_params,
+			ExportGenesisWasm(params) => &params.shared_params,
+			ExportMetadata(params) => &params.shared_params,
+			ExportEpochs(params) => &params.shared_params,
+			BuildGenesisState(params) => &params.shared_params,
+			NetworkState(params) => &params.shared_params,
+			Admin(params) => &params.shared_params,
 		}
 	}
 
@@ -104,6 +175,17 @@ impl Subcommand {
 			Subcommand::CheckBlock(cmd) => cmd.run(config, builder),
 			Subcommand::PurgeChain(cmd) => cmd.run(config),
 			Subcommand::Revert(cmd) => cmd.run(config, builder),
+			Subcommand::ExportRuntime(cmd) => cmd.run(config, builder),
+			Subcommand::ExportState(cmd) => cmd.run(config, builder),
+			Subcommand::Key(cmd) => cmd.run(config),
+			Subcommand::ChainInfo(cmd) => cmd.run(config, builder),
+			Subcommand::Audit(cmd) => cmd.run(config, builder),
+			Subcommand::ExportGenesisWasm(cmd) => cmd.run(config),
+			Subcommand::ExportMetadata(cmd) => cmd.run(config, builder),
+			Subcommand::ExportEpochs(cmd) => cmd.run(config, builder),
+			Subcommand::BuildGenesisState(cmd) => cmd.run(config),
+			Subcommand::NetworkState(cmd) => cmd.run(config, builder),
+			Subcommand::Admin(cmd) => cmd.run(config, builder),
 		}
 	}
 
@@ -123,6 +205,19 @@ impl Subcommand {
 			Subcommand::CheckBlock(cmd) => cmd.update_config(&mut config, spec_factory, version),
 			Subcommand::PurgeChain(cmd) => cmd.update_config(&mut config, spec_factory, version),
 			Subcommand::Revert(cmd) => cmd.update_config(&mut config, spec_factory, version),
+			Subcommand::ExportRuntime(cmd) => cmd.update_config(&mut config, spec_factory, version),
+			Subcommand::ExportState(cmd) => cmd.update_config(&mut config, spec_factory, version),
+			Subcommand::Key(cmd) => cmd.update_config(&mut config, spec_factory, version),
+			Subcommand::ChainInfo(cmd) => cmd.update_config(&mut config, spec_factory, version),
+			Subcommand::Audit(cmd) => cmd.update_config(&mut config, spec_factory, version),
+			Subcommand::ExportGenesisWasm(cmd) =>
+				cmd.update_config(&mut config, spec_factory, version),
+			Subcommand::ExportMetadata(cmd) => cmd.update_config(&mut config, spec_factory, version),
+			Subcommand::ExportEpochs(cmd) => cmd.update_config(&mut config, spec_factory, version),
+			Subcommand::BuildGenesisState(cmd) =>
+				cmd.update_config(&mut config, spec_factory, version),
+			Subcommand::NetworkState(cmd) => cmd.update_config(&mut config, spec_factory, version),
+			Subcommand::Admin(cmd) => cmd.update_config(&mut config, spec_factory, version),
 		}
 	}
 
@@ -137,3 +232,17 @@ impl Subcommand {
 		self.get_shared_params().init(version)
 	}
 }
+
+/// Parse a block given either by its hash, with or without the `0x` prefix, or by its number.
+pub(crate) fn parse_block_id<B: BlockT>(input: &str) -> error::Result<BlockId<B>> where
+	<B as BlockT>::Hash: FromStr,
+{
+	let hash = if input.starts_with("0x") { &input[2..] } else { input };
+	match FromStr::from_str(hash) {
+		Ok(hash) => Ok(BlockId::hash(hash)),
+		Err(_) => match input.parse::<u32>() {
+			Ok(n) => Ok(BlockId::number(n.into())),
+			Err(_) => Err(error::Error::Input("Invalid hash or number specified".into())),
+		}
+	}
+}
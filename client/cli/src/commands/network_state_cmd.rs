@@ -0,0 +1,121 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::Debug;
+use structopt::StructOpt;
+use sc_service::{
+	Configuration, ServiceBuilderCommand, ChainSpec, Roles, config::DatabaseConfig,
+};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+
+use crate::error;
+use crate::VersionInfo;
+use crate::params::{SharedParams, PruningParams};
+
+/// The `network-state` command used to print the network address book stored in the database.
+#[derive(Debug, StructOpt, Clone)]
+pub struct NetworkStateCmd {
+	/// Print the address book as JSON.
+	#[structopt(long = "json")]
+	pub json: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+impl NetworkStateCmd {
+	/// Run the network-state command
+	pub fn run<B, BC, BB>(
+		self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: sp_runtime::traits::Block + Debug,
+		<<<BB as BlockT>::Header as HeaderT>::Number as std::str::FromStr>::Err: std::fmt::Debug,
+		<BB as BlockT>::Hash: std::str::FromStr,
+	{
+		// Opening an empty database would initialize it with the genesis block.
+		if let DatabaseConfig::Path { path, .. } = config.expect_database() {
+			if !path.exists() {
+				return Err(error::Error::Input(
+					format!("No database found at {}", path.display())
+				));
+			}
+		}
+
+		let peers = builder(config)?.address_book()?;
+
+		if self.json {
+			let peers = peers.iter()
+				.map(|peer| serde_json::json!({
+					"peerId": peer.peer_id.to_base58(),
+					"reputation": peer.reputation,
+					"addresses": peer.addresses.iter()
+						.map(|address| serde_json::json!({
+							"address": address.address.to_string(),
+							"lastSeen": address.last_seen,
+							"successes": address.successes,
+							"failures": address.failures,
+						}))
+						.collect::<Vec<_>>(),
+				}))
+				.collect::<Vec<_>>();
+			println!("{}", serde_json::Value::Array(peers));
+		} else {
+			println!("{} known peers", peers.len());
+			for peer in &peers {
+				println!("{} (reputation {})", peer.peer_id.to_base58(), peer.reputation);
+				for address in &peer.addresses {
+					let last_seen = address.last_seen
+						.map_or_else(|| "never".to_owned(), |secs| format!("at {}", secs));
+					println!(
+						"  {} seen {}, {} successes, {} failures",
+						address.address,
+						last_seen,
+						address.successes,
+						address.failures,
+					);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Update and prepare a `Configuration` with command line parameters
+	pub fn update_config<F>(
+		&self,
+		mut config: &mut Configuration,
+		spec_factory: F,
+		version: &VersionInfo,
+	) -> error::Result<()> where
+		F: FnOnce(&str) -> Result<Box<dyn ChainSpec>, String>,
+	{
+		self.shared_params.update_config(&mut config, spec_factory, version)?;
+		self.pruning_params.update_config(&mut config, Roles::FULL, true)?;
+		config.use_in_memory_keystore()?;
+
+		Ok(())
+	}
+}
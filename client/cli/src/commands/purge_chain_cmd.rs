@@ -17,20 +17,36 @@
 use std::fmt::Debug;
 use std::io::{Write, self};
 use std::fs;
+use std::path::PathBuf;
 use structopt::StructOpt;
 use sc_service::{ Configuration, ChainSpec, config::{DatabaseConfig} };
 
 use crate::error;
 use crate::VersionInfo;
-use crate::params::SharedParams;
+use crate::params::{SharedParams, DEFAULT_KEYSTORE_CONFIG_PATH};
+use crate::commands::DEFAULT_NETWORK_CONFIG_PATH;
 
-/// The `purge-chain` command used to remove the whole chain.
+/// The `purge-chain` command used to remove the data of a chain.
 #[derive(Debug, StructOpt, Clone)]
 pub struct PurgeChainCmd {
 	/// Skip interactive prompt by answering yes automatically.
 	#[structopt(short = "y")]
 	pub yes: bool,
 
+	/// Remove everything in the chain directory instead of only the database, including the
+	/// keystore holding the session keys.
+	#[structopt(long = "all")]
+	pub all: bool,
+
+	/// Keep the keystore of the chain, holding the session keys, when removing everything.
+	#[structopt(long = "keep-keystore", requires = "all")]
+	pub keep_keystore: bool,
+
+	/// Keep the network configuration of the chain, holding the node key and thus the peer id,
+	/// when removing everything.
+	#[structopt(long = "keep-network-key", requires = "all")]
+	pub keep_network_key: bool,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub shared_params: SharedParams,
@@ -43,15 +59,38 @@ impl PurgeChainCmd {
 		config: Configuration,
 	) -> error::Result<()> {
 		let db_path = match config.expect_database() {
-			DatabaseConfig::Path { path, .. } => path,
+			DatabaseConfig::Path { path, .. } => path.clone(),
 			_ => {
 				eprintln!("Cannot purge custom database implementation");
 				return Ok(());
 			}
 		};
 
+		let paths = if self.all {
+			let chain_path = config.in_chain_config_dir("")
+				.expect("We provided a base_path/config_dir.");
+			self.chain_dir_entries(&chain_path)?
+		} else {
+			vec![db_path]
+		};
+
+		if paths.is_empty() {
+			eprintln!("Nothing to remove.");
+			return Ok(());
+		}
+
+		let keystore = paths.iter().find(|path| {
+			path.file_name().map_or(false, |name| name == DEFAULT_KEYSTORE_CONFIG_PATH)
+		});
+
 		if !self.yes {
-			print!("Are you sure to remove {:?}? [y/N]: ", &db_path);
+			if let Some(keystore) = keystore {
+				println!(
+					"The keystore {:?} will be removed, with the session keys it holds.",
+					keystore,
+				);
+			}
+			print!("Are you sure to remove {:?}? [y/N]: ", &paths);
 			io::stdout().flush().expect("failed to flush stdout");
 
 			let mut input = String::new();
@@ -67,17 +106,46 @@ impl PurgeChainCmd {
 			}
 		}
 
-		match fs::remove_dir_all(&db_path) {
-			Ok(_) => {
-				println!("{:?} removed.", &db_path);
-				Ok(())
-			},
-			Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
-				eprintln!("{:?} did not exist.", &db_path);
-				Ok(())
-			},
-			Err(err) => Result::Err(err.into())
+		for path in paths {
+			let removed = if path.is_dir() {
+				fs::remove_dir_all(&path)
+			} else {
+				fs::remove_file(&path)
+			};
+			match removed {
+				Ok(_) => println!("{:?} removed.", &path),
+				Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+					eprintln!("{:?} did not exist.", &path);
+				},
+				Err(err) => return Err(err.into()),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// The entries of the chain directory to remove, leaving out the ones to keep.
+	fn chain_dir_entries(&self, chain_path: &PathBuf) -> error::Result<Vec<PathBuf>> {
+		let entries = match fs::read_dir(chain_path) {
+			Ok(entries) => entries,
+			Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(err) => return Err(err.into()),
+		};
+
+		let mut paths = Vec::new();
+		for entry in entries {
+			let entry = entry?;
+			let name = entry.file_name();
+			if (self.keep_keystore && name == DEFAULT_KEYSTORE_CONFIG_PATH)
+				|| (self.keep_network_key && name == DEFAULT_NETWORK_CONFIG_PATH)
+			{
+				continue;
+			}
+			paths.push(entry.path());
 		}
+		paths.sort();
+
+		Ok(paths)
 	}
 
 	/// Update and prepare a `Configuration` with command line parameters
@@ -14,34 +14,38 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::net::SocketAddr;
-use std::fs;
-use log::info;
+use std::time::Duration;
+use log::{info, error};
 use structopt::{StructOpt, clap::arg_enum};
 use names::{Generator, Name};
 use regex::Regex;
 use chrono::prelude::*;
 use sc_service::{
-	AbstractService, Configuration, ChainSpec, Roles,
-	config::{KeystoreConfig, PrometheusConfig},
+	AbstractService, Configuration, ChainSpec, Roles, ReloadHandle,
+	config::{
+		PeerId, PrometheusConfig, ReloadableConfig, ConsensusRecorderConfig, FinalizedExportConfig,
+		EpochSnapshotConfig, DiskWatchdogConfig, RpcLimits, RpcRateLimit, RpcTlsConfig,
+		StorageSubscriptionThrottle, WorkerBridgeConfig, SecondarySlots,
+	},
 };
 use sc_telemetry::TelemetryEndpoints;
+use sp_core::{crypto::Ss58Codec, sr25519};
 
 use crate::VersionInfo;
+use crate::RpcMethods;
 use crate::error;
 use crate::params::ImportParams;
 use crate::params::SharedParams;
 use crate::params::NetworkConfigurationParams;
 use crate::params::TransactionPoolParams;
-use crate::runtime::run_service_until_exit;
+use crate::params::KeystoreParams;
+use crate::runtime::run_service_until_exit_with_reload;
 
 /// The maximum number of characters for a node name.
 const NODE_NAME_MAX_LENGTH: usize = 32;
 
-/// default sub directory for the key store
-const DEFAULT_KEYSTORE_CONFIG_PATH : &'static str = "keystore";
-
 arg_enum! {
 	/// Whether off-chain workers are enabled.
 	#[allow(missing_docs)]
@@ -56,6 +60,18 @@ arg_enum! {
 /// The `run` command used to run a node.
 #[derive(Debug, StructOpt, Clone)]
 pub struct RunCmd {
+	/// Load the node parameters from a TOML configuration file.
+	///
+	/// Every key of the file is the long name of a flag of this command, e.g.
+	/// `rpc-port = 9933` or `validator = true`. Flags given on the command
+	/// line take precedence over the values found in the file.
+	///
+	/// The file is read again when the node receives `SIGHUP`, and the new
+	/// `log`, `reserved-nodes`, `rpc-cors`, `telemetry-url` and `no-telemetry`
+	/// values are applied without restarting the node.
+	#[structopt(long = "config", value_name = "PATH", parse(from_os_str))]
+	pub config: Option<PathBuf>,
+
 	/// Enable validator mode.
 	///
 	/// The node will be started with the authority role and actively
@@ -76,11 +92,16 @@ pub struct RunCmd {
 	/// detached from the network), since we want this node to participate in
 	/// the full consensus protocols in order to have all needed consensus data
 	/// available to relay to private nodes.
+	///
+	/// The addresses of the validators this node is a sentry of can be given,
+	/// including their peer id, so that the node stays connected to them.
 	#[structopt(
 		long = "sentry",
+		value_name = "VALIDATOR",
+		min_values = 0,
 		conflicts_with_all = &[ "validator", "light" ]
 	)]
-	pub sentry: bool,
+	pub sentry: Option<Vec<String>>,
 
 	/// Disable GRANDPA voter when running in validator mode, otherwise disable the GRANDPA observer.
 	#[structopt(long = "no-grandpa")]
@@ -92,8 +113,9 @@ pub struct RunCmd {
 
 	/// Listen to all RPC interfaces.
 	///
-	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Use an RPC proxy
-	/// server to filter out dangerous methods. More details: https://github.com/paritytech/substrate/wiki/Public-RPC.
+	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Unless
+	/// `--rpc-methods unsafe` is passed, only the safe subset of RPC methods is served.
+	/// More details: https://github.com/paritytech/substrate/wiki/Public-RPC.
 	/// Use `--unsafe-rpc-external` to suppress the warning if you understand the risks.
 	#[structopt(long = "rpc-external")]
 	pub rpc_external: bool,
@@ -106,8 +128,9 @@ pub struct RunCmd {
 
 	/// Listen to all Websocket interfaces.
 	///
-	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Use an RPC proxy
-	/// server to filter out dangerous methods. More details: https://github.com/paritytech/substrate/wiki/Public-RPC.
+	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Unless
+	/// `--rpc-methods unsafe` is passed, only the safe subset of RPC methods is served.
+	/// More details: https://github.com/paritytech/substrate/wiki/Public-RPC.
 	/// Use `--unsafe-ws-external` to suppress the warning if you understand the risks.
 	#[structopt(long = "ws-external")]
 	pub ws_external: bool,
@@ -118,6 +141,27 @@ pub struct RunCmd {
 	#[structopt(long = "unsafe-ws-external")]
 	pub unsafe_ws_external: bool,
 
+	/// RPC methods to expose.
+	///
+	/// `Unsafe` exposes every RPC method, `Safe` only a safe subset of them. `Auto` acts as
+	/// `Safe` if RPC is served externally (e.g. when `--rpc-external` or `--ws-external` is
+	/// passed), otherwise as `Unsafe`.
+	#[structopt(
+		long = "rpc-methods",
+		value_name = "METHOD SET",
+		possible_values = &RpcMethods::variants(),
+		case_insensitive = true,
+		default_value = "Auto"
+	)]
+	pub rpc_methods: RpcMethods,
+
+	/// Network identity of a peer allowed to administrate this node.
+	///
+	/// Admin peers can send any RPC request (plus `admin_setLogLevel`) to this node over the
+	/// p2p network, without an RPC port being exposed. Can be passed multiple times.
+	#[structopt(long = "admin-peer", value_name = "PEER_ID", parse(try_from_str = parse_peer_id))]
+	pub admin_peers: Vec<PeerId>,
+
 	/// Listen to all Prometheus data source interfaces.
 	///
 	/// Default is local.
@@ -136,6 +180,13 @@ pub struct RunCmd {
 	#[structopt(long = "ws-max-connections", value_name = "COUNT")]
 	pub ws_max_connections: Option<usize>,
 
+	/// Do not compress the messages of the WS RPC server.
+	///
+	/// By default, messages are compressed for the clients supporting the `permessage-deflate`
+	/// WebSocket extension.
+	#[structopt(long = "ws-no-compression")]
+	pub ws_no_compression: bool,
+
 	/// Specify browser Origins allowed to access the HTTP & WS RPC servers.
 	///
 	/// A comma-separated list of origins (protocol://domain or special `null`
@@ -145,6 +196,88 @@ pub struct RunCmd {
 	#[structopt(long = "rpc-cors", value_name = "ORIGINS", parse(try_from_str = parse_cors))]
 	pub rpc_cors: Option<Cors>,
 
+	/// Maximal size of the HTTP requests and WS messages of the RPC servers, in megabytes.
+	///
+	/// Default is 15.
+	#[structopt(long = "rpc-max-payload", value_name = "MB")]
+	pub rpc_max_payload: Option<usize>,
+
+	/// Maximal number of calls in an RPC batch request.
+	///
+	/// Default is 1024.
+	#[structopt(long = "rpc-max-batch-size", value_name = "COUNT")]
+	pub rpc_max_batch_size: Option<usize>,
+
+	/// Maximal number of subscriptions of a WS RPC connection.
+	///
+	/// Default is 1024.
+	#[structopt(long = "rpc-max-subscriptions-per-connection", value_name = "COUNT")]
+	pub rpc_max_subscriptions_per_connection: Option<usize>,
+
+	/// Limit the rate of calls to the HTTP & WS RPC servers, in calls per second.
+	///
+	/// Each WS connection and each HTTP client IP address is limited separately. Calls over the
+	/// limit are answered with an error. HTTP clients must open a new connection per request.
+	#[structopt(long = "rpc-rate-limit", value_name = "COUNT")]
+	pub rpc_rate_limit: Option<u32>,
+
+	/// Number of RPC calls allowed at once under the rate limit. Defaults to the rate.
+	#[structopt(long = "rpc-rate-limit-burst", value_name = "COUNT", requires = "rpc-rate-limit")]
+	pub rpc_rate_limit_burst: Option<u32>,
+
+	/// Serve the HTTP & WS RPC servers over TLS (https:// and wss://), with the PEM encoded
+	/// certificate chain in the given file.
+	#[structopt(
+		long = "rpc-tls-cert",
+		value_name = "PATH",
+		parse(from_os_str),
+		requires = "rpc-tls-key"
+	)]
+	pub rpc_tls_cert: Option<PathBuf>,
+
+	/// PEM encoded private key of the RPC TLS certificate, in PKCS#8 or RSA format.
+	#[structopt(
+		long = "rpc-tls-key",
+		value_name = "PATH",
+		parse(from_os_str),
+		requires = "rpc-tls-cert"
+	)]
+	pub rpc_tls_key: Option<PathBuf>,
+
+	/// Notify each storage subscription at most once every given number of blocks.
+	///
+	/// Changes made in between are merged into a single notification, protecting the node from
+	/// clients watching keys that change every block.
+	#[structopt(long = "rpc-storage-throttle-blocks", value_name = "COUNT")]
+	pub rpc_storage_throttle_blocks: Option<u32>,
+
+	/// Notify each storage subscription at most once every given number of milliseconds.
+	///
+	/// Changes made in between are merged into a single notification.
+	#[structopt(long = "rpc-storage-throttle-ms", value_name = "MS")]
+	pub rpc_storage_throttle_ms: Option<u64>,
+
+	/// Listen for external workers, such as oracles or provers, on the given unix socket.
+	///
+	/// The workers can follow the chain and submit transactions through the socket, which is
+	/// only accessible to the user running the node.
+	#[structopt(
+		long = "worker-socket",
+		value_name = "PATH",
+		parse(from_os_str),
+		requires = "worker-token-file"
+	)]
+	pub worker_socket: Option<PathBuf>,
+
+	/// File containing the token the workers must present to the worker socket.
+	#[structopt(
+		long = "worker-token-file",
+		value_name = "PATH",
+		parse(from_os_str),
+		requires = "worker-socket"
+	)]
+	pub worker_token_file: Option<PathBuf>,
+
 	/// Specify Prometheus data source server TCP Port.
 	#[structopt(long = "prometheus-port", value_name = "PORT")]
 	pub prometheus_port: Option<u16>,
@@ -188,6 +321,58 @@ pub struct RunCmd {
 	)]
 	pub offchain_worker: OffchainWorkerEnabled,
 
+	/// Record GRANDPA round votes and BABE slot claims as newline-delimited JSON to files in the
+	/// given directory, for research on the behaviour of the network.
+	#[structopt(long = "consensus-recorder", value_name = "PATH", parse(from_os_str))]
+	pub consensus_recorder: Option<PathBuf>,
+
+	/// Replace the authority keys in the recorded consensus artifacts with salted hashes, which
+	/// can't be linked across restarts.
+	#[structopt(long = "consensus-recorder-anonymize", requires = "consensus-recorder")]
+	pub consensus_recorder_anonymize: bool,
+
+	/// Size in MiB above which the consensus recorder starts a new file.
+	#[structopt(long = "consensus-recorder-file-size", value_name = "MIB", default_value = "100")]
+	pub consensus_recorder_file_size: u64,
+
+	/// Number of older consensus recorder files kept.
+	#[structopt(long = "consensus-recorder-files", value_name = "COUNT", default_value = "10")]
+	pub consensus_recorder_files: usize,
+
+	/// Atomically write the number and hash of the last finalized block to the given file, as
+	/// JSON, each time a block is finalized.
+	#[structopt(long = "export-finalized", value_name = "PATH", parse(from_os_str))]
+	pub export_finalized: Option<PathBuf>,
+
+	/// Shell command to run after the finalized block was exported. The block is passed in the
+	/// `FINALIZED_NUMBER` and `FINALIZED_HASH` environment variables, and the file in
+	/// `FINALIZED_FILE`. Not run again while the previous run is in progress.
+	#[structopt(
+		long = "export-finalized-hook",
+		value_name = "COMMAND",
+		requires = "export-finalized"
+	)]
+	pub export_finalized_hook: Option<String>,
+
+	/// Import the signed epoch snapshot written by `export-epochs` on start, and import the
+	/// headers of its transitions without verifying their seals. Light clients only.
+	#[structopt(
+		long = "epoch-snapshot",
+		value_name = "PATH",
+		parse(from_os_str),
+		requires = "epoch-snapshot-signer"
+	)]
+	pub epoch_snapshot: Option<PathBuf>,
+
+	/// SS58 address of the sr25519 key the epoch snapshot must be signed with.
+	#[structopt(
+		long = "epoch-snapshot-signer",
+		value_name = "SS58",
+		parse(try_from_str = parse_sr25519_public),
+		requires = "epoch-snapshot"
+	)]
+	pub epoch_snapshot_signer: Option<sr25519::Public>,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub shared_params: SharedParams,
@@ -236,42 +421,73 @@ pub struct RunCmd {
 	#[structopt(long, conflicts_with_all = &["alice", "bob", "charlie", "dave", "eve", "ferdie", "one"])]
 	pub two: bool,
 
-	/// Enable authoring even when offline.
+	/// Enable authoring even when offline, or when no block is received from the peers.
 	#[structopt(long = "force-authoring")]
 	pub force_authoring: bool,
 
-	/// Specify custom keystore path.
-	#[structopt(long = "keystore-path", value_name = "PATH", parse(from_os_str))]
-	pub keystore_path: Option<PathBuf>,
-
-	/// Use interactive shell for entering the password used by the keystore.
+	/// Whether the secondary slots are claimed, overriding the chain spec.
+	///
+	/// With `skip`, blocks are only authored on the primary slots.
 	#[structopt(
-		long = "password-interactive",
-		conflicts_with_all = &[ "password", "password-filename" ]
+		long = "secondary-slots",
+		value_name = "POLICY",
+		possible_values = &SecondarySlots::VARIANTS,
+		case_insensitive = true
 	)]
-	pub password_interactive: bool,
+	pub secondary_slots: Option<SecondarySlots>,
 
-	/// Password used by the keystore.
-	#[structopt(
-		long = "password",
-		conflicts_with_all = &[ "password-interactive", "password-filename" ]
-	)]
-	pub password: Option<String>,
+	/// Stop authoring while the best block is more than this number of blocks ahead of the
+	/// finalized block, e.g. while finality is stalled.
+	#[structopt(long = "max-unfinalized-blocks", value_name = "COUNT")]
+	pub max_unfinalized_blocks: Option<u32>,
 
-	/// File that contains the password used by the keystore.
+	/// Proportion of the slot given to build a block, overriding the chain spec.
+	///
+	/// The rest of the slot is left to import and announce the block, e.g. with `0.8` the
+	/// transactions are no longer pulled from the pool once 80% of the slot has elapsed. Must be
+	/// greater than 0 and at most 1.
 	#[structopt(
-		long = "password-filename",
-		value_name = "PATH",
-		parse(from_os_str),
-		conflicts_with_all = &[ "password-interactive", "password" ]
+		long = "proposal-slot-proportion",
+		value_name = "FRACTION",
+		parse(try_from_str = parse_slot_proportion),
 	)]
-	pub password_filename: Option<PathBuf>,
+	pub proposal_slot_proportion: Option<f32>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub keystore_params: KeystoreParams,
 
 	/// The size of the instances cache for each runtime.
 	///
 	/// The default value is 8 and the values higher than 256 are ignored.
 	#[structopt(long = "max-runtime-instances", default_value = "8")]
 	pub max_runtime_instances: usize,
+
+	/// Maximum number of seconds given to the node to shut down gracefully.
+	///
+	/// On SIGTERM or SIGINT the node stops serving RPC, stops its background tasks,
+	/// disconnects from its peers and closes the database. If this takes longer than
+	/// the given timeout the node exits anyway.
+	#[structopt(long = "shutdown-timeout", value_name = "SECS", default_value = "60")]
+	pub shutdown_timeout: u64,
+
+	/// Don't check the free space on the disk of the database.
+	#[structopt(long = "no-disk-watchdog")]
+	pub no_disk_watchdog: bool,
+
+	/// Free space on the disk of the database, in MiB, under which a warning is logged.
+	#[structopt(long = "disk-warn-threshold", value_name = "MIB", default_value = "2048")]
+	pub disk_warn_threshold: u64,
+
+	/// Free space on the disk of the database, in MiB, under which the gossip messages and
+	/// transactions received from the network are dropped.
+	#[structopt(long = "disk-degrade-threshold", value_name = "MIB", default_value = "1024")]
+	pub disk_degrade_threshold: u64,
+
+	/// Free space on the disk of the database, in MiB, under which the node shuts down before
+	/// the database gets corrupted.
+	#[structopt(long = "disk-shutdown-threshold", value_name = "MIB", default_value = "256")]
+	pub disk_shutdown_threshold: u64,
 }
 
 impl RunCmd {
@@ -302,34 +518,12 @@ impl RunCmd {
 	{
 		self.shared_params.update_config(&mut config, spec_factory, version)?;
 
-		let password = if self.password_interactive {
-			#[cfg(not(target_os = "unknown"))]
-			{
-				Some(input_keystore_password()?.into())
-			}
-			#[cfg(target_os = "unknown")]
-			None
-		} else if let Some(ref file) = self.password_filename {
-			Some(fs::read_to_string(file).map_err(|e| format!("{}", e))?.into())
-		} else if let Some(ref password) = self.password {
-			Some(password.clone().into())
-		} else {
-			None
-		};
-
-		let path = self.keystore_path.clone().or(
-			config.in_chain_config_dir(DEFAULT_KEYSTORE_CONFIG_PATH)
-		);
-
-		config.keystore = KeystoreConfig::Path {
-			path: path.ok_or_else(|| "No `base_path` provided to create keystore path!".to_string())?,
-			password,
-		};
+		self.keystore_params.update_config(&mut config)?;
 
 		let keyring = self.get_keyring();
 		let is_dev = self.shared_params.dev;
 		let is_light = self.light;
-		let is_authority = (self.validator || self.sentry || is_dev || keyring.is_some())
+		let is_authority = (self.validator || self.sentry.is_some() || is_dev || keyring.is_some())
 			&& !is_light;
 		let role =
 			if is_light {
@@ -357,7 +551,7 @@ impl RunCmd {
 		}
 
 		// set sentry mode (i.e. act as an authority but **never** actively participate)
-		config.sentry_mode = self.sentry;
+		config.sentry_mode = self.sentry.is_some();
 
 		config.offchain_worker = match (&self.offchain_worker, role) {
 			(OffchainWorkerEnabled::WhenValidating, sc_service::Roles::AUTHORITY) => true,
@@ -379,6 +573,9 @@ impl RunCmd {
 			client_id,
 			is_dev,
 		)?;
+		if let Some(validators) = self.sentry.as_ref() {
+			config.network.sentry_validators.extend(validators.iter().cloned());
+		}
 
 		self.pool_config.update_config(&mut config)?;
 
@@ -392,15 +589,70 @@ impl RunCmd {
 			});
 
 		if config.rpc_http.is_none() || self.rpc_port.is_some() {
-			let rpc_interface: &str = interface_str(self.rpc_external, self.unsafe_rpc_external, self.validator)?;
+			let rpc_interface: &str = interface_str(
+				self.rpc_external,
+				self.unsafe_rpc_external,
+				self.rpc_methods,
+				self.validator,
+			)?;
 			config.rpc_http = Some(parse_address(&format!("{}:{}", rpc_interface, 9933), self.rpc_port)?);
 		}
 		if config.rpc_ws.is_none() || self.ws_port.is_some() {
-			let ws_interface: &str = interface_str(self.ws_external, self.unsafe_ws_external, self.validator)?;
+			let ws_interface: &str = interface_str(
+				self.ws_external,
+				self.unsafe_ws_external,
+				self.rpc_methods,
+				self.validator,
+			)?;
 			config.rpc_ws = Some(parse_address(&format!("{}:{}", ws_interface, 9944), self.ws_port)?);
 		}
 
+		config.rpc_methods = self.rpc_methods.into();
+		config.admin_peers = self.admin_peers.clone();
 		config.rpc_ws_max_connections = self.ws_max_connections;
+		config.rpc_ws_compression = !self.ws_no_compression;
+		let default_limits = RpcLimits::default();
+		config.rpc_limits = RpcLimits {
+			max_payload: self.rpc_max_payload
+				.map(|megabytes| megabytes.saturating_mul(1024 * 1024))
+				.unwrap_or(default_limits.max_payload),
+			max_batch_size: self.rpc_max_batch_size.unwrap_or(default_limits.max_batch_size),
+			max_subscriptions_per_connection: self.rpc_max_subscriptions_per_connection
+				.unwrap_or(default_limits.max_subscriptions_per_connection),
+		};
+		config.rpc_rate_limit = match self.rpc_rate_limit {
+			Some(0) => return Err(error::Error::Input("--rpc-rate-limit must be positive".into())),
+			Some(requests_per_second) => Some(RpcRateLimit {
+				requests_per_second,
+				burst: self.rpc_rate_limit_burst.unwrap_or(requests_per_second).max(1),
+			}),
+			None => None,
+		};
+		config.rpc_tls = match (&self.rpc_tls_cert, &self.rpc_tls_key) {
+			(Some(cert), Some(key)) => Some(RpcTlsConfig { cert: cert.clone(), key: key.clone() }),
+			_ => None,
+		};
+		config.rpc_storage_subscription_throttle =
+			match (self.rpc_storage_throttle_blocks, self.rpc_storage_throttle_ms) {
+				(None, None) => None,
+				(blocks, ms) => Some(StorageSubscriptionThrottle {
+					min_blocks: blocks.unwrap_or(0),
+					min_interval: Duration::from_millis(ms.unwrap_or(0)),
+				}),
+			};
+		config.worker_bridge = match (&self.worker_socket, &self.worker_token_file) {
+			(Some(path), Some(token_file)) => {
+				let token = std::fs::read_to_string(token_file).map_err(|e| error::Error::Input(
+					format!("Failed to read --worker-token-file: {}", e),
+				))?;
+				let token = token.trim();
+				if token.is_empty() {
+					return Err(error::Error::Input("--worker-token-file is empty".into()));
+				}
+				Some(WorkerBridgeConfig { path: path.clone(), token: token.into() })
+			},
+			_ => None,
+		};
 		config.rpc_cors = self.rpc_cors.clone().unwrap_or_else(|| if is_dev {
 			log::warn!("Running in --dev mode, RPC CORS has been disabled.");
 			Cors::All
@@ -433,13 +685,53 @@ impl RunCmd {
 			));
 		}
 
+		config.consensus_recorder = self.consensus_recorder.clone().map(|path| ConsensusRecorderConfig {
+			path,
+			max_file_size: self.consensus_recorder_file_size * 1024 * 1024,
+			max_files: self.consensus_recorder_files,
+			anonymize: self.consensus_recorder_anonymize,
+		});
+
+		config.export_finalized = self.export_finalized.clone().map(|path| FinalizedExportConfig {
+			path,
+			hook: self.export_finalized_hook.clone(),
+		});
+
+		config.epoch_snapshot = self.epoch_snapshot.clone()
+			.and_then(|path| self.epoch_snapshot_signer.map(|signer| EpochSnapshotConfig {
+				path,
+				signer,
+			}));
+
+		config.disk_watchdog = if self.no_disk_watchdog {
+			None
+		} else {
+			if self.disk_warn_threshold < self.disk_degrade_threshold
+				|| self.disk_degrade_threshold < self.disk_shutdown_threshold
+			{
+				return Err(error::Error::Input(
+					"Disk thresholds must be ordered: warn >= degrade >= shutdown".into()
+				));
+			}
+			Some(DiskWatchdogConfig {
+				warn: self.disk_warn_threshold * 1024 * 1024,
+				degrade: self.disk_degrade_threshold * 1024 * 1024,
+				shutdown: self.disk_shutdown_threshold * 1024 * 1024,
+				interval: Duration::from_secs(10),
+			})
+		};
+
 		config.tracing_targets = self.import_params.tracing_targets.clone().into();
 		config.tracing_receiver = self.import_params.tracing_receiver.clone().into();
 
 		// Imply forced authoring on --dev
 		config.force_authoring = self.shared_params.dev || self.force_authoring;
+		config.secondary_slots = self.secondary_slots;
+		config.max_unfinalized_blocks = self.max_unfinalized_blocks;
+		config.proposal_slot_proportion = self.proposal_slot_proportion;
 
 		config.max_runtime_instances = self.max_runtime_instances.min(256);
+		config.shutdown_timeout = Duration::from_secs(self.shutdown_timeout);
 
 		Ok(())
 	}
@@ -467,14 +759,27 @@ impl RunCmd {
 
 		info!("Test msg-output line~~~~~~~~~~~~~~~~~~~~~~~~~~~~~");
 
+		let mut reloader = self.config.clone().map(|path| ConfigReloader {
+			path,
+			log: self.shared_params.log.clone(),
+			config: config.reloadable(),
+		});
+		let on_reload = move |service: &ReloadHandle| if let Some(reloader) = reloader.as_mut() {
+			if let Err(e) = reloader.reload(service) {
+				error!("Failed to reload the configuration file: {}", e);
+			}
+		};
+
 		match config.roles {
-			Roles::LIGHT => run_service_until_exit(
+			Roles::LIGHT => run_service_until_exit_with_reload(
 				config,
 				new_light,
+				on_reload,
 			),
-			_ => run_service_until_exit(
+			_ => run_service_until_exit_with_reload(
 				config,
 				new_full,
+				on_reload,
 			),
 		}
 	}
@@ -491,6 +796,68 @@ impl RunCmd {
 	}
 }
 
+/// Applies the values of the configuration file that can be changed while the node is running.
+struct ConfigReloader {
+	path: PathBuf,
+	/// The log pattern currently applied.
+	log: Option<String>,
+	/// The configuration currently applied.
+	config: ReloadableConfig,
+}
+
+impl ConfigReloader {
+	/// Read the configuration file again and apply it to the logger and to the service.
+	///
+	/// Values given on the command line, or missing from the file, are left untouched.
+	fn reload(&mut self, service: &ReloadHandle) -> error::Result<()> {
+		let (log, config) = reloaded_config(&self.path, &self.log, &self.config)?;
+
+		crate::reload_log_filters(log.as_ref().map(|v| v.as_ref()).unwrap_or(""));
+		service.reload(config.clone());
+
+		self.log = log;
+		self.config = config;
+		Ok(())
+	}
+}
+
+/// Returns the log pattern and the configuration in `path`, falling back to the given ones.
+fn reloaded_config(
+	path: &Path,
+	log: &Option<String>,
+	config: &ReloadableConfig,
+) -> error::Result<(Option<String>, ReloadableConfig)> {
+	let invalid = |flag: &str, e: Box<dyn std::error::Error>| error::Error::Input(
+		format!("Invalid value for `{}` in configuration file: {}", flag, e)
+	);
+
+	let mut log = log.clone();
+	let mut config = config.clone();
+	let mut no_telemetry = false;
+	for (flag, values) in crate::config_file::reload(path)? {
+		match flag.as_str() {
+			"--log" => log = values.last().cloned(),
+			"--reserved-nodes" => config.reserved_nodes = values,
+			"--rpc-cors" => if let Some(value) = values.last() {
+				config.rpc_cors = parse_cors(value).map_err(|e| invalid(&flag, e))?.into();
+			},
+			"--telemetry-url" => config.telemetry_endpoints = Some(TelemetryEndpoints::new(
+				values.iter()
+					.map(|value| parse_telemetry_endpoints(value))
+					.collect::<Result<_, _>>()
+					.map_err(|e| invalid(&flag, e))?
+			)),
+			"--no-telemetry" => no_telemetry = true,
+			_ => {},
+		}
+	}
+	if no_telemetry {
+		config.telemetry_endpoints = None;
+	}
+
+	Ok((log, config))
+}
+
 /// Check whether a node name is considered as valid.
 pub fn is_node_name_valid(_name: &str) -> Result<(), &str> {
 	let name = _name.to_string();
@@ -513,12 +880,6 @@ pub fn is_node_name_valid(_name: &str) -> Result<(), &str> {
 	Ok(())
 }
 
-#[cfg(not(target_os = "unknown"))]
-fn input_keystore_password() -> Result<String, String> {
-	rpassword::read_password_from_tty(Some("Keystore password: "))
-		.map_err(|e| format!("{:?}", e))
-}
-
 fn generate_node_name() -> String {
 	let result = loop {
 		let node_name = Generator::with_naming(Name::Numbered).next().unwrap();
@@ -549,6 +910,7 @@ fn parse_address(
 fn interface_str(
 	is_external: bool,
 	is_unsafe_external: bool,
+	rpc_methods: RpcMethods,
 	is_validator: bool,
 ) -> Result<&'static str, error::Error> {
 	if is_external && is_validator {
@@ -558,8 +920,10 @@ fn interface_str(
 	}
 
 	if is_external || is_unsafe_external {
-		log::warn!("It isn't safe to expose RPC publicly without a proxy server that filters \
-		available set of RPC methods.");
+		if rpc_methods == RpcMethods::Unsafe {
+			log::warn!("It isn't safe to expose RPC publicly without a proxy server that filters \
+			available set of RPC methods.");
+		}
 
 		Ok("0.0.0.0")
 	} else {
@@ -567,6 +931,21 @@ fn interface_str(
 	}
 }
 
+fn parse_peer_id(s: &str) -> Result<PeerId, String> {
+	s.parse().map_err(|_| format!("Invalid peer id: {}", s))
+}
+
+fn parse_slot_proportion(s: &str) -> Result<f32, String> {
+	match s.parse::<f32>() {
+		Ok(proportion) if proportion > 0.0 && proportion <= 1.0 => Ok(proportion),
+		_ => Err(format!("Invalid slot proportion, expected a number in (0, 1]: {}", s)),
+	}
+}
+
+fn parse_sr25519_public(s: &str) -> Result<sr25519::Public, String> {
+	sr25519::Public::from_ss58check(s).map_err(|_| format!("Invalid SS58 address: {}", s))
+}
+
 /// Default to verbosity level 0, if none is provided.
 fn parse_telemetry_endpoints(s: &str) -> Result<(String, u8), Box<dyn std::error::Error>> {
 	let pos = s.find(' ');
@@ -623,7 +1002,9 @@ fn parse_cors(s: &str) -> Result<Cors, Box<dyn std::error::Error>> {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use sc_network::config::{TransportConfig, WebSocketTlsConfig};
 	use sc_service::{GenericChainSpec, config::DatabaseConfig};
+	use std::iter;
 
 	const TEST_VERSION_INFO: &'static VersionInfo = &VersionInfo {
 		name: "node-test",
@@ -651,6 +1032,17 @@ mod tests {
 		assert!(is_node_name_valid("email@domain").is_err());
 	}
 
+	#[test]
+	fn slot_proportion_must_be_in_range() {
+		assert_eq!(parse_slot_proportion("0.8"), Ok(0.8));
+		assert_eq!(parse_slot_proportion("1"), Ok(1.0));
+		assert!(parse_slot_proportion("0").is_err());
+		assert!(parse_slot_proportion("-0.5").is_err());
+		assert!(parse_slot_proportion("1.5").is_err());
+		assert!(parse_slot_proportion("NaN").is_err());
+		assert!(parse_slot_proportion("two thirds").is_err());
+	}
+
 	#[test]
 	fn keystore_path_is_generated_correctly() {
 		let chain_spec = GenericChainSpec::from_genesis(
@@ -667,7 +1059,7 @@ mod tests {
 		for keystore_path in vec![None, Some("/keystore/path")] {
 			let args: Vec<&str> = vec![];
 			let mut cli = RunCmd::from_iter(args);
-			cli.keystore_path = keystore_path.clone().map(PathBuf::from);
+			cli.keystore_params.keystore_path = keystore_path.clone().map(PathBuf::from);
 
 			let mut config = Configuration::default();
 			config.config_dir = Some(PathBuf::from("/test/path"));
@@ -739,4 +1131,71 @@ mod tests {
 		assert!(config.network.config_path.is_some());
 		assert!(!config.network.listen_addresses.is_empty());
 	}
+
+	#[test]
+	fn wss_listen_address_requires_certificate() {
+		let chain_spec = GenericChainSpec::from_genesis(
+			"test",
+			"test-id",
+			|| (),
+			vec![],
+			None,
+			None,
+			None,
+			None::<()>,
+		);
+		let update_config = |args: &[&str]| {
+			let cli = RunCmd::from_iter(
+				iter::once("node-test").chain(args.iter().cloned()).collect::<Vec<_>>()
+			);
+			let mut config = Configuration::from_version(TEST_VERSION_INFO);
+			let chain_spec = chain_spec.clone();
+			cli.update_config(&mut config, move |_| Ok(Box::new(chain_spec)), TEST_VERSION_INFO)
+				.map(|()| config)
+		};
+
+		assert!(update_config(&["--listen-addr", "/ip4/0.0.0.0/tcp/443/wss"]).is_err());
+		assert!(update_config(&["--listen-addr", "/ip4/0.0.0.0/tcp/30334/ws"]).is_ok());
+
+		let config = update_config(&[
+			"--listen-addr", "/ip4/0.0.0.0/tcp/443/wss",
+			"--network-tls-cert", "/tls/cert.pem",
+			"--network-tls-key", "/tls/key.pem",
+		]).unwrap();
+		match config.network.transport {
+			TransportConfig::Normal { websocket_tls, .. } => assert_eq!(
+				websocket_tls,
+				Some(WebSocketTlsConfig {
+					cert: PathBuf::from("/tls/cert.pem"),
+					key: PathBuf::from("/tls/key.pem"),
+				}),
+			),
+			TransportConfig::MemoryOnly => panic!("invalid transport"),
+		}
+	}
+
+	#[test]
+	fn reloaded_config_falls_back_to_current_values() {
+		use std::io::Write;
+
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		writeln!(file, "log = \"sync=trace\"").unwrap();
+		writeln!(file, "rpc-cors = \"all\"").unwrap();
+		writeln!(file, "telemetry-url = [\"ws://telemetry.example.com/submit 1\"]").unwrap();
+		let current = ReloadableConfig {
+			telemetry_endpoints: None,
+			reserved_nodes: vec!["/ip4/127.0.0.1/tcp/30333".into()],
+			rpc_cors: Some(vec!["http://localhost:*".into()]),
+		};
+
+		let (log, config) = reloaded_config(file.path(), &None, &current).unwrap();
+
+		assert_eq!(log, Some("sync=trace".into()));
+		assert_eq!(config.reserved_nodes, current.reserved_nodes);
+		assert_eq!(config.rpc_cors, None);
+		assert_eq!(
+			config.telemetry_endpoints,
+			Some(TelemetryEndpoints::new(vec![("ws://telemetry.example.com/submit".into(), 1)])),
+		);
+	}
 }
@@ -0,0 +1,301 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Loading of command line parameters from a TOML configuration file.
+//!
+//! Every key of the file is the long name of a command line flag (without the leading `--`).
+//! Booleans enable a flag when `true`, arrays are expanded into one occurrence per element and
+//! every other scalar is passed as the value of the flag. Flags given on the command line, by their
+//! long or short name, always take precedence over the values found in the file.
+//!
+//! ```toml
+//! name = "my-node"
+//! validator = true
+//! rpc-port = 9933
+//! telemetry-url = ["ws://telemetry.example.com:8000/submit 0"]
+//! ```
+//!
+//! Some of the parameters can be changed while the node is running, see [`reload`].
+
+use std::{collections::HashSet, ffi::OsString, fs, path::Path};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use structopt::clap::{App, ArgMatches};
+
+use crate::error;
+
+/// The long name of the flag used to point at the configuration file.
+const CONFIG_FLAG: &str = "--config";
+
+lazy_static! {
+	/// The command line, recorded by `merge_args`.
+	static ref COMMAND_LINE: Mutex<CommandLine> = Default::default();
+}
+
+/// The arguments given on the command line.
+#[derive(Default)]
+struct CommandLine {
+	/// The arguments, without the executable name.
+	args: Vec<OsString>,
+	/// The flags of the configuration file that are given in `args`.
+	given: HashSet<String>,
+}
+
+impl CommandLine {
+	fn is_flag_given(&self, flag: &str) -> bool {
+		self.given.contains(flag) || is_flag_given(None, &self.args, flag)
+	}
+}
+
+/// Extend the given command line arguments of `app` with the parameters found in the
+/// configuration file passed with `--config`, if any.
+///
+/// The first element of `args` is expected to be the executable name. The parameters of the file
+/// are inserted right after it, skipping every flag that is already present in `args`.
+pub fn merge_args(app: &App, args: Vec<OsString>) -> error::Result<Vec<OsString>> {
+	let path = match config_path(&args) {
+		Some(path) => path,
+		None => return Ok(args),
+	};
+
+	let file_args = parse(&read(path)?)?;
+	let matches = app.clone().get_matches_from_safe(args.iter()).ok();
+
+	let mut merged = Vec::with_capacity(args.len() + file_args.len());
+	let mut args = args.into_iter();
+	merged.extend(args.next());
+
+	let mut command_line = CommandLine { args: args.collect(), given: HashSet::new() };
+	for (flag, values) in file_args {
+		if is_flag_given(matches.as_ref(), &command_line.args, &flag) {
+			command_line.given.insert(flag);
+			continue
+		}
+		if values.is_empty() {
+			merged.push(flag.into());
+		}
+		for value in values {
+			merged.push(flag.clone().into());
+			merged.push(value.into());
+		}
+	}
+	merged.extend(command_line.args.iter().cloned());
+	*COMMAND_LINE.lock() = command_line;
+
+	Ok(merged)
+}
+
+/// Read the configuration file again, returning its flags that are not given on the command line.
+///
+/// Only meaningful once the command line went through `merge_args`.
+pub fn reload(path: &Path) -> error::Result<Vec<(String, Vec<String>)>> {
+	reload_with_given(path, &COMMAND_LINE.lock())
+}
+
+fn reload_with_given(
+	path: &Path,
+	command_line: &CommandLine,
+) -> error::Result<Vec<(String, Vec<String>)>> {
+	let file_args = parse(&read(path)?)?;
+	Ok(file_args.into_iter().filter(|(flag, _)| !command_line.is_flag_given(flag)).collect())
+}
+
+fn read(path: &Path) -> error::Result<String> {
+	fs::read_to_string(path).map_err(|e| error::Error::Input(
+		format!("Failed to read configuration file {}: {}", path.display(), e)
+	))
+}
+
+/// Convert the content of a TOML configuration file into a list of flags and their values.
+///
+/// A flag without value is a switch; a flag with multiple values is repeated once per value.
+pub fn parse(content: &str) -> error::Result<Vec<(String, Vec<String>)>> {
+	let table: toml::value::Table = toml::from_str(content).map_err(|e| error::Error::Input(
+		format!("Invalid configuration file: {}", e)
+	))?;
+
+	let mut args = Vec::with_capacity(table.len());
+	for (key, value) in table {
+		let flag = format!("--{}", key);
+		match value {
+			toml::Value::Boolean(true) => args.push((flag, Vec::new())),
+			toml::Value::Boolean(false) => {},
+			toml::Value::Array(values) => {
+				let values = values.into_iter()
+					.map(|v| scalar_to_string(&key, v))
+					.collect::<error::Result<Vec<_>>>()?;
+				if !values.is_empty() {
+					args.push((flag, values));
+				}
+			},
+			value => args.push((flag, vec![scalar_to_string(&key, value)?])),
+		}
+	}
+
+	Ok(args)
+}
+
+fn scalar_to_string(key: &str, value: toml::Value) -> error::Result<String> {
+	match value {
+		toml::Value::String(s) => Ok(s),
+		toml::Value::Integer(i) => Ok(i.to_string()),
+		toml::Value::Float(f) => Ok(f.to_string()),
+		_ => Err(error::Error::Input(
+			format!("Unsupported value for `{}` in configuration file", key)
+		)),
+	}
+}
+
+/// Find the value of `--config` in the command line arguments.
+fn config_path(args: &[OsString]) -> Option<&Path> {
+	let mut args = args.iter().skip(1);
+	while let Some(arg) = args.next() {
+		let arg = match arg.to_str() {
+			Some(arg) => arg,
+			None => continue,
+		};
+		if arg == CONFIG_FLAG {
+			return args.next().map(Path::new)
+		}
+		if arg.starts_with(CONFIG_FLAG) && arg[CONFIG_FLAG.len()..].starts_with('=') {
+			return Some(Path::new(&arg[CONFIG_FLAG.len() + 1..]))
+		}
+	}
+
+	None
+}
+
+/// Check whether the given long flag is present in the command line arguments.
+///
+/// The arguments parsed by clap in `matches` find the flag by its long or short name. Structopt
+/// names the arguments after their field, which is also the long name of most flags; the other
+/// ones are only found in `args` by their long name, either as `--flag` or as `--flag=value`.
+fn is_flag_given(matches: Option<&ArgMatches>, args: &[OsString], flag: &str) -> bool {
+	let occurs = matches.map_or(false, |matches| {
+		let matches = matches.subcommand().1.unwrap_or(matches);
+		matches.occurrences_of(&flag[2..]) > 0
+	});
+
+	occurs || args.iter()
+		.filter_map(|arg| arg.to_str())
+		.any(|arg| arg == flag || (arg.starts_with(flag) && arg[flag.len()..].starts_with('=')))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Write;
+	use structopt::StructOpt;
+
+	#[derive(Debug, StructOpt)]
+	struct Cmd {
+		#[structopt(long = "config")]
+		config: Option<String>,
+
+		#[structopt(long = "name")]
+		name: Option<String>,
+
+		#[structopt(short = "l", long = "log")]
+		log: Option<String>,
+
+		#[structopt(long = "rpc-port")]
+		rpc_port: Option<u16>,
+
+		#[structopt(long = "validator")]
+		validator: bool,
+	}
+
+	fn os_args(args: &[&str]) -> Vec<OsString> {
+		args.iter().map(OsString::from).collect()
+	}
+
+	#[test]
+	fn parses_scalars_switches_and_arrays() {
+		let args = parse(r#"
+			name = "node"
+			rpc-port = 9933
+			validator = true
+			light = false
+			bootnodes = ["/ip4/127.0.0.1/tcp/30333", "/ip4/127.0.0.1/tcp/30334"]
+		"#).unwrap();
+
+		assert_eq!(args, vec![
+			("--bootnodes".into(), vec![
+				"/ip4/127.0.0.1/tcp/30333".into(),
+				"/ip4/127.0.0.1/tcp/30334".into(),
+			]),
+			("--name".into(), vec!["node".into()]),
+			("--rpc-port".into(), vec!["9933".into()]),
+			("--validator".into(), vec![]),
+		]);
+	}
+
+	#[test]
+	fn rejects_tables() {
+		assert!(parse("[network]\nport = 1").is_err());
+	}
+
+	#[test]
+	fn command_line_overrides_config_file() {
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		writeln!(file, "name = \"from-file\"\nrpc-port = 1000\nvalidator = true").unwrap();
+		let config = format!("--config={}", file.path().display());
+
+		let args = merge_args(&Cmd::clap(), os_args(&["node", &config, "--rpc-port", "2000"]))
+			.unwrap();
+
+		assert_eq!(args, os_args(&[
+			"node",
+			"--name", "from-file",
+			"--validator",
+			&config,
+			"--rpc-port", "2000",
+		]));
+	}
+
+	#[test]
+	fn short_flags_override_config_file() {
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		writeln!(file, "name = \"from-file\"\nlog = \"sync=debug\"").unwrap();
+		let config = format!("--config={}", file.path().display());
+
+		let args = merge_args(&Cmd::clap(), os_args(&["node", &config, "-linfo"])).unwrap();
+
+		assert_eq!(args, os_args(&["node", "--name", "from-file", &config, "-linfo"]));
+	}
+
+	#[test]
+	fn reload_skips_flags_given_on_command_line() {
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		writeln!(file, "log = \"sync=debug\"\nrpc-cors = \"all\"").unwrap();
+		writeln!(file, "reserved-nodes = [\"/ip4/127.0.0.1/tcp/30333\"]").unwrap();
+		let command_line = CommandLine {
+			args: os_args(&["--rpc-cors", "none"]),
+			given: HashSet::new(),
+		};
+
+		assert_eq!(reload_with_given(file.path(), &command_line).unwrap(), vec![
+			("--log".into(), vec!["sync=debug".into()]),
+			("--reserved-nodes".into(), vec!["/ip4/127.0.0.1/tcp/30333".into()]),
+		]);
+	}
+
+	#[test]
+	fn no_config_flag_leaves_arguments_untouched() {
+		let args = os_args(&["node", "--validator"]);
+		assert_eq!(merge_args(&Cmd::clap(), args.clone()).unwrap(), args);
+	}
+}
@@ -0,0 +1,254 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Environment variable fallback for command line parameters.
+//!
+//! Every long flag `--some-flag` of the invoked command can be provided through the environment
+//! variable `SUBSTRATE_SOME_FLAG`. Switches are enabled by `true` or `1` and disabled by `false`
+//! or `0`, while the value of an option is always passed as is. Flags given on the command line
+//! always take precedence over the environment. Environment variables that don't match any flag
+//! of the invoked command are ignored.
+//!
+//! The variables are merged by rewriting the command line arguments before they are parsed, so
+//! that they are validated like any other argument.
+
+use std::ffi::OsString;
+use structopt::clap::{App, ErrorKind};
+
+/// The prefix of the environment variables that are mapped to command line flags.
+pub const ENV_PREFIX: &str = "SUBSTRATE_";
+
+/// Extend the given command line arguments with the flags provided through the environment.
+///
+/// The first element of `args` is expected to be the executable name.
+pub fn merge_args(app: &App, args: Vec<OsString>) -> Vec<OsString> {
+	merge_args_from_vars(app, args, std::env::vars_os())
+}
+
+fn merge_args_from_vars<I>(app: &App, args: Vec<OsString>, vars: I) -> Vec<OsString> where
+	I: IntoIterator<Item = (OsString, OsString)>,
+{
+	let subcommand = app.clone().get_matches_from_safe(args.iter())
+		.ok()
+		.and_then(|matches| matches.subcommand_name().map(ToOwned::to_owned));
+	let command = args.iter().take(1).cloned()
+		.chain(subcommand.iter().map(OsString::from))
+		.collect::<Vec<_>>();
+
+	let mut env_args = vars.into_iter()
+		.filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+		.filter_map(|(key, value)| env_to_flag(&key, value, |long| {
+			takes_value(app, &command, long)
+		}))
+		.filter(|(flag, _)| !is_flag_given(&args, flag))
+		.collect::<Vec<_>>();
+	env_args.sort();
+
+	if env_args.is_empty() {
+		return args
+	}
+
+	// the flags of a subcommand must be given after the subcommand name.
+	let position = subcommand
+		.and_then(|name| args.iter().skip(1).position(|arg| arg.to_str() == Some(&name)))
+		.map(|position| position + 2)
+		.unwrap_or(1)
+		.min(args.len());
+
+	loop {
+		let merged = insert_flags(&args, position, &env_args);
+
+		// drop the variables that aren't flags of the invoked command.
+		match app.clone().get_matches_from_safe(merged.iter()) {
+			Err(structopt::clap::Error { kind: ErrorKind::UnknownArgument, info: Some(info), .. }) => {
+				let len = env_args.len();
+				env_args.retain(|(flag, _)| !info.contains(flag));
+				if env_args.len() == len {
+					return merged
+				}
+			},
+			_ => return merged,
+		}
+	}
+}
+
+/// Convert an environment variable to a flag and its value, if it has the right prefix.
+///
+/// `takes_value` tells whether the long flag, without its leading dashes, is an option taking a
+/// value rather than a switch. Returns `None` for switches that are explicitly disabled.
+fn env_to_flag(
+	key: &str,
+	value: String,
+	takes_value: impl Fn(&str) -> bool,
+) -> Option<(String, Option<String>)> {
+	if !key.starts_with(ENV_PREFIX) || key.len() == ENV_PREFIX.len() {
+		return None
+	}
+
+	let long = key[ENV_PREFIX.len()..].to_lowercase().replace('_', "-");
+	let flag = format!("--{}", long);
+	if takes_value(&long) {
+		return Some((flag, Some(value)))
+	}
+
+	match value.as_str() {
+		"true" | "1" => Some((flag, None)),
+		"false" | "0" => None,
+		_ => Some((flag, Some(value))),
+	}
+}
+
+/// Check whether the long flag `long` of the command invoked by the `command` arguments, i.e. the
+/// executable name and the subcommand name if any, is an option taking a value.
+///
+/// Clap asks for the value of such an option when it's given alone.
+fn takes_value(app: &App, command: &[OsString], long: &str) -> bool {
+	let mut args = command.to_vec();
+	args.push(format!("--{}", long).into());
+
+	match app.clone().get_matches_from_safe(args) {
+		Err(structopt::clap::Error { kind: ErrorKind::EmptyValue, .. }) => true,
+		_ => false,
+	}
+}
+
+fn insert_flags(
+	args: &[OsString],
+	position: usize,
+	flags: &[(String, Option<String>)],
+) -> Vec<OsString> {
+	let mut merged = Vec::with_capacity(args.len() + flags.len() * 2);
+	merged.extend_from_slice(&args[..position]);
+	for (flag, value) in flags {
+		merged.push(flag.into());
+		merged.extend(value.iter().map(OsString::from));
+	}
+	merged.extend_from_slice(&args[position..]);
+	merged
+}
+
+/// Check whether the given long flag is present in the command line arguments, either as
+/// `--flag` or as `--flag=value`.
+fn is_flag_given(args: &[OsString], flag: &str) -> bool {
+	args.iter()
+		.filter_map(|arg| arg.to_str())
+		.any(|arg| arg == flag || (arg.starts_with(flag) && arg[flag.len()..].starts_with('=')))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use structopt::StructOpt;
+
+	#[derive(Debug, StructOpt)]
+	struct Cmd {
+		#[structopt(long = "rpc-port")]
+		rpc_port: Option<u16>,
+
+		#[structopt(long = "validator")]
+		validator: bool,
+
+		#[structopt(subcommand)]
+		subcommand: Option<Subcommand>,
+	}
+
+	#[derive(Debug, StructOpt)]
+	enum Subcommand {
+		PurgeChain {
+			#[structopt(long = "dev")]
+			dev: bool,
+
+			#[structopt(long = "pruning")]
+			pruning: Option<u32>,
+		},
+	}
+
+	fn os_args(args: &[&str]) -> Vec<OsString> {
+		args.iter().map(OsString::from).collect()
+	}
+
+	fn vars(vars: &[(&str, &str)]) -> Vec<(OsString, OsString)> {
+		vars.iter().map(|(k, v)| (k.into(), v.into())).collect()
+	}
+
+	#[test]
+	fn env_vars_are_mapped_to_flags() {
+		let args = merge_args_from_vars(
+			&Cmd::clap(),
+			os_args(&["node"]),
+			vars(&[("SUBSTRATE_RPC_PORT", "9933"), ("SUBSTRATE_VALIDATOR", "true"), ("HOME", "/")]),
+		);
+
+		assert_eq!(args, os_args(&["node", "--rpc-port", "9933", "--validator"]));
+	}
+
+	#[test]
+	fn command_line_overrides_env_vars() {
+		let args = merge_args_from_vars(
+			&Cmd::clap(),
+			os_args(&["node", "--rpc-port=1000"]),
+			vars(&[("SUBSTRATE_RPC_PORT", "9933"), ("SUBSTRATE_VALIDATOR", "false")]),
+		);
+
+		assert_eq!(args, os_args(&["node", "--rpc-port=1000"]));
+	}
+
+	#[test]
+	fn unknown_env_vars_are_ignored() {
+		let args = merge_args_from_vars(
+			&Cmd::clap(),
+			os_args(&["node"]),
+			vars(&[("SUBSTRATE_UNKNOWN", "1"), ("SUBSTRATE_RPC_PORT", "9933")]),
+		);
+
+		assert_eq!(args, os_args(&["node", "--rpc-port", "9933"]));
+	}
+
+	#[test]
+	fn env_vars_are_given_to_subcommands() {
+		let args = merge_args_from_vars(
+			&Cmd::clap(),
+			os_args(&["node", "purge-chain"]),
+			vars(&[("SUBSTRATE_DEV", "1"), ("SUBSTRATE_RPC_PORT", "9933")]),
+		);
+
+		assert_eq!(args, os_args(&["node", "purge-chain", "--dev"]));
+	}
+
+	#[test]
+	fn numeric_options_are_not_switches() {
+		let args = merge_args_from_vars(
+			&Cmd::clap(),
+			os_args(&["node"]),
+			vars(&[("SUBSTRATE_RPC_PORT", "0"), ("SUBSTRATE_VALIDATOR", "0")]),
+		);
+		assert_eq!(args, os_args(&["node", "--rpc-port", "0"]));
+
+		let args = merge_args_from_vars(
+			&Cmd::clap(),
+			os_args(&["node"]),
+			vars(&[("SUBSTRATE_RPC_PORT", "1"), ("SUBSTRATE_VALIDATOR", "1")]),
+		);
+		assert_eq!(args, os_args(&["node", "--rpc-port", "1", "--validator"]));
+
+		let args = merge_args_from_vars(
+			&Cmd::clap(),
+			os_args(&["node", "purge-chain"]),
+			vars(&[("SUBSTRATE_PRUNING", "1"), ("SUBSTRATE_DEV", "0")]),
+		);
+		assert_eq!(args, os_args(&["node", "purge-chain", "--pruning", "1"]));
+	}
+}
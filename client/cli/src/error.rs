@@ -16,6 +16,8 @@
 
 //! Initialization errors.
 
+pub use sp_blockchain::ErrorKind;
+
 /// Result type alias for the CLI.
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -30,6 +32,8 @@ pub enum Error {
 	Service(sc_service::Error),
 	/// Client error
 	Client(sp_blockchain::Error),
+	/// Keystore error
+	Keystore(sc_keystore::Error),
 	/// Input error
 	#[from(ignore)]
 	Input(String),
@@ -55,6 +59,24 @@ impl std::convert::From<&str> for Error {
 	}
 }
 
+impl Error {
+	/// The category of the error.
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			Error::Service(e) => e.kind(),
+			Error::Client(e) => e.kind(),
+			Error::Keystore(_) => ErrorKind::Keystore,
+			Error::Cli(_) | Error::Input(_) | Error::InvalidListenMultiaddress => ErrorKind::Config,
+			Error::Io(_) | Error::Other(_) => ErrorKind::Other,
+		}
+	}
+
+	/// The exit code of the process failing with this error, the code of its category.
+	pub fn exit_code(&self) -> i32 {
+		self.kind().code().into()
+	}
+}
+
 impl std::error::Error for Error {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
@@ -62,9 +84,26 @@ impl std::error::Error for Error {
 			Error::Cli(ref err) => Some(err),
 			Error::Service(ref err) => Some(err),
 			Error::Client(ref err) => Some(err),
+			Error::Keystore(ref err) => Some(err),
 			Error::Input(_) => None,
 			Error::InvalidListenMultiaddress => None,
 			Error::Other(_) => None,
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn exit_codes_follow_error_kinds() {
+		let database = Error::Service(sc_service::Error::Client(
+			sp_blockchain::Error::Blockchain(Box::new(sp_blockchain::Error::Backend("".into()))),
+		));
+		assert_eq!(database.kind(), ErrorKind::Database);
+		assert_eq!(database.exit_code(), 14);
+		assert_eq!(Error::Input("".into()).exit_code(), 10);
+		assert_eq!(Error::Other("".into()).exit_code(), 1);
+	}
+}
@@ -24,6 +24,9 @@ mod arg_enums;
 mod error;
 mod runtime;
 mod commands;
+mod config_file;
+mod env_args;
+mod log_file;
 
 pub use sc_service::config::VersionInfo;
 
@@ -38,7 +41,9 @@ pub use arg_enums::*;
 pub use error::*;
 use log::info;
 use lazy_static::lazy_static;
-pub use crate::runtime::{run_until_exit, run_service_until_exit};
+pub use crate::runtime::{run_until_exit, run_service_until_exit, run_service_until_exit_with_reload};
+pub use crate::env_args::ENV_PREFIX;
+pub use crate::log_file::{LogFile, reopen_log_files};
 
 /// Helper function used to parse the command line arguments. This is the equivalent of
 /// `structopt`'s `from_iter()` except that it takes a `VersionInfo` argument to provide the name of
@@ -65,6 +70,11 @@ where
 ///
 /// Gets the struct from any iterator such as a `Vec` of your making.
 /// Print the error message and quit the program in case of failure.
+///
+/// Every flag that is not given on the command line can be provided through an environment
+/// variable, e.g. `SUBSTRATE_RPC_PORT` for `--rpc-port`. When `--config <PATH>` is passed, the
+/// flags found in the TOML file at `PATH` are used for every flag that is given neither on the
+/// command line nor through the environment.
 pub fn from_iter<T, I>(iter: I, version: &VersionInfo) -> T
 where
 	T: StructOpt + Sized,
@@ -90,7 +100,14 @@ where
 			AppSettings::SubcommandsNegateReqs,
 		]);
 
-	T::from_clap(&app.get_matches_from(iter))
+	let args = env_args::merge_args(&app, iter.into_iter().map(Into::into).collect());
+	let args = config_file::merge_args(&app, args)
+		.unwrap_or_else(|e| clap::Error::with_description(
+			&e.to_string(),
+			clap::ErrorKind::InvalidValue,
+		).exit());
+
+	T::from_clap(&app.get_matches_from(args))
 }
 
 /// Helper function used to parse the command line arguments. This is the equivalent of
@@ -127,7 +144,10 @@ where
 		.about(version.description)
 		.version(full_version.as_str());
 
-	let matches = app.get_matches_from_safe(iter)?;
+	let args = env_args::merge_args(&app, iter.into_iter().map(Into::into).collect());
+	let args = config_file::merge_args(&app, args)
+		.map_err(|e| clap::Error::with_description(&e.to_string(), clap::ErrorKind::InvalidValue))?;
+	let matches = app.get_matches_from_safe(args)?;
 
 	Ok(T::from_clap(&matches))
 }
@@ -139,7 +159,12 @@ where
 /// 1. Set the panic handler
 /// 2. Raise the FD limit
 /// 3. Initialize the logger
-pub fn init(logger_pattern: &str, version: &VersionInfo) -> error::Result<()> {
+pub fn init(
+	logger_pattern: &str,
+	log_format: LogFormat,
+	log_file: Option<LogFile>,
+	version: &VersionInfo,
+) -> error::Result<()> {
 	let full_version = sc_service::config::full_version_from_strs(
 		version.version,
 		version.commit
@@ -147,16 +172,103 @@ pub fn init(logger_pattern: &str, version: &VersionInfo) -> error::Result<()> {
 	sp_panic_handler::set(version.support_url, &full_version);
 
 	fdlimit::raise_fd_limit();
-	init_logger(logger_pattern);
+	init_logger_with_format(logger_pattern, log_format, log_file);
 
 	Ok(())
 }
 
 /// Initialize the logger
 pub fn init_logger(pattern: &str) {
-	use ansi_term::Colour;
+	init_logger_with_format(pattern, LogFormat::Text, None)
+}
 
+/// Initialize the logger, writing the records in the given format.
+///
+/// The records are written to the given log file if any, and to stderr otherwise.
+pub fn init_logger_with_format(pattern: &str, format: LogFormat, log_file: Option<LogFile>) {
 	let mut builder = env_logger::Builder::new();
+	// The records are filtered by `ReloadableLogger` before reaching this logger.
+	builder.filter(None, log::LevelFilter::Trace);
+	let isatty = atty::is(atty::Stream::Stderr);
+	let enable_color = isatty && log_file.is_none();
+	let duplicate_to_stdout = !isatty && log_file.is_none() && atty::is(atty::Stream::Stdout);
+	let log_file = log_file.map(parking_lot::Mutex::new);
+
+	builder.format(move |buf, record| {
+		let output = match format {
+			LogFormat::Text => {
+				let output = text_record(record);
+				if duplicate_to_stdout && record.level() <= log::Level::Info {
+					// duplicate INFO/WARN output to console
+					println!("{}", output);
+				}
+
+				if enable_color {
+					output
+				} else {
+					kill_color(output.as_ref())
+				}
+			},
+			LogFormat::Json => json_record(record, time::now_utc()),
+		};
+
+		match &log_file {
+			Some(log_file) => log_file.lock().write_line(&output),
+			None => writeln!(buf, "{}", output),
+		}
+	});
+
+	let logger = ReloadableLogger(builder.build());
+	if log::set_boxed_logger(Box::new(logger)).is_err() {
+		info!("Not registering Substrate logger, as there is already a global logger registered!");
+		return
+	}
+
+	let filter = log_filter(pattern);
+	log::set_max_level(filter.filter());
+	*LOG_FILTER.write() = Some(filter);
+}
+
+/// Replace the filters of the logger registered by `init_logger`.
+///
+/// The pattern has the same syntax as the one given to `init_logger`, and is applied on top of
+/// the default filters and of `RUST_LOG`. Does nothing if another logger is registered.
+pub fn reload_log_filters(pattern: &str) {
+	let mut current = LOG_FILTER.write();
+	if current.is_some() {
+		let filter = log_filter(pattern);
+		log::set_max_level(filter.filter());
+		*current = Some(filter);
+	}
+}
+
+lazy_static! {
+	/// Filter of the logger registered by `init_logger`. `None` until it is registered.
+	static ref LOG_FILTER: parking_lot::RwLock<Option<env_logger::filter::Filter>> = Default::default();
+}
+
+/// Logger checking the records against `LOG_FILTER` before writing them.
+struct ReloadableLogger(env_logger::Logger);
+
+impl log::Log for ReloadableLogger {
+	fn enabled(&self, metadata: &log::Metadata) -> bool {
+		LOG_FILTER.read().as_ref().map_or(false, |filter| filter.enabled(metadata))
+	}
+
+	fn log(&self, record: &log::Record) {
+		if LOG_FILTER.read().as_ref().map_or(false, |filter| filter.matches(record)) {
+			self.0.log(record);
+		}
+	}
+
+	fn flush(&self) {
+		self.0.flush();
+	}
+}
+
+/// Build the log filter for the given pattern.
+fn log_filter(pattern: &str) -> env_logger::filter::Filter {
+	let mut builder = env_logger::filter::Builder::new();
 	// Disable info logging by default for some modules:
 	builder.filter(Some("ws"), log::LevelFilter::Off);
 	builder.filter(Some("hyper"), log::LevelFilter::Warn);
@@ -167,52 +279,56 @@ pub fn init_logger(pattern: &str) {
 	builder.filter(None, log::LevelFilter::Info);
 
 	if let Ok(lvl) = std::env::var("RUST_LOG") {
-		builder.parse_filters(&lvl);
+		builder.parse(&lvl);
 	}
 
-	builder.parse_filters(pattern);
-	let isatty = atty::is(atty::Stream::Stderr);
-	let enable_color = isatty;
-
-	builder.format(move |buf, record| {
-		let now = time::now();
-		let timestamp =
-			time::strftime("%Y-%m-%d %H:%M:%S", &now)
-				.expect("Error formatting log timestamp");
-
-		let mut output = if log::max_level() <= log::LevelFilter::Info {
-			format!("{} {}", Colour::Black.bold().paint(timestamp), record.args())
-		} else {
-			let name = ::std::thread::current()
-				.name()
-				.map_or_else(Default::default, |x| format!("{}", Colour::Blue.bold().paint(x)));
-			let millis = (now.tm_nsec as f32 / 1000000.0).round() as usize;
-			let timestamp = format!("{}.{:03}", timestamp, millis);
-			format!(
-				"{} {} {} {}  {}",
-				Colour::Black.bold().paint(timestamp),
-				name,
-				record.level(),
-				record.target(),
-				record.args()
-			)
-		};
+	builder.parse(pattern);
+	builder.build()
+}
 
-		if !isatty && record.level() <= log::Level::Info && atty::is(atty::Stream::Stdout) {
-			// duplicate INFO/WARN output to console
-			println!("{}", output);
-		}
+/// Format a log record as a human readable line.
+fn text_record(record: &log::Record) -> String {
+	use ansi_term::Colour;
 
-		if !enable_color {
-			output = kill_color(output.as_ref());
-		}
+	let now = time::now();
+	let timestamp =
+		time::strftime("%Y-%m-%d %H:%M:%S", &now)
+			.expect("Error formatting log timestamp");
+
+	if log::max_level() <= log::LevelFilter::Info {
+		format!("{} {}", Colour::Black.bold().paint(timestamp), record.args())
+	} else {
+		let name = ::std::thread::current()
+			.name()
+			.map_or_else(Default::default, |x| format!("{}", Colour::Blue.bold().paint(x)));
+		let millis = (now.tm_nsec as f32 / 1000000.0).round() as usize;
+		let timestamp = format!("{}.{:03}", timestamp, millis);
+		format!(
+			"{} {} {} {}  {}",
+			Colour::Black.bold().paint(timestamp),
+			name,
+			record.level(),
+			record.target(),
+			record.args()
+		)
+	}
+}
 
-		writeln!(buf, "{}", output)
-	});
+/// Format a log record as a JSON object on a single line.
+fn json_record(record: &log::Record, now: time::Tm) -> String {
+	let timestamp = format!(
+		"{}.{:03}Z",
+		time::strftime("%Y-%m-%dT%H:%M:%S", &now).expect("Error formatting log timestamp"),
+		now.tm_nsec / 1_000_000,
+	);
 
-	if builder.try_init().is_err() {
-		info!("Not registering Substrate logger, as there is already a global logger registered!");
-	}
+	serde_json::json!({
+		"timestamp": timestamp,
+		"level": record.level().to_string(),
+		"target": record.target(),
+		"thread": std::thread::current().name(),
+		"message": kill_color(&record.args().to_string()),
+	}).to_string()
 }
 
 fn kill_color(s: &str) -> String {
@@ -221,3 +337,41 @@ fn kill_color(s: &str) -> String {
 	}
 	RE.replace_all(s, "").to_string()
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ansi_term::Colour;
+
+	#[test]
+	fn log_filter_pattern_overrides_defaults() {
+		let filter = log_filter("sync=debug,ws=info");
+		let enabled = |target, level| filter.enabled(
+			&log::Metadata::builder().target(target).level(level).build()
+		);
+
+		assert!(enabled("sync", log::Level::Debug));
+		assert!(enabled("ws", log::Level::Info));
+		assert!(!enabled("hyper", log::Level::Info));
+		assert!(!enabled("substrate", log::Level::Debug));
+	}
+
+	#[test]
+	fn json_record_is_a_single_line_without_colors() {
+		let line = json_record(
+			&log::Record::builder()
+				.args(format_args!("{}\n#1", Colour::White.bold().paint("Imported")))
+				.level(log::Level::Info)
+				.target("substrate")
+				.build(),
+			time::at_utc(time::Timespec::new(0, 5_000_000)),
+		);
+		assert!(!line.contains('\n'));
+
+		let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+		assert_eq!(json["timestamp"], "1970-01-01T00:00:00.005Z");
+		assert_eq!(json["level"], "INFO");
+		assert_eq!(json["target"], "substrate");
+		assert_eq!(json["message"], "Imported\n#1");
+	}
+}
@@ -0,0 +1,151 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Log file output, with size based rotation.
+//!
+//! When the log file grows past the rotation size, it is renamed to `<path>.1`, the previous
+//! `<path>.1` to `<path>.2` and so on, keeping at most the configured number of rotated files.
+//!
+//! The file is re-opened on the next write after [`reopen_log_files`] has been called (which
+//! happens when the node receives `SIGHUP`), so that external tools such as `logrotate` can move
+//! the file away.
+
+use std::{
+	fs::{self, File, OpenOptions}, io::{self, Write}, path::{Path, PathBuf},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Incremented every time the log files must be re-opened.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Re-open the log files before their next write.
+pub fn reopen_log_files() {
+	GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A log file, rotated once it grows past a given size.
+pub struct LogFile {
+	path: PathBuf,
+	rotate_size: Option<u64>,
+	rotate_keep: usize,
+	file: File,
+	size: u64,
+	generation: usize,
+}
+
+impl LogFile {
+	/// Open the log file at the given path, appending to it if it already exists.
+	///
+	/// The file is rotated once it grows past `rotate_size` bytes, if any, and at most
+	/// `rotate_keep` rotated files are kept.
+	pub fn open(path: PathBuf, rotate_size: Option<u64>, rotate_keep: usize) -> io::Result<Self> {
+		let generation = GENERATION.load(Ordering::Relaxed);
+		let (file, size) = open_append(&path)?;
+		Ok(LogFile { path, rotate_size, rotate_keep, file, size, generation })
+	}
+
+	/// Write a line to the log file, rotating or re-opening it first if needed.
+	pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+		let generation = GENERATION.load(Ordering::Relaxed);
+		if generation != self.generation {
+			self.generation = generation;
+			self.reopen()?;
+		}
+
+		let len = line.len() as u64 + 1;
+		if let Some(rotate_size) = self.rotate_size {
+			if self.size > 0 && self.size + len > rotate_size {
+				self.rotate()?;
+			}
+		}
+
+		writeln!(self.file, "{}", line)?;
+		self.size += len;
+		Ok(())
+	}
+
+	fn reopen(&mut self) -> io::Result<()> {
+		let (file, size) = open_append(&self.path)?;
+		self.file = file;
+		self.size = size;
+		Ok(())
+	}
+
+	fn rotate(&mut self) -> io::Result<()> {
+		if self.rotate_keep > 0 {
+			for index in (1..self.rotate_keep).rev() {
+				let from = rotated_path(&self.path, index);
+				if from.exists() {
+					fs::rename(from, rotated_path(&self.path, index + 1))?;
+				}
+			}
+			fs::rename(&self.path, rotated_path(&self.path, 1))?;
+		} else {
+			fs::remove_file(&self.path)?;
+		}
+
+		self.reopen()
+	}
+}
+
+fn open_append(path: &Path) -> io::Result<(File, u64)> {
+	let file = OpenOptions::new().create(true).append(true).open(path)?;
+	let size = file.metadata()?.len();
+	Ok((file, size))
+}
+
+/// Returns the path of the log file rotated `index` times.
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+	let mut rotated = path.as_os_str().to_owned();
+	rotated.push(format!(".{}", index));
+	rotated.into()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn log_file_is_rotated_past_its_size() {
+		let temp_dir = tempfile::TempDir::new().unwrap();
+		let path = temp_dir.path().join("node.log");
+		let mut log_file = LogFile::open(path.clone(), Some(10), 2).unwrap();
+
+		for line in &["first", "second", "third", "fourth"] {
+			log_file.write_line(line).unwrap();
+		}
+
+		assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+		assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "third\n");
+		assert_eq!(fs::read_to_string(rotated_path(&path, 2)).unwrap(), "second\n");
+		assert!(!rotated_path(&path, 3).exists());
+	}
+
+	#[test]
+	fn log_file_is_reopened_when_requested() {
+		let temp_dir = tempfile::TempDir::new().unwrap();
+		let path = temp_dir.path().join("node.log");
+		let mut log_file = LogFile::open(path.clone(), None, 0).unwrap();
+
+		log_file.write_line("before").unwrap();
+		fs::rename(&path, temp_dir.path().join("moved.log")).unwrap();
+		reopen_log_files();
+		log_file.write_line("after").unwrap();
+
+		assert_eq!(fs::read_to_string(temp_dir.path().join("moved.log")).unwrap(), "before\n");
+		assert_eq!(fs::read_to_string(&path).unwrap(), "after\n");
+	}
+}
@@ -16,13 +16,19 @@
 
 use std::path::PathBuf;
 use std::iter;
-use std::net::Ipv4Addr;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::{num::NonZeroUsize, time::Duration};
 use structopt::StructOpt;
 use sc_network::{
-	config::{NonReservedPeerMode, TransportConfig}, multiaddr::Protocol,
+	config::{
+		DiscoveryConfig, NonReservedPeerMode, PeerFilterEntry, ProxyConfig, TransportConfig,
+		WebSocketTlsConfig,
+	},
+	multiaddr::Protocol,
 };
 use sc_service::Configuration;
 
+use crate::arg_enums::NatMode;
 use crate::error;
 use crate::params::node_key_params::NodeKeyParams;
 
@@ -30,6 +36,9 @@ use crate::params::node_key_params::NodeKeyParams;
 #[derive(Debug, StructOpt, Clone)]
 pub struct NetworkConfigurationParams {
 	/// Specify a list of bootnodes.
+	///
+	/// `/dnsaddr/<host>/p2p/<peer id>` addresses are resolved with the TXT records of
+	/// `_dnsaddr.<host>`, and resolved again when the bootnode can't be reached.
 	#[structopt(long = "bootnodes", value_name = "URL")]
 	pub bootnodes: Vec<String>,
 
@@ -44,7 +53,23 @@ pub struct NetworkConfigurationParams {
 	#[structopt(long = "reserved-only")]
 	pub reserved_only: bool,
 
+	/// Only accept the connections with these peers, given by peer id or by IP address range in
+	/// the CIDR notation, such as `192.0.2.0/24`.
+	///
+	/// Connections with every peer are accepted if none is given.
+	#[structopt(long = "allow-peers", value_name = "PEER_ID_OR_CIDR")]
+	pub allow_peers: Vec<PeerFilterEntry>,
+
+	/// Refuse the connections with these peers, given by peer id or by IP address range in the
+	/// CIDR notation, such as `192.0.2.0/24`.
+	#[structopt(long = "deny-peers", value_name = "PEER_ID_OR_CIDR")]
+	pub deny_peers: Vec<PeerFilterEntry>,
+
 	/// Specify a list of sentry node public addresses.
+	///
+	/// The addresses must include the peer id of the sentries, as in
+	/// `/ip4/198.51.100.19/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV`.
+	/// The node then only connects to its sentries, which relay its blocks and votes.
 	#[structopt(
 		long = "sentry-nodes",
 		value_name = "URL",
@@ -53,21 +78,74 @@ pub struct NetworkConfigurationParams {
 	pub sentry_nodes: Vec<String>,
 
 	/// Listen on this multiaddress.
+	///
+	/// Browsers connect to WebSocket addresses such as `/ip4/0.0.0.0/tcp/30334/ws`, or
+	/// `/ip4/0.0.0.0/tcp/443/wss` with `--network-tls-cert` and `--network-tls-key`.
 	#[structopt(long = "listen-addr", value_name = "LISTEN_ADDR")]
 	pub listen_addr: Vec<String>,
 
+	/// Serve the `/wss` listen addresses with the PEM encoded certificate chain in the given file.
+	#[structopt(
+		long = "network-tls-cert",
+		value_name = "PATH",
+		parse(from_os_str),
+		requires = "network-tls-key"
+	)]
+	pub network_tls_cert: Option<PathBuf>,
+
+	/// PEM encoded private key of the `/wss` certificate, in PKCS#8 or RSA format.
+	#[structopt(
+		long = "network-tls-key",
+		value_name = "PATH",
+		parse(from_os_str),
+		requires = "network-tls-cert"
+	)]
+	pub network_tls_key: Option<PathBuf>,
+
 	/// Specify p2p protocol TCP port.
 	///
 	/// Only used if --listen-addr is not specified.
 	#[structopt(long = "port", value_name = "PORT")]
 	pub port: Option<u16>,
 
+	/// Listen on IPv6 as well as IPv4, on the same port.
+	///
+	/// Only used if --listen-addr is not specified. The addresses of both families are announced
+	/// to the peers.
+	#[structopt(long = "listen-ipv6")]
+	pub listen_ipv6: bool,
+
+	/// How the node is reached through the NAT of its local network.
+	///
+	/// With `upnp`, the router is asked to forward the listened TCP ports to the node, and the
+	/// external addresses of the router are announced to the peers.
+	#[structopt(
+		long = "nat",
+		value_name = "MODE",
+		possible_values = &NatMode::variants(),
+		case_insensitive = true,
+		default_value = "None"
+	)]
+	pub nat: NatMode,
+
+	/// Dial the IPv6 addresses of the peers before their IPv4 addresses.
+	#[structopt(long = "prefer-ipv6")]
+	pub prefer_ipv6: bool,
+
 	/// Forbid connecting to private IPv4 addresses (as specified in
 	/// [RFC1918](https://tools.ietf.org/html/rfc1918)), unless the address was passed with
 	/// `--reserved-nodes` or `--bootnodes`.
 	#[structopt(long = "no-private-ipv4")]
 	pub no_private_ipv4: bool,
 
+	/// Number of distinct peers that must report observing the node at the same address before
+	/// it's advertised.
+	///
+	/// This lets a node with a dynamic public IP be reachable without `--public-addr`. 0 disables
+	/// learning addresses from the peers.
+	#[structopt(long = "observed-address-confirmations", value_name = "COUNT", default_value = "3")]
+	pub observed_address_confirmations: usize,
+
 	/// Specify the number of outgoing connections we're trying to maintain.
 	#[structopt(long = "out-peers", value_name = "COUNT", default_value = "25")]
 	pub out_peers: u32,
@@ -76,6 +154,15 @@ pub struct NetworkConfigurationParams {
 	#[structopt(long = "in-peers", value_name = "COUNT", default_value = "25")]
 	pub in_peers: u32,
 
+	/// Specify the maximum number of light clients we're connected to, out of the incoming
+	/// connections.
+	///
+	/// By default, light clients can take all the incoming slots. Limiting them keeps slots for
+	/// the full nodes. The light clients over the limit are disconnected, and their reputation is
+	/// lowered so that they don't reconnect right away.
+	#[structopt(long = "in-peers-light", value_name = "COUNT")]
+	pub in_peers_light: Option<u32>,
+
 	/// Disable mDNS discovery.
 	///
 	/// By default, the network will use mDNS to discover other nodes on the
@@ -83,6 +170,31 @@ pub struct NetworkConfigurationParams {
 	#[structopt(long = "no-mdns")]
 	pub no_mdns: bool,
 
+	/// Disable the discovery of the peers.
+	///
+	/// The node only connects to the bootnodes, the reserved nodes and the peers it is given,
+	/// which suits networks whose topology is static. Implies `--no-mdns`.
+	#[structopt(long = "no-discovery")]
+	pub no_discovery: bool,
+
+	/// Maximal interval between two random Kademlia queries discovering peers, in seconds.
+	///
+	/// The queries start every second, and slow down up to this interval.
+	#[structopt(long = "discovery-interval", value_name = "SECONDS", default_value = "60")]
+	pub discovery_interval: u64,
+
+	/// Number of peers the Kademlia queries are sent to in parallel.
+	#[structopt(long = "kademlia-parallelism", value_name = "COUNT")]
+	pub kademlia_parallelism: Option<NonZeroUsize>,
+
+	/// Time to live of the records stored in the Kademlia DHT, in seconds.
+	#[structopt(long = "kademlia-record-ttl", value_name = "SECONDS")]
+	pub kademlia_record_ttl: Option<u64>,
+
+	/// Time to live of the provider records stored in the Kademlia DHT, in seconds.
+	#[structopt(long = "kademlia-provider-ttl", value_name = "SECONDS")]
+	pub kademlia_provider_ttl: Option<u64>,
+
 	/// Maximum number of peers from which to ask for the same blocks in parallel.
 	///
 	/// This allows downloading announced blocks from multiple peers. Decrease to save
@@ -90,6 +202,26 @@ pub struct NetworkConfigurationParams {
 	#[structopt(long = "max-parallel-downloads", value_name = "COUNT", default_value = "5")]
 	pub max_parallel_downloads: u32,
 
+	/// Maximum rate at which data is sent to the peers, in KiB per second.
+	///
+	/// Unlimited by default. Limiting it slows down the propagation of blocks and transactions,
+	/// and the peers syncing from this node.
+	#[structopt(long = "max-upload-rate", value_name = "KIB_PER_SEC")]
+	pub max_upload_rate: Option<u64>,
+
+	/// Maximum rate at which data is received from the peers, in KiB per second.
+	///
+	/// Unlimited by default. Limiting it slows down the sync of this node.
+	#[structopt(long = "max-download-rate", value_name = "KIB_PER_SEC")]
+	pub max_download_rate: Option<u64>,
+
+	/// Join the private network of the pre-shared key in this file.
+	///
+	/// All the connections are encrypted with the key, so that only the nodes holding it can
+	/// connect to each other. The file uses the `swarm.key` format of libp2p.
+	#[structopt(long = "psk-file", value_name = "PATH", parse(from_os_str))]
+	pub psk_file: Option<PathBuf>,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub node_key_params: NodeKeyParams,
@@ -97,6 +229,13 @@ pub struct NetworkConfigurationParams {
 	/// Experimental feature flag.
 	#[structopt(long = "use-yamux-flow-control")]
 	pub use_yamux_flow_control: bool,
+
+	/// Dial the outbound TCP connections through this proxy, given as `socks5://HOST:PORT` or
+	/// `http://HOST:PORT`.
+	///
+	/// Listening is unaffected. The proxy resolves the host names of the peers itself.
+	#[structopt(long = "network-proxy", value_name = "URL")]
+	pub network_proxy: Option<ProxyConfig>,
 }
 
 impl NetworkConfigurationParams {
@@ -117,6 +256,9 @@ impl NetworkConfigurationParams {
 			config.network.non_reserved_mode = NonReservedPeerMode::Deny;
 		}
 
+		config.network.allowed_peers.extend(self.allow_peers.iter().cloned());
+		config.network.denied_peers.extend(self.deny_peers.iter().cloned());
+
 		config.network.sentry_nodes.extend(self.sentry_nodes.clone());
 
 		for addr in self.listen_addr.iter() {
@@ -124,33 +266,94 @@ impl NetworkConfigurationParams {
 			config.network.listen_addresses.push(addr);
 		}
 
+		// The libp2p version we're built on has no QUIC transport.
+		let listens_quic = config.network.listen_addresses.iter()
+			.any(|addr| addr.iter().any(|protocol| if let Protocol::Quic = protocol {
+				true
+			} else {
+				false
+			}));
+		if listens_quic {
+			return Err(error::Error::Input(
+				"QUIC isn't supported, listen on a /tcp address instead".into(),
+			));
+		}
+
 		if config.network.listen_addresses.is_empty() {
 			let port = match self.port {
 				Some(port) => port,
 				None => 30333,
 			};
 
-			config.network.listen_addresses = vec![
+			if self.listen_ipv6 {
+				config.network.listen_addresses.push(
+					iter::once(Protocol::Ip6(Ipv6Addr::UNSPECIFIED))
+						.chain(iter::once(Protocol::Tcp(port)))
+						.collect()
+				);
+			}
+			config.network.listen_addresses.push(
 				iter::once(Protocol::Ip4(Ipv4Addr::new(0, 0, 0, 0)))
 					.chain(iter::once(Protocol::Tcp(port)))
 					.collect()
-			];
+			);
 		}
 
 		config.network.client_version = client_id;
 		self.node_key_params.update_config(&mut config, Some(&config_path))?;
 
+		config.network.observed_address_confirmations = self.observed_address_confirmations;
+		config.network.nat = self.nat.into();
 		config.network.in_peers = self.in_peers;
 		config.network.out_peers = self.out_peers;
+		config.network.max_light_peers = self.in_peers_light;
+		config.network.discovery = DiscoveryConfig {
+			enabled: !self.no_discovery,
+			kademlia_parallelism: self.kademlia_parallelism,
+			record_ttl: self.kademlia_record_ttl.map(Duration::from_secs),
+			provider_record_ttl: self.kademlia_provider_ttl.map(Duration::from_secs),
+			max_interval: Duration::from_secs(self.discovery_interval),
+		};
+
+		let websocket_tls = match (&self.network_tls_cert, &self.network_tls_key) {
+			(Some(cert), Some(key)) =>
+				Some(WebSocketTlsConfig { cert: cert.clone(), key: key.clone() }),
+			_ => None,
+		};
+		let listens_wss = config.network.listen_addresses.iter()
+			.any(|addr| addr.iter().any(|protocol| if let Protocol::Wss(_) = protocol {
+				true
+			} else {
+				false
+			}));
+		if listens_wss && websocket_tls.is_none() {
+			return Err(error::Error::Input(
+				"Listening on a /wss address requires --network-tls-cert and --network-tls-key"
+					.into(),
+			));
+		}
 
 		config.network.transport = TransportConfig::Normal {
 			enable_mdns: !is_dev && !self.no_mdns,
 			allow_private_ipv4: !self.no_private_ipv4,
+			prefer_ipv6: self.prefer_ipv6,
 			wasm_external_transport: None,
 			use_yamux_flow_control: self.use_yamux_flow_control,
+			proxy: self.network_proxy.clone(),
+			websocket_tls,
 		};
 
 		config.network.max_parallel_downloads = self.max_parallel_downloads;
+		config.network.max_upload_rate = self.max_upload_rate.map(|rate| rate.saturating_mul(1024));
+		config.network.max_download_rate = self.max_download_rate
+			.map(|rate| rate.saturating_mul(1024));
+
+		if let Some(path) = &self.psk_file {
+			let key = std::fs::read_to_string(path)?;
+			config.network.pre_shared_key = Some(key.parse().map_err(|e| error::Error::Input(
+				format!("Invalid pre-shared key file {}: {}", path.display(), e),
+			))?);
+		}
 
 		Ok(())
 	}
@@ -23,6 +23,8 @@ use sc_service::{
 
 use crate::VersionInfo;
 use crate::error;
+use crate::arg_enums::LogFormat;
+use crate::log_file::LogFile;
 
 /// default sub directory to store database
 const DEFAULT_DB_CONFIG_PATH : &'static str = "db";
@@ -48,6 +50,33 @@ pub struct SharedParams {
 	/// By default, all targets log `info`. The global log level can be set with -l<level>.
 	#[structopt(short = "l", long = "log", value_name = "LOG_PATTERN")]
 	pub log: Option<String>,
+
+	/// Format of the log output.
+	///
+	/// `Json` writes one JSON object per line, with the `timestamp`, `level`, `target`, `thread`
+	/// and `message` fields.
+	#[structopt(
+		long = "log-format",
+		value_name = "FORMAT",
+		possible_values = &LogFormat::variants(),
+		case_insensitive = true,
+		default_value = "Text"
+	)]
+	pub log_format: LogFormat,
+
+	/// Write the logs to the given file instead of stderr.
+	///
+	/// The file is re-opened when the node receives `SIGHUP`.
+	#[structopt(long = "log-file", value_name = "PATH", parse(from_os_str))]
+	pub log_file: Option<PathBuf>,
+
+	/// Rotate the log file once it grows past the given size, in MiB.
+	#[structopt(long = "log-rotate-size", value_name = "MIB", requires = "log-file")]
+	pub log_rotate_size: Option<u64>,
+
+	/// Number of rotated log files to keep.
+	#[structopt(long = "log-rotate-keep", value_name = "COUNT", default_value = "5")]
+	pub log_rotate_keep: usize,
 }
 
 impl SharedParams {
@@ -94,7 +123,21 @@ impl SharedParams {
 	/// 2. Raise the FD limit
 	/// 3. Initialize the logger
 	pub fn init(&self, version: &VersionInfo) -> error::Result<()> {
-		crate::init(self.log.as_ref().map(|v| v.as_ref()).unwrap_or(""), version)
+		let log_file = match &self.log_file {
+			Some(path) => Some(LogFile::open(
+				path.clone(),
+				self.log_rotate_size.map(|size| size * 1024 * 1024),
+				self.log_rotate_keep,
+			)?),
+			None => None,
+		};
+
+		crate::init(
+			self.log.as_ref().map(|v| v.as_ref()).unwrap_or(""),
+			self.log_format,
+			log_file,
+			version,
+		)
 	}
 }
 
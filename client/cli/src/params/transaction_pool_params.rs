@@ -27,6 +27,10 @@ pub struct TransactionPoolParams {
 	/// Maximum number of kilobytes of all transactions stored in the pool.
 	#[structopt(long = "pool-kbytes", value_name = "COUNT", default_value = "20480")]
 	pub pool_kbytes: usize,
+	/// Maximum encoded size in bytes of an extrinsic submitted over RPC or received from the
+	/// network. Larger ones are rejected before being validated.
+	#[structopt(long = "max-extrinsic-size", value_name = "BYTES")]
+	pub max_extrinsic_size: Option<usize>,
 }
 
 impl TransactionPoolParams {
@@ -44,6 +48,8 @@ impl TransactionPoolParams {
 		config.transaction_pool.future.count = self.pool_limit / factor;
 		config.transaction_pool.future.total_bytes = self.pool_kbytes * 1024 / factor;
 
+		config.max_extrinsic_size = self.max_extrinsic_size;
+
 		Ok(())
 	}
 }
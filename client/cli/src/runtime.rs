@@ -14,45 +14,58 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::sync::Arc;
+use std::{sync::Arc, thread, time::Duration};
 
 use futures::{Future, future, future::FutureExt};
 use futures::select;
 use futures::pin_mut;
-use sc_service::{AbstractService, Configuration};
+use log::info;
+use sc_service::{AbstractService, Configuration, ReloadHandle};
 use crate::error;
 
 #[cfg(target_family = "unix")]
-async fn main<F, E>(func: F) -> Result<(), Box<dyn std::error::Error>>
+async fn main<F, E, H>(func: F, mut on_hangup: H) -> Result<(), Box<dyn std::error::Error>>
 where
 	F: Future<Output = Result<(), E>> + future::FusedFuture,
 	E: 'static + std::error::Error,
+	H: FnMut(),
 {
 	use tokio::signal::unix::{signal, SignalKind};
 
 	let mut stream_int = signal(SignalKind::interrupt())?;
 	let mut stream_term = signal(SignalKind::terminate())?;
+	let mut stream_hup = signal(SignalKind::hangup())?;
 
 	let t1 = stream_int.recv().fuse();
 	let t2 = stream_term.recv().fuse();
 	let t3 = func;
+	let t4 = async move {
+		// SIGHUP doesn't stop the node, it asks for the log files to be re-opened and for the
+		// configuration to be reloaded.
+		while stream_hup.recv().await.is_some() {
+			crate::log_file::reopen_log_files();
+			on_hangup();
+		}
+	}.fuse();
 
-	pin_mut!(t1, t2, t3);
+	pin_mut!(t1, t2, t3, t4);
 
 	select! {
 		_ = t1 => {},
 		_ = t2 => {},
 		res = t3 => res?,
+		_ = t4 => {},
 	}
 
 	Ok(())
 }
 
 #[cfg(not(unix))]
-async fn main<F, E>(func: F) -> Result<(), Box<dyn std::error::Error>>
+async fn main<F, E, H>(func: F, _on_hangup: H) -> Result<(), Box<dyn std::error::Error>>
 where
 	F: Future<Output = Result<(), E>> + future::FusedFuture,
 	E: 'static + std::error::Error,
+	H: FnMut(),
 {
 	use tokio::signal::ctrl_c;
 
@@ -99,43 +112,94 @@ where
 	let f = f.fuse();
 	pin_mut!(f);
 
-	runtime.block_on(main(f)).map_err(|e| e.to_string())?;
+	runtime.block_on(main(f, || {})).map_err(|e| e.to_string())?;
 
 	Ok(())
 }
 
 /// A helper function that runs an `AbstractService` with tokio and stops if the process receives
 /// the signal SIGTERM or SIGINT
+///
+/// Once stopped, the service is shut down gracefully (see `AbstractService::shutdown`). The
+/// shutdown is aborted with an error if it takes longer than the configured `shutdown_timeout`.
 pub fn run_service_until_exit<T, F>(
+	config: Configuration,
+	service_builder: F,
+) -> error::Result<()>
+where
+	F: FnOnce(Configuration) -> Result<T, sc_service::error::Error>,
+	T: AbstractService + Unpin,
+{
+	run_service_until_exit_with_reload(config, service_builder, |_| {})
+}
+
+/// Same as `run_service_until_exit`, but calls `on_reload` with a handle to the service every
+/// time the process receives the signal SIGHUP.
+pub fn run_service_until_exit_with_reload<T, F, R>(
 	mut config: Configuration,
 	service_builder: F,
+	mut on_reload: R,
 ) -> error::Result<()>
 where
 	F: FnOnce(Configuration) -> Result<T, sc_service::error::Error>,
 	T: AbstractService + Unpin,
+	R: FnMut(&ReloadHandle),
 {
 	let mut runtime = build_runtime()?;
+	let shutdown_timeout = config.shutdown_timeout;
 
 	config.task_executor = {
 		let runtime_handle = runtime.handle().clone();
 		Some(Arc::new(move |fut| { runtime_handle.spawn(fut); }))
 	};
 
-	let service = service_builder(config)?;
+	let mut service = service_builder(config)?;
 
 	let informant_future = sc_informant::build(&service, sc_informant::OutputFormat::Coloured);
 	let _informant_handle = runtime.spawn(informant_future);
 
-	// we eagerly drop the service so that the internal exit future is fired,
-	// but we need to keep holding a reference to the global telemetry guard
-	// and drop the runtime first.
+	// we need to keep holding a reference to the global telemetry guard
+	// until the service and the runtime are gone.
 	let _telemetry = service.telemetry();
 
-	let f = service.fuse();
-	pin_mut!(f);
+	let reload_handle = service.reload_handle();
+	let result = {
+		let f = (&mut service).fuse();
+		pin_mut!(f);
+
+		runtime.block_on(main(f, || on_reload(&reload_handle))).map_err(|e| e.to_string())
+	};
 
-	runtime.block_on(main(f)).map_err(|e| e.to_string())?;
-	drop(runtime);
+	shutdown(service, runtime, shutdown_timeout)?;
 
-	Ok(())
+	result.map_err(Into::into)
+}
+
+/// Shut the service down, then drop the runtime so that the remaining tasks release the resources
+/// they hold (e.g. the database).
+///
+/// Both happen on a separate thread so that we can give up after `timeout`.
+fn shutdown<T: AbstractService>(
+	service: T,
+	runtime: tokio::runtime::Runtime,
+	timeout: Duration,
+) -> error::Result<()> {
+	info!("Shutting down, waiting at most {} seconds", timeout.as_secs());
+
+	let (tx, rx) = std::sync::mpsc::channel();
+	thread::Builder::new()
+		.name("shutdown".into())
+		.spawn(move || {
+			service.shutdown();
+			drop(runtime);
+			let _ = tx.send(());
+		})?;
+
+	match rx.recv_timeout(timeout) {
+		Ok(()) => Ok(()),
+		Err(_) => Err(error::Error::Other(format!(
+			"Node didn't shut down within {} seconds",
+			timeout.as_secs(),
+		))),
+	}
 }
@@ -16,7 +16,7 @@
 
 //! RPC api for babe.
 
-use sc_consensus_babe::{Epoch, authorship, Config};
+use sc_consensus_babe::{Epoch, authorship, Config, MissedSlots};
 use futures::{FutureExt as _, TryFutureExt as _};
 use jsonrpc_core::{
 	Error as RpcError,
@@ -27,13 +27,14 @@ use sc_consensus_epochs::{descendent_query, Epoch as EpochT, SharedEpochChanges}
 use sp_consensus_babe::{
 	AuthorityId,
 	BabeApi as BabeRuntimeApi,
-	digests::PreDigest,
+	BabeAuthorityWeight,
+	digests::{CompatibleDigestItem, PreDigest},
 };
 use serde::{Deserialize, Serialize};
 use sc_keystore::KeyStorePtr;
 use sp_api::{ProvideRuntimeApi, BlockId};
-use sp_core::crypto::Pair;
-use sp_runtime::traits::{Block as BlockT, Header as _};
+use sp_core::{Bytes, crypto::Pair};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, Zero};
 use sp_consensus::{SelectChain, Error as ConsensusError};
 use sp_blockchain::{HeaderBackend, HeaderMetadata, Error as BlockChainError};
 use std::{collections::HashMap, fmt, sync::Arc};
@@ -42,11 +43,21 @@ type FutureResult<T> = Box<dyn rpc_future::Future<Item = T, Error = RpcError> +
 
 /// Provides rpc methods for interacting with Babe.
 #[rpc]
-pub trait BabeApi {
-	/// Returns data about which slots (primary or secondary) can be claimed in the current epoch
-	/// with the keys in the keystore.
+pub trait BabeApi<Hash> {
+	/// Returns data about which slots (primary or secondary) can be claimed in the current and
+	/// next epochs with the keys in the keystore.
 	#[rpc(name = "babe_epochAuthorship")]
 	fn epoch_authorship(&self) -> FutureResult<HashMap<AuthorityId, EpochAuthorship>>;
+
+	/// Returns the last slots one of the keys in the keystore was expected to author but that
+	/// weren't authored, oldest first, with the reason why.
+	#[rpc(name = "babe_missedSlots")]
+	fn missed_slots(&self) -> jsonrpc_core::Result<Vec<MissedSlot>>;
+
+	/// Returns the epoch of the given block, or of the best block if none is given, and the next
+	/// epoch if it was already announced on the chain of the block.
+	#[rpc(name = "babe_epoch")]
+	fn epoch(&self, at: Option<Hash>) -> jsonrpc_core::Result<EpochInfo>;
 }
 
 /// Implements the BabeRPC trait for interacting with Babe.
@@ -61,6 +72,8 @@ pub struct BabeRPCHandler<B: BlockT, C, SC> {
 	babe_config: Config,
 	/// The SelectChain strategy
 	select_chain: SC,
+	/// The scheduled slots that weren't authored
+	missed_slots: MissedSlots,
 }
 
 impl<B: BlockT, C, SC> BabeRPCHandler<B, C, SC> {
@@ -71,6 +84,7 @@ impl<B: BlockT, C, SC> BabeRPCHandler<B, C, SC> {
 		keystore: KeyStorePtr,
 		babe_config: Config,
 		select_chain: SC,
+		missed_slots: MissedSlots,
 	) -> Self {
 		Self {
 			client,
@@ -78,11 +92,12 @@ impl<B: BlockT, C, SC> BabeRPCHandler<B, C, SC> {
 			keystore,
 			babe_config,
 			select_chain,
+			missed_slots,
 		}
 	}
 }
 
-impl<B, C, SC> BabeApi for BabeRPCHandler<B, C, SC>
+impl<B, C, SC> BabeApi<B::Hash> for BabeRPCHandler<B, C, SC>
 	where
 		B: BlockT,
 		C: ProvideRuntimeApi<B> + HeaderBackend<B> + HeaderMetadata<B, Error=BlockChainError> + 'static,
@@ -112,21 +127,24 @@ impl<B, C, SC> BabeApi for BabeRPCHandler<B, C, SC>
 					Error::StringError(format!("{:?}", err))
 				})?;
 			let epoch = epoch_data(&shared_epoch, &client, &babe_config, epoch_start, &select_chain)?;
-			let (epoch_start, epoch_end) = (epoch.start_slot(), epoch.end_slot());
+			// The next epoch was announced at the start of the current one.
+			let next_epoch =
+				epoch_data(&shared_epoch, &client, &babe_config, epoch.end_slot(), &select_chain)?;
 
 			let mut claims: HashMap<AuthorityId, EpochAuthorship> = HashMap::new();
 
-			for slot_number in epoch_start..epoch_end {
-				let epoch = epoch_data(&shared_epoch, &client, &babe_config, slot_number, &select_chain)?;
-				if let Some((claim, key)) = authorship::claim_slot(slot_number, &epoch, &babe_config, &keystore) {
-					match claim {
-						PreDigest::Primary { .. } => {
-							claims.entry(key.public()).or_default().primary.push(slot_number);
-						}
-						PreDigest::Secondary { .. } => {
-							claims.entry(key.public()).or_default().secondary.push(slot_number);
-						}
-					};
+			for (slot_number, claim, key) in claimable_slots(&epoch, &keystore) {
+				let authorship = claims.entry(key).or_default();
+				match claim {
+					PreDigest::Primary { .. } => authorship.primary.push(slot_number),
+					PreDigest::Secondary { .. } => authorship.secondary.push(slot_number),
+				}
+			}
+			for (slot_number, claim, key) in claimable_slots(&next_epoch, &keystore) {
+				let authorship = claims.entry(key).or_default();
+				match claim {
+					PreDigest::Primary { .. } => authorship.next_primary.push(slot_number),
+					PreDigest::Secondary { .. } => authorship.next_secondary.push(slot_number),
 				}
 			}
 
@@ -135,15 +153,116 @@ impl<B, C, SC> BabeApi for BabeRPCHandler<B, C, SC>
 
 		Box::new(future.compat())
 	}
+
+	fn missed_slots(&self) -> jsonrpc_core::Result<Vec<MissedSlot>> {
+		Ok(self.missed_slots.recent().into_iter()
+			.map(|missed| MissedSlot {
+				slot_number: missed.slot_number,
+				reason: missed.reason.kind().into(),
+				description: missed.reason.to_string(),
+			})
+			.collect())
+	}
+
+	fn epoch(&self, at: Option<B::Hash>) -> jsonrpc_core::Result<EpochInfo> {
+		let header = match at {
+			Some(hash) => self.client.header(BlockId::Hash(hash))
+				.map_err(|e| Error::StringError(format!("{:?}", e)))?
+				.ok_or_else(|| Error::StringError(format!("Unknown block {:?}", hash)))?,
+			None => self.select_chain.best_chain().map_err(Error::Consensus)?,
+		};
+		let hash = header.hash();
+
+		// the genesis block has no slot, its epoch is the one starting at the first block.
+		let slot_number = if header.number().is_zero() {
+			self.client.runtime_api()
+				.current_epoch_start(&BlockId::Hash(hash))
+				.map_err(|err| Error::StringError(format!("{:?}", err)))?
+		} else {
+			slot_of::<B>(&header).ok_or_else(|| {
+				Error::StringError(format!("No BABE pre-runtime digest in {:?}", hash))
+			})?
+		};
+
+		let (current, next) = self.shared_epoch_changes.lock().epochs_for_child_of(
+			descendent_query(&*self.client),
+			&hash,
+			*header.number(),
+			slot_number,
+			|slot| self.babe_config.genesis_epoch(slot),
+		)
+			.map_err(|e| Error::Consensus(ConsensusError::ChainLookup(format!("{:?}", e))))?
+			.ok_or(Error::Consensus(ConsensusError::InvalidAuthoritiesSet))?;
+
+		Ok(EpochInfo {
+			current: current.clone_for_slot(slot_number).into(),
+			next: next.map(Into::into),
+		})
+	}
+}
+
+/// The epoch of a block, and the next one if it was already announced.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochInfo {
+	/// the epoch the block is in
+	current: EpochDetails,
+	/// the epoch following it, if it was already announced
+	next: Option<EpochDetails>,
+}
+
+/// The data of an epoch, from which its authorship schedule is computed.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochDetails {
+	/// the index of the epoch
+	epoch_index: u64,
+	/// the first slot of the epoch
+	start_slot: u64,
+	/// the slot following the last one of the epoch
+	end_slot: u64,
+	/// the authorities of the epoch, with their weights
+	authorities: Vec<(AuthorityId, BabeAuthorityWeight)>,
+	/// the randomness of the epoch
+	randomness: Bytes,
+}
+
+impl From<Epoch> for EpochDetails {
+	fn from(epoch: Epoch) -> Self {
+		EpochDetails {
+			epoch_index: epoch.epoch_index,
+			start_slot: epoch.start_slot(),
+			end_slot: epoch.end_slot(),
+			authorities: epoch.authorities,
+			randomness: epoch.randomness.to_vec().into(),
+		}
+	}
 }
 
 /// Holds information about the `slot_number`'s that can be claimed by a given key.
 #[derive(Default, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EpochAuthorship {
 	/// the array of primary slots that can be claimed
 	primary: Vec<u64>,
 	/// the array of secondary slots that can be claimed
 	secondary: Vec<u64>,
+	/// the array of primary slots that can be claimed in the next epoch
+	next_primary: Vec<u64>,
+	/// the array of secondary slots that can be claimed in the next epoch
+	next_secondary: Vec<u64>,
+}
+
+/// A slot that was expected to be authored by one of the keys in the keystore, but wasn't.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissedSlot {
+	/// the number of the slot
+	slot_number: u64,
+	/// why the slot wasn't authored, e.g. `proposalTimeout`
+	reason: String,
+	/// human-readable description of the reason
+	description: String,
 }
 
 /// Errors encountered by the RPC
@@ -165,7 +284,26 @@ impl From<Error> for jsonrpc_core::Error {
 	}
 }
 
-/// fetches the epoch data for a given slot_number.
+/// the slots of `epoch` that can be claimed with the keys in the keystore, with the claim and the
+/// key claiming them.
+fn claimable_slots(epoch: &Epoch, keystore: &KeyStorePtr) -> Vec<(u64, PreDigest, AuthorityId)> {
+	(epoch.start_slot()..epoch.end_slot())
+		.filter_map(|slot_number| authorship::claim_slot(slot_number, epoch, keystore)
+			.map(|(claim, key)| (slot_number, claim, key.public()))
+		)
+		.collect()
+}
+
+/// the slot of a block, from its pre-runtime digest.
+fn slot_of<B: BlockT>(header: &B::Header) -> Option<u64> {
+	header.digest().logs().iter()
+		.filter_map(|log| log.as_babe_pre_digest())
+		.map(|pre_digest| pre_digest.slot_number())
+		.next()
+}
+
+/// fetches the epoch data for a given slot_number, accounting for the epochs skipped if the
+/// chain was stalled.
 fn epoch_data<B, C, SC>(
 	epoch_changes: &SharedEpochChanges<B, Epoch>,
 	client: &Arc<C>,
@@ -187,6 +325,7 @@ fn epoch_data<B, C, SC>(
 		|slot| babe_config.genesis_epoch(slot),
 	)
 		.map_err(|e| Error::Consensus(ConsensusError::ChainLookup(format!("{:?}", e))))?
+		.map(|epoch| epoch.clone_for_slot(slot_number))
 		.ok_or(Error::Consensus(ConsensusError::InvalidAuthoritiesSet))
 }
 
@@ -203,7 +342,7 @@ mod tests {
 	use sc_keystore::Store;
 
 	use std::sync::Arc;
-	use sc_consensus_babe::{Config, block_import, AuthorityPair};
+	use sc_consensus_babe::{Config, block_import, AuthorityPair, MissedSlotReason};
 	use jsonrpc_core::IoHandler;
 
 	/// creates keystore backed by a temp file
@@ -231,12 +370,50 @@ mod tests {
 		let epoch_changes = link.epoch_changes().clone();
 		let select_chain = longest_chain;
 		let keystore = create_temp_keystore::<AuthorityPair>(Ed25519Keyring::Alice).0;
-		let handler = BabeRPCHandler::new(client.clone(), epoch_changes, keystore, config, select_chain);
+		let missed_slots = link.missed_slots().clone();
+		let handler = BabeRPCHandler::new(
+			client.clone(),
+			epoch_changes,
+			keystore,
+			config,
+			select_chain,
+			missed_slots.clone(),
+		);
 		let mut io = IoHandler::new();
 
 		io.extend_with(BabeApi::to_delegate(handler));
 		let request = r#"{"jsonrpc":"2.0","method":"babe_epochAuthorship","params": [],"id":1}"#;
-		let response = r#"{"jsonrpc":"2.0","result":{"5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY":{"primary":[0],"secondary":[1,2,4]}},"id":1}"#;
+		let response: serde_json::Value = serde_json::from_str(
+			&io.handle_request_sync(request).expect("babe_epochAuthorship responds"),
+		).unwrap();
+		let authorship = &response["result"]["5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"];
+
+		assert_eq!(authorship["primary"], serde_json::json!([0]));
+		assert_eq!(authorship["secondary"], serde_json::json!([1, 2, 4]));
+		// The genesis epoch lasts 6 slots, and the next epoch follows it.
+		for slots in &[&authorship["nextPrimary"], &authorship["nextSecondary"]] {
+			assert!(slots.as_array().unwrap().iter()
+				.all(|slot| (6..12).contains(&slot.as_u64().unwrap())));
+		}
+
+		let request = r#"{"jsonrpc":"2.0","method":"babe_epoch","params": [],"id":1}"#;
+		let response: serde_json::Value = serde_json::from_str(
+			&io.handle_request_sync(request).expect("babe_epoch responds"),
+		).unwrap();
+		let epoch = &response["result"]["current"];
+		assert_eq!(epoch["epochIndex"], 0);
+		assert_eq!(epoch["startSlot"], 0);
+		assert_eq!(epoch["endSlot"], 6);
+		assert_eq!(
+			epoch["authorities"][0][0],
+			"5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+		);
+		// no block announced the next epoch yet.
+		assert_eq!(response["result"]["next"], serde_json::Value::Null);
+
+		missed_slots.record(3, MissedSlotReason::ProposalTimeout);
+		let request = r#"{"jsonrpc":"2.0","method":"babe_missedSlots","params": [],"id":1}"#;
+		let response = r#"{"jsonrpc":"2.0","result":[{"description":"building the block took too long","reason":"proposalTimeout","slotNumber":3}],"id":1}"#;
 
 		assert_eq!(Some(response.into()), io.handle_request_sync(request));
 	}
@@ -19,7 +19,7 @@
 use merlin::Transcript;
 use sp_consensus_babe::{
 	AuthorityId, BabeAuthorityWeight, BABE_ENGINE_ID, BABE_VRF_PREFIX,
-	SlotNumber, AuthorityPair, BabeConfiguration
+	SlotNumber, AuthorityPair,
 };
 use sp_consensus_babe::digests::PreDigest;
 use sp_core::{U256, blake2_256};
@@ -146,12 +146,11 @@ fn claim_secondary_slot(
 pub fn claim_slot(
 	slot_number: SlotNumber,
 	epoch: &Epoch,
-	config: &BabeConfiguration,
 	keystore: &KeyStorePtr,
 ) -> Option<(PreDigest, AuthorityPair)> {
-	claim_primary_slot(slot_number, epoch, config.c, keystore)
+	claim_primary_slot(slot_number, epoch, epoch.config.c, keystore)
 		.or_else(|| {
-			if config.secondary_slots {
+			if epoch.config.secondary_slots {
 				claim_secondary_slot(
 					slot_number,
 					&epoch.authorities,
@@ -164,6 +163,27 @@ pub fn claim_slot(
 		})
 }
 
+/// Whether the given slot is the secondary slot of an authority whose public key is in the
+/// keystore, even if its secret key can't be loaded. The primary slots can only be told by
+/// claiming them with `claim_slot`.
+pub fn is_secondary_slot_scheduled(
+	slot_number: SlotNumber,
+	epoch: &Epoch,
+	keystore: &KeyStorePtr,
+) -> bool {
+	if !epoch.config.secondary_slots {
+		return false;
+	}
+
+	match secondary_slot_author(slot_number, &epoch.authorities, epoch.randomness) {
+		Some(expected_author) => keystore.read()
+			.public_keys::<AuthorityId>()
+			.map(|keys| keys.contains(expected_author))
+			.unwrap_or(false),
+		None => false,
+	}
+}
+
 fn get_keypair(q: &AuthorityPair) -> &schnorrkel::Keypair {
 	use sp_core::crypto::IsWrappedBy;
 	sp_core::sr25519::Pair::from_ref(q).as_ref()
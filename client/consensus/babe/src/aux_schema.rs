@@ -24,13 +24,65 @@ use codec::{Decode, Encode};
 use sc_client_api::backend::AuxStore;
 use sp_blockchain::{Result as ClientResult, Error as ClientError};
 use sp_runtime::traits::Block as BlockT;
-use sp_consensus_babe::BabeBlockWeight;
-use sc_consensus_epochs::{EpochChangesFor, SharedEpochChanges};
-use crate::Epoch;
+use sp_consensus_babe::{
+	AuthorityId, BabeAuthorityWeight, BabeBlockWeight, BabeConfiguration, SlotNumber,
+	VRF_OUTPUT_LENGTH,
+};
+use sc_consensus_epochs::{EpochChangesFor, SharedEpochChanges, Epoch as EpochT};
+use crate::{Epoch, NextEpochDescriptor};
 
 const BABE_EPOCH_CHANGES_VERSION: &[u8] = b"babe_epoch_changes_version";
 const BABE_EPOCH_CHANGES_KEY: &[u8] = b"babe_epoch_changes";
-const BABE_EPOCH_CHANGES_CURRENT_VERSION: u32 = 1;
+const BABE_EPOCH_CHANGES_CURRENT_VERSION: u32 = 2;
+
+/// BABE epoch information as persisted before epoch configurations were
+/// tracked, i.e. in version 1 of the aux schema.
+#[derive(Decode, Encode, PartialEq, Eq, Clone, Debug)]
+struct EpochV1 {
+	epoch_index: u64,
+	start_slot: SlotNumber,
+	duration: SlotNumber,
+	authorities: Vec<(AuthorityId, BabeAuthorityWeight)>,
+	randomness: [u8; VRF_OUTPUT_LENGTH],
+}
+
+impl EpochT for EpochV1 {
+	type NextEpochDescriptor = NextEpochDescriptor;
+	type SlotNumber = SlotNumber;
+
+	fn increment(&self, descriptor: NextEpochDescriptor) -> EpochV1 {
+		EpochV1 {
+			epoch_index: self.epoch_index + 1,
+			start_slot: self.start_slot + self.duration,
+			duration: self.duration,
+			authorities: descriptor.authorities,
+			randomness: descriptor.randomness,
+		}
+	}
+
+	fn start_slot(&self) -> SlotNumber {
+		self.start_slot
+	}
+
+	fn end_slot(&self) -> SlotNumber {
+		self.start_slot + self.duration
+	}
+}
+
+impl EpochV1 {
+	/// Migrate the epoch to the current format. Epochs persisted in version 1
+	/// were always using the genesis configuration.
+	fn migrate(self, config: &BabeConfiguration) -> Epoch {
+		Epoch {
+			epoch_index: self.epoch_index,
+			start_slot: self.start_slot,
+			duration: self.duration,
+			authorities: self.authorities,
+			randomness: self.randomness,
+			config: config.genesis_epoch_configuration(),
+		}
+	}
+}
 
 fn block_weight_key<H: Encode>(block_hash: H) -> Vec<u8> {
 	(b"block_weight", block_hash).encode()
@@ -53,11 +105,19 @@ fn load_decode<B, T>(backend: &B, key: &[u8]) -> ClientResult<Option<T>>
 /// Load or initialize persistent epoch change data from backend.
 pub(crate) fn load_epoch_changes<Block: BlockT, B: AuxStore>(
 	backend: &B,
+	config: &BabeConfiguration,
 ) -> ClientResult<SharedEpochChanges<Block, Epoch>> {
 	let version = load_decode::<_, u32>(backend, BABE_EPOCH_CHANGES_VERSION)?;
 
 	let maybe_epoch_changes = match version {
-		None | Some(BABE_EPOCH_CHANGES_CURRENT_VERSION) => load_decode::<_, EpochChangesFor<Block, Epoch>>(
+		None | Some(1) => load_decode::<_, EpochChangesFor<Block, EpochV1>>(
+			backend,
+			BABE_EPOCH_CHANGES_KEY,
+		)?.map(|epoch_changes| {
+			info!(target: "babe", "Migrating BABE epoch changes to include epoch configurations.");
+			epoch_changes.map(|_, _, epoch| epoch.migrate(config))
+		}),
+		Some(BABE_EPOCH_CHANGES_CURRENT_VERSION) => load_decode::<_, EpochChangesFor<Block, Epoch>>(
 			backend,
 			BABE_EPOCH_CHANGES_KEY,
 		)?,
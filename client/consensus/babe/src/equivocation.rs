@@ -0,0 +1,208 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reporting of the equivocations detected by the verifier.
+//!
+//! The verifier remembers the headers seen for each slot and author, and builds an
+//! `EquivocationProof` when an author claims a slot twice. The proofs are handed to the
+//! subscribers of `EquivocationReports`, and logged.
+//!
+//! `submit_equivocation_reports` subscribes to the reports and submits them to the transaction
+//! pool, as the unsigned extrinsics built by the `report_equivocation_extrinsic` runtime API
+//! at the best block. The runtime then checks the proof and reports the offender.
+
+use futures::{channel::mpsc, prelude::*};
+use log::{debug, warn};
+use parking_lot::Mutex;
+use sc_telemetry::{telemetry, CONSENSUS_WARN};
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_consensus_babe::{BabeApi, EquivocationProof};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header}};
+use sp_transaction_pool::TransactionPool;
+use std::sync::Arc;
+
+/// The subscribers to the equivocations detected by the verifier.
+pub struct EquivocationReports<Block: BlockT>(
+	Arc<Mutex<Vec<mpsc::UnboundedSender<EquivocationProof<Block::Header>>>>>,
+);
+
+impl<Block: BlockT> Clone for EquivocationReports<Block> {
+	fn clone(&self) -> Self {
+		EquivocationReports(self.0.clone())
+	}
+}
+
+impl<Block: BlockT> Default for EquivocationReports<Block> {
+	fn default() -> Self {
+		EquivocationReports(Arc::new(Mutex::new(Vec::new())))
+	}
+}
+
+impl<Block: BlockT> EquivocationReports<Block> {
+	/// Returns a stream of the equivocations detected from now on.
+	pub fn subscribe(&self) -> mpsc::UnboundedReceiver<EquivocationProof<Block::Header>> {
+		let (tx, rx) = mpsc::unbounded();
+		self.0.lock().push(tx);
+		rx
+	}
+
+	/// Hands `proof` to the subscribers.
+	pub(crate) fn report(&self, proof: EquivocationProof<Block::Header>) {
+		warn!(
+			target: "babe",
+			"Slot author {:?} is equivocating at slot {} with headers {:?} and {:?}",
+			proof.offender,
+			proof.slot_number,
+			proof.first_header.hash(),
+			proof.second_header.hash(),
+		);
+		telemetry!(CONSENSUS_WARN; "babe.equivocation";
+			"slot" => proof.slot_number,
+			"offender" => ?proof.offender,
+		);
+
+		self.0.lock().retain(|tx| tx.unbounded_send(proof.clone()).is_ok());
+	}
+}
+
+/// Submits the equivocations of `reports` to `pool`, until `reports` ends.
+///
+/// The reports are ignored when the runtime at the best block doesn't support them.
+pub fn submit_equivocation_reports<Block, Client, Pool>(
+	client: Arc<Client>,
+	pool: Arc<Pool>,
+	reports: mpsc::UnboundedReceiver<EquivocationProof<Block::Header>>,
+) -> impl Future<Output = ()> where
+	Block: BlockT,
+	Client: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	Client::Api: BabeApi<Block, Error = sp_blockchain::Error>,
+	Pool: TransactionPool<Block = Block>,
+{
+	reports.for_each(move |proof| {
+		let extrinsic = report_equivocation_extrinsic(&*client, proof);
+		let pool = pool.clone();
+		async move {
+			let (at, extrinsic) = match extrinsic {
+				Some(extrinsic) => extrinsic,
+				None => return,
+			};
+			match pool.submit_one(&at, extrinsic).await {
+				Ok(hash) => debug!(target: "babe", "Submitted equivocation report {:?}", hash),
+				Err(e) => warn!(target: "babe", "Failed to submit equivocation report: {:?}", e),
+			}
+		}
+	})
+}
+
+/// Builds the extrinsic reporting `proof` with the runtime at the best block.
+fn report_equivocation_extrinsic<Block, Client>(
+	client: &Client,
+	proof: EquivocationProof<Block::Header>,
+) -> Option<(BlockId<Block>, Block::Extrinsic)> where
+	Block: BlockT,
+	Client: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	Client::Api: BabeApi<Block, Error = sp_blockchain::Error>,
+{
+	let at = BlockId::Hash(client.info().best_hash);
+	let runtime_api = client.runtime_api();
+	match runtime_api.has_api_with::<dyn BabeApi<Block, Error = ()>, _>(&at, |v| v >= 2) {
+		Ok(true) => {},
+		Ok(false) => {
+			debug!(target: "babe", "Runtime at {:?} doesn't support equivocation reports", at);
+			return None;
+		},
+		Err(e) => {
+			warn!(target: "babe", "Failed to get the BABE runtime API version: {:?}", e);
+			return None;
+		},
+	}
+
+	match runtime_api.report_equivocation_extrinsic(&at, proof) {
+		Ok(Some(extrinsic)) => Some((at, extrinsic)),
+		Ok(None) => None,
+		Err(e) => {
+			warn!(target: "babe", "Failed to build equivocation report: {:?}", e);
+			None
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use futures::executor::block_on;
+	use futures::StreamExt;
+	use sc_transaction_pool::{BasicPool, FullChainApi};
+	use sp_core::crypto::Pair;
+	use sp_consensus_babe::AuthorityPair;
+	use sp_transaction_pool::InPoolTransaction;
+	use substrate_test_runtime_client::runtime::{Block, Extrinsic, Header};
+
+	fn header(number: u64) -> Header {
+		Header {
+			parent_hash: Default::default(),
+			number,
+			state_root: Default::default(),
+			extrinsics_root: Default::default(),
+			digest: Default::default(),
+		}
+	}
+
+	#[test]
+	fn equivocations_are_sent_to_the_subscribers() {
+		let reports = EquivocationReports::<Block>::default();
+		let mut subscriber = reports.subscribe();
+		drop(reports.subscribe());
+
+		let proof = EquivocationProof {
+			offender: AuthorityPair::from_seed(&[1; 32]).public(),
+			slot_number: 42,
+			first_header: header(1),
+			second_header: header(2),
+		};
+		reports.report(proof.clone());
+
+		assert_eq!(block_on(subscriber.next()), Some(proof));
+		// the dropped subscriber was forgotten.
+		assert_eq!(reports.0.lock().len(), 1);
+	}
+
+	#[test]
+	fn equivocations_are_submitted_to_the_pool() {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let pool = Arc::new(
+			BasicPool::new(Default::default(), Arc::new(FullChainApi::new(client.clone()))).0
+		);
+		let reports = EquivocationReports::<Block>::default();
+		let submission = submit_equivocation_reports(client, pool.clone(), reports.subscribe());
+
+		let proof = EquivocationProof {
+			offender: AuthorityPair::from_seed(&[1; 32]).public(),
+			slot_number: 42,
+			first_header: header(1),
+			second_header: header(2),
+		};
+		reports.report(proof.clone());
+		// ends the submission once the report is submitted.
+		drop(reports);
+		block_on(submission);
+
+		let ready = pool.ready().map(|tx| tx.data().clone()).collect::<Vec<_>>();
+		assert_eq!(ready, vec![Extrinsic::IncludeData(proof.encode())]);
+	}
+}
@@ -61,10 +61,13 @@
 pub use sp_consensus_babe::{
 	BabeApi, ConsensusLog, BABE_ENGINE_ID, SlotNumber, BabeConfiguration,
 	AuthorityId, AuthorityPair, AuthoritySignature,
-	BabeAuthorityWeight, VRF_OUTPUT_LENGTH,
-	digests::{PreDigest, CompatibleDigestItem, NextEpochDescriptor},
+	BabeAuthorityWeight, BabeEpochConfiguration, EquivocationProof, SecondarySlots,
+	VRF_OUTPUT_LENGTH,
+	digests::{PreDigest, CompatibleDigestItem, NextEpochDescriptor, NextConfigDescriptor},
 };
 pub use sp_consensus::SyncOracle;
+pub use sc_consensus_slots::{MissedSlot, MissedSlotReason, MissedSlots, SlotProportion};
+pub use equivocation::{EquivocationReports, submit_equivocation_reports};
 use std::{
 	collections::HashMap, sync::Arc, u64, pin::Pin, time::{Instant, Duration},
 	any::Any, borrow::Cow
@@ -76,7 +79,7 @@ use sp_consensus::import_queue::{
 };
 use sp_runtime::{
 	generic::{BlockId, OpaqueDigestItemId}, Justification,
-	traits::{Block as BlockT, Header, DigestItemFor, Zero},
+	traits::{Block as BlockT, Header, DigestItemFor, Saturating, Zero},
 };
 use sp_api::{ProvideRuntimeApi, NumberFor};
 use sc_keystore::KeyStorePtr;
@@ -94,6 +97,7 @@ use sp_timestamp::{TimestampInherentData, InherentType as TimestampInherent};
 use sp_consensus::import_queue::{Verifier, BasicQueue, CacheKeyId};
 use sc_client_api::{
 	backend::AuxStore,
+	health::HealthSink,
 	BlockchainEvents, ProvideUncles,
 };
 use sp_block_builder::BlockBuilder as BlockBuilderApi;
@@ -102,9 +106,11 @@ use futures::prelude::*;
 use log::{warn, debug, info, trace};
 use sc_consensus_slots::{
 	SlotWorker, SlotInfo, SlotCompatible, StorageChanges, CheckedHeader, check_equivocation,
+	SlotMetrics,
 };
 use sc_consensus_epochs::{
 	descendent_query, SharedEpochChanges, EpochChangesFor, Epoch as EpochT, ViableEpochDescriptor,
+	snapshot::{CheckpointsFor, load_checkpoints},
 };
 use sp_blockchain::{
 	Result as ClientResult, Error as ClientError,
@@ -113,8 +119,10 @@ use sp_blockchain::{
 use schnorrkel::SignatureError;
 use codec::{Encode, Decode};
 use sp_api::ApiExt;
+use prometheus_endpoint::{register, Gauge, PrometheusError, Registry, U64};
 
 mod aux_schema;
+mod equivocation;
 mod verification;
 pub mod authorship;
 #[cfg(test)]
@@ -133,19 +141,25 @@ pub struct Epoch {
 	pub authorities: Vec<(AuthorityId, BabeAuthorityWeight)>,
 	/// Randomness for this epoch
 	pub randomness: [u8; VRF_OUTPUT_LENGTH],
+	/// Configuration of the epoch
+	pub config: BabeEpochConfiguration,
 }
 
 impl EpochT for Epoch {
-	type NextEpochDescriptor = NextEpochDescriptor;
+	type NextEpochDescriptor = (NextEpochDescriptor, BabeEpochConfiguration);
 	type SlotNumber = SlotNumber;
 
-	fn increment(&self, descriptor: NextEpochDescriptor) -> Epoch {
+	fn increment(
+		&self,
+		(descriptor, config): (NextEpochDescriptor, BabeEpochConfiguration)
+	) -> Epoch {
 		Epoch {
 			epoch_index: self.epoch_index + 1,
 			start_slot: self.start_slot + self.duration,
 			duration: self.duration,
 			authorities: descriptor.authorities,
 			randomness: descriptor.randomness,
+			config,
 		}
 	}
 
@@ -158,6 +172,24 @@ impl EpochT for Epoch {
 	}
 }
 
+impl Epoch {
+	/// Produce the epoch data that applies to the given slot. If the chain
+	/// was stalled for longer than an epoch, the given slot might be past the
+	/// end of this epoch, in which case the epoch data is re-used for the
+	/// epoch that contains the slot (i.e. the epoch index and start slot are
+	/// adjusted to account for the skipped epochs).
+	pub fn clone_for_slot(&self, slot_number: SlotNumber) -> Epoch {
+		let mut epoch = self.clone();
+		let skipped_epochs = slot_number.saturating_sub(self.start_slot) / self.duration;
+
+		epoch.epoch_index = self.epoch_index.checked_add(skipped_epochs)
+			.expect("epoch indices will never reach 2^64 before the death of the universe; qed");
+		epoch.start_slot = self.start_slot + skipped_epochs * self.duration;
+
+		epoch
+	}
+}
+
 #[derive(derive_more::Display, Debug)]
 enum Error<B: BlockT> {
 	#[display(fmt = "Multiple BABE pre-runtime digests, rejecting!")]
@@ -166,6 +198,8 @@ enum Error<B: BlockT> {
 	NoPreRuntimeDigest,
 	#[display(fmt = "Multiple BABE epoch change digests, rejecting!")]
 	MultipleEpochChangeDigests,
+	#[display(fmt = "Multiple BABE config change digests, rejecting!")]
+	MultipleConfigChangeDigests,
 	#[display(fmt = "Could not extract timestamp and slot: {:?}", _0)]
 	Extraction(sp_consensus::Error),
 	#[display(fmt = "Could not fetch epoch at {:?}", _0)]
@@ -200,10 +234,14 @@ enum Error<B: BlockT> {
 	ExpectedEpochChange(B::Hash, u64),
 	#[display(fmt = "Unexpected epoch change")]
 	UnexpectedEpochChange,
+	#[display(fmt = "Unexpected config change")]
+	UnexpectedConfigChange,
 	#[display(fmt = "Parent block of {} has no associated weight", _0)]
 	ParentBlockNoAssociatedWeight(B::Hash),
 	#[display(fmt = "Checking inherents failed: {}", _0)]
 	CheckInherents(String),
+	#[display(fmt = "Header {:?} rejected by the epoch snapshot: {}", _0, _1)]
+	Checkpoint(B::Hash, String),
 	Client(sp_blockchain::Error),
 	Runtime(sp_inherents::Error),
 	ForkTree(Box<fork_tree::Error<sp_blockchain::Error>>),
@@ -271,6 +309,7 @@ impl Config {
 			duration: self.epoch_length,
 			authorities: self.genesis_authorities.clone(),
 			randomness: self.randomness.clone(),
+			config: self.genesis_epoch_configuration(),
 		}
 	}
 }
@@ -311,11 +350,38 @@ pub struct BabeParams<B: BlockT, C, E, I, SO, SC, CAW> {
 	/// Force authoring of blocks even if we are offline
 	pub force_authoring: bool,
 
+	/// Whether the secondary slots are claimed.
+	pub secondary_slots: SecondarySlots,
+
+	/// Skip authoring while the best block is more than this number of blocks ahead of the
+	/// finalized block. `None` means no limit.
+	pub max_unfinalized_blocks: Option<u32>,
+
+	/// Proportion of the slot given to build a block, the rest being left to import and
+	/// announce it.
+	pub proposal_slot_proportion: SlotProportion,
+
 	/// The source of timestamps for relative slots
 	pub babe_link: BabeLink<B>,
 
 	/// Checks if the current native implementation can author with a runtime at a given block.
 	pub can_author_with: CAW,
+
+	/// Registry the metrics of the slots are exported to.
+	pub prometheus_registry: Option<Registry>,
+
+	/// Sink the claimed slots are reported to, for the health gauges of the service.
+	pub health_sink: Option<HealthSink>,
+}
+
+/// Registers the metrics of the slot worker, and the index of the current epoch.
+fn register_metrics(registry: &Registry) -> Result<(SlotMetrics, Gauge<U64>), PrometheusError> {
+	let slot_metrics = SlotMetrics::register(registry)?;
+	let epoch_index = register(Gauge::new(
+		"babe_epoch_index",
+		"Index of the epoch of the current slot",
+	)?, registry)?;
+	Ok((slot_metrics, epoch_index))
 }
 
 /// Start the babe worker.
@@ -328,8 +394,13 @@ pub fn start_babe<B, C, SC, E, I, SO, CAW, Error>(BabeParams {
 	sync_oracle,
 	inherent_data_providers,
 	force_authoring,
+	secondary_slots,
+	max_unfinalized_blocks,
+	proposal_slot_proportion,
 	babe_link,
 	can_author_with,
+	prometheus_registry,
+	health_sink,
 }: BabeParams<B, C, E, I, SO, SC, CAW>) -> Result<
 	impl futures::Future<Output=()>,
 	sp_consensus::Error,
@@ -347,6 +418,15 @@ pub fn start_babe<B, C, SC, E, I, SO, CAW, Error>(BabeParams {
 	SO: SyncOracle + Send + Sync + Clone,
 	CAW: CanAuthorWith<B> + Send,
 {
+	let (metrics, epoch_index) = match prometheus_registry.as_ref().map(register_metrics) {
+		Some(Ok((metrics, epoch_index))) => (Some(metrics), Some(epoch_index)),
+		Some(Err(err)) => {
+			warn!(target: "babe", "Failed to register metrics: {:?}", err);
+			(None, None)
+		},
+		None => (None, None),
+	};
+
 	let config = babe_link.config;
 	let worker = BabeWorker {
 		client: client.clone(),
@@ -354,9 +434,16 @@ pub fn start_babe<B, C, SC, E, I, SO, CAW, Error>(BabeParams {
 		env,
 		sync_oracle: sync_oracle.clone(),
 		force_authoring,
+		secondary_slots,
+		max_unfinalized_blocks,
+		proposal_slot_proportion,
 		keystore,
 		epoch_changes: babe_link.epoch_changes.clone(),
 		config: config.clone(),
+		missed_slots: babe_link.missed_slots.clone(),
+		metrics,
+		epoch_index,
+		health_sink,
 	};
 
 	register_babe_inherent_data_provider(&inherent_data_providers, config.slot_duration())?;
@@ -384,9 +471,37 @@ struct BabeWorker<B: BlockT, C, E, I, SO> {
 	env: E,
 	sync_oracle: SO,
 	force_authoring: bool,
+	secondary_slots: SecondarySlots,
+	max_unfinalized_blocks: Option<u32>,
+	proposal_slot_proportion: SlotProportion,
 	keystore: KeyStorePtr,
 	epoch_changes: SharedEpochChanges<B, Epoch>,
 	config: Config,
+	missed_slots: MissedSlots,
+	metrics: Option<SlotMetrics>,
+	epoch_index: Option<Gauge<U64>>,
+	health_sink: Option<HealthSink>,
+}
+
+impl<B: BlockT, C, E, I, SO> BabeWorker<B, C, E, I, SO> {
+	/// The epoch to claim `slot_number` in, without the secondary slots if they are skipped.
+	fn claimable_epoch(&self, epoch: &Epoch, slot_number: SlotNumber) -> Epoch {
+		claimable_epoch(epoch, slot_number, self.secondary_slots)
+	}
+}
+
+/// The epoch to claim `slot_number` in, without the secondary slots if `secondary_slots` skips
+/// them.
+fn claimable_epoch(
+	epoch: &Epoch,
+	slot_number: SlotNumber,
+	secondary_slots: SecondarySlots,
+) -> Epoch {
+	let mut epoch = epoch.clone_for_slot(slot_number);
+	if secondary_slots == SecondarySlots::Skip {
+		epoch.config.secondary_slots = false;
+	}
+	epoch
 }
 
 impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWorker<B, C, E, I, SO> where
@@ -424,14 +539,25 @@ impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWork
 		parent: &B::Header,
 		slot_number: u64,
 	) -> Result<Self::EpochData, ConsensusError> {
-		self.epoch_changes.lock().epoch_descriptor_for_child_of(
+		let epoch_descriptor = self.epoch_changes.lock().epoch_descriptor_for_child_of(
 			descendent_query(&*self.client),
 			&parent.hash(),
 			parent.number().clone(),
 			slot_number,
 		)
 			.map_err(|e| ConsensusError::ChainLookup(format!("{:?}", e)))?
-			.ok_or(sp_consensus::Error::InvalidAuthoritiesSet)
+			.ok_or(sp_consensus::Error::InvalidAuthoritiesSet)?;
+
+		if let Some(epoch_index) = &self.epoch_index {
+			let epoch_changes = self.epoch_changes.lock();
+			let epoch = epoch_changes
+				.viable_epoch(&epoch_descriptor, |slot| self.config.genesis_epoch(slot));
+			if let Some(epoch) = epoch {
+				epoch_index.set(epoch.as_ref().clone_for_slot(slot_number).epoch_index);
+			}
+		}
+
+		Ok(epoch_descriptor)
 	}
 
 	fn authorities_len(&self, epoch_descriptor: &Self::EpochData) -> Option<usize> {
@@ -449,11 +575,13 @@ impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWork
 		debug!(target: "babe", "Attempting to claim slot {}", slot_number);
 		let s = authorship::claim_slot(
 			slot_number,
-			self.epoch_changes.lock().viable_epoch(
-				&epoch_descriptor,
-				|slot| self.config.genesis_epoch(slot)
-			)?.as_ref(),
-			&*self.config,
+			&self.claimable_epoch(
+				self.epoch_changes.lock().viable_epoch(
+					&epoch_descriptor,
+					|slot| self.config.genesis_epoch(slot)
+				)?.as_ref(),
+				slot_number,
+			),
 			&self.keystore,
 		);
 
@@ -464,6 +592,30 @@ impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWork
 		s
 	}
 
+	fn is_scheduled(
+		&self,
+		_parent_header: &B::Header,
+		slot_number: SlotNumber,
+		epoch_descriptor: &ViableEpochDescriptor<B::Hash, NumberFor<B>, Epoch>,
+	) -> bool {
+		self.epoch_changes.lock()
+			.viable_epoch(&epoch_descriptor, |slot| self.config.genesis_epoch(slot))
+			.map(|epoch| authorship::is_secondary_slot_scheduled(
+				slot_number,
+				&self.claimable_epoch(epoch.as_ref(), slot_number),
+				&self.keystore,
+			))
+			.unwrap_or(false)
+	}
+
+	fn missed_slots(&self) -> Option<MissedSlots> {
+		Some(self.missed_slots.clone())
+	}
+
+	fn metrics(&self) -> Option<SlotMetrics> {
+		self.metrics.clone()
+	}
+
 	fn pre_digest_data(
 		&self,
 		_slot_number: u64,
@@ -505,6 +657,19 @@ impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWork
 		self.force_authoring
 	}
 
+	fn proposal_slot_proportion(&self) -> SlotProportion {
+		self.proposal_slot_proportion
+	}
+
+	fn should_backoff(&self, chain_head: &B::Header) -> bool {
+		let max_unfinalized_blocks = match self.max_unfinalized_blocks {
+			Some(max_unfinalized_blocks) => max_unfinalized_blocks,
+			None => return false,
+		};
+		let finalized_number = self.client.info().finalized_number;
+		chain_head.number().saturating_sub(finalized_number) > max_unfinalized_blocks.into()
+	}
+
 	fn sync_oracle(&mut self) -> &mut Self::SyncOracle {
 		&mut self.sync_oracle
 	}
@@ -515,6 +680,10 @@ impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWork
 		}))
 	}
 
+	fn health_sink(&self) -> Option<HealthSink> {
+		self.health_sink.clone()
+	}
+
 	fn proposing_remaining_duration(
 		&self,
 		head: &B::Header,
@@ -617,6 +786,25 @@ fn find_next_epoch_digest<B: BlockT>(header: &B::Header)
 	Ok(epoch_digest)
 }
 
+/// Extract the BABE config change digest from the given header, if it exists.
+fn find_next_config_digest<B: BlockT>(header: &B::Header)
+	-> Result<Option<NextConfigDescriptor>, Error<B>>
+	where DigestItemFor<B>: CompatibleDigestItem,
+{
+	let mut config_digest: Option<_> = None;
+	for log in header.digest().logs() {
+		trace!(target: "babe", "Checking log {:?}, looking for config change digest.", log);
+		let log = log.try_to::<ConsensusLog>(OpaqueDigestItemId::Consensus(&BABE_ENGINE_ID));
+		match (log, config_digest.is_some()) {
+			(Some(ConsensusLog::NextConfigData(_)), true) => return Err(babe_err(Error::MultipleConfigChangeDigests)),
+			(Some(ConsensusLog::NextConfigData(config)), false) => config_digest = Some(config),
+			_ => trace!(target: "babe", "Ignoring digest not meant for us"),
+		}
+	}
+
+	Ok(config_digest)
+}
+
 
 #[derive(Default, Clone)]
 struct TimeSource(Arc<Mutex<(Option<Duration>, Vec<(Instant, u64)>)>>);
@@ -641,6 +829,8 @@ pub struct BabeLink<Block: BlockT> {
 	time_source: TimeSource,
 	epoch_changes: SharedEpochChanges<Block, Epoch>,
 	config: Config,
+	missed_slots: MissedSlots,
+	equivocation_reports: EquivocationReports<Block>,
 }
 
 impl<Block: BlockT> BabeLink<Block> {
@@ -653,6 +843,16 @@ impl<Block: BlockT> BabeLink<Block> {
 	pub fn config(&self) -> &Config {
 		&self.config
 	}
+
+	/// Get the log of the slots the authoring worker was scheduled for but didn't author.
+	pub fn missed_slots(&self) -> &MissedSlots {
+		&self.missed_slots
+	}
+
+	/// Get the subscribers to the equivocations detected by the verifier.
+	pub fn equivocation_reports(&self) -> &EquivocationReports<Block> {
+		&self.equivocation_reports
+	}
 }
 
 /// A verifier for Babe blocks.
@@ -662,6 +862,9 @@ pub struct BabeVerifier<Block: BlockT, Client> {
 	config: Config,
 	epoch_changes: SharedEpochChanges<Block, Epoch>,
 	time_source: TimeSource,
+	/// Headers of the imported epoch snapshot, whose seals aren't verified.
+	checkpoints: CheckpointsFor<Block>,
+	equivocation_reports: EquivocationReports<Block>,
 }
 
 impl<Block, Client> BabeVerifier<Block, Client>
@@ -742,10 +945,38 @@ impl<Block, Client> Verifier<Block> for BabeVerifier<Block, Client> where
 		)
 			.map_err(|e| Error::<Block>::ForkTree(Box::new(e)))?
 			.ok_or_else(|| Error::<Block>::FetchEpoch(parent_hash))?;
-		let viable_epoch = epoch_changes.viable_epoch(
+
+		// The headers of a trusted snapshot are imported without checking their seals. The other
+		// ones, including those below a checkpoint, are verified as usual.
+		let covered = self.checkpoints.covers(*header.number(), &hash)
+			.map_err(|e| Error::<Block>::Checkpoint(hash, e))?;
+		if covered {
+			let mut pre_header = header.clone();
+			let seal = pre_header.digest_mut().pop()
+				.ok_or_else(|| Error::<Block>::HeaderUnsealed(hash))?;
+
+			trace!(target: "babe", "Checkpointed {:?}; importing.", pre_header);
+
+			let mut import_block = BlockImportParams::new(origin, pre_header);
+			import_block.post_digests.push(seal);
+			import_block.body = body;
+			import_block.justification = justification;
+			import_block.intermediates.insert(
+				Cow::from(INTERMEDIATE_KEY),
+				Box::new(BabeIntermediate::<Block> { epoch_descriptor }) as Box<dyn Any>,
+			);
+			import_block.post_hash = Some(hash);
+
+			return Ok((import_block, Default::default()));
+		}
+
+		let epoch = epoch_changes.viable_epoch(
 			&epoch_descriptor,
 			|slot| self.config.genesis_epoch(slot)
-		).ok_or_else(|| Error::<Block>::FetchEpoch(parent_hash))?;
+		)
+			.ok_or_else(|| Error::<Block>::FetchEpoch(parent_hash))?
+			.as_ref()
+			.clone_for_slot(pre_digest.slot_number());
 
 		// We add one to the current slot to allow for some small drift.
 		// FIXME #1019 in the future, alter this queue to allow deferring of headers
@@ -753,8 +984,7 @@ impl<Block, Client> Verifier<Block> for BabeVerifier<Block, Client> where
 			header: header.clone(),
 			pre_digest: Some(pre_digest.clone()),
 			slot_now: slot_now + 1,
-			epoch: viable_epoch.as_ref(),
-			config: &self.config,
+			epoch: &epoch,
 		};
 
 		match verification::check_header::<Block>(v_params)? {
@@ -775,13 +1005,12 @@ impl<Block, Client> Verifier<Block> for BabeVerifier<Block, Client> where
 					&header,
 					&author,
 				).map_err(|e| e.to_string())? {
-					info!(
-						"Slot author {:?} is equivocating at slot {} with headers {:?} and {:?}",
-						author,
-						babe_pre_digest.slot_number(),
-						equivocation_proof.fst_header().hash(),
-						equivocation_proof.snd_header().hash(),
-					);
+					self.equivocation_reports.report(EquivocationProof {
+						offender: author.clone(),
+						slot_number: equivocation_proof.slot(),
+						first_header: equivocation_proof.fst_header().clone(),
+						second_header: equivocation_proof.snd_header().clone(),
+					});
 				}
 
 				// if the body is passed through, we need to use the runtime
@@ -801,6 +1030,18 @@ impl<Block, Client> Verifier<Block> for BabeVerifier<Block, Client> where
 					body = Some(inner_body);
 				}
 
+				sc_consensus_recorder::record("babe", "slot_claim", || serde_json::json!({
+					"hash": hash,
+					"number": pre_header.number(),
+					"slot": slot_number,
+					"epoch": epoch.epoch_index,
+					"claim": match babe_pre_digest {
+						PreDigest::Primary { .. } => "primary",
+						PreDigest::Secondary { .. } => "secondary",
+					},
+					"author": sc_consensus_recorder::authority_id(author.as_ref()),
+				}));
+
 				trace!(target: "babe", "Checked {:?}; importing.", pre_header);
 				telemetry!(
 					CONSENSUS_TRACE;
@@ -976,18 +1217,27 @@ impl<Block, Client, Inner> BlockImport<Block> for BabeBlockImport<Block, Client,
 		// search for this all the time so we can reject unexpected announcements.
 		let next_epoch_digest = find_next_epoch_digest::<Block>(&block.header)
 			.map_err(|e| ConsensusError::ClientImport(e.to_string()))?;
+		let next_config_digest = find_next_config_digest::<Block>(&block.header)
+			.map_err(|e| ConsensusError::ClientImport(e.to_string()))?;
 
-		match (first_in_epoch, next_epoch_digest.is_some()) {
-			(true, true) => {},
-			(false, false) => {},
-			(true, false) => {
+		match (first_in_epoch, next_epoch_digest.is_some(), next_config_digest.is_some()) {
+			(true, true, _) => {},
+			(false, false, false) => {},
+			(false, false, true) => {
+				return Err(
+					ConsensusError::ClientImport(
+						babe_err(Error::<Block>::UnexpectedConfigChange).into(),
+					)
+				)
+			},
+			(true, false, _) => {
 				return Err(
 					ConsensusError::ClientImport(
 						babe_err(Error::<Block>::ExpectedEpochChange(hash, slot_number)).into(),
 					)
 				);
 			},
-			(false, true) => {
+			(false, true, _) => {
 				return Err(ConsensusError::ClientImport(Error::<Block>::UnexpectedEpochChange.into()));
 			},
 		}
@@ -1001,12 +1251,33 @@ impl<Block, Client, Inner> BlockImport<Block> for BabeBlockImport<Block, Client,
 		if let Some(next_epoch_descriptor) = next_epoch_digest {
 			old_epoch_changes = Some(epoch_changes.clone());
 
-			let viable_epoch = epoch_changes.viable_epoch(
+			let mut viable_epoch = epoch_changes.viable_epoch(
 				&epoch_descriptor,
 				|slot| self.config.genesis_epoch(slot),
 			).ok_or_else(|| {
 				ConsensusError::ClientImport(Error::<Block>::FetchEpoch(parent_hash).into())
-			})?;
+			})?.into_cloned();
+
+			if viable_epoch.as_ref().end_slot() <= slot_number {
+				// some epochs were skipped since the chain was stalled for longer
+				// than an epoch. we re-use the data of the signaled epoch for the
+				// epoch the block belongs to. only our local copy is updated, the
+				// signaled epoch is kept as is in the tree since there might be
+				// other forks on which it wasn't skipped.
+				let epoch = viable_epoch.as_mut();
+				let skipped = epoch.clone_for_slot(slot_number);
+
+				warn!(target: "babe",
+					"Epochs {} to {} were skipped, block {} is in epoch {} (slot {})",
+					epoch.epoch_index,
+					skipped.epoch_index - 1,
+					hash,
+					skipped.epoch_index,
+					slot_number,
+				);
+
+				*epoch = skipped;
+			}
 
 			babe_info!("New epoch {} launching at block {} (block slot {} >= start slot {}).",
 					   viable_epoch.as_ref().epoch_index,
@@ -1014,7 +1285,13 @@ impl<Block, Client, Inner> BlockImport<Block> for BabeBlockImport<Block, Client,
 					   slot_number,
 					   viable_epoch.as_ref().start_slot);
 
-			let next_epoch = viable_epoch.increment(next_epoch_descriptor);
+			// the configuration of the next epoch is inherited from the
+			// current one, unless a config change was signaled in this block.
+			let epoch_config = next_config_digest.map(Into::into).unwrap_or_else(
+				|| viable_epoch.as_ref().config.clone()
+			);
+
+			let next_epoch = viable_epoch.increment((next_epoch_descriptor, epoch_config));
 
 			babe_info!("Next epoch starts at slot {}", next_epoch.as_ref().start_slot);
 
@@ -1037,7 +1314,7 @@ impl<Block, Client, Inner> BlockImport<Block> for BabeBlockImport<Block, Client,
 					number,
 					*block.header.parent_hash(),
 					next_epoch,
-				).map_err(|e| ConsensusError::ClientImport(format!("{:?}", e)))?;
+				).map_err(|e| ConsensusError::ClientImport(e.to_string()))?;
 
 				Ok(())
 			};
@@ -1156,11 +1433,13 @@ pub fn block_import<Client, Block: BlockT, I>(
 ) -> ClientResult<(BabeBlockImport<Block, Client, I>, BabeLink<Block>)> where
 	Client: AuxStore + HeaderBackend<Block> + HeaderMetadata<Block, Error = sp_blockchain::Error>,
 {
-	let epoch_changes = aux_schema::load_epoch_changes::<Block, _>(&*client)?;
+	let epoch_changes = aux_schema::load_epoch_changes::<Block, _>(&*client, &config)?;
 	let link = BabeLink {
 		epoch_changes: epoch_changes.clone(),
 		time_source: Default::default(),
 		config: config.clone(),
+		missed_slots: Default::default(),
+		equivocation_reports: Default::default(),
 	};
 
 	// NOTE: this isn't entirely necessary, but since we didn't use to prune the
@@ -1212,6 +1491,8 @@ pub fn import_queue<Block: BlockT, Client, Inner>(
 		config: babe_link.config,
 		epoch_changes: babe_link.epoch_changes,
 		time_source: babe_link.time_source,
+		checkpoints: load_checkpoints::<Block, _>(&*client)?,
+		equivocation_reports: babe_link.equivocation_reports,
 	};
 
 	Ok(BasicQueue::new(
@@ -1255,7 +1536,6 @@ pub mod test_helpers {
 		authorship::claim_slot(
 			slot_number,
 			&epoch,
-			&link.config,
 			keystore,
 		).map(|(digest, _)| digest)
 	}
@@ -299,6 +299,8 @@ impl TestNetFactory for BabeTestNet {
 				config: data.link.config.clone(),
 				epoch_changes: data.link.epoch_changes.clone(),
 				time_source: data.link.time_source.clone(),
+				checkpoints: Default::default(),
+				equivocation_reports: data.link.equivocation_reports.clone(),
 			},
 			mutator: MUTATOR.with(|m| m.borrow().clone()),
 		}
@@ -405,9 +407,14 @@ fn run_one_test(
 			sync_oracle: DummyOracle,
 			inherent_data_providers: data.inherent_data_providers.clone(),
 			force_authoring: false,
+			secondary_slots: Default::default(),
+			max_unfinalized_blocks: None,
+			proposal_slot_proportion: Default::default(),
 			babe_link: data.link.clone(),
 			keystore,
 			can_author_with: sp_consensus::AlwaysCanAuthor,
+			prometheus_registry: None,
+			health_sink: None,
 		}).expect("Starts babe"));
 	}
 
@@ -499,34 +506,29 @@ fn can_author_block() {
 		.expect("Generates authority pair");
 
 	let mut i = 0;
-	let epoch = Epoch {
+	let mut epoch = Epoch {
 		start_slot: 0,
 		authorities: vec![(pair.public(), 1)],
 		randomness: [0; 32],
 		epoch_index: 1,
 		duration: 100,
-	};
-
-	let mut config = crate::BabeConfiguration {
-		slot_duration: 1000,
-		epoch_length: 100,
-		c: (3, 10),
-		genesis_authorities: Vec::new(),
-		randomness: [0; 32],
-		secondary_slots: true,
+		config: BabeEpochConfiguration {
+			c: (3, 10),
+			secondary_slots: true,
+		},
 	};
 
 	// with secondary slots enabled it should never be empty
-	match claim_slot(i, &epoch, &config, &keystore) {
+	match claim_slot(i, &epoch, &keystore) {
 		None => i += 1,
 		Some(s) => debug!(target: "babe", "Authored block {:?}", s.0),
 	}
 
 	// otherwise with only vrf-based primary slots we might need to try a couple
 	// of times.
-	config.secondary_slots = false;
+	epoch.config.secondary_slots = false;
 	loop {
-		match claim_slot(i, &epoch, &config, &keystore) {
+		match claim_slot(i, &epoch, &keystore) {
 			None => i += 1,
 			Some(s) => {
 				debug!(target: "babe", "Authored block {:?}", s.0);
@@ -536,6 +538,73 @@ fn can_author_block() {
 	}
 }
 
+#[test]
+fn secondary_slots_are_claimed_or_skipped() {
+	let _ = env_logger::try_init();
+	let keystore_path = tempfile::tempdir().expect("Creates keystore path");
+	let keystore = sc_keystore::Store::open(keystore_path.path(), None).expect("Creates keystore");
+	let pair = keystore.write().insert_ephemeral_from_seed::<AuthorityPair>("//Alice")
+		.expect("Generates authority pair");
+
+	// the primary slots are never won, so any claimed slot is a secondary slot.
+	let epoch = Epoch {
+		start_slot: 0,
+		authorities: vec![(pair.public(), 1)],
+		randomness: [0; 32],
+		epoch_index: 1,
+		duration: 100,
+		config: BabeEpochConfiguration {
+			c: (0, 1),
+			secondary_slots: true,
+		},
+	};
+
+	for slot_number in 0..10 {
+		let claim = claim_slot(
+			slot_number,
+			&claimable_epoch(&epoch, slot_number, SecondarySlots::Claim),
+			&keystore,
+		).map(|(pre_digest, _)| pre_digest);
+		match claim {
+			Some((PreDigest::Secondary { slot_number: claimed, .. }, _)) =>
+				assert_eq!(claimed, slot_number),
+			other => panic!("Expected a secondary claim, got {:?}", other),
+		}
+
+		assert!(claim_slot(
+			slot_number,
+			&claimable_epoch(&epoch, slot_number, SecondarySlots::Skip),
+			&keystore,
+		).is_none());
+	}
+}
+
+#[test]
+fn epoch_is_adjusted_for_skipped_epochs() {
+	let epoch = Epoch {
+		start_slot: 100,
+		authorities: vec![],
+		randomness: [0; 32],
+		epoch_index: 1,
+		duration: 10,
+		config: BabeEpochConfiguration {
+			c: (3, 10),
+			secondary_slots: true,
+		},
+	};
+
+	// slots within the epoch leave it untouched.
+	assert_eq!(epoch.clone_for_slot(100), epoch);
+	assert_eq!(epoch.clone_for_slot(109), epoch);
+
+	// slots after the end of the epoch are mapped to the epoch they belong to.
+	let skipped = epoch.clone_for_slot(135);
+	assert_eq!(skipped.epoch_index, 4);
+	assert_eq!(skipped.start_slot, 130);
+	assert_eq!(skipped.authorities, epoch.authorities);
+	assert_eq!(skipped.randomness, epoch.randomness);
+}
+
 // Propose and import a new BABE block on top of the given parent.
 fn propose_and_import_block<Transaction>(
 	parent: &TestHeader,
@@ -792,3 +861,150 @@ fn verify_slots_are_strictly_increasing() {
 		&mut block_import,
 	);
 }
+
+#[test]
+fn authoring_backs_off_while_finality_lags_behind() {
+	use sc_client_api::Finalizer;
+	use sc_consensus_slots::SimpleSlotWorker;
+
+	let mut net = BabeTestNet::new(1);
+
+	let peer = net.peer(0);
+	let data = peer.data.as_ref().expect("babe link set up during initialization");
+
+	let client = peer.client().as_full().expect("Only full clients are used in tests").clone();
+	let mut block_import = data.block_import.lock().take().expect("import set up during init");
+
+	let mut proposer_factory = DummyFactory {
+		client: client.clone(),
+		config: data.link.config.clone(),
+		epoch_changes: data.link.epoch_changes.clone(),
+		mutator: Arc::new(|_, _| ()),
+	};
+
+	let mut hashes = Vec::new();
+	let mut parent_header = client.header(&BlockId::Number(0)).unwrap().unwrap();
+	for _ in 0..5 {
+		let block_hash = propose_and_import_block(
+			&parent_header,
+			None,
+			&mut proposer_factory,
+			&mut block_import,
+		);
+		hashes.push(block_hash);
+		parent_header = client.header(&BlockId::Hash(block_hash)).unwrap().unwrap();
+	}
+
+	let keystore_path = tempfile::tempdir().expect("Creates keystore path");
+	let keystore = sc_keystore::Store::open(keystore_path.path(), None).expect("Creates keystore");
+	let worker = BabeWorker {
+		client: client.clone(),
+		block_import: Arc::new(Mutex::new(block_import)),
+		env: proposer_factory,
+		sync_oracle: DummyOracle,
+		force_authoring: false,
+		secondary_slots: Default::default(),
+		max_unfinalized_blocks: Some(2),
+		proposal_slot_proportion: Default::default(),
+		keystore,
+		epoch_changes: data.link.epoch_changes.clone(),
+		config: data.link.config.clone(),
+		missed_slots: data.link.missed_slots.clone(),
+		metrics: None,
+		epoch_index: None,
+	};
+
+	let best_header = |hash| client.header(&BlockId::Hash(hash)).unwrap().unwrap();
+
+	// nothing is finalized, so the best block #5 is too far ahead of the genesis.
+	assert!(worker.should_backoff(&best_header(hashes[4])));
+	// block #2 is exactly at the limit.
+	assert!(!worker.should_backoff(&best_header(hashes[1])));
+
+	// once finality catches up, authoring resumes on top of the best block.
+	client.finalize_block(BlockId::Hash(hashes[2]), None, false).unwrap();
+	assert!(!worker.should_backoff(&best_header(hashes[4])));
+}
+
+// Propose a BABE block on top of the given parent, and seal it with the key of `seed`.
+fn propose_sealed_header(
+	parent: &TestHeader,
+	slot_number: SlotNumber,
+	seed: u8,
+	proposer_factory: &mut DummyFactory,
+) -> TestHeader {
+	let mut proposer = futures::executor::block_on(proposer_factory.init(parent)).unwrap();
+	let pre_digest = sp_runtime::generic::Digest {
+		logs: vec![
+			Item::babe_pre_digest(
+				PreDigest::Secondary {
+					authority_index: 0,
+					slot_number,
+				},
+			),
+		],
+	};
+
+	let mut header = futures::executor::block_on(proposer.propose_with(pre_digest))
+		.unwrap()
+		.block
+		.header;
+	let pair = AuthorityPair::from_seed(&[seed; 32]);
+	let signature = pair.sign(header.hash().as_ref());
+	header.digest_mut().push(Item::babe_seal(signature));
+	header
+}
+
+#[test]
+fn checkpoints_do_not_cover_sibling_forks() {
+	let mut net = BabeTestNet::new(1);
+
+	let peer = net.peer(0);
+	let data = peer.data.as_ref().expect("babe link set up during initialization");
+
+	let client = peer.client().as_full().expect("Only full clients are used in tests").clone();
+	let mut block_import = data.block_import.lock().take().expect("import set up during init");
+
+	let mut proposer_factory = DummyFactory {
+		client: client.clone(),
+		config: data.link.config.clone(),
+		epoch_changes: data.link.epoch_changes.clone(),
+		mutator: Arc::new(|_, _| ()),
+	};
+
+	let genesis_header = client.header(&BlockId::Number(0)).unwrap().unwrap();
+	let b1 = propose_and_import_block(
+		&genesis_header,
+		Some(1),
+		&mut proposer_factory,
+		&mut block_import,
+	);
+	let b1 = client.header(&BlockId::Hash(b1)).unwrap().unwrap();
+
+	// the checkpoint is a header on top of block 1, signed by an unknown key.
+	let checkpoint = propose_sealed_header(&b1, 2, 2, &mut proposer_factory);
+	let snapshot = sc_consensus_epochs::snapshot::EpochSnapshot {
+		genesis_hash: genesis_header.hash(),
+		transitions: vec![sc_consensus_epochs::snapshot::Transition {
+			header: checkpoint.clone(),
+			justification: None,
+		}],
+	};
+
+	let mut verifier = BabeVerifier {
+		client: client.clone(),
+		inherent_data_providers: data.inherent_data_providers.clone(),
+		config: data.link.config.clone(),
+		epoch_changes: data.link.epoch_changes.clone(),
+		time_source: data.link.time_source.clone(),
+		checkpoints: sc_consensus_epochs::snapshot::Checkpoints::from_snapshot(&snapshot).unwrap(),
+		equivocation_reports: data.link.equivocation_reports.clone(),
+	};
+
+	// a sibling of block 1, below the checkpoint, signed by the same unknown key.
+	let sibling = propose_sealed_header(&genesis_header, 3, 2, &mut proposer_factory);
+	assert!(verifier.verify(BlockOrigin::NetworkInitialSync, sibling, None, None).is_err());
+
+	// the checkpoint itself is imported without checking its seal.
+	assert!(verifier.verify(BlockOrigin::NetworkInitialSync, checkpoint, None, None).is_ok());
+}
@@ -16,6 +16,8 @@
 
 //! Generic utilities for epoch-based consensus engines.
 
+pub mod snapshot;
+
 use std::{sync::Arc, ops::Add, collections::BTreeMap, borrow::{Borrow, BorrowMut}};
 use parking_lot::Mutex;
 use codec::{Encode, Decode};
@@ -227,7 +229,7 @@ impl<Hash, Number, E: Epoch> ViableEpochDescriptor<Hash, Number, E> {
 }
 
 /// Persisted epoch stored in EpochChanges.
-#[derive(Clone, Encode, Decode, Debug)]
+#[derive(Clone, Encode, Decode, Debug, PartialEq)]
 pub enum PersistedEpoch<E: Epoch> {
 	/// Genesis persisted epoch data. epoch_0, epoch_1.
 	Genesis(E, E),
@@ -255,6 +257,24 @@ pub enum PersistedEpochHeader<E: Epoch> {
 	Regular(EpochHeader<E>),
 }
 
+impl<E: Epoch> PersistedEpochHeader<E> {
+	/// The start slot of the first epoch of the header.
+	fn start_slot(&self) -> E::SlotNumber {
+		match self {
+			Self::Genesis(epoch_0, _) => epoch_0.start_slot,
+			Self::Regular(epoch_n) => epoch_n.start_slot,
+		}
+	}
+
+	/// The end slot of the last epoch of the header.
+	fn end_slot(&self) -> E::SlotNumber {
+		match self {
+			Self::Genesis(_, epoch_1) => epoch_1.end_slot,
+			Self::Regular(epoch_n) => epoch_n.end_slot,
+		}
+	}
+}
+
 impl<E: Epoch> Clone for PersistedEpochHeader<E> {
 	fn clone(&self) -> Self {
 		match self {
@@ -264,6 +284,40 @@ impl<E: Epoch> Clone for PersistedEpochHeader<E> {
 	}
 }
 
+/// Error when importing an epoch change with `EpochChanges::import`.
+#[derive(Debug, PartialEq)]
+pub enum ImportError<E> {
+	/// An epoch change was already signalled at the block.
+	Duplicate,
+	/// The signalled epoch starts before the end of the epoch signalled by an ancestor of the
+	/// block, i.e. it was already signalled on this chain.
+	OutOfOrder,
+	/// Error of the fork tree, or when checking the ancestry of the block.
+	Tree(fork_tree::Error<E>),
+}
+
+impl<E: std::error::Error> std::fmt::Display for ImportError<E> {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			ImportError::Duplicate => write!(f, "Epoch change already signalled at this block"),
+			ImportError::OutOfOrder =>
+				write!(f, "Epoch change starts before the end of the previous epoch"),
+			ImportError::Tree(err) => write!(f, "{}", err),
+		}
+	}
+}
+
+impl<E: std::error::Error> std::error::Error for ImportError<E> {}
+
+impl<E> From<fork_tree::Error<E>> for ImportError<E> {
+	fn from(err: fork_tree::Error<E>) -> Self {
+		match err {
+			fork_tree::Error::Duplicate => ImportError::Duplicate,
+			err => ImportError::Tree(err),
+		}
+	}
+}
+
 /// A fresh, incremented epoch to import into the underlying fork-tree.
 ///
 /// Create this with `ViableEpoch::increment`.
@@ -327,6 +381,51 @@ impl<Hash, Number, E: Epoch> EpochChanges<Hash, Number, E> where
 		Self::default()
 	}
 
+	/// Map the epoch changes from one storing data to a different one.
+	pub fn map<B, F>(self, mut f: F) -> EpochChanges<Hash, Number, B> where
+		B: Epoch<SlotNumber=E::SlotNumber>,
+		F: FnMut(&Hash, &Number, E) -> B,
+	{
+		EpochChanges {
+			inner: self.inner.map(&mut |_, _, header| match header {
+				PersistedEpochHeader::Genesis(epoch_0, epoch_1) => {
+					PersistedEpochHeader::Genesis(
+						EpochHeader {
+							start_slot: epoch_0.start_slot,
+							end_slot: epoch_0.end_slot,
+						},
+						EpochHeader {
+							start_slot: epoch_1.start_slot,
+							end_slot: epoch_1.end_slot,
+						},
+					)
+				},
+				PersistedEpochHeader::Regular(epoch_n) => {
+					PersistedEpochHeader::Regular(
+						EpochHeader {
+							start_slot: epoch_n.start_slot,
+							end_slot: epoch_n.end_slot,
+						},
+					)
+				},
+			}),
+			epochs: self.epochs.into_iter().map(|((hash, number), epoch)| {
+				let epoch = match epoch {
+					PersistedEpoch::Genesis(epoch_0, epoch_1) => {
+						PersistedEpoch::Genesis(
+							f(&hash, &number, epoch_0),
+							f(&hash, &number, epoch_1),
+						)
+					},
+					PersistedEpoch::Regular(epoch_n) => {
+						PersistedEpoch::Regular(f(&hash, &number, epoch_n))
+					},
+				};
+				((hash, number), epoch)
+			}).collect(),
+		}
+	}
+
 	/// Rebalances the tree of epoch changes so that it is sorted by length of
 	/// fork (longest fork first).
 	pub fn rebalance(&mut self) {
@@ -486,6 +585,61 @@ impl<Hash, Number, E: Epoch> EpochChanges<Hash, Number, E> where
 		Ok(descriptor.and_then(|des| self.epoch_data(&des, make_genesis)))
 	}
 
+	/// Finds the epoch data for a child of the given block, assuming the given slot number, and
+	/// the data of the epoch following it if it was already signalled on the chain of the block.
+	///
+	/// Like `epoch_data_for_child_of`, this ignores the fact that a genesis epoch might need to
+	/// be imported.
+	pub fn epochs_for_child_of<D: IsDescendentOfBuilder<Hash>, G>(
+		&self,
+		descendent_of_builder: D,
+		parent_hash: &Hash,
+		parent_number: Number,
+		slot_number: E::SlotNumber,
+		make_genesis: G,
+	) -> Result<Option<(E, Option<E>)>, fork_tree::Error<D::Error>> where
+		G: FnOnce(E::SlotNumber) -> E,
+		E: Clone,
+	{
+		let descriptor = self.descriptor_for_child_of(
+			&descendent_of_builder,
+			parent_hash,
+			parent_number,
+			slot_number,
+		)?;
+		let descriptor = match descriptor {
+			Some(descriptor) => descriptor,
+			None => return Ok(None),
+		};
+		let current = match self.epoch_data(&descriptor, make_genesis) {
+			Some(current) => current,
+			None => return Ok(None),
+		};
+
+		// the next epoch is the one a child would be in once the current one ended, if it was
+		// signalled already. otherwise the lookup finds the current epoch again.
+		let next = match descriptor {
+			ViableEpochDescriptor::UnimportedGenesis(_) => None,
+			ViableEpochDescriptor::Signaled(..) => {
+				let next = self.descriptor_for_child_of(
+					&descendent_of_builder,
+					parent_hash,
+					parent_number,
+					current.end_slot(),
+				)?;
+				match next {
+					Some(ViableEpochDescriptor::Signaled(identifier, _)) =>
+						self.epoch(&identifier)
+							.filter(|next| next.start_slot() >= current.end_slot())
+							.cloned(),
+					_ => None,
+				}
+			},
+		};
+
+		Ok(Some((current, next)))
+	}
+
 	/// Finds the epoch for a child of the given block, assuming the given slot number.
 	///
 	/// If the returned epoch is an `UnimportedGenesis` epoch, it should be imported into the
@@ -496,6 +650,21 @@ impl<Hash, Number, E: Epoch> EpochChanges<Hash, Number, E> where
 		parent_hash: &Hash,
 		parent_number: Number,
 		slot_number: E::SlotNumber,
+	) -> Result<Option<ViableEpochDescriptor<Hash, Number, E>>, fork_tree::Error<D::Error>> {
+		self.descriptor_for_child_of(
+			&descendent_of_builder,
+			parent_hash,
+			parent_number,
+			slot_number,
+		)
+	}
+
+	fn descriptor_for_child_of<D: IsDescendentOfBuilder<Hash>>(
+		&self,
+		descendent_of_builder: &D,
+		parent_hash: &Hash,
+		parent_number: Number,
+		slot_number: E::SlotNumber,
 	) -> Result<Option<ViableEpochDescriptor<Hash, Number, E>>, fork_tree::Error<D::Error>> {
 		// find_node_where will give you the node in the fork-tree which is an ancestor
 		// of the `parent_hash` by default. if the last epoch was signalled at the parent_hash,
@@ -557,6 +726,11 @@ impl<Hash, Number, E: Epoch> EpochChanges<Hash, Number, E> where
 	/// This assumes that the given block is prospective (i.e. has not been
 	/// imported yet), but its parent has. This is why the parent hash needs
 	/// to be provided.
+	///
+	/// Importing the same epoch change twice, e.g. when the block is imported
+	/// again, is a no-op. Fails if another epoch change was already signalled at
+	/// the block, or if the epoch starts before the end of the one signalled by
+	/// the closest ancestor of the block in the tree.
 	pub fn import<D: IsDescendentOfBuilder<Hash>>(
 		&mut self,
 		descendent_of_builder: D,
@@ -564,25 +738,38 @@ impl<Hash, Number, E: Epoch> EpochChanges<Hash, Number, E> where
 		number: Number,
 		parent_hash: Hash,
 		epoch: IncrementedEpoch<E>,
-	) -> Result<(), fork_tree::Error<D::Error>> {
+	) -> Result<(), ImportError<D::Error>> where E: PartialEq {
+		match self.epochs.get(&(hash, number)) {
+			Some(signalled) if *signalled == epoch.0 => return Ok(()),
+			Some(_) => return Err(ImportError::Duplicate),
+			None => {},
+		}
+
 		let is_descendent_of = descendent_of_builder
 			.build_is_descendent_of(Some((hash, parent_hash)));
 		let header = PersistedEpochHeader::<E>::from(&epoch.0);
 
-		let res = self.inner.import(
+		let ancestor = self.inner.find_node_where(
+			&hash,
+			&number,
+			&is_descendent_of,
+			&|_| true,
+		)?;
+		if let Some(ancestor) = ancestor {
+			if header.start_slot() < ancestor.data.end_slot() {
+				return Err(ImportError::OutOfOrder);
+			}
+		}
+
+		self.inner.import(
 			hash,
 			number,
 			header,
 			&is_descendent_of,
-		);
+		)?;
+		self.epochs.insert((hash, number), epoch.0);
 
-		match res {
-			Ok(_) | Err(fork_tree::Error::Duplicate) => {
-				self.epochs.insert((hash, number), epoch.0);
-				Ok(())
-			},
-			Err(e) => Err(e),
-		}
+		Ok(())
 	}
 
 	/// Return the inner fork tree.
@@ -916,4 +1103,164 @@ mod tests {
 			assert!(epoch_for_x_child_before_genesis.is_none());
 		}
 	}
+
+	#[test]
+	fn epoch_signalled_at_parent_is_only_used_on_its_fork() {
+		//
+		// 0 - A - B
+		//      \
+		//       — C
+		//
+		let is_descendent_of = |base: &Hash, block: &Hash| -> Result<bool, TestError> {
+			match (base, *block) {
+				(b"A", b) => Ok(b == *b"B" || b == *b"C"),
+				(b"0", _) => Ok(true),
+				_ => Ok(false),
+			}
+		};
+
+		let make_genesis = |slot| Epoch {
+			start_slot: slot,
+			duration: 100,
+		};
+
+		let mut epoch_changes = EpochChanges::<_, _, Epoch>::new();
+		let genesis_descriptor = epoch_changes.epoch_descriptor_for_child_of(
+			&is_descendent_of,
+			b"0",
+			0,
+			100,
+		).unwrap().unwrap();
+		let epoch_1 = epoch_changes.viable_epoch(&genesis_descriptor, &make_genesis)
+			.unwrap()
+			.increment(());
+		epoch_changes.import(&is_descendent_of, *b"A", 1, *b"0", epoch_1).unwrap();
+
+		// B, the first block of epoch_1, signals epoch_2. Its sibling C doesn't.
+		let descriptor = epoch_changes.epoch_descriptor_for_child_of(
+			&is_descendent_of,
+			b"A",
+			1,
+			200,
+		).unwrap().unwrap();
+		let epoch_1 = epoch_changes.epoch_data(&descriptor, &make_genesis).unwrap();
+		let epoch_2 = epoch_changes.viable_epoch(&descriptor, &make_genesis)
+			.unwrap()
+			.increment(());
+		let epoch_2_data = epoch_2.as_ref().clone();
+		epoch_changes.import(&is_descendent_of, *b"B", 2, *b"A", epoch_2).unwrap();
+
+		// the epoch signalled exactly at the parent block is found.
+		let child_of_b = epoch_changes.epoch_data_for_child_of(
+			&is_descendent_of,
+			b"B",
+			2,
+			300,
+			&make_genesis,
+		).unwrap().unwrap();
+		assert_eq!(child_of_b, epoch_2_data);
+
+		// but only once it started.
+		let child_of_b = epoch_changes.epoch_data_for_child_of(
+			&is_descendent_of,
+			b"B",
+			2,
+			299,
+			&make_genesis,
+		).unwrap().unwrap();
+		assert_eq!(child_of_b, epoch_1);
+
+		// the sibling fork never saw the signal of B.
+		let child_of_c = epoch_changes.epoch_data_for_child_of(
+			&is_descendent_of,
+			b"C",
+			2,
+			300,
+			&make_genesis,
+		).unwrap().unwrap();
+		assert_eq!(child_of_c, epoch_1);
+
+		// the next epoch is only known on the fork it was signalled on.
+		let epochs = epoch_changes.epochs_for_child_of(
+			&is_descendent_of,
+			b"B",
+			2,
+			250,
+			&make_genesis,
+		).unwrap();
+		assert_eq!(epochs, Some((epoch_1.clone(), Some(epoch_2_data))));
+		let epochs = epoch_changes.epochs_for_child_of(
+			&is_descendent_of,
+			b"C",
+			2,
+			250,
+			&make_genesis,
+		).unwrap();
+		assert_eq!(epochs, Some((epoch_1, None)));
+	}
+
+	#[test]
+	fn duplicate_and_out_of_order_signals_are_rejected() {
+		//
+		// 0 - A - B - C
+		//
+		let is_descendent_of = |base: &Hash, block: &Hash| -> Result<bool, TestError> {
+			match (base, *block) {
+				(b"A", b) => Ok(b == *b"B" || b == *b"C"),
+				(b"B", b) => Ok(b == *b"C"),
+				(b"0", _) => Ok(true),
+				_ => Ok(false),
+			}
+		};
+
+		let make_genesis = |slot| Epoch {
+			start_slot: slot,
+			duration: 100,
+		};
+
+		let mut epoch_changes = EpochChanges::<_, _, Epoch>::new();
+		let incremented_epoch = |epoch_changes: &EpochChanges<_, _, Epoch>, parent, number, slot| {
+			let descriptor = epoch_changes.epoch_descriptor_for_child_of(
+				&is_descendent_of,
+				parent,
+				number,
+				slot,
+			).unwrap().unwrap();
+			epoch_changes.viable_epoch(&descriptor, &make_genesis).unwrap().increment(())
+		};
+
+		// A signals epoch_1, starting at slot 200.
+		let epoch = incremented_epoch(&epoch_changes, b"0", 0, 100);
+		epoch_changes.import(&is_descendent_of, *b"A", 1, *b"0", epoch).unwrap();
+
+		// importing A again is a no-op.
+		let epoch = incremented_epoch(&epoch_changes, b"0", 0, 100);
+		epoch_changes.import(&is_descendent_of, *b"A", 1, *b"0", epoch).unwrap();
+		assert_eq!(epoch_changes.tree().iter().count(), 1);
+
+		// another epoch change at A is rejected.
+		let mut epoch = incremented_epoch(&epoch_changes, b"0", 0, 100);
+		match epoch.0 {
+			PersistedEpoch::Genesis(_, ref mut epoch_1) => epoch_1.duration = 50,
+			PersistedEpoch::Regular(ref mut epoch_n) => epoch_n.duration = 50,
+		}
+		assert_eq!(
+			epoch_changes.import(&is_descendent_of, *b"A", 1, *b"0", epoch),
+			Err(ImportError::Duplicate),
+		);
+
+		// B is still in epoch_0, so it signals epoch_1 again.
+		let epoch = incremented_epoch(&epoch_changes, b"A", 1, 150);
+		assert_eq!(epoch.as_ref().start_slot, 200);
+		assert_eq!(
+			epoch_changes.import(&is_descendent_of, *b"B", 2, *b"A", epoch),
+			Err(ImportError::OutOfOrder),
+		);
+
+		// C is in epoch_1, and signals epoch_2.
+		let epoch = incremented_epoch(&epoch_changes, b"B", 2, 200);
+		assert_eq!(epoch.as_ref().start_slot, 300);
+		epoch_changes.import(&is_descendent_of, *b"C", 3, *b"B", epoch).unwrap();
+		assert_eq!(epoch_changes.tree().iter().count(), 2);
+	}
 }
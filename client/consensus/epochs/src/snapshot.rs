@@ -0,0 +1,229 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signed snapshots of the epoch and authority set transitions of a chain.
+//!
+//! A full node exports the finalized headers enacting consensus changes, which are the ones
+//! carrying a consensus digest, along with their justifications. A light client trusting the
+//! signer of the snapshot imports the hashes of these headers as checkpoints, and then imports
+//! the checkpointed headers without verifying their seals.
+//!
+//! A header at the number of a checkpoint is rejected unless its hash matches. Headers are
+//! imported by increasing number, so the ones between the checkpoints can't be proven to be their
+//! ancestors when they are imported: they are verified as usual.
+
+use codec::{Encode, Decode};
+use sc_client_api::backend::AuxStore;
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use sp_core::{crypto::Pair, sr25519};
+use sp_runtime::{Justification, traits::{Block as BlockT, Header as HeaderT, NumberFor}};
+
+/// Key of the checkpoints in auxiliary storage.
+const CHECKPOINTS_KEY: &[u8] = b"epoch_snapshot_checkpoints";
+
+/// Prefix of the signed snapshots, so that their signatures can't be used for anything else.
+const SIGNING_CONTEXT: &[u8] = b"substrate-epoch-snapshot:";
+
+/// A header enacting a change of epoch or authority set.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct Transition<Header> {
+	/// The header itself.
+	pub header: Header,
+	/// Justification of the finality of the header, if the node has one.
+	pub justification: Option<Justification>,
+}
+
+/// The epoch and authority set transitions of a chain.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct EpochSnapshot<Hash, Header> {
+	/// Hash of the genesis block of the chain.
+	pub genesis_hash: Hash,
+	/// The transitions, by increasing number. The last one is the last finalized header of the
+	/// exporting node, whether it enacts a change or not.
+	pub transitions: Vec<Transition<Header>>,
+}
+
+/// `EpochSnapshot` of the chain of `Block`.
+pub type EpochSnapshotFor<Block> =
+	EpochSnapshot<<Block as BlockT>::Hash, <Block as BlockT>::Header>;
+
+fn signing_payload(snapshot: &[u8]) -> Vec<u8> {
+	[SIGNING_CONTEXT, snapshot].concat()
+}
+
+/// An encoded `EpochSnapshot`, signed with an sr25519 key.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct SignedEpochSnapshot {
+	/// The encoded snapshot.
+	pub snapshot: Vec<u8>,
+	/// Public key of the signer.
+	pub signer: sr25519::Public,
+	/// Signature of the encoded snapshot.
+	pub signature: sr25519::Signature,
+}
+
+impl SignedEpochSnapshot {
+	/// Signs `snapshot` with `pair`.
+	pub fn sign<Hash: Encode, Header: Encode>(
+		snapshot: &EpochSnapshot<Hash, Header>,
+		pair: &sr25519::Pair,
+	) -> Self {
+		let snapshot = snapshot.encode();
+		let signature = pair.sign(&signing_payload(&snapshot));
+		SignedEpochSnapshot { snapshot, signer: pair.public(), signature }
+	}
+
+	/// Checks that the snapshot was signed by `signer`, and decodes it.
+	pub fn verify<Hash: Decode, Header: Decode>(
+		&self,
+		signer: &sr25519::Public,
+	) -> Result<EpochSnapshot<Hash, Header>, String> {
+		if &self.signer != signer {
+			return Err(format!("Snapshot signed by {} instead of {}", self.signer, signer));
+		}
+		if !sr25519::Pair::verify(&self.signature, signing_payload(&self.snapshot), signer) {
+			return Err("Invalid snapshot signature".into());
+		}
+
+		EpochSnapshot::decode(&mut &self.snapshot[..])
+			.map_err(|e| format!("Invalid snapshot: {}", e.what()))
+	}
+}
+
+/// Hashes of the headers of a snapshot, by increasing number.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct Checkpoints<Hash, Number> {
+	hashes: Vec<(Number, Hash)>,
+}
+
+/// `Checkpoints` of the chain of `Block`.
+pub type CheckpointsFor<Block> = Checkpoints<<Block as BlockT>::Hash, NumberFor<Block>>;
+
+impl<Hash, Number> Default for Checkpoints<Hash, Number> {
+	fn default() -> Self {
+		Checkpoints { hashes: Vec::new() }
+	}
+}
+
+impl<Hash: PartialEq, Number: Ord + Copy> Checkpoints<Hash, Number> {
+	/// Checkpoints of the headers of `snapshot`, which must be sorted by increasing number.
+	pub fn from_snapshot<Header>(
+		snapshot: &EpochSnapshot<Hash, Header>,
+	) -> Result<Self, String> where
+		Header: HeaderT<Hash = Hash, Number = Number>,
+	{
+		let hashes = snapshot.transitions.iter()
+			.map(|transition| (*transition.header.number(), transition.header.hash()))
+			.collect::<Vec<_>>();
+		if hashes.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+			return Err("Snapshot transitions are not sorted by increasing number".into());
+		}
+
+		Ok(Checkpoints { hashes })
+	}
+
+	/// Returns whether there is no checkpoint.
+	pub fn is_empty(&self) -> bool {
+		self.hashes.is_empty()
+	}
+
+	/// Returns whether the header with the given `number` and `hash` is a checkpoint, meaning
+	/// that it doesn't need verifying.
+	///
+	/// Fails if there is a checkpoint at `number` with another hash.
+	pub fn covers(&self, number: Number, hash: &Hash) -> Result<bool, String> {
+		match self.hashes.binary_search_by_key(&number, |(number, _)| *number) {
+			Ok(index) if &self.hashes[index].1 == hash => Ok(true),
+			Ok(_) => Err("Header doesn't match the checkpoint at its number".into()),
+			Err(_) => Ok(false),
+		}
+	}
+}
+
+/// Loads the checkpoints from auxiliary storage. Empty if none were imported.
+pub fn load_checkpoints<Block: BlockT, B: AuxStore>(
+	backend: &B,
+) -> ClientResult<CheckpointsFor<Block>> {
+	match backend.get_aux(CHECKPOINTS_KEY)? {
+		Some(encoded) => Checkpoints::decode(&mut &encoded[..]).map_err(|e| ClientError::Backend(
+			format!("Snapshot checkpoints DB is corrupted. Decode error: {}", e.what()),
+		)),
+		None => Ok(Default::default()),
+	}
+}
+
+/// Writes the checkpoints to auxiliary storage, replacing the previous ones.
+pub fn write_checkpoints<Block: BlockT, B: AuxStore>(
+	backend: &B,
+	checkpoints: &CheckpointsFor<Block>,
+) -> ClientResult<()> {
+	let encoded = checkpoints.encode();
+	backend.insert_aux(&[(CHECKPOINTS_KEY, encoded.as_slice())], &[])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::testing::{Header, H256};
+
+	fn header(number: u64) -> Header {
+		Header {
+			parent_hash: H256::repeat_byte(number as u8),
+			number,
+			state_root: Default::default(),
+			extrinsics_root: Default::default(),
+			digest: Default::default(),
+		}
+	}
+
+	fn snapshot(numbers: &[u64]) -> EpochSnapshot<H256, Header> {
+		EpochSnapshot {
+			genesis_hash: H256::repeat_byte(0xff),
+			transitions: numbers.iter()
+				.map(|number| Transition { header: header(*number), justification: None })
+				.collect(),
+		}
+	}
+
+	#[test]
+	fn signed_snapshot_is_verified() {
+		let pair = sr25519::Pair::from_seed(&[1; 32]);
+		let other = sr25519::Pair::from_seed(&[2; 32]);
+		let signed = SignedEpochSnapshot::sign(&snapshot(&[10, 20]), &pair);
+
+		assert_eq!(signed.verify(&pair.public()), Ok(snapshot(&[10, 20])));
+		assert!(signed.verify::<H256, Header>(&other.public()).is_err());
+
+		let mut tampered = signed.clone();
+		tampered.snapshot = snapshot(&[10, 21]).encode();
+		assert!(tampered.verify::<H256, Header>(&pair.public()).is_err());
+	}
+
+	#[test]
+	fn checkpoints_cover_the_checkpointed_headers_only() {
+		let checkpoints = Checkpoints::from_snapshot(&snapshot(&[10, 20])).unwrap();
+
+		// a header of a sibling fork below a checkpoint is not trusted.
+		assert_eq!(checkpoints.covers(5, &H256::zero()), Ok(false));
+		assert_eq!(checkpoints.covers(15, &header(15).hash()), Ok(false));
+		assert_eq!(checkpoints.covers(10, &header(10).hash()), Ok(true));
+		assert!(checkpoints.covers(10, &header(11).hash()).is_err());
+		assert_eq!(checkpoints.covers(20, &header(20).hash()), Ok(true));
+		assert_eq!(checkpoints.covers(21, &H256::zero()), Ok(false));
+
+		assert!(Checkpoints::from_snapshot(&snapshot(&[20, 10])).is_err());
+	}
+}
@@ -0,0 +1,265 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Opt-in recorder of consensus artifacts, for research on the behaviour of live networks.
+//!
+//! Like telemetry, the recorder is global to the process: consensus engines report events with
+//! [`record`], which does nothing until a recorder is installed with [`init`]. Each event is
+//! written as a line of JSON to `consensus.ndjson` in the configured directory. Once that file
+//! exceeds the maximum size it is rotated to `consensus.ndjson.1`, shifting older files up to
+//! the maximum number kept.
+//!
+//! Recording never blocks the consensus engines: the events are sent through a bounded channel
+//! to the task returned by [`init`], which writes them to disk. The events are dropped while the
+//! channel is full.
+
+use std::{
+	fs::{self, File, OpenOptions}, io::{self, Write, BufWriter}, path::PathBuf,
+	sync::{Arc, atomic::{AtomicU64, Ordering}}, time::{SystemTime, UNIX_EPOCH},
+};
+use futures::{prelude::*, channel::mpsc};
+use log::warn;
+use parking_lot::{Mutex, RwLock};
+use sp_core::{blake2_256, hexdisplay::HexDisplay};
+
+/// Name of the file events are written to.
+const FILE_NAME: &str = "consensus.ndjson";
+
+/// Number of events waiting to be written above which new ones are dropped.
+const CHANNEL_SIZE: usize = 1024;
+
+/// Configuration of the consensus recorder.
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+	/// Directory the events are written to.
+	pub path: PathBuf,
+	/// Size in bytes above which the file is rotated.
+	pub max_file_size: u64,
+	/// Number of rotated files kept.
+	pub max_files: usize,
+	/// Whether to replace authority keys with salted hashes, unlinkable across runs.
+	pub anonymize: bool,
+}
+
+struct Writer {
+	config: RecorderConfig,
+	file: BufWriter<File>,
+	size: u64,
+}
+
+impl Writer {
+	fn open(config: RecorderConfig) -> io::Result<Self> {
+		fs::create_dir_all(&config.path)?;
+		let file = OpenOptions::new().create(true).append(true).open(config.path.join(FILE_NAME))?;
+		let size = file.metadata()?.len();
+		Ok(Writer { config, file: BufWriter::new(file), size })
+	}
+
+	fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+		if self.size > 0 && self.size + line.len() as u64 >= self.config.max_file_size {
+			self.rotate()?;
+		}
+
+		self.file.write_all(line)?;
+		self.file.write_all(b"\n")?;
+		self.file.flush()?;
+		self.size += line.len() as u64 + 1;
+		Ok(())
+	}
+
+	fn rotate(&mut self) -> io::Result<()> {
+		self.file.flush()?;
+
+		let path = |index: usize| self.config.path.join(format!("{}.{}", FILE_NAME, index));
+		let current = self.config.path.join(FILE_NAME);
+		if self.config.max_files == 0 {
+			fs::remove_file(&current)?;
+		} else {
+			for index in (1..self.config.max_files).rev() {
+				if path(index).exists() {
+					fs::rename(path(index), path(index + 1))?;
+				}
+			}
+			fs::rename(&current, path(1))?;
+		}
+
+		self.file = BufWriter::new(File::create(&current)?);
+		self.size = 0;
+		Ok(())
+	}
+}
+
+/// Write the events received from `receiver` with `writer`, until all the senders are dropped.
+fn write_events(
+	mut writer: Writer,
+	receiver: mpsc::Receiver<Vec<u8>>,
+) -> impl Future<Output = ()> + Send {
+	receiver.for_each(move |line| {
+		if let Err(e) = writer.write_line(&line) {
+			warn!(target: "consensus-recorder", "Failed to record event: {}", e);
+		}
+		future::ready(())
+	})
+}
+
+struct Recorder {
+	sender: Mutex<mpsc::Sender<Vec<u8>>>,
+	salt: Option<[u8; 32]>,
+	dropped: AtomicU64,
+}
+
+impl Recorder {
+	/// Queue `line` for writing, dropping it if the writing task lags behind.
+	fn send(&self, line: Vec<u8>) {
+		if let Err(e) = self.sender.lock().try_send(line) {
+			if e.is_full() {
+				let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+				if dropped.is_power_of_two() {
+					warn!(
+						target: "consensus-recorder",
+						"Recording lags behind, {} events dropped so far",
+						dropped,
+					);
+				}
+			}
+		}
+	}
+}
+
+lazy_static::lazy_static! {
+	static ref RECORDER: RwLock<Option<Arc<Recorder>>> = RwLock::new(None);
+}
+
+fn recorder() -> Option<Arc<Recorder>> {
+	RECORDER.read().clone()
+}
+
+/// Install the global recorder, replacing any previous one.
+///
+/// Returns the task writing the events to disk, which must be spawned.
+pub fn init(config: RecorderConfig) -> io::Result<impl Future<Output = ()> + Send> {
+	let salt = if config.anonymize { Some(rand::random()) } else { None };
+	let writer = Writer::open(config)?;
+	let (sender, receiver) = mpsc::channel(CHANNEL_SIZE);
+	*RECORDER.write() = Some(Arc::new(Recorder {
+		sender: Mutex::new(sender),
+		salt,
+		dropped: AtomicU64::new(0),
+	}));
+	Ok(write_events(writer, receiver))
+}
+
+/// Whether a recorder is installed.
+pub fn is_enabled() -> bool {
+	RECORDER.read().is_some()
+}
+
+/// Record an event of the given consensus engine.
+///
+/// `event` is only called if a recorder is installed.
+pub fn record<F>(engine: &str, kind: &str, event: F) where
+	F: FnOnce() -> serde_json::Value,
+{
+	let recorder = match recorder() {
+		Some(recorder) => recorder,
+		None => return,
+	};
+
+	let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+	let line = serde_json::json!({
+		"time": time as u64,
+		"engine": engine,
+		"kind": kind,
+		"event": event(),
+	});
+
+	match serde_json::to_vec(&line) {
+		Ok(line) => recorder.send(line),
+		Err(e) => warn!(
+			target: "consensus-recorder",
+			"Failed to record {} {} event: {}",
+			engine,
+			kind,
+			e,
+		),
+	}
+}
+
+/// The representation of an authority key in the recorded events: the key itself in hex, or a
+/// salted hash of it if the recorder anonymizes events.
+pub fn authority_id(public: &[u8]) -> String {
+	match recorder().and_then(|recorder| recorder.salt) {
+		Some(salt) => {
+			let hash = blake2_256(&[&salt[..], public].concat());
+			format!("anon-{}", HexDisplay::from(&&hash[..8]))
+		},
+		None => format!("0x{}", HexDisplay::from(&public)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config(dir: &tempfile::TempDir, max_file_size: u64) -> RecorderConfig {
+		RecorderConfig {
+			path: dir.path().to_path_buf(),
+			max_file_size,
+			max_files: 2,
+			anonymize: false,
+		}
+	}
+
+	#[test]
+	fn rotates_files() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut writer = Writer::open(config(&dir, 10)).unwrap();
+
+		for line in &[b"first", b"secnd", b"third", b"forth"] {
+			writer.write_line(&line[..]).unwrap();
+		}
+
+		let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+		assert_eq!(read(FILE_NAME), "forth\n");
+		assert_eq!(read("consensus.ndjson.1"), "third\n");
+		assert_eq!(read("consensus.ndjson.2"), "secnd\n");
+		assert!(!dir.path().join("consensus.ndjson.3").exists());
+	}
+
+	#[test]
+	fn drops_events_instead_of_blocking() {
+		let dir = tempfile::tempdir().unwrap();
+		let writer = Writer::open(config(&dir, 1024)).unwrap();
+		let (sender, receiver) = mpsc::channel(1);
+		let recorder = Recorder {
+			sender: Mutex::new(sender),
+			salt: None,
+			dropped: AtomicU64::new(0),
+		};
+
+		// nothing is written until the task runs, so the channel fills up.
+		for line in &[b"first", b"secnd", b"third", b"forth"] {
+			recorder.send(line.to_vec());
+		}
+		assert_eq!(recorder.dropped.load(Ordering::Relaxed), 2);
+
+		drop(recorder);
+		futures::executor::block_on(write_events(writer, receiver));
+
+		let written = fs::read_to_string(dir.path().join(FILE_NAME)).unwrap();
+		assert_eq!(written, "first\nsecnd\n");
+	}
+}
@@ -25,8 +25,12 @@
 
 mod slots;
 mod aux_schema;
+mod missed;
+mod metrics;
 
 pub use slots::{SignedDuration, SlotInfo};
+pub use missed::{MissedSlot, MissedSlotReason, MissedSlots};
+pub use metrics::{BlockOutcome, SlotMetrics};
 use slots::Slots;
 pub use aux_schema::{check_equivocation, MAX_SLOT_CAPACITY, PRUNING_BOUND};
 
@@ -39,9 +43,13 @@ use log::{debug, error, info, warn};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{Block as BlockT, Header, HashFor, NumberFor};
 use sp_api::{ProvideRuntimeApi, ApiRef};
-use std::{fmt::Debug, ops::Deref, pin::Pin, sync::Arc, time::{Instant, Duration}};
+use std::{
+	convert::TryFrom, fmt::Debug, ops::Deref, pin::Pin, sync::Arc, time::{Instant, Duration},
+};
 use sc_telemetry::{telemetry, CONSENSUS_DEBUG, CONSENSUS_WARN, CONSENSUS_INFO};
+use sc_client_api::health::{HealthEvent, HealthSink};
 use parking_lot::Mutex;
+use serde::{Serialize, Deserialize};
 
 /// The changes that need to applied to the storage to create the state for a block.
 ///
@@ -105,6 +113,37 @@ pub trait SimpleSlotWorker<B: BlockT> {
 		epoch_data: &Self::EpochData,
 	) -> Option<Self::Claim>;
 
+	/// Whether one of our keys is expected to author the given slot, although `claim_slot` can't
+	/// claim it, e.g. because the key can't be loaded from the keystore.
+	///
+	/// Only called to diagnose the slots that aren't claimed, if `missed_slots` or `metrics` is
+	/// set.
+	fn is_scheduled(
+		&self,
+		_header: &B::Header,
+		_slot_number: u64,
+		_epoch_data: &Self::EpochData,
+	) -> bool {
+		false
+	}
+
+	/// Log of the scheduled slots that weren't authored. `None` if not kept.
+	fn missed_slots(&self) -> Option<MissedSlots> {
+		None
+	}
+
+	/// Prometheus metrics of the slots. `None` if not exported.
+	fn metrics(&self) -> Option<SlotMetrics> {
+		None
+	}
+
+	/// Records that the given slot isn't authored for `reason`, if it was `scheduled`.
+	fn report_missed_slot(&self, slot_number: u64, scheduled: bool, reason: MissedSlotReason) {
+		if let (true, Some(missed_slots)) = (scheduled, self.missed_slots()) {
+			missed_slots.record(slot_number, reason);
+		}
+	}
+
 	/// Return the pre digest data to include in a block authored with the given claim.
 	fn pre_digest_data(
 		&self,
@@ -131,12 +170,24 @@ pub trait SimpleSlotWorker<B: BlockT> {
 	/// Whether to force authoring if offline.
 	fn force_authoring(&self) -> bool;
 
+	/// Whether to skip authoring on top of `chain_head`, e.g. because it is too far ahead of the
+	/// finalized block.
+	fn should_backoff(&self, _chain_head: &B::Header) -> bool {
+		false
+	}
+
 	/// Returns a handle to a `SyncOracle`.
 	fn sync_oracle(&mut self) -> &mut Self::SyncOracle;
 
 	/// Returns a `Proposer` to author on top of the given block.
 	fn proposer(&mut self, block: &B::Header) -> Self::CreateProposer;
 
+	/// Sink the claimed slots are reported to, for the health gauges of the service. `None` if
+	/// not reported.
+	fn health_sink(&self) -> Option<HealthSink> {
+		None
+	}
+
 	/// Remaining duration of the slot.
 	fn slot_remaining_duration(&self, slot_info: &SlotInfo) -> Duration {
 		let now = Instant::now();
@@ -147,6 +198,18 @@ pub trait SimpleSlotWorker<B: BlockT> {
 		}
 	}
 
+	/// Proportion of the slot given to the proposer to build a block. The rest of the slot is left
+	/// to import and announce it.
+	fn proposal_slot_proportion(&self) -> SlotProportion {
+		SlotProportion::default()
+	}
+
+	/// Remaining duration for the proposer to build a block, until the
+	/// `proposal_slot_proportion` of the slot has elapsed.
+	fn proposal_remaining_duration(&self, slot_info: &SlotInfo) -> Duration {
+		self.proposal_slot_proportion().remaining_duration(slot_info, Instant::now())
+	}
+
 	/// Remaining duration for proposing. None means unlimited.
 	fn proposing_remaining_duration(
 		&self,
@@ -173,6 +236,18 @@ pub trait SimpleSlotWorker<B: BlockT> {
 					"Skipping proposal slot {} since our current view is {}",
 					slot_number, slot_now,
 				);
+				let scheduled = self.missed_slots().is_some() &&
+					self.epoch_data(&chain_head, slot_number)
+						.map(|epoch_data|
+							self.claim_slot(&chain_head, slot_number, &epoch_data).is_some() ||
+								self.is_scheduled(&chain_head, slot_number, &epoch_data)
+						)
+						.unwrap_or(false);
+				self.report_missed_slot(
+					slot_number,
+					scheduled,
+					MissedSlotReason::ClockDrift { current_slot: slot_now },
+				);
 
 				return Box::pin(future::ready(Ok(())));
 			}
@@ -193,6 +268,18 @@ pub trait SimpleSlotWorker<B: BlockT> {
 			}
 		};
 
+		// The slot is claimed once, and the claim tells whether it is scheduled: the VRF of the
+		// slot isn't evaluated again.
+		let claim = self.claim_slot(&chain_head, slot_number, &epoch_data);
+		let metrics = self.metrics();
+		let diagnosed = metrics.is_some() || self.missed_slots().is_some();
+		let scheduled = claim.is_some() ||
+			(diagnosed && self.is_scheduled(&chain_head, slot_number, &epoch_data));
+		let eligible_metrics = metrics.as_ref().filter(|_| scheduled);
+		if let Some(metrics) = eligible_metrics {
+			metrics.on_eligible();
+		}
+
 		let authorities_len = self.authorities_len(&epoch_data);
 
 		if !self.force_authoring() &&
@@ -205,14 +292,65 @@ pub trait SimpleSlotWorker<B: BlockT> {
 				"slots.skipping_proposal_slot";
 				"authorities_len" => authorities_len,
 			);
+			self.report_missed_slot(slot_number, scheduled, MissedSlotReason::NoPeers);
 
 			return Box::pin(future::ready(Ok(())));
 		}
 
-		let claim = match self.claim_slot(&chain_head, slot_number, &epoch_data) {
-			None => return Box::pin(future::ready(Ok(()))),
+		// Connected peers not sending us blocks could be on another fork, or not following the
+		// chain at all: authoring alone would only grow a fork of our own.
+		if !self.force_authoring() &&
+			self.sync_oracle().is_isolated() &&
+			authorities_len.map(|a| a > 1).unwrap_or(false)
+		{
+			debug!(
+				target: self.logging_target(),
+				"Skipping proposal slot. No block received from the network recently.",
+			);
+			self.report_missed_slot(slot_number, scheduled, MissedSlotReason::Isolated);
+
+			return Box::pin(future::ready(Ok(())));
+		}
+
+		// Authoring more blocks while they aren't finalized would only grow the chain that has to
+		// be kept around until finality resumes.
+		if self.should_backoff(&chain_head) {
+			debug!(
+				target: self.logging_target(),
+				"Skipping proposal slot. Block #{} is too far ahead of the finalized block.",
+				chain_head.number(),
+			);
+			telemetry!(
+				CONSENSUS_DEBUG;
+				"slots.backing_off";
+				"best_number" => ?chain_head.number(),
+			);
+			self.report_missed_slot(slot_number, scheduled, MissedSlotReason::FinalityLag);
+			if let Some(metrics) = eligible_metrics {
+				metrics.on_backed_off();
+			}
+
+			return Box::pin(future::ready(Ok(())));
+		}
+
+		let claim = match claim {
+			None => {
+				self.report_missed_slot(
+					slot_number,
+					scheduled,
+					MissedSlotReason::KeystoreUnavailable,
+				);
+				return Box::pin(future::ready(Ok(())));
+			},
 			Some(claim) => claim,
 		};
+		if let Some(metrics) = &metrics {
+			metrics.on_claimed();
+		}
+
+		if let Some(health_sink) = self.health_sink() {
+			let _ = health_sink.unbounded_send(HealthEvent::SlotClaimed);
+		}
 
 		debug!(
 			target: self.logging_target(), "Starting authorship at slot {}; timestamp = {}",
@@ -235,7 +373,7 @@ pub trait SimpleSlotWorker<B: BlockT> {
 			err
 		});
 
-		let slot_remaining_duration = self.slot_remaining_duration(&slot_info);
+		let proposal_remaining_duration = self.proposal_remaining_duration(&slot_info);
 		let proposing_remaining_duration = self.proposing_remaining_duration(&chain_head, &slot_info);
 		let logs = self.pre_digest_data(slot_number, &claim);
 
@@ -245,7 +383,7 @@ pub trait SimpleSlotWorker<B: BlockT> {
 			sp_runtime::generic::Digest {
 				logs,
 			},
-			slot_remaining_duration,
+			proposal_remaining_duration,
 			RecordProof::No,
 		).map_err(|e| sp_consensus::Error::ClientImport(format!("{:?}", e))));
 
@@ -254,10 +392,34 @@ pub trait SimpleSlotWorker<B: BlockT> {
 			None => Box::new(future::pending()),
 		};
 
+		// The slot was claimed, so any failure from here on misses a scheduled slot.
+		let missed_slots = self.missed_slots();
+		let record_missed = move |reason: MissedSlotReason| if let Some(missed_slots) = &missed_slots {
+			missed_slots.record(slot_number, reason);
+		};
+		let record_missed_on_import = record_missed.clone();
+		let metrics_on_import = metrics.clone();
+		let proposing_started = Instant::now();
+
 		let proposal_work =
 			Box::new(futures::future::select(proposing, delay).map(move |v| match v {
-				futures::future::Either::Left((b, _)) => b.map(|b| (b, claim)),
+				futures::future::Either::Left((b, _)) => {
+					if let Some(metrics) = &metrics {
+						metrics.on_proposal(proposing_started.elapsed());
+						if b.is_err() {
+							metrics.on_block(BlockOutcome::Failed);
+						}
+					}
+					b.map(|b| (b, claim)).map_err(|err| {
+						record_missed(MissedSlotReason::AuthoringFailed(format!("{:?}", err)));
+						err
+					})
+				},
 				futures::future::Either::Right(_) => {
+					if let Some(metrics) = &metrics {
+						metrics.on_proposal(proposing_started.elapsed());
+						metrics.on_block(BlockOutcome::Discarded);
+					}
 					info!("Discarding proposal for slot {}; block production took too long", slot_number);
 					// If the node was compiled with debug, tell the user to use release optimizations.
 					#[cfg(build_type="debug")]
@@ -265,6 +427,7 @@ pub trait SimpleSlotWorker<B: BlockT> {
 					telemetry!(CONSENSUS_INFO; "slots.discarding_proposal_took_too_long";
 						"slot" => slot_number,
 					);
+					record_missed(MissedSlotReason::ProposalTimeout);
 					Err(sp_consensus::Error::ClientImport("Timeout in the Slots proposer".into()))
 				},
 			}));
@@ -301,7 +464,15 @@ pub trait SimpleSlotWorker<B: BlockT> {
 				"hash_previously" => ?header_hash,
 			);
 
-			if let Err(err) = block_import.lock().import_block(block_import_params, Default::default()) {
+			let imported = block_import.lock()
+				.import_block(block_import_params, Default::default());
+			if let Some(metrics) = &metrics_on_import {
+				metrics.on_block(match &imported {
+					Ok(_) => BlockOutcome::Imported,
+					Err(_) => BlockOutcome::Failed,
+				});
+			}
+			if let Err(err) = imported {
 				warn!(target: logging_target,
 					"Error with block built on {:?}: {:?}",
 					parent_hash,
@@ -311,6 +482,7 @@ pub trait SimpleSlotWorker<B: BlockT> {
 				telemetry!(CONSENSUS_WARN; "slots.err_with_block_built_on";
 					"hash" => ?parent_hash, "err" => ?err,
 				);
+				record_missed_on_import(MissedSlotReason::AuthoringFailed(format!("{:?}", err)));
 			}
 		}))
 	}
@@ -483,3 +655,99 @@ impl<T: Clone> SlotDuration<T> {
 		self.0.clone()
 	}
 }
+
+/// A proportion of the slot, greater than 0 and at most 1.
+///
+/// The proposer stops pulling transactions from the pool once this proportion of the slot has
+/// elapsed, e.g. at 80% of the slot with `0.8`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "f32", into = "f32")]
+pub struct SlotProportion(f32);
+
+impl SlotProportion {
+	/// Creates a proportion of the slot. Fails unless `proportion` is greater than 0 and at most 1.
+	pub fn new(proportion: f32) -> Result<Self, String> {
+		if proportion > 0.0 && proportion <= 1.0 {
+			Ok(SlotProportion(proportion))
+		} else {
+			Err(format!("Invalid slot proportion, expected a number in (0, 1]: {}", proportion))
+		}
+	}
+
+	/// Returns the proportion, greater than 0 and at most 1.
+	pub fn get(&self) -> f32 {
+		self.0
+	}
+
+	/// Time left at `now` until the proportion of the slot has elapsed.
+	fn remaining_duration(&self, slot_info: &SlotInfo, now: Instant) -> Duration {
+		let reserved = Duration::from_millis(slot_info.duration).mul_f32(1.0 - self.0);
+		match slot_info.ends_at.checked_sub(reserved) {
+			Some(end) if now < end => end.duration_since(now),
+			_ => Duration::from_millis(0),
+		}
+	}
+}
+
+impl Default for SlotProportion {
+	/// Two thirds of the slot are given to build the block, and the last third to import and
+	/// announce it.
+	fn default() -> Self {
+		SlotProportion(2.0 / 3.0)
+	}
+}
+
+impl TryFrom<f32> for SlotProportion {
+	type Error = String;
+
+	fn try_from(proportion: f32) -> Result<Self, Self::Error> {
+		SlotProportion::new(proportion)
+	}
+}
+
+impl From<SlotProportion> for f32 {
+	fn from(proportion: SlotProportion) -> Self {
+		proportion.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn slot_info(ends_at: Instant) -> SlotInfo {
+		SlotInfo {
+			number: 1,
+			last_number: 0,
+			timestamp: 0,
+			ends_at,
+			inherent_data: Default::default(),
+			duration: 6000,
+		}
+	}
+
+	#[test]
+	fn proposal_ends_at_the_proportion_of_the_slot() {
+		let now = Instant::now();
+		let slot_info = slot_info(now + Duration::from_secs(6));
+
+		let proportion = SlotProportion::new(0.8).unwrap();
+		assert_eq!(proportion.remaining_duration(&slot_info, now).as_millis(), 4800);
+		let later = now + Duration::from_secs(4);
+		assert_eq!(proportion.remaining_duration(&slot_info, later).as_millis(), 800);
+		let too_late = now + Duration::from_secs(5);
+		assert_eq!(proportion.remaining_duration(&slot_info, too_late), Duration::from_millis(0));
+
+		let whole = SlotProportion::new(1.0).unwrap();
+		assert_eq!(whole.remaining_duration(&slot_info, now).as_millis(), 6000);
+	}
+
+	#[test]
+	fn slot_proportion_must_be_in_range() {
+		assert!(SlotProportion::new(0.0).is_err());
+		assert!(SlotProportion::new(-1.0).is_err());
+		assert!(SlotProportion::new(1.5).is_err());
+		assert!(SlotProportion::new(std::f32::NAN).is_err());
+		assert_eq!(SlotProportion::try_from(0.5).map(|proportion| proportion.get()), Ok(0.5));
+	}
+}
@@ -0,0 +1,140 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Prometheus metrics of the slot worker.
+//!
+//! The slots the node was scheduled for are compared with the ones it claimed, and the blocks
+//! built are counted by outcome, so that operators can alert on the missed slots. The slots the
+//! node was scheduled for but deliberately skipped while finality lags behind are counted apart,
+//! so they aren't mistaken for missed ones.
+
+use std::time::Duration;
+use prometheus_endpoint::{
+	register, Counter, CounterVec, Histogram, HistogramOpts, Opts, PrometheusError, Registry, U64,
+};
+
+/// Outcome of a block built on a claimed slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockOutcome {
+	/// The block was built and imported.
+	Imported,
+	/// Building the block took too long, and it was discarded.
+	Discarded,
+	/// Building or importing the block failed.
+	Failed,
+}
+
+impl BlockOutcome {
+	/// Label of the outcome.
+	fn label(&self) -> &'static str {
+		match self {
+			BlockOutcome::Imported => "imported",
+			BlockOutcome::Discarded => "discarded",
+			BlockOutcome::Failed => "failed",
+		}
+	}
+}
+
+/// Prometheus metrics of the slots and of the blocks authored on them.
+#[derive(Clone)]
+pub struct SlotMetrics {
+	eligible: Counter<U64>,
+	backed_off: Counter<U64>,
+	claimed: Counter<U64>,
+	proposal_time: Histogram,
+	blocks: CounterVec<U64>,
+}
+
+impl SlotMetrics {
+	/// Registers the metrics in `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(SlotMetrics {
+			eligible: register(Counter::new(
+				"slots_eligible_total",
+				"Number of slots one of the keys of the node was scheduled to author",
+			)?, registry)?,
+			backed_off: register(Counter::new(
+				"slots_backed_off_total",
+				"Number of slots the node was scheduled to author, but skipped while finality lags",
+			)?, registry)?,
+			claimed: register(Counter::new(
+				"slots_claimed_total",
+				"Number of slots claimed by the node to author a block",
+			)?, registry)?,
+			proposal_time: register(Histogram::with_opts(
+				HistogramOpts::new(
+					"slots_proposal_time_seconds",
+					"Time taken to build the proposals of the claimed slots, in seconds",
+				).buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0]),
+			)?, registry)?,
+			blocks: register(CounterVec::new(
+				Opts::new("slots_blocks_total", "Number of blocks built on the claimed slots"),
+				&["outcome"],
+			)?, registry)?,
+		})
+	}
+
+	/// Call this method when the node is scheduled to author a slot.
+	pub fn on_eligible(&self) {
+		self.eligible.inc();
+	}
+
+	/// Call this method when the node skips a slot it is scheduled to author, to let finality
+	/// catch up.
+	pub fn on_backed_off(&self) {
+		self.backed_off.inc();
+	}
+
+	/// Call this method when the node claims a slot.
+	pub fn on_claimed(&self) {
+		self.claimed.inc();
+	}
+
+	/// Call this method when building a proposal finished after `duration`.
+	pub fn on_proposal(&self, duration: Duration) {
+		self.proposal_time.observe(duration.as_secs_f64());
+	}
+
+	/// Call this method with the outcome of a block built on a claimed slot.
+	pub fn on_block(&self, outcome: BlockOutcome) {
+		self.blocks.with_label_values(&[outcome.label()]).inc();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn blocks_are_counted_by_outcome() {
+		let metrics = SlotMetrics::register(&Registry::new()).unwrap();
+		metrics.on_eligible();
+		metrics.on_eligible();
+		metrics.on_eligible();
+		metrics.on_backed_off();
+		metrics.on_claimed();
+		metrics.on_block(BlockOutcome::Imported);
+		metrics.on_block(BlockOutcome::Discarded);
+		metrics.on_block(BlockOutcome::Discarded);
+
+		assert_eq!(metrics.eligible.get(), 3);
+		assert_eq!(metrics.backed_off.get(), 1);
+		assert_eq!(metrics.claimed.get(), 1);
+		assert_eq!(metrics.blocks.with_label_values(&["imported"]).get(), 1);
+		assert_eq!(metrics.blocks.with_label_values(&["discarded"]).get(), 2);
+		assert_eq!(metrics.blocks.with_label_values(&["failed"]).get(), 0);
+	}
+}
@@ -0,0 +1,133 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Diagnostics of the slots one of our keys was scheduled for, but that weren't authored.
+
+use std::{collections::VecDeque, fmt, sync::Arc};
+use log::warn;
+use parking_lot::Mutex;
+use sc_telemetry::{telemetry, CONSENSUS_WARN};
+
+/// Number of missed slots kept.
+const MAX_MISSED_SLOTS: usize = 100;
+
+/// Why a scheduled slot wasn't authored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MissedSlotReason {
+	/// Our clock was already past the slot when it was processed.
+	ClockDrift {
+		/// The slot our clock was at.
+		current_slot: u64,
+	},
+	/// The node was waiting for peers.
+	NoPeers,
+	/// The node was connected to peers, but received no block from them while building its own.
+	Isolated,
+	/// The key of the scheduled authority couldn't be loaded from the keystore.
+	KeystoreUnavailable,
+	/// The best block was too far ahead of the finalized block.
+	FinalityLag,
+	/// Building the block took longer than allowed.
+	ProposalTimeout,
+	/// Creating the proposer, building or importing the block failed.
+	AuthoringFailed(String),
+}
+
+impl MissedSlotReason {
+	/// Short identifier of the reason.
+	pub fn kind(&self) -> &'static str {
+		match self {
+			MissedSlotReason::ClockDrift { .. } => "clockDrift",
+			MissedSlotReason::NoPeers => "noPeers",
+			MissedSlotReason::Isolated => "isolated",
+			MissedSlotReason::KeystoreUnavailable => "keystoreUnavailable",
+			MissedSlotReason::FinalityLag => "finalityLag",
+			MissedSlotReason::ProposalTimeout => "proposalTimeout",
+			MissedSlotReason::AuthoringFailed(_) => "authoringFailed",
+		}
+	}
+}
+
+impl fmt::Display for MissedSlotReason {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			MissedSlotReason::ClockDrift { current_slot } =>
+				write!(f, "our clock was already at slot {}", current_slot),
+			MissedSlotReason::NoPeers => write!(f, "the node had no peers"),
+			MissedSlotReason::Isolated =>
+				write!(f, "the node received no block from its peers"),
+			MissedSlotReason::KeystoreUnavailable =>
+				write!(f, "the authority key couldn't be loaded from the keystore"),
+			MissedSlotReason::FinalityLag =>
+				write!(f, "the best block was too far ahead of the finalized block"),
+			MissedSlotReason::ProposalTimeout => write!(f, "building the block took too long"),
+			MissedSlotReason::AuthoringFailed(err) => write!(f, "authoring failed: {}", err),
+		}
+	}
+}
+
+/// A scheduled slot that wasn't authored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissedSlot {
+	/// Number of the slot.
+	pub slot_number: u64,
+	/// Why it wasn't authored.
+	pub reason: MissedSlotReason,
+}
+
+/// The last missed slots, shared between the slot worker and whoever reports them.
+#[derive(Clone, Default)]
+pub struct MissedSlots(Arc<Mutex<VecDeque<MissedSlot>>>);
+
+impl MissedSlots {
+	/// Records that the scheduled `slot_number` wasn't authored.
+	pub fn record(&self, slot_number: u64, reason: MissedSlotReason) {
+		warn!(target: "slots", "Missed scheduled slot {}: {}", slot_number, reason);
+		telemetry!(CONSENSUS_WARN; "slots.missed_slot";
+			"slot" => slot_number,
+			"reason" => reason.kind(),
+		);
+
+		let mut missed = self.0.lock();
+		if missed.len() == MAX_MISSED_SLOTS {
+			missed.pop_front();
+		}
+		missed.push_back(MissedSlot { slot_number, reason });
+	}
+
+	/// The last missed slots, oldest first.
+	pub fn recent(&self) -> Vec<MissedSlot> {
+		self.0.lock().iter().cloned().collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn keeps_last_missed_slots() {
+		let missed = MissedSlots::default();
+		for slot_number in 0..MAX_MISSED_SLOTS as u64 + 10 {
+			missed.record(slot_number, MissedSlotReason::NoPeers);
+		}
+
+		let recent = missed.recent();
+		assert_eq!(recent.len(), MAX_MISSED_SLOTS);
+		assert_eq!(recent[0].slot_number, 10);
+		assert_eq!(recent[MAX_MISSED_SLOTS - 1].slot_number, MAX_MISSED_SLOTS as u64 + 9);
+	}
+}
@@ -55,6 +55,11 @@ pub struct BenchmarkingState<B: BlockT> {
 	state: RefCell<Option<DbState<B>>>,
 	db: Cell<Option<Arc<dyn KeyValueDB>>>,
 	genesis: <DbState<B> as StateBackend<HashFor<B>>>::Transaction,
+	/// Number of values read since the last reset.
+	reads: Cell<u32>,
+	/// Number of values written since the last reset, counted when computing the storage root
+	/// of the changes.
+	writes: Cell<u32>,
 }
 
 impl<B: BlockT> BenchmarkingState<B> {
@@ -76,6 +81,8 @@ impl<B: BlockT> BenchmarkingState<B> {
 			root: Cell::new(root),
 			genesis: Default::default(),
 			genesis_root: Default::default(),
+			reads: Cell::new(0),
+			writes: Cell::new(0),
 		};
 
 		state.reopen()?;
@@ -117,6 +124,14 @@ impl<B: BlockT> BenchmarkingState<B> {
 
 		std::fs::remove_dir_all(&self.path).map_err(|_| "Error removing database dir".into())
 	}
+
+	fn note_read(&self) {
+		self.reads.set(self.reads.get().saturating_add(1));
+	}
+
+	fn note_write(&self) {
+		self.writes.set(self.writes.get().saturating_add(1));
+	}
 }
 
 impl<B: BlockT> Drop for BenchmarkingState<B> {
@@ -135,6 +150,7 @@ impl<B: BlockT> StateBackend<HashFor<B>> for BenchmarkingState<B> {
 	type TrieBackendStorage = <DbState<B> as StateBackend<HashFor<B>>>::TrieBackendStorage;
 
 	fn storage(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+		self.note_read();
 		self.state.borrow().as_ref().ok_or_else(state_err)?.storage(key)
 	}
 
@@ -148,10 +164,12 @@ impl<B: BlockT> StateBackend<HashFor<B>> for BenchmarkingState<B> {
 		child_info: ChildInfo,
 		key: &[u8],
 	) -> Result<Option<Vec<u8>>, Self::Error> {
+		self.note_read();
 		self.state.borrow().as_ref().ok_or_else(state_err)?.child_storage(storage_key, child_info, key)
 	}
 
 	fn exists_storage(&self, key: &[u8]) -> Result<bool, Self::Error> {
+		self.note_read();
 		self.state.borrow().as_ref().ok_or_else(state_err)?.exists_storage(key)
 	}
 
@@ -161,6 +179,7 @@ impl<B: BlockT> StateBackend<HashFor<B>> for BenchmarkingState<B> {
 		child_info: ChildInfo,
 		key: &[u8],
 	) -> Result<bool, Self::Error> {
+		self.note_read();
 		self.state.borrow().as_ref().ok_or_else(state_err)?.exists_child_storage(storage_key, child_info, key)
 	}
 
@@ -215,6 +234,7 @@ impl<B: BlockT> StateBackend<HashFor<B>> for BenchmarkingState<B> {
 	fn storage_root<I>(&self, delta: I) -> (B::Hash, Self::Transaction) where
 		I: IntoIterator<Item=(Vec<u8>, Option<Vec<u8>>)>
 	{
+		let delta = delta.into_iter().inspect(|_| self.note_write());
 		self.state.borrow().as_ref().map_or(Default::default(), |s| s.storage_root(delta))
 	}
 
@@ -226,6 +246,7 @@ impl<B: BlockT> StateBackend<HashFor<B>> for BenchmarkingState<B> {
 	) -> (B::Hash, bool, Self::Transaction) where
 		I: IntoIterator<Item=(Vec<u8>, Option<Vec<u8>>)>,
 	{
+		let delta = delta.into_iter().inspect(|_| self.note_write());
 		self.state.borrow().as_ref().map_or(Default::default(), |s| s.child_storage_root(storage_key, child_info, delta))
 	}
 
@@ -279,6 +300,15 @@ impl<B: BlockT> StateBackend<HashFor<B>> for BenchmarkingState<B> {
 		self.commit(self.genesis_root.clone(), self.genesis.clone())?;
 		Ok(())
 	}
+
+	fn read_write_count(&self) -> (u32, u32) {
+		(self.reads.get(), self.writes.get())
+	}
+
+	fn reset_read_write_count(&self) {
+		self.reads.set(0);
+		self.writes.set(0);
+	}
 }
 
 impl<Block: BlockT> std::fmt::Debug for BenchmarkingState<Block> {
@@ -48,6 +48,7 @@ use std::collections::HashMap;
 
 use sc_client_api::{
 	ForkBlocks, UsageInfo, MemoryInfo, BadBlocks, IoInfo, MemorySize, CloneableSpawn,
+	DatabaseColumnInfo,
 	execution_extensions::ExecutionExtensions,
 	backend::{NewBlockState, PrunableStateChangesTrieStorage},
 };
@@ -83,7 +84,8 @@ use sp_blockchain::{CachedHeaderMetadata, HeaderMetadata, HeaderMetadataCache};
 use crate::storage_cache::{CachingState, SyncingCachingState, SharedCache, new_shared_cache};
 use crate::stats::StateUsageStats;
 use log::{trace, debug, warn};
-pub use sc_state_db::PruningMode;
+pub use sc_state_db::{PruningMode, StoredPruning};
+pub use crate::utils::open_read_only_database;
 use prometheus_endpoint::Registry;
 
 #[cfg(any(feature = "kvdb-rocksdb", test))]
@@ -280,6 +282,10 @@ pub enum DatabaseSettingsSrc {
 		cache_size: Option<usize>,
 	},
 
+	/// Load the full node database from a given path for reading only: the writes to it fail.
+	/// The database isn't upgraded.
+	ReadOnlyPath(PathBuf),
+
 	/// Use a custom already-open database.
 	Custom(Arc<dyn KeyValueDB>),
 }
@@ -339,6 +345,24 @@ pub(crate) mod columns {
 	/// Offchain workers local storage
 	pub const OFFCHAIN: u32 = 9;
 	pub const CACHE: u32 = 10;
+	/// State of the network kept across restarts.
+	pub const NETWORK: u32 = 11;
+
+	/// Names of the columns, indexed by column.
+	pub const NAMES: [&str; crate::utils::NUM_COLUMNS as usize] = [
+		"meta",
+		"state",
+		"state_meta",
+		"key_lookup",
+		"header",
+		"body",
+		"justification",
+		"changes_trie",
+		"aux",
+		"offchain",
+		"cache",
+		"network",
+	];
 }
 
 struct PendingBlock<Block: BlockT> {
@@ -351,6 +375,13 @@ struct PendingBlock<Block: BlockT> {
 // wrapper that implements trait required for state_db
 struct StateMetaDb<'a>(&'a dyn KeyValueDB);
 
+/// Read the pruning mode and progress stored in the state database of `db`.
+pub fn stored_pruning(db: &dyn KeyValueDB) -> ClientResult<Option<StoredPruning>> {
+	sc_state_db::read_pruning(&StateMetaDb(db)).map_err(|e| sp_blockchain::Error::from(
+		format!("State database error: {:?}", e)
+	))
+}
+
 impl<'a> sc_state_db::MetaDb for StateMetaDb<'a> {
 	type Error = io::Error;
 
@@ -759,15 +790,22 @@ pub struct Backend<Block: BlockT> {
 	is_archive: bool,
 	io_stats: FrozenForDuration<(kvdb::IoStats, StateUsageInfo)>,
 	state_usage: Arc<StateUsageStats>,
+	num_keys: Option<utils::NumKeys>,
 }
 
+/// Number of entries of each column read to estimate the size of its entries.
+const SAMPLED_COLUMN_ENTRIES: usize = 1000;
+
 impl<Block: BlockT> Backend<Block> {
 	/// Create a new instance of database backend.
 	///
 	/// The pruning window is how old a block must be before the state is pruned.
 	pub fn new(config: DatabaseSettings, canonicalization_delay: u64) -> ClientResult<Self> {
-		let db = crate::utils::open_database::<Block>(&config, DatabaseType::Full)?;
-		Self::from_kvdb(db as Arc<_>, canonicalization_delay, &config)
+		let (db, num_keys) =
+			crate::utils::open_database_with_estimates::<Block>(&config, DatabaseType::Full)?;
+		let mut backend = Self::from_kvdb(db as Arc<_>, canonicalization_delay, &config)?;
+		backend.num_keys = num_keys;
+		Ok(backend)
 	}
 
 	/// Create new memory-backed client backend for tests.
@@ -831,6 +869,7 @@ impl<Block: BlockT> Backend<Block> {
 			is_archive: is_archive_pruning,
 			io_stats: FrozenForDuration::new(std::time::Duration::from_secs(1)),
 			state_usage: Arc::new(StateUsageStats::new()),
+			num_keys: None,
 		})
 	}
 
@@ -1504,6 +1543,56 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		})
 	}
 
+	fn get_network_state(&self, key: &[u8]) -> ClientResult<Option<Vec<u8>>> {
+		self.storage.db.get(columns::NETWORK, key).map(|r| r.map(|v| v.to_vec())).map_err(db_err)
+	}
+
+	fn insert_network_state(&self, key: &[u8], value: &[u8]) -> ClientResult<()> {
+		let mut transaction = DBTransaction::new();
+		transaction.put(columns::NETWORK, key, value);
+		self.storage.db.write(transaction).map_err(db_err)
+	}
+
+	fn flush(&self) -> ClientResult<()> {
+		self.storage.db.flush().map_err(db_err)
+	}
+
+	fn database_columns(&self) -> Option<Vec<DatabaseColumnInfo>> {
+		let columns = columns::NAMES.iter().enumerate().map(|(column, name)| {
+			let column = column as u32;
+			let num_keys = match &self.num_keys {
+				Some(num_keys) => num_keys,
+				// Not a RocksDB database, e.g. kept in memory.
+				None => {
+					let mut info = DatabaseColumnInfo { name: *name, entries: 0, bytes: 0 };
+					for (key, value) in self.storage.db.iter(column) {
+						info.entries += 1;
+						info.bytes += (key.len() + value.len()) as u64;
+					}
+					return info;
+				},
+			};
+
+			let entries = num_keys(column).unwrap_or_else(|e| {
+				warn!("Failed to estimate the number of keys of the {} column: {}", name, e);
+				0
+			});
+			// RocksDB doesn't estimate the size of the values written by kvdb, so it is
+			// extrapolated from the first entries of the column.
+			let (sampled, sampled_bytes) = self.storage.db.iter(column)
+				.take(SAMPLED_COLUMN_ENTRIES)
+				.fold((0u64, 0u64), |(count, bytes), (key, value)|
+					(count + 1, bytes + (key.len() + value.len()) as u64)
+				);
+			let bytes = match sampled {
+				0 => 0,
+				_ => sampled_bytes.saturating_mul(entries.max(sampled)) / sampled,
+			};
+			DatabaseColumnInfo { name: *name, entries: entries.max(sampled), bytes }
+		}).collect();
+		Some(columns)
+	}
+
 	fn revert(&self, n: NumberFor<Block>, revert_finalized: bool) -> ClientResult<NumberFor<Block>> {
 		let mut best_number = self.blockchain.info().best_number;
 		let mut best_hash = self.blockchain.info().best_hash;
@@ -1751,6 +1840,39 @@ pub(crate) mod tests {
 		header_hash
 	}
 
+	#[test]
+	fn database_columns_are_estimated() {
+		let dir = tempfile::tempdir().unwrap();
+		let settings = |source| DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			pruning: PruningMode::keep_blocks(1),
+			source,
+		};
+		let path = dir.path().to_owned();
+		let header_column = |backend: &Backend<Block>| backend.database_columns().unwrap()
+			.into_iter()
+			.find(|column| column.name == "header")
+			.unwrap();
+
+		{
+			let source = DatabaseSettingsSrc::Path { path: path.clone(), cache_size: None };
+			let backend = Backend::<Block>::new(settings(source), 0).unwrap();
+			let mut hash = Default::default();
+			for number in 0..10 {
+				hash = insert_header(&backend, number, hash, None, Default::default());
+			}
+
+			let column = header_column(&backend);
+			assert!(column.entries >= 10);
+			assert!(column.bytes > 0);
+		}
+
+		let source = DatabaseSettingsSrc::ReadOnlyPath(path);
+		let backend = Backend::<Block>::new(settings(source), 0).unwrap();
+		assert!(header_column(&backend).entries >= 10);
+	}
+
 	#[test]
 	fn block_hash_inserted_correctly() {
 		let backing = {
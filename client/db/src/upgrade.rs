@@ -36,17 +36,24 @@ use crate::{
 const VERSION_FILE_NAME: &'static str = "db_version";
 
 /// Current db version.
-const CURRENT_VERSION: u32 = 1;
+const CURRENT_VERSION: u32 = 2;
 
 /// Number of columns in v0.
 const V0_NUM_COLUMNS: u32 = 10;
 
+/// Number of columns in v1.
+const V1_NUM_COLUMNS: u32 = 11;
+
 /// Upgrade database to current version.
 pub fn upgrade_db<Block: BlockT>(db_path: &Path, db_type: DatabaseType) -> sp_blockchain::Result<()> {
 	let db_version = current_version(db_path)?;
 	match db_version {
-		0 => migrate_0_to_1::<Block>(db_path, db_type)?,
-		1 => (),
+		0 => {
+			migrate_0_to_1::<Block>(db_path, db_type)?;
+			migrate_1_to_2(db_path, db_type)?;
+		},
+		1 => migrate_1_to_2(db_path, db_type)?,
+		2 => (),
 		_ => Err(sp_blockchain::Error::Backend(format!("Future database version: {}", db_version)))?,
 	}
 
@@ -102,6 +109,26 @@ fn migrate_0_to_1<Block: BlockT>(db_path: &Path, db_type: DatabaseType) -> sp_bl
 	Ok(())
 }
 
+/// Check that the database at given path is at the current version, without upgrading it.
+pub fn check_version(db_path: &Path) -> sp_blockchain::Result<()> {
+	match current_version(db_path)? {
+		CURRENT_VERSION => Ok(()),
+		db_version => Err(sp_blockchain::Error::Backend(format!(
+			"Database version {} isn't the current version {}, run the node to upgrade it",
+			db_version,
+			CURRENT_VERSION,
+		))),
+	}
+}
+
+/// Migration from version1 to version2:
+/// the number of columns has changed from 11 to 12, the new one storing the network state.
+fn migrate_1_to_2(db_path: &Path, db_type: DatabaseType) -> sp_blockchain::Result<()> {
+	let db = open_database(db_path, db_type, V1_NUM_COLUMNS)?;
+	db.add_column().map_err(db_err)?;
+	db.flush().map_err(db_err)
+}
+
 /// Reads current database version from the file at given path.
 /// If the file does not exist returns 0.
 fn current_version(path: &Path) -> sp_blockchain::Result<u32> {
@@ -195,4 +222,16 @@ mod tests {
 			assert_eq!(current_version(db_path).unwrap(), CURRENT_VERSION);
 		}
 	}
+
+	#[test]
+	fn upgrade_from_1_to_2_works() {
+		let db_dir = tempfile::TempDir::new().unwrap();
+		let db_path = db_dir.path();
+		create_db(db_path, Some(0));
+		migrate_0_to_1::<Block>(db_path, DatabaseType::Full).unwrap();
+		fs::write(version_file_path(db_path), b"1").unwrap();
+
+		open_database(db_path).unwrap();
+		assert_eq!(current_version(db_path).unwrap(), CURRENT_VERSION);
+	}
 }
@@ -19,6 +19,7 @@
 
 use std::sync::Arc;
 use std::{io, convert::TryInto};
+use std::path::Path;
 
 use kvdb::{KeyValueDB, DBTransaction};
 #[cfg(any(feature = "kvdb-rocksdb", test))]
@@ -37,7 +38,7 @@ use crate::{DatabaseSettings, DatabaseSettingsSrc};
 /// Number of columns in the db. Must be the same for both full && light dbs.
 /// Otherwise RocksDb will fail to open database && check its type.
 #[cfg(any(feature = "kvdb-rocksdb", feature = "test-helpers", test))]
-pub const NUM_COLUMNS: u32 = 11;
+pub const NUM_COLUMNS: u32 = 12;
 /// Meta column. The set of keys in the column is shared by full && light storages.
 pub const COLUMN_META: u32 = 0;
 
@@ -214,12 +215,23 @@ pub fn db_err(err: io::Error) -> sp_blockchain::Error {
 	sp_blockchain::Error::Backend(format!("{}", err))
 }
 
+/// Returns RocksDB's estimate of the number of keys of a column.
+pub(crate) type NumKeys = Arc<dyn Fn(u32) -> io::Result<u64> + Send + Sync>;
+
 /// Open RocksDB database.
 pub fn open_database<Block: BlockT>(
 	config: &DatabaseSettings,
 	db_type: DatabaseType,
 ) -> sp_blockchain::Result<Arc<dyn KeyValueDB>> {
-	let db: Arc<dyn KeyValueDB> = match &config.source {
+	open_database_with_estimates::<Block>(config, db_type).map(|(db, _)| db)
+}
+
+/// Open RocksDB database, along with the estimates of its columns if it is read from a path.
+pub(crate) fn open_database_with_estimates<Block: BlockT>(
+	config: &DatabaseSettings,
+	db_type: DatabaseType,
+) -> sp_blockchain::Result<(Arc<dyn KeyValueDB>, Option<NumKeys>)> {
+	let (db, num_keys) = match &config.source {
 		#[cfg(any(feature = "kvdb-rocksdb", test))]
 		DatabaseSettingsSrc::Path { path, cache_size } => {
 			// first upgrade database to required version
@@ -245,19 +257,130 @@ pub fn open_database<Block: BlockT>(
 			}
 			let path = path.to_str()
 				.ok_or_else(|| sp_blockchain::Error::Backend("Invalid database path".into()))?;
-			Arc::new(Database::open(&db_config, &path).map_err(db_err)?)
+			let db = Arc::new(Database::open(&db_config, &path).map_err(db_err)?);
+			(db.clone() as Arc<dyn KeyValueDB>, Some(num_keys(db)))
+		},
+		#[cfg(any(feature = "kvdb-rocksdb", test))]
+		DatabaseSettingsSrc::ReadOnlyPath(path) => {
+			let db = open_read_only(path)?;
+			let read_only: Arc<dyn KeyValueDB> = Arc::new(ReadOnlyDatabase(db.clone()));
+			(read_only, Some(num_keys(db)))
 		},
 		#[cfg(not(any(feature = "kvdb-rocksdb", test)))]
-		DatabaseSettingsSrc::Path { .. } => {
+		DatabaseSettingsSrc::Path { .. } | DatabaseSettingsSrc::ReadOnlyPath(_) => {
 			let msg = "Try to open RocksDB database with RocksDB disabled".into();
 			return Err(sp_blockchain::Error::Backend(msg));
 		},
-		DatabaseSettingsSrc::Custom(db) => db.clone(),
+		DatabaseSettingsSrc::Custom(db) => (db.clone(), None),
 	};
 
 	check_database_type(&*db, db_type)?;
 
-	Ok(db)
+	Ok((db, num_keys))
+}
+
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+fn num_keys(db: Arc<Database>) -> NumKeys {
+	Arc::new(move |column| db.num_keys(column))
+}
+
+/// Open the RocksDB database of a full node for reading only: the writes to it fail.
+///
+/// The database isn't upgraded, since that would write to it.
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+pub fn open_read_only_database(path: &Path) -> sp_blockchain::Result<Arc<dyn KeyValueDB>> {
+	Ok(Arc::new(ReadOnlyDatabase(open_read_only(path)?)))
+}
+
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+fn open_read_only(path: &Path) -> sp_blockchain::Result<Arc<Database>> {
+	if !path.exists() {
+		let msg = format!("No database found at {}", path.display());
+		return Err(sp_blockchain::Error::Backend(msg));
+	}
+	crate::upgrade::check_version(path)?;
+
+	let path = path.to_str()
+		.ok_or_else(|| sp_blockchain::Error::Backend("Invalid database path".into()))?;
+	let db = Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), &path).map_err(db_err)?;
+	let db_type = DatabaseType::Full;
+	if db.get(COLUMN_META, meta_keys::TYPE).map_err(db_err)?.as_ref().map(|t| &t[..])
+		!= Some(db_type.as_str().as_bytes())
+	{
+		return Err(sp_blockchain::Error::Backend(
+			format!("Unexpected database type. Expected: {}", db_type.as_str())
+		));
+	}
+
+	Ok(Arc::new(db))
+}
+
+/// Open the RocksDB database of a full node for reading only: the writes to it fail.
+#[cfg(not(any(feature = "kvdb-rocksdb", test)))]
+pub fn open_read_only_database(_path: &Path) -> sp_blockchain::Result<Arc<dyn KeyValueDB>> {
+	let msg = "Try to open RocksDB database with RocksDB disabled".into();
+	Err(sp_blockchain::Error::Backend(msg))
+}
+
+/// Database refusing the writes, used to inspect a database without changing it.
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+struct ReadOnlyDatabase(Arc<dyn KeyValueDB>);
+
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+impl ReadOnlyDatabase {
+	fn write_err() -> io::Error {
+		io::Error::new(io::ErrorKind::Other, "The database is opened read-only")
+	}
+}
+
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+impl parity_util_mem::MallocSizeOf for ReadOnlyDatabase {
+	fn size_of(&self, ops: &mut parity_util_mem::MallocSizeOfOps) -> usize {
+		(*self.0).size_of(ops)
+	}
+}
+
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+impl KeyValueDB for ReadOnlyDatabase {
+	fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+		self.0.get(col, key)
+	}
+
+	fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+		self.0.get_by_prefix(col, prefix)
+	}
+
+	fn write_buffered(&self, _transaction: DBTransaction) {
+		log::warn!(target: "db", "Ignoring a write to the read-only database");
+	}
+
+	fn write(&self, _transaction: DBTransaction) -> io::Result<()> {
+		Err(Self::write_err())
+	}
+
+	fn flush(&self) -> io::Result<()> {
+		Ok(())
+	}
+
+	fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a> {
+		self.0.iter(col)
+	}
+
+	fn iter_from_prefix<'a>(
+		&'a self,
+		col: u32,
+		prefix: &'a [u8],
+	) -> Box<dyn Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a> {
+		self.0.iter_from_prefix(col, prefix)
+	}
+
+	fn restore(&self, _new_db: &str) -> io::Result<()> {
+		Err(Self::write_err())
+	}
+
+	fn io_stats(&self, kind: kvdb::IoStatsKind) -> kvdb::IoStats {
+		self.0.io_stats(kind)
+	}
 }
 
 /// Check database type.
@@ -0,0 +1,263 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC api for GRANDPA.
+
+use jsonrpc_derive::rpc;
+use parity_scale_codec::Encode;
+use sc_finality_grandpa::{AuthorityId, RoundVotes, SharedRoundState, VoterRounds};
+use serde::{Deserialize, Serialize};
+use sp_core::Bytes;
+use sp_runtime::traits::Block as BlockT;
+
+/// Error code returned before the voter started its first round.
+const NOT_READY_ERROR_CODE: i64 = 1;
+/// Error code returned for rounds that aren't kept.
+const UNKNOWN_ROUND_ERROR_CODE: i64 = 2;
+
+/// Provides rpc methods for interacting with GRANDPA.
+#[rpc]
+pub trait GrandpaApi {
+	/// Returns the state of the recent rounds of the voter: the voters seen prevoting and
+	/// precommitting in each round, and whether the local voter did.
+	#[rpc(name = "grandpa_roundState")]
+	fn round_state(&self) -> jsonrpc_core::Result<ReportedRoundStates>;
+
+	/// Returns the SCALE-encoded signed precommits seen in the given round of the current set,
+	/// proving how far the round got.
+	#[rpc(name = "grandpa_provePrecommits")]
+	fn prove_precommits(&self, round: u64) -> jsonrpc_core::Result<PrecommitsProof>;
+}
+
+/// Implements the GrandpaApi trait by reading the votes recorded by the voter.
+pub struct GrandpaRpcHandler<Block: BlockT> {
+	/// The votes seen by the voter in its recent rounds.
+	round_state: SharedRoundState<Block>,
+}
+
+impl<Block: BlockT> GrandpaRpcHandler<Block> {
+	/// Creates a new instance of the GRANDPA RPC handler.
+	pub fn new(round_state: SharedRoundState<Block>) -> Self {
+		Self { round_state }
+	}
+
+	fn rounds(&self) -> Result<VoterRounds<Block>, Error> {
+		self.round_state.get().ok_or(Error::NotReady)
+	}
+}
+
+impl<Block: BlockT> GrandpaApi for GrandpaRpcHandler<Block> {
+	fn round_state(&self) -> jsonrpc_core::Result<ReportedRoundStates> {
+		Ok(report(&self.rounds()?))
+	}
+
+	fn prove_precommits(&self, round: u64) -> jsonrpc_core::Result<PrecommitsProof> {
+		let rounds = self.rounds()?;
+		let votes = rounds.round(round).ok_or(Error::UnknownRound(round))?;
+		Ok(PrecommitsProof {
+			set_id: rounds.set_id,
+			round,
+			precommits: votes.precommits.encode().into(),
+		})
+	}
+}
+
+/// The state of the recent rounds of the current authority set.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportedRoundStates {
+	/// the id of the current authority set
+	set_id: u64,
+	/// the total weight of the voters
+	total_weight: u64,
+	/// the weight of votes needed to complete a round
+	threshold_weight: u64,
+	/// the id of the local voter, if it is part of the set
+	local_id: Option<String>,
+	/// the most recent rounds, oldest first
+	rounds: Vec<RoundState>,
+}
+
+/// The state of a round.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundState {
+	/// the round number
+	round: u64,
+	/// whether the round is completed
+	completed: bool,
+	/// the voters seen prevoting
+	prevotes: Participation,
+	/// the voters seen precommitting
+	precommits: Participation,
+	/// the votes of the local voter, if it is part of the set
+	local: Option<LocalVotes>,
+}
+
+/// The voters seen voting in a round, and the ones missing.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Participation {
+	/// the weight of the voters seen voting
+	current_weight: u64,
+	/// the voters seen voting
+	voted: Vec<String>,
+	/// the voters not seen voting
+	missing: Vec<String>,
+}
+
+/// Whether the local voter voted in a round.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalVotes {
+	/// whether the local voter prevoted
+	prevoted: bool,
+	/// whether the local voter precommitted
+	precommitted: bool,
+}
+
+/// The precommits seen in a round.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrecommitsProof {
+	/// the id of the authority set
+	set_id: u64,
+	/// the round number
+	round: u64,
+	/// the SCALE-encoded `Vec<SignedPrecommit>` seen in the round
+	precommits: Bytes,
+}
+
+/// Errors encountered by the RPC
+#[derive(Debug, derive_more::Display)]
+pub enum Error {
+	/// The voter didn't start a round yet.
+	#[display(fmt = "GRANDPA voter didn't start a round yet")]
+	NotReady,
+	/// The round isn't kept.
+	#[display(fmt = "Round {} of the current set is unknown", _0)]
+	UnknownRound(u64),
+}
+
+impl From<Error> for jsonrpc_core::Error {
+	fn from(error: Error) -> Self {
+		let code = match error {
+			Error::NotReady => NOT_READY_ERROR_CODE,
+			Error::UnknownRound(_) => UNKNOWN_ROUND_ERROR_CODE,
+		};
+		jsonrpc_core::Error {
+			message: format!("{}", error).into(),
+			code: jsonrpc_core::ErrorCode::ServerError(code),
+			data: None,
+		}
+	}
+}
+
+fn report<Block: BlockT>(rounds: &VoterRounds<Block>) -> ReportedRoundStates {
+	ReportedRoundStates {
+		set_id: rounds.set_id,
+		total_weight: rounds.total_weight,
+		threshold_weight: rounds.threshold,
+		local_id: rounds.local_id.as_ref().map(ToString::to_string),
+		rounds: rounds.rounds.iter().map(|votes| round_state(rounds, votes)).collect(),
+	}
+}
+
+fn round_state<Block: BlockT>(
+	rounds: &VoterRounds<Block>,
+	votes: &RoundVotes<Block>,
+) -> RoundState {
+	let precommitters: Vec<_> = votes.precommits.iter().map(|signed| signed.id.clone()).collect();
+	RoundState {
+		round: votes.round,
+		completed: votes.completed,
+		prevotes: participation(&rounds.voters, &votes.prevotes),
+		precommits: participation(&rounds.voters, &precommitters),
+		local: rounds.local_id.as_ref().map(|id| LocalVotes {
+			prevoted: votes.prevotes.contains(id),
+			precommitted: precommitters.contains(id),
+		}),
+	}
+}
+
+fn participation(voters: &[(AuthorityId, u64)], voted: &[AuthorityId]) -> Participation {
+	let mut participation = Participation {
+		current_weight: 0,
+		voted: Vec::new(),
+		missing: Vec::new(),
+	};
+	for (id, weight) in voters {
+		if voted.contains(id) {
+			participation.current_weight += weight;
+			participation.voted.push(id.to_string());
+		} else {
+			participation.missing.push(id.to_string());
+		}
+	}
+	participation
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::IoHandler;
+	use sp_core::crypto::Public;
+	use substrate_test_runtime_client::runtime::Block;
+
+	fn id(byte: u8) -> AuthorityId {
+		AuthorityId::from_slice(&[byte; 32])
+	}
+
+	#[test]
+	fn reports_round_state() {
+		let rounds = VoterRounds::<Block> {
+			set_id: 1,
+			voters: vec![(id(1), 1), (id(2), 1), (id(3), 1)],
+			total_weight: 3,
+			threshold: 3,
+			local_id: Some(id(1)),
+			rounds: vec![RoundVotes {
+				round: 4,
+				completed: false,
+				prevotes: vec![id(2), id(1)],
+				precommits: Vec::new(),
+			}],
+		};
+
+		let report = report(&rounds);
+		assert_eq!(report.rounds.len(), 1);
+		let round = &report.rounds[0];
+		assert_eq!(round.prevotes.current_weight, 2);
+		assert_eq!(round.prevotes.voted, vec![id(1).to_string(), id(2).to_string()]);
+		assert_eq!(round.prevotes.missing, vec![id(3).to_string()]);
+		assert_eq!(round.precommits.current_weight, 0);
+		assert_eq!(round.precommits.missing.len(), 3);
+		let local = round.local.as_ref().unwrap();
+		assert!(local.prevoted);
+		assert!(!local.precommitted);
+	}
+
+	#[test]
+	fn rejects_calls_before_first_round() {
+		let handler = GrandpaRpcHandler::<Block>::new(SharedRoundState::empty());
+		let mut io = IoHandler::new();
+		io.extend_with(GrandpaApi::to_delegate(handler));
+
+		let request = r#"{"jsonrpc":"2.0","method":"grandpa_roundState","params":[],"id":1}"#;
+		let response = r#"{"jsonrpc":"2.0","error":{"code":1,"message":"GRANDPA voter didn't start a round yet"},"id":1}"#;
+		assert_eq!(Some(response.into()), io.handle_request_sync(request));
+	}
+}
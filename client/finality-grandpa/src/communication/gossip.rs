@@ -241,6 +241,12 @@ impl<B: BlockT> KeepTopics<B> {
 	fn push(&mut self, round: Round, set_id: SetId) {
 		self.current_set = std::cmp::max(self.current_set, set_id);
 
+		// after a catch up the rounds we were tracking might be far in the past, there's no point
+		// in keeping messages for them any longer.
+		self.rounds.retain(|&(r, s)| {
+			s != set_id || r.0.saturating_add(KEEP_RECENT_ROUNDS as u64) >= round.0
+		});
+
 		// under normal operation the given round is already tracked (since we
 		// track one round ahead). if we skip rounds (with a catch up) the given
 		// round topic might not be tracked yet.
@@ -1780,6 +1786,34 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn messages_of_rounds_skipped_by_catch_up_expired() {
+		let (val, _) = GossipValidator::<Block>::new(
+			config(),
+			voter_set_state(),
+			None,
+		);
+
+		val.note_set(SetId(1), Vec::new(), |_, _| {});
+		val.note_round(Round(4), |_, _| {});
+		val.note_round(Round(5), |_, _| {});
+
+		// we catch up to round 20.
+		val.note_round(Round(20), |_, _| {});
+
+		let mut is_expired = val.message_expired();
+
+		for round in 4..7 {
+			let topic = crate::communication::round_topic::<Block>(round, 1);
+			assert!(is_expired(topic, &[1, 2, 3]));
+		}
+
+		for round in 20..22 {
+			let topic = crate::communication::round_topic::<Block>(round, 1);
+			assert!(!is_expired(topic, &[1, 2, 3]));
+		}
+	}
+
 	#[test]
 	fn message_from_unknown_authority_discarded() {
 		assert!(cost::UNKNOWN_VOTER != cost::BAD_SIGNATURE);
@@ -36,7 +36,7 @@ use std::{pin::Pin, sync::Arc, task::{Context, Poll}};
 use finality_grandpa::Message::{Prevote, Precommit, PrimaryPropose};
 use finality_grandpa::{voter, voter_set::VoterSet};
 use sc_network::{NetworkService, ReputationChange};
-use sc_network_gossip::{GossipEngine, Network as GossipNetwork};
+use sc_network_gossip::{GossipConfig, GossipEngine, Network as GossipNetwork};
 use parity_scale_codec::{Encode, Decode};
 use sp_core::Pair;
 use sp_runtime::traits::{Block as BlockT, Hash as HashT, Header as HeaderT, NumberFor};
@@ -188,11 +188,14 @@ impl<B: BlockT, N: Network<B>> NetworkBridge<B, N> {
 		);
 
 		let validator = Arc::new(validator);
-		let gossip_engine = Arc::new(Mutex::new(GossipEngine::new(
+		let gossip_engine = Arc::new(Mutex::new(GossipEngine::with_config(
 			service.clone(),
 			GRANDPA_ENGINE_ID,
 			GRANDPA_PROTOCOL_NAME,
-			validator.clone()
+			validator.clone(),
+			// the GRANDPA messages are needed to finalize, and must not be dropped.
+			GossipConfig::unbounded(),
+			prometheus_registry,
 		)));
 
 		{
@@ -28,7 +28,9 @@ use parking_lot::RwLock;
 use sp_blockchain::{HeaderBackend, Error as ClientError, HeaderMetadata};
 use std::marker::PhantomData;
 
-use sc_client_api::{backend::Backend, utils::is_descendent_of};
+use sc_client_api::{
+	backend::Backend, health::{HealthEvent, HealthSink}, utils::is_descendent_of,
+};
 use sc_client::apply_aux;
 use finality_grandpa::{
 	BlockNumberOps, Equivocation, Error as GrandpaError, round::State as RoundState,
@@ -51,6 +53,7 @@ use sp_consensus::SelectChain;
 use crate::authorities::{AuthoritySet, SharedAuthoritySet};
 use crate::communication::Network as NetworkT;
 use crate::consensus_changes::SharedConsensusChanges;
+use crate::round_state::SharedRoundState;
 use crate::justification::GrandpaJustification;
 use crate::until_imported::UntilVoteTargetImported;
 use crate::voting_rule::VotingRule;
@@ -401,6 +404,8 @@ pub(crate) struct Environment<Backend, Block: BlockT, C, N: NetworkT<Block>, SC,
 	pub(crate) voter_set_state: SharedVoterSetState<Block>,
 	pub(crate) voting_rule: VR,
 	pub(crate) metrics: Option<Metrics>,
+	pub(crate) health_sink: Option<HealthSink>,
+	pub(crate) round_state: SharedRoundState<Block>,
 	pub(crate) _phantom: PhantomData<Backend>,
 }
 
@@ -609,6 +614,16 @@ where
 
 		let local_key = crate::is_voter(&self.voters, &self.config.keystore);
 
+		if let Some(health_sink) = self.health_sink.as_ref() {
+			let _ = health_sink.unbounded_send(HealthEvent::RoundStarted(round));
+		}
+		self.round_state.note_round_started(
+			self.set_id,
+			&self.voters,
+			local_key.as_ref().map(|pair| pair.public()),
+			round,
+		);
+
 		let has_voted = match self.voter_set_state.has_voted(round) {
 			HasVoted::Yes(id, vote) => {
 				if local_key.as_ref().map(|k| k.public() == id).unwrap_or(false) {
@@ -628,6 +643,13 @@ where
 			has_voted,
 		);
 
+		// record the votes, including ours which are merged into the incoming ones.
+		let (round_state, set_id) = (self.round_state.clone(), self.set_id);
+		let incoming = incoming.map(move |vote| {
+			round_state.note_vote(set_id, round, &vote);
+			vote
+		});
+
 		// schedule incoming messages from the network to be held until
 		// corresponding blocks are imported.
 		let incoming = Box::pin(UntilVoteTargetImported::new(
@@ -797,6 +819,38 @@ where
 			state.finalized.as_ref().map(|e| e.1),
 		);
 
+		if let Some(health_sink) = self.health_sink.as_ref() {
+			let _ = health_sink.unbounded_send(HealthEvent::RoundCompleted(round));
+		}
+		self.round_state.note_round_completed(self.set_id, round);
+
+		sc_consensus_recorder::record("grandpa", "round_completed", || {
+			let votes = historical_votes.seen().iter().map(|vote| {
+				let (kind, target_hash, target_number) = match &vote.message {
+					finality_grandpa::Message::Prevote(v) =>
+						("prevote", v.target_hash, v.target_number),
+					finality_grandpa::Message::Precommit(v) =>
+						("precommit", v.target_hash, v.target_number),
+					finality_grandpa::Message::PrimaryPropose(v) =>
+						("primary_propose", v.target_hash, v.target_number),
+				};
+				serde_json::json!({
+					"voter": sc_consensus_recorder::authority_id(vote.id.as_ref()),
+					"kind": kind,
+					"targetHash": target_hash,
+					"targetNumber": target_number,
+				})
+			}).collect::<Vec<_>>();
+
+			serde_json::json!({
+				"setId": self.set_id,
+				"round": round,
+				"estimate": state.estimate.as_ref().map(|e| e.1),
+				"finalized": state.finalized.as_ref().map(|e| e.1),
+				"votes": votes,
+			})
+		});
+
 		self.update_voter_set_state(|voter_set_state| {
 			// NOTE: we don't use `with_current_round` here, it is possible that
 			// we are not currently tracking this round if it is a round we
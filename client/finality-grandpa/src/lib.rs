@@ -58,6 +58,7 @@ use log::{debug, info};
 use futures::channel::mpsc;
 use sc_client_api::{
 	backend::{AuxStore, Backend},
+	health::HealthSink,
 	LockImportRun, BlockchainEvents, CallExecutor,
 	ExecutionStrategy, Finalizer, TransactionFor, ExecutorProvider,
 };
@@ -93,12 +94,14 @@ mod import;
 mod justification;
 mod light_import;
 mod observer;
+mod round_state;
 mod until_imported;
 mod voting_rule;
 
 pub use finality_proof::{FinalityProofProvider, StorageAndProofProvider};
 pub use justification::GrandpaJustification;
 pub use light_import::light_block_import;
+pub use round_state::{RoundVotes, SharedRoundState, VoterRounds};
 pub use voting_rule::{
 	BeforeBestBlockBy, ThreeQuartersOfTheUnfinalizedChain, VotingRule, VotingRulesBuilder
 };
@@ -134,6 +137,13 @@ pub type PrimaryPropose<Block> = finality_grandpa::PrimaryPropose<<Block as Bloc
 pub type Prevote<Block> = finality_grandpa::Prevote<<Block as BlockT>::Hash, NumberFor<Block>>;
 /// A precommit message for this chain's block type.
 pub type Precommit<Block> = finality_grandpa::Precommit<<Block as BlockT>::Hash, NumberFor<Block>>;
+/// A signed precommit message for this chain's block type.
+pub type SignedPrecommit<Block> = finality_grandpa::SignedPrecommit<
+	<Block as BlockT>::Hash,
+	NumberFor<Block>,
+	AuthoritySignature,
+	AuthorityId,
+>;
 /// A catch up message for this chain's block type.
 pub type CatchUp<Block> = finality_grandpa::CatchUp<
 	<Block as BlockT>::Hash,
@@ -379,6 +389,14 @@ pub struct LinkHalf<Block: BlockT, C, SC> {
 	select_chain: SC,
 	persistent_data: PersistentData<Block>,
 	voter_commands_rx: mpsc::UnboundedReceiver<VoterCommand<Block::Hash, NumberFor<Block>>>,
+	round_state: SharedRoundState<Block>,
+}
+
+impl<Block: BlockT, C, SC> LinkHalf<Block, C, SC> {
+	/// Get the votes seen by the voter in its recent rounds, to report them over RPC.
+	pub fn round_state(&self) -> &SharedRoundState<Block> {
+		&self.round_state
+	}
 }
 
 /// Provider for the Grandpa authority set configured on the genesis block.
@@ -509,6 +527,7 @@ where
 			select_chain,
 			persistent_data,
 			voter_commands_rx,
+			round_state: SharedRoundState::empty(),
 		},
 	))
 }
@@ -600,6 +619,8 @@ pub struct GrandpaParams<Block: BlockT, C, N, SC, VR> {
 	pub voting_rule: VR,
 	/// The prometheus metrics registry.
 	pub prometheus_registry: Option<prometheus_endpoint::Registry>,
+	/// Sink the rounds are reported to, for the health gauges of the service.
+	pub health_sink: Option<HealthSink>,
 }
 
 /// Run a GRANDPA voter as a task. Provide configuration and a link to a
@@ -624,6 +645,7 @@ pub fn run_grandpa_voter<Block: BlockT, BE: 'static, C, N, SC, VR>(
 		telemetry_on_connect,
 		voting_rule,
 		prometheus_registry,
+		health_sink,
 	} = grandpa_params;
 
 	// NOTE: we have recently removed `run_grandpa_observer` from the public
@@ -637,6 +659,7 @@ pub fn run_grandpa_voter<Block: BlockT, BE: 'static, C, N, SC, VR>(
 		select_chain,
 		persistent_data,
 		voter_commands_rx,
+		round_state,
 	} = link;
 
 	let network = NetworkBridge::new(
@@ -684,6 +707,8 @@ pub fn run_grandpa_voter<Block: BlockT, BE: 'static, C, N, SC, VR>(
 		persistent_data,
 		voter_commands_rx,
 		prometheus_registry,
+		health_sink,
+		round_state,
 	);
 
 	let voter_work = voter_work
@@ -724,6 +749,8 @@ where
 		persistent_data: PersistentData<Block>,
 		voter_commands_rx: mpsc::UnboundedReceiver<VoterCommand<Block::Hash, NumberFor<Block>>>,
 		prometheus_registry: Option<prometheus_endpoint::Registry>,
+		health_sink: Option<HealthSink>,
+		round_state: SharedRoundState<Block>,
 	) -> Self {
 
 		let voters = persistent_data.authority_set.current_authorities();
@@ -742,6 +769,8 @@ where
 				Metrics::register(&registry)
 					.expect("Other metrics would have failed to register before these; qed")
 			}),
+			health_sink,
+			round_state,
 			_phantom: PhantomData,
 		});
 
@@ -863,6 +892,8 @@ where
 					network: self.env.network.clone(),
 					voting_rule: self.env.voting_rule.clone(),
 					metrics: self.env.metrics.clone(),
+					health_sink: self.env.health_sink.clone(),
+					round_state: self.env.round_state.clone(),
 					_phantom: PhantomData,
 				});
 
@@ -172,6 +172,7 @@ where
 		select_chain: _,
 		persistent_data,
 		voter_commands_rx,
+		round_state: _,
 	} = link;
 
 	let network = NetworkBridge::new(
@@ -0,0 +1,232 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Votes seen by the voter in its recent rounds.
+//!
+//! The environment records every vote it imports, including the ones of the local voter, so that
+//! the state of the rounds can be reported over RPC when finality is stalled.
+
+use std::sync::Arc;
+
+use finality_grandpa::{Message, voter_set::VoterSet};
+use parking_lot::RwLock;
+use sp_finality_grandpa::{AuthorityId, RoundNumber, SetId};
+use sp_runtime::traits::Block as BlockT;
+
+use crate::{SignedMessage, SignedPrecommit};
+
+/// Maximal number of rounds kept, the most recent ones.
+const MAX_ROUNDS: usize = 3;
+
+/// Votes seen in a round.
+#[derive(Debug, Clone)]
+pub struct RoundVotes<Block: BlockT> {
+	/// The round number.
+	pub round: RoundNumber,
+	/// Whether the round is completed.
+	pub completed: bool,
+	/// The voters seen prevoting, in the order their prevotes were imported.
+	pub prevotes: Vec<AuthorityId>,
+	/// The precommits seen, at most one per voter.
+	pub precommits: Vec<SignedPrecommit<Block>>,
+}
+
+impl<Block: BlockT> RoundVotes<Block> {
+	fn new(round: RoundNumber) -> Self {
+		RoundVotes { round, completed: false, prevotes: Vec::new(), precommits: Vec::new() }
+	}
+}
+
+/// Votes seen by the voter in its recent rounds of the current set.
+#[derive(Debug, Clone)]
+pub struct VoterRounds<Block: BlockT> {
+	/// The id of the current authority set.
+	pub set_id: SetId,
+	/// The voters of the set, with their weight.
+	pub voters: Vec<(AuthorityId, u64)>,
+	/// The total weight of the set.
+	pub total_weight: u64,
+	/// The weight of votes needed to complete a round.
+	pub threshold: u64,
+	/// The id of the local voter, if it is part of the set.
+	pub local_id: Option<AuthorityId>,
+	/// The most recent rounds, oldest first.
+	pub rounds: Vec<RoundVotes<Block>>,
+}
+
+impl<Block: BlockT> VoterRounds<Block> {
+	/// The votes of the given round, if it is still kept.
+	pub fn round(&self, round: RoundNumber) -> Option<&RoundVotes<Block>> {
+		self.rounds.iter().find(|votes| votes.round == round)
+	}
+
+	fn round_mut(&mut self, set_id: SetId, round: RoundNumber) -> Option<&mut RoundVotes<Block>> {
+		if set_id != self.set_id {
+			return None;
+		}
+		self.rounds.iter_mut().find(|votes| votes.round == round)
+	}
+}
+
+/// Shared handle on the votes seen by the voter in its recent rounds.
+pub struct SharedRoundState<Block: BlockT> {
+	inner: Arc<RwLock<Option<VoterRounds<Block>>>>,
+}
+
+impl<Block: BlockT> Clone for SharedRoundState<Block> {
+	fn clone(&self) -> Self {
+		SharedRoundState { inner: self.inner.clone() }
+	}
+}
+
+impl<Block: BlockT> SharedRoundState<Block> {
+	/// A state in which no round was started yet.
+	pub fn empty() -> Self {
+		SharedRoundState { inner: Arc::new(RwLock::new(None)) }
+	}
+
+	/// The votes seen in the recent rounds, or `None` if the voter didn't start a round yet.
+	pub fn get(&self) -> Option<VoterRounds<Block>> {
+		self.inner.read().clone()
+	}
+
+	/// Notes the start of `round` in the set `set_id`. Rounds of the previous sets are dropped.
+	pub(crate) fn note_round_started(
+		&self,
+		set_id: SetId,
+		voters: &VoterSet<AuthorityId>,
+		local_id: Option<AuthorityId>,
+		round: RoundNumber,
+	) {
+		let mut inner = self.inner.write();
+		if inner.as_ref().map_or(true, |state| state.set_id != set_id) {
+			*inner = Some(VoterRounds {
+				set_id,
+				voters: voters.voters().to_vec(),
+				total_weight: voters.total_weight(),
+				threshold: voters.threshold(),
+				local_id: None,
+				rounds: Vec::new(),
+			});
+		}
+		let state = inner.as_mut().expect("set above for a new set; qed");
+		state.local_id = local_id;
+		if state.round(round).is_none() {
+			state.rounds.push(RoundVotes::new(round));
+			state.rounds.sort_by_key(|votes| votes.round);
+			let excess = state.rounds.len().saturating_sub(MAX_ROUNDS);
+			state.rounds.drain(..excess);
+		}
+	}
+
+	/// Notes a vote imported in `round` of the set `set_id`. Only the first prevote and precommit
+	/// of each voter are kept.
+	pub(crate) fn note_vote(&self, set_id: SetId, round: RoundNumber, vote: &SignedMessage<Block>) {
+		let mut inner = self.inner.write();
+		let votes = match inner.as_mut().and_then(|state| state.round_mut(set_id, round)) {
+			Some(votes) => votes,
+			None => return,
+		};
+		match vote.message {
+			Message::Prevote(_) => if !votes.prevotes.contains(&vote.id) {
+				votes.prevotes.push(vote.id.clone());
+			},
+			Message::Precommit(ref precommit) =>
+				if !votes.precommits.iter().any(|signed| signed.id == vote.id) {
+					votes.precommits.push(SignedPrecommit::<Block> {
+						precommit: precommit.clone(),
+						signature: vote.signature.clone(),
+						id: vote.id.clone(),
+					});
+				},
+			Message::PrimaryPropose(_) => {},
+		}
+	}
+
+	/// Notes the completion of `round` in the set `set_id`.
+	pub(crate) fn note_round_completed(&self, set_id: SetId, round: RoundNumber) {
+		if let Some(votes) = self.inner.write().as_mut()
+			.and_then(|state| state.round_mut(set_id, round))
+		{
+			votes.completed = true;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::crypto::Public;
+	use substrate_test_runtime_client::runtime::Block;
+
+	fn id(byte: u8) -> AuthorityId {
+		AuthorityId::from_slice(&[byte; 32])
+	}
+
+	fn vote(voter: u8, precommit: bool) -> SignedMessage<Block> {
+		let target_hash = Default::default();
+		let message = if precommit {
+			Message::Precommit(finality_grandpa::Precommit { target_hash, target_number: 1 })
+		} else {
+			Message::Prevote(finality_grandpa::Prevote { target_hash, target_number: 1 })
+		};
+		SignedMessage::<Block> {
+			message,
+			signature: Default::default(),
+			id: id(voter),
+		}
+	}
+
+	#[test]
+	fn keeps_votes_of_recent_rounds() {
+		let voters: VoterSet<AuthorityId> = vec![(id(1), 1), (id(2), 1), (id(3), 1)]
+			.into_iter()
+			.collect();
+		let state = SharedRoundState::<Block>::empty();
+		assert!(state.get().is_none());
+
+		state.note_round_started(0, &voters, Some(id(1)), 1);
+		state.note_vote(0, 1, &vote(1, false));
+		state.note_vote(0, 1, &vote(1, false));
+		state.note_vote(0, 1, &vote(2, false));
+		state.note_vote(0, 1, &vote(2, true));
+		// votes of rounds or sets not started are ignored.
+		state.note_vote(0, 2, &vote(3, false));
+		state.note_vote(1, 1, &vote(3, false));
+		state.note_round_completed(0, 1);
+
+		let rounds = state.get().unwrap();
+		assert_eq!(rounds.threshold, 3);
+		assert_eq!(rounds.local_id, Some(id(1)));
+		let round = rounds.round(1).unwrap();
+		assert!(round.completed);
+		assert_eq!(round.prevotes, vec![id(1), id(2)]);
+		assert_eq!(round.precommits.len(), 1);
+		assert_eq!(round.precommits[0].id, id(2));
+
+		for round in 2..6 {
+			state.note_round_started(0, &voters, Some(id(1)), round);
+		}
+		let rounds: Vec<_> = state.get().unwrap().rounds.iter().map(|votes| votes.round).collect();
+		assert_eq!(rounds, vec![3, 4, 5]);
+
+		state.note_round_started(1, &voters, None, 1);
+		let rounds = state.get().unwrap();
+		assert_eq!(rounds.set_id, 1);
+		assert_eq!(rounds.rounds.len(), 1);
+		assert!(rounds.round(1).unwrap().prevotes.is_empty());
+	}
+}
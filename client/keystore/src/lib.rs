@@ -18,7 +18,10 @@
 
 #![warn(missing_docs)]
 
-use std::{collections::HashMap, path::PathBuf, fs::{self, File}, io::{self, Write}, sync::Arc};
+use std::{
+	collections::HashMap, path::{Path, PathBuf}, fs::{self, File}, io::{self, Write}, sync::Arc,
+	thread::{self, ThreadId},
+};
 use sp_core::{
 	crypto::{KeyTypeId, Pair as PairT, Public, IsWrappedBy, Protected}, traits::BareCryptoStore,
 };
@@ -28,6 +31,11 @@ use parking_lot::RwLock;
 /// Keystore pointer
 pub type KeyStorePtr = Arc<RwLock<Store>>;
 
+/// Name of the intent log file of a batch, within the keystore directory.
+const INTENT_LOG: &str = "intent.log";
+/// Extension of the key files that are written but not placed into the store yet.
+const PENDING_EXTENSION: &str = "pending";
+
 /// Keystore error.
 #[derive(Debug, derive_more::Display, derive_more::From)]
 pub enum Error {
@@ -47,6 +55,12 @@ pub enum Error {
 	/// Keystore unavailable
 	#[display(fmt="Keystore unavailable")]
 	Unavailable,
+	/// A batch is already in progress
+	#[display(fmt="A batch of key insertions is already in progress")]
+	BatchInProgress,
+	/// The batch was started by another thread
+	#[display(fmt="The batch of key insertions was started by another thread")]
+	BatchNotOwned,
 }
 
 /// Keystore Result
@@ -72,17 +86,24 @@ pub struct Store {
 	/// Map over `(KeyTypeId, Raw public key)` -> `Key phrase/seed`
 	additional: HashMap<(KeyTypeId, Vec<u8>), String>,
 	password: Option<Protected<String>>,
+	/// The thread that started the current batch, if any. Only the keys written to the file
+	/// system store by that thread are part of the batch, the other threads write in place.
+	batch: Option<ThreadId>,
 }
 
 impl Store {
 	/// Open the store at the given path.
 	///
 	/// Optionally takes a password that will be used to encrypt/decrypt the keys.
+	///
+	/// A batch of key insertions that was interrupted by a crash is completed if it was committed,
+	/// and discarded otherwise.
 	pub fn open<T: Into<PathBuf>>(path: T, password: Option<Protected<String>>) -> Result<KeyStorePtr> {
 		let path = path.into();
 		fs::create_dir_all(&path)?;
+		recover_batch(&path)?;
 
-		let instance = Self { path: Some(path), additional: HashMap::new(), password };
+		let instance = Self { path: Some(path), additional: HashMap::new(), password, batch: None };
 		Ok(Arc::new(RwLock::new(instance)))
 	}
 
@@ -91,7 +112,8 @@ impl Store {
 		Arc::new(RwLock::new(Self {
 			path: None,
 			additional: HashMap::new(),
-			password: None
+			password: None,
+			batch: None,
 		}))
 	}
 
@@ -117,12 +139,7 @@ impl Store {
 	///
 	/// Places it into the file system store.
 	fn insert_unknown(&self, key_type: KeyTypeId, suri: &str, public: &[u8]) -> Result<()> {
-		if let Some(path) = self.key_file_path(public, key_type) {
-			let mut file = File::create(path).map_err(Error::Io)?;
-			serde_json::to_writer(&file, &suri).map_err(Error::Json)?;
-			file.flush().map_err(Error::Io)?;
-		}
-		Ok(())
+		self.write_key_file(public, key_type, suri)
 	}
 
 	/// Insert a new key.
@@ -150,11 +167,7 @@ impl Store {
 	/// Places it into the file system store.
 	pub fn generate_by_type<Pair: PairT>(&self, key_type: KeyTypeId) -> Result<Pair> {
 		let (pair, phrase, _) = Pair::generate_with_phrase(self.password.as_ref().map(|p| &***p));
-		if let Some(path) = self.key_file_path(pair.public().as_slice(), key_type) {
-			let mut file = File::create(path)?;
-			serde_json::to_writer(&file, &phrase)?;
-			file.flush()?;
-		}
+		self.write_key_file(pair.public().as_slice(), key_type, &phrase)?;
 		Ok(pair)
 	}
 
@@ -220,17 +233,9 @@ impl Store {
 		self.key_pair_by_type::<Pair::Generic>(IsWrappedBy::from_ref(public), Pair::ID).map(Into::into)
 	}
 
-	/// Get public keys of all stored keys that match the given key type.
-	pub fn public_keys_by_type<TPublic: Public>(&self, key_type: KeyTypeId) -> Result<Vec<TPublic>> {
-		let mut public_keys: Vec<TPublic> = self.additional.keys()
-			.filter_map(|(ty, public)| {
-				if *ty == key_type {
-					Some(TPublic::from_slice(public))
-				} else {
-					None
-				}
-			})
-			.collect();
+	/// Get the key types and raw public keys of all stored keys.
+	pub fn keys(&self) -> Result<Vec<(KeyTypeId, Vec<u8>)>> {
+		let mut keys: Vec<_> = self.additional.keys().cloned().collect();
 
 		if let Some(path) = &self.path {
 			for entry in fs::read_dir(&path)? {
@@ -241,9 +246,9 @@ impl Store {
 				if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
 					match hex::decode(name) {
 						Ok(ref hex) if hex.len() > 4 => {
-							if &hex[0..4] != &key_type.0 { continue }
-							let public = TPublic::from_slice(&hex[4..]);
-							public_keys.push(public);
+							let mut key_type = KeyTypeId::default();
+							key_type.0.copy_from_slice(&hex[0..4]);
+							keys.push((key_type, hex[4..].to_vec()));
 						}
 						_ => continue,
 					}
@@ -251,7 +256,18 @@ impl Store {
 			}
 		}
 
-		Ok(public_keys)
+		Ok(keys)
+	}
+
+	/// Get public keys of all stored keys that match the given key type.
+	pub fn public_keys_by_type<TPublic: Public>(&self, key_type: KeyTypeId) -> Result<Vec<TPublic>> {
+		Ok(
+			self.keys()?
+				.into_iter()
+				.filter(|(ty, _)| *ty == key_type)
+				.map(|(_, public)| TPublic::from_slice(&public))
+				.collect()
+		)
 	}
 
 	/// Get public keys of all stored keys that match the key type.
@@ -265,6 +281,108 @@ impl Store {
 			.map(|v| v.into_iter().map(Into::into).collect())
 	}
 
+	/// Write the key phrase of the given key to the file system store.
+	///
+	/// The phrase is first written to a pending file. Outside of a batch, the pending file is then
+	/// moved in place right away. Within a batch, it is recorded in the intent log and only moved
+	/// in place when the batch is committed.
+	fn write_key_file(&self, public: &[u8], key_type: KeyTypeId, phrase: &str) -> Result<()> {
+		let path = match self.key_file_path(public, key_type) {
+			Some(path) => path,
+			None => return Ok(()),
+		};
+
+		let batch = self.owns_batch();
+		if batch {
+			let name = path.file_name().and_then(|n| n.to_str()).ok_or_else(|| Error::Unavailable)?;
+			self.append_intent(&format!("prepare {}", name))?;
+		}
+
+		let pending = path.with_extension(PENDING_EXTENSION);
+		let mut file = File::create(&pending)?;
+		serde_json::to_writer(&file, phrase)?;
+		file.flush()?;
+		file.sync_all()?;
+
+		if !batch {
+			fs::rename(pending, path)?;
+		}
+		Ok(())
+	}
+
+	/// Whether the current thread started the current batch.
+	fn owns_batch(&self) -> bool {
+		self.batch == Some(thread::current().id())
+	}
+
+	/// Checks that there is no batch, or that it was started by the current thread. Returns
+	/// whether there is a batch.
+	fn check_batch_owner(&self) -> Result<bool> {
+		match self.batch {
+			None => Ok(false),
+			Some(_) if self.owns_batch() => Ok(true),
+			Some(_) => Err(Error::BatchNotOwned),
+		}
+	}
+
+	/// Start a batch of key insertions on the current thread.
+	///
+	/// Until the batch is committed, the keys written to the file system store by the current
+	/// thread are not available. If the node crashes before the batch is committed, they are
+	/// discarded. The keys written by the other threads meanwhile are not part of the batch, and
+	/// only the current thread can commit or abort it.
+	pub fn begin_batch(&mut self) -> Result<()> {
+		if self.batch.is_some() {
+			return Err(Error::BatchInProgress)
+		}
+
+		if let Some(path) = &self.path {
+			File::create(path.join(INTENT_LOG))?.sync_all()?;
+		}
+		self.batch = Some(thread::current().id());
+		Ok(())
+	}
+
+	/// Place the keys written since the start of the batch into the file system store.
+	pub fn commit_batch(&mut self) -> Result<()> {
+		if !self.check_batch_owner()? {
+			return Ok(())
+		}
+
+		self.append_intent("commit")?;
+		self.batch = None;
+		match &self.path {
+			Some(path) => recover_batch(path),
+			None => Ok(()),
+		}
+	}
+
+	/// Discard the keys written since the start of the batch.
+	pub fn abort_batch(&mut self) -> Result<()> {
+		if !self.check_batch_owner()? {
+			return Ok(())
+		}
+
+		self.batch = None;
+		match &self.path {
+			Some(path) => recover_batch(path),
+			None => Ok(()),
+		}
+	}
+
+	/// Append an entry to the intent log and make sure it reached the disk.
+	fn append_intent(&self, entry: &str) -> Result<()> {
+		if let Some(path) = &self.path {
+			let mut log = fs::OpenOptions::new()
+				.create(true)
+				.append(true)
+				.open(path.join(INTENT_LOG))?;
+			writeln!(log, "{}", entry)?;
+			log.sync_all()?;
+		}
+		Ok(())
+	}
+
 	/// Returns the file path for the given public key and key type.
 	fn key_file_path(&self, public: &[u8], key_type: KeyTypeId) -> Option<PathBuf> {
 		let mut buf = self.path.as_ref()?.clone();
@@ -275,6 +393,49 @@ impl Store {
 	}
 }
 
+/// Complete or discard the batch recorded in the intent log of the given keystore directory.
+///
+/// The pending key files of the batch are moved in place if the batch was committed, and removed
+/// otherwise. The intent log is removed afterwards.
+fn recover_batch(path: &Path) -> Result<()> {
+	let log_path = path.join(INTENT_LOG);
+	let log = match fs::read_to_string(&log_path) {
+		Ok(log) => log,
+		Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+		Err(e) => return Err(e.into()),
+	};
+
+	let mut committed = false;
+	let mut names = Vec::new();
+	for line in log.lines() {
+		let mut entry = line.splitn(2, ' ');
+		match (entry.next(), entry.next()) {
+			// key file names are hex encoded, anything else is a torn write.
+			(Some("prepare"), Some(name)) if hex::decode(name).is_ok() => names.push(name),
+			(Some("commit"), None) => committed = true,
+			_ => {},
+		}
+	}
+
+	for name in names {
+		let key_path = path.join(name);
+		let pending = key_path.with_extension(PENDING_EXTENSION);
+		let result = if committed {
+			fs::rename(&pending, &key_path)
+		} else {
+			fs::remove_file(&pending)
+		};
+
+		match result {
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+			result => result?,
+		}
+	}
+
+	fs::remove_file(log_path)?;
+	Ok(())
+}
+
 impl BareCryptoStore for Store {
 	fn sr25519_public_keys(&self, key_type: KeyTypeId) -> Vec<sr25519::Public> {
 		self.public_keys_by_type::<sr25519::Public>(key_type).unwrap_or_default()
@@ -331,6 +492,18 @@ impl BareCryptoStore for Store {
 	fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
 		public_keys.iter().all(|(p, t)| self.key_phrase_by_type(&p, *t).is_ok())
 	}
+
+	fn begin_batch(&mut self) -> std::result::Result<(), String> {
+		Store::begin_batch(self).map_err(|e| e.to_string())
+	}
+
+	fn commit_batch(&mut self) -> std::result::Result<(), String> {
+		Store::commit_batch(self).map_err(|e| e.to_string())
+	}
+
+	fn abort_batch(&mut self) -> std::result::Result<(), String> {
+		Store::abort_batch(self).map_err(|e| e.to_string())
+	}
 }
 
 #[cfg(test)]
@@ -454,4 +627,100 @@ mod tests {
 			store.read().public_keys_by_type::<sr25519::AppPublic>(SR25519).unwrap().is_empty(),
 		);
 	}
+
+	#[test]
+	fn keys_of_all_types_are_returned() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = Store::open(temp_dir.path(), None).unwrap();
+
+		let sr25519_pair = store.write().generate::<sr25519::AppPair>().unwrap();
+		let ed25519_pair = store.write().generate::<ed25519::AppPair>().unwrap();
+
+		let mut keys = store.read().keys().unwrap();
+		keys.sort();
+		let mut expected = vec![
+			(sr25519::AppPublic::ID, sr25519_pair.public().to_raw_vec()),
+			(ed25519::AppPublic::ID, ed25519_pair.public().to_raw_vec()),
+		];
+		expected.sort();
+
+		assert_eq!(keys, expected);
+	}
+
+	#[test]
+	fn batch_keys_are_only_available_once_committed() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = Store::open(temp_dir.path(), None).unwrap();
+
+		store.write().begin_batch().unwrap();
+		assert!(store.write().begin_batch().is_err());
+		let ed_key = store.write().generate::<ed25519::AppPair>().unwrap();
+		let sr_key = store.write().generate::<sr25519::AppPair>().unwrap();
+		assert!(store.read().public_keys::<ed25519::AppPublic>().unwrap().is_empty());
+
+		store.write().commit_batch().unwrap();
+		assert!(store.read().key_pair::<ed25519::AppPair>(&ed_key.public()).is_ok());
+		assert!(store.read().key_pair::<sr25519::AppPair>(&sr_key.public()).is_ok());
+		assert!(!temp_dir.path().join(INTENT_LOG).exists());
+	}
+
+	#[test]
+	fn aborted_batch_keys_are_discarded() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = Store::open(temp_dir.path(), None).unwrap();
+		let key = store.write().generate::<ed25519::AppPair>().unwrap();
+
+		store.write().begin_batch().unwrap();
+		store.write().generate::<ed25519::AppPair>().unwrap();
+		store.write().abort_batch().unwrap();
+
+		assert_eq!(store.read().public_keys::<ed25519::AppPublic>().unwrap(), vec![key.public()]);
+		assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+	}
+
+	#[test]
+	fn interrupted_batch_is_recovered_on_open() {
+		let temp_dir = TempDir::new().unwrap();
+
+		// crash before the batch is committed.
+		let store = Store::open(temp_dir.path(), None).unwrap();
+		store.write().begin_batch().unwrap();
+		store.write().generate::<ed25519::AppPair>().unwrap();
+		drop(store);
+
+		let store = Store::open(temp_dir.path(), None).unwrap();
+		assert!(store.read().public_keys::<ed25519::AppPublic>().unwrap().is_empty());
+		assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+		// crash after the batch is committed, before the keys are moved in place.
+		store.write().begin_batch().unwrap();
+		let key = store.write().generate::<ed25519::AppPair>().unwrap();
+		store.read().append_intent("commit").unwrap();
+		drop(store);
+
+		let store = Store::open(temp_dir.path(), None).unwrap();
+		assert_eq!(store.read().public_keys::<ed25519::AppPublic>().unwrap(), vec![key.public()]);
+		assert!(!temp_dir.path().join(INTENT_LOG).exists());
+	}
+
+	#[test]
+	fn batch_only_contains_the_keys_of_its_thread() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = Store::open(temp_dir.path(), None).unwrap();
+
+		store.write().begin_batch().unwrap();
+		let batch_key = store.write().generate::<ed25519::AppPair>().unwrap();
+
+		let other_store = store.clone();
+		let other_key = std::thread::spawn(move || {
+			assert!(other_store.write().begin_batch().is_err());
+			assert!(other_store.write().abort_batch().is_err());
+			other_store.write().generate::<ed25519::AppPair>().unwrap()
+		}).join().unwrap();
+		assert!(store.read().key_pair::<ed25519::AppPair>(&other_key.public()).is_ok());
+
+		store.write().abort_batch().unwrap();
+		assert!(store.read().key_pair::<ed25519::AppPair>(&other_key.public()).is_ok());
+		assert!(store.read().key_pair::<ed25519::AppPair>(&batch_key.public()).is_err());
+	}
 }
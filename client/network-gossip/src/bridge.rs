@@ -15,12 +15,15 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{Network, Validator};
-use crate::state_machine::{ConsensusGossip, TopicNotification, PERIODIC_MAINTENANCE_INTERVAL};
+use crate::state_machine::{
+	ConsensusGossip, GossipConfig, TopicNotification, PERIODIC_MAINTENANCE_INTERVAL,
+};
 
 use sc_network::{Event, ReputationChange};
 
-use futures::{prelude::*, channel::mpsc};
+use futures::prelude::*;
 use libp2p::PeerId;
+use prometheus_endpoint::Registry;
 use sp_runtime::{traits::Block as BlockT, ConsensusEngineId};
 use std::{borrow::Cow, pin::Pin, sync::Arc, task::{Context, Poll}};
 
@@ -37,14 +40,29 @@ pub struct GossipEngine<B: BlockT> {
 impl<B: BlockT> Unpin for GossipEngine<B> {}
 
 impl<B: BlockT> GossipEngine<B> {
-	/// Create a new instance.
+	/// Create a new instance with the default queue sizes.
 	pub fn new<N: Network<B> + Send + Clone + 'static>(
+		network: N,
+		engine_id: ConsensusEngineId,
+		protocol_name: impl Into<Cow<'static, [u8]>>,
+		validator: Arc<dyn Validator<B>>,
+	) -> Self where B: 'static {
+		let config = GossipConfig::default();
+		Self::with_config(network, engine_id, protocol_name, validator, config, None)
+	}
+
+	/// Create a new instance with the given queue sizes, reporting the messages dropped because
+	/// of them to `prometheus_registry`.
+	pub fn with_config<N: Network<B> + Send + Clone + 'static>(
 		mut network: N,
 		engine_id: ConsensusEngineId,
 		protocol_name: impl Into<Cow<'static, [u8]>>,
 		validator: Arc<dyn Validator<B>>,
+		config: GossipConfig,
+		prometheus_registry: Option<&Registry>,
 	) -> Self where B: 'static {
-		let mut state_machine = ConsensusGossip::new();
+		let mut state_machine =
+			ConsensusGossip::with_config(config, engine_id, prometheus_registry);
 
 		// We grab the event stream before registering the notifications protocol, otherwise we
 		// might miss events.
@@ -86,7 +104,7 @@ impl<B: BlockT> GossipEngine<B> {
 
 	/// Get data of valid, incoming messages for a topic (but might have expired meanwhile).
 	pub fn messages_for(&mut self, topic: B::Hash)
-		-> mpsc::UnboundedReceiver<TopicNotification>
+		-> Pin<Box<dyn Stream<Item = TopicNotification> + Send>>
 	{
 		self.state_machine.messages_for(self.engine_id, topic)
 	}
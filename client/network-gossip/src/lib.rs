@@ -34,7 +34,8 @@
 //!   already implemented on `sc_network::NetworkService`.
 //! - Implement the `Validator` trait. See the section below.
 //! - Decide on a `ConsensusEngineId`. Each gossiping protocol should have a different one.
-//! - Build a `GossipEngine` using these three elements.
+//! - Build a `GossipEngine` using these three elements, and optionally a `GossipConfig` bounding
+//!   the queues of the messages.
 //! - Use the methods of the `GossipEngine` in order to send out messages and receive incoming
 //!   messages.
 //!
@@ -55,7 +56,7 @@
 //! used to inform peers of a current view of protocol state.
 
 pub use self::bridge::GossipEngine;
-pub use self::state_machine::TopicNotification;
+pub use self::state_machine::{GossipConfig, TopicNotification};
 pub use self::validator::{DiscardAll, MessageIntent, Validator, ValidatorContext, ValidationResult};
 
 use futures::prelude::*;
@@ -16,14 +16,16 @@
 
 use crate::{Network, MessageIntent, Validator, ValidatorContext, ValidationResult};
 
-use std::collections::{HashMap, HashSet, hash_map::Entry};
+use std::collections::{HashMap, VecDeque, hash_map::Entry};
 use std::sync::Arc;
 use std::iter;
+use std::pin::Pin;
 use std::time;
-use log::trace;
-use futures::channel::mpsc;
+use log::{debug, trace};
+use futures::{prelude::*, channel::mpsc};
 use lru::LruCache;
 use libp2p::PeerId;
+use prometheus_endpoint::{CounterVec, Opts, PrometheusError, register, Registry, U64};
 use sp_runtime::traits::{Block as BlockT, Hash, HashFor};
 use sp_runtime::ConsensusEngineId;
 use sc_network::config::Roles;
@@ -49,11 +51,118 @@ mod rep {
 	pub const UNREGISTERED_TOPIC: Rep = Rep::new(-(1 << 10), "Unregistered gossip message topic");
 }
 
-struct PeerConsensus<H> {
-	known_messages: HashSet<H>,
+/// Sizes of the queues of the gossip state machine, `None` for an unbounded queue.
+///
+/// Once a queue is full, the messages are dropped as documented for each field, and counted by
+/// the `network_gossip_dropped_messages_total` metric. The consensus-critical protocols, whose
+/// messages must not be dropped, should use `GossipConfig::unbounded`.
+#[derive(Debug, Clone)]
+pub struct GossipConfig {
+	/// Number of hashes of the messages known to be known by each peer. Once full, the least
+	/// recently seen hash is forgotten, and the message may be sent again to the peer. If `None`,
+	/// as many hashes as the state machine remembers messages are kept.
+	pub known_messages_per_peer: Option<usize>,
+	/// Number of messages kept to be sent to the peers. Once full, the oldest message is dropped.
+	pub max_messages: Option<usize>,
+	/// Number of messages waiting in each of the streams returned by `messages_for`. Once full,
+	/// the new messages are dropped until the stream is polled again.
+	pub topic_queue_size: Option<usize>,
+}
+
+impl GossipConfig {
+	/// Queues without bounds, never dropping a message before it expires.
+	pub fn unbounded() -> Self {
+		GossipConfig {
+			known_messages_per_peer: None,
+			max_messages: None,
+			topic_queue_size: None,
+		}
+	}
+}
+
+impl Default for GossipConfig {
+	fn default() -> Self {
+		GossipConfig {
+			known_messages_per_peer: Some(KNOWN_MESSAGES_CACHE_SIZE),
+			max_messages: Some(2 * KNOWN_MESSAGES_CACHE_SIZE),
+			topic_queue_size: Some(KNOWN_MESSAGES_CACHE_SIZE),
+		}
+	}
+}
+
+/// Sender of the messages of a topic to a stream returned by `messages_for`.
+enum TopicSink {
+	Bounded(mpsc::Sender<TopicNotification>),
+	Unbounded(mpsc::UnboundedSender<TopicNotification>),
+}
+
+impl TopicSink {
+	fn try_send(
+		&mut self,
+		notification: TopicNotification,
+	) -> Result<(), mpsc::TrySendError<TopicNotification>> {
+		match self {
+			TopicSink::Bounded(sink) => sink.try_send(notification),
+			TopicSink::Unbounded(sink) => sink.unbounded_send(notification),
+		}
+	}
+
+	fn is_closed(&self) -> bool {
+		match self {
+			TopicSink::Bounded(sink) => sink.is_closed(),
+			TopicSink::Unbounded(sink) => sink.is_closed(),
+		}
+	}
+}
+
+/// Prometheus metrics of the gossip state machine.
+struct Metrics {
+	dropped_messages: CounterVec<U64>,
+}
+
+impl Metrics {
+	fn register(
+		registry: &Registry,
+		engine_id: ConsensusEngineId,
+	) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			dropped_messages: register(
+				CounterVec::new(
+					Opts::new(
+						"network_gossip_dropped_messages_total",
+						"Number of messages dropped by the gossip state machine because a queue \
+						was full."
+					).const_label("engine_id", String::from_utf8_lossy(&engine_id[..])),
+					&["queue"]
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	fn on_dropped(&self, queue: &str) {
+		self.dropped_messages.with_label_values(&[queue]).inc();
+	}
+}
+
+struct PeerConsensus<H: std::hash::Hash + Eq> {
+	known_messages: LruCache<H, ()>,
 	roles: Roles,
 }
 
+impl<H: std::hash::Hash + Eq> PeerConsensus<H> {
+	/// Notes that the peer knows the message, forgetting the least recently seen one if the
+	/// cache is full.
+	fn note_known(&mut self, message_hash: H, metrics: Option<&Metrics>) {
+		let full = self.known_messages.len() >= self.known_messages.cap();
+		if self.known_messages.put(message_hash, ()).is_none() && full {
+			if let Some(metrics) = metrics {
+				metrics.on_dropped("peer_known");
+			}
+		}
+	}
+}
+
 /// Topic stream message with sender.
 #[derive(Debug, Eq, PartialEq)]
 pub struct TopicNotification {
@@ -112,6 +221,7 @@ fn propagate<'a, B: BlockT, I>(
 	intent: MessageIntent,
 	peers: &mut HashMap<PeerId, PeerConsensus<B::Hash>>,
 	validators: &HashMap<ConsensusEngineId, Arc<dyn Validator<B>>>,
+	metrics: Option<&Metrics>,
 )
 	// (msg_hash, topic, message)
 	where I: Clone + IntoIterator<Item=(&'a B::Hash, &'a B::Hash, ConsensusEngineId, &'a Vec<u8>)>,
@@ -153,7 +263,7 @@ fn propagate<'a, B: BlockT, I>(
 				continue;
 			}
 
-			peer.known_messages.insert(message_hash.clone());
+			peer.note_known(message_hash.clone(), metrics);
 
 			trace!(target: "gossip", "Propagating to {}: {:?}", id, message);
 			network.write_notification(id.clone(), engine_id, message.clone());
@@ -164,23 +274,50 @@ fn propagate<'a, B: BlockT, I>(
 /// Consensus network protocol handler. Manages statements and candidate requests.
 pub struct ConsensusGossip<B: BlockT> {
 	peers: HashMap<PeerId, PeerConsensus<B::Hash>>,
-	live_message_sinks: HashMap<(ConsensusEngineId, B::Hash), Vec<mpsc::UnboundedSender<TopicNotification>>>,
-	messages: Vec<MessageEntry<B>>,
+	live_message_sinks: HashMap<(ConsensusEngineId, B::Hash), Vec<TopicSink>>,
+	messages: VecDeque<MessageEntry<B>>,
 	known_messages: LruCache<B::Hash, ()>,
 	validators: HashMap<ConsensusEngineId, Arc<dyn Validator<B>>>,
 	next_broadcast: Instant,
+	config: GossipConfig,
+	metrics: Option<Metrics>,
 }
 
 impl<B: BlockT> ConsensusGossip<B> {
-	/// Create a new instance.
+	/// Create a new instance with the default queue sizes.
 	pub fn new() -> Self {
+		Self::with_config(GossipConfig::default(), [0, 0, 0, 0], None)
+	}
+
+	/// Create a new instance with the given queue sizes, reporting the dropped messages of
+	/// `engine_id` to `prometheus_registry`.
+	pub fn with_config(
+		config: GossipConfig,
+		engine_id: ConsensusEngineId,
+		prometheus_registry: Option<&Registry>,
+	) -> Self {
+		let metrics = prometheus_registry.map(|registry| Metrics::register(registry, engine_id));
+		let metrics = match metrics {
+			Some(Ok(metrics)) => Some(metrics),
+			Some(Err(e)) => {
+				debug!(target: "gossip", "Failed to register metrics: {:?}", e);
+				None
+			},
+			None => None,
+		};
+
 		ConsensusGossip {
 			peers: HashMap::new(),
 			live_message_sinks: HashMap::new(),
 			messages: Default::default(),
-			known_messages: LruCache::new(KNOWN_MESSAGES_CACHE_SIZE),
+			// the messages kept are all known, so that they aren't registered twice.
+			known_messages: LruCache::new(
+				KNOWN_MESSAGES_CACHE_SIZE.max(config.max_messages.unwrap_or(0)),
+			),
 			validators: Default::default(),
 			next_broadcast: Instant::now() + REBROADCAST_INTERVAL,
+			config,
+			metrics,
 		}
 	}
 
@@ -211,8 +348,11 @@ impl<B: BlockT> ConsensusGossip<B> {
 		}
 
 		trace!(target:"gossip", "Registering {:?} {}", roles, who);
+		// the hashes of the messages known by a peer are pruned along with the messages anyway.
+		let known_messages = self.config.known_messages_per_peer
+			.unwrap_or_else(|| self.known_messages.cap());
 		self.peers.insert(who.clone(), PeerConsensus {
-			known_messages: HashSet::new(),
+			known_messages: LruCache::new(known_messages.max(1)),
 			roles,
 		});
 		for (engine_id, v) in self.validators.clone() {
@@ -230,7 +370,15 @@ impl<B: BlockT> ConsensusGossip<B> {
 		sender: Option<PeerId>,
 	) {
 		if self.known_messages.put(message_hash.clone(), ()).is_none() {
-			self.messages.push(MessageEntry {
+			let full = self.config.max_messages.map_or(false, |max| self.messages.len() >= max);
+			if full {
+				self.messages.pop_front();
+				trace!(target: "gossip", "Too many messages kept, dropped the oldest");
+				if let Some(metrics) = self.metrics.as_ref() {
+					metrics.on_dropped("messages");
+				}
+			}
+			self.messages.push_back(MessageEntry {
 				message_hash,
 				topic,
 				engine_id,
@@ -277,7 +425,14 @@ impl<B: BlockT> ConsensusGossip<B> {
 	fn rebroadcast(&mut self, network: &mut dyn Network<B>) {
 		let messages = self.messages.iter()
 			.map(|entry| (&entry.message_hash, &entry.topic, entry.engine_id, &entry.message));
-		propagate(network, messages, MessageIntent::PeriodicRebroadcast, &mut self.peers, &self.validators);
+		propagate(
+			network,
+			messages,
+			MessageIntent::PeriodicRebroadcast,
+			&mut self.peers,
+			&self.validators,
+			self.metrics.as_ref(),
+		);
 	}
 
 	/// Broadcast all messages with given topic.
@@ -289,7 +444,8 @@ impl<B: BlockT> ConsensusGossip<B> {
 				} else { None }
 			);
 		let intent = if force { MessageIntent::ForcedBroadcast } else { MessageIntent::Broadcast };
-		propagate(network, messages, intent, &mut self.peers, &self.validators);
+		let metrics = self.metrics.as_ref();
+		propagate(network, messages, intent, &mut self.peers, &self.validators, metrics);
 	}
 
 	/// Prune old or no longer relevant consensus messages. Provide a predicate
@@ -327,23 +483,47 @@ impl<B: BlockT> ConsensusGossip<B> {
 		);
 
 		for (_, ref mut peer) in self.peers.iter_mut() {
-			peer.known_messages.retain(|h| known_messages.contains(h));
+			let forgotten = peer.known_messages.iter()
+				.filter(|(h, _)| !known_messages.contains(h))
+				.map(|(h, _)| h.clone())
+				.collect::<Vec<_>>();
+			for h in forgotten {
+				peer.known_messages.pop(&h);
+			}
 		}
 	}
 
-	/// Get data of valid, incoming messages for a topic (but might have expired meanwhile)
+	/// Get data of valid, incoming messages for a topic (but might have expired meanwhile).
+	///
+	/// At most `GossipConfig::topic_queue_size` messages wait in the stream, the new ones are
+	/// dropped while it is full.
 	pub fn messages_for(&mut self, engine_id: ConsensusEngineId, topic: B::Hash)
-		-> mpsc::UnboundedReceiver<TopicNotification>
+		-> Pin<Box<dyn Stream<Item = TopicNotification> + Send>>
 	{
-		let (tx, rx) = mpsc::unbounded();
-		for entry in self.messages.iter_mut()
+		let (mut tx, rx) = match self.config.topic_queue_size {
+			Some(size) => {
+				// the capacity of the channel is one more than its buffer, for its single sender.
+				let (tx, rx) = mpsc::channel(size.saturating_sub(1));
+				(TopicSink::Bounded(tx), rx.boxed())
+			},
+			None => {
+				let (tx, rx) = mpsc::unbounded();
+				(TopicSink::Unbounded(tx), rx.boxed())
+			},
+		};
+		for entry in self.messages.iter()
 			.filter(|e| e.topic == topic && e.engine_id == engine_id)
 		{
-			tx.unbounded_send(TopicNotification {
-					message: entry.message.clone(),
-					sender: entry.sender.clone(),
-				})
-				.expect("receiver known to be live; qed");
+			let notification = TopicNotification {
+				message: entry.message.clone(),
+				sender: entry.sender.clone(),
+			};
+			if tx.try_send(notification).is_err() {
+				trace!(target: "gossip", "Topic queue full, dropped message for {}", topic);
+				if let Some(metrics) = self.metrics.as_ref() {
+					metrics.on_dropped("topic");
+				}
+			}
 		}
 
 		self.live_message_sinks.entry((engine_id, topic)).or_default().push(tx);
@@ -397,18 +577,34 @@ impl<B: BlockT> ConsensusGossip<B> {
 			if let Some((topic, keep)) = validation_result {
 				network.report_peer(who.clone(), rep::GOSSIP_SUCCESS);
 				if let Some(ref mut peer) = self.peers.get_mut(&who) {
-					peer.known_messages.insert(message_hash);
+					peer.note_known(message_hash, self.metrics.as_ref());
 					if let Entry::Occupied(mut entry) = self.live_message_sinks.entry((engine_id, topic)) {
 						trace!(target: "gossip", "Pushing consensus message to sinks for {}.", topic);
-						entry.get_mut().retain(|sink| {
-							if let Err(e) = sink.unbounded_send(TopicNotification {
+						for sink in entry.get_mut().iter_mut() {
+							let notification = TopicNotification {
 								message: message.clone(),
 								sender: Some(who.clone())
-							}) {
-								trace!(target: "gossip", "Error broadcasting message notification: {:?}", e);
+							};
+							match sink.try_send(notification) {
+								Err(ref e) if e.is_full() => {
+									trace!(
+										target: "gossip",
+										"Topic queue full, dropped message for {}",
+										topic,
+									);
+									if let Some(metrics) = self.metrics.as_ref() {
+										metrics.on_dropped("topic");
+									}
+								},
+								Err(e) => trace!(
+									target: "gossip",
+									"Error broadcasting message notification: {:?}",
+									e,
+								),
+								Ok(()) => {},
 							}
-							!sink.is_closed()
-						});
+						}
+						entry.get_mut().retain(|sink| !sink.is_closed());
 						if entry.get().is_empty() {
 							entry.remove_entry();
 						}
@@ -457,7 +653,7 @@ impl<B: BlockT> ConsensusGossip<B> {
 					continue;
 				}
 
-				peer.known_messages.insert(entry.message_hash.clone());
+				peer.note_known(entry.message_hash.clone(), self.metrics.as_ref());
 
 				trace!(target: "gossip", "Sending topic message to {}: {:?}", who, entry.message);
 				network.write_notification(who.clone(), engine_id, entry.message.clone());
@@ -477,7 +673,14 @@ impl<B: BlockT> ConsensusGossip<B> {
 		let message_hash = HashFor::<B>::hash(&message);
 		self.register_message_hashed(message_hash, topic, engine_id, message.clone(), None);
 		let intent = if force { MessageIntent::ForcedBroadcast } else { MessageIntent::Broadcast };
-		propagate(network, iter::once((&message_hash, &topic, engine_id, &message)), intent, &mut self.peers, &self.validators);
+		propagate(
+			network,
+			iter::once((&message_hash, &topic, engine_id, &message)),
+			intent,
+			&mut self.peers,
+			&self.validators,
+			self.metrics.as_ref(),
+		);
 	}
 
 	/// Send addressed message to a peer. The message is not kept or multicast
@@ -498,7 +701,7 @@ impl<B: BlockT> ConsensusGossip<B> {
 
 		trace!(target: "gossip", "Sending direct to {}: {:?}", who, message);
 
-		peer.known_messages.insert(message_hash);
+		peer.note_known(message_hash, self.metrics.as_ref());
 		network.write_notification(who.clone(), engine_id, message);
 	}
 }
@@ -516,7 +719,7 @@ mod tests {
 	macro_rules! push_msg {
 		($consensus:expr, $topic:expr, $hash: expr, $m:expr) => {
 			if $consensus.known_messages.put($hash, ()).is_none() {
-				$consensus.messages.push(MessageEntry {
+				$consensus.messages.push_back(MessageEntry {
 					message_hash: $hash,
 					topic: $topic,
 					engine_id: [0, 0, 0, 0],
@@ -656,6 +859,70 @@ mod tests {
 		assert_eq!(stream.next(), None);
 	}
 
+	#[test]
+	fn queues_are_bounded() {
+		let registry = Registry::new();
+		let config = GossipConfig {
+			known_messages_per_peer: Some(2),
+			max_messages: Some(2),
+			topic_queue_size: Some(1),
+		};
+		let mut consensus =
+			ConsensusGossip::<Block>::with_config(config, [0, 0, 0, 0], Some(&registry));
+		consensus.register_validator_internal([0, 0, 0, 0], Arc::new(AllowAll));
+		let dropped = |consensus: &ConsensusGossip<Block>, queue| consensus.metrics.as_ref()
+			.unwrap()
+			.dropped_messages
+			.with_label_values(&[queue])
+			.get();
+
+		let topic = HashFor::<Block>::hash(&[1, 2, 3]);
+		for message in vec![vec![1], vec![2], vec![3]] {
+			consensus.register_message(topic, [0, 0, 0, 0], message);
+		}
+		// the oldest message is dropped.
+		assert_eq!(consensus.messages.len(), 2);
+		assert_eq!(consensus.messages[0].message, vec![2]);
+		assert_eq!(dropped(&consensus, "messages"), 1);
+
+		// the messages that don't fit in the stream are dropped.
+		let stream = consensus.messages_for([0, 0, 0, 0], topic);
+		consensus.live_message_sinks.clear();
+		assert_eq!(
+			block_on_stream(stream).collect::<Vec<_>>(),
+			vec![TopicNotification { message: vec![2], sender: None }],
+		);
+		assert_eq!(dropped(&consensus, "topic"), 1);
+
+		let mut peer = PeerConsensus { known_messages: LruCache::new(2), roles: Roles::FULL };
+		for hash in vec![H256::random(), H256::random(), H256::random()] {
+			peer.note_known(hash, consensus.metrics.as_ref());
+		}
+		assert_eq!(peer.known_messages.len(), 2);
+		assert_eq!(dropped(&consensus, "peer_known"), 1);
+	}
+
+	#[test]
+	fn unbounded_queues_keep_all_messages() {
+		let mut consensus = ConsensusGossip::<Block>::with_config(
+			GossipConfig::unbounded(),
+			[0, 0, 0, 0],
+			None,
+		);
+		consensus.register_validator_internal([0, 0, 0, 0], Arc::new(AllowAll));
+
+		let topic = HashFor::<Block>::hash(&[1, 2, 3]);
+		let count = 2 * KNOWN_MESSAGES_CACHE_SIZE as u32 + 1;
+		for message in 0..count {
+			consensus.register_message(topic, [0, 0, 0, 0], message.to_le_bytes().to_vec());
+		}
+		assert_eq!(consensus.messages.len(), count as usize);
+
+		let stream = consensus.messages_for([0, 0, 0, 0], topic);
+		consensus.live_message_sinks.clear();
+		assert_eq!(block_on_stream(stream).count(), count as usize);
+	}
+
 	#[test]
 	fn peer_is_removed_on_disconnect() {
 		struct TestNetwork;
@@ -0,0 +1,44 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fuzzes the decoding of the messages of the legacy substream, which carry the sync, gossip
+//! and light client messages.
+//!
+//! # Running
+//! Running this fuzzer can be done with `cargo hfuzz run message`, setting
+//! `HFUZZ_INPUT=corpus/message` to start from the inputs of the corpus. `honggfuzz` CLI
+//! options can be used by setting `HFUZZ_RUN_ARGS`, such as `-n 4` to use 4 threads.
+//!
+//! # Debugging a panic
+//! Once a panic is found, it can be debugged with
+//! `cargo hfuzz run-debug message hfuzz_workspace/message/*.fuzz`.
+//! The input should then be added to `corpus/message`, whose inputs are decoded by the
+//! tests of `sc-network`.
+//!
+//! # More information
+//! More information about `honggfuzz` can be found
+//! [here](https://docs.rs/honggfuzz/).
+
+use honggfuzz::fuzz;
+use sp_runtime::testing::{Block, ExtrinsicWrapper};
+
+fn main() {
+	loop {
+		fuzz!(|data: &[u8]| {
+			let _ = sc_network::decode::message::<Block<ExtrinsicWrapper<u64>>>(data);
+		});
+	}
+}
@@ -0,0 +1,315 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The address book, persisting the known peers across restarts.
+//!
+//! The reputations of the peerset manager and the addresses of the peers are stored in the
+//! database, so that a restarted node reconnects to the good peers it knew and keeps ignoring
+//! the banned ones, instead of discovering the network from the bootnodes again.
+//!
+//! The outcome of the dials is recorded for each address, and the addresses that worked are
+//! dialed first on restart. The addresses that never worked are eventually forgotten.
+
+use codec::{Decode, Encode};
+use libp2p::{Multiaddr, PeerId};
+use log::warn;
+use std::{collections::HashMap, convert::TryFrom, time::Duration};
+use wasm_timer::SystemTime;
+
+use crate::chain::NetworkStateStore;
+
+/// Key under which the address book is stored.
+const ADDRESS_BOOK_KEY: &[u8] = b"address_book";
+
+/// Maximal number of peers that are stored.
+const MAX_PERSISTED_PEERS: usize = 1000;
+
+/// Maximal number of addresses stored for each peer.
+const MAX_ADDRESSES_PER_PEER: usize = 8;
+
+/// Number of failed dials after which an address that never worked is forgotten.
+const MAX_FAILURES: u32 = 5;
+
+/// Interval at which the address book is stored.
+pub const PERSIST_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// A peer of the address book.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersistedPeer {
+	pub peer_id: PeerId,
+	pub reputation: i32,
+	/// The addresses of the peer, the most reliable first.
+	pub addresses: Vec<PersistedAddress>,
+}
+
+/// An address of a peer, with the outcome of the dials to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersistedAddress {
+	pub address: Multiaddr,
+	/// When the peer was last reached through the address, in seconds since the UNIX epoch.
+	pub last_seen: Option<u64>,
+	/// Number of successful dials.
+	pub successes: u32,
+	/// Number of failed dials.
+	pub failures: u32,
+}
+
+impl PersistedAddress {
+	/// Creates an address that was never dialed.
+	pub fn new(address: Multiaddr) -> Self {
+		PersistedAddress { address, last_seen: None, successes: 0, failures: 0 }
+	}
+
+	/// Returns true if the address never worked, and was tried enough to give up on it.
+	fn is_hopeless(&self) -> bool {
+		self.successes == 0 && self.failures >= MAX_FAILURES
+	}
+
+	/// Sort key of the addresses, the most reliable first.
+	fn order(&self) -> (std::cmp::Reverse<i64>, std::cmp::Reverse<Option<u64>>) {
+		let score = i64::from(self.successes) - i64::from(self.failures);
+		(std::cmp::Reverse(score), std::cmp::Reverse(self.last_seen))
+	}
+}
+
+impl PersistedPeer {
+	/// Returns the entry of `address`, adding it if needed.
+	fn address_mut(&mut self, address: &Multiaddr) -> &mut PersistedAddress {
+		let position = match self.addresses.iter().position(|a| a.address == *address) {
+			Some(position) => position,
+			None => {
+				self.addresses.push(PersistedAddress::new(address.clone()));
+				self.addresses.len() - 1
+			},
+		};
+		&mut self.addresses[position]
+	}
+
+	/// Most recent time the peer was reached, through any of its addresses.
+	fn last_seen(&self) -> Option<u64> {
+		self.addresses.iter().filter_map(|a| a.last_seen).max()
+	}
+}
+
+/// The peers known to the node, and the outcome of the dials to their addresses.
+#[derive(Debug, Default)]
+pub struct AddressBook {
+	peers: HashMap<PeerId, PersistedPeer>,
+}
+
+impl AddressBook {
+	/// Loads the address book stored by `store`.
+	pub fn load(store: &dyn NetworkStateStore) -> Self {
+		let peers = load(store).into_iter()
+			.map(|peer| (peer.peer_id.clone(), peer))
+			.collect();
+		AddressBook { peers }
+	}
+
+	/// Returns the known peers.
+	pub fn peers(&self) -> impl Iterator<Item = &PersistedPeer> {
+		self.peers.values()
+	}
+
+	/// Returns the entry of `peer_id`, adding it if needed.
+	fn peer_mut(&mut self, peer_id: &PeerId) -> &mut PersistedPeer {
+		self.peers.entry(peer_id.clone()).or_insert_with(|| PersistedPeer {
+			peer_id: peer_id.clone(),
+			reputation: 0,
+			addresses: Vec::new(),
+		})
+	}
+
+	/// Call this method when `peer_id` was successfully dialed through `address`.
+	pub fn on_dial_success(&mut self, peer_id: &PeerId, address: &Multiaddr) {
+		let now = SystemTime::UNIX_EPOCH.elapsed().ok().map(|elapsed| elapsed.as_secs());
+		let entry = self.peer_mut(peer_id).address_mut(address);
+		entry.successes = entry.successes.saturating_add(1);
+		entry.last_seen = now.or(entry.last_seen);
+	}
+
+	/// Call this method when `peer_id` couldn't be reached through `address`.
+	pub fn on_dial_failure(&mut self, peer_id: &PeerId, address: &Multiaddr) {
+		let entry = self.peer_mut(peer_id).address_mut(address);
+		entry.failures = entry.failures.saturating_add(1);
+	}
+
+	/// Updates the reputations and addresses of the peers with the current ones, and stores the
+	/// address book with `store`.
+	pub fn store(
+		&mut self,
+		store: &dyn NetworkStateStore,
+		reputations: Vec<(PeerId, i32)>,
+		mut addresses_of_peer: impl FnMut(&PeerId) -> Vec<Multiaddr>,
+	) {
+		for (peer_id, reputation) in reputations {
+			let peer = self.peer_mut(&peer_id);
+			peer.reputation = reputation;
+			for address in addresses_of_peer(&peer_id) {
+				peer.address_mut(&address);
+			}
+		}
+		self::store(store, self.peers.values().cloned().collect());
+	}
+}
+
+/// Encoded form of a `PersistedPeer`.
+#[derive(Encode, Decode)]
+struct EncodedPeer {
+	peer_id: Vec<u8>,
+	reputation: i32,
+	addresses: Vec<EncodedAddress>,
+}
+
+/// Encoded form of a `PersistedAddress`.
+#[derive(Encode, Decode)]
+struct EncodedAddress {
+	address: Vec<u8>,
+	last_seen: Option<u64>,
+	successes: u32,
+	failures: u32,
+}
+
+/// Loads the peers stored by `store`. Returns nothing if they can't be read.
+pub fn load(store: &dyn NetworkStateStore) -> Vec<PersistedPeer> {
+	let encoded = match store.load_network_state(ADDRESS_BOOK_KEY) {
+		Ok(Some(encoded)) => encoded,
+		Ok(None) => return Vec::new(),
+		Err(err) => {
+			warn!(target: "sub-libp2p", "Failed to load the address book: {:?}", err);
+			return Vec::new()
+		}
+	};
+	let peers = match Vec::<EncodedPeer>::decode(&mut &encoded[..]) {
+		Ok(peers) => peers,
+		Err(err) => {
+			warn!(target: "sub-libp2p", "Failed to decode the address book: {:?}", err);
+			return Vec::new()
+		}
+	};
+
+	peers.into_iter()
+		.filter_map(|peer| Some(PersistedPeer {
+			peer_id: PeerId::from_bytes(peer.peer_id).ok()?,
+			reputation: peer.reputation,
+			addresses: peer.addresses.into_iter()
+				.filter_map(|addr| Some(PersistedAddress {
+					address: Multiaddr::try_from(addr.address).ok()?,
+					last_seen: addr.last_seen,
+					successes: addr.successes,
+					failures: addr.failures,
+				}))
+				.collect(),
+		}))
+		.collect()
+}
+
+/// Stores the given peers with `store`, replacing the previously stored ones.
+///
+/// The addresses that never worked despite `MAX_FAILURES` dials are dropped, and the others are
+/// sorted the most reliable first. The peers with neither a reputation nor an address are of no
+/// use and aren't stored. At most `MAX_PERSISTED_PEERS` are stored, the ones with the most
+/// significant reputations first, so that both the best and the banned peers are kept, and then
+/// the ones seen most recently.
+pub fn store(store: &dyn NetworkStateStore, mut peers: Vec<PersistedPeer>) {
+	for peer in &mut peers {
+		peer.addresses.retain(|addr| !addr.is_hopeless());
+		peer.addresses.sort_by_key(PersistedAddress::order);
+		peer.addresses.truncate(MAX_ADDRESSES_PER_PEER);
+	}
+	peers.retain(|peer| peer.reputation != 0 || !peer.addresses.is_empty());
+	peers.sort_by_key(|peer| (
+		std::cmp::Reverse(i64::from(peer.reputation).abs()),
+		std::cmp::Reverse(peer.last_seen()),
+	));
+	peers.truncate(MAX_PERSISTED_PEERS);
+
+	let encoded = peers.into_iter()
+		.map(|peer| EncodedPeer {
+			peer_id: peer.peer_id.into_bytes(),
+			reputation: peer.reputation,
+			addresses: peer.addresses.into_iter()
+				.map(|addr| EncodedAddress {
+					address: addr.address.to_vec(),
+					last_seen: addr.last_seen,
+					successes: addr.successes,
+					failures: addr.failures,
+				})
+				.collect(),
+		})
+		.collect::<Vec<_>>()
+		.encode();
+	if let Err(err) = store.store_network_state(ADDRESS_BOOK_KEY, &encoded) {
+		warn!(target: "sub-libp2p", "Failed to store the address book: {:?}", err);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn peer(reputation: i32, addresses: &[&str]) -> PersistedPeer {
+		PersistedPeer {
+			peer_id: PeerId::random(),
+			reputation,
+			addresses: addresses.iter()
+				.map(|addr| PersistedAddress::new(addr.parse().unwrap()))
+				.collect(),
+		}
+	}
+
+	#[test]
+	fn peers_are_restored() {
+		let client = substrate_test_runtime_client::new();
+		assert!(load(&client).is_empty());
+
+		let good = peer(500, &["/ip4/198.51.100.19/tcp/30333"]);
+		let banned = peer(i32::min_value(), &[]);
+		let neutral = peer(0, &["/dns4/example.com/tcp/30333", "/ip6/2001:db8::1/tcp/30333"]);
+		let useless = peer(0, &[]);
+		store(&client, vec![neutral.clone(), useless, good.clone(), banned.clone()]);
+
+		assert_eq!(load(&client), vec![banned, good, neutral]);
+	}
+
+	#[test]
+	fn reliable_addresses_come_first() {
+		let client = substrate_test_runtime_client::new();
+		let peer_id = PeerId::random();
+		let working: Multiaddr = "/ip4/198.51.100.19/tcp/30333".parse().unwrap();
+		let flaky: Multiaddr = "/ip6/2001:db8::1/tcp/30333".parse().unwrap();
+		let broken: Multiaddr = "/ip4/192.0.2.1/tcp/30333".parse().unwrap();
+
+		let mut book = AddressBook::default();
+		for _ in 0..MAX_FAILURES {
+			book.on_dial_failure(&peer_id, &broken);
+		}
+		book.on_dial_success(&peer_id, &flaky);
+		book.on_dial_failure(&peer_id, &flaky);
+		book.on_dial_success(&peer_id, &working);
+		book.store(&client, vec![(peer_id.clone(), 10)], |_| vec![broken.clone()]);
+
+		let book = AddressBook::load(&client);
+		let peers = book.peers().collect::<Vec<_>>();
+		assert_eq!(peers.len(), 1);
+		assert_eq!(peers[0].reputation, 10);
+		let addresses = peers[0].addresses.iter().map(|a| a.address.clone()).collect::<Vec<_>>();
+		assert_eq!(addresses, vec![working, flaky]);
+		assert_eq!(peers[0].addresses[1].successes, 1);
+		assert_eq!(peers[0].addresses[1].failures, 1);
+		assert!(peers[0].addresses[0].last_seen.is_some());
+	}
+}
@@ -18,7 +18,10 @@ use crate::{
 	debug_info, discovery::DiscoveryBehaviour, discovery::DiscoveryOut, DiscoveryNetBehaviour,
 	Event, protocol::event::DhtEvent, ExHashT,
 };
+use crate::config::DiscoveryConfig;
 use crate::protocol::{self, light_client_handler, CustomMessageOutcome, Protocol};
+use crate::request_responses::{RequestFailure, RequestResponses};
+use futures::channel::oneshot;
 use libp2p::NetworkBehaviour;
 use libp2p::core::{Multiaddr, PeerId, PublicKey};
 use libp2p::kad::record;
@@ -44,6 +47,8 @@ pub struct Behaviour<B: BlockT, H: ExHashT> {
 	block_requests: protocol::BlockRequests<B>,
 	/// Light client request handling.
 	light_client_handler: protocol::LightClientHandler<B>,
+	/// Request/response protocols of the users of the network.
+	request_responses: RequestResponses,
 	/// Queue of events to produce for the outside.
 	#[behaviour(ignore)]
 	events: Vec<BehaviourOut<B>>,
@@ -68,22 +73,33 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 		known_addresses: Vec<(PeerId, Multiaddr)>,
 		enable_mdns: bool,
 		allow_private_ipv4: bool,
+		prefer_ipv6: bool,
 		discovery_only_if_under_num: u64,
+		discovery_config: DiscoveryConfig,
+		observed_address_confirmations: usize,
 		block_requests: protocol::BlockRequests<B>,
 		light_client_handler: protocol::LightClientHandler<B>,
+		request_responses: RequestResponses,
 	) -> Self {
 		Behaviour {
 			substrate,
-			debug_info: debug_info::DebugInfoBehaviour::new(user_agent, local_public_key.clone()),
+			debug_info: debug_info::DebugInfoBehaviour::new(
+				user_agent,
+				local_public_key.clone(),
+				observed_address_confirmations,
+			),
 			discovery: DiscoveryBehaviour::new(
 				local_public_key,
 				known_addresses,
 				enable_mdns,
 				allow_private_ipv4,
+				prefer_ipv6,
 				discovery_only_if_under_num,
+				discovery_config,
 			).await,
 			block_requests,
 			light_client_handler,
+			request_responses,
 			events: Vec::new()
 		}
 	}
@@ -98,6 +114,11 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 		self.discovery.add_known_address(peer_id, addr)
 	}
 
+	/// Adds an address of the given peer that was known before a restart.
+	pub fn add_persisted_address(&mut self, peer_id: PeerId, addr: Multiaddr) {
+		self.discovery.add_persisted_address(peer_id, addr)
+	}
+
 	/// Returns the number of nodes that are in the Kademlia k-buckets.
 	pub fn num_kbuckets_entries(&mut self) -> usize {
 		self.discovery.num_kbuckets_entries()
@@ -137,6 +158,17 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 	pub fn light_client_request(&mut self, r: light_client_handler::Request<B>) -> Result<(), light_client_handler::Error> {
 		self.light_client_handler.request(r)
 	}
+
+	/// Sends a request on one of the request/response protocols of the users of the network.
+	pub fn send_request(
+		&mut self,
+		target: &PeerId,
+		protocol: &str,
+		request: Vec<u8>,
+		response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+	) {
+		self.request_responses.send_request(target, protocol, request, response)
+	}
 }
 
 impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<void::Void> for
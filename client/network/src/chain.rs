@@ -17,20 +17,44 @@
 //! Blockchain access trait
 
 use sp_blockchain::{Error, HeaderBackend, HeaderMetadata};
-use sc_client_api::{BlockBackend, ProofProvider};
+use sc_client_api::{BlockBackend, CallExecutor, ProofProvider, backend::Backend};
 use sp_runtime::traits::{Block as BlockT, BlockIdTo};
 
 /// Local client abstraction for the network.
 pub trait Client<Block: BlockT>: HeaderBackend<Block> + ProofProvider<Block> + BlockIdTo<Block, Error = Error>
-	+ BlockBackend<Block> + HeaderMetadata<Block, Error = Error> + Send + Sync
+	+ BlockBackend<Block> + HeaderMetadata<Block, Error = Error> + NetworkStateStore + Send + Sync
 {}
 
 impl<Block: BlockT, T> Client<Block> for T
 	where
 		T: HeaderBackend<Block> + ProofProvider<Block> + BlockIdTo<Block, Error = Error>
-			+ BlockBackend<Block> + HeaderMetadata<Block, Error = Error> + Send + Sync
+			+ BlockBackend<Block> + HeaderMetadata<Block, Error = Error> + NetworkStateStore + Send + Sync
 {}
 
+/// Storage of the network state that is kept across restarts.
+pub trait NetworkStateStore {
+	/// Returns the value stored under the given key.
+	fn load_network_state(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Error>;
+
+	/// Stores a value under the given key, replacing the previous one.
+	fn store_network_state(&self, key: &[u8], value: &[u8]) -> Result<(), Error>;
+}
+
+impl<B, E, Block, RA> NetworkStateStore for sc_client::Client<B, E, Block, RA>
+	where
+		B: Backend<Block>,
+		E: CallExecutor<Block>,
+		Block: BlockT,
+{
+	fn load_network_state(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+		self.network_state(key)
+	}
+
+	fn store_network_state(&self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+		self.insert_network_state(key, value)
+	}
+}
+
 /// Finality proof provider.
 pub trait FinalityProofProvider<Block: BlockT>: Send + Sync {
 	/// Prove finality of the block.
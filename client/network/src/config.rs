@@ -19,10 +19,14 @@
 //! The [`Params`] struct is the struct that must be passed in order to initialize the networking.
 //! See the documentation of [`Params`].
 
-pub use crate::chain::{Client, FinalityProofProvider};
+pub use crate::chain::{Client, FinalityProofProvider, NetworkStateStore};
+pub use crate::request_responses::RequestResponseConfig;
 pub use crate::on_demand_layer::OnDemand;
 pub use crate::service::{TransactionPool, EmptyTransactionPool};
 pub use libp2p::{identity, core::PublicKey, wasm_ext::ExtTransport, build_multiaddr};
+pub use sp_consensus::block_validation::{
+	BlockAnnounceValidator, Validation as BlockAnnounceValidation,
+};
 
 // Note: this re-export shouldn't be part of the public API of the crate and will be removed in
 // the future.
@@ -32,14 +36,15 @@ pub use crate::protocol::ProtocolConfig;
 use crate::service::ExHashT;
 
 use bitflags::bitflags;
-use sp_consensus::{block_validation::BlockAnnounceValidator, import_queue::ImportQueue};
-use sp_runtime::traits::{Block as BlockT};
+use sp_consensus::import_queue::ImportQueue;
+use sp_runtime::{ConsensusEngineId, traits::{Block as BlockT}};
 use libp2p::identity::{Keypair, ed25519};
 use libp2p::wasm_ext;
 use libp2p::{PeerId, Multiaddr, multiaddr};
 use core::{fmt, iter};
-use std::{future::Future, pin::Pin};
-use std::{error::Error, fs, io::{self, Write}, net::Ipv4Addr, path::{Path, PathBuf}, sync::Arc};
+use std::{borrow::Cow, future::Future, num::NonZeroUsize, pin::Pin, time::Duration};
+use std::{error::Error, fs, io::{self, Write}, path::{Path, PathBuf}, sync::Arc};
+use std::net::{IpAddr, Ipv4Addr};
 use zeroize::Zeroize;
 use prometheus_endpoint::Registry;
 
@@ -260,22 +265,40 @@ pub struct NetworkConfiguration {
 	pub net_config_path: Option<PathBuf>,
 	/// Multiaddresses to listen for incoming connections.
 	pub listen_addresses: Vec<Multiaddr>,
-	/// Multiaddresses to advertise. Detected automatically if empty.
+	/// Multiaddresses to advertise, in addition to the ones learned from the peers.
 	pub public_addresses: Vec<Multiaddr>,
-	/// List of initial node addresses
+	/// Number of distinct peers that must report observing us at the same address before it's
+	/// advertised. Addresses observed by peers are ignored if 0.
+	pub observed_address_confirmations: usize,
+	/// List of initial node addresses. `/dnsaddr/<host>` addresses are resolved with the TXT
+	/// records of `_dnsaddr.<host>`.
 	pub boot_nodes: Vec<String>,
 	/// The node key configuration, which determines the node's network identity keypair.
 	pub node_key: NodeKeyConfig,
+	/// How the node is reached through the NAT of its local network.
+	pub nat: NatTraversal,
+	/// Configuration of the discovery of the peers and of the Kademlia DHT.
+	pub discovery: DiscoveryConfig,
 	/// Maximum allowed number of incoming connections.
 	pub in_peers: u32,
 	/// Number of outgoing connections we're trying to maintain.
 	pub out_peers: u32,
+	/// Maximum number of light clients connected at the same time, taking slots of `in_peers`.
+	/// `None` if light clients can take all of them.
+	pub max_light_peers: Option<u32>,
 	/// List of reserved node addresses.
 	pub reserved_nodes: Vec<String>,
 	/// The non-reserved peer mode.
 	pub non_reserved_mode: NonReservedPeerMode,
-	/// List of sentry node public addresses.
+	/// If not empty, the connections are only accepted with the peers matching one of these.
+	pub allowed_peers: Vec<PeerFilterEntry>,
+	/// The connections with the peers matching one of these are refused.
+	pub denied_peers: Vec<PeerFilterEntry>,
+	/// List of sentry node public addresses. If not empty, the node only connects to these.
 	pub sentry_nodes: Vec<String>,
+	/// List of the addresses of the validators this node is a sentry of. The node stays
+	/// connected to these.
+	pub sentry_validators: Vec<String>,
 	/// Client identifier. Sent over the wire for debugging purposes.
 	pub client_version: String,
 	/// Name of the node. Sent over the wire for debugging purposes.
@@ -284,6 +307,24 @@ pub struct NetworkConfiguration {
 	pub transport: TransportConfig,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	pub max_parallel_downloads: u32,
+	/// Maximum encoded size of an extrinsic received from a peer. Larger ones are discarded
+	/// without being submitted to the transaction pool. `None` if unlimited.
+	pub max_extrinsic_size: Option<usize>,
+	/// Maximum rate at which all the connections send data, in bytes per second. `None` if
+	/// unlimited.
+	pub max_upload_rate: Option<u64>,
+	/// Maximum rate at which all the connections receive data, in bytes per second. `None` if
+	/// unlimited.
+	pub max_download_rate: Option<u64>,
+	/// Key of the private network the node is part of, if any. All the connections are
+	/// encrypted with it, so that only the nodes holding it can connect to each other.
+	pub pre_shared_key: Option<PreSharedKey>,
+	/// Request/response protocols of the users of the network, the requests of which are sent
+	/// with `NetworkService::request`.
+	pub request_response_protocols: Vec<RequestResponseConfig>,
+	/// Notifications protocols of the users of the network, registered before any connection is
+	/// opened.
+	pub notifications_protocols: Vec<NotificationsProtocolConfig>,
 }
 
 impl Default for NetworkConfiguration {
@@ -293,22 +334,38 @@ impl Default for NetworkConfiguration {
 			net_config_path: None,
 			listen_addresses: Vec::new(),
 			public_addresses: Vec::new(),
+			observed_address_confirmations: 3,
 			boot_nodes: Vec::new(),
 			node_key: NodeKeyConfig::Ed25519(Secret::New),
+			nat: NatTraversal::None,
+			discovery: DiscoveryConfig::default(),
 			in_peers: 25,
 			out_peers: 75,
+			max_light_peers: None,
 			reserved_nodes: Vec::new(),
 			non_reserved_mode: NonReservedPeerMode::Accept,
+			allowed_peers: Vec::new(),
+			denied_peers: Vec::new(),
 			sentry_nodes: Vec::new(),
+			sentry_validators: Vec::new(),
 			client_version: "unknown".into(),
 			node_name: "unknown".into(),
 			transport: TransportConfig::Normal {
 				enable_mdns: false,
 				allow_private_ipv4: true,
+				prefer_ipv6: false,
 				wasm_external_transport: None,
 				use_yamux_flow_control: false,
+				proxy: None,
+				websocket_tls: None,
 			},
 			max_parallel_downloads: 5,
+			max_extrinsic_size: None,
+			max_upload_rate: None,
+			max_download_rate: None,
+			pre_shared_key: None,
+			request_response_protocols: Vec::new(),
+			notifications_protocols: Vec::new(),
 		}
 	}
 }
@@ -342,6 +399,45 @@ impl NetworkConfiguration {
 	}
 }
 
+/// Configuration of a notifications protocol, such as the gossiping of a consensus engine.
+#[derive(Clone, Debug)]
+pub struct NotificationsProtocolConfig {
+	/// Engine ID of the protocol, used for its messages on the legacy substream and in the
+	/// network events.
+	pub engine_id: ConsensusEngineId,
+	/// Name of the protocol, negotiated when opening its substreams.
+	pub protocol_name: Cow<'static, [u8]>,
+	/// Message sent to the remote when opening a substream of the protocol.
+	pub handshake: Vec<u8>,
+	/// Maximum number of peers the protocol is opened with, among the connected ones. The
+	/// reserved peers are always given a slot. `None` if the protocol is opened with all of them.
+	pub max_peers: Option<u32>,
+}
+
+impl NotificationsProtocolConfig {
+	/// Creates the configuration of a protocol with an empty handshake, opened with all the peers.
+	pub fn new(engine_id: ConsensusEngineId, protocol_name: impl Into<Cow<'static, [u8]>>) -> Self {
+		NotificationsProtocolConfig {
+			engine_id,
+			protocol_name: protocol_name.into(),
+			handshake: Vec::new(),
+			max_peers: None,
+		}
+	}
+
+	/// Sets the message sent to the remote when opening a substream of the protocol.
+	pub fn with_handshake(mut self, handshake: impl Into<Vec<u8>>) -> Self {
+		self.handshake = handshake.into();
+		self
+	}
+
+	/// Limits the number of peers the protocol is opened with.
+	pub fn with_max_peers(mut self, max_peers: u32) -> Self {
+		self.max_peers = Some(max_peers);
+		self
+	}
+}
+
 /// Configuration for the transport layer.
 #[derive(Clone, Debug)]
 pub enum TransportConfig {
@@ -357,6 +453,9 @@ pub enum TransportConfig {
 		/// [`NetworkConfiguration::boot_nodes`].
 		allow_private_ipv4: bool,
 
+		/// If true, the IPv6 addresses of the peers are dialed before their IPv4 addresses.
+		prefer_ipv6: bool,
+
 		/// Optional external implementation of a libp2p transport. Used in WASM contexts where we
 		/// need some binding between the networking provided by the operating system or environment
 		/// and libp2p.
@@ -366,6 +465,12 @@ pub enum TransportConfig {
 		wasm_external_transport: Option<wasm_ext::ExtTransport>,
 		/// Use flow control for yamux streams if set to true.
 		use_yamux_flow_control: bool,
+		/// Optional proxy through which the outbound TCP connections are dialed. Listening is
+		/// unaffected.
+		proxy: Option<ProxyConfig>,
+		/// Certificate the `/wss` listen addresses terminate TLS with. Listening on these fails
+		/// without it, while `/ws` addresses are always served in plain text.
+		websocket_tls: Option<WebSocketTlsConfig>,
 	},
 
 	/// Only allow connections within the same process.
@@ -373,6 +478,218 @@ pub enum TransportConfig {
 	MemoryOnly,
 }
 
+/// How the node is reached through the NAT of its local network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NatTraversal {
+	/// Nothing is done: the node is reachable without a NAT, or its ports are forwarded by hand.
+	None,
+	/// The router maps the listened TCP ports to the node with UPnP, and the resulting external
+	/// addresses are announced to the peers. The ports chosen by the system (`/tcp/0`) aren't
+	/// mapped, and the mappings are removed when the network stops.
+	Upnp,
+}
+
+/// Configuration of the discovery of the peers and of the Kademlia DHT.
+#[derive(Clone, Debug)]
+pub struct DiscoveryConfig {
+	/// If false, no peer is discovered: the node only connects to the bootnodes, the reserved
+	/// nodes and the peers it is given. The records of the DHT can still be fetched and published.
+	pub enabled: bool,
+	/// Number of peers the Kademlia queries are sent to in parallel. `None` for the libp2p
+	/// default.
+	pub kademlia_parallelism: Option<NonZeroUsize>,
+	/// Time to live of the records stored in the DHT. `None` for the libp2p default.
+	pub record_ttl: Option<Duration>,
+	/// Time to live of the provider records stored in the DHT. `None` for the libp2p default.
+	pub provider_record_ttl: Option<Duration>,
+	/// Maximal interval between two random Kademlia queries discovering peers. The queries
+	/// start every second, and slow down exponentially up to this interval.
+	pub max_interval: Duration,
+}
+
+impl Default for DiscoveryConfig {
+	fn default() -> Self {
+		DiscoveryConfig {
+			enabled: true,
+			kademlia_parallelism: None,
+			record_ttl: None,
+			provider_record_ttl: None,
+			max_interval: Duration::from_secs(60),
+		}
+	}
+}
+
+/// Certificate and private key the WebSocket listeners terminate TLS with, so that browsers can
+/// connect to the `/wss` listen addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSocketTlsConfig {
+	/// Path to the PEM encoded certificate chain.
+	pub cert: PathBuf,
+	/// Path to the PEM encoded private key, in PKCS#8 or RSA format.
+	pub key: PathBuf,
+}
+
+/// Proxy through which the outbound TCP connections are dialed.
+///
+/// Parsed from `socks5://HOST:PORT` or `http://HOST:PORT`. The host names of the peers are
+/// resolved by the proxy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProxyConfig {
+	/// Protocol spoken with the proxy.
+	pub protocol: ProxyProtocol,
+	/// Host name or IP address of the proxy.
+	pub host: String,
+	/// Port of the proxy.
+	pub port: u16,
+}
+
+/// Peer the connections are accepted or refused with, by identity or by IP address.
+///
+/// Parsed from a base58 `PeerId`, or from an IP address range in the CIDR notation, such as
+/// `192.0.2.0/24` or `2001:db8::/32`. A single IP address is a range of one address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PeerFilterEntry {
+	/// The peer with this identity.
+	PeerId(PeerId),
+	/// The peers connecting from, or dialed at, an IP address in this range.
+	IpRange {
+		/// First address of the range.
+		address: IpAddr,
+		/// Number of leading bits of the addresses in the range equal to the ones of `address`.
+		prefix_len: u8,
+	},
+}
+
+impl PeerFilterEntry {
+	/// Returns true if the peer with this identity, and this IP address if known, matches.
+	pub fn matches(&self, peer_id: &PeerId, ip: Option<IpAddr>) -> bool {
+		let (address, prefix_len) = match self {
+			PeerFilterEntry::PeerId(id) => return id == peer_id,
+			PeerFilterEntry::IpRange { address, prefix_len } => (address, *prefix_len),
+		};
+		match (address, ip) {
+			(IpAddr::V4(range), Some(IpAddr::V4(ip))) =>
+				prefix_matches(&range.octets(), &ip.octets(), prefix_len),
+			(IpAddr::V6(range), Some(IpAddr::V6(ip))) =>
+				prefix_matches(&range.octets(), &ip.octets(), prefix_len),
+			_ => false,
+		}
+	}
+}
+
+/// Returns true if the first `prefix_len` bits of `range` and `ip` are equal.
+fn prefix_matches(range: &[u8], ip: &[u8], prefix_len: u8) -> bool {
+	let (bytes, bits) = (usize::from(prefix_len / 8), prefix_len % 8);
+	range[..bytes] == ip[..bytes] && (bits == 0 || (range[bytes] ^ ip[bytes]) >> (8 - bits) == 0)
+}
+
+impl std::str::FromStr for PeerFilterEntry {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Ok(peer_id) = s.parse() {
+			return Ok(PeerFilterEntry::PeerId(peer_id));
+		}
+		let invalid = || format!("Invalid peer {}, expected a peer id or an IP address range", s);
+		let mut parts = s.splitn(2, '/');
+		let address: IpAddr = parts.next().unwrap_or_default().parse().map_err(|_| invalid())?;
+		let max_len = if address.is_ipv4() { 32 } else { 128 };
+		let prefix_len = match parts.next() {
+			Some(len) => len.parse().ok().filter(|len| *len <= max_len).ok_or_else(invalid)?,
+			None => max_len,
+		};
+		Ok(PeerFilterEntry::IpRange { address, prefix_len })
+	}
+}
+
+/// Protocol spoken with a proxy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProxyProtocol {
+	/// SOCKS5, without authentication.
+	Socks5,
+	/// HTTP, with the `CONNECT` method.
+	Http,
+}
+
+impl std::str::FromStr for ProxyConfig {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!(
+			"Invalid proxy {}, expected socks5://HOST:PORT or http://HOST:PORT",
+			s,
+		);
+		let (protocol, address) = if s.starts_with("socks5://") {
+			(ProxyProtocol::Socks5, &s["socks5://".len()..])
+		} else if s.starts_with("http://") {
+			(ProxyProtocol::Http, &s["http://".len()..])
+		} else {
+			return Err(invalid());
+		};
+		let address = address.trim_end_matches('/');
+		let separator = address.rfind(':').ok_or_else(invalid)?;
+		let host = address[..separator].trim_start_matches('[').trim_end_matches(']');
+		let port = address[separator + 1..].parse().map_err(|_| invalid())?;
+		if host.is_empty() {
+			return Err(invalid());
+		}
+
+		Ok(ProxyConfig { protocol, host: host.into(), port })
+	}
+}
+
+/// Key shared by the nodes of a private network.
+///
+/// Parsed from the format of the `swarm.key` files of libp2p:
+///
+/// ```text
+/// /key/swarm/psk/1.0.0/
+/// /base16/
+/// <64 hexadecimal digits>
+/// ```
+#[derive(Clone)]
+pub struct PreSharedKey(pub [u8; 32]);
+
+impl fmt::Debug for PreSharedKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "PreSharedKey(<secret>)")
+	}
+}
+
+impl Drop for PreSharedKey {
+	fn drop(&mut self) {
+		self.0.zeroize();
+	}
+}
+
+impl std::str::FromStr for PreSharedKey {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		use rustc_hex::FromHex;
+
+		let mut lines = s.lines().map(str::trim);
+		if lines.next() != Some("/key/swarm/psk/1.0.0/") {
+			return Err("Pre-shared key doesn't start with /key/swarm/psk/1.0.0/".into());
+		}
+		if lines.next() != Some("/base16/") {
+			return Err("Pre-shared key isn't encoded in /base16/".into());
+		}
+		let mut bytes: Vec<u8> = lines.next()
+			.unwrap_or_default()
+			.from_hex()
+			.map_err(|_| "Pre-shared key isn't hexadecimal".to_string())?;
+		if bytes.len() != 32 {
+			bytes.zeroize();
+			return Err("Pre-shared key isn't 32 bytes long".into());
+		}
+		let mut key = [0; 32];
+		key.copy_from_slice(&bytes);
+		bytes.zeroize();
+		Ok(PreSharedKey(key))
+	}
+}
+
 /// The policy for connections to non-reserved peers.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum NonReservedPeerMode {
@@ -566,4 +883,55 @@ mod tests {
 		let kp2 = NodeKeyConfig::Ed25519(Secret::New).into_keypair().unwrap();
 		assert!(secret_bytes(&kp1) != secret_bytes(&kp2));
 	}
+
+	#[test]
+	fn test_proxy_parse() {
+		let proxy: ProxyConfig = "socks5://127.0.0.1:9050".parse().unwrap();
+		assert_eq!(proxy, ProxyConfig {
+			protocol: ProxyProtocol::Socks5,
+			host: "127.0.0.1".into(),
+			port: 9050,
+		});
+		let proxy: ProxyConfig = "http://[::1]:3128/".parse().unwrap();
+		assert_eq!(proxy.protocol, ProxyProtocol::Http);
+		assert_eq!(proxy.host, "::1");
+		assert_eq!(proxy.port, 3128);
+
+		assert!("socks4://127.0.0.1:9050".parse::<ProxyConfig>().is_err());
+		assert!("socks5://127.0.0.1".parse::<ProxyConfig>().is_err());
+		assert!("http://:3128".parse::<ProxyConfig>().is_err());
+	}
+
+	#[test]
+	fn test_peer_filter_entry() {
+		let peer_id = PeerId::random();
+		let entry: PeerFilterEntry = peer_id.to_base58().parse().unwrap();
+		assert!(entry.matches(&peer_id, None));
+		assert!(!entry.matches(&PeerId::random(), None));
+
+		let entry: PeerFilterEntry = "192.0.2.0/23".parse().unwrap();
+		let matches = |ip: &str| entry.matches(&peer_id, Some(ip.parse().unwrap()));
+		assert!(matches("192.0.2.1") && matches("192.0.3.255"));
+		assert!(!matches("192.0.4.1") && !matches("::ffff:192.0.2.1"));
+		assert!(!entry.matches(&peer_id, None));
+
+		let entry: PeerFilterEntry = "2001:db8::1".parse().unwrap();
+		assert!(entry.matches(&peer_id, Some("2001:db8::1".parse().unwrap())));
+		assert!(!entry.matches(&peer_id, Some("2001:db8::2".parse().unwrap())));
+
+		assert!("192.0.2.0/33".parse::<PeerFilterEntry>().is_err());
+		assert!("example.com".parse::<PeerFilterEntry>().is_err());
+	}
+
+	#[test]
+	fn test_pre_shared_key_parse() {
+		let key = format!("/key/swarm/psk/1.0.0/\n/base16/\n{}\n", "2a".repeat(32));
+		assert_eq!(key.parse::<PreSharedKey>().unwrap().0, [42; 32]);
+
+		let short = format!("/key/swarm/psk/1.0.0/\n/base16/\n{}\n", "2a".repeat(31));
+		assert!(short.parse::<PreSharedKey>().is_err());
+		let base64 = format!("/key/swarm/psk/1.0.0/\n/base64/\n{}\n", "2a".repeat(32));
+		assert!(base64.parse::<PreSharedKey>().is_err());
+		assert!("2a".repeat(32).parse::<PreSharedKey>().is_err());
+	}
 }
@@ -18,18 +18,19 @@ use fnv::FnvHashMap;
 use futures::prelude::*;
 use libp2p::Multiaddr;
 use libp2p::core::nodes::listeners::ListenerId;
-use libp2p::core::{ConnectedPoint, either::EitherOutput, PeerId, PublicKey};
+use libp2p::core::{ConnectedPoint, either::EitherOutput, PeerId, PublicKey, address_translation};
 use libp2p::swarm::{IntoProtocolsHandler, IntoProtocolsHandlerSelect, ProtocolsHandler};
 use libp2p::swarm::{NetworkBehaviour, NetworkBehaviourAction, PollParameters};
 use libp2p::identify::{Identify, IdentifyEvent, IdentifyInfo};
 use libp2p::ping::{Ping, PingConfig, PingEvent, PingSuccess};
-use log::{debug, trace, error};
+use log::{debug, info, trace, error};
 use std::error;
-use std::collections::hash_map::Entry;
+use std::collections::{VecDeque, hash_map::Entry};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use wasm_timer::Instant;
+use crate::observed_addresses::ObservedAddresses;
 use crate::utils::interval;
 
 /// Time after we disconnect from a node before we purge its information from the cache.
@@ -46,7 +47,12 @@ pub struct DebugInfoBehaviour {
 	identify: Identify,
 	/// Information that we know about all nodes.
 	nodes_info: FnvHashMap<PeerId, NodeInfo>,
-	/// Interval at which we perform garbage collection in `nodes_info`.
+	/// Addresses the nodes observe us at, advertised once confirmed.
+	observed_addresses: ObservedAddresses,
+	/// Confirmed addresses to report to the swarm.
+	confirmed_addresses: VecDeque<Multiaddr>,
+	/// Interval at which we perform garbage collection in `nodes_info` and
+	/// `observed_addresses`.
 	garbage_collect: Pin<Box<dyn Stream<Item = ()> + Send>>,
 }
 
@@ -66,9 +72,13 @@ struct NodeInfo {
 
 impl DebugInfoBehaviour {
 	/// Builds a new `DebugInfoBehaviour`.
+	///
+	/// An address nodes observe us at is only advertised once `observed_address_confirmations`
+	/// distinct nodes reported it, or never if 0.
 	pub fn new(
 		user_agent: String,
 		local_public_key: PublicKey,
+		observed_address_confirmations: usize,
 	) -> Self {
 		let identify = {
 			let proto_version = "/substrate/1.0".to_string();
@@ -79,6 +89,8 @@ impl DebugInfoBehaviour {
 			ping: Ping::new(PingConfig::new()),
 			identify,
 			nodes_info: FnvHashMap::default(),
+			observed_addresses: ObservedAddresses::new(observed_address_confirmations),
+			confirmed_addresses: VecDeque::new(),
 			garbage_collect: Box::pin(interval(GARBAGE_COLLECT_INTERVAL)),
 		}
 	}
@@ -115,6 +127,24 @@ impl DebugInfoBehaviour {
 				"Received pong from node we're not connected to {:?}", peer_id);
 		}
 	}
+
+	/// Registers the address a node observed us at. The address is translated to each of our
+	/// listen addresses first, as the port it was observed with is usually the one of an
+	/// outgoing connection.
+	fn handle_observed_address(
+		&mut self,
+		peer_id: &PeerId,
+		observed: &Multiaddr,
+		listen_addresses: impl Iterator<Item = Multiaddr>,
+	) {
+		let now = Instant::now();
+		for address in listen_addresses.filter_map(|listen| address_translation(&listen, observed)) {
+			if self.observed_addresses.report(peer_id, address.clone(), now) {
+				info!(target: "sub-libp2p", "Discovered our external address {}", address);
+				self.confirmed_addresses.push_back(address);
+			}
+		}
+	}
 }
 
 /// Gives access to the information about a node.
@@ -273,6 +303,10 @@ impl NetworkBehaviour for DebugInfoBehaviour {
 			Self::OutEvent
 		>
 	> {
+		if let Some(address) = self.confirmed_addresses.pop_front() {
+			return Poll::Ready(NetworkBehaviourAction::ReportObservedAddr { address });
+		}
+
 		loop {
 			match self.ping.poll(cx, params) {
 				Poll::Pending => break,
@@ -300,8 +334,13 @@ impl NetworkBehaviour for DebugInfoBehaviour {
 				Poll::Pending => break,
 				Poll::Ready(NetworkBehaviourAction::GenerateEvent(event)) => {
 					match event {
-						IdentifyEvent::Received { peer_id, info, .. } => {
+						IdentifyEvent::Received { peer_id, info, observed_addr } => {
 							self.handle_identify_report(&peer_id, &info);
+							self.handle_observed_address(
+								&peer_id,
+								&observed_addr,
+								params.listened_addresses(),
+							);
 							let event = DebugInfoEvent::Identified { peer_id, info };
 							return Poll::Ready(NetworkBehaviourAction::GenerateEvent(event));
 						}
@@ -319,8 +358,8 @@ impl NetworkBehaviour for DebugInfoBehaviour {
 						peer_id,
 						event: EitherOutput::Second(event)
 					}),
-				Poll::Ready(NetworkBehaviourAction::ReportObservedAddr { address }) =>
-					return Poll::Ready(NetworkBehaviourAction::ReportObservedAddr { address }),
+				// Only reported to the swarm once confirmed, see `handle_observed_address`.
+				Poll::Ready(NetworkBehaviourAction::ReportObservedAddr { .. }) => {},
 			}
 		}
 
@@ -328,6 +367,7 @@ impl NetworkBehaviour for DebugInfoBehaviour {
 			self.nodes_info.retain(|_, node| {
 				node.info_expire.as_ref().map(|exp| *exp >= Instant::now()).unwrap_or(true)
 			});
+			self.observed_addresses.collect_garbage(Instant::now());
 		}
 
 		Poll::Pending
@@ -0,0 +1,187 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding of the messages received from the peers.
+//!
+//! The protocols decode everything a peer sends them through these functions, which are public
+//! so that they can be fuzzed in isolation (see the `fuzzer` directory of this crate). Messages
+//! larger than the limits, or with bytes left after the decoded message, are rejected.
+
+use codec::Decode;
+use sp_runtime::traits::Block as BlockT;
+
+use crate::protocol::{api, message::Message};
+
+/// Maximal size of a message of the legacy substream, which carries the sync, gossip and light
+/// client messages.
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Error decoding a message.
+#[derive(Debug, derive_more::Display)]
+pub enum DecodeError {
+	/// The message is larger than allowed.
+	#[display(fmt = "Message of {} bytes exceeds the limit of {} bytes", size, max)]
+	TooLarge {
+		/// Size of the message.
+		size: usize,
+		/// Maximal size of the message.
+		max: usize,
+	},
+	/// Bytes are left after the decoded message.
+	#[display(fmt = "{} bytes left after the message", _0)]
+	TrailingBytes(usize),
+	/// Invalid SCALE encoding.
+	#[display(fmt = "Invalid message: {}", _0)]
+	Scale(&'static str),
+	/// Invalid protobuf encoding.
+	#[display(fmt = "Invalid message: {}", _0)]
+	Protobuf(prost::DecodeError),
+}
+
+impl std::error::Error for DecodeError {}
+
+fn check_size(data: &[u8], max: usize) -> Result<(), DecodeError> {
+	if data.len() > max {
+		return Err(DecodeError::TooLarge { size: data.len(), max });
+	}
+	Ok(())
+}
+
+/// Decodes a message of the legacy substream.
+pub fn message<B: BlockT>(data: &[u8]) -> Result<Message<B>, DecodeError> {
+	check_size(data, MAX_MESSAGE_SIZE)?;
+	let mut input = data;
+	let message = Message::<B>::decode(&mut input).map_err(|e| DecodeError::Scale(e.what()))?;
+	if !input.is_empty() {
+		return Err(DecodeError::TrailingBytes(input.len()));
+	}
+	Ok(message)
+}
+
+/// Decodes a request of the block requests protocol, of at most `max_size` bytes.
+pub fn block_request(data: &[u8], max_size: usize) -> Result<api::v1::BlockRequest, DecodeError> {
+	check_size(data, max_size)?;
+	prost::Message::decode(data).map_err(DecodeError::Protobuf)
+}
+
+/// Decodes a request of the light client protocol, of at most `max_size` bytes.
+pub fn light_request(
+	data: &[u8],
+	max_size: usize,
+) -> Result<api::v1::light::Request, DecodeError> {
+	check_size(data, max_size)?;
+	prost::Message::decode(data).map_err(DecodeError::Protobuf)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::{Compact, Encode};
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
+	use std::{fs, path::Path};
+	use crate::protocol::message::generic::ConsensusMessage;
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+	/// Index of the `Consensus` variant of the messages.
+	const CONSENSUS: u8 = 5;
+
+	fn consensus_message() -> Vec<u8> {
+		Message::<Block>::Consensus(ConsensusMessage {
+			engine_id: *b"test",
+			data: vec![1, 2, 3],
+		}).encode()
+	}
+
+	#[test]
+	fn valid_message_is_decoded() {
+		assert!(message::<Block>(&consensus_message()).is_ok());
+	}
+
+	#[test]
+	fn invalid_messages_are_rejected() {
+		let mut trailing = consensus_message();
+		trailing.push(0);
+		let mut huge_length = vec![CONSENSUS];
+		huge_length.extend_from_slice(b"test");
+		huge_length.extend(Compact(u32::max_value()).encode());
+
+		let vectors: Vec<(&str, Vec<u8>)> = vec![
+			("empty", Vec::new()),
+			("unknown variant", vec![0xff]),
+			("truncated", vec![CONSENSUS, b't', b'e']),
+			("huge length", huge_length),
+			("truncated data", consensus_message()[..8].to_vec()),
+		];
+		for (name, data) in vectors {
+			assert!(
+				matches_scale_error(message::<Block>(&data)),
+				"{} should be rejected",
+				name,
+			);
+		}
+
+		match message::<Block>(&trailing) {
+			Err(DecodeError::TrailingBytes(1)) => {},
+			other => panic!("Unexpected result {:?}", other.map(|m| m.id())),
+		}
+		match message::<Block>(&vec![0; MAX_MESSAGE_SIZE + 1]) {
+			Err(DecodeError::TooLarge { .. }) => {},
+			other => panic!("Unexpected result {:?}", other.map(|m| m.id())),
+		}
+	}
+
+	fn matches_scale_error(result: Result<Message<Block>, DecodeError>) -> bool {
+		if let Err(DecodeError::Scale(_)) = result {
+			true
+		} else {
+			false
+		}
+	}
+
+	#[test]
+	fn requests_are_limited() {
+		let mut request = Vec::new();
+		prost::Message::encode(&api::v1::BlockRequest {
+			fields: 1,
+			from_block: Some(api::v1::block_request::FromBlock::Number(vec![1])),
+			to_block: Vec::new(),
+			direction: api::v1::Direction::Ascending as i32,
+			max_blocks: 1,
+		}, &mut request).unwrap();
+		assert!(block_request(&request, request.len()).is_ok());
+		assert!(block_request(&request, request.len() - 1).is_err());
+		assert!(block_request(&[0xff; 3], 1024).is_err());
+		assert!(light_request(&[0xff; 3], 1024).is_err());
+		assert!(light_request(&[0; 2048], 1024).is_err());
+	}
+
+	/// Decodes the inputs collected by the fuzzer, which must not make the decoding panic.
+	#[test]
+	fn fuzzer_corpus_is_decoded() {
+		let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzzer").join("corpus");
+		let decoders: &[(&str, &dyn Fn(&[u8]))] = &[
+			("message", &|data: &[u8]| { let _ = message::<Block>(data); }),
+			("block_request", &|data: &[u8]| { let _ = block_request(data, 1024 * 1024); }),
+			("light_request", &|data: &[u8]| { let _ = light_request(data, 1024 * 1024); }),
+		];
+		for (target, decode) in decoders {
+			for entry in fs::read_dir(corpus.join(target)).unwrap() {
+				decode(&fs::read(entry.unwrap().path()).unwrap());
+			}
+		}
+	}
+}
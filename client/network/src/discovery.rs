@@ -30,6 +30,9 @@
 //! order for nodes to propagate to us their view of the network. This is performed automatically
 //! by the `DiscoveryBehaviour`.
 //!
+//! The mDNS and the Kademlia random walk can be disabled with `DiscoveryConfig::enabled`, for
+//! networks whose topology is static.
+//!
 //! Additionally, the `DiscoveryBehaviour` is also capable of storing and loading value in the
 //! network-wide DHT.
 //!
@@ -49,7 +52,7 @@ use futures::prelude::*;
 use futures_timer::Delay;
 use libp2p::core::{nodes::listeners::ListenerId, ConnectedPoint, Multiaddr, PeerId, PublicKey};
 use libp2p::swarm::{ProtocolsHandler, NetworkBehaviour, NetworkBehaviourAction, PollParameters};
-use libp2p::kad::{Kademlia, KademliaEvent, Quorum, Record};
+use libp2p::kad::{Kademlia, KademliaConfig, KademliaEvent, Quorum, Record};
 use libp2p::kad::GetClosestPeersError;
 use libp2p::kad::record::{self, store::MemoryStore};
 #[cfg(not(target_os = "unknown"))]
@@ -62,6 +65,8 @@ use std::{cmp, collections::VecDeque, time::Duration};
 use std::task::{Context, Poll};
 use sp_core::hexdisplay::HexDisplay;
 
+use crate::config::DiscoveryConfig;
+
 /// Implementation of `NetworkBehaviour` that discovers the nodes on the network.
 pub struct DiscoveryBehaviour {
 	/// User-defined list of nodes and their addresses. Typically includes bootstrap nodes and
@@ -76,6 +81,11 @@ pub struct DiscoveryBehaviour {
 	next_kad_random_query: Delay,
 	/// After `next_kad_random_query` triggers, the next one triggers after this duration.
 	duration_to_next_kad: Duration,
+	/// Maximal value of `duration_to_next_kad`, at least one second.
+	max_duration_to_next_kad: Duration,
+	/// If false, no random Kademlia query is started and no peer is reported as discovered,
+	/// except the ones that are explicitly added.
+	discovery_enabled: bool,
 	/// Discovered nodes to return.
 	discoveries: VecDeque<PeerId>,
 	/// Identity of our local node.
@@ -85,6 +95,9 @@ pub struct DiscoveryBehaviour {
 	/// If false, `addresses_of_peer` won't return any private IPv4 address, except for the ones
 	/// stored in `user_defined`.
 	allow_private_ipv4: bool,
+	/// If true, `addresses_of_peer` returns the IPv6 addresses first, so that they're dialed
+	/// first.
+	prefer_ipv6: bool,
 	/// Number of active connections over which we interrupt the discovery process.
 	discovery_only_if_under_num: u64,
 }
@@ -98,16 +111,30 @@ impl DiscoveryBehaviour {
 		user_defined: Vec<(PeerId, Multiaddr)>,
 		enable_mdns: bool,
 		allow_private_ipv4: bool,
+		prefer_ipv6: bool,
 		discovery_only_if_under_num: u64,
+		config: DiscoveryConfig,
 	) -> Self {
+		let enable_mdns = enable_mdns && config.enabled;
 		if enable_mdns {
 			#[cfg(target_os = "unknown")]
 			warn!(target: "sub-libp2p", "mDNS is not available on this platform");
 		}
 
+		let mut kademlia_config = KademliaConfig::default();
+		if let Some(parallelism) = config.kademlia_parallelism {
+			kademlia_config.set_parallelism(parallelism);
+		}
+		if let Some(ttl) = config.record_ttl {
+			kademlia_config.set_record_ttl(Some(ttl));
+		}
+		if let Some(ttl) = config.provider_record_ttl {
+			kademlia_config.set_provider_record_ttl(Some(ttl));
+		}
+
 		let local_id = local_public_key.clone().into_peer_id();
 		let store = MemoryStore::new(local_id.clone());
-		let mut kademlia = Kademlia::new(local_id.clone(), store);
+		let mut kademlia = Kademlia::with_config(local_id.clone(), store, kademlia_config);
 		for (peer_id, addr) in &user_defined {
 			kademlia.add_address(peer_id, addr.clone());
 		}
@@ -117,10 +144,14 @@ impl DiscoveryBehaviour {
 			kademlia,
 			next_kad_random_query: Delay::new(Duration::new(0, 0)),
 			duration_to_next_kad: Duration::from_secs(1),
+			// The queries are started at most every second.
+			max_duration_to_next_kad: cmp::max(config.max_interval, Duration::from_secs(1)),
+			discovery_enabled: config.enabled,
 			discoveries: VecDeque::new(),
 			local_peer_id: local_public_key.into_peer_id(),
 			num_connections: 0,
 			allow_private_ipv4,
+			prefer_ipv6,
 			discovery_only_if_under_num,
 			#[cfg(not(target_os = "unknown"))]
 			mdns: if enable_mdns {
@@ -154,6 +185,17 @@ impl DiscoveryBehaviour {
 		}
 	}
 
+	/// Adds an address of the given peer that was known before a restart, and generates a
+	/// `Discovered` event.
+	///
+	/// Contrary to `add_known_address`, the address can expire like the ones found by the DHT.
+	pub fn add_persisted_address(&mut self, peer_id: PeerId, addr: Multiaddr) {
+		self.kademlia.add_address(&peer_id, addr);
+		if self.discovery_enabled && !self.discoveries.contains(&peer_id) {
+			self.discoveries.push_back(peer_id);
+		}
+	}
+
 	/// Call this method when a node reports an address for itself.
 	///
 	/// **Note**: It is important that you call this method, otherwise the discovery mechanism will
@@ -183,6 +225,14 @@ impl DiscoveryBehaviour {
 	}
 }
 
+/// Returns true if the address is an IPv6 one.
+fn is_ipv6(addr: &Multiaddr) -> bool {
+	match addr.iter().next() {
+		Some(Protocol::Ip6(_)) | Some(Protocol::Dns6(_)) => true,
+		_ => false,
+	}
+}
+
 /// Event generated by the `DiscoveryBehaviour`.
 pub enum DiscoveryOut {
 	/// The address of a peer has been added to the Kademlia routing table.
@@ -246,6 +296,10 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 			list.extend(list_to_filter);
 		}
 
+		if self.prefer_ipv6 {
+			list.sort_by_key(|addr| !is_ipv6(addr));
+		}
+
 		trace!(target: "sub-libp2p", "Addresses of {:?} are {:?}", peer_id, list);
 		if list.is_empty() {
 			if self.kademlia.kbuckets_entries().any(|p| p == peer_id) {
@@ -338,7 +392,9 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 
 		// Poll the stream that fires when we need to start a random Kademlia query.
 		while let Poll::Ready(_) = self.next_kad_random_query.poll_unpin(cx) {
-			let actually_started = if self.num_connections < self.discovery_only_if_under_num {
+			let actually_started = if !self.discovery_enabled {
+				false
+			} else if self.num_connections < self.discovery_only_if_under_num {
 				let random_peer_id = PeerId::random();
 				debug!(target: "sub-libp2p", "Libp2p <= Starting random Kademlia request for \
 					{:?}", random_peer_id);
@@ -356,10 +412,10 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 			};
 
 			// Schedule the next random query with exponentially increasing delay,
-			// capped at 60 seconds.
+			// capped at `max_duration_to_next_kad`.
 			self.next_kad_random_query = Delay::new(self.duration_to_next_kad);
 			self.duration_to_next_kad = cmp::min(self.duration_to_next_kad * 2,
-				Duration::from_secs(60));
+				self.max_duration_to_next_kad);
 
 			if actually_started {
 				let ev = DiscoveryOut::RandomKademliaStarted;
@@ -376,8 +432,10 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 						return Poll::Ready(NetworkBehaviourAction::GenerateEvent(ev));
 					}
 					KademliaEvent::RoutingUpdated { peer, .. } => {
-						let ev = DiscoveryOut::Discovered(peer);
-						return Poll::Ready(NetworkBehaviourAction::GenerateEvent(ev));
+						if self.discovery_enabled {
+							let ev = DiscoveryOut::Discovered(peer);
+							return Poll::Ready(NetworkBehaviourAction::GenerateEvent(ev));
+						}
 					}
 					KademliaEvent::GetClosestPeersResult(res) => {
 						match res {
@@ -505,7 +563,8 @@ mod tests {
 	use libp2p::core::upgrade::{InboundUpgradeExt, OutboundUpgradeExt};
 	use libp2p::swarm::Swarm;
 	use std::{collections::HashSet, task::Poll};
-	use super::{DiscoveryBehaviour, DiscoveryOut};
+	use libp2p::swarm::NetworkBehaviour;
+	use super::{DiscoveryBehaviour, DiscoveryConfig, DiscoveryOut};
 
 	#[test]
 	fn discovery_working() {
@@ -538,7 +597,15 @@ mod tests {
 				let user_defined = user_defined.clone();
 				let keypair_public = keypair.public();
 				async move {
-					DiscoveryBehaviour::new(keypair_public, user_defined, false, true, 50).await
+					DiscoveryBehaviour::new(
+						keypair_public,
+						user_defined,
+						false,
+						true,
+						false,
+						50,
+						DiscoveryConfig::default(),
+					).await
 				}
 			});
 			let mut swarm = Swarm::new(transport, behaviour, keypair.public().into_peer_id());
@@ -598,4 +665,50 @@ mod tests {
 
 		futures::executor::block_on(fut);
 	}
+
+	#[test]
+	fn ipv6_addresses_are_preferred() {
+		let peer_id = Keypair::generate_ed25519().public().into_peer_id();
+		let ipv4: Multiaddr = "/ip4/198.51.100.19/tcp/30333".parse().unwrap();
+		let ipv6: Multiaddr = "/ip6/2001:db8::1/tcp/30333".parse().unwrap();
+		let user_defined = vec![(peer_id.clone(), ipv4.clone()), (peer_id.clone(), ipv6.clone())];
+
+		for &prefer_ipv6 in &[false, true] {
+			let mut behaviour = futures::executor::block_on(DiscoveryBehaviour::new(
+				Keypair::generate_ed25519().public(),
+				user_defined.clone(),
+				false,
+				true,
+				prefer_ipv6,
+				50,
+				DiscoveryConfig::default(),
+			));
+			let addresses = behaviour.addresses_of_peer(&peer_id);
+			assert_eq!(addresses[0], if prefer_ipv6 { ipv6.clone() } else { ipv4.clone() });
+		}
+	}
+
+	#[test]
+	fn only_added_peers_are_discovered_without_discovery() {
+		let config = DiscoveryConfig { enabled: false, .. DiscoveryConfig::default() };
+		let mut behaviour = futures::executor::block_on(DiscoveryBehaviour::new(
+			Keypair::generate_ed25519().public(),
+			Vec::new(),
+			true,
+			true,
+			false,
+			50,
+			config,
+		));
+		let (persisted, added) = (
+			Keypair::generate_ed25519().public().into_peer_id(),
+			Keypair::generate_ed25519().public().into_peer_id(),
+		);
+		let addr: Multiaddr = "/ip4/198.51.100.19/tcp/30333".parse().unwrap();
+
+		behaviour.add_persisted_address(persisted.clone(), addr.clone());
+		behaviour.add_known_address(added.clone(), addr.clone());
+		assert_eq!(behaviour.discoveries.iter().collect::<Vec<_>>(), vec![&added]);
+		assert_eq!(behaviour.addresses_of_peer(&persisted), vec![addr]);
+	}
 }
@@ -0,0 +1,284 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Resolution of the `/dnsaddr` bootnode addresses.
+//!
+//! The addresses of `/dnsaddr/<host>` are given by the TXT records of `_dnsaddr.<host>`, of the
+//! form `dnsaddr=<multiaddr>`. These can themselves be `/dnsaddr` addresses, which are resolved
+//! recursively.
+//!
+//! The bootnodes are resolved when the network starts, and again when one of the peers they
+//! resolved to can't be reached, so that their operators can change their IP addresses without
+//! shipping a new chain spec. The resolutions run on a background thread, so that a slow DNS
+//! server doesn't hold the network up.
+
+use futures::{channel::oneshot, prelude::*};
+use libp2p::{Multiaddr, PeerId};
+use log::{debug, warn};
+use std::{collections::HashSet, task::{Context, Poll}, time::Duration};
+use wasm_timer::Instant;
+
+use crate::config::parse_str_addr;
+
+/// Maximal number of nested `/dnsaddr` addresses that are resolved.
+const MAX_RESOLUTION_DEPTH: usize = 4;
+
+/// Minimal time between two resolutions.
+const MIN_RESOLUTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A `/dnsaddr/<host>` address, optionally followed by `/p2p/<peer id>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsaddrBootnode {
+	/// The host whose TXT records are resolved.
+	pub host: String,
+	/// If set, only the addresses of this peer are used.
+	pub peer_id: Option<PeerId>,
+}
+
+impl DnsaddrBootnode {
+	/// Parses a `/dnsaddr` address. Returns `None` if `addr` isn't a valid one.
+	pub fn parse(addr: &str) -> Option<Self> {
+		let mut parts = addr.split('/');
+		if parts.next() != Some("") || parts.next() != Some("dnsaddr") {
+			return None;
+		}
+		let host = match parts.next() {
+			Some(host) if !host.is_empty() => host.to_owned(),
+			_ => return None,
+		};
+		let peer_id = match (parts.next(), parts.next()) {
+			(None, _) => None,
+			(Some("p2p"), Some(peer_id)) | (Some("ipfs"), Some(peer_id)) =>
+				Some(peer_id.parse().ok()?),
+			_ => return None,
+		};
+		if parts.next().is_some() {
+			return None;
+		}
+		Some(DnsaddrBootnode { host, peer_id })
+	}
+}
+
+/// Resolves `bootnode` into addresses, looking the TXT records up with `lookup`.
+fn resolve_with(
+	bootnode: &DnsaddrBootnode,
+	lookup: &mut dyn FnMut(&str) -> Result<Vec<String>, String>,
+	depth: usize,
+) -> Vec<(PeerId, Multiaddr)> {
+	let records = match lookup(&format!("_dnsaddr.{}", bootnode.host)) {
+		Ok(records) => records,
+		Err(err) => {
+			warn!(target: "sub-libp2p", "Failed to resolve /dnsaddr/{}: {}", bootnode.host, err);
+			return Vec::new()
+		}
+	};
+
+	let mut addresses = Vec::new();
+	for record in records {
+		let mut key_value = record.trim().splitn(2, '=');
+		let entry = match (key_value.next(), key_value.next()) {
+			(Some("dnsaddr"), Some(entry)) => entry,
+			_ => continue,
+		};
+		let found = if let Some(nested) = DnsaddrBootnode::parse(entry) {
+			if depth >= MAX_RESOLUTION_DEPTH {
+				warn!(target: "sub-libp2p", "Too many nested /dnsaddr in {}", bootnode.host);
+				continue
+			}
+			resolve_with(&nested, lookup, depth + 1)
+		} else {
+			match parse_str_addr(entry) {
+				Ok(address) => vec![address],
+				Err(err) => {
+					debug!(target: "sub-libp2p", "Invalid /dnsaddr entry {}: {}", entry, err);
+					continue
+				}
+			}
+		};
+		for address in found {
+			let expected = bootnode.peer_id.as_ref().map_or(true, |peer_id| *peer_id == address.0);
+			if expected && !addresses.contains(&address) {
+				addresses.push(address);
+			}
+		}
+	}
+	addresses
+}
+
+/// Returns the TXT records of `name`.
+#[cfg(not(target_os = "unknown"))]
+fn lookup_txt(name: &str) -> Result<Vec<String>, String> {
+	let resolver = trust_dns_resolver::Resolver::from_system_conf().map_err(|e| e.to_string())?;
+	let records = resolver.txt_lookup(name).map_err(|e| e.to_string())?;
+	Ok(records.iter()
+		.map(|txt| txt.txt_data().iter()
+			.map(|data| String::from_utf8_lossy(data).into_owned())
+			.collect())
+		.collect())
+}
+
+/// Returns the TXT records of `name`.
+#[cfg(target_os = "unknown")]
+fn lookup_txt(_: &str) -> Result<Vec<String>, String> {
+	Err("DNS resolution isn't supported in the browser".into())
+}
+
+/// Resolves the bootnodes into addresses.
+fn resolve(bootnodes: &[DnsaddrBootnode]) -> Vec<(PeerId, Multiaddr)> {
+	bootnodes.iter()
+		.flat_map(|bootnode| resolve_with(bootnode, &mut lookup_txt, 0))
+		.collect()
+}
+
+/// The `/dnsaddr` bootnodes, re-resolved when the peers they resolved to can't be reached.
+pub struct DnsaddrBootnodes {
+	bootnodes: Vec<DnsaddrBootnode>,
+	/// Peers the last resolution found.
+	peers: HashSet<PeerId>,
+	/// Resolution in progress on a background thread.
+	pending: Option<oneshot::Receiver<Vec<(PeerId, Multiaddr)>>>,
+	/// When the last resolution started.
+	last_resolution: Option<Instant>,
+}
+
+impl DnsaddrBootnodes {
+	/// Creates the list of bootnodes, and starts resolving them in the background. Their
+	/// addresses are returned by `poll`.
+	pub fn new(bootnodes: Vec<DnsaddrBootnode>) -> Self {
+		let mut dnsaddr_bootnodes = DnsaddrBootnodes {
+			bootnodes,
+			peers: HashSet::new(),
+			pending: None,
+			last_resolution: None,
+		};
+		if !dnsaddr_bootnodes.bootnodes.is_empty() {
+			dnsaddr_bootnodes.start_resolution();
+		}
+		dnsaddr_bootnodes
+	}
+
+	/// Call this method when an address of a peer can't be reached. Resolves the bootnodes again
+	/// in the background if it is one of theirs, and they weren't resolved recently.
+	pub fn on_unreachable(&mut self, peer_id: &PeerId) {
+		if !self.peers.contains(peer_id) || self.pending.is_some() {
+			return;
+		}
+		if let Some(last) = self.last_resolution {
+			if last.elapsed() < MIN_RESOLUTION_INTERVAL {
+				return;
+			}
+		}
+
+		debug!(target: "sub-libp2p", "Bootnode {} unreachable, resolving /dnsaddr again", peer_id);
+		self.start_resolution();
+	}
+
+	/// Resolves the bootnodes on a background thread.
+	fn start_resolution(&mut self) {
+		self.last_resolution = Some(Instant::now());
+		let (tx, rx) = oneshot::channel();
+		let bootnodes = self.bootnodes.clone();
+		let spawned = std::thread::Builder::new()
+			.name("dnsaddr-resolver".into())
+			.spawn(move || { let _ = tx.send(resolve(&bootnodes)); });
+		match spawned {
+			Ok(_) => self.pending = Some(rx),
+			Err(err) => warn!(target: "sub-libp2p", "Failed to resolve /dnsaddr: {}", err),
+		}
+	}
+
+	/// Returns the addresses of a finished resolution, the first one included.
+	pub fn poll(&mut self, cx: &mut Context) -> Poll<Vec<(PeerId, Multiaddr)>> {
+		let pending = match self.pending.as_mut() {
+			Some(pending) => pending,
+			None => return Poll::Pending,
+		};
+		let result = match pending.poll_unpin(cx) {
+			Poll::Ready(result) => result,
+			Poll::Pending => return Poll::Pending,
+		};
+		self.pending = None;
+		match result {
+			Ok(addresses) => {
+				self.peers = addresses.iter().map(|(peer_id, _)| peer_id.clone()).collect();
+				Poll::Ready(addresses)
+			},
+			Err(_) => Poll::Pending,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::HashMap;
+
+	const PEER_ID: &str = "QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV";
+
+	#[test]
+	fn dnsaddr_is_parsed() {
+		assert_eq!(
+			DnsaddrBootnode::parse("/dnsaddr/bootnodes.example.com"),
+			Some(DnsaddrBootnode { host: "bootnodes.example.com".into(), peer_id: None }),
+		);
+		assert_eq!(
+			DnsaddrBootnode::parse(&format!("/dnsaddr/example.com/p2p/{}", PEER_ID)),
+			Some(DnsaddrBootnode {
+				host: "example.com".into(),
+				peer_id: Some(PEER_ID.parse().unwrap()),
+			}),
+		);
+		assert_eq!(DnsaddrBootnode::parse("/dns4/example.com/tcp/30333"), None);
+		assert_eq!(DnsaddrBootnode::parse("/dnsaddr/example.com/p2p/invalid"), None);
+		assert_eq!(DnsaddrBootnode::parse("/dnsaddr/"), None);
+	}
+
+	#[test]
+	fn dnsaddr_is_resolved_recursively() {
+		let other = PeerId::random();
+		let records: HashMap<&str, Vec<String>> = vec![
+			("_dnsaddr.example.com", vec![
+				"dnsaddr=/dnsaddr/nested.example.com".to_owned(),
+				format!("dnsaddr=/ip4/198.51.100.19/tcp/30333/p2p/{}", PEER_ID),
+				"unrelated record".to_owned(),
+			]),
+			("_dnsaddr.nested.example.com", vec![
+				format!("dnsaddr=/ip6/2001:db8::1/tcp/30333/p2p/{}", PEER_ID),
+				format!("dnsaddr=/ip4/198.51.100.20/tcp/30333/p2p/{}", other),
+				"dnsaddr=/dnsaddr/nested.example.com".to_owned(),
+			]),
+		].into_iter().collect();
+		let mut lookup = |name: &str| -> Result<Vec<String>, String> {
+			records.get(name).cloned().ok_or_else(|| "NXDOMAIN".into())
+		};
+
+		let bootnode = DnsaddrBootnode::parse("/dnsaddr/example.com").unwrap();
+		let addresses = resolve_with(&bootnode, &mut lookup, 0);
+		// the loop of the nested record stops at the maximal depth.
+		assert_eq!(addresses.len(), 3);
+
+		let peer_id: PeerId = PEER_ID.parse().unwrap();
+		let bootnode = DnsaddrBootnode {
+			host: "example.com".into(),
+			peer_id: Some(peer_id.clone()),
+		};
+		let addresses = resolve_with(&bootnode, &mut lookup, 0);
+		assert_eq!(addresses, vec![
+			(peer_id.clone(), "/ip6/2001:db8::1/tcp/30333".parse().unwrap()),
+			(peer_id, "/ip4/198.51.100.19/tcp/30333".parse().unwrap()),
+		]);
+	}
+}
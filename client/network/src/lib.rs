@@ -161,22 +161,33 @@
 //! More precise usage details are still being worked on and will likely change in the future.
 //!
 
+mod address_book;
 mod behaviour;
 mod chain;
 mod debug_info;
 mod discovery;
+mod dnsaddr;
+mod observed_addresses;
 mod on_demand_layer;
+mod peer_filter;
 mod protocol;
+mod request_responses;
 mod service;
 mod transport;
+mod upnp;
 mod utils;
 
 pub mod config;
+pub mod decode;
 pub mod error;
 pub mod network_state;
 
-pub use service::{NetworkService, NetworkStateInfo, NetworkWorker, ExHashT, ReportHandle};
+pub use service::{
+	NetworkService, NetworkStateInfo, NetworkWorker, ExHashT, ReportHandle, Signature,
+};
+pub use address_book::{load as load_address_book, PersistedAddress, PersistedPeer};
 pub use protocol::PeerInfo;
+pub use request_responses::{IncomingRequest, RequestFailure};
 pub use protocol::event::{Event, DhtEvent};
 pub use protocol::sync::SyncState;
 pub use libp2p::{Multiaddr, PeerId};
@@ -0,0 +1,153 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Confirmation of the addresses peers observe us at.
+//!
+//! Through the identify protocol, every peer reports the address it sees our connection coming
+//! from. A single peer can't be trusted with this, so an address is only advertised once
+//! enough distinct peers reported it recently. To protect against flapping between several
+//! addresses, e.g. behind a load-balancing NAT, a new address is only confirmed a while after
+//! the previous one.
+
+use fnv::FnvHashMap;
+use libp2p::{Multiaddr, PeerId};
+use std::time::Duration;
+use wasm_timer::Instant;
+
+/// Time after which the report of a peer is no longer taken into account.
+const REPORT_EXPIRE: Duration = Duration::from_secs(30 * 60);
+/// Minimum time between the confirmation of two different addresses.
+const MIN_CONFIRMATION_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Addresses observed by peers, waiting for confirmation.
+pub struct ObservedAddresses {
+	/// Number of distinct peers that must report an address for it to be confirmed. Observed
+	/// addresses are ignored if 0.
+	confirmations: usize,
+	/// Addresses not confirmed yet, with the peers that reported them and when they last did.
+	pending: FnvHashMap<Multiaddr, FnvHashMap<PeerId, Instant>>,
+	/// Confirmed addresses, with when they were last reported.
+	confirmed: FnvHashMap<Multiaddr, Instant>,
+	/// When an address was last confirmed.
+	last_confirmation: Option<Instant>,
+}
+
+impl ObservedAddresses {
+	/// Builds a new `ObservedAddresses` confirming addresses reported by `confirmations` peers.
+	pub fn new(confirmations: usize) -> Self {
+		ObservedAddresses {
+			confirmations,
+			pending: FnvHashMap::default(),
+			confirmed: FnvHashMap::default(),
+			last_confirmation: None,
+		}
+	}
+
+	/// Registers that `peer_id` observed us at `address`.
+	///
+	/// Returns `true` if this report confirms the address, which should then be advertised.
+	pub fn report(&mut self, peer_id: &PeerId, address: Multiaddr, now: Instant) -> bool {
+		if self.confirmations == 0 {
+			return false;
+		}
+
+		if let Some(last_report) = self.confirmed.get_mut(&address) {
+			*last_report = now;
+			return false;
+		}
+
+		let reports = self.pending.entry(address.clone()).or_default();
+		reports.insert(peer_id.clone(), now);
+		reports.retain(|_, reported| now.duration_since(*reported) < REPORT_EXPIRE);
+		if reports.len() < self.confirmations {
+			return false;
+		}
+
+		if let Some(last_confirmation) = self.last_confirmation {
+			if now.duration_since(last_confirmation) < MIN_CONFIRMATION_INTERVAL {
+				return false;
+			}
+		}
+
+		self.pending.remove(&address);
+		self.confirmed.insert(address, now);
+		self.last_confirmation = Some(now);
+		true
+	}
+
+	/// Forgets the reports that expired, and the confirmed addresses no peer reported since.
+	pub fn collect_garbage(&mut self, now: Instant) {
+		for reports in self.pending.values_mut() {
+			reports.retain(|_, reported| now.duration_since(*reported) < REPORT_EXPIRE);
+		}
+		self.pending.retain(|_, reports| !reports.is_empty());
+		self.confirmed.retain(|_, reported| now.duration_since(*reported) < REPORT_EXPIRE);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn address(port: u16) -> Multiaddr {
+		format!("/ip4/1.2.3.4/tcp/{}", port).parse().unwrap()
+	}
+
+	#[test]
+	fn confirms_address_reported_by_enough_peers() {
+		let mut observed = ObservedAddresses::new(2);
+		let now = Instant::now();
+		let (alice, bob) = (PeerId::random(), PeerId::random());
+
+		assert!(!observed.report(&alice, address(30333), now));
+		// The same peer reporting again doesn't count.
+		assert!(!observed.report(&alice, address(30333), now));
+		assert!(observed.report(&bob, address(30333), now));
+		assert!(observed.confirmed.contains_key(&address(30333)));
+		// Confirmed only once.
+		assert!(!observed.report(&bob, address(30333), now));
+	}
+
+	#[test]
+	fn ignores_expired_reports() {
+		let mut observed = ObservedAddresses::new(2);
+		let now = Instant::now();
+
+		assert!(!observed.report(&PeerId::random(), address(30333), now));
+		let later = now + REPORT_EXPIRE;
+		assert!(!observed.report(&PeerId::random(), address(30333), later));
+
+		observed.collect_garbage(later + REPORT_EXPIRE);
+		assert!(observed.pending.is_empty());
+	}
+
+	#[test]
+	fn delays_confirmation_of_new_address() {
+		let mut observed = ObservedAddresses::new(1);
+		let now = Instant::now();
+
+		assert!(observed.report(&PeerId::random(), address(30333), now));
+		assert!(!observed.report(&PeerId::random(), address(30334), now));
+		let later = now + MIN_CONFIRMATION_INTERVAL;
+		assert!(observed.report(&PeerId::random(), address(30334), later));
+	}
+
+	#[test]
+	fn disabled_without_confirmations() {
+		let mut observed = ObservedAddresses::new(0);
+		assert!(!observed.report(&PeerId::random(), address(30333), Instant::now()));
+	}
+}
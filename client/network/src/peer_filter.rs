@@ -0,0 +1,134 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Allowing and denying the connections with peers, by identity or by IP address.
+//!
+//! The filter is applied by the transport once the identity of the remote is authenticated,
+//! before the connection is used by any protocol.
+
+use libp2p::{PeerId, core::ConnectedPoint, multiaddr::Protocol};
+use std::net::IpAddr;
+
+use crate::config::PeerFilterEntry;
+
+/// Peers the connections are accepted or refused with.
+#[derive(Debug, Default)]
+pub struct PeerFilter {
+	/// If not empty, only the peers matching one of these are accepted.
+	allowed: Vec<PeerFilterEntry>,
+	/// The peers matching one of these are refused.
+	denied: Vec<PeerFilterEntry>,
+}
+
+impl PeerFilter {
+	/// Creates a filter with the given lists.
+	pub fn new(allowed: Vec<PeerFilterEntry>, denied: Vec<PeerFilterEntry>) -> Self {
+		PeerFilter { allowed, denied }
+	}
+
+	/// Returns true if the connection with `peer_id` through `endpoint` is accepted: the peer
+	/// matches none of the denied entries, and one of the allowed entries if there are any.
+	pub fn is_allowed(&self, peer_id: &PeerId, endpoint: &ConnectedPoint) -> bool {
+		let ip = remote_ip(endpoint);
+		if self.denied.iter().any(|entry| entry.matches(peer_id, ip)) {
+			return false;
+		}
+		self.allowed.is_empty() || self.allowed.iter().any(|entry| entry.matches(peer_id, ip))
+	}
+
+	/// Adds an entry to the allowed ones.
+	pub fn allow(&mut self, entry: PeerFilterEntry) {
+		if !self.allowed.contains(&entry) {
+			self.allowed.push(entry);
+		}
+	}
+
+	/// Removes an entry from the allowed ones.
+	pub fn remove_allowed(&mut self, entry: &PeerFilterEntry) {
+		self.allowed.retain(|allowed| allowed != entry);
+	}
+
+	/// Adds an entry to the denied ones.
+	pub fn deny(&mut self, entry: PeerFilterEntry) {
+		if !self.denied.contains(&entry) {
+			self.denied.push(entry);
+		}
+	}
+
+	/// Removes an entry from the denied ones.
+	pub fn remove_denied(&mut self, entry: &PeerFilterEntry) {
+		self.denied.retain(|denied| denied != entry);
+	}
+}
+
+/// Returns the IP address of the remote of a connection, if it has one.
+fn remote_ip(endpoint: &ConnectedPoint) -> Option<IpAddr> {
+	let address = match endpoint {
+		ConnectedPoint::Dialer { address } => address,
+		ConnectedPoint::Listener { send_back_addr, .. } => send_back_addr,
+	};
+	match address.iter().next() {
+		Some(Protocol::Ip4(ip)) => Some(ip.into()),
+		Some(Protocol::Ip6(ip)) => Some(ip.into()),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn dialer(address: &str) -> ConnectedPoint {
+		ConnectedPoint::Dialer { address: address.parse().unwrap() }
+	}
+
+	#[test]
+	fn denied_peers_are_refused() {
+		let peer_id = PeerId::random();
+		let mut filter = PeerFilter::default();
+		assert!(filter.is_allowed(&peer_id, &dialer("/ip4/192.0.2.1/tcp/30333")));
+
+		filter.deny("192.0.2.0/24".parse().unwrap());
+		assert!(!filter.is_allowed(&peer_id, &dialer("/ip4/192.0.2.1/tcp/30333")));
+		assert!(filter.is_allowed(&peer_id, &dialer("/ip4/198.51.100.19/tcp/30333")));
+		let listener = ConnectedPoint::Listener {
+			local_addr: "/ip4/198.51.100.19/tcp/30333".parse().unwrap(),
+			send_back_addr: "/ip4/192.0.2.1/tcp/41234".parse().unwrap(),
+		};
+		assert!(!filter.is_allowed(&peer_id, &listener));
+
+		filter.remove_denied(&"192.0.2.0/24".parse().unwrap());
+		assert!(filter.is_allowed(&peer_id, &listener));
+	}
+
+	#[test]
+	fn only_allowed_peers_are_accepted() {
+		let (validator, relay, other) = (PeerId::random(), PeerId::random(), PeerId::random());
+		let mut filter = PeerFilter::new(
+			vec![PeerFilterEntry::PeerId(validator.clone()), "192.0.2.0/24".parse().unwrap()],
+			vec![PeerFilterEntry::PeerId(relay.clone())],
+		);
+		let address = dialer("/dns4/example.com/tcp/30333");
+		assert!(filter.is_allowed(&validator, &address));
+		assert!(!filter.is_allowed(&other, &address));
+		assert!(filter.is_allowed(&other, &dialer("/ip4/192.0.2.1/tcp/30333")));
+		// the denied entries take precedence.
+		assert!(!filter.is_allowed(&relay, &dialer("/ip4/192.0.2.1/tcp/30333")));
+
+		filter.allow(PeerFilterEntry::PeerId(other.clone()));
+		assert!(filter.is_allowed(&other, &address));
+	}
+}
@@ -25,7 +25,7 @@ use libp2p::swarm::{ProtocolsHandler, IntoProtocolsHandler};
 use libp2p::swarm::{NetworkBehaviour, NetworkBehaviourAction, PollParameters};
 use sp_core::storage::{StorageKey, ChildInfo};
 use sp_consensus::{
-	BlockOrigin,
+	BlockOrigin, BlockStatus,
 	block_validation::BlockAnnounceValidator,
 	import_queue::{BlockImportResult, BlockImportError, IncomingBlock, Origin}
 };
@@ -35,16 +35,18 @@ use sp_runtime::traits::{
 	Block as BlockT, Header as HeaderT, NumberFor, One, Zero, CheckedSub
 };
 use sp_arithmetic::traits::SaturatedConversion;
-use message::{BlockAnnounce, BlockAttributes, Direction, FromBlock, Message, RequestId};
+use message::{BlockAnnounce, BlockAttributes, Capabilities, Direction, FromBlock, Message, RequestId};
 use message::generic::Message as GenericMessage;
 use light_dispatch::{LightDispatch, LightDispatchNetwork, RequestData};
-use prometheus_endpoint::{Registry, Gauge, GaugeVec, PrometheusError, Opts, register, U64};
+use prometheus_endpoint::{
+	Registry, CounterVec, Gauge, GaugeVec, PrometheusError, Opts, register, U64
+};
 use sync::{ChainSync, SyncState};
 use crate::service::{TransactionPool, ExHashT};
 use crate::config::{BoxFinalityProofRequestBuilder, Roles};
 use rustc_hex::ToHex;
 use std::borrow::Cow;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::fmt::Write;
 use std::{cmp, num::NonZeroUsize, pin::Pin, task::Poll, time};
@@ -90,9 +92,11 @@ const MAX_KNOWN_BLOCKS: usize = 1024; // ~32kb per peer + LruHashSet overhead
 const MAX_KNOWN_EXTRINSICS: usize = 4096; // ~128kb per peer + overhead
 
 /// Current protocol version.
-pub(crate) const CURRENT_VERSION: u32 = 6;
+pub(crate) const CURRENT_VERSION: u32 = 7;
 /// Lowest version we support
 pub(crate) const MIN_VERSION: u32 = 3;
+/// First version sending its capabilities in the status message.
+const CAPABILITIES_VERSION: u32 = 7;
 
 // Maximum allowed entries in `BlockResponse`
 const MAX_BLOCK_DATA_RESPONSE: u32 = 128;
@@ -100,6 +104,9 @@ const MAX_BLOCK_DATA_RESPONSE: u32 = 128;
 /// for at least `LIGHT_MAXIMAL_BLOCKS_DIFFERENCE` blocks, we consider it not useful
 /// and disconnect to free connection slot.
 const LIGHT_MAXIMAL_BLOCKS_DIFFERENCE: u64 = 8192;
+/// Number of blocks imported on top of the best block at the time a block was last received
+/// from a peer, after which a node connected to peers is considered isolated from them.
+const MAX_BLOCKS_WITHOUT_PEERS: u32 = 5;
 
 mod rep {
 	use sc_peerset::ReputationChange as Rep;
@@ -112,10 +119,15 @@ mod rep {
 	pub const UNEXPECTED_STATUS: Rep = Rep::new(-(1 << 20), "Unexpected status message");
 	/// Reputation change when we are a light client and a peer is behind us.
 	pub const PEER_BEHIND_US_LIGHT: Rep = Rep::new(-(1 << 8), "Useless for a light peer");
+	/// Reputation change when a light peer connects while all the slots of the light peers are
+	/// taken.
+	pub const TOO_MANY_LIGHT_PEERS: Rep = Rep::new(-(1 << 8), "Too many light peers");
 	/// Reputation change when a peer sends us an extrinsic that we didn't know about.
 	pub const GOOD_EXTRINSIC: Rep = Rep::new(1 << 7, "Good extrinsic");
 	/// Reputation change when a peer sends us a bad extrinsic.
 	pub const BAD_EXTRINSIC: Rep = Rep::new(-(1 << 12), "Bad extrinsic");
+	/// Reputation change when a peer sends us an extrinsic above the size limit.
+	pub const OVERSIZED_EXTRINSIC: Rep = Rep::new(-(1 << 12), "Oversized extrinsic");
 	/// We sent an RPC query to the given node, but it failed.
 	pub const RPC_FAILED: Rep = Rep::new(-(1 << 12), "Remote call failed");
 	/// We received a message that failed to decode.
@@ -134,6 +146,8 @@ mod rep {
 	pub const BAD_ROLE: Rep = Rep::new_fatal("Unsupported role");
 	/// Peer response data does not have requested bits.
 	pub const BAD_RESPONSE: Rep = Rep::new(-(1 << 12), "Incomplete response");
+	/// Peer sent us a block announcement refused by the block announce validator.
+	pub const BAD_BLOCK_ANNOUNCEMENT: Rep = Rep::new(-(1 << 12), "Bad block announcement");
 }
 
 struct Metrics {
@@ -144,6 +158,7 @@ struct Metrics {
 	fork_targets: Gauge<U64>,
 	finality_proofs: GaugeVec<U64>,
 	justifications: GaugeVec<U64>,
+	peer_versions: GaugeVec<U64>,
 }
 
 impl Metrics {
@@ -189,6 +204,16 @@ impl Metrics {
 				)?;
 				register(g, r)?
 			},
+			peer_versions: {
+				let g = GaugeVec::new(
+					Opts::new(
+						"sync_peer_protocol_versions",
+						"Number of peers we sync with, by advertised protocol version",
+					),
+					&["version"],
+				)?;
+				register(g, r)?
+			},
 		})
 	}
 }
@@ -221,8 +246,14 @@ pub struct Protocol<B: BlockT, H: ExHashT> {
 	protocol_name_by_engine: HashMap<ConsensusEngineId, Cow<'static, [u8]>>,
 	/// For each protocol name, the legacy gossiping engine ID.
 	protocol_engine_by_name: HashMap<Cow<'static, [u8]>, ConsensusEngineId>,
+	/// For each legacy gossiping engine ID, the peers the protocol is opened with.
+	notif_slots: HashMap<ConsensusEngineId, NotifSlots>,
+	/// Outcomes to report on the next calls to `poll`.
+	pending_outcomes: VecDeque<CustomMessageOutcome<B>>,
 	/// Prometheus metrics.
 	metrics: Option<Metrics>,
+	/// Our best block number when a new block was last received from a peer.
+	best_number_at_block_received: NumberFor<B>,
 }
 
 #[derive(Default)]
@@ -233,12 +264,121 @@ struct PacketStats {
 	count_out: u64,
 }
 
+/// Messages exchanged with the peers, by kind of message.
+struct Traffic {
+	stats: HashMap<&'static str, PacketStats>,
+	/// Prometheus counters the traffic is also reported to, by kind of message or by consensus
+	/// engine for the consensus messages.
+	metrics: Option<TrafficMetrics>,
+	/// The consensus engines of the registered notifications protocols. The consensus messages
+	/// of the other engines are reported as "unknown", so that the peers can't make us create
+	/// arbitrarily many time series.
+	engines: HashSet<ConsensusEngineId>,
+}
+
+struct TrafficMetrics {
+	bytes: CounterVec<U64>,
+	messages: CounterVec<U64>,
+}
+
+impl TrafficMetrics {
+	fn register(r: &Registry) -> Result<Self, PrometheusError> {
+		Ok(TrafficMetrics {
+			bytes: {
+				let c = CounterVec::new(
+					Opts::new(
+						"sub_libp2p_protocol_bytes_total",
+						"Number of bytes of the messages exchanged with the peers, by protocol",
+					),
+					&["direction", "protocol"],
+				)?;
+				register(c, r)?
+			},
+			messages: {
+				let c = CounterVec::new(
+					Opts::new(
+						"sub_libp2p_protocol_messages_total",
+						"Number of messages exchanged with the peers, by protocol",
+					),
+					&["direction", "protocol"],
+				)?;
+				register(c, r)?
+			},
+		})
+	}
+}
+
+impl Traffic {
+	/// Records a message received if `inbound`, or sent, whose encoding is `len` bytes long.
+	fn record<B: BlockT>(&mut self, message: &Message<B>, len: usize, inbound: bool) {
+		let stats = self.stats.entry(message.id()).or_default();
+		if inbound {
+			stats.bytes_in += len as u64;
+			stats.count_in += 1;
+		} else {
+			stats.bytes_out += len as u64;
+			stats.count_out += 1;
+		}
+
+		let metrics = match self.metrics.as_ref() {
+			Some(metrics) => metrics,
+			None => return,
+		};
+		let direction = if inbound { "in" } else { "out" };
+		let report = |protocol: &str, len: usize| {
+			metrics.bytes.with_label_values(&[direction, protocol]).inc_by(len as u64);
+			metrics.messages.with_label_values(&[direction, protocol]).inc();
+		};
+		let engines = &self.engines;
+		let report_consensus = |engine_id: &ConsensusEngineId, len: usize| {
+			if engines.contains(engine_id) {
+				report(&String::from_utf8_lossy(&engine_id[..]), len)
+			} else {
+				report("unknown", len)
+			}
+		};
+		match message {
+			GenericMessage::Consensus(msg) => report_consensus(&msg.engine_id, len),
+			GenericMessage::ConsensusBatch(messages) =>
+				for msg in messages {
+					report_consensus(&msg.engine_id, msg.data.len());
+				},
+			_ => report(message.id(), len),
+		}
+	}
+}
+
 /// A peer that we are connected to
 /// and from whom we have not yet received a Status message.
 struct HandshakingPeer {
 	timestamp: Instant,
 }
 
+/// The peers a notifications protocol is opened with.
+struct NotifSlots {
+	/// Maximum number of peers, not counting the important ones. `None` if unlimited.
+	max_peers: Option<usize>,
+	/// Peers given a slot.
+	peers: HashSet<PeerId>,
+}
+
+impl NotifSlots {
+	/// Gives a slot to `who` if there is one left, or if it is one of the `important_peers`.
+	/// Returns true if `who` has a slot.
+	fn allocate(&mut self, who: &PeerId, important_peers: &HashSet<PeerId>) -> bool {
+		if self.peers.contains(who) {
+			return true;
+		}
+		let is_free = important_peers.contains(who) || self.max_peers.map_or(true, |max|
+			self.peers.iter().filter(|peer| !important_peers.contains(peer)).count() < max
+		);
+		if is_free {
+			self.peers.insert(who.clone());
+		}
+		is_free
+	}
+}
+
 /// Peer information
 #[derive(Debug, Clone)]
 struct Peer<B: BlockT, H: ExHashT> {
@@ -262,6 +402,10 @@ pub struct PeerInfo<B: BlockT> {
 	pub roles: Roles,
 	/// Protocol version
 	pub protocol_version: u32,
+	/// Protocol version used with the peer, the highest one supported by both sides.
+	pub negotiated_version: u32,
+	/// Optional protocol features supported by both sides.
+	pub capabilities: Capabilities,
 	/// Peer best block hash
 	pub best_hash: B::Hash,
 	/// Peer best block number
@@ -398,7 +542,7 @@ impl<'a, B: BlockT> LightDispatchNetwork<B> for LightDispatchIn<'a> {
 struct ContextData<B: BlockT, H: ExHashT> {
 	// All connected peers
 	peers: HashMap<PeerId, Peer<B, H>>,
-	stats: HashMap<&'static str, PacketStats>,
+	traffic: Traffic,
 	pub chain: Arc<dyn Client<B>>,
 }
 
@@ -409,6 +553,10 @@ pub struct ProtocolConfig {
 	pub roles: Roles,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	pub max_parallel_downloads: u32,
+	/// Maximum encoded size of an extrinsic received from a peer. `None` if unlimited.
+	pub max_extrinsic_size: Option<usize>,
+	/// Maximum number of light peers connected at the same time. `None` if unlimited.
+	pub max_light_peers: Option<u32>,
 }
 
 impl Default for ProtocolConfig {
@@ -416,6 +564,8 @@ impl Default for ProtocolConfig {
 		ProtocolConfig {
 			roles: Roles::FULL,
 			max_parallel_downloads: 5,
+			max_extrinsic_size: None,
+			max_light_peers: None,
 		}
 	}
 }
@@ -463,7 +613,15 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			config,
 			context_data: ContextData {
 				peers: HashMap::new(),
-				stats: HashMap::new(),
+				traffic: Traffic {
+					stats: HashMap::new(),
+					metrics: if let Some(r) = metrics_registry {
+						Some(TrafficMetrics::register(r)?)
+					} else {
+						None
+					},
+					engines: HashSet::new(),
+				},
 				chain,
 			},
 			light_dispatch: LightDispatch::new(checker),
@@ -477,11 +635,14 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			behaviour,
 			protocol_name_by_engine: HashMap::new(),
 			protocol_engine_by_name: HashMap::new(),
+			notif_slots: HashMap::new(),
+			pending_outcomes: VecDeque::new(),
 			metrics: if let Some(r) = metrics_registry {
 				Some(Metrics::register(r)?)
 			} else {
 				None
-			}
+			},
+			best_number_at_block_received: info.best_number,
 		};
 
 		Ok((protocol, peerset_handle))
@@ -522,6 +683,11 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.behaviour.peerset_debug_info()
 	}
 
+	/// Returns the peers known by the peerset manager, with their reputation.
+	pub fn peers_reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.behaviour.peers_reputations()
+	}
+
 	/// Returns the number of peers we're connected to.
 	pub fn num_connected_peers(&self) -> usize {
 		self.context_data.peers.values().count()
@@ -541,6 +707,19 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.sync.status().state
 	}
 
+	/// Whether we are connected to peers, but imported `MAX_BLOCKS_WITHOUT_PEERS` blocks on top
+	/// of our best block at the time a new block was last received from them. Such a node likely
+	/// builds a fork of its own.
+	pub fn is_isolated(&self) -> bool {
+		let best_number = self.context_data.chain.info().best_number;
+		self.num_connected_peers() > 0 &&
+			best_number >= self.best_number_at_block_received + MAX_BLOCKS_WITHOUT_PEERS.into()
+	}
+
+	fn on_block_received(&mut self) {
+		self.best_number_at_block_received = self.context_data.chain.info().best_number;
+	}
+
 	/// Target sync block number.
 	pub fn best_seen_block(&self) -> Option<NumberFor<B>> {
 		self.sync.status().best_seen_block
@@ -551,6 +730,16 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.sync.status().num_peers
 	}
 
+	/// The best block number when the sync started.
+	pub fn sync_starting_block(&self) -> NumberFor<B> {
+		self.sync.status().starting_block
+	}
+
+	/// Estimated time left to reach the best seen block, while downloading.
+	pub fn sync_eta(&self) -> Option<time::Duration> {
+		self.sync.status().eta
+	}
+
 	/// Number of blocks in the import queue.
 	pub fn num_queued_blocks(&self) -> u32 {
 		self.sync.status().queued_blocks
@@ -584,7 +773,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		&mut self,
 		who: PeerId,
 		response: &message::BlockResponse<B>
-	) -> Option<message::BlockRequest<B>> {
+	) -> Option<(Instant, message::BlockRequest<B>)> {
 		if let Some(ref mut peer) = self.context_data.peers.get_mut(&who) {
 			if let Some(_) = peer.obsolete_requests.remove(&response.id) {
 				trace!(target: "sync", "Ignoring obsolete block response packet from {} ({})", who, response.id);
@@ -593,7 +782,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			// Clear the request. If the response is invalid peer will be disconnected anyway.
 			let request = peer.block_request.take();
 			if request.as_ref().map_or(false, |(_, r)| r.id == response.id) {
-				return request.map(|(_, r)| r)
+				return request
 			}
 			trace!(target: "sync", "Unexpected response packet from {} ({})", who, response.id);
 			self.peerset_handle.report_peer(who.clone(), rep::UNEXPECTED_RESPONSE);
@@ -622,18 +811,16 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		data: BytesMut,
 	) -> CustomMessageOutcome<B> {
 
-		let message = match <Message<B> as Decode>::decode(&mut &data[..]) {
+		let message = match crate::decode::message::<B>(&data[..]) {
 			Ok(message) => message,
 			Err(err) => {
-				debug!(target: "sync", "Couldn't decode packet sent by {}: {:?}: {}", who, data, err.what());
+				debug!(target: "sync", "Couldn't decode packet sent by {}: {:?}: {}", who, data, err);
 				self.peerset_handle.report_peer(who.clone(), rep::BAD_MESSAGE);
 				return CustomMessageOutcome::None;
 			}
 		};
 
-		let mut stats = self.context_data.stats.entry(message.id()).or_default();
-		stats.bytes_in += data.len() as u64;
-		stats.count_in += 1;
+		self.context_data.traffic.record(&message, data.len(), true);
 
 		match message {
 			GenericMessage::Status(s) => return self.on_status_message(who, s),
@@ -643,7 +830,8 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				if self.is_light_response(&who, r.id) {
 					self.on_remote_body_response(who, r);
 				} else {
-					if let Some(request) = self.handle_response(who.clone(), &r) {
+					if let Some((timestamp, request)) = self.handle_response(who.clone(), &r) {
+						self.sync.on_request_completed(&who, timestamp.elapsed(), r.blocks.len());
 						let outcome = self.on_block_response(who.clone(), request, r);
 						self.update_peer_info(&who);
 						return outcome
@@ -679,11 +867,15 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			GenericMessage::RemoteReadChildRequest(request) =>
 				self.on_remote_read_child_request(who, request),
 			GenericMessage::Consensus(msg) =>
-				return if self.protocol_name_by_engine.contains_key(&msg.engine_id) {
+				return if self.has_notif_slot(&who, msg.engine_id) {
 					CustomMessageOutcome::NotificationsReceived {
 						remote: who.clone(),
 						messages: vec![(msg.engine_id, From::from(msg.data))],
 					}
+				} else if self.protocol_name_by_engine.contains_key(&msg.engine_id) {
+					trace!(target: "sync", "Ignoring {:?} message of {} without a slot",
+						msg.engine_id, who);
+					CustomMessageOutcome::None
 				} else {
 					warn!(target: "sync", "Received message on non-registered protocol: {:?}", msg.engine_id);
 					CustomMessageOutcome::None
@@ -692,8 +884,12 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				let messages = messages
 					.into_iter()
 					.filter_map(|msg| {
-						if self.protocol_name_by_engine.contains_key(&msg.engine_id) {
+						if self.has_notif_slot(&who, msg.engine_id) {
 							Some((msg.engine_id, From::from(msg.data)))
+						} else if self.protocol_name_by_engine.contains_key(&msg.engine_id) {
+							trace!(target: "sync", "Ignoring {:?} message of {} without a slot",
+								msg.engine_id, who);
+							None
 						} else {
 							warn!(target: "sync", "Received message on non-registered protocol: {:?}", msg.engine_id);
 							None
@@ -718,7 +914,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 	fn send_request(&mut self, who: &PeerId, message: Message<B>) {
 		send_request::<B, H>(
 			&mut self.behaviour,
-			&mut self.context_data.stats,
+			&mut self.context_data.traffic,
 			&mut self.context_data.peers,
 			who,
 			message,
@@ -728,7 +924,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 	fn send_message(&mut self, who: &PeerId, message: Message<B>) {
 		send_message::<B>(
 			&mut self.behaviour,
-			&mut self.context_data.stats,
+			&mut self.context_data.traffic,
 			who,
 			message,
 		);
@@ -761,11 +957,23 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				peerset: self.peerset_handle.clone(),
 			}, &peer);
 
-			// Notify all the notification protocols as closed.
-			CustomMessageOutcome::NotificationStreamClosed {
-				remote: peer,
-				protocols: self.protocol_name_by_engine.keys().cloned().collect(),
+			// Notify the notification protocols the peer had a slot in as closed, and give the
+			// freed slots to other peers.
+			let protocols = self.notif_slots.iter_mut()
+				.filter_map(|(engine_id, slots)| {
+					if slots.peers.remove(&peer) { Some(*engine_id) } else { None }
+				})
+				.collect::<Vec<_>>();
+			for engine_id in &protocols {
+				for (remote, roles) in self.fill_notif_slots(*engine_id) {
+					self.pending_outcomes.push_back(CustomMessageOutcome::NotificationStreamOpened {
+						remote,
+						protocols: vec![*engine_id],
+						roles,
+					});
+				}
 			}
+			CustomMessageOutcome::NotificationStreamClosed { remote: peer, protocols }
 		} else {
 			CustomMessageOutcome::None
 		}
@@ -923,6 +1131,10 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				return CustomMessageOutcome::None
 			}
 
+			if !response.blocks.is_empty() {
+				self.on_block_received();
+			}
+
 			match self.sync.on_block_data(peer, Some(request), response) {
 				Ok(sync::OnBlockData::Import(origin, blocks)) =>
 					CustomMessageOutcome::BlockImport(origin, blocks),
@@ -1014,16 +1226,19 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				self.behaviour.disconnect_peer(&who);
 				return CustomMessageOutcome::None;
 			}
-			if status.version < MIN_VERSION && CURRENT_VERSION < status.min_supported_version {
-				log!(
-					target: "sync",
-					if self.important_peers.contains(&who) { Level::Warn } else { Level::Trace },
-					"Peer {:?} using unsupported protocol version {}", who, status.version
-				);
-				self.peerset_handle.report_peer(who.clone(), rep::BAD_PROTOCOL);
-				self.behaviour.disconnect_peer(&who);
-				return CustomMessageOutcome::None;
-			}
+			let (negotiated_version, capabilities) = match negotiate(&status) {
+				Some(negotiated) => negotiated,
+				None => {
+					log!(
+						target: "sync",
+						if self.important_peers.contains(&who) { Level::Warn } else { Level::Trace },
+						"Peer {:?} using unsupported protocol version {}", who, status.version
+					);
+					self.peerset_handle.report_peer(who.clone(), rep::BAD_PROTOCOL);
+					self.behaviour.disconnect_peer(&who);
+					return CustomMessageOutcome::None;
+				}
+			};
 
 			if self.config.roles.is_light() {
 				// we're not interested in light peers
@@ -1050,12 +1265,27 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 					self.behaviour.disconnect_peer(&who);
 					return CustomMessageOutcome::None;
 				}
+			} else if status.roles.is_light() {
+				// light peers can't take the slots kept for the full peers
+				if let Some(max_light_peers) = self.config.max_light_peers {
+					let light_peers = self.context_data.peers.values()
+						.filter(|peer| peer.info.roles.is_light())
+						.count();
+					if light_peers >= max_light_peers as usize {
+						debug!(target: "sync", "Too many light peers, disconnecting {}", who);
+						self.peerset_handle.report_peer(who.clone(), rep::TOO_MANY_LIGHT_PEERS);
+						self.behaviour.disconnect_peer(&who);
+						return CustomMessageOutcome::None;
+					}
+				}
 			}
 
 			let info = match self.handshaking_peers.remove(&who) {
 				Some(_handshaking) => {
 					PeerInfo {
 						protocol_version: status.version,
+						negotiated_version,
+						capabilities,
 						roles: status.roles,
 						best_hash: status.best_hash,
 						best_number: status.best_number
@@ -1079,7 +1309,11 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			};
 			self.context_data.peers.insert(who.clone(), peer);
 
-			debug!(target: "sync", "Connected {}", who);
+			debug!(
+				target: "sync",
+				"Connected {} (protocol version {}, capabilities {:?})",
+				who, negotiated_version, capabilities,
+			);
 			status.version
 		};
 
@@ -1099,18 +1333,45 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			}
 		}
 
-		// Notify all the notification protocols as open.
+		// Notify the notification protocols the peer is given a slot in as open.
+		let important_peers = &self.important_peers;
+		let protocols = self.notif_slots.iter_mut()
+			.filter_map(|(engine_id, slots)| {
+				if slots.allocate(&who, important_peers) { Some(*engine_id) } else { None }
+			})
+			.collect();
 		CustomMessageOutcome::NotificationStreamOpened {
 			remote: who,
-			protocols: self.protocol_name_by_engine.keys().cloned().collect(),
+			protocols,
 			roles: info.roles,
 		}
 	}
 
+	/// Returns true if the notifications protocol of `engine_id` is opened with `who`.
+	fn has_notif_slot(&self, who: &PeerId, engine_id: ConsensusEngineId) -> bool {
+		self.notif_slots.get(&engine_id).map_or(false, |slots| slots.peers.contains(who))
+	}
+
+	/// Gives the free slots of the notifications protocol of `engine_id` to the connected peers
+	/// that don't have one. Returns the peers given a slot, and their roles.
+	fn fill_notif_slots(&mut self, engine_id: ConsensusEngineId) -> Vec<(PeerId, Roles)> {
+		let slots = match self.notif_slots.get_mut(&engine_id) {
+			Some(slots) => slots,
+			None => return Vec::new(),
+		};
+		let mut allocated = Vec::new();
+		for (peer_id, peer) in &self.context_data.peers {
+			if !slots.peers.contains(peer_id) && slots.allocate(peer_id, &self.important_peers) {
+				allocated.push((peer_id.clone(), peer.info.roles));
+			}
+		}
+		allocated
+	}
+
 	/// Send a notification to the given peer we're connected to.
 	///
 	/// Doesn't do anything if we don't have a notifications substream for that protocol with that
-	/// peer.
+	/// peer, or if the peer wasn't given a slot in the protocol.
 	pub fn write_notification(
 		&mut self,
 		target: PeerId,
@@ -1118,7 +1379,15 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		message: impl Into<Vec<u8>>
 	) {
 		if let Some(protocol_name) = self.protocol_name_by_engine.get(&engine_id) {
-			self.behaviour.write_notification(&target, engine_id, protocol_name.clone(), message);
+			let has_slot = self.notif_slots.get(&engine_id)
+				.map_or(false, |slots| slots.peers.contains(&target));
+			if has_slot {
+				let protocol_name = protocol_name.clone();
+				self.behaviour.write_notification(&target, engine_id, protocol_name, message);
+			} else {
+				trace!(target: "sub-libp2p", "Dropping {:?} notification to {} without a slot",
+					engine_id, target);
+			}
 		} else {
 			error!(
 				target: "sub-libp2p",
@@ -1130,30 +1399,38 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 
 	/// Registers a new notifications protocol.
 	///
+	/// `handshake` is sent to the remotes when opening the substreams of the protocol. If
+	/// `max_peers` is set, the protocol is only opened with that many of the connected peers, in
+	/// addition to the reserved ones.
+	///
 	/// You are very strongly encouraged to call this method very early on. Any connection open
 	/// will retain the protocols that were registered then, and not any new one.
 	pub fn register_notifications_protocol(
 		&mut self,
 		engine_id: ConsensusEngineId,
 		protocol_name: impl Into<Cow<'static, [u8]>>,
+		handshake: impl Into<Vec<u8>>,
+		max_peers: Option<u32>,
 	) -> Vec<event::Event> {
 		let protocol_name = protocol_name.into();
 		if self.protocol_name_by_engine.insert(engine_id, protocol_name.clone()).is_some() {
 			error!(target: "sub-libp2p", "Notifications protocol already registered: {:?}", protocol_name);
 		} else {
-			self.behaviour.register_notif_protocol(protocol_name.clone(), engine_id, Vec::new());
+			self.behaviour.register_notif_protocol(protocol_name.clone(), engine_id, handshake);
 			self.protocol_engine_by_name.insert(protocol_name, engine_id);
+			self.context_data.traffic.engines.insert(engine_id);
+			self.notif_slots.insert(engine_id, NotifSlots {
+				max_peers: max_peers.map(|max| max as usize),
+				peers: HashSet::new(),
+			});
 		}
 
 		// Registering a protocol while we already have open connections isn't great, but for now
-		// we handle it by notifying that we opened channels with everyone.
-		self.context_data.peers.iter()
-			.map(|(peer_id, peer)|
-				event::Event::NotificationStreamOpened {
-					remote: peer_id.clone(),
-					engine_id,
-					roles: peer.info.roles,
-				})
+		// we handle it by notifying that we opened channels with the peers given a slot.
+		self.fill_notif_slots(engine_id).into_iter()
+			.map(|(remote, roles)| {
+				event::Event::NotificationStreamOpened { remote, engine_id, roles }
+			})
 			.collect()
 	}
 
@@ -1179,6 +1456,21 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		trace!(target: "sync", "Received {} extrinsics from {}", extrinsics.len(), who);
 		if let Some(ref mut peer) = self.context_data.peers.get_mut(&who) {
 			for t in extrinsics {
+				if let Some(max) = self.config.max_extrinsic_size {
+					let size = t.encoded_size();
+					if size > max {
+						debug!(
+							target: "sync",
+							"Discarding extrinsic of {} bytes from {}, above the limit of {} bytes",
+							size,
+							who,
+							max,
+						);
+						self.peerset_handle.report_peer(who.clone(), rep::OVERSIZED_EXTRINSIC);
+						continue;
+					}
+				}
+
 				let hash = self.transaction_pool.hash_of(&t);
 				peer.known_extrinsics.insert(hash);
 
@@ -1236,7 +1528,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				trace!(target: "sync", "Sending {} transactions to {}", to_send.len(), who);
 				send_message::<B> (
 					&mut self.behaviour,
-					&mut self.context_data.stats,
+					&mut self.context_data.traffic,
 					&who,
 					GenericMessage::Transactions(to_send)
 				)
@@ -1294,7 +1586,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			if inserted || force {
 				let message: Message<B> = GenericMessage::BlockAnnounce(message::BlockAnnounce {
 					header: header.clone(),
-					state: if peer.info.protocol_version >= 4  {
+					state: if peer.info.capabilities.contains(Capabilities::ANNOUNCE_STATE) {
 						if is_best {
 							Some(message::BlockState::Best)
 						} else {
@@ -1303,7 +1595,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 					} else  {
 						None
 					},
-					data: if peer.info.protocol_version >= 4 {
+					data: if peer.info.capabilities.contains(Capabilities::ANNOUNCE_STATE) {
 						Some(data.clone())
 					} else {
 						None
@@ -1312,7 +1604,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 
 				send_message::<B> (
 					&mut self.behaviour,
-					&mut self.context_data.stats,
+					&mut self.context_data.traffic,
 					&who,
 					message,
 				)
@@ -1330,7 +1622,8 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			roles: self.config.roles.into(),
 			best_number: info.best_number,
 			best_hash: info.best_hash,
-			chain_status: Vec::new(), // TODO: find a way to make this backwards-compatible
+			// Older peers ignore this field, which lets us use it for the capabilities.
+			chain_status: Capabilities::supported().to_chain_status(),
 		};
 
 		self.send_message(&who, GenericMessage::Status(status))
@@ -1341,6 +1634,11 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		if let Some(ref mut peer) = self.context_data.peers.get_mut(&who) {
 			peer.known_blocks.insert(hash.clone());
 		}
+		// Peers following our fork announce our own blocks back.
+		match self.context_data.chain.block_status(&BlockId::Hash(hash)) {
+			Ok(BlockStatus::InChainWithState) | Ok(BlockStatus::InChainPruned) => {},
+			_ => self.on_block_received(),
+		}
 		self.light_dispatch.update_best_number(LightDispatchIn {
 			behaviour: &mut self.behaviour,
 			peerset: self.peerset_handle.clone(),
@@ -1361,6 +1659,13 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				// 2) parent block is already imported and not pruned.
 				return CustomMessageOutcome::None
 			}
+			sync::OnBlockAnnounce::Invalid { disconnect } => {
+				if disconnect {
+					self.behaviour.disconnect_peer(&who);
+				}
+				self.peerset_handle.report_peer(who, rep::BAD_BLOCK_ANNOUNCEMENT);
+				return CustomMessageOutcome::None
+			}
 			sync::OnBlockAnnounce::ImportHeader => () // We proceed with the import.
 		}
 
@@ -1493,7 +1798,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 					let msg = GenericMessage::BlockRequest(req);
 					send_request(
 						&mut self.behaviour,
-						&mut self.context_data.stats,
+						&mut self.context_data.traffic,
 						&mut self.context_data.peers,
 						&id,
 						msg
@@ -1844,7 +2149,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 
 	fn format_stats(&self) -> String {
 		let mut out = String::new();
-		for (id, stats) in &self.context_data.stats {
+		for (id, stats) in &self.context_data.traffic.stats {
 			let _ = writeln!(
 				&mut out,
 				"{}: In: {} bytes ({}), Out: {} bytes ({})",
@@ -1875,6 +2180,15 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			let n = self.context_data.peers.len().try_into().unwrap_or(std::u64::MAX);
 			metrics.peers.set(n);
 
+			let mut peer_versions = BTreeMap::<u32, u64>::new();
+			for peer in self.context_data.peers.values() {
+				*peer_versions.entry(peer.info.protocol_version).or_default() += 1;
+			}
+			metrics.peer_versions.reset();
+			for (version, n) in peer_versions {
+				metrics.peer_versions.with_label_values(&[&version.to_string()]).set(n);
+			}
+
 			let m = self.sync.metrics();
 
 			metrics.fork_targets.set(m.fork_targets.into());
@@ -1918,7 +2232,7 @@ pub enum CustomMessageOutcome<B: BlockT> {
 
 fn send_request<B: BlockT, H: ExHashT>(
 	behaviour: &mut GenericProto,
-	stats: &mut HashMap<&'static str, PacketStats>,
+	traffic: &mut Traffic,
 	peers: &mut HashMap<PeerId, Peer<B, H>>,
 	who: &PeerId,
 	mut message: Message<B>,
@@ -1934,19 +2248,17 @@ fn send_request<B: BlockT, H: ExHashT>(
 			peer.block_request = Some((Instant::now(), r.clone()));
 		}
 	}
-	send_message::<B>(behaviour, stats, who, message)
+	send_message::<B>(behaviour, traffic, who, message)
 }
 
 fn send_message<B: BlockT>(
 	behaviour: &mut GenericProto,
-	stats: &mut HashMap<&'static str, PacketStats>,
+	traffic: &mut Traffic,
 	who: &PeerId,
 	message: Message<B>,
 ) {
 	let encoded = message.encode();
-	let mut stats = stats.entry(message.id()).or_default();
-	stats.bytes_out += encoded.len() as u64;
-	stats.count_out += 1;
+	traffic.record(&message, encoded.len(), false);
 	behaviour.send_packet(who, encoded);
 }
 
@@ -1999,7 +2311,7 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 		for (id, r) in self.sync.block_requests() {
 			send_request(
 				&mut self.behaviour,
-				&mut self.context_data.stats,
+				&mut self.context_data.traffic,
 				&mut self.context_data.peers,
 				&id,
 				GenericMessage::BlockRequest(r)
@@ -2008,7 +2320,7 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 		for (id, r) in self.sync.justification_requests() {
 			send_request(
 				&mut self.behaviour,
-				&mut self.context_data.stats,
+				&mut self.context_data.traffic,
 				&mut self.context_data.peers,
 				&id,
 				GenericMessage::BlockRequest(r)
@@ -2017,12 +2329,16 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 		for (id, r) in self.sync.finality_proof_requests() {
 			send_request(
 				&mut self.behaviour,
-				&mut self.context_data.stats,
+				&mut self.context_data.traffic,
 				&mut self.context_data.peers,
 				&id,
 				GenericMessage::FinalityProofRequest(r))
 		}
 
+		if let Some(outcome) = self.pending_outcomes.pop_front() {
+			return Poll::Ready(NetworkBehaviourAction::GenerateEvent(outcome));
+		}
+
 		let event = match self.behaviour.poll(cx, params) {
 			Poll::Pending => return Poll::Pending,
 			Poll::Ready(NetworkBehaviourAction::GenerateEvent(ev)) => ev,
@@ -2114,15 +2430,48 @@ impl<B: BlockT, H: ExHashT> Drop for Protocol<B, H> {
 	}
 }
 
+/// Negotiate the protocol version and the capabilities to use with a peer, given its status.
+///
+/// Returns `None` if there is no protocol version supported by both sides.
+fn negotiate<Hash, Number>(
+	status: &message::generic::Status<Hash, Number>,
+) -> Option<(u32, Capabilities)> {
+	let version = cmp::min(CURRENT_VERSION, status.version);
+	if version < cmp::max(MIN_VERSION, status.min_supported_version) {
+		return None;
+	}
+
+	let capabilities = if status.version >= CAPABILITIES_VERSION {
+		Capabilities::from_chain_status(&status.chain_status)
+	} else {
+		implied_capabilities(status.version)
+	};
+
+	Some((version, Capabilities::supported() & capabilities))
+}
+
+/// Capabilities of the peers predating the capabilities exchange, deduced from their version.
+fn implied_capabilities(version: u32) -> Capabilities {
+	let mut capabilities = Capabilities::empty();
+	if version >= 4 {
+		capabilities |= Capabilities::ANNOUNCE_STATE;
+	}
+	capabilities
+}
+
 #[cfg(test)]
 mod tests {
 	use crate::PeerId;
 	use crate::protocol::light_dispatch::AlwaysBadChecker;
 	use crate::config::{EmptyTransactionPool, Roles};
-	use super::{CustomMessageOutcome, Protocol, ProtocolConfig};
+	use crate::protocol::message::{Capabilities, generic::Status};
+	use super::{
+		CustomMessageOutcome, NotifSlots, Protocol, ProtocolConfig, CURRENT_VERSION, negotiate,
+	};
 
+	use futures::{future::poll_fn, StreamExt};
 	use sp_consensus::block_validation::DefaultBlockAnnounceValidator;
-	use std::sync::Arc;
+	use std::{collections::HashSet, sync::Arc, task::Poll};
 	use substrate_test_runtime_client::{TestClientBuilder, TestClientBuilderExt};
 	use substrate_test_runtime_client::runtime::{Block, Hash};
 
@@ -2134,6 +2483,8 @@ mod tests {
 			ProtocolConfig {
 				roles: Roles::FULL,
 				max_parallel_downloads: 10,
+				max_extrinsic_size: None,
+				max_light_peers: None,
 			},
 			client.clone(),
 			Arc::new(AlwaysBadChecker),
@@ -2159,4 +2510,118 @@ mod tests {
 			_ => panic!()
 		};
 	}
+
+	#[test]
+	fn notif_slots_are_limited() {
+		let reserved = PeerId::random();
+		let important_peers = vec![reserved.clone()].into_iter().collect::<HashSet<_>>();
+		let mut slots = NotifSlots { max_peers: Some(2), peers: HashSet::new() };
+
+		let (first, second, third) = (PeerId::random(), PeerId::random(), PeerId::random());
+		assert!(slots.allocate(&first, &important_peers));
+		assert!(slots.allocate(&second, &important_peers));
+		assert!(!slots.allocate(&third, &important_peers));
+		// Reserved peers always get a slot, without taking the one of another peer.
+		assert!(slots.allocate(&reserved, &important_peers));
+		assert!(slots.allocate(&first, &important_peers));
+
+		slots.peers.remove(&second);
+		assert!(slots.allocate(&third, &important_peers));
+		assert_eq!(slots.peers.len(), 3);
+	}
+
+	fn status(version: u32, min_supported_version: u32, chain_status: Vec<u8>) -> Status<Hash, u64> {
+		Status {
+			version,
+			min_supported_version,
+			roles: Roles::FULL,
+			best_number: 0,
+			best_hash: Default::default(),
+			genesis_hash: Default::default(),
+			chain_status,
+		}
+	}
+
+	#[test]
+	fn negotiates_versions_and_capabilities() {
+		// Older peers don't send their capabilities, they are deduced from the version.
+		assert_eq!(negotiate(&status(3, 3, Vec::new())), Some((3, Capabilities::empty())));
+		assert_eq!(negotiate(&status(6, 3, Vec::new())), Some((6, Capabilities::ANNOUNCE_STATE)));
+
+		// Newer peers only get the capabilities both sides support.
+		let theirs = (Capabilities::ANNOUNCE_STATE.bits() | 1 << 31).to_le_bytes().to_vec();
+		assert_eq!(
+			negotiate(&status(CURRENT_VERSION + 1, 3, theirs)),
+			Some((CURRENT_VERSION, Capabilities::ANNOUNCE_STATE)),
+		);
+		assert_eq!(
+			negotiate(&status(CURRENT_VERSION, 3, Vec::new())),
+			Some((CURRENT_VERSION, Capabilities::empty())),
+		);
+
+		// No common version.
+		assert_eq!(negotiate(&status(2, 2, Vec::new())), None);
+		assert_eq!(negotiate(&status(CURRENT_VERSION + 2, CURRENT_VERSION + 1, Vec::new())), None);
+	}
+
+	#[test]
+	fn light_peers_over_the_limit_are_disconnected_and_reported() {
+		let client = Arc::new(TestClientBuilder::with_default_backend().build_with_longest_chain().0);
+		let peerset_config = || sc_peerset::PeersetConfig {
+			in_peers: 10,
+			out_peers: 10,
+			bootnodes: Vec::new(),
+			reserved_only: false,
+			reserved_nodes: Vec::new(),
+		};
+
+		let (mut protocol, _) = Protocol::<Block, Hash>::new(
+			ProtocolConfig {
+				roles: Roles::FULL,
+				max_parallel_downloads: 10,
+				max_extrinsic_size: None,
+				max_light_peers: Some(1),
+			},
+			client.clone(),
+			Arc::new(AlwaysBadChecker),
+			Arc::new(EmptyTransactionPool),
+			None,
+			None,
+			From::from(&b"test"[..]),
+			peerset_config(),
+			Box::new(DefaultBlockAnnounceValidator::new(client.clone())),
+			None
+		).unwrap();
+		// Observe the reports of the protocol.
+		let (mut peerset, peerset_handle) = sc_peerset::Peerset::from_config(peerset_config());
+		protocol.peerset_handle = peerset_handle;
+
+		let genesis_hash = client.chain_info().genesis_hash;
+		let (first, second, full) = (PeerId::random(), PeerId::random(), PeerId::random());
+		for (peer, roles) in vec![
+			(first.clone(), Roles::LIGHT),
+			(second.clone(), Roles::LIGHT),
+			(full.clone(), Roles::FULL),
+		] {
+			protocol.on_peer_connected(peer.clone());
+			protocol.on_status_message(peer, Status {
+				roles,
+				genesis_hash,
+				..status(CURRENT_VERSION, 3, Vec::new())
+			});
+		}
+
+		// The full peer doesn't take the slot of the light peers.
+		assert!(protocol.context_data.peers.contains_key(&first));
+		assert!(!protocol.context_data.peers.contains_key(&second));
+		assert!(protocol.context_data.peers.contains_key(&full));
+
+		futures::executor::block_on(poll_fn(|cx| {
+			while let Poll::Ready(Some(_)) = peerset.poll_next_unpin(cx) {}
+			Poll::Ready(())
+		}));
+		let reputations = peerset.reputations();
+		assert!(reputations.iter().any(|(peer, reputation)| *peer == second && *reputation < 0));
+		assert!(!reputations.iter().any(|(peer, _)| *peer == first || *peer == full));
+	}
 }
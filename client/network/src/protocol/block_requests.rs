@@ -344,7 +344,7 @@ where
 		let future = async move {
 			let len = self.max_request_len;
 			let vec = read_one(&mut s, len).await?;
-			match api::v1::BlockRequest::decode(&vec[..]) {
+			match crate::decode::block_request(&vec[..], len) {
 				Ok(r) => Ok(Request(r, s)),
 				Err(e) => Err(ReadOneError::Io(io::Error::new(io::ErrorKind::Other, e)))
 			}
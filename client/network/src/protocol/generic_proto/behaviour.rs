@@ -463,6 +463,11 @@ impl GenericProto {
 		self.peerset.debug_info()
 	}
 
+	/// Returns the peers known by the peerset manager, with their reputation.
+	pub fn peers_reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.peerset.reputations()
+	}
+
 	/// Function that is called when the peerset wants us to connect to a node.
 	fn peerset_report_connect(&mut self, peer_id: PeerId) {
 		let mut occ_entry = match self.peers.entry(peer_id) {
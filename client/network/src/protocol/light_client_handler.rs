@@ -1031,7 +1031,7 @@ where
     fn upgrade_inbound(self, mut s: T, _: Self::Info) -> Self::Future {
 		let future = async move {
 			let vec = read_one(&mut s, self.max_data_size).await?;
-			match api::v1::light::Request::decode(&vec[..]) {
+			match crate::decode::light_request(&vec[..], self.max_data_size) {
 				Ok(r) => Ok(Event::Request(r, s)),
 				Err(e) => Err(ReadOneError::Io(io::Error::new(io::ErrorKind::Other, e)))
 			}
@@ -99,6 +99,38 @@ impl Decode for BlockAttributes {
 	}
 }
 
+bitflags! {
+	/// Optional protocol features supported by a node.
+	///
+	/// The capabilities are sent in the `chain_status` field of the status message, which older
+	/// nodes leave empty. A feature is only used with a peer if both sides support it.
+	pub struct Capabilities: u32 {
+		/// Block announcements carry the state of the announced block.
+		const ANNOUNCE_STATE = 0b00000001;
+	}
+}
+
+impl Capabilities {
+	/// Capabilities supported by this node.
+	pub fn supported() -> Self {
+		Capabilities::ANNOUNCE_STATE
+	}
+
+	/// Decode the capabilities found in the `chain_status` of a status message.
+	///
+	/// Unknown bits are ignored, so that newer peers can advertise features we don't know about.
+	pub fn from_chain_status(chain_status: &[u8]) -> Self {
+		u32::decode(&mut &chain_status[..])
+			.map(Capabilities::from_bits_truncate)
+			.unwrap_or_else(|_| Capabilities::empty())
+	}
+
+	/// Encode the capabilities in the `chain_status` of a status message.
+	pub fn to_chain_status(&self) -> Vec<u8> {
+		self.bits().encode()
+	}
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
 /// Block enumeration direction.
 pub enum Direction {
@@ -45,9 +45,12 @@ use log::{debug, trace, warn, info, error};
 use sp_runtime::{
 	Justification,
 	generic::BlockId,
-	traits::{Block as BlockT, Header, NumberFor, Zero, One, CheckedSub, SaturatedConversion}
+	traits::{
+		Block as BlockT, Header, NumberFor, Zero, One, CheckedSub, SaturatedConversion, Saturating,
+	}
 };
-use std::{fmt, ops::Range, collections::{HashMap, HashSet, VecDeque}, sync::Arc};
+use std::{fmt, ops::Range, collections::{HashMap, HashSet, VecDeque}, sync::Arc, time::Duration};
+use wasm_timer::Instant;
 
 mod blocks;
 mod extra_requests;
@@ -55,6 +58,9 @@ mod extra_requests;
 /// Maximum blocks to request in a single packet.
 const MAX_BLOCKS_TO_REQUEST: usize = 128;
 
+/// Minimum period over which the import rate of the blocks is sampled.
+const IMPORT_RATE_SAMPLE_PERIOD: Duration = Duration::from_secs(5);
+
 /// Maximum blocks to store in the import queue.
 const MAX_IMPORTING_BLOCKS: usize = 2048;
 
@@ -71,6 +77,13 @@ const MAJOR_SYNC_BLOCKS: u8 = 5;
 /// Number of recently announced blocks to track for each peer.
 const ANNOUNCE_HISTORY_SIZE: usize = 64;
 
+/// Weight of the last measure in the smoothed round-trip time and throughput of the peers.
+const PERFORMANCE_SMOOTHING: f64 = 0.25;
+
+/// During a major sync, no new blocks are requested from a peer that is this many times slower
+/// than a peer with at least as many blocks.
+const SLOW_PEER_FACTOR: u32 = 4;
+
 mod rep {
 	use sc_peerset::ReputationChange as Rep;
 	/// Reputation change when a peer sent us a message that led to a
@@ -140,6 +153,12 @@ pub struct ChainSync<B: BlockT> {
 	max_parallel_downloads: u32,
 	/// Total number of processed blocks (imported or failed).
 	processed_blocks: usize,
+	/// The best block number when the sync started.
+	starting_block: NumberFor<B>,
+	/// Start of the current import rate sample, and the best imported number at that time.
+	import_rate_sample: (Instant, NumberFor<B>),
+	/// Smoothed number of blocks imported per second, once a sample is complete.
+	import_rate: Option<f64>,
 }
 
 /// All the data we have about a Peer that we are trying to sync with
@@ -157,7 +176,44 @@ pub struct PeerSync<B: BlockT> {
 	pub state: PeerSyncState<B>,
 	/// A queue of blocks that this peer has announced to us, should only
 	/// contain `ANNOUNCE_HISTORY_SIZE` entries.
-	pub recently_announced: VecDeque<B::Hash>
+	pub recently_announced: VecDeque<B::Hash>,
+	/// How fast the peer answers our requests.
+	pub performance: PeerPerformance,
+}
+
+/// Round-trip time and throughput measured on the block requests made to a peer.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PeerPerformance {
+	/// Smoothed time between a request and its response. `None` until a response is received.
+	pub round_trip: Option<Duration>,
+	/// Smoothed number of blocks received per second, over the responses with several blocks.
+	pub blocks_per_sec: Option<f64>,
+}
+
+impl PeerPerformance {
+	/// Takes the response to a request into account.
+	fn on_response(&mut self, elapsed: Duration, num_blocks: usize) {
+		let smooth = |previous: Option<f64>, measure: f64| match previous {
+			Some(previous) => previous + PERFORMANCE_SMOOTHING * (measure - previous),
+			None => measure,
+		};
+		let round_trip = smooth(self.round_trip.map(|d| d.as_secs_f64()), elapsed.as_secs_f64());
+		self.round_trip = Some(Duration::from_secs_f64(round_trip));
+		// The responses with a single block, such as the ancestry ones, only measure the latency.
+		if num_blocks > 1 && elapsed > Duration::from_secs(0) {
+			let rate = num_blocks as f64 / elapsed.as_secs_f64();
+			self.blocks_per_sec = Some(smooth(self.blocks_per_sec, rate));
+		}
+	}
+
+	/// Expected time to download `MAX_BLOCKS_TO_REQUEST` blocks from the peer, if it was measured.
+	fn expected_request_time(&self) -> Option<Duration> {
+		match (self.blocks_per_sec, self.round_trip) {
+			(Some(rate), _) if rate > 0.0 =>
+				Some(Duration::from_secs_f64(MAX_BLOCKS_TO_REQUEST as f64 / rate)),
+			(_, round_trip) => round_trip,
+		}
+	}
 }
 
 /// The sync status of a peer we are trying to sync with
@@ -227,6 +283,10 @@ pub struct Status<B: BlockT> {
 	pub num_peers: u32,
 	/// Number of blocks queued for import
 	pub queued_blocks: u32,
+	/// The best block number when the sync started.
+	pub starting_block: NumberFor<B>,
+	/// Estimated time left to reach `best_seen_block`, while downloading.
+	pub eta: Option<Duration>,
 }
 
 /// A peer did not behave as expected and should be reported.
@@ -257,6 +317,11 @@ pub enum OnBlockAnnounce {
 	Nothing,
 	/// The announcement header should be imported.
 	ImportHeader,
+	/// The announcement was refused by the block announce validator. The peer should be
+	/// penalized, and disconnected from if `disconnect`.
+	Invalid {
+		disconnect: bool,
+	},
 }
 
 /// Result of [`ChainSync::on_block_justification`].
@@ -321,6 +386,9 @@ impl<B: BlockT> ChainSync<B> {
 			block_announce_validator,
 			max_parallel_downloads,
 			processed_blocks: 0,
+			starting_block: info.best_number,
+			import_rate_sample: (Instant::now(), info.best_number),
+			import_rate: None,
 		}
 	}
 
@@ -347,12 +415,39 @@ impl<B: BlockT> ChainSync<B> {
 				SyncState::Idle
 			};
 
+		let eta = match (&sync_state, best_seen, self.import_rate) {
+			(SyncState::Downloading, Some(best_seen), Some(rate)) if rate > 0.0 => {
+				let remaining = best_seen.saturating_sub(self.best_imported_number);
+				Some(Duration::from_secs_f64(remaining.saturated_into::<u64>() as f64 / rate))
+			},
+			_ => None,
+		};
+
 		Status {
 			state: sync_state,
 			best_seen_block: best_seen,
 			num_peers: self.peers.len() as u32,
 			queued_blocks: self.queue_blocks.len() as u32,
+			starting_block: self.starting_block,
+			eta,
+		}
+	}
+
+	/// Updates the import rate once the current sample spans `IMPORT_RATE_SAMPLE_PERIOD`.
+	fn update_import_rate(&mut self) {
+		let (sample_start, sample_number) = self.import_rate_sample;
+		let elapsed = sample_start.elapsed();
+		if elapsed < IMPORT_RATE_SAMPLE_PERIOD {
+			return;
 		}
+
+		let imported = self.best_imported_number.saturating_sub(sample_number);
+		let rate = imported.saturated_into::<u64>() as f64 / elapsed.as_secs_f64();
+		self.import_rate = Some(match self.import_rate {
+			Some(previous) => (previous + rate) / 2.0,
+			None => rate,
+		});
+		self.import_rate_sample = (Instant::now(), self.best_imported_number);
 	}
 
 	/// Number of active sync requests.
@@ -365,6 +460,41 @@ impl<B: BlockT> ChainSync<B> {
 		self.processed_blocks
 	}
 
+	/// Call this method when `who` answers a block request after `elapsed`, with `num_blocks`
+	/// blocks, so that the fastest peers are preferred for the next requests.
+	pub fn on_request_completed(&mut self, who: &PeerId, elapsed: Duration, num_blocks: usize) {
+		if let Some(peer) = self.peers.get_mut(who) {
+			peer.performance.on_response(elapsed, num_blocks);
+			trace!(target: "sync", "Peer {} performance: {:?}", who, peer.performance);
+		}
+	}
+
+	/// Returns the order in which the peers are given block requests: the ones never measured
+	/// first, so that they are measured, then the fastest ones.
+	///
+	/// During a major sync, the peers much slower than a peer with at least as many blocks are
+	/// flagged, and aren't given new blocks to download, as the import would wait for them.
+	fn peers_by_performance(&self, major_sync: bool) -> Vec<(PeerId, bool)> {
+		let is_slow = |id: &PeerId, peer: &PeerSync<B>| {
+			let expected = match peer.performance.expected_request_time() {
+				Some(expected) if major_sync => expected,
+				_ => return false,
+			};
+			self.peers.iter().any(|(other_id, other)| {
+				other_id != id
+					&& other.best_number >= peer.best_number
+					&& other.performance.expected_request_time()
+						.map_or(false, |other| other * SLOW_PEER_FACTOR < expected)
+			})
+		};
+		let mut peers = self.peers.iter()
+			.map(|(id, peer)| (id, peer.performance.expected_request_time(), is_slow(id, peer)))
+			.collect::<Vec<_>>();
+		// `None` is ordered before `Some`.
+		peers.sort_by_key(|(_, expected, _)| *expected);
+		peers.into_iter().map(|(id, _, slow)| (id.clone(), slow)).collect()
+	}
+
 	/// Handle a new connected peer.
 	///
 	/// Call this method whenever we connect to a new peer.
@@ -401,7 +531,8 @@ impl<B: BlockT> ChainSync<B> {
 						best_hash,
 						best_number,
 						state: PeerSyncState::Available,
-						recently_announced: Default::default()
+						recently_announced: Default::default(),
+						performance: Default::default(),
 					});
 					return Ok(None)
 				}
@@ -415,6 +546,7 @@ impl<B: BlockT> ChainSync<B> {
 						best_number,
 						state: PeerSyncState::Available,
 						recently_announced: Default::default(),
+						performance: Default::default(),
 					});
 					self.is_idle = false;
 					return Ok(None)
@@ -436,7 +568,8 @@ impl<B: BlockT> ChainSync<B> {
 						common_best,
 						AncestorSearchState::ExponentialBackoff(One::one())
 					),
-					recently_announced: Default::default()
+					recently_announced: Default::default(),
+					performance: Default::default(),
 				});
 				self.is_idle = false;
 
@@ -450,6 +583,7 @@ impl<B: BlockT> ChainSync<B> {
 					best_number,
 					state: PeerSyncState::Available,
 					recently_announced: Default::default(),
+					performance: Default::default(),
 				});
 				self.is_idle = false;
 				Ok(None)
@@ -587,6 +721,8 @@ impl<B: BlockT> ChainSync<B> {
 			return Either::Left(std::iter::empty())
 		}
 		let major_sync = self.status().state == SyncState::Downloading;
+		let order = self.peers_by_performance(major_sync);
+		let peers = &mut self.peers;
 		let blocks = &mut self.blocks;
 		let attrs = &self.required_block_attributes;
 		let fork_targets = &mut self.fork_targets;
@@ -596,20 +732,28 @@ impl<B: BlockT> ChainSync<B> {
 		let client = &self.client;
 		let queue = &self.queue_blocks;
 		let max_parallel = if major_sync { 1 } else { self.max_parallel_downloads };
-		let iter = self.peers.iter_mut().filter_map(move |(id, peer)| {
+		let iter = order.into_iter().filter_map(move |(id, slow)| {
+			let peer = peers.get_mut(&id)?;
+			let id = &id;
 			if !peer.state.is_available() {
 				trace!(target: "sync", "Peer {} is busy", id);
 				return None
 			}
-			if let Some((range, req)) = peer_block_request(
-				id,
-				peer,
-				blocks,
-				attrs,
-				max_parallel,
-				last_finalized,
-				best_queued,
-			) {
+			let block_request = if slow {
+				trace!(target: "sync", "Peer {} is too slow for the major sync", id);
+				None
+			} else {
+				peer_block_request(
+					id,
+					peer,
+					blocks,
+					attrs,
+					max_parallel,
+					last_finalized,
+					best_queued,
+				)
+			};
+			if let Some((range, req)) = block_request {
 				peer.state = PeerSyncState::DownloadingNew(range.start);
 				trace!(
 					target: "sync",
@@ -992,6 +1136,7 @@ impl<B: BlockT> ChainSync<B> {
 			};
 		}
 
+		self.update_import_rate();
 		self.is_idle = false;
 		output.into_iter()
 	}
@@ -1130,9 +1275,9 @@ impl<B: BlockT> ChainSync<B> {
 		let assoc_data = announce.data.as_ref().map_or(&[][..], |v| v.as_slice());
 		match self.block_announce_validator.validate(&header, assoc_data) {
 			Ok(Validation::Success) => (),
-			Ok(Validation::Failure) => {
+			Ok(Validation::Failure { disconnect }) => {
 				debug!(target: "sync", "Block announcement validation of block {} from {} failed", hash, who);
-				return OnBlockAnnounce::Nothing
+				return OnBlockAnnounce::Invalid { disconnect }
 			}
 			Err(e) => {
 				error!(target: "sync", "Block announcement validation errored: {}", e);
@@ -1499,4 +1644,80 @@ mod test {
 			})
 		);
 	}
+
+	/// Accepts the announcements whose data is `valid`.
+	struct DataValidator;
+
+	impl BlockAnnounceValidator<Block> for DataValidator {
+		fn validate(
+			&mut self,
+			_: &<Block as BlockT>::Header,
+			data: &[u8],
+		) -> Result<Validation, Box<dyn std::error::Error + Send>> {
+			if data == b"valid" {
+				Ok(Validation::Success)
+			} else {
+				Ok(Validation::Failure { disconnect: true })
+			}
+		}
+	}
+
+	#[test]
+	fn invalid_block_announcements_are_refused() {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let info = client.info();
+		let peer_id = PeerId::random();
+		let mut sync = ChainSync::new(
+			Roles::FULL,
+			client.clone(),
+			&info,
+			None,
+			Box::new(DataValidator),
+			1,
+		);
+		sync.new_peer(peer_id.clone(), info.genesis_hash, 0).unwrap();
+
+		let header = client.new_block(Default::default()).unwrap().build().unwrap().block.header;
+		let announce = |data: &[u8]| BlockAnnounce {
+			header: header.clone(),
+			state: None,
+			data: Some(data.to_vec()),
+		};
+		assert_eq!(
+			sync.on_block_announce(peer_id.clone(), header.hash(), &announce(b"invalid"), true),
+			OnBlockAnnounce::Invalid { disconnect: true },
+		);
+		assert_eq!(
+			sync.on_block_announce(peer_id, header.hash(), &announce(b"valid"), true),
+			OnBlockAnnounce::Nothing,
+		);
+	}
+
+	#[test]
+	fn slow_peers_are_left_out_of_major_sync() {
+		use sp_core::H256;
+
+		let client = Arc::new(TestClientBuilder::new().build());
+		let info = client.info();
+		let mut sync = ChainSync::new(
+			Roles::FULL,
+			client.clone(),
+			&info,
+			None,
+			Box::new(DefaultBlockAnnounceValidator::new(client.clone())),
+			1,
+		);
+		let (fast, slow, unknown) = (PeerId::random(), PeerId::random(), PeerId::random());
+		for peer_id in vec![&fast, &slow] {
+			sync.new_peer(peer_id.clone(), H256::random(), 1000).unwrap();
+		}
+		sync.on_request_completed(&fast, Duration::from_millis(100), MAX_BLOCKS_TO_REQUEST);
+		sync.on_request_completed(&slow, Duration::from_secs(2), MAX_BLOCKS_TO_REQUEST);
+		assert_eq!(sync.status().state, SyncState::Downloading);
+
+		// the peers never measured are given a request first.
+		sync.new_peer(unknown.clone(), H256::random(), 1000).unwrap();
+		let requested = sync.block_requests().map(|(peer_id, _)| peer_id).collect::<Vec<_>>();
+		assert_eq!(requested, vec![unknown, fast]);
+	}
 }
@@ -0,0 +1,551 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Request/response protocols registered by the users of the network.
+//!
+//! Every request is sent on a separate substream of its protocol, which gets closed after the
+//! response is read back. The requests and responses are opaque bytes, encoded the way each
+//! protocol wants, and limited in size by its configuration.
+//!
+//! The requests received from the peers are sent on the inbound queue of their protocol, along
+//! with a channel to send the response back on. A request is refused when the queue is full, or
+//! when its response isn't sent within the timeout of the protocol.
+
+use bytes::Bytes;
+use futures::{channel::{mpsc, oneshot}, future::BoxFuture, prelude::*, stream::FuturesUnordered};
+use futures_timer::Delay;
+use libp2p::{
+	core::{
+		ConnectedPoint,
+		Multiaddr,
+		PeerId,
+		upgrade::{InboundUpgrade, OutboundUpgrade, ReadOneError, UpgradeInfo, read_one, write_one},
+	},
+	swarm::{
+		NegotiatedSubstream,
+		NetworkBehaviour,
+		NetworkBehaviourAction,
+		OneShotHandler,
+		PollParameters,
+		SubstreamProtocol,
+	},
+};
+use log::debug;
+use std::{
+	borrow::Cow,
+	collections::{HashMap, HashSet, VecDeque},
+	io,
+	pin::Pin,
+	task::{Context, Poll},
+	time::Duration,
+};
+use void::Void;
+use wasm_timer::Instant;
+
+use crate::utils::interval;
+
+/// Interval at which the outgoing requests are checked for their timeout.
+const TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time after which an idle connection is closed by the handler.
+const INACTIVITY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Configuration of a request/response protocol.
+#[derive(Debug, Clone)]
+pub struct RequestResponseConfig {
+	/// Name of the protocol, negotiated on the substreams, e.g. `/my-chain/collation/1`.
+	pub name: Cow<'static, str>,
+	/// Maximal size of a request, in bytes. Larger ones are refused.
+	pub max_request_size: usize,
+	/// Maximal size of a response, in bytes. Larger ones are refused.
+	pub max_response_size: usize,
+	/// Time after which a request fails if its response hasn't arrived. It's also the time
+	/// within which the requests received from the peers must be answered.
+	pub request_timeout: Duration,
+	/// Queue on which the requests of the peers are sent. If `None`, the protocol isn't
+	/// advertised and the requests of the peers are refused, the node only sends requests.
+	pub inbound_queue: Option<mpsc::Sender<IncomingRequest>>,
+}
+
+impl RequestResponseConfig {
+	/// Creates the configuration of a protocol that only sends requests, with the following
+	/// options:
+	///
+	/// - max. request size = 1 MiB
+	/// - max. response size = 16 MiB
+	/// - request timeout = 20s
+	pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+		RequestResponseConfig {
+			name: name.into(),
+			max_request_size: 1024 * 1024,
+			max_response_size: 16 * 1024 * 1024,
+			request_timeout: Duration::from_secs(20),
+			inbound_queue: None,
+		}
+	}
+
+	/// Makes the protocol answer the requests of the peers. Returns the receiver of the requests,
+	/// of which at most `queue_size` are queued, the others being refused.
+	pub fn inbound_queue(&mut self, queue_size: usize) -> mpsc::Receiver<IncomingRequest> {
+		let (tx, rx) = mpsc::channel(queue_size);
+		self.inbound_queue = Some(tx);
+		rx
+	}
+}
+
+/// A request received from a peer.
+#[derive(Debug)]
+pub struct IncomingRequest {
+	/// Peer which sent the request.
+	pub peer: PeerId,
+	/// The request, as sent by the peer.
+	pub payload: Vec<u8>,
+	/// Channel to send the response on. Dropping it refuses the request.
+	pub pending_response: oneshot::Sender<Vec<u8>>,
+}
+
+/// Reason why a request failed.
+#[derive(Debug, PartialEq, derive_more::Display)]
+pub enum RequestFailure {
+	/// No protocol with this name is registered.
+	#[display(fmt = "Unknown request/response protocol")]
+	UnknownProtocol,
+	/// The request is larger than the maximal request size of the protocol.
+	#[display(fmt = "Request too large")]
+	TooLarge,
+	/// The node isn't connected to the peer.
+	#[display(fmt = "Not connected to the peer")]
+	NotConnected,
+	/// The peer refused the request, or the connection was closed before the response.
+	#[display(fmt = "Request refused")]
+	Refused,
+	/// The response didn't arrive within the timeout of the protocol.
+	#[display(fmt = "Request timeout")]
+	Timeout,
+	/// The network was shut down before the response.
+	#[display(fmt = "Network shut down")]
+	Obsolete,
+}
+
+impl std::error::Error for RequestFailure {}
+
+/// A request sent to a peer, awaiting its response.
+struct PendingRequest {
+	peer: PeerId,
+	/// When the request fails if its response hasn't arrived.
+	deadline: Instant,
+	response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+}
+
+/// Behaviour sending and answering the requests of the registered protocols.
+pub struct RequestResponses {
+	protocols: Vec<RequestResponseConfig>,
+	/// Peers the node is connected to.
+	connected: HashSet<PeerId>,
+	/// Identifier of the next request.
+	next_request_id: u64,
+	/// Requests sent to the peers, by identifier.
+	pending_requests: HashMap<u64, PendingRequest>,
+	/// Requests to send to the peers on the next poll.
+	requests_to_send: VecDeque<(PeerId, OutboundProtocol)>,
+	/// Futures answering the requests of the peers.
+	pending_responses: FuturesUnordered<BoxFuture<'static, ()>>,
+	/// Interval at which the pending requests are checked for their timeout.
+	timeout_check: Pin<Box<dyn Stream<Item = ()> + Send>>,
+}
+
+impl RequestResponses {
+	/// Creates the behaviour for the given protocols.
+	pub fn new(protocols: Vec<RequestResponseConfig>) -> Self {
+		RequestResponses {
+			protocols,
+			connected: HashSet::new(),
+			next_request_id: 0,
+			pending_requests: HashMap::new(),
+			requests_to_send: VecDeque::new(),
+			pending_responses: FuturesUnordered::new(),
+			timeout_check: Box::pin(interval(TIMEOUT_CHECK_INTERVAL)),
+		}
+	}
+
+	/// Sends a request to `target` on `protocol`. The response, or the reason of the failure, is
+	/// sent on `response`.
+	pub fn send_request(
+		&mut self,
+		target: &PeerId,
+		protocol: &str,
+		request: Vec<u8>,
+		response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+	) {
+		let config = match self.protocols.iter().find(|config| config.name == protocol) {
+			Some(config) => config,
+			None => {
+				let _ = response.send(Err(RequestFailure::UnknownProtocol));
+				return
+			}
+		};
+		if request.len() > config.max_request_size {
+			let _ = response.send(Err(RequestFailure::TooLarge));
+			return
+		}
+		if !self.connected.contains(target) {
+			let _ = response.send(Err(RequestFailure::NotConnected));
+			return
+		}
+
+		let request_id = self.next_request_id;
+		self.next_request_id += 1;
+		log::trace!(target: "sub-libp2p", "Request {} on {} to {}", request_id, protocol, target);
+		self.requests_to_send.push_back((target.clone(), OutboundProtocol {
+			protocol: Bytes::from(protocol.as_bytes().to_vec()),
+			request,
+			request_id,
+			max_response_size: config.max_response_size,
+		}));
+		self.pending_requests.insert(request_id, PendingRequest {
+			peer: target.clone(),
+			deadline: Instant::now() + config.request_timeout,
+			response,
+		});
+	}
+
+	/// Passes a request received from `peer` to the inbound queue of its protocol, and sends
+	/// the response back on `substream` once it's there.
+	fn on_request(
+		&mut self,
+		peer: PeerId,
+		protocol: &[u8],
+		payload: Vec<u8>,
+		mut substream: NegotiatedSubstream,
+	) {
+		let config = match self.protocols.iter_mut().find(|c| c.name.as_bytes() == protocol) {
+			Some(config) => config,
+			None => return,
+		};
+		let queue = match config.inbound_queue.as_mut() {
+			Some(queue) => queue,
+			None => return,
+		};
+
+		let (tx, rx) = oneshot::channel();
+		let request = IncomingRequest { peer: peer.clone(), payload, pending_response: tx };
+		if let Err(err) = queue.try_send(request) {
+			debug!(
+				target: "sub-libp2p",
+				"Refused request on {} from {}: {}",
+				config.name,
+				peer,
+				if err.is_full() { "queue full" } else { "queue closed" },
+			);
+			return
+		}
+
+		let timeout = Delay::new(config.request_timeout);
+		let future = async move {
+			match future::select(rx, timeout).await {
+				future::Either::Left((Ok(response), _)) => {
+					if let Err(err) = write_one(&mut substream, response).await {
+						debug!(target: "sub-libp2p", "Error writing response to {}: {}", peer, err)
+					}
+				},
+				future::Either::Left((Err(_), _)) =>
+					debug!(target: "sub-libp2p", "Request from {} refused", peer),
+				future::Either::Right(_) =>
+					debug!(target: "sub-libp2p", "Request from {} not answered in time", peer),
+			}
+		};
+		self.pending_responses.push(future.boxed());
+	}
+
+	/// Fails the pending requests sent to `peer`.
+	fn fail_requests_to(&mut self, peer: &PeerId) {
+		let failed = self.pending_requests.iter()
+			.filter(|(_, request)| request.peer == *peer)
+			.map(|(id, _)| *id)
+			.collect::<Vec<_>>();
+		for id in failed {
+			if let Some(request) = self.pending_requests.remove(&id) {
+				let _ = request.response.send(Err(RequestFailure::Refused));
+			}
+		}
+	}
+}
+
+impl NetworkBehaviour for RequestResponses {
+	type ProtocolsHandler =
+		OneShotHandler<InboundProtocol, OutboundProtocol, Event<NegotiatedSubstream>>;
+	type OutEvent = Void;
+
+	fn new_handler(&mut self) -> Self::ProtocolsHandler {
+		let protocols = self.protocols.iter()
+			.filter(|config| config.inbound_queue.is_some())
+			.map(|config| (Bytes::from(config.name.as_bytes().to_vec()), config.max_request_size))
+			.collect();
+		let protocol = SubstreamProtocol::new(InboundProtocol { protocols });
+		OneShotHandler::new(protocol, INACTIVITY_TIMEOUT)
+	}
+
+	fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+		Vec::new()
+	}
+
+	fn inject_connected(&mut self, peer: PeerId, _: ConnectedPoint) {
+		self.connected.insert(peer);
+	}
+
+	fn inject_disconnected(&mut self, peer: &PeerId, _: ConnectedPoint) {
+		self.connected.remove(peer);
+		self.requests_to_send.retain(|(target, _)| target != peer);
+		self.fail_requests_to(peer);
+	}
+
+	fn inject_node_event(&mut self, peer: PeerId, event: Event<NegotiatedSubstream>) {
+		match event {
+			Event::Request { protocol, payload, substream } =>
+				self.on_request(peer, &protocol, payload, substream),
+			Event::Response { request_id, payload } => {
+				match self.pending_requests.remove(&request_id) {
+					Some(request) if request.peer == peer => {
+						let _ = request.response.send(Ok(payload));
+					},
+					Some(request) => {
+						debug!(
+							target: "sub-libp2p",
+							"Response {} from wrong peer {}",
+							request_id,
+							peer,
+						);
+						self.pending_requests.insert(request_id, request);
+					},
+					// The request timed out in the meantime.
+					None =>
+					debug!(target: "sub-libp2p", "Late response {} from {}", request_id, peer),
+				}
+			},
+		}
+	}
+
+	fn poll(
+		&mut self,
+		cx: &mut Context,
+		_: &mut impl PollParameters,
+	) -> Poll<NetworkBehaviourAction<OutboundProtocol, Void>> {
+		while let Poll::Ready(Some(_)) = self.pending_responses.poll_next_unpin(cx) {}
+
+		while let Poll::Ready(Some(())) = self.timeout_check.poll_next_unpin(cx) {
+			let now = Instant::now();
+			let expired = self.pending_requests.iter()
+				.filter(|(_, request)| request.deadline <= now)
+				.map(|(id, _)| *id)
+				.collect::<Vec<_>>();
+			for id in expired {
+				if let Some(request) = self.pending_requests.remove(&id) {
+					debug!(target: "sub-libp2p", "Request {} to {} timed out", id, request.peer);
+					let _ = request.response.send(Err(RequestFailure::Timeout));
+				}
+			}
+		}
+
+		if let Some((peer_id, event)) = self.requests_to_send.pop_front() {
+			return Poll::Ready(NetworkBehaviourAction::SendEvent { peer_id, event })
+		}
+
+		Poll::Pending
+	}
+}
+
+/// Output type of inbound and outbound substream upgrades.
+#[derive(Debug)]
+pub enum Event<T> {
+	/// Request received from a peer, and substream to send the response on.
+	Request { protocol: Bytes, payload: Vec<u8>, substream: T },
+	/// Response to one of our requests.
+	Response { request_id: u64, payload: Vec<u8> },
+}
+
+/// Substream upgrade protocol.
+///
+/// Reads the requests of the peers on the protocols that answer them.
+#[derive(Debug, Clone)]
+pub struct InboundProtocol {
+	/// Names of the protocols, and their max. request sizes.
+	protocols: Vec<(Bytes, usize)>,
+}
+
+impl UpgradeInfo for InboundProtocol {
+	type Info = Bytes;
+	type InfoIter = std::vec::IntoIter<Self::Info>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		self.protocols.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>().into_iter()
+	}
+}
+
+impl<T> InboundUpgrade<T> for InboundProtocol
+where
+	T: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+	type Output = Event<T>;
+	type Error = ReadOneError;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_inbound(self, mut s: T, protocol: Self::Info) -> Self::Future {
+		let max_request_size = self.protocols.iter()
+			.find(|(name, _)| *name == protocol)
+			.map_or(0, |(_, size)| *size);
+		let future = async move {
+			let payload = read_one(&mut s, max_request_size).await?;
+			Ok(Event::Request { protocol, payload, substream: s })
+		};
+		future.boxed()
+	}
+}
+
+/// Substream upgrade protocol.
+///
+/// Sends a request to a peer and awaits the response.
+#[derive(Debug, Clone)]
+pub struct OutboundProtocol {
+	/// The protocol to use for upgrade negotiation.
+	protocol: Bytes,
+	/// The request, as encoded by the user.
+	request: Vec<u8>,
+	/// Local identifier of the request, associating it with its response.
+	request_id: u64,
+	/// The max. response size in bytes.
+	max_response_size: usize,
+}
+
+impl UpgradeInfo for OutboundProtocol {
+	type Info = Bytes;
+	type InfoIter = std::iter::Once<Self::Info>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		std::iter::once(self.protocol.clone())
+	}
+}
+
+impl<T> OutboundUpgrade<T> for OutboundProtocol
+where
+	T: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+	type Output = Event<T>;
+	type Error = ReadOneError;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_outbound(self, mut s: T, _: Self::Info) -> Self::Future {
+		let future = async move {
+			write_one(&mut s, &self.request).await?;
+			let payload = read_one(&mut s, self.max_response_size).await?;
+			Ok(Event::Response { request_id: self.request_id, payload })
+		};
+		future.boxed()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use async_std::task;
+	use libp2p::{
+		core::{
+			identity,
+			muxing::StreamMuxerBox,
+			transport::{Transport, memory::MemoryTransport},
+			upgrade,
+		},
+		multiaddr::Protocol,
+		noise::{Keypair, X25519, NoiseConfig},
+		swarm::Swarm,
+		yamux,
+	};
+
+	const PROTOCOL: &str = "/test/reverse/1";
+
+	fn make_swarm(protocols: Vec<RequestResponseConfig>) -> (Swarm<RequestResponses>, PeerId) {
+		let id_key = identity::Keypair::generate_ed25519();
+		let dh_key = Keypair::<X25519>::new().into_authentic(&id_key).unwrap();
+		let local_peer = id_key.public().into_peer_id();
+		let transport = MemoryTransport::default()
+			.upgrade(upgrade::Version::V1)
+			.authenticate(NoiseConfig::xx(dh_key).into_authenticated())
+			.multiplex(yamux::Config::default())
+			.map(|(peer, muxer), _| (peer, StreamMuxerBox::new(muxer)))
+			.map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+			.boxed();
+		let swarm = Swarm::new(transport, RequestResponses::new(protocols), local_peer.clone());
+		(swarm, local_peer)
+	}
+
+	#[test]
+	fn invalid_requests_fail_immediately() {
+		let mut behaviour = RequestResponses::new(vec![RequestResponseConfig::new(PROTOCOL)]);
+		let peer = PeerId::random();
+		let mut send = |protocol: &str, request: Vec<u8>| {
+			let (tx, mut rx) = oneshot::channel();
+			behaviour.send_request(&peer, protocol, request, tx);
+			rx.try_recv().unwrap().unwrap()
+		};
+
+		assert_eq!(send("/test/unknown/1", vec![1]), Err(RequestFailure::UnknownProtocol));
+		assert_eq!(send(PROTOCOL, vec![0; 1024 * 1024 + 1]), Err(RequestFailure::TooLarge));
+		assert_eq!(send(PROTOCOL, vec![1]), Err(RequestFailure::NotConnected));
+	}
+
+	#[test]
+	fn requests_are_answered() {
+		let mut local_config = RequestResponseConfig::new(PROTOCOL);
+		let mut requests = local_config.inbound_queue(4);
+		let (mut local_swarm, local_peer) = make_swarm(vec![local_config]);
+		let listen_addr: Multiaddr = Protocol::Memory(rand::random()).into();
+		Swarm::listen_on(&mut local_swarm, listen_addr.clone()).unwrap();
+
+		// The local node answers the requests with the reversed payload.
+		task::spawn(async move {
+			while let Some(request) = requests.next().await {
+				let mut response = request.payload;
+				response.reverse();
+				let _ = request.pending_response.send(response);
+			}
+		});
+		task::spawn(local_swarm.for_each(|_| future::ready(())));
+
+		let (mut remote_swarm, _) = make_swarm(vec![RequestResponseConfig::new(PROTOCOL)]);
+		Swarm::dial_addr(&mut remote_swarm, listen_addr).unwrap();
+
+		let mut pending = None;
+		let response = task::block_on(future::poll_fn(move |cx| {
+			loop {
+				match remote_swarm.poll_next_unpin(cx) {
+					Poll::Ready(Some(event)) => void::unreachable(event),
+					Poll::Ready(None) | Poll::Pending => {},
+				}
+				if pending.is_none() && remote_swarm.connected.contains(&local_peer) {
+					let (tx, rx) = oneshot::channel();
+					remote_swarm.send_request(&local_peer, PROTOCOL, vec![1, 2, 3], tx);
+					pending = Some(rx);
+					continue
+				}
+				return match pending.as_mut().map(|rx| rx.poll_unpin(cx)) {
+					Some(Poll::Ready(response)) => Poll::Ready(response.unwrap()),
+					_ => Poll::Pending,
+				}
+			}
+		}));
+
+		assert_eq!(response.unwrap(), vec![3, 2, 1]);
+	}
+}
@@ -25,30 +25,43 @@
 //! The methods of the [`NetworkService`] are implemented by sending a message over a channel,
 //! which is then processed by [`NetworkWorker::poll`].
 
-use std::{borrow::Cow, collections::{HashMap, HashSet}, fs, marker::PhantomData, io, path::Path, str};
+use std::{
+	borrow::Cow, collections::{HashMap, HashSet}, fs, marker::PhantomData, io, path::Path, str,
+	time::Duration,
+};
 use std::sync::{Arc, atomic::{AtomicBool, AtomicUsize, Ordering}};
 use std::pin::Pin;
 use std::task::Poll;
 
 use sp_consensus::import_queue::{ImportQueue, Link};
 use sp_consensus::import_queue::{BlockImportResult, BlockImportError};
-use futures::{prelude::*, channel::mpsc};
+use futures::{prelude::*, channel::{mpsc, oneshot}};
 use log::{warn, error, info, trace};
-use libp2p::{PeerId, Multiaddr, kad::record};
+use libp2p::{PeerId, Multiaddr, core::ConnectedPoint, kad::record};
+use libp2p::identity::{Keypair, PublicKey, error::SigningError};
 use libp2p::swarm::{NetworkBehaviour, SwarmBuilder, SwarmEvent};
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use sc_peerset::PeersetHandle;
 use sp_runtime::{traits::{Block as BlockT, NumberFor}, ConsensusEngineId};
 use prometheus_endpoint::{Registry, Counter, CounterVec, Gauge, GaugeVec, Opts, U64, register, PrometheusError};
 
 use crate::{behaviour::{Behaviour, BehaviourOut}, config::{parse_str_addr, parse_addr}};
-use crate::{transport, config::NonReservedPeerMode, ReputationChange};
-use crate::config::{Params, TransportConfig};
+use crate::{transport, upnp, config::NonReservedPeerMode, ReputationChange};
+use crate::chain::Client;
+use crate::config::{NatTraversal, Params, PeerFilterEntry, TransportConfig};
+use crate::dnsaddr::{DnsaddrBootnode, DnsaddrBootnodes};
 use crate::error::Error;
-use crate::network_state::{NetworkState, NotConnectedPeer as NetworkStateNotConnectedPeer, Peer as NetworkStatePeer};
+use crate::network_state::{
+	NetworkState, NotConnectedPeer as NetworkStateNotConnectedPeer, Peer as NetworkStatePeer,
+	PeerEndpoint,
+};
 use crate::protocol::{self, Protocol, PeerInfo};
 use crate::protocol::{event::Event, light_dispatch::{AlwaysBadChecker, RequestData}};
 use crate::protocol::sync::SyncState;
+use crate::peer_filter::PeerFilter;
+use crate::address_book::{AddressBook, PERSIST_INTERVAL};
+use crate::request_responses::{RequestFailure, RequestResponses};
+use crate::utils::interval;
 
 
 /// Minimum Requirements for a Hash within Networking
@@ -140,13 +153,21 @@ pub struct NetworkService<B: BlockT + 'static, H: ExHashT> {
 	external_addresses: Arc<Mutex<Vec<Multiaddr>>>,
 	/// Are we actively catching up with the chain?
 	is_major_syncing: Arc<AtomicBool>,
+	/// Are we connected to peers, but no longer receiving blocks from them?
+	is_isolated: Arc<AtomicBool>,
+	/// Are the notifications received from the network dispatched to the event streams?
+	accepts_notifications: AtomicBool,
 	/// Local copy of the `PeerId` of the local node.
 	local_peer_id: PeerId,
+	/// The network key of the local node, from which `local_peer_id` is derived.
+	local_identity: Keypair,
 	/// Bandwidth logging system. Can be queried to know the average bandwidth consumed.
 	bandwidth: Arc<transport::BandwidthSinks>,
 	/// Peerset manager (PSM); manages the reputation of nodes and indicates the network which
 	/// nodes it should be connected to or not.
 	peerset: PeersetHandle,
+	/// Peers the connections are accepted or refused with. Shared with the transport.
+	peer_filter: Arc<RwLock<PeerFilter>>,
 	/// Channel that sends messages to the actual worker.
 	to_worker: mpsc::UnboundedSender<ServiceToWorkerMsg<B, H>>,
 	/// Marker to pin the `H` generic. Serves no purpose except to not break backwards
@@ -173,7 +194,12 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 		let mut reserved_nodes = Vec::new();
 
 		// Process the bootnodes.
+		let mut dnsaddr_bootnodes = Vec::new();
 		for bootnode in params.network_config.boot_nodes.iter() {
+			if let Some(dnsaddr) = DnsaddrBootnode::parse(bootnode) {
+				dnsaddr_bootnodes.push(dnsaddr);
+				continue;
+			}
 			match parse_str_addr(bootnode) {
 				Ok((peer_id, addr)) => {
 					bootnodes.push(peer_id.clone());
@@ -183,6 +209,10 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			}
 		}
 
+		// The `/dnsaddr` bootnodes are resolved in the background, and their addresses added once
+		// known.
+		let dnsaddr_bootnodes = DnsaddrBootnodes::new(dnsaddr_bootnodes);
+
 		// Check for duplicate bootnodes.
 		known_addresses.iter()
 			.try_for_each(|(peer_id, addr)|
@@ -210,11 +240,30 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			}
 		}
 
+		// The sentries of a validator and the validators of a sentry are kept connected, as
+		// reserved peers. A validator with sentries connects to none of the other peers.
+		let has_sentries = !params.network_config.sentry_nodes.is_empty();
+		let mut allowed_peers = params.network_config.allowed_peers.clone();
+		let sentry_topology = params.network_config.sentry_nodes.iter()
+			.chain(params.network_config.sentry_validators.iter());
+		for node in sentry_topology {
+			if let Ok((peer_id, addr)) = parse_str_addr(node) {
+				if has_sentries {
+					allowed_peers.push(PeerFilterEntry::PeerId(peer_id.clone()));
+				}
+				reserved_nodes.push(peer_id.clone());
+				known_addresses.push((peer_id, addr));
+			} else {
+				warn!(target: "sub-libp2p", "Not a valid sentry or validator address: {}", node);
+			}
+		}
+
 		let peerset_config = sc_peerset::PeersetConfig {
 			in_peers: params.network_config.in_peers,
 			out_peers: params.network_config.out_peers,
 			bootnodes,
-			reserved_only: params.network_config.non_reserved_mode == NonReservedPeerMode::Deny,
+			reserved_only: has_sentries ||
+				params.network_config.non_reserved_mode == NonReservedPeerMode::Deny,
 			reserved_nodes,
 		};
 
@@ -230,10 +279,14 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 
 		let num_connected = Arc::new(AtomicUsize::new(0));
 		let is_major_syncing = Arc::new(AtomicBool::new(false));
-		let (protocol, peerset_handle) = Protocol::new(
+		let is_isolated = Arc::new(AtomicBool::new(false));
+		let chain = params.chain.clone();
+		let (mut protocol, peerset_handle) = Protocol::new(
 			protocol::ProtocolConfig {
 				roles: params.roles,
 				max_parallel_downloads: params.network_config.max_parallel_downloads,
+				max_extrinsic_size: params.network_config.max_extrinsic_size,
+				max_light_peers: params.network_config.max_light_peers,
 			},
 			params.chain.clone(),
 			checker.clone(),
@@ -245,6 +298,19 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			params.block_announce_validator,
 			params.metrics_registry.as_ref()
 		)?;
+		for notif in &params.network_config.notifications_protocols {
+			protocol.register_notifications_protocol(
+				notif.engine_id,
+				notif.protocol_name.clone(),
+				notif.handshake.clone(),
+				notif.max_peers,
+			);
+		}
+
+		let peer_filter = Arc::new(RwLock::new(PeerFilter::new(
+			allowed_peers,
+			params.network_config.denied_peers.clone(),
+		)));
 
 		// Build the swarm.
 		let (mut swarm, bandwidth): (Swarm::<B, H>, _) = {
@@ -274,17 +340,44 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 					TransportConfig::MemoryOnly => false,
 					TransportConfig::Normal { allow_private_ipv4, .. } => allow_private_ipv4,
 				},
+				match params.network_config.transport {
+					TransportConfig::MemoryOnly => false,
+					TransportConfig::Normal { prefer_ipv6, .. } => prefer_ipv6,
+				},
 				u64::from(params.network_config.out_peers) + 15,
+				params.network_config.discovery.clone(),
+				params.network_config.observed_address_confirmations,
 				block_requests,
-				light_client_handler
+				light_client_handler,
+				RequestResponses::new(params.network_config.request_response_protocols.clone()),
 			));
 			let (transport, bandwidth) = {
-				let (config_mem, config_wasm, flowctrl) = match params.network_config.transport {
-					TransportConfig::MemoryOnly => (true, None, false),
-					TransportConfig::Normal { wasm_external_transport, use_yamux_flow_control, .. } =>
-						(false, wasm_external_transport, use_yamux_flow_control)
+				let transport_config = params.network_config.transport;
+				let websocket_tls = match transport_config {
+					TransportConfig::MemoryOnly => None,
+					TransportConfig::Normal { ref websocket_tls, .. } => websocket_tls.clone(),
+				};
+				let (config_mem, config_wasm, flowctrl, proxy) = match transport_config {
+					TransportConfig::MemoryOnly => (true, None, false, None),
+					TransportConfig::Normal {
+						wasm_external_transport,
+						use_yamux_flow_control,
+						proxy,
+						..
+					} => (false, wasm_external_transport, use_yamux_flow_control, proxy),
 				};
-				transport::build_transport(local_identity, config_mem, config_wasm, flowctrl)
+				transport::build_transport(
+					local_identity.clone(),
+					config_mem,
+					config_wasm,
+					flowctrl,
+					proxy,
+					websocket_tls,
+					params.network_config.max_upload_rate,
+					params.network_config.max_download_rate,
+					params.network_config.pre_shared_key,
+					peer_filter.clone(),
+				)?
 			};
 			let mut builder = SwarmBuilder::new(transport, behaviour, local_peer_id.clone());
 			if let Some(spawner) = params.executor {
@@ -305,6 +398,25 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			Swarm::<B, H>::add_external_address(&mut swarm, addr.clone());
 		}
 
+		// Restore the peers known before the restart. Their addresses are stored the most reliable
+		// first, and are dialed in that order.
+		let address_book = AddressBook::load(&*chain);
+		for peer in address_book.peers() {
+			for addr in &peer.addresses {
+				swarm.add_persisted_address(peer.peer_id.clone(), addr.address.clone());
+			}
+			peerset_handle.report_peer(
+				peer.peer_id.clone(),
+				ReputationChange::new(peer.reputation, "Persisted reputation"),
+			);
+		}
+
+		// Map the listened ports on the router of the local network.
+		let upnp_addresses = match params.network_config.nat {
+			NatTraversal::None => None,
+			NatTraversal::Upnp => Some(upnp::start(&params.network_config.listen_addresses)),
+		};
+
 		let external_addresses = Arc::new(Mutex::new(Vec::new()));
 
 		let service = Arc::new(NetworkService {
@@ -312,8 +424,12 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			external_addresses: external_addresses.clone(),
 			num_connected: num_connected.clone(),
 			is_major_syncing: is_major_syncing.clone(),
+			is_isolated: is_isolated.clone(),
+			accepts_notifications: AtomicBool::new(true),
 			peerset: peerset_handle,
+			peer_filter,
 			local_peer_id,
+			local_identity,
 			to_worker: to_worker.clone(),
 			_marker: PhantomData,
 		});
@@ -322,8 +438,14 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			external_addresses,
 			num_connected,
 			is_major_syncing,
+			is_isolated,
 			network_service: swarm,
 			service,
+			chain,
+			address_book,
+			dnsaddr_bootnodes,
+			upnp_addresses,
+			persist_peers_interval: Box::pin(interval(PERSIST_INTERVAL)),
 			import_queue: params.import_queue,
 			from_worker,
 			light_client_rqs: params.on_demand.and_then(|od| od.extract_receiver()),
@@ -370,6 +492,25 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 		self.network_service.user_protocol().num_sync_peers()
 	}
 
+	/// The best block number when the sync started.
+	pub fn sync_starting_block(&self) -> NumberFor<B> {
+		self.network_service.user_protocol().sync_starting_block()
+	}
+
+	/// Estimated time left to reach the best seen block, while downloading.
+	pub fn sync_eta(&self) -> Option<Duration> {
+		self.network_service.user_protocol().sync_eta()
+	}
+
+	/// Stores the reputations and addresses of the known peers, to restore them on restart.
+	fn persist_peers(&mut self) {
+		let reputations = self.network_service.user_protocol_mut().peers_reputations();
+		let network_service = &mut self.network_service;
+		self.address_book.store(&*self.chain, reputations, |peer_id| {
+			network_service.addresses_of_peer(peer_id)
+		});
+	}
+
 	/// Number of blocks in the import queue.
 	pub fn num_queued_blocks(&self) -> u32 {
 		self.network_service.user_protocol().num_queued_blocks()
@@ -475,6 +616,11 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			.collect()
 	}
 
+	/// Returns the endpoint of the connection to the given peer, if it is connected.
+	pub fn peer_endpoint(&self, peer_id: &PeerId) -> Option<PeerEndpoint> {
+		self.network_service.node(peer_id).map(|node| node.endpoint().clone().into())
+	}
+
 	/// Removes a `PeerId` from the list of reserved peers.
 	pub fn remove_reserved_peer(&self, peer: PeerId) {
 		self.service.remove_reserved_peer(peer);
@@ -488,6 +634,41 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 }
 
 impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
+	/// Signs `message` with the network key of the local node.
+	///
+	/// Anyone can check with the returned public key that the signature was produced by the node
+	/// with our `PeerId`. Callers should prefix their messages, so that signatures produced for
+	/// one purpose can't be used for another.
+	pub fn sign_with_local_identity(
+		&self,
+		message: impl AsRef<[u8]>,
+	) -> Result<Signature, SigningError> {
+		Ok(Signature {
+			public_key: self.local_identity.public(),
+			bytes: self.local_identity.sign(message.as_ref())?,
+		})
+	}
+
+	/// Sends a request to a peer on one of the request/response protocols of
+	/// `NetworkConfiguration::request_response_protocols`, and returns its response.
+	///
+	/// The node must already be connected to the peer, no connection is opened for the request.
+	pub fn request(
+		&self,
+		target: PeerId,
+		protocol: impl Into<Cow<'static, str>>,
+		request: Vec<u8>,
+	) -> impl Future<Output = Result<Vec<u8>, RequestFailure>> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::Request {
+			target,
+			protocol: protocol.into(),
+			request,
+			pending_response: tx,
+		});
+		rx.map(|response| response.unwrap_or(Err(RequestFailure::Obsolete)))
+	}
+
 	/// Writes a message on an open notifications channel. Has no effect if the notifications
 	/// channel with this protocol name is closed.
 	///
@@ -527,7 +708,9 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 	/// about the protocol that you have registered.
 	///
 	/// You are very strongly encouraged to call this method very early on. Any connection open
-	/// will retain the protocols that were registered then, and not any new one.
+	/// will retain the protocols that were registered then, and not any new one. The protocols
+	/// that need a handshake or a limited number of peers must be registered through
+	/// `NetworkConfiguration::notifications_protocols` instead.
 	pub fn register_notifications_protocol(
 		&self,
 		engine_id: ConsensusEngineId,
@@ -611,6 +794,12 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 			.unbounded_send(ServiceToWorkerMsg::PutValue(key, value));
 	}
 
+	/// Sets whether the notifications received from the network, such as gossip messages, are
+	/// dispatched to the event streams. They are dropped otherwise.
+	pub fn set_accepts_notifications(&self, accepts: bool) {
+		self.accepts_notifications.store(accepts, Ordering::Relaxed);
+	}
+
 	/// Connect to unreserved peers and allow unreserved peers to connect.
 	pub fn accept_unreserved_peers(&self) {
 		self.peerset.set_reserved_only(false);
@@ -637,6 +826,38 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		Ok(())
 	}
 
+	/// Refuses the connections with the peers matching the entry: see `PeerFilterEntry` for its
+	/// format. Disconnects the peer if the entry is a `PeerId`.
+	pub fn add_denied_peer(&self, entry: String) -> Result<(), String> {
+		let entry: PeerFilterEntry = entry.parse()?;
+		self.peer_filter.write().deny(entry.clone());
+		if let PeerFilterEntry::PeerId(peer_id) = entry {
+			self.disconnect_peer(peer_id);
+		}
+		Ok(())
+	}
+
+	/// Removes an entry from the denied peers.
+	pub fn remove_denied_peer(&self, entry: String) -> Result<(), String> {
+		self.peer_filter.write().remove_denied(&entry.parse()?);
+		Ok(())
+	}
+
+	/// Accepts the connections with the peers matching the entry: see `PeerFilterEntry` for its
+	/// format. Once a peer is allowed, only the connections with the allowed peers are accepted.
+	///
+	/// The peers we're already connected to are kept.
+	pub fn add_allowed_peer(&self, entry: String) -> Result<(), String> {
+		self.peer_filter.write().allow(entry.parse()?);
+		Ok(())
+	}
+
+	/// Removes an entry from the allowed peers.
+	pub fn remove_allowed_peer(&self, entry: String) -> Result<(), String> {
+		self.peer_filter.write().remove_allowed(&entry.parse()?);
+		Ok(())
+	}
+
 	/// Configure an explicit fork sync request.
 	/// Note that this function should not be used for recent blocks.
 	/// Sync should be able to download all the recent forks normally.
@@ -683,6 +904,10 @@ impl<B: BlockT + 'static, H: ExHashT> sp_consensus::SyncOracle
 	fn is_offline(&mut self) -> bool {
 		self.num_connected.load(Ordering::Relaxed) == 0
 	}
+
+	fn is_isolated(&mut self) -> bool {
+		self.is_isolated.load(Ordering::Relaxed)
+	}
 }
 
 impl<'a, B: BlockT + 'static, H: ExHashT> sp_consensus::SyncOracle
@@ -695,6 +920,19 @@ impl<'a, B: BlockT + 'static, H: ExHashT> sp_consensus::SyncOracle
 	fn is_offline(&mut self) -> bool {
 		self.num_connected.load(Ordering::Relaxed) == 0
 	}
+
+	fn is_isolated(&mut self) -> bool {
+		self.is_isolated.load(Ordering::Relaxed)
+	}
+}
+
+/// Signature of a message by the network key of a node.
+#[derive(Debug, Clone)]
+pub struct Signature {
+	/// Public network key of the node, from which its `PeerId` is derived.
+	pub public_key: PublicKey,
+	/// The signature itself.
+	pub bytes: Vec<u8>,
 }
 
 /// Trait for providing information about the local network state
@@ -744,6 +982,12 @@ enum ServiceToWorkerMsg<B: BlockT, H: ExHashT> {
 		engine_id: ConsensusEngineId,
 		protocol_name: Cow<'static, [u8]>,
 	},
+	Request {
+		target: PeerId,
+		protocol: Cow<'static, str>,
+		request: Vec<u8>,
+		pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+	},
 	DisconnectPeer(PeerId),
 }
 
@@ -758,10 +1002,22 @@ pub struct NetworkWorker<B: BlockT + 'static, H: ExHashT> {
 	num_connected: Arc<AtomicUsize>,
 	/// Updated by the `NetworkWorker` and loaded by the `NetworkService`.
 	is_major_syncing: Arc<AtomicBool>,
+	/// Updated by the `NetworkWorker` and loaded by the `NetworkService`.
+	is_isolated: Arc<AtomicBool>,
 	/// The network service that can be extracted and shared through the codebase.
 	service: Arc<NetworkService<B, H>>,
 	/// The *actual* network.
 	network_service: Swarm<B, H>,
+	/// Client, storing the known peers across restarts.
+	chain: Arc<dyn Client<B>>,
+	/// Known peers and the outcome of the dials to their addresses.
+	address_book: AddressBook,
+	/// Bootnodes to resolve again when they can't be reached.
+	dnsaddr_bootnodes: DnsaddrBootnodes,
+	/// External addresses of the ports mapped with UPnP, if enabled.
+	upnp_addresses: Option<upnp::Mappings>,
+	/// Interval at which the known peers are stored.
+	persist_peers_interval: Pin<Box<dyn Stream<Item = ()> + Send>>,
 	/// The import queue that was passed as initialization.
 	import_queue: Box<dyn ImportQueue<B>>,
 	/// Messages from the `NetworkService` and that must be processed.
@@ -886,6 +1142,24 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 			protocol: &mut this.network_service,
 		});
 
+		while let Poll::Ready(Some(())) = this.persist_peers_interval.poll_next_unpin(cx) {
+			this.persist_peers();
+		}
+
+		// Add the addresses of the resolved `/dnsaddr` bootnodes.
+		while let Poll::Ready(addresses) = this.dnsaddr_bootnodes.poll(cx) {
+			for (peer_id, addr) in addresses {
+				this.network_service.add_known_address(peer_id, addr);
+			}
+		}
+
+		// Announce the addresses of the ports mapped on the router.
+		if let Some(upnp_addresses) = this.upnp_addresses.as_mut() {
+			while let Poll::Ready(Some(addr)) = upnp_addresses.poll_next_unpin(cx) {
+				Swarm::<B, H>::add_external_address(&mut this.network_service, addr);
+			}
+		}
+
 		// Check for new incoming light client requests.
 		if let Some(light_client_rqs) = this.light_client_rqs.as_mut() {
 			while let Poll::Ready(Some(rq)) = light_client_rqs.poll_next_unpin(cx) {
@@ -928,11 +1202,20 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 				},
 				ServiceToWorkerMsg::RegisterNotifProtocol { engine_id, protocol_name } => {
 					let events = this.network_service.user_protocol_mut()
-						.register_notifications_protocol(engine_id, protocol_name);
+						.register_notifications_protocol(
+							engine_id,
+							protocol_name,
+							Vec::new(),
+							None,
+						);
 					for event in events {
 						this.event_streams.retain(|sender| sender.unbounded_send(event.clone()).is_ok());
 					}
 				},
+				ServiceToWorkerMsg::Request { target, protocol, request, pending_response } => {
+					let network = &mut this.network_service;
+					network.send_request(&target, &protocol, request, pending_response)
+				},
 				ServiceToWorkerMsg::DisconnectPeer(who) =>
 					this.network_service.user_protocol_mut().disconnect_peer(&who),
 			}
@@ -969,6 +1252,10 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 						metrics.random_kademalia_queries_total.inc();
 					}
 				},
+				Poll::Ready(SwarmEvent::Behaviour(
+					BehaviourOut::Event(Event::NotificationsReceived { .. })
+				)) if !this.service.accepts_notifications.load(Ordering::Relaxed) =>
+					trace!(target: "sub-libp2p", "Dropping notifications, not accepted"),
 				Poll::Ready(SwarmEvent::Behaviour(BehaviourOut::Event(ev))) => {
 					this.event_streams.retain(|sender| sender.unbounded_send(ev.clone()).is_ok());
 					if let Some(metrics) = this.metrics.as_ref() {
@@ -980,6 +1267,11 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 					if let Some(metrics) = this.metrics.as_ref() {
 						metrics.connections.inc();
 					}
+					let endpoint = this.network_service.node(&peer_id)
+						.map(|node| node.endpoint().clone());
+					if let Some(ConnectedPoint::Dialer { address }) = endpoint {
+						this.address_book.on_dial_success(&peer_id, &address);
+					}
 				},
 				Poll::Ready(SwarmEvent::Disconnected(peer_id)) => {
 					trace!(target: "sub-libp2p", "Libp2p => Disconnected({:?})", peer_id);
@@ -991,8 +1283,11 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 					trace!(target: "sub-libp2p", "Libp2p => NewListenAddr({})", addr),
 				Poll::Ready(SwarmEvent::ExpiredListenAddr(addr)) =>
 					trace!(target: "sub-libp2p", "Libp2p => ExpiredListenAddr({})", addr),
-				Poll::Ready(SwarmEvent::UnreachableAddr { peer_id, address, error }) =>
-					trace!(target: "sub-libp2p", "Libp2p => Failed to reach {:?} through {:?}: {}", peer_id, address, error),
+				Poll::Ready(SwarmEvent::UnreachableAddr { peer_id, address, error }) => {
+					trace!(target: "sub-libp2p", "Libp2p => Failed to reach {:?} through {:?}: {}", peer_id, address, error);
+					this.dnsaddr_bootnodes.on_unreachable(&peer_id);
+					this.address_book.on_dial_failure(&peer_id, &address);
+				},
 				Poll::Ready(SwarmEvent::StartConnect(peer_id)) =>
 					trace!(target: "sub-libp2p", "Libp2p => StartConnect({:?})", peer_id),
 			};
@@ -1013,6 +1308,8 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 		};
 
 		this.is_major_syncing.store(is_major_syncing, Ordering::Relaxed);
+		let is_isolated = this.network_service.user_protocol().is_isolated();
+		this.is_isolated.store(is_isolated, Ordering::Relaxed);
 
 		if let Some(metrics) = this.metrics.as_ref() {
 			metrics.network_per_sec_bytes.with_label_values(&["in"]).set(this.service.bandwidth.average_download_per_sec());
@@ -1032,6 +1329,12 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 impl<B: BlockT + 'static, H: ExHashT> Unpin for NetworkWorker<B, H> {
 }
 
+impl<B: BlockT + 'static, H: ExHashT> Drop for NetworkWorker<B, H> {
+	fn drop(&mut self) {
+		self.persist_peers();
+	}
+}
+
 /// Turns a `ConsensusEngineId` into a representable string.
 fn engine_id_to_string(id: &ConsensusEngineId) -> Cow<str> {
 	if let Ok(s) = std::str::from_utf8(&id[..]) {
@@ -21,24 +21,58 @@ use libp2p::{
 };
 #[cfg(not(target_os = "unknown"))]
 use libp2p::{tcp, dns, websocket};
-use libp2p::core::{self, upgrade, transport::boxed::Boxed, transport::OptionalTransport, muxing::StreamMuxerBox};
+use libp2p::core::{
+	self, upgrade, either::EitherOutput, transport::boxed::Boxed, transport::OptionalTransport,
+	muxing::StreamMuxerBox,
+};
+use parking_lot::RwLock;
 use std::{io, sync::Arc, time::Duration, usize};
 
+use crate::config::{PreSharedKey, ProxyConfig, WebSocketTlsConfig};
+use crate::peer_filter::PeerFilter;
+
 pub use self::bandwidth::BandwidthSinks;
 
+#[cfg(not(target_os = "unknown"))]
+mod proxy;
+mod pnet;
+mod throttle;
+#[cfg(not(target_os = "unknown"))]
+mod tls;
+
 /// Builds the transport that serves as a common ground for all connections.
 ///
 /// If `memory_only` is true, then only communication within the same process are allowed. Only
 /// addresses with the format `/memory/...` are allowed.
 ///
+/// If a `proxy` is passed, the TCP and WebSocket addresses are dialed through it. Listening is
+/// unaffected.
+///
+/// The `/wss` addresses are listened on with the certificate of `websocket_tls`, which fails to
+/// load with an error.
+///
+/// The bandwidth used by all the connections is limited to `max_upload_rate` and
+/// `max_download_rate`, in bytes per second, if passed.
+///
+/// If a `pre_shared_key` is passed, all the connections are encrypted with it before any other
+/// handshake, so that only the nodes holding it can connect.
+///
+/// The connections with the peers refused by `peer_filter` fail once their identity is known.
+///
 /// Returns a `BandwidthSinks` object that allows querying the average bandwidth produced by all
 /// the connections spawned with this transport.
 pub fn build_transport(
 	keypair: identity::Keypair,
 	memory_only: bool,
 	wasm_external_transport: Option<wasm_ext::ExtTransport>,
-	use_yamux_flow_control: bool
-) -> (Boxed<(PeerId, StreamMuxerBox), io::Error>, Arc<bandwidth::BandwidthSinks>) {
+	use_yamux_flow_control: bool,
+	proxy: Option<ProxyConfig>,
+	websocket_tls: Option<WebSocketTlsConfig>,
+	max_upload_rate: Option<u64>,
+	max_download_rate: Option<u64>,
+	pre_shared_key: Option<PreSharedKey>,
+	peer_filter: Arc<RwLock<PeerFilter>>,
+) -> io::Result<(Boxed<(PeerId, StreamMuxerBox), io::Error>, Arc<bandwidth::BandwidthSinks>)> {
 	// Build configuration objects for encryption mechanisms.
 	let noise_config = {
 		let noise_keypair = noise::Keypair::new().into_authentic(&keypair)
@@ -71,11 +105,27 @@ pub fn build_transport(
 	} else {
 		OptionalTransport::none()
 	};
+	// The dials are first attempted through the proxy, which doesn't listen.
+	#[cfg(not(target_os = "unknown"))]
+	let transport = transport.or_transport(match proxy {
+		Some(proxy) if !memory_only => {
+			let proxy_trans = proxy::ProxyTransport::new(proxy);
+			OptionalTransport::some(
+				websocket::WsConfig::new(proxy_trans.clone()).or_transport(proxy_trans)
+			)
+		},
+		_ => OptionalTransport::none(),
+	});
+	#[cfg(target_os = "unknown")]
+	let _ = (proxy, websocket_tls);
 	#[cfg(not(target_os = "unknown"))]
 	let transport = transport.or_transport(if !memory_only {
 		let desktop_trans = tcp::TcpConfig::new();
-		let desktop_trans = websocket::WsConfig::new(desktop_trans.clone())
-			.or_transport(desktop_trans);
+		let mut ws_trans = websocket::WsConfig::new(desktop_trans.clone());
+		if let Some(websocket_tls) = websocket_tls {
+			ws_trans.set_tls_config(tls::load(&websocket_tls)?);
+		}
+		let desktop_trans = ws_trans.or_transport(desktop_trans);
 		OptionalTransport::some(if let Ok(dns) = dns::DnsConfig::new(desktop_trans.clone()) {
 			dns.boxed()
 		} else {
@@ -91,8 +141,17 @@ pub fn build_transport(
 		OptionalTransport::none()
 	});
 
+	let throttle = throttle::Throttle::new(max_upload_rate, max_download_rate);
+	let transport = transport.map(move |stream, _| throttle.wrap(stream));
+
 	let (transport, sinks) = bandwidth::BandwidthLogging::new(transport, Duration::from_secs(5));
 
+	// Private network
+	let transport = transport.and_then(move |stream, _| match pre_shared_key.clone() {
+		Some(key) => pnet::handshake(stream, key).map_ok(EitherOutput::First).left_future(),
+		None => future::ready(Ok::<_, io::Error>(EitherOutput::Second(stream))).right_future(),
+	});
+
 	// Encryption
 	let transport = transport.and_then(move |stream, endpoint| {
 		core::upgrade::apply(stream, noise_config, endpoint, upgrade::Version::V1)
@@ -105,6 +164,19 @@ pub fn build_transport(
 			})
 	});
 
+	// Filtering of the peers
+	let transport = transport.and_then(move |(stream, peer_id), endpoint| {
+		let allowed = peer_filter.read().is_allowed(&peer_id, &endpoint);
+		future::ready(if allowed {
+			Ok((stream, peer_id))
+		} else {
+			Err(io::Error::new(
+				io::ErrorKind::PermissionDenied,
+				format!("Connection with {} refused by the peer filter", peer_id),
+			))
+		})
+	});
+
 	// Multiplexing
 	let transport = transport.and_then(move |(stream, peer_id), endpoint| {
 			let peer_id2 = peer_id.clone();
@@ -121,5 +193,5 @@ pub fn build_transport(
 			.map_err(|err| io::Error::new(io::ErrorKind::Other, err))
 			.boxed();
 
-	(transport, sinks)
+	Ok((transport, sinks))
 }
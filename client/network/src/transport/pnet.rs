@@ -0,0 +1,158 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Private networks: the connections are encrypted with a key shared by the nodes, before any
+//! other handshake, so that the nodes without the key can't complete one.
+//!
+//! Compatible with the `/key/swarm/psk/1.0.0/` private networks of libp2p: each side sends a
+//! random 24 bytes nonce, and then encrypts everything it sends with XSalsa20, keyed with the
+//! shared key and its nonce.
+
+use futures::{prelude::*, ready};
+use salsa20::{XSalsa20, stream_cipher::{NewStreamCipher, SyncStreamCipher}};
+use std::{io, pin::Pin, task::{Context, Poll}};
+
+use crate::config::PreSharedKey;
+
+/// Size of the nonce sent by each side.
+const NONCE_SIZE: usize = 24;
+
+/// Exchanges the nonces on a new connection, and returns the encrypted connection.
+pub async fn handshake<S>(mut socket: S, key: PreSharedKey) -> io::Result<PnetOutput<S>> where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let local_nonce: [u8; NONCE_SIZE] = rand::random();
+	socket.write_all(&local_nonce).await?;
+	socket.flush().await?;
+	let mut remote_nonce = [0; NONCE_SIZE];
+	socket.read_exact(&mut remote_nonce).await?;
+
+	let cipher = |nonce: &[u8]| XSalsa20::new_var(&key.0, nonce)
+		.expect("the key is 32 bytes and the nonce 24 bytes, as expected by XSalsa20; qed");
+	Ok(PnetOutput {
+		inner: socket,
+		read_cipher: cipher(&remote_nonce),
+		write_cipher: cipher(&local_nonce),
+		pending: Vec::new(),
+	})
+}
+
+/// Connection encrypted with the shared key.
+pub struct PnetOutput<S> {
+	inner: S,
+	read_cipher: XSalsa20,
+	write_cipher: XSalsa20,
+	/// Bytes encrypted but not written yet.
+	pending: Vec<u8>,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for PnetOutput<S> {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context,
+		buf: &mut [u8],
+	) -> Poll<io::Result<usize>> {
+		let this = &mut *self;
+		let read = ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+		this.read_cipher.apply_keystream(&mut buf[..read]);
+		Poll::Ready(Ok(read))
+	}
+}
+
+/// Writes the pending bytes.
+fn poll_write_pending<S: AsyncWrite + Unpin>(
+	inner: &mut S,
+	pending: &mut Vec<u8>,
+	cx: &mut Context,
+) -> Poll<io::Result<()>> {
+	while !pending.is_empty() {
+		let written = ready!(Pin::new(&mut *inner).poll_write(cx, pending))?;
+		if written == 0 {
+			return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+		}
+		pending.drain(..written);
+	}
+	Poll::Ready(Ok(()))
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for PnetOutput<S> {
+	fn poll_write(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context,
+		buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		let this = &mut *self;
+		ready!(poll_write_pending(&mut this.inner, &mut this.pending, cx))?;
+		this.pending.extend_from_slice(buf);
+		this.write_cipher.apply_keystream(&mut this.pending);
+		// the keystream advanced over the whole buffer, so what isn't written now is written by
+		// the next calls.
+		if let Poll::Ready(Err(e)) = poll_write_pending(&mut this.inner, &mut this.pending, cx) {
+			return Poll::Ready(Err(e));
+		}
+		Poll::Ready(Ok(buf.len()))
+	}
+
+	fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		let this = &mut *self;
+		ready!(poll_write_pending(&mut this.inner, &mut this.pending, cx))?;
+		Pin::new(&mut this.inner).poll_flush(cx)
+	}
+
+	fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		let this = &mut *self;
+		ready!(poll_write_pending(&mut this.inner, &mut this.pending, cx))?;
+		Pin::new(&mut this.inner).poll_close(cx)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use async_std::net::{TcpListener, TcpStream};
+	use futures::executor::block_on;
+
+	/// Connects two sockets over TCP, with the given keys, and returns what the second receives
+	/// when the first sends `message`.
+	fn exchange(first_key: [u8; 32], second_key: [u8; 32], message: &[u8]) -> Vec<u8> {
+		block_on(async {
+			let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+			let addr = listener.local_addr().unwrap();
+			let (first, second) = future::join(
+				TcpStream::connect(addr),
+				async { listener.accept().await.map(|(socket, _)| socket) },
+			).await;
+			let (first, second) = future::join(
+				handshake(first.unwrap(), PreSharedKey(first_key)),
+				handshake(second.unwrap(), PreSharedKey(second_key)),
+			).await;
+			let (mut first, mut second) = (first.unwrap(), second.unwrap());
+
+			first.write_all(message).await.unwrap();
+			first.flush().await.unwrap();
+			let mut received = vec![0; message.len()];
+			second.read_exact(&mut received).await.unwrap();
+			received
+		})
+	}
+
+	#[test]
+	fn only_nodes_with_the_key_understand_each_other() {
+		let message = b"/multistream/1.0.0\n".to_vec();
+		assert_eq!(exchange([1; 32], [1; 32], &message), message);
+		assert_ne!(exchange([1; 32], [2; 32], &message), message);
+	}
+}
@@ -0,0 +1,308 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Dialing of TCP connections through a SOCKS5 or HTTP proxy.
+//!
+//! The transport only dials: listening is left to the TCP transport. The host names of the
+//! `/dns4` and `/dns6` addresses are passed to the proxy, so they aren't resolved locally.
+
+use async_std::net::TcpStream;
+use futures::{prelude::*, future::BoxFuture};
+use libp2p::{
+	Multiaddr, Transport, multiaddr::Protocol,
+	core::transport::{ListenerEvent, TransportError},
+};
+use std::{io, net::IpAddr};
+
+use crate::config::{ProxyConfig, ProxyProtocol};
+
+/// Maximal size of the response headers of an HTTP proxy.
+const MAX_HTTP_RESPONSE_SIZE: usize = 8 * 1024;
+
+/// Transport dialing the `/ip4`, `/ip6`, `/dns4` and `/dns6` TCP addresses through a proxy.
+#[derive(Clone, Debug)]
+pub struct ProxyTransport {
+	config: ProxyConfig,
+}
+
+impl ProxyTransport {
+	/// Creates a transport dialing through the given proxy.
+	pub fn new(config: ProxyConfig) -> Self {
+		ProxyTransport { config }
+	}
+}
+
+impl Transport for ProxyTransport {
+	type Output = TcpStream;
+	type Error = io::Error;
+	type Listener = stream::Pending<Result<ListenerEvent<Self::ListenerUpgrade>, io::Error>>;
+	type ListenerUpgrade = future::Pending<Result<TcpStream, io::Error>>;
+	type Dial = BoxFuture<'static, Result<TcpStream, io::Error>>;
+
+	fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<io::Error>> {
+		Err(TransportError::MultiaddrNotSupported(addr))
+	}
+
+	fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<io::Error>> {
+		let (host, port) = match destination(&addr) {
+			Some(destination) => destination,
+			None => return Err(TransportError::MultiaddrNotSupported(addr)),
+		};
+		let config = self.config;
+		Ok(async move {
+			let mut stream = TcpStream::connect((config.host.as_str(), config.port)).await?;
+			match config.protocol {
+				ProxyProtocol::Socks5 => socks5_connect(&mut stream, &host, port).await?,
+				ProxyProtocol::Http => http_connect(&mut stream, &host, port).await?,
+			}
+			Ok(stream)
+		}.boxed())
+	}
+}
+
+/// Host to connect to through the proxy.
+#[derive(Debug, PartialEq)]
+enum Host {
+	Ip(IpAddr),
+	Name(String),
+}
+
+/// Extracts the host and port of a `/ip4`, `/ip6`, `/dns4` or `/dns6` TCP address.
+fn destination(addr: &Multiaddr) -> Option<(Host, u16)> {
+	let mut protocols = addr.iter();
+	let host = match protocols.next()? {
+		Protocol::Ip4(ip) => Host::Ip(ip.into()),
+		Protocol::Ip6(ip) => Host::Ip(ip.into()),
+		Protocol::Dns4(name) | Protocol::Dns6(name) => Host::Name(name.into_owned()),
+		_ => return None,
+	};
+	let port = match protocols.next()? {
+		Protocol::Tcp(port) => port,
+		_ => return None,
+	};
+	if protocols.next().is_some() {
+		return None;
+	}
+	Some((host, port))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+/// Asks a SOCKS5 proxy to connect to `host:port`, as specified by RFC 1928.
+async fn socks5_connect<S>(stream: &mut S, host: &Host, port: u16) -> io::Result<()> where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	// version 5, with the "no authentication" method only.
+	stream.write_all(&[5, 1, 0]).await?;
+	let mut method = [0; 2];
+	stream.read_exact(&mut method).await?;
+	if method != [5, 0] {
+		return Err(invalid_data("SOCKS5 proxy requires authentication"));
+	}
+
+	// version 5, CONNECT command, reserved byte, then the destination.
+	let mut request = vec![5, 1, 0];
+	match host {
+		Host::Ip(IpAddr::V4(ip)) => {
+			request.push(1);
+			request.extend_from_slice(&ip.octets());
+		},
+		Host::Ip(IpAddr::V6(ip)) => {
+			request.push(4);
+			request.extend_from_slice(&ip.octets());
+		},
+		Host::Name(name) => {
+			if name.len() > 255 {
+				return Err(invalid_data("Host name too long for SOCKS5"));
+			}
+			request.push(3);
+			request.push(name.len() as u8);
+			request.extend_from_slice(name.as_bytes());
+		},
+	}
+	request.extend_from_slice(&port.to_be_bytes());
+	stream.write_all(&request).await?;
+
+	let mut reply = [0; 4];
+	stream.read_exact(&mut reply).await?;
+	if reply[0] != 5 {
+		return Err(invalid_data("Invalid SOCKS5 reply"));
+	}
+	if reply[1] != 0 {
+		return Err(io::Error::new(
+			io::ErrorKind::ConnectionRefused,
+			format!("SOCKS5 proxy failed to connect with code {}", reply[1]),
+		));
+	}
+	// the address the proxy bound, and its port, are of no use.
+	let bound_len = match reply[3] {
+		1 => 4,
+		4 => 16,
+		3 => {
+			let mut len = [0; 1];
+			stream.read_exact(&mut len).await?;
+			len[0] as usize
+		},
+		_ => return Err(invalid_data("Invalid SOCKS5 reply")),
+	};
+	let mut bound = vec![0; bound_len + 2];
+	stream.read_exact(&mut bound).await?;
+	Ok(())
+}
+
+/// Asks an HTTP proxy to connect to `host:port` with the `CONNECT` method.
+async fn http_connect<S>(stream: &mut S, host: &Host, port: u16) -> io::Result<()> where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let authority = match host {
+		Host::Ip(IpAddr::V6(ip)) => format!("[{}]:{}", ip, port),
+		Host::Ip(IpAddr::V4(ip)) => format!("{}:{}", ip, port),
+		Host::Name(name) => format!("{}:{}", name, port),
+	};
+	let request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n\r\n", authority);
+	stream.write_all(request.as_bytes()).await?;
+
+	// the response is read byte by byte, not to consume the data following its headers.
+	let mut response = Vec::new();
+	while !response.ends_with(b"\r\n\r\n") {
+		if response.len() == MAX_HTTP_RESPONSE_SIZE {
+			return Err(invalid_data("HTTP proxy response too large"));
+		}
+		let mut byte = [0; 1];
+		stream.read_exact(&mut byte).await?;
+		response.push(byte[0]);
+	}
+	let status = String::from_utf8_lossy(&response);
+	let status = status.split_whitespace().nth(1).unwrap_or_default();
+	if !status.starts_with('2') || status.len() != 3 {
+		return Err(io::Error::new(
+			io::ErrorKind::ConnectionRefused,
+			format!("HTTP proxy failed to connect with status {}", status),
+		));
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{executor::block_on, io::Cursor};
+	use std::{pin::Pin, task::{Context, Poll}};
+
+	/// Stream replying the given bytes, and recording the bytes written to it.
+	struct MockStream {
+		reply: Cursor<Vec<u8>>,
+		written: Vec<u8>,
+	}
+
+	impl MockStream {
+		fn new(reply: &[u8]) -> Self {
+			MockStream { reply: Cursor::new(reply.to_vec()), written: Vec::new() }
+		}
+
+		fn rest(&mut self) -> Vec<u8> {
+			let mut rest = Vec::new();
+			block_on(self.read_to_end(&mut rest)).unwrap();
+			rest
+		}
+	}
+
+	impl AsyncRead for MockStream {
+		fn poll_read(
+			mut self: Pin<&mut Self>,
+			cx: &mut Context,
+			buf: &mut [u8],
+		) -> Poll<io::Result<usize>> {
+			Pin::new(&mut self.reply).poll_read(cx, buf)
+		}
+	}
+
+	impl AsyncWrite for MockStream {
+		fn poll_write(
+			mut self: Pin<&mut Self>,
+			_: &mut Context,
+			buf: &[u8],
+		) -> Poll<io::Result<usize>> {
+			self.written.extend_from_slice(buf);
+			Poll::Ready(Ok(buf.len()))
+		}
+
+		fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+			Poll::Ready(Ok(()))
+		}
+
+		fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+			Poll::Ready(Ok(()))
+		}
+	}
+
+	#[test]
+	fn only_tcp_addresses_are_dialed() {
+		let destination = |addr: &str| destination(&addr.parse().unwrap());
+		assert_eq!(
+			destination("/ip4/198.51.100.19/tcp/30333"),
+			Some((Host::Ip("198.51.100.19".parse().unwrap()), 30333)),
+		);
+		assert_eq!(
+			destination("/dns4/example.com/tcp/30333"),
+			Some((Host::Name("example.com".into()), 30333)),
+		);
+		assert_eq!(destination("/ip4/198.51.100.19/tcp/30333/ws"), None);
+		assert_eq!(destination("/ip4/198.51.100.19/udp/30333"), None);
+		assert_eq!(destination("/memory/1"), None);
+	}
+
+	#[test]
+	fn socks5_proxy_connects() {
+		let mut stream = MockStream::new(&[5, 0, 5, 0, 0, 1, 127, 0, 0, 1, 0, 80, 42]);
+		let host = Host::Name("example.com".into());
+		block_on(socks5_connect(&mut stream, &host, 30333)).unwrap();
+
+		let mut expected = vec![5, 1, 0, 5, 1, 0, 3, 11];
+		expected.extend_from_slice(b"example.com");
+		expected.extend_from_slice(&30333u16.to_be_bytes());
+		assert_eq!(stream.written, expected);
+		assert_eq!(stream.rest(), vec![42]);
+
+		// the connection is refused by the destination.
+		let mut stream = MockStream::new(&[5, 0, 5, 5, 0, 1, 0, 0, 0, 0, 0, 0]);
+		let host = Host::Ip("198.51.100.19".parse().unwrap());
+		assert!(block_on(socks5_connect(&mut stream, &host, 30333)).is_err());
+		// the proxy requires authentication.
+		let mut stream = MockStream::new(&[5, 0xff]);
+		assert!(block_on(socks5_connect(&mut stream, &host, 30333)).is_err());
+	}
+
+	#[test]
+	fn http_proxy_connects() {
+		let mut stream = MockStream::new(b"HTTP/1.1 200 Connection established\r\n\r\nrest");
+		let host = Host::Ip("2001:db8::1".parse().unwrap());
+		block_on(http_connect(&mut stream, &host, 30333)).unwrap();
+
+		assert_eq!(
+			stream.written,
+			b"CONNECT [2001:db8::1]:30333 HTTP/1.1\r\nHost: [2001:db8::1]:30333\r\n\r\n".to_vec(),
+		);
+		assert_eq!(stream.rest(), b"rest".to_vec());
+
+		let mut stream = MockStream::new(b"HTTP/1.1 403 Forbidden\r\n\r\n");
+		assert!(block_on(http_connect(&mut stream, &host, 30333)).is_err());
+		let mut stream = MockStream::new(b"HTTP/1.1 200 OK\r\n");
+		assert!(block_on(http_connect(&mut stream, &host, 30333)).is_err());
+	}
+}
@@ -0,0 +1,223 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Limiting of the bandwidth used by all the connections of the node.
+//!
+//! Each direction has a bucket shared by all the connections, refilled at the maximal rate and
+//! holding at most one second worth of bytes. A connection reads or writes at most as many bytes
+//! as the bucket holds, and waits for it to be refilled when it is empty.
+
+use futures::{prelude::*, ready};
+use futures_timer::Delay;
+use parking_lot::Mutex;
+use std::{io, pin::Pin, sync::Arc, task::{Context, Poll}, time::Duration};
+use wasm_timer::Instant;
+
+/// Limits of the bandwidth shared by the connections. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct Throttle {
+	upload: Option<Arc<Bucket>>,
+	download: Option<Arc<Bucket>>,
+}
+
+impl Throttle {
+	/// Creates limits of the given rates, in bytes per second. `None` if unlimited.
+	pub fn new(max_upload_rate: Option<u64>, max_download_rate: Option<u64>) -> Self {
+		Throttle {
+			upload: max_upload_rate.map(|rate| Arc::new(Bucket::new(rate))),
+			download: max_download_rate.map(|rate| Arc::new(Bucket::new(rate))),
+		}
+	}
+
+	/// Wraps a connection so that it respects the limits.
+	pub fn wrap<S>(&self, inner: S) -> ThrottledStream<S> {
+		ThrottledStream {
+			inner,
+			throttle: self.clone(),
+			read_delay: None,
+			write_delay: None,
+		}
+	}
+}
+
+/// Bytes that can be transferred in one direction.
+struct Bucket {
+	/// Bytes added per second, and maximal content of the bucket.
+	rate: u64,
+	state: Mutex<BucketState>,
+}
+
+struct BucketState {
+	/// Bytes that can be transferred. Negative if the connections overdrew the bucket at the
+	/// same time.
+	available: i64,
+	/// Time up to which the bucket was refilled.
+	refilled_at: Instant,
+}
+
+impl Bucket {
+	fn new(rate: u64) -> Self {
+		let rate = rate.max(1);
+		Bucket {
+			rate,
+			state: Mutex::new(BucketState { available: rate as i64, refilled_at: Instant::now() }),
+		}
+	}
+
+	/// Returns the number of bytes that can be transferred, or the time to wait until some can.
+	fn available(&self) -> Result<usize, Duration> {
+		let mut state = self.state.lock();
+		let now = Instant::now();
+		let elapsed = now.duration_since(state.refilled_at);
+		let added = elapsed.as_nanos() * u128::from(self.rate) / 1_000_000_000;
+		// only the time accounted for by whole bytes is consumed, not to starve the bucket when
+		// it is polled often.
+		if added > 0 {
+			let available = (state.available as i128).saturating_add(added as i128);
+			if available >= self.rate as i128 {
+				state.available = self.rate as i64;
+				state.refilled_at = now;
+			} else {
+				state.available = available as i64;
+				state.refilled_at += Duration::from_nanos(
+					(added * 1_000_000_000 / u128::from(self.rate)) as u64
+				);
+			}
+		}
+
+		if state.available > 0 {
+			Ok(state.available as usize)
+		} else {
+			let missing = (1 - state.available) as u128;
+			let wait = missing * 1_000_000_000 / u128::from(self.rate) + 1;
+			Err(Duration::from_nanos(wait as u64))
+		}
+	}
+
+	fn consume(&self, bytes: usize) {
+		let mut state = self.state.lock();
+		state.available = state.available.saturating_sub(bytes as i64);
+	}
+}
+
+/// Waits until the bucket isn't empty, and returns the number of bytes that can be transferred.
+fn poll_available(
+	bucket: &Bucket,
+	delay: &mut Option<Delay>,
+	cx: &mut Context,
+) -> Poll<usize> {
+	loop {
+		if let Some(pending) = delay {
+			ready!(Pin::new(pending).poll(cx));
+			*delay = None;
+		}
+		match bucket.available() {
+			Ok(bytes) => return Poll::Ready(bytes),
+			Err(wait) => *delay = Some(Delay::new(wait)),
+		}
+	}
+}
+
+/// Connection respecting the limits of a [`Throttle`].
+pub struct ThrottledStream<S> {
+	inner: S,
+	throttle: Throttle,
+	read_delay: Option<Delay>,
+	write_delay: Option<Delay>,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ThrottledStream<S> {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context,
+		buf: &mut [u8],
+	) -> Poll<io::Result<usize>> {
+		let this = &mut *self;
+		let bucket = match &this.throttle.download {
+			Some(bucket) => bucket,
+			None => return Pin::new(&mut this.inner).poll_read(cx, buf),
+		};
+		let len = ready!(poll_available(bucket, &mut this.read_delay, cx)).min(buf.len());
+		let read = ready!(Pin::new(&mut this.inner).poll_read(cx, &mut buf[..len]))?;
+		bucket.consume(read);
+		Poll::Ready(Ok(read))
+	}
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ThrottledStream<S> {
+	fn poll_write(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context,
+		buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		let this = &mut *self;
+		let bucket = match &this.throttle.upload {
+			Some(bucket) => bucket,
+			None => return Pin::new(&mut this.inner).poll_write(cx, buf),
+		};
+		let len = ready!(poll_available(bucket, &mut this.write_delay, cx)).min(buf.len());
+		let written = ready!(Pin::new(&mut this.inner).poll_write(cx, &buf[..len]))?;
+		bucket.consume(written);
+		Poll::Ready(Ok(written))
+	}
+
+	fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.inner).poll_flush(cx)
+	}
+
+	fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.inner).poll_close(cx)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{executor::block_on, io::Cursor};
+
+	#[test]
+	fn bucket_is_refilled_at_the_rate() {
+		let bucket = Bucket::new(1000);
+		assert_eq!(bucket.available(), Ok(1000));
+		bucket.consume(1500);
+		let wait = bucket.available().unwrap_err();
+		assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(502));
+
+		std::thread::sleep(Duration::from_millis(600));
+		let available = bucket.available().unwrap();
+		assert!(available > 0 && available < 1000);
+	}
+
+	#[test]
+	fn transfers_are_limited() {
+		let throttle = Throttle::new(Some(100), Some(1000));
+
+		let mut stream = throttle.wrap(Cursor::new(vec![0; 1500]));
+		let started = Instant::now();
+		let mut data = Vec::new();
+		block_on(stream.read_to_end(&mut data)).unwrap();
+		assert_eq!(data.len(), 1500);
+		// one second worth of bytes is available at once, the rest is read at the rate.
+		assert!(started.elapsed() >= Duration::from_millis(450));
+
+		let mut stream = throttle.wrap(Cursor::new(Vec::new()));
+		assert_eq!(block_on(stream.write(&[0; 200])).unwrap(), 100);
+
+		// unlimited directions are untouched.
+		let mut stream = Throttle::default().wrap(Cursor::new(Vec::new()));
+		assert_eq!(block_on(stream.write(&[0; 200])).unwrap(), 200);
+	}
+}
@@ -0,0 +1,33 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Loading of the certificate the `/wss` listeners terminate TLS with.
+
+use libp2p::websocket::tls;
+use sc_tls::invalid_data;
+use std::io;
+
+use crate::config::WebSocketTlsConfig;
+
+/// Loads the certificate and private key of `config`.
+pub fn load(config: &WebSocketTlsConfig) -> io::Result<tls::Config> {
+	let identity = sc_tls::load(&config.cert, &config.key)?;
+	let key = tls::PrivateKey::new(identity.key);
+	let certs = identity.certs.into_iter().map(tls::Certificate::new);
+
+	tls::Config::new(key, certs)
+		.map_err(|e| invalid_data(format!("Invalid TLS certificate or key: {}", e)))
+}
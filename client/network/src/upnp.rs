@@ -0,0 +1,304 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Port mapping with UPnP.
+//!
+//! A background thread requests the router of the local network to forward the listened TCP
+//! ports to the node, and reports the external addresses of the mappings so that they can be
+//! announced to the peers. The mappings are leased, and renewed until the `Mappings` handle is
+//! dropped, which removes them.
+
+use futures::{prelude::*, channel::mpsc};
+use libp2p::{Multiaddr, multiaddr::Protocol};
+use std::{net::Ipv4Addr, pin::Pin, task::{Context, Poll}};
+
+/// Returns the TCP ports of the listened IPv4 addresses, except the loopback ones.
+///
+/// The ports chosen by the system (port 0) are skipped, as the port actually listened on isn't
+/// known from the address.
+fn tcp_ports(listen_addresses: &[Multiaddr]) -> Vec<u16> {
+	let mut ports = Vec::new();
+	for addr in listen_addresses {
+		let mut protocols = addr.iter();
+		match (protocols.next(), protocols.next(), protocols.next()) {
+			(Some(Protocol::Ip4(_)), Some(Protocol::Tcp(0)), None) => log::warn!(
+				target: "sub-libp2p",
+				"UPnP: can't map the port of {}, chosen by the system",
+				addr,
+			),
+			(Some(Protocol::Ip4(ip)), Some(Protocol::Tcp(port)), None)
+				if !ip.is_loopback() && !ports.contains(&port) => ports.push(port),
+			_ => {},
+		}
+	}
+	ports
+}
+
+/// Returns the address through which a port mapped to `ip` is reached.
+fn external_address(ip: Ipv4Addr, port: u16) -> Multiaddr {
+	Multiaddr::empty().with(Protocol::Ip4(ip)).with(Protocol::Tcp(port))
+}
+
+/// Handle to the port mappings, yielding their external addresses as they're made.
+///
+/// Dropping it stops renewing the mappings, and waits for the router to remove them.
+pub struct Mappings {
+	addresses: mpsc::UnboundedReceiver<Multiaddr>,
+	worker: Option<imp::Worker>,
+}
+
+impl Stream for Mappings {
+	type Item = Multiaddr;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		self.addresses.poll_next_unpin(cx)
+	}
+}
+
+impl Drop for Mappings {
+	fn drop(&mut self) {
+		if let Some(worker) = self.worker.take() {
+			worker.stop();
+		}
+	}
+}
+
+/// Starts mapping the TCP ports of the listened IPv4 addresses.
+pub fn start(listen_addresses: &[Multiaddr]) -> Mappings {
+	let (tx, addresses) = mpsc::unbounded();
+	let ports = tcp_ports(listen_addresses);
+	let worker = if ports.is_empty() {
+		log::warn!(target: "sub-libp2p", "UPnP: no listened TCP port to map");
+		None
+	} else {
+		imp::spawn(ports, tx)
+	};
+	Mappings { addresses, worker }
+}
+
+#[cfg(not(target_os = "unknown"))]
+mod imp {
+	use futures::channel::mpsc;
+	use igd::{Gateway, PortMappingProtocol, SearchOptions};
+	use libp2p::Multiaddr;
+	use log::{debug, info, warn};
+	use parking_lot::{Condvar, Mutex};
+	use std::{
+		net::{Ipv4Addr, SocketAddrV4, UdpSocket}, sync::Arc, thread, time::{Duration, Instant},
+	};
+
+	/// Duration of the leases requested for the mappings, in seconds.
+	const LEASE_DURATION: u32 = 60 * 60;
+	/// Interval at which the mappings are renewed.
+	const RENEW_INTERVAL: Duration = Duration::from_secs(20 * 60);
+	/// Interval at which the router is searched again when it isn't found.
+	const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+	/// Description of the mappings shown by the router.
+	const DESCRIPTION: &str = "substrate";
+
+	/// Request to stop the thread, shared with its `Worker`.
+	#[derive(Default)]
+	struct Stop {
+		stopped: Mutex<bool>,
+		condvar: Condvar,
+	}
+
+	impl Stop {
+		fn stop(&self) {
+			*self.stopped.lock() = true;
+			self.condvar.notify_all();
+		}
+
+		/// Waits for `duration`, and returns false if the thread was stopped in the meantime.
+		fn wait(&self, duration: Duration) -> bool {
+			let deadline = Instant::now() + duration;
+			let mut stopped = self.stopped.lock();
+			while !*stopped {
+				if self.condvar.wait_until(&mut stopped, deadline).timed_out() {
+					break;
+				}
+			}
+			!*stopped
+		}
+	}
+
+	/// The thread mapping the ports.
+	pub struct Worker {
+		stop: Arc<Stop>,
+		thread: thread::JoinHandle<()>,
+	}
+
+	impl Worker {
+		/// Stops the thread, and waits for it to remove the mappings.
+		pub fn stop(self) {
+			self.stop.stop();
+			if self.thread.join().is_err() {
+				warn!(target: "sub-libp2p", "UPnP: the mapping thread panicked");
+			}
+		}
+	}
+
+	pub fn spawn(ports: Vec<u16>, tx: mpsc::UnboundedSender<Multiaddr>) -> Option<Worker> {
+		let stop = Arc::new(Stop::default());
+		let thread_stop = stop.clone();
+		let spawned = thread::Builder::new()
+			.name("upnp".into())
+			.spawn(move || run(ports, tx, &thread_stop));
+		match spawned {
+			Ok(thread) => Some(Worker { stop, thread }),
+			Err(err) => {
+				warn!(target: "sub-libp2p", "UPnP: failed to start: {}", err);
+				None
+			}
+		}
+	}
+
+	/// Returns the local address from which the gateway is reached.
+	fn local_ip(gateway: &Gateway) -> Option<Ipv4Addr> {
+		let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+		socket.connect(gateway.addr).ok()?;
+		match socket.local_addr().ok()?.ip() {
+			std::net::IpAddr::V4(ip) => Some(ip),
+			std::net::IpAddr::V6(_) => None,
+		}
+	}
+
+	fn run(ports: Vec<u16>, tx: mpsc::UnboundedSender<Multiaddr>, stop: &Stop) {
+		let mut mapped = Vec::new();
+		let mut reported = Vec::new();
+		let mut gateway = None;
+		loop {
+			if gateway.is_none() {
+				match igd::search_gateway(SearchOptions::default()) {
+					Ok(found) => {
+						debug!(target: "sub-libp2p", "UPnP: found gateway at {}", found.addr);
+						gateway = Some(found);
+					},
+					Err(err) => warn!(target: "sub-libp2p", "UPnP: no gateway found: {}", err),
+				}
+			}
+
+			if let Some(found) = gateway.as_ref() {
+				if !map_ports(found, &ports, &mut mapped, &mut reported, &tx) {
+					// the gateway is searched again, in case it changed.
+					gateway = None;
+				}
+			}
+
+			let wait = if gateway.is_some() { RENEW_INTERVAL } else { RETRY_INTERVAL };
+			if !stop.wait(wait) {
+				break;
+			}
+		}
+
+		if let Some(gateway) = gateway {
+			for port in mapped {
+				if let Err(err) = gateway.remove_port(PortMappingProtocol::TCP, port) {
+					debug!(target: "sub-libp2p", "UPnP: failed to unmap port {}: {}", port, err);
+				}
+			}
+		}
+	}
+
+	/// Maps or renews the mappings of the ports, and reports the external addresses that weren't
+	/// reported yet. Returns false if the gateway can't be used.
+	fn map_ports(
+		gateway: &Gateway,
+		ports: &[u16],
+		mapped: &mut Vec<u16>,
+		reported: &mut Vec<Multiaddr>,
+		tx: &mpsc::UnboundedSender<Multiaddr>,
+	) -> bool {
+		let local_ip = match local_ip(gateway) {
+			Some(ip) => ip,
+			None => {
+				warn!(target: "sub-libp2p", "UPnP: can't find the local address of the gateway");
+				return false
+			}
+		};
+		let external_ip = match gateway.get_external_ip() {
+			Ok(ip) => ip,
+			Err(err) => {
+				warn!(target: "sub-libp2p", "UPnP: failed to get the external address: {}", err);
+				return false
+			}
+		};
+
+		for &port in ports {
+			let local = SocketAddrV4::new(local_ip, port);
+			let result = gateway.add_port(
+				PortMappingProtocol::TCP,
+				port,
+				local,
+				LEASE_DURATION,
+				DESCRIPTION,
+			);
+			if let Err(err) = result {
+				warn!(target: "sub-libp2p", "UPnP: failed to map port {}: {}", port, err);
+				continue;
+			}
+			if !mapped.contains(&port) {
+				mapped.push(port);
+			}
+			let address = super::external_address(external_ip, port);
+			if !reported.contains(&address) {
+				info!(target: "sub-libp2p", "UPnP: mapped {} to {}", address, local);
+				reported.push(address.clone());
+				let _ = tx.unbounded_send(address);
+			}
+		}
+		true
+	}
+}
+
+#[cfg(target_os = "unknown")]
+mod imp {
+	use futures::channel::mpsc;
+	use libp2p::Multiaddr;
+
+	pub enum Worker {}
+
+	impl Worker {
+		pub fn stop(self) {}
+	}
+
+	pub fn spawn(_: Vec<u16>, _: mpsc::UnboundedSender<Multiaddr>) -> Option<Worker> {
+		log::warn!(target: "sub-libp2p", "UPnP is not available on this platform");
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn listened_tcp_ports_are_mapped() {
+		let listen_addresses = vec![
+			"/ip4/0.0.0.0/tcp/30333".parse().unwrap(),
+			"/ip6/::/tcp/30333".parse().unwrap(),
+			"/ip4/127.0.0.1/tcp/30336".parse().unwrap(),
+			"/ip4/0.0.0.0/tcp/30334/ws".parse().unwrap(),
+			"/ip4/0.0.0.0/tcp/30335".parse().unwrap(),
+			"/ip4/0.0.0.0/tcp/0".parse().unwrap(),
+		];
+		assert_eq!(tcp_ports(&listen_addresses), vec![30333, 30335]);
+		assert_eq!(
+			external_address(Ipv4Addr::new(198, 51, 100, 19), 30333),
+			"/ip4/198.51.100.19/tcp/30333".parse().unwrap(),
+		);
+	}
+}
@@ -217,6 +217,11 @@ impl<D> Peer<D> {
 		self.num_peers() == 0
 	}
 
+	/// Returns true if we have peers, but receive no block from them.
+	pub fn is_isolated(&self) -> bool {
+		sp_consensus::SyncOracle::is_isolated(&mut &**self.network.service())
+	}
+
 	/// Request a justification for the given block.
 	pub fn request_justification(&self, hash: &<Block as BlockT>::Hash, number: NumberFor<Block>) {
 		self.network.service().request_justification(hash, number);
@@ -142,6 +142,43 @@ fn syncing_node_not_major_syncing_when_disconnected() {
 	}));
 }
 
+#[test]
+fn node_building_blocks_alone_is_isolated() {
+	let _ = ::env_logger::try_init();
+	let mut net = TestNet::new(2);
+	block_on(futures::future::poll_fn::<(), _>(|cx| {
+		net.poll(cx);
+		if net.peer(0).num_peers() != 1 || net.peer(1).num_peers() != 1 {
+			return Poll::Pending
+		}
+		Poll::Ready(())
+	}));
+	assert!(!net.peer(0).is_isolated());
+
+	// Our blocks are announced, but the peer has none to send us.
+	net.peer(0).push_blocks(5, false);
+	block_on(futures::future::poll_fn::<(), _>(|cx| {
+		net.poll(cx);
+		if net.peer(0).is_isolated() {
+			Poll::Ready(())
+		} else {
+			Poll::Pending
+		}
+	}));
+	assert!(!net.peer(1).is_isolated());
+
+	net.block_until_sync();
+	net.peer(1).push_blocks(1, false);
+	block_on(futures::future::poll_fn::<(), _>(|cx| {
+		net.poll(cx);
+		if net.peer(0).is_isolated() {
+			Poll::Pending
+		} else {
+			Poll::Ready(())
+		}
+	}));
+}
+
 #[test]
 fn sync_from_two_peers_works() {
 	let _ = ::env_logger::try_init();
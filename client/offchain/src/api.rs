@@ -23,7 +23,6 @@ use std::{
 
 use sp_core::offchain::OffchainStorage;
 use futures::Future;
-use log::error;
 use sc_network::{PeerId, Multiaddr, NetworkStateInfo};
 use codec::{Encode, Decode};
 use sp_core::offchain::{
@@ -31,6 +30,7 @@ use sp_core::offchain::{
 	OpaqueNetworkState, OpaquePeerId, OpaqueMultiaddr, StorageKind,
 };
 pub use sp_offchain::STORAGE_PREFIX;
+use sp_offchain::storage_prefix;
 
 #[cfg(not(target_os = "unknown"))]
 mod http;
@@ -56,16 +56,6 @@ pub(crate) struct Api<Storage> {
 	http: http::HttpApi,
 }
 
-fn unavailable_yet<R: Default>(name: &str) -> R {
-	error!(
-		"The {:?} API is not available for offchain workers yet. Follow \
-		https://github.com/paritytech/substrate/issues/1458 for details", name
-	);
-	Default::default()
-}
-
-const LOCAL_DB: &str = "LOCAL (fork-aware) DB";
-
 impl<Storage: OffchainStorage> OffchainExt for Api<Storage> {
 	fn is_validator(&self) -> bool {
 		self.is_validator
@@ -94,10 +84,7 @@ impl<Storage: OffchainStorage> OffchainExt for Api<Storage> {
 	}
 
 	fn local_storage_set(&mut self, kind: StorageKind, key: &[u8], value: &[u8]) {
-		match kind {
-			StorageKind::PERSISTENT => self.db.set(STORAGE_PREFIX, key, value),
-			StorageKind::LOCAL => unavailable_yet(LOCAL_DB),
-		}
+		self.db.set(storage_prefix(kind), key, value)
 	}
 
 	fn local_storage_compare_and_set(
@@ -107,19 +94,11 @@ impl<Storage: OffchainStorage> OffchainExt for Api<Storage> {
 		old_value: Option<&[u8]>,
 		new_value: &[u8],
 	) -> bool {
-		match kind {
-			StorageKind::PERSISTENT => {
-				self.db.compare_and_set(STORAGE_PREFIX, key, old_value, new_value)
-			},
-			StorageKind::LOCAL => unavailable_yet(LOCAL_DB),
-		}
+		self.db.compare_and_set(storage_prefix(kind), key, old_value, new_value)
 	}
 
 	fn local_storage_get(&mut self, kind: StorageKind, key: &[u8]) -> Option<Vec<u8>> {
-		match kind {
-			StorageKind::PERSISTENT => self.db.get(STORAGE_PREFIX, key),
-			StorageKind::LOCAL => unavailable_yet(LOCAL_DB),
-		}
+		self.db.get(storage_prefix(kind), key)
 	}
 
 	fn http_request_start(
@@ -358,6 +337,22 @@ mod tests {
 		assert_eq!(api.local_storage_get(kind, key), Some(b"value".to_vec()));
 	}
 
+	#[test]
+	fn should_keep_storage_kinds_apart() {
+		// given
+		let mut api = offchain_api().0;
+		let key = b"test";
+
+		// when
+		api.local_storage_set(StorageKind::LOCAL, key, b"local");
+		api.local_storage_set(StorageKind::PERSISTENT, key, b"persistent");
+
+		// then
+		assert_eq!(api.local_storage_get(StorageKind::LOCAL, key), Some(b"local".to_vec()));
+		let persistent = api.local_storage_get(StorageKind::PERSISTENT, key);
+		assert_eq!(persistent, Some(b"persistent".to_vec()));
+	}
+
 	#[test]
 	fn should_compare_and_set_local_storage() {
 		// given
@@ -290,10 +290,13 @@ impl Peerset {
 		// We want reputations to be up-to-date before adjusting them.
 		self.update_time();
 
+		// The reserved nodes are connected to whatever their reputation, and are thus never
+		// disconnected because of it.
+		let is_reserved = self.is_reserved(&peer_id);
 		match self.data.peer(&peer_id) {
 			peersstate::Peer::Connected(mut peer) => {
 				peer.add_reputation(change.value);
-				if peer.reputation() < BANNED_THRESHOLD {
+				if peer.reputation() < BANNED_THRESHOLD && !is_reserved {
 					debug!(target: "peerset", "Report {}: {:+} to {}. Reason: {}, Disconnecting",
 						peer_id, change.value, peer.reputation(), change.reason
 					);
@@ -310,6 +313,12 @@ impl Peerset {
 		}
 	}
 
+	/// Returns true if `peer_id` is one of the reserved nodes.
+	fn is_reserved(&self, peer_id: &PeerId) -> bool {
+		self.data.get_priority_group(RESERVED_NODES)
+			.map_or(false, |reserved| reserved.contains(peer_id))
+	}
+
 	/// Updates the value of `self.latest_time_update` and performs all the updates that happen
 	/// over time, such as reputation increases for staying connected.
 	fn update_time(&mut self) {
@@ -421,6 +430,8 @@ impl Peerset {
 		trace!(target: "peerset", "Incoming {:?}", peer_id);
 		self.update_time();
 
+		let is_reserved = self.is_reserved(&peer_id);
+
 		let not_connected = match self.data.peer(&peer_id) {
 			// If we're already connected, don't answer, as the docs mention.
 			peersstate::Peer::Connected(_) => return,
@@ -431,7 +442,7 @@ impl Peerset {
 			peersstate::Peer::Unknown(entry) => entry.discover(),
 		};
 
-		if not_connected.reputation() < BANNED_THRESHOLD {
+		if not_connected.reputation() < BANNED_THRESHOLD && !is_reserved {
 			self.message_queue.push_back(Message::Reject(index));
 			return
 		}
@@ -518,6 +529,21 @@ impl Peerset {
 		})
 	}
 
+	/// Returns the peers that we have discovered, with their reputation.
+	pub fn reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.update_time();
+
+		self.data.peers().cloned().collect::<Vec<_>>().into_iter().map(|peer_id| {
+			let reputation = match self.data.peer(&peer_id) {
+				peersstate::Peer::Connected(entry) => entry.reputation(),
+				peersstate::Peer::NotConnected(entry) => entry.reputation(),
+				peersstate::Peer::Unknown(_) =>
+					unreachable!("We iterate over the known peers; QED")
+			};
+			(peer_id, reputation)
+		}).collect()
+	}
+
 	/// Returns the number of peers that we have discovered.
 	pub fn num_discovered_peers(&self) -> usize {
 		self.data.peers().len()
@@ -666,6 +692,31 @@ mod tests {
 		]);
 	}
 
+	#[test]
+	fn test_peerset_reserved_peers_are_not_banned() {
+		let reserved_peer = PeerId::random();
+		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
+			in_peers: 0,
+			out_peers: 0,
+			bootnodes: vec![],
+			reserved_only: true,
+			reserved_nodes: vec![reserved_peer.clone()],
+		});
+		handle.report_peer(reserved_peer.clone(), ReputationChange::new(BANNED_THRESHOLD - 1, ""));
+
+		let fut = futures::future::poll_fn(move |cx| {
+			assert_eq!(
+				Stream::poll_next(Pin::new(&mut peerset), cx),
+				Poll::Ready(Some(Message::Connect(reserved_peer.clone()))),
+			);
+			// The report is processed, and the reserved peer isn't dropped.
+			assert_eq!(Stream::poll_next(Pin::new(&mut peerset), cx), Poll::Pending);
+			Poll::Ready(())
+		});
+
+		futures::executor::block_on(fut);
+	}
+
 	#[test]
 	fn test_peerset_banned() {
 		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
@@ -0,0 +1,125 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Substrate archive API.
+//!
+//! Expensive queries over the state are run as background jobs. Starting a query returns the id
+//! of its job, whose progress can then be polled and whose results are fetched in pages.
+
+pub mod error;
+
+use jsonrpc_derive::rpc;
+use serde::{Serialize, Deserialize};
+use sp_core::storage::{StorageKey, StorageData, StorageChangeSet};
+use self::error::Result;
+
+pub use self::gen_client::Client as ArchiveClient;
+
+/// Identifier of a query job.
+pub type JobId = u64;
+
+/// A query run as a background job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Query<Hash> {
+	/// The keys starting with `prefix` at block `at`, or at the best block if `None`.
+	Keys {
+		/// Prefix of the keys.
+		prefix: StorageKey,
+		/// Block whose state is queried.
+		at: Option<Hash>,
+	},
+	/// The key/value pairs whose key starts with `prefix` at block `at`, or at the best block if
+	/// `None`.
+	Pairs {
+		/// Prefix of the keys.
+		prefix: StorageKey,
+		/// Block whose state is queried.
+		at: Option<Hash>,
+	},
+	/// The changes of the values of `keys` from block `from` to block `to`, or to the best block
+	/// if `None`.
+	///
+	/// Like `state_queryStorage`, the first change set contains the values at `from`.
+	Range {
+		/// Keys whose changes are queried.
+		keys: Vec<StorageKey>,
+		/// First block of the range.
+		from: Hash,
+		/// Last block of the range.
+		to: Option<Hash>,
+	},
+}
+
+/// A result of a query job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JobItem<Hash> {
+	/// A key, for `Keys` queries.
+	Key(StorageKey),
+	/// A key/value pair, for `Pairs` queries.
+	Pair(StorageKey, StorageData),
+	/// The changes at a block, for `Range` queries.
+	ChangeSet(StorageChangeSet<Hash>),
+}
+
+/// State of a query job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+	/// The job is still running.
+	Running,
+	/// The job completed, all its results are available.
+	Done,
+	/// The job failed with the given error. The results found until then are available.
+	Failed(String),
+}
+
+/// Progress of a query job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+	/// State of the job.
+	pub state: JobState,
+	/// Number of keys scanned, or of blocks walked for `Range` queries.
+	pub processed: u64,
+	/// Total number of blocks to walk for `Range` queries, once known.
+	pub total: Option<u64>,
+	/// Number of results available.
+	pub results: u64,
+}
+
+/// Substrate archive RPC API
+#[rpc]
+pub trait ArchiveApi<Hash> {
+	/// Start a query job in the background, and return its id.
+	#[rpc(name = "archive_startQuery")]
+	fn start_query(&self, query: Query<Hash>) -> Result<JobId>;
+
+	/// Returns the progress of a query job.
+	#[rpc(name = "archive_jobStatus")]
+	fn job_status(&self, id: JobId) -> Result<JobStatus>;
+
+	/// Returns at most `count` results of a query job, starting at the result with index `start`.
+	#[rpc(name = "archive_jobResults")]
+	fn job_results(&self, id: JobId, start: u64, count: u32) -> Result<Vec<JobItem<Hash>>>;
+
+	/// Stop a query job if it is still running, and discard its results.
+	///
+	/// Returns `false` if there is no job with the given id.
+	#[rpc(name = "archive_removeJob")]
+	fn remove_job(&self, id: JobId) -> Result<bool>;
+}
@@ -16,7 +16,7 @@
 
 //! Authoring RPC module errors.
 
-use crate::errors;
+use crate::{errors, policy::UnsafeRpcError};
 use jsonrpc_core as rpc;
 
 /// Author RPC Result type.
@@ -42,6 +42,14 @@ pub enum Error {
 	/// Incorrect extrinsic format.
 	#[display(fmt="Invalid extrinsic format: {}", _0)]
 	BadFormat(codec::Error),
+	/// Extrinsic is larger than the node accepts.
+	#[display(fmt="Extrinsic of {} bytes exceeds the limit of {} bytes", size, max)]
+	ExtrinsicTooLarge {
+		/// Size of the encoded extrinsic.
+		size: usize,
+		/// Maximum accepted size.
+		max: usize,
+	},
 	/// Incorrect seed phrase.
 	#[display(fmt="Invalid seed phrase/SURI")]
 	BadSeedPhrase,
@@ -57,6 +65,8 @@ pub enum Error {
 	/// Invalid session keys encoding.
 	#[display(fmt="Session keys are not encoded correctly")]
 	InvalidSessionKeys,
+	/// Call to an unsafe RPC was denied.
+	UnsafeRpcCalled(UnsafeRpcError),
 }
 
 impl std::error::Error for Error {
@@ -65,6 +75,7 @@ impl std::error::Error for Error {
 			Error::Client(ref err) => Some(&**err),
 			Error::Pool(ref err) => Some(err),
 			Error::Verification(ref err) => Some(&**err),
+			Error::UnsafeRpcCalled(ref err) => Some(err),
 			_ => None,
 		}
 	}
@@ -76,6 +87,8 @@ const BASE_ERROR: i64 = 1000;
 const BAD_FORMAT: i64 = BASE_ERROR + 1;
 /// Error during transaction verification in runtime.
 const VERIFICATION_ERROR: i64 = BASE_ERROR + 2;
+/// Extrinsic is larger than the node accepts.
+const EXTRINSIC_TOO_LARGE: i64 = BASE_ERROR + 3;
 
 /// Pool rejected the transaction as invalid
 const POOL_INVALID_TX: i64 = BASE_ERROR + 10;
@@ -104,6 +117,11 @@ impl From<Error> for rpc::Error {
 				message: format!("Extrinsic has invalid format: {}", e).into(),
 				data: None,
 			},
+			Error::ExtrinsicTooLarge { .. } => rpc::Error {
+				code: rpc::ErrorCode::ServerError(EXTRINSIC_TOO_LARGE),
+				message: format!("{}", e),
+				data: None,
+			},
 			Error::Verification(e) => rpc::Error {
 				code: rpc::ErrorCode::ServerError(VERIFICATION_ERROR),
 				message: format!("Verification Error: {}", e).into(),
@@ -152,6 +170,8 @@ impl From<Error> for rpc::Error {
 					request to insert the key successfully.".into()
 				),
 			},
+			Error::UnsafeRpcCalled(e) => e.into(),
+			Error::Client(e) => errors::client(e),
 			e => errors::internal(e),
 		}
 	}
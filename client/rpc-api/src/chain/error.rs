@@ -17,7 +17,7 @@
 
 //! Error helpers for Chain RPC module.
 
-use crate::errors;
+use crate::{errors, policy::UnsafeRpcError};
 use jsonrpc_core as rpc;
 
 /// Chain RPC Result type.
@@ -34,12 +34,15 @@ pub enum Error {
 	Client(Box<dyn std::error::Error + Send>),
 	/// Other error type.
 	Other(String),
+	/// Call to an unsafe RPC was denied.
+	UnsafeRpcCalled(UnsafeRpcError),
 }
 
 impl std::error::Error for Error {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
 			Error::Client(ref err) => Some(&**err),
+			Error::UnsafeRpcCalled(ref err) => Some(err),
 			_ => None,
 		}
 	}
@@ -56,6 +59,8 @@ impl From<Error> for rpc::Error {
 				message,
 				data: None,
 			},
+			Error::UnsafeRpcCalled(e) => e.into(),
+			Error::Client(e) => errors::client(e),
 			e => errors::internal(e),
 		}
 	}
@@ -0,0 +1,85 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Substrate blockchain API helpers.
+
+use serde::{Serialize, Deserialize};
+
+/// Finality status of a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FinalityStatus {
+	/// The block is finalized.
+	Finalized,
+	/// The block is above the last finalized block, and may still get finalized.
+	Unfinalized,
+	/// The block is on a fork that diverged from the finalized chain, and will never get finalized.
+	Stale,
+}
+
+/// A fork containing a block.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkLocation<Hash, Number> {
+	/// Leaf (head) of the fork.
+	pub leaf: Hash,
+	/// Number of blocks between the leaf of the fork and the block.
+	pub depth: Number,
+}
+
+/// Statistics of a block.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockStats {
+	/// Number of extrinsics in the block.
+	pub extrinsics: u64,
+	/// Length of the encoded block, in bytes.
+	pub length: u64,
+	/// Weight declared by the extrinsics, if the runtime reports it through the `BlockWeight` api.
+	pub weight: Option<u64>,
+	/// Size of the storage proof needed to re-execute the block, in bytes. `None` if the state of
+	/// its parent is pruned.
+	pub witness_size: Option<u64>,
+	/// Time this node took to execute the block when importing it, in nanoseconds. `None` if the
+	/// block wasn't executed since the node started, e.g. when it authored the block.
+	pub import_time: Option<u64>,
+}
+
+/// Location of a block within the tree of known blocks.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockLocation<Hash, Number> {
+	/// Block hash
+	pub hash: Hash,
+	/// Block number
+	pub number: Number,
+	/// Whether the block is part of the canon chain.
+	pub canonical: bool,
+	/// Finality status of the block.
+	pub finality: FinalityStatus,
+	/// Forks containing the block.
+	pub forks: Vec<ForkLocation<Hash, Number>>,
+}
+
+/// Location of an extrinsic within the tree of known blocks.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtrinsicLocation<Hash, Number> {
+	/// Block containing the extrinsic.
+	pub block: BlockLocation<Hash, Number>,
+	/// Index of the extrinsic within the block.
+	pub index: u32,
+}
@@ -17,6 +17,7 @@
 //! Substrate blockchain API.
 
 pub mod error;
+pub mod helpers;
 
 use jsonrpc_core::Result as RpcResult;
 use jsonrpc_core::futures::Future;
@@ -26,6 +27,9 @@ use sp_rpc::{number::NumberOrHex, list::ListOrValue};
 use self::error::{FutureResult, Result};
 
 pub use self::gen_client::Client as ChainClient;
+pub use self::helpers::{
+	BlockLocation, BlockStats, ExtrinsicLocation, FinalityStatus, ForkLocation,
+};
 
 /// Substrate blockchain API
 #[rpc]
@@ -54,6 +58,26 @@ pub trait ChainApi<Number, Hash, Header, SignedBlock> {
 	#[rpc(name = "chain_getFinalizedHead", alias("chain_getFinalisedHead"))]
 	fn finalized_head(&self) -> Result<Hash>;
 
+	/// Locate a block on any known fork, not only on the canon chain.
+	///
+	/// Returns `None` if the block is unknown.
+	#[rpc(name = "chain_findBlock")]
+	fn find_block(&self, hash: Hash) -> Result<Option<BlockLocation<Hash, Number>>>;
+
+	/// Locate the blocks containing the given extrinsic on any known fork.
+	///
+	/// Only the blocks that aren't finalized are searched, starting from the heads of the known
+	/// forks.
+	#[rpc(name = "chain_findExtrinsic")]
+	fn find_extrinsic(&self, hash: Hash) -> Result<Vec<ExtrinsicLocation<Hash, Number>>>;
+
+	/// Get the statistics of a block: its extrinsic count, length, declared weight, the size of
+	/// the witness needed to re-execute it and the time its import took.
+	///
+	/// The block is re-executed to measure its witness. This method is unsafe.
+	#[rpc(name = "chain_getBlockStats")]
+	fn block_stats(&self, hash: Hash) -> Result<Option<BlockStats>>;
+
 	/// All head subscription
 	#[pubsub(subscription = "chain_allHead", subscribe, name = "chain_subscribeAllHeads")]
 	fn subscribe_all_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Header>);
@@ -16,6 +16,9 @@
 
 use log::warn;
 
+/// Base code of the errors of the client, offset by the code of their category.
+const CLIENT_BASE_ERROR: i64 = 9000;
+
 pub fn internal<E: ::std::fmt::Debug>(e: E) -> jsonrpc_core::Error {
 	warn!("Unknown error: {:?}", e);
 	jsonrpc_core::Error {
@@ -24,3 +27,20 @@ pub fn internal<E: ::std::fmt::Debug>(e: E) -> jsonrpc_core::Error {
 		data: Some(format!("{:?}", e).into()),
 	}
 }
+
+/// Converts an error of the client to an RPC error whose code identifies the category of the
+/// error, e.g. `9014` for database errors. Other errors are internal errors.
+pub fn client(e: Box<dyn std::error::Error + Send>) -> jsonrpc_core::Error {
+	match e.downcast_ref::<sp_blockchain::Error>() {
+		Some(error) => {
+			let kind = error.kind();
+			let code = CLIENT_BASE_ERROR + i64::from(kind.code());
+			jsonrpc_core::Error {
+				code: jsonrpc_core::ErrorCode::ServerError(code),
+				message: error.to_string(),
+				data: Some(kind.name().into()),
+			}
+		},
+		None => internal(e),
+	}
+}
@@ -28,10 +28,15 @@ pub use self::gen_client::Client as OffchainClient;
 #[rpc]
 pub trait OffchainApi {
 	/// Set offchain local storage under given key and prefix.
+	///
+	/// The values of the `LOCAL` kind are kept apart from the `PERSISTENT` ones, but aren't
+	/// fork-aware yet. This method is unsafe.
 	#[rpc(name = "offchain_localStorageSet")]
 	fn set_local_storage(&self, kind: StorageKind, key: Bytes, value: Bytes) -> Result<()>;
 
 	/// Get offchain local storage under given key and prefix.
+	///
+	/// This method is unsafe.
 	#[rpc(name = "offchain_localStorageGet")]
 	fn get_local_storage(&self, kind: StorageKind, key: Bytes) -> Result<Option<Bytes>>;
 }
@@ -16,7 +16,7 @@
 
 //! State RPC errors.
 
-use crate::errors;
+use crate::{errors, policy::UnsafeRpcError};
 use jsonrpc_core as rpc;
 
 /// State RPC Result type.
@@ -49,12 +49,15 @@ pub enum Error {
 		/// Maximum allowed value
 		max: u32,
 	},
+	/// Call to an unsafe RPC was denied.
+	UnsafeRpcCalled(UnsafeRpcError),
 }
 
 impl std::error::Error for Error {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
 			Error::Client(ref err) => Some(&**err),
+			Error::UnsafeRpcCalled(ref err) => Some(err),
 			_ => None,
 		}
 	}
@@ -76,6 +79,8 @@ impl From<Error> for rpc::Error {
 				message: format!("{}", e),
 				data: None,
 			},
+			Error::UnsafeRpcCalled(e) => e.into(),
+			Error::Client(e) => errors::client(e),
 			e => errors::internal(e),
 		}
 	}
@@ -24,6 +24,7 @@ use jsonrpc_derive::rpc;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use sp_core::Bytes;
 use sp_core::storage::{StorageKey, StorageData, StorageChangeSet};
+use sp_rpc::tracing::BlockTrace;
 use sp_version::RuntimeVersion;
 use self::error::FutureResult;
 
@@ -39,6 +40,21 @@ pub trait StateApi<Hash> {
 	#[rpc(name = "state_call", alias("state_callAt"))]
 	fn call(&self, name: String, bytes: Bytes, hash: Option<Hash>) -> FutureResult<Bytes>;
 
+	/// Call several runtime methods, given as `(name, bytes)` pairs, at a block's state.
+	///
+	/// The calls are executed in order on top of the same state, the changes made by a call being
+	/// visible to the next ones. Returns the result of each call, or the first error.
+	#[rpc(name = "state_callBatch")]
+	fn call_batch(&self, calls: Vec<(String, Bytes)>, hash: Option<Hash>) -> FutureResult<Vec<Bytes>>;
+
+	/// Re-executes a block on top of its parent state, and returns the spans of the given comma
+	/// separated targets entered during the execution, along with every storage access made.
+	///
+	/// Spans of all the targets are returned if `targets` is `None`. Spans are only emitted by the
+	/// native runtime. This method is unsafe.
+	#[rpc(name = "state_traceBlock")]
+	fn trace_block(&self, block: Hash, targets: Option<String>) -> FutureResult<BlockTrace>;
+
 	/// DEPRECATED: Please use `state_getKeysPaged` with proper paging support.
 	/// Returns the keys with prefix, leave empty to get all the keys.
 	#[rpc(name = "state_getKeys")]
@@ -165,4 +181,31 @@ pub trait StateApi<Hash> {
 	fn unsubscribe_storage(
 		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
 	) -> RpcResult<bool>;
+
+	/// New storage subscription to the keys starting with one of the given prefixes.
+	///
+	/// No initial values are sent: each notification only contains the entries under the
+	/// prefixes changed by a block, with `null` for the removed ones. Blocks changing none of
+	/// them are not notified.
+	#[pubsub(
+		subscription = "state_storagePrefix",
+		subscribe,
+		name = "state_subscribeStoragePrefix"
+	)]
+	fn subscribe_storage_prefix(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Hash>>,
+		prefixes: Vec<StorageKey>,
+	);
+
+	/// Unsubscribe from storage prefix subscription
+	#[pubsub(
+		subscription = "state_storagePrefix",
+		unsubscribe,
+		name = "state_unsubscribeStoragePrefix"
+	)]
+	fn unsubscribe_storage_prefix(
+		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
+	) -> RpcResult<bool>;
 }
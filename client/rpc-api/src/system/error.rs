@@ -16,7 +16,7 @@
 
 //! System RPC module errors.
 
-use crate::system::helpers::Health;
+use crate::{policy::UnsafeRpcError, system::helpers::Health};
 use jsonrpc_core as rpc;
 
 /// System RPC Result type.
@@ -30,6 +30,11 @@ pub enum Error {
 	NotHealthy(Health),
 	/// Peer argument is malformatted.
 	MalformattedPeerArg(String),
+	/// Call to an unsafe RPC was denied.
+	UnsafeRpcCalled(UnsafeRpcError),
+	/// Signing with the network key failed.
+	#[display(fmt = "Failed to sign with the network key: {}", _0)]
+	SigningFailed(String),
 }
 
 impl std::error::Error for Error {}
@@ -49,7 +54,13 @@ impl From<Error> for rpc::Error {
 				code :rpc::ErrorCode::ServerError(BASE_ERROR + 2),
 				message: e.clone(),
 				data: None,
-			}
+			},
+			Error::UnsafeRpcCalled(e) => e.into(),
+			Error::SigningFailed(_) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 3),
+				message: format!("{}", e),
+				data: None,
+			},
 		}
 	}
 }
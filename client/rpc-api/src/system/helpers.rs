@@ -19,6 +19,7 @@
 use std::fmt;
 use serde::{Serialize, Deserialize};
 use serde_json::{Value, map::Map};
+use sp_core::Bytes;
 
 /// Node properties
 pub type Properties = Map<String, Value>;
@@ -36,6 +37,29 @@ pub struct SystemInfo {
 	pub properties: Properties,
 }
 
+/// Prefix of the challenges signed by `system_attestIdentity`.
+///
+/// Keeps the signatures of arbitrary challenges from being valid for other uses of the network
+/// key.
+pub const IDENTITY_ATTESTATION_PREFIX: &[u8] = b"substrate-rpc-identity-attestation:";
+
+/// Returns the message signed by `system_attestIdentity` for `challenge`.
+pub fn identity_attestation_message(challenge: &[u8]) -> Vec<u8> {
+	[IDENTITY_ATTESTATION_PREFIX, challenge].concat()
+}
+
+/// Proof that the node holds the network key of its `PeerId`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityAttestation {
+	/// `PeerId` of the node, in base58.
+	pub peer_id: String,
+	/// Protobuf encoded public network key of the node, from which `peer_id` is derived.
+	pub public_key: Bytes,
+	/// Signature of `identity_attestation_message(challenge)` by the network key.
+	pub signature: Bytes,
+}
+
 /// Health struct returned by the RPC
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -72,6 +96,33 @@ pub struct PeerInfo<Hash, Number> {
 	pub best_hash: Hash,
 	/// Peer best block number
 	pub best_number: Number,
+	/// Direction of the connection, `None` if unknown
+	pub direction: Option<PeerDirection>,
+}
+
+/// Direction of the connection to a peer
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PeerDirection {
+	/// The node dialed the peer
+	Outbound,
+	/// The peer dialed the node
+	Inbound,
+}
+
+/// Progress of the sync of the chain.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncState<Number> {
+	/// Best block number when the sync started.
+	pub starting_block: Number,
+	/// Current best block number.
+	pub current_block: Number,
+	/// Best block number announced by the peers, if any.
+	pub highest_block: Option<Number>,
+	/// Estimated number of seconds left to reach `highest_block`, only while catching up with
+	/// the chain and once the import rate is known.
+	pub eta_secs: Option<u64>,
 }
 
 /// The role the node is running as
@@ -112,8 +163,22 @@ mod tests {
 				protocol_version: 2,
 				best_hash: 5u32,
 				best_number: 6u32,
+				direction: Some(PeerDirection::Inbound),
+			}).unwrap(),
+			r#"{"peerId":"2","roles":"a","protocolVersion":2,"bestHash":5,"bestNumber":6,"direction":"inbound"}"#,
+		);
+	}
+
+	#[test]
+	fn should_serialize_sync_state() {
+		assert_eq!(
+			::serde_json::to_string(&SyncState {
+				starting_block: 1u32,
+				current_block: 2u32,
+				highest_block: Some(3u32),
+				eta_secs: None,
 			}).unwrap(),
-			r#"{"peerId":"2","roles":"a","protocolVersion":2,"bestHash":5,"bestNumber":6}"#,
+			r#"{"startingBlock":1,"currentBlock":2,"highestBlock":3,"etaSecs":null}"#,
 		);
 	}
 }
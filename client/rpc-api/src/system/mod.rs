@@ -22,10 +22,14 @@ pub mod helpers;
 use crate::helpers::Receiver;
 use jsonrpc_derive::rpc;
 use futures::{future::BoxFuture, compat::Compat};
+use sp_core::Bytes;
 
 use self::error::Result as SystemResult;
 
-pub use self::helpers::{Properties, SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{
+	Properties, SystemInfo, Health, PeerInfo, PeerDirection, NodeRole, IdentityAttestation,
+	SyncState, IDENTITY_ATTESTATION_PREFIX, identity_attestation_message,
+};
 pub use self::gen_client::Client as SystemClient;
 
 /// Substrate system RPC API
@@ -55,7 +59,8 @@ pub trait SystemApi<Hash, Number> {
 	#[rpc(name = "system_health", returns = "Health")]
 	fn system_health(&self) -> Receiver<Health>;
 
-	/// Returns currently connected peers
+	/// Returns currently connected peers, with their roles, best block, protocol version and the
+	/// direction of the connection.
 	#[rpc(name = "system_peers", returns = "Vec<PeerInfo<Hash, Number>>")]
 	fn system_peers(&self) -> Receiver<Vec<PeerInfo<Hash, Number>>>;
 
@@ -81,7 +86,43 @@ pub trait SystemApi<Hash, Number> {
 	fn system_remove_reserved_peer(&self, peer_id: String)
 		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
 
+	/// Refuses the connections with a peer, given by PeerId or by IP address range in the CIDR
+	/// notation, e.g. `192.0.2.0/24`. The peer is disconnected if given by PeerId.
+	#[rpc(name = "system_addDeniedPeer", returns = "()")]
+	fn system_add_denied_peer(&self, peer: String)
+		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
+
+	/// Removes a peer from the denied ones. The string should be the one that was added.
+	#[rpc(name = "system_removeDeniedPeer", returns = "()")]
+	fn system_remove_denied_peer(&self, peer: String)
+		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
+
+	/// Accepts the connections with a peer, given by PeerId or by IP address range in the CIDR
+	/// notation. Once a peer is allowed, only the connections with the allowed peers are
+	/// accepted.
+	#[rpc(name = "system_addAllowedPeer", returns = "()")]
+	fn system_add_allowed_peer(&self, peer: String)
+		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
+
+	/// Removes a peer from the allowed ones. The string should be the one that was added.
+	#[rpc(name = "system_removeAllowedPeer", returns = "()")]
+	fn system_remove_allowed_peer(&self, peer: String)
+		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
+
 	/// Returns the roles the node is running as.
 	#[rpc(name = "system_nodeRoles", returns = "Vec<NodeRole>")]
 	fn system_node_roles(&self) -> Receiver<Vec<NodeRole>>;
+
+	/// Signs the given challenge with the network key of the node.
+	///
+	/// Proves that the node answering holds the key of its `PeerId`. The challenge should be
+	/// random, so that the answer can't have been recorded beforehand. It is prefixed with
+	/// `IDENTITY_ATTESTATION_PREFIX` before being signed.
+	#[rpc(name = "system_attestIdentity", returns = "IdentityAttestation")]
+	fn system_attest_identity(&self, challenge: Bytes)
+		-> Compat<BoxFuture<'static, Result<IdentityAttestation, jsonrpc_core::Error>>>;
+
+	/// Returns the progress of the sync of the chain.
+	#[rpc(name = "system_syncState", returns = "SyncState<Number>")]
+	fn system_sync_state(&self) -> Receiver<SyncState<Number>>;
 }
@@ -18,14 +18,23 @@
 
 #![warn(missing_docs)]
 
-use std::io;
+mod limits;
+mod metrics;
+#[cfg(not(target_os = "unknown"))]
+mod proxy;
+mod rate_limit;
+#[cfg(not(target_os = "unknown"))]
+mod tls;
+#[cfg(not(target_os = "unknown"))]
+mod ws_deflate;
+#[cfg(unix)]
+mod worker_bridge;
+
+use std::{io, path::PathBuf};
 use jsonrpc_core::IoHandlerExtension;
 use log::error;
 use pubsub::PubSubMetadata;
 
-/// Maximal payload accepted by RPC servers.
-const MAX_PAYLOAD: usize = 15 * 1024 * 1024;
-
 /// Default maximum number of connections for WS RPC servers.
 const WS_MAX_CONNECTIONS: usize = 100;
 
@@ -33,6 +42,20 @@ const WS_MAX_CONNECTIONS: usize = 100;
 pub type RpcHandler<T> = pubsub::PubSubHandler<T>;
 
 pub use self::inner::*;
+pub use self::limits::{LimitsMiddleware, RpcLimits, LIMIT_ERROR_CODE};
+pub use self::metrics::{MetricsMiddleware, RpcMetrics};
+pub use self::rate_limit::{RateLimit, RateLimitMiddleware, RemoteAddress, RATE_LIMIT_ERROR_CODE};
+#[cfg(unix)]
+pub use self::worker_bridge::{WorkerBridge, WORKER_METHODS};
+
+/// Certificate and private key the RPC servers terminate TLS with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+	/// Path to the PEM encoded certificate chain.
+	pub cert: PathBuf,
+	/// Path to the PEM encoded private key, in PKCS#8 or RSA format.
+	pub key: PathBuf,
+}
 
 /// Construct rpc `IoHandler`
 pub fn rpc_handler<M: PubSubMetadata>(
@@ -56,52 +79,150 @@ pub fn rpc_handler<M: PubSubMetadata>(
 	io
 }
 
+/// Copy of an RPC `IoHandler` enforcing the given limits and rate limit, and updating the
+/// `metrics` of `transport`.
+fn limited<M: PubSubMetadata + RemoteAddress>(
+	io: RpcHandler<M>,
+	limits: RpcLimits,
+	rate_limit: Option<RateLimit>,
+	metrics: Option<RpcMetrics>,
+	transport: &'static str,
+) -> jsonrpc_core::MetaIoHandler<M, (MetricsMiddleware, LimitsMiddleware, RateLimitMiddleware)> {
+	let middleware = (
+		MetricsMiddleware::new(metrics, transport, io.iter().map(|(name, _)| name.clone())),
+		LimitsMiddleware::new(limits),
+		RateLimitMiddleware::new(rate_limit),
+	);
+	let mut limited = jsonrpc_core::MetaIoHandler::with_middleware(middleware);
+	limited.extend_with(io.iter().map(|(name, method)| (name.clone(), method.clone())));
+	limited
+}
+
 #[cfg(not(target_os = "unknown"))]
 mod inner {
 	use super::*;
+	use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+	use crate::proxy::{Proxy, ProxyConfig, REMOTE_ADDRESS_HEADER};
 
-	/// Type alias for http server
-	pub type HttpServer = http::Server;
-	/// Type alias for ws server
-	pub type WsServer = ws::Server;
+	/// HTTP server, behind a proxy if TLS or a rate limit is enabled.
+	pub struct HttpServer {
+		server: http::Server,
+		proxy: Option<Proxy>,
+	}
+
+	impl HttpServer {
+		/// Returns a handle closing the server.
+		pub fn close_handle(&self) -> http::CloseHandle {
+			self.server.close_handle()
+		}
+
+		/// Stops the proxy and waits for the server to be closed.
+		pub fn wait(self) {
+			drop(self.proxy);
+			self.server.wait()
+		}
+	}
+
+	/// WS server, behind a proxy if TLS or compression is enabled.
+	pub struct WsServer {
+		server: ws::Server,
+		proxy: Option<Proxy>,
+	}
+
+	impl WsServer {
+		/// Returns a handle closing the server.
+		pub fn close_handle(&self) -> ws::CloseHandle {
+			self.server.close_handle()
+		}
+
+		/// Stops the proxy and waits for the server to be closed.
+		pub fn wait(self) -> Result<(), ws::Error> {
+			drop(self.proxy);
+			self.server.wait()
+		}
+	}
+
+	/// Address a server should listen on: `addr` itself, or a local address if a proxy listens
+	/// on `addr`.
+	fn listen_address(addr: &SocketAddr, proxy: Option<&ProxyConfig>) -> SocketAddr {
+		match proxy {
+			Some(_) => SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+			None => *addr,
+		}
+	}
 
 	/// Start HTTP server listening on given address.
 	///
+	/// The metadata of the calls is built from the address of the client, known when the server
+	/// is behind the proxy.
+	///
 	/// **Note**: Only available if `not(target_os = "unknown")`.
-	pub fn start_http<M: pubsub::PubSubMetadata + Default>(
+	pub fn start_http<M: pubsub::PubSubMetadata + RemoteAddress + From<Option<IpAddr>>>(
 		addr: &std::net::SocketAddr,
 		cors: Option<&Vec<String>>,
+		limits: RpcLimits,
+		rate_limit: Option<RateLimit>,
+		tls: Option<&TlsConfig>,
+		metrics: Option<RpcMetrics>,
 		io: RpcHandler<M>,
-	) -> io::Result<http::Server> {
-		http::ServerBuilder::new(io)
+	) -> io::Result<HttpServer> {
+		let proxy_config = ProxyConfig::new(tls, false, limits.max_payload, rate_limit.is_some())?;
+		let forwarded = proxy_config.as_ref().map_or(false, |config| config.forward_remote_address);
+		let server = http::ServerBuilder::with_meta_extractor(
+			limited(io, limits, rate_limit, metrics, "http"),
+			move |request: &http::hyper::Request<http::hyper::Body>| {
+				// Only the proxy can be trusted with the address.
+				M::from(if forwarded { remote_address(request) } else { None })
+			},
+		)
 			.threads(4)
 			.health_api(("/health", "system_health"))
-			.allowed_hosts(hosts_filtering(cors.is_some()))
+			.allowed_hosts(hosts_filtering(cors.is_some(), addr, proxy_config.as_ref()))
 			.rest_api(if cors.is_some() {
 				http::RestApi::Secure
 			} else {
 				http::RestApi::Unsecure
 			})
 			.cors(map_cors::<http::AccessControlAllowOrigin>(cors))
-			.max_request_body_size(MAX_PAYLOAD)
-			.start_http(addr)
+			.max_request_body_size(limits.max_payload)
+			.start_http(&listen_address(addr, proxy_config.as_ref()))?;
+
+		let proxy = proxy_config
+			.map(|config| Proxy::start(addr, *server.address(), config))
+			.transpose()?;
+		Ok(HttpServer { server, proxy })
 	}
 
 	/// Start WS server listening on given address.
 	///
+	/// With `ws_compression`, messages are compressed for the clients supporting the
+	/// `permessage-deflate` extension.
+	///
 	/// **Note**: Only available if `not(target_os = "unknown")`.
-	pub fn start_ws<M: pubsub::PubSubMetadata + From<jsonrpc_core::futures::sync::mpsc::Sender<String>>> (
+	pub fn start_ws<
+		M: pubsub::PubSubMetadata + RemoteAddress
+			+ From<jsonrpc_core::futures::sync::mpsc::Sender<String>>,
+	>(
 		addr: &std::net::SocketAddr,
 		max_connections: Option<usize>,
 		cors: Option<&Vec<String>>,
+		limits: RpcLimits,
+		rate_limit: Option<RateLimit>,
+		tls: Option<&TlsConfig>,
+		ws_compression: bool,
+		metrics: Option<RpcMetrics>,
 		io: RpcHandler<M>,
-	) -> io::Result<ws::Server> {
-		ws::ServerBuilder::with_meta_extractor(io, |context: &ws::RequestContext| context.sender().into())
-			.max_payload(MAX_PAYLOAD)
+	) -> io::Result<WsServer> {
+		let proxy_config = ProxyConfig::new(tls, ws_compression, limits.max_payload, false)?;
+		let server = ws::ServerBuilder::with_meta_extractor(
+			limited(io, limits, rate_limit, metrics, "ws"),
+			|context: &ws::RequestContext| context.sender().into(),
+		)
+			.max_payload(limits.max_payload)
 			.max_connections(max_connections.unwrap_or(WS_MAX_CONNECTIONS))
 			.allowed_origins(map_cors(cors))
-			.allowed_hosts(hosts_filtering(cors.is_some()))
-			.start(addr)
+			.allowed_hosts(hosts_filtering(cors.is_some(), addr, proxy_config.as_ref()))
+			.start(&listen_address(addr, proxy_config.as_ref()))
 			.map_err(|err| match err {
 				ws::Error::Io(io) => io,
 				ws::Error::ConnectionClosed => io::ErrorKind::BrokenPipe.into(),
@@ -109,7 +230,17 @@ mod inner {
 					error!("{}", e);
 					io::ErrorKind::Other.into()
 				}
-			})
+			})?;
+
+		let proxy = proxy_config
+			.map(|config| Proxy::start(addr, *server.addr(), config))
+			.transpose()?;
+		Ok(WsServer { server, proxy })
+	}
+
+	/// Address of the client passed by the proxy.
+	fn remote_address(request: &http::hyper::Request<http::hyper::Body>) -> Option<IpAddr> {
+		request.headers().get(REMOTE_ADDRESS_HEADER)?.to_str().ok()?.trim().parse().ok()
 	}
 
 	fn map_cors<T: for<'a> From<&'a str>>(
@@ -118,14 +249,28 @@ mod inner {
 		cors.map(|x| x.iter().map(AsRef::as_ref).map(Into::into).collect::<Vec<_>>()).into()
 	}
 
-	fn hosts_filtering(enable: bool) -> http::DomainsValidation<http::Host> {
-		if enable {
+	fn hosts_filtering(
+		enable: bool,
+		addr: &SocketAddr,
+		proxy: Option<&ProxyConfig>,
+	) -> http::DomainsValidation<http::Host> {
+		match proxy {
+			_ if !enable => http::DomainsValidation::Disabled,
 			// NOTE The listening address is whitelisted by default.
 			// Setting an empty vector here enables the validation
 			// and allows only the listening address.
-			http::DomainsValidation::AllowOnly(vec![])
-		} else {
-			http::DomainsValidation::Disabled
+			None => http::DomainsValidation::AllowOnly(vec![]),
+			// The server only sees the local address behind the TLS proxy, while clients use the
+			// name of the certificate. That name is already validated by the clients.
+			Some(ProxyConfig { tls: Some(_), .. }) => http::DomainsValidation::Disabled,
+			// Behind the proxy, the address of the proxy must be whitelisted instead.
+			Some(_) => {
+				let mut hosts = vec![addr.to_string().into()];
+				if addr.ip().is_loopback() {
+					hosts.push(format!("localhost:{}", addr.port()).into());
+				}
+				http::DomainsValidation::AllowOnly(hosts)
+			},
 		}
 	}
 }
@@ -0,0 +1,211 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Limits on the size of the requests and on the subscriptions of the RPC servers.
+//!
+//! The payload limit is enforced by the servers themselves, while batches and subscriptions are
+//! limited by [`LimitsMiddleware`].
+//!
+//! Subscriptions are counted per WebSocket connection, identified by its pub-sub session. The
+//! subscription methods are recognized by their names: methods containing `subscribe` or
+//! `watch` add a subscription, and methods containing `unsubscribe` or `unwatch` remove one.
+//! Subscriptions ended by the node still count until the client unsubscribes or disconnects.
+
+use std::{collections::HashMap, sync::Arc};
+use jsonrpc_core::{
+	futures::{Future, future::{self, Either}},
+	Call, Error, ErrorCode, FutureOutput, FutureResponse, Middleware, Output, Request, Response,
+	Version,
+};
+use parking_lot::Mutex;
+use pubsub::{PubSubMetadata, Session};
+
+/// Error code of the requests and calls rejected because of the limits.
+pub const LIMIT_ERROR_CODE: i64 = -32006;
+
+/// Limits protecting the memory of the node from the clients of the RPC servers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpcLimits {
+	/// Maximal size of an HTTP request or WS message, in bytes.
+	pub max_payload: usize,
+	/// Maximal number of calls in a batch.
+	pub max_batch_size: usize,
+	/// Maximal number of subscriptions of a WS connection.
+	pub max_subscriptions_per_connection: usize,
+}
+
+impl Default for RpcLimits {
+	fn default() -> Self {
+		RpcLimits {
+			max_payload: 15 * 1024 * 1024,
+			max_batch_size: 1024,
+			max_subscriptions_per_connection: 1024,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SubscriptionCall {
+	Subscribe,
+	Unsubscribe,
+}
+
+fn subscription_call(method: &str) -> Option<SubscriptionCall> {
+	let method = method.to_lowercase();
+	if method.contains("unsubscribe") || method.contains("unwatch") {
+		Some(SubscriptionCall::Unsubscribe)
+	} else if method.contains("subscribe") || method.contains("watch") {
+		Some(SubscriptionCall::Subscribe)
+	} else {
+		None
+	}
+}
+
+fn limit_error(message: &str) -> Error {
+	Error {
+		code: ErrorCode::ServerError(LIMIT_ERROR_CODE),
+		message: message.into(),
+		data: None,
+	}
+}
+
+/// Middleware rejecting the batches and subscriptions over the `RpcLimits`.
+pub struct LimitsMiddleware {
+	limits: RpcLimits,
+	/// Number of subscriptions of the sessions, by address of the session.
+	subscriptions: Arc<Mutex<HashMap<usize, usize>>>,
+}
+
+impl LimitsMiddleware {
+	/// Creates a new middleware enforcing `limits`.
+	pub fn new(limits: RpcLimits) -> Self {
+		LimitsMiddleware { limits, subscriptions: Arc::new(Mutex::new(HashMap::new())) }
+	}
+
+	/// Counts a new subscription of `session`, returning `false` if it has too many already.
+	fn subscribe(&self, session: &Arc<Session>) -> bool {
+		let key = session_key(session);
+		let mut subscriptions = self.subscriptions.lock();
+		if !subscriptions.contains_key(&key) {
+			// The address may be reused by a later session once this one is dropped.
+			let subscriptions = self.subscriptions.clone();
+			session.on_drop(move || { subscriptions.lock().remove(&key); });
+		}
+
+		let count = subscriptions.entry(key).or_insert(0);
+		if *count >= self.limits.max_subscriptions_per_connection {
+			return false;
+		}
+		*count += 1;
+		true
+	}
+}
+
+fn session_key(session: &Arc<Session>) -> usize {
+	&**session as *const Session as usize
+}
+
+fn unsubscribe(subscriptions: &Mutex<HashMap<usize, usize>>, key: usize) {
+	if let Some(count) = subscriptions.lock().get_mut(&key) {
+		*count = count.saturating_sub(1);
+	}
+}
+
+impl<M: PubSubMetadata> Middleware<M> for LimitsMiddleware {
+	type Future = FutureResponse;
+	type CallFuture = FutureOutput;
+
+	fn on_request<F, X>(&self, request: Request, meta: M, next: F) -> Either<Self::Future, X> where
+		F: Fn(Request, M) -> X + Send + Sync,
+		X: Future<Item = Option<Response>, Error = ()> + Send + 'static,
+	{
+		match &request {
+			Request::Batch(calls) if calls.len() > self.limits.max_batch_size => {
+				let error = limit_error("Too many calls in batch");
+				Either::A(Box::new(future::ok(Some(Response::from(error, Some(Version::V2))))))
+			},
+			_ => Either::B(next(request, meta)),
+		}
+	}
+
+	fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X> where
+		F: Fn(Call, M) -> X + Send + Sync,
+		X: Future<Item = Option<Output>, Error = ()> + Send + 'static,
+	{
+		let kind = match &call {
+			Call::MethodCall(method_call) => subscription_call(&method_call.method),
+			_ => None,
+		};
+		let (kind, session) = match (kind, meta.session()) {
+			(Some(kind), Some(session)) => (kind, session),
+			_ => return Either::B(next(call, meta)),
+		};
+		let key = session_key(&session);
+		let subscriptions = self.subscriptions.clone();
+
+		match kind {
+			SubscriptionCall::Subscribe => {
+				if !self.subscribe(&session) {
+					let output = match call {
+						Call::MethodCall(call) => {
+							let error = limit_error("Too many subscriptions on the connection");
+							Some(Output::from(Err(error), call.id, call.jsonrpc))
+						},
+						_ => None,
+					};
+					return Either::A(Box::new(future::ok(output)));
+				}
+				// The subscription was counted beforehand, so that concurrent calls can't exceed
+				// the limit.
+				Either::A(Box::new(next(call, meta).map(move |output| {
+					match output {
+						Some(Output::Success(_)) => {},
+						_ => unsubscribe(&subscriptions, key),
+					}
+					output
+				})))
+			},
+			SubscriptionCall::Unsubscribe => Either::A(Box::new(next(call, meta).map(move |output| {
+				if let Some(Output::Success(ref success)) = output {
+					if success.result == serde_json::Value::Bool(true) {
+						unsubscribe(&subscriptions, key);
+					}
+				}
+				output
+			}))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn recognizes_subscription_methods() {
+		assert_eq!(subscription_call("chain_subscribeNewHeads"), Some(SubscriptionCall::Subscribe));
+		assert_eq!(
+			subscription_call("author_submitAndWatchExtrinsic"),
+			Some(SubscriptionCall::Subscribe),
+		);
+		assert_eq!(subscription_call("unsubscribe_newHead"), Some(SubscriptionCall::Unsubscribe));
+		assert_eq!(
+			subscription_call("author_unwatchExtrinsic"),
+			Some(SubscriptionCall::Unsubscribe),
+		);
+		assert_eq!(subscription_call("state_getStorage"), None);
+	}
+}
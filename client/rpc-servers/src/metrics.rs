@@ -0,0 +1,167 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Prometheus metrics of the calls served by the RPC servers.
+//!
+//! Calls are counted by method and transport, with their errors and the time taken to answer
+//! them. Calls of unknown methods are counted as `unknown`, so that clients can't create labels.
+
+use std::{collections::HashSet, sync::Arc, time::Instant};
+use jsonrpc_core::{
+	futures::{Future, future::Either},
+	Call, FutureOutput, FutureResponse, Metadata, Middleware, Output,
+};
+use prometheus_endpoint::{
+	register, CounterVec, HistogramOpts, HistogramVec, Opts, PrometheusError, Registry, U64,
+};
+
+/// Label of the calls of unknown methods.
+const UNKNOWN_METHOD: &str = "unknown";
+
+/// Label of the invalid calls.
+const INVALID_CALL: &str = "invalid";
+
+/// Prometheus metrics of the RPC calls, shared by the servers.
+#[derive(Clone)]
+pub struct RpcMetrics {
+	calls: CounterVec<U64>,
+	errors: CounterVec<U64>,
+	time: HistogramVec,
+}
+
+impl RpcMetrics {
+	/// Registers the metrics in `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(RpcMetrics {
+			calls: register(CounterVec::new(
+				Opts::new("rpc_calls_total", "Number of RPC calls received"),
+				&["transport", "method"],
+			)?, registry)?,
+			errors: register(CounterVec::new(
+				Opts::new("rpc_calls_failed_total", "Number of RPC calls answered with an error"),
+				&["transport", "method"],
+			)?, registry)?,
+			time: register(HistogramVec::new(
+				HistogramOpts::new(
+					"rpc_calls_time_seconds",
+					"Time taken to answer the RPC calls, in seconds",
+				).buckets(vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0]),
+				&["transport", "method"],
+			)?, registry)?,
+		})
+	}
+}
+
+/// Middleware updating the `RpcMetrics` of the calls of a transport. Does nothing without
+/// metrics.
+pub struct MetricsMiddleware {
+	metrics: Option<RpcMetrics>,
+	transport: &'static str,
+	methods: Arc<HashSet<String>>,
+}
+
+impl MetricsMiddleware {
+	/// Creates a new middleware updating `metrics` with the calls received over `transport`, to
+	/// the given `methods`.
+	pub fn new(
+		metrics: Option<RpcMetrics>,
+		transport: &'static str,
+		methods: impl IntoIterator<Item = String>,
+	) -> Self {
+		MetricsMiddleware { metrics, transport, methods: Arc::new(methods.into_iter().collect()) }
+	}
+
+	/// Label of the method of `call`.
+	fn method(&self, call: &Call) -> String {
+		let method = match call {
+			Call::MethodCall(call) => &call.method,
+			Call::Notification(notification) => &notification.method,
+			Call::Invalid { .. } => return INVALID_CALL.into(),
+		};
+		if self.methods.contains(method) {
+			method.clone()
+		} else {
+			UNKNOWN_METHOD.into()
+		}
+	}
+}
+
+impl<M: Metadata> Middleware<M> for MetricsMiddleware {
+	type Future = FutureResponse;
+	type CallFuture = FutureOutput;
+
+	fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X> where
+		F: Fn(Call, M) -> X + Send + Sync,
+		X: Future<Item = Option<Output>, Error = ()> + Send + 'static,
+	{
+		let metrics = match &self.metrics {
+			Some(metrics) => metrics.clone(),
+			None => return Either::B(next(call, meta)),
+		};
+		let transport = self.transport;
+		let method = self.method(&call);
+		metrics.calls.with_label_values(&[transport, &method]).inc();
+
+		let started = Instant::now();
+		Either::A(Box::new(next(call, meta).map(move |output| {
+			let labels = [transport, method.as_str()];
+			metrics.time.with_label_values(&labels).observe(started.elapsed().as_secs_f64());
+			if let Some(Output::Failure(_)) = output {
+				metrics.errors.with_label_values(&labels).inc();
+			}
+			output
+		})))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::{MetaIoHandler, Params, Value};
+
+	#[test]
+	fn calls_are_counted_by_method() {
+		let registry = Registry::new();
+		let metrics = RpcMetrics::register(&registry).unwrap();
+		let middleware = MetricsMiddleware::new(
+			Some(metrics.clone()),
+			"http",
+			vec!["system_name".to_owned(), "system_fail".to_owned()],
+		);
+		let mut io = MetaIoHandler::<(), _>::with_middleware(middleware);
+		io.add_method("system_name", |_: Params| Ok(Value::from("node")));
+		io.add_method("system_fail", |_: Params| Err(jsonrpc_core::Error::internal_error()));
+
+		for method in &["system_name", "system_name", "system_fail", "system_foo"] {
+			let request = format!(
+				r#"{{"jsonrpc":"2.0","method":"{}","params":[],"id":1}}"#,
+				method,
+			);
+			io.handle_request_sync(&request, ());
+		}
+
+		let count = |counter: &CounterVec<U64>, method| {
+			counter.with_label_values(&["http", method]).get()
+		};
+		assert_eq!(count(&metrics.calls, "system_name"), 2);
+		assert_eq!(count(&metrics.calls, "system_fail"), 1);
+		assert_eq!(count(&metrics.calls, UNKNOWN_METHOD), 1);
+		assert_eq!(count(&metrics.errors, "system_name"), 0);
+		assert_eq!(count(&metrics.errors, "system_fail"), 1);
+		assert_eq!(count(&metrics.errors, UNKNOWN_METHOD), 1);
+		assert_eq!(metrics.time.with_label_values(&["http", "system_name"]).get_sample_count(), 2);
+	}
+}
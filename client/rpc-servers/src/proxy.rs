@@ -0,0 +1,276 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Proxy in front of the RPC servers, for the features they don't support themselves.
+//!
+//! The HTTP and WS servers can neither terminate TLS nor compress WebSocket messages. When one
+//! of these is enabled, the server listens on a local address instead, and a proxy listening on
+//! the configured address forwards the connections to it.
+//!
+//! The HTTP server doesn't know the address of its clients either. With a rate limit, it is
+//! always behind the proxy, which passes the address of the client in the `X-Forwarded-For`
+//! header of the requests.
+
+use std::{io, net::{IpAddr, SocketAddr}, thread};
+use futures::{channel::oneshot, future::{self, Either}, pin_mut, FutureExt};
+use log::{debug, warn};
+use tokio::{
+	io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+	net::{TcpListener, TcpStream},
+};
+use tokio_rustls::TlsAcceptor;
+
+use crate::{TlsConfig, tls, ws_deflate};
+
+/// What the proxy does besides forwarding the connections.
+#[derive(Clone)]
+pub(crate) struct ProxyConfig {
+	/// Terminates TLS with this acceptor if set.
+	pub tls: Option<TlsAcceptor>,
+	/// Negotiates the compression of WebSocket messages if set.
+	pub ws_compression: bool,
+	/// Maximal size of the decompressed WebSocket messages.
+	pub max_payload: usize,
+	/// Passes the address of the clients to the HTTP server if set.
+	pub forward_remote_address: bool,
+}
+
+/// Header of the HTTP requests holding the address of the client.
+pub(crate) const REMOTE_ADDRESS_HEADER: &str = "x-forwarded-for";
+
+/// Maximal size of the request line and headers of an HTTP request.
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+impl ProxyConfig {
+	/// Builds the configuration of the proxy, or `None` if no proxy is needed.
+	pub fn new(
+		tls: Option<&TlsConfig>,
+		ws_compression: bool,
+		max_payload: usize,
+		forward_remote_address: bool,
+	) -> io::Result<Option<Self>> {
+		if tls.is_none() && !ws_compression && !forward_remote_address {
+			return Ok(None);
+		}
+
+		let tls = tls.map(tls::acceptor).transpose()?;
+		Ok(Some(ProxyConfig { tls, ws_compression, max_payload, forward_remote_address }))
+	}
+}
+
+/// Proxy in front of a server. Stops when dropped.
+pub struct Proxy {
+	shutdown: Option<oneshot::Sender<()>>,
+	thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Proxy {
+	/// Starts a proxy listening on `address`, forwarding the connections to `backend`.
+	pub(crate) fn start(
+		address: &SocketAddr,
+		backend: SocketAddr,
+		config: ProxyConfig,
+	) -> io::Result<Self> {
+		let listener = std::net::TcpListener::bind(address)?;
+		listener.set_nonblocking(true)?;
+
+		let (shutdown, shutdown_receiver) = oneshot::channel();
+		let thread = thread::Builder::new()
+			.name("rpc-proxy".into())
+			.spawn(move || {
+				let runtime = tokio::runtime::Builder::new().basic_scheduler().enable_io().build();
+				let mut runtime = match runtime {
+					Ok(runtime) => runtime,
+					Err(e) => {
+						warn!("Failed to start RPC proxy: {}", e);
+						return;
+					},
+				};
+				runtime.block_on(async move {
+					let listener = match TcpListener::from_std(listener) {
+						Ok(listener) => listener,
+						Err(e) => {
+							warn!("Failed to start RPC proxy: {}", e);
+							return;
+						},
+					};
+					let accept = accept_connections(listener, config, backend);
+					pin_mut!(accept);
+					future::select(accept, shutdown_receiver).await;
+				})
+			})?;
+
+		Ok(Proxy { shutdown: Some(shutdown), thread: Some(thread) })
+	}
+}
+
+impl Drop for Proxy {
+	fn drop(&mut self) {
+		if let Some(shutdown) = self.shutdown.take() {
+			let _ = shutdown.send(());
+		}
+		// Wait for the listening address to be released, so that it can be bound again.
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+async fn accept_connections(mut listener: TcpListener, config: ProxyConfig, backend: SocketAddr) {
+	loop {
+		match listener.accept().await {
+			Ok((stream, remote)) => {
+				let relay = relay(stream, remote, config.clone(), backend).map(move |result| {
+					if let Err(e) = result {
+						debug!("RPC connection from {} closed: {}", remote, e);
+					}
+				});
+				tokio::spawn(relay);
+			},
+			Err(e) => warn!("Failed to accept RPC connection: {}", e),
+		}
+	}
+}
+
+async fn relay(
+	stream: TcpStream,
+	remote: SocketAddr,
+	config: ProxyConfig,
+	backend: SocketAddr,
+) -> io::Result<()> {
+	match &config.tls {
+		Some(acceptor) => {
+			let client = acceptor.accept(stream).await?;
+			forward(client, remote, TcpStream::connect(backend).await?, &config).await
+		},
+		None => forward(stream, remote, TcpStream::connect(backend).await?, &config).await,
+	}
+}
+
+async fn forward<C>(
+	client: C,
+	remote: SocketAddr,
+	backend: TcpStream,
+	config: &ProxyConfig,
+) -> io::Result<()> where
+	C: AsyncRead + AsyncWrite + Unpin,
+{
+	if config.ws_compression {
+		ws_deflate::forward(client, backend, config.max_payload).await
+	} else if config.forward_remote_address {
+		forward_http(client, backend, remote.ip()).await
+	} else {
+		copy_both(client, backend).await
+	}
+}
+
+/// Forwards an HTTP request of `client` to `backend`, passing the address of the client.
+///
+/// Only the first request of the connection is rewritten, so the server is asked to close the
+/// connection after its response: the clients then send every request on a new connection.
+async fn forward_http<C>(mut client: C, mut backend: TcpStream, remote: IpAddr) -> io::Result<()>
+where
+	C: AsyncRead + AsyncWrite + Unpin,
+{
+	let mut head = Vec::new();
+	let mut buffer = [0; 4096];
+	let end = loop {
+		if let Some(end) = head_end(&head) {
+			break end;
+		}
+		if head.len() > MAX_HEAD_SIZE {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "HTTP request head too large"));
+		}
+		let read = client.read(&mut buffer).await?;
+		if read == 0 {
+			return Ok(());
+		}
+		head.extend_from_slice(&buffer[..read]);
+	};
+
+	let body = head.split_off(end);
+	backend.write_all(&rewrite_head(&head, remote)?).await?;
+	backend.write_all(&body).await?;
+	copy_both(client, backend).await
+}
+
+/// Returns the length of the request line and headers at the start of `data`, including the
+/// empty line ending them.
+fn head_end(data: &[u8]) -> Option<usize> {
+	data.windows(4).position(|window| window == b"\r\n\r\n").map(|position| position + 4)
+}
+
+/// Sets the `X-Forwarded-For` header of the request `head` to `remote`, and asks the server to
+/// close the connection after the response. The values sent by the client are dropped.
+fn rewrite_head(head: &[u8], remote: IpAddr) -> io::Result<Vec<u8>> {
+	let head = std::str::from_utf8(head)
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid HTTP request head"))?;
+
+	let mut rewritten = String::with_capacity(head.len() + 64);
+	for line in head.split("\r\n").filter(|line| !line.is_empty()) {
+		let name = line.split(':').next().unwrap_or_default().trim();
+		let dropped = name.eq_ignore_ascii_case(REMOTE_ADDRESS_HEADER)
+			|| name.eq_ignore_ascii_case("connection");
+		if dropped {
+			continue;
+		}
+		rewritten.push_str(line);
+		rewritten.push_str("\r\n");
+	}
+	rewritten.push_str(&format!("X-Forwarded-For: {}\r\nConnection: close\r\n\r\n", remote));
+	Ok(rewritten.into_bytes())
+}
+
+/// Copies the traffic between `client` and `backend`, until either side closes.
+pub(crate) async fn copy_both<C, B>(client: C, backend: B) -> io::Result<()> where
+	C: AsyncRead + AsyncWrite + Unpin,
+	B: AsyncRead + AsyncWrite + Unpin,
+{
+	let (mut client_read, mut client_write) = tokio::io::split(client);
+	let (mut backend_read, mut backend_write) = tokio::io::split(backend);
+	let to_backend = tokio::io::copy(&mut client_read, &mut backend_write);
+	let to_client = tokio::io::copy(&mut backend_read, &mut client_write);
+	pin_mut!(to_backend, to_client);
+
+	match future::select(to_backend, to_client).await {
+		Either::Left((result, _)) | Either::Right((result, _)) => result.map(|_| ()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn head_is_rewritten() {
+		let head = b"POST / HTTP/1.1\r\nHost: localhost\r\nX-Forwarded-For: 1.2.3.4\r\n\
+			Connection: keep-alive\r\nContent-Length: 2\r\n\r\n";
+		let data = [&head[..], b"{}"].concat();
+		assert_eq!(head_end(&data), Some(head.len()));
+
+		let rewritten = rewrite_head(head, [10, 0, 0, 1].into()).unwrap();
+		assert_eq!(
+			String::from_utf8(rewritten).unwrap(),
+			"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\
+				X-Forwarded-For: 10.0.0.1\r\nConnection: close\r\n\r\n",
+		);
+	}
+
+	#[test]
+	fn head_end_waits_for_the_empty_line() {
+		assert_eq!(head_end(b"POST / HTTP/1.1\r\nHost: localhost\r\n"), None);
+	}
+}
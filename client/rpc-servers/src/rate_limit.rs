@@ -0,0 +1,216 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Rate limiting of the calls served by the RPC servers.
+//!
+//! Calls are limited with token buckets: `burst` calls can be made at once, and then
+//! `requests_per_second` on average. Every call of a batch counts. Over the limit, method calls
+//! are answered with an error and notifications are dropped.
+//!
+//! Each WebSocket connection, identified by its pub-sub session, has its own limit. HTTP
+//! requests have no session, and are limited by the IP address of the client instead. The HTTP
+//! server only learns that address from the proxy in front of it, which is always started with a
+//! rate limit. The calls with neither a session nor an address share a single limit.
+
+use std::{collections::HashMap, net::IpAddr, sync::Arc, time::Instant};
+use jsonrpc_core::{
+	futures::{Future, future::{self, Either, FutureResult}},
+	Call, Error, ErrorCode, FutureResponse, Middleware, Output, Version,
+};
+use parking_lot::Mutex;
+use pubsub::{PubSubMetadata, Session};
+
+/// Error code of the calls rejected because of the rate limit.
+pub const RATE_LIMIT_ERROR_CODE: i64 = -32005;
+
+/// Limit on the rate of calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+	/// Average number of calls allowed per second.
+	pub requests_per_second: u32,
+	/// Number of calls allowed at once.
+	pub burst: u32,
+}
+
+/// Metadata of the calls knowing the address of the client making them.
+pub trait RemoteAddress {
+	/// IP address of the client, if known.
+	fn remote_address(&self) -> Option<IpAddr>;
+}
+
+struct TokenBucket {
+	tokens: f64,
+	updated: Instant,
+}
+
+impl TokenBucket {
+	fn new(limit: &RateLimit, now: Instant) -> Self {
+		TokenBucket { tokens: f64::from(limit.burst), updated: now }
+	}
+
+	/// Takes a token, returning `false` if there is none left.
+	fn take(&mut self, limit: &RateLimit, now: Instant) -> bool {
+		let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+		self.tokens = (self.tokens + elapsed * f64::from(limit.requests_per_second))
+			.min(f64::from(limit.burst));
+		self.updated = now;
+
+		if self.tokens >= 1.0 {
+			self.tokens -= 1.0;
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Whether the bucket would be full again at `now`, i.e. it is the same as a new bucket.
+	fn is_full(&self, limit: &RateLimit, now: Instant) -> bool {
+		let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+		self.tokens + elapsed * f64::from(limit.requests_per_second) >= f64::from(limit.burst)
+	}
+}
+
+struct Buckets {
+	/// Bucket of the calls made with neither a session nor a remote address.
+	server: Option<TokenBucket>,
+	/// Buckets of the calls made without a session, by remote address.
+	addresses: HashMap<IpAddr, TokenBucket>,
+	/// Buckets of the sessions, by address of the session.
+	sessions: HashMap<usize, TokenBucket>,
+}
+
+/// Middleware rejecting the calls over a `RateLimit`. Lets everything through without a limit.
+pub struct RateLimitMiddleware {
+	limit: Option<RateLimit>,
+	buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimitMiddleware {
+	/// Creates a new middleware enforcing `limit`.
+	pub fn new(limit: Option<RateLimit>) -> Self {
+		RateLimitMiddleware {
+			limit,
+			buckets: Arc::new(Mutex::new(Buckets {
+				server: None,
+				addresses: HashMap::new(),
+				sessions: HashMap::new(),
+			})),
+		}
+	}
+
+	fn allow(&self, session: Option<Arc<Session>>, address: Option<IpAddr>) -> bool {
+		let limit = match &self.limit {
+			Some(limit) => limit,
+			None => return true,
+		};
+		let now = Instant::now();
+
+		let session = match (session, address) {
+			(Some(session), _) => session,
+			(None, Some(address)) => return self.allow_address(limit, address, now),
+			(None, None) => return self.buckets.lock().server
+				.get_or_insert_with(|| TokenBucket::new(limit, now))
+				.take(limit, now),
+		};
+		let key = &*session as *const Session as usize;
+		let mut buckets = self.buckets.lock();
+		if !buckets.sessions.contains_key(&key) {
+			// The address may be reused by a later session once this one is dropped.
+			let buckets = self.buckets.clone();
+			session.on_drop(move || { buckets.lock().sessions.remove(&key); });
+		}
+		buckets.sessions.entry(key)
+			.or_insert_with(|| TokenBucket::new(limit, now))
+			.take(limit, now)
+	}
+
+	fn allow_address(&self, limit: &RateLimit, address: IpAddr, now: Instant) -> bool {
+		let mut buckets = self.buckets.lock();
+		if !buckets.addresses.contains_key(&address) {
+			// Nothing tells when a client is gone: forget the clients whose bucket refilled
+			// instead, they start over with a full bucket anyway.
+			buckets.addresses.retain(|_, bucket| !bucket.is_full(limit, now));
+		}
+		buckets.addresses.entry(address)
+			.or_insert_with(|| TokenBucket::new(limit, now))
+			.take(limit, now)
+	}
+}
+
+impl<M: PubSubMetadata + RemoteAddress> Middleware<M> for RateLimitMiddleware {
+	type Future = FutureResponse;
+	type CallFuture = FutureResult<Option<Output>, ()>;
+
+	fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X> where
+		F: Fn(Call, M) -> X + Send + Sync,
+		X: Future<Item = Option<Output>, Error = ()> + Send + 'static,
+	{
+		if self.allow(meta.session(), meta.remote_address()) {
+			return Either::B(next(call, meta));
+		}
+
+		let error = Error {
+			code: ErrorCode::ServerError(RATE_LIMIT_ERROR_CODE),
+			message: "Rate limit exceeded".into(),
+			data: None,
+		};
+		let output = match call {
+			Call::MethodCall(call) => Some(Output::from(Err(error), call.id, call.jsonrpc)),
+			Call::Notification(_) => None,
+			Call::Invalid { id } => Some(Output::from(Err(error), id, Some(Version::V2))),
+		};
+		Either::A(future::ok(output))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	#[test]
+	fn allows_burst_then_refills_at_rate() {
+		let limit = RateLimit { requests_per_second: 2, burst: 3 };
+		let now = Instant::now();
+		let mut bucket = TokenBucket::new(&limit, now);
+
+		assert!((0..3).all(|_| bucket.take(&limit, now)));
+		assert!(!bucket.take(&limit, now));
+
+		let later = now + Duration::from_millis(500);
+		assert!(bucket.take(&limit, later));
+		assert!(!bucket.take(&limit, later));
+
+		// Never refilled above the burst.
+		let much_later = later + Duration::from_secs(60);
+		assert_eq!((0..10).filter(|_| bucket.take(&limit, much_later)).count(), 3);
+	}
+
+	#[test]
+	fn addresses_have_their_own_limit() {
+		let limit = RateLimit { requests_per_second: 1, burst: 2 };
+		let middleware = RateLimitMiddleware::new(Some(limit));
+		let first: IpAddr = [10, 0, 0, 1].into();
+		let second: IpAddr = [10, 0, 0, 2].into();
+
+		assert!((0..2).all(|_| middleware.allow(None, Some(first))));
+		assert!(!middleware.allow(None, Some(first)));
+		assert!((0..2).all(|_| middleware.allow(None, Some(second))));
+		assert!(!middleware.allow(None, Some(second)));
+		// The calls without an address are limited separately.
+		assert!(middleware.allow(None, None));
+	}
+}
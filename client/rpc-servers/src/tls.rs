@@ -0,0 +1,37 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Loading of the certificate the RPC servers terminate TLS with.
+
+use sc_tls::invalid_data;
+use std::{io, sync::Arc};
+use tokio_rustls::{
+	TlsAcceptor,
+	rustls::{Certificate, NoClientAuth, PrivateKey, ServerConfig},
+};
+
+use crate::TlsConfig;
+
+/// Loads the certificate and private key of `tls`.
+pub(crate) fn acceptor(tls: &TlsConfig) -> io::Result<TlsAcceptor> {
+	let identity = sc_tls::load(&tls.cert, &tls.key)?;
+	let certs = identity.certs.into_iter().map(Certificate).collect();
+
+	let mut config = ServerConfig::new(NoClientAuth::new());
+	config.set_single_cert(certs, PrivateKey(identity.key))
+		.map_err(|e| invalid_data(format!("Invalid TLS certificate or key: {}", e)))?;
+	Ok(TlsAcceptor::from(Arc::new(config)))
+}
@@ -0,0 +1,360 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Local bridge for the workers running next to the node, such as oracles or provers.
+//!
+//! The bridge listens on a unix socket only accessible to the user running the node. A worker
+//! first sends the shared token on a line of its own, and then exchanges JSON-RPC messages with
+//! the node, one per line. Only the `WORKER_METHODS` are served, with local privileges: the
+//! workers can follow the imported blocks and submit transactions, without going through the
+//! public RPC servers.
+
+use std::{
+	fs, io, os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt}, path::{Path, PathBuf},
+	sync::Arc, thread,
+};
+use futures::{
+	channel::{mpsc, oneshot}, compat::{Future01CompatExt, Stream01CompatExt},
+	future::{self, Either}, pin_mut, FutureExt, StreamExt,
+};
+use jsonrpc_core::{MetaIoHandler, futures::sync::mpsc::Sender};
+use log::{debug, warn};
+use pubsub::PubSubMetadata;
+use tokio::{
+	io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+	net::{UnixListener, UnixStream},
+};
+
+use crate::RpcHandler;
+
+/// Methods served to the workers.
+pub const WORKER_METHODS: &[&str] = &[
+	"chain_getBlock",
+	"chain_getHeader",
+	"chain_subscribeNewHeads",
+	"chain_unsubscribeNewHeads",
+	"chain_subscribeFinalizedHeads",
+	"chain_unsubscribeFinalizedHeads",
+	"author_submitExtrinsic",
+	"author_submitAndWatchExtrinsic",
+	"author_unwatchExtrinsic",
+];
+
+/// Maximal size of a message, in bytes.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Number of notifications buffered for a worker before the subscriptions are throttled.
+const NOTIFICATIONS_BUFFER: usize = 1024;
+
+/// Bridge serving the workers. Stops and removes its socket when dropped.
+pub struct WorkerBridge {
+	path: PathBuf,
+	shutdown: Option<oneshot::Sender<()>>,
+	thread: Option<thread::JoinHandle<()>>,
+}
+
+impl WorkerBridge {
+	/// Starts the bridge on the unix socket at `path`, serving the `WORKER_METHODS` of `io` to
+	/// the workers presenting `token`.
+	///
+	/// A stale socket left at `path` is replaced, any other file is an error.
+	pub fn start<M>(path: &Path, token: String, io: RpcHandler<M>) -> io::Result<Self> where
+		M: PubSubMetadata + From<Sender<String>>,
+	{
+		if token.is_empty() {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "Empty worker token"));
+		}
+		match fs::symlink_metadata(path) {
+			Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+			Ok(_) => return Err(io::Error::new(
+				io::ErrorKind::AlreadyExists,
+				format!("{} exists and is not a socket", path.display()),
+			)),
+			Err(_) => {},
+		}
+
+		let listener = bind_private(path)?;
+		listener.set_nonblocking(true)?;
+
+		let mut handler = MetaIoHandler::default();
+		handler.extend_with(io.iter()
+			.filter(|(name, _)| WORKER_METHODS.contains(&name.as_str()))
+			.map(|(name, method)| (name.clone(), method.clone()))
+		);
+		let handler = Arc::new(handler);
+		let token = Arc::new(token);
+
+		let (shutdown, shutdown_receiver) = oneshot::channel();
+		let thread = thread::Builder::new()
+			.name("worker-bridge".into())
+			.spawn(move || {
+				let runtime = tokio::runtime::Builder::new().basic_scheduler().enable_io().build();
+				let mut runtime = match runtime {
+					Ok(runtime) => runtime,
+					Err(e) => {
+						warn!("Failed to start worker bridge: {}", e);
+						return;
+					},
+				};
+				runtime.block_on(async move {
+					let listener = match UnixListener::from_std(listener) {
+						Ok(listener) => listener,
+						Err(e) => {
+							warn!("Failed to start worker bridge: {}", e);
+							return;
+						},
+					};
+					let accept = accept_connections(listener, handler, token);
+					pin_mut!(accept);
+					future::select(accept, shutdown_receiver).await;
+				})
+			})?;
+
+		Ok(WorkerBridge { path: path.to_owned(), shutdown: Some(shutdown), thread: Some(thread) })
+	}
+}
+
+/// Binds the unix socket at `path`, only accessible to the user running the node.
+///
+/// The socket is bound and restricted in a private directory next to `path`, and only then moved
+/// in place, so that no other user can connect to it in the meantime.
+fn bind_private(path: &Path) -> io::Result<std::os::unix::net::UnixListener> {
+	let file_name = path.file_name().ok_or_else(|| io::Error::new(
+		io::ErrorKind::InvalidInput,
+		format!("{} is not a file path", path.display()),
+	))?;
+	let private_dir = path.with_file_name(
+		format!(".{}.{}", file_name.to_string_lossy(), std::process::id()),
+	);
+	fs::DirBuilder::new().mode(0o700).create(&private_dir)?;
+
+	let private_path = private_dir.join(file_name);
+	let listener = std::os::unix::net::UnixListener::bind(&private_path).and_then(|listener| {
+		fs::set_permissions(&private_path, fs::Permissions::from_mode(0o600))?;
+		fs::rename(&private_path, path)?;
+		Ok(listener)
+	});
+
+	let _ = fs::remove_file(&private_path);
+	if let Err(e) = fs::remove_dir(&private_dir) {
+		warn!("Failed to remove {}: {}", private_dir.display(), e);
+	}
+	listener
+}
+
+impl Drop for WorkerBridge {
+	fn drop(&mut self) {
+		if let Some(shutdown) = self.shutdown.take() {
+			let _ = shutdown.send(());
+		}
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+		let _ = fs::remove_file(&self.path);
+	}
+}
+
+async fn accept_connections<M>(
+	mut listener: UnixListener,
+	handler: Arc<MetaIoHandler<M>>,
+	token: Arc<String>,
+) where
+	M: PubSubMetadata + From<Sender<String>>,
+{
+	loop {
+		match listener.accept().await {
+			Ok((stream, _)) => {
+				let serve = serve(stream, handler.clone(), token.clone()).map(|result| {
+					if let Err(e) = result {
+						debug!("Worker connection closed: {}", e);
+					}
+				});
+				tokio::spawn(serve);
+			},
+			Err(e) => warn!("Failed to accept worker connection: {}", e),
+		}
+	}
+}
+
+/// Serves a worker until it disconnects.
+async fn serve<M>(
+	stream: UnixStream,
+	handler: Arc<MetaIoHandler<M>>,
+	token: Arc<String>,
+) -> io::Result<()> where
+	M: PubSubMetadata + From<Sender<String>>,
+{
+	let (read, write) = tokio::io::split(stream);
+	let mut reader = BufReader::new(read);
+	match read_line(&mut reader).await? {
+		Some(line) if tokens_match(line.as_bytes(), token.as_bytes()) => {},
+		_ => return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Invalid worker token")),
+	}
+
+	let (notifications_sender, notifications) = jsonrpc_core::futures::sync::mpsc::channel(
+		NOTIFICATIONS_BUFFER,
+	);
+	let metadata = M::from(notifications_sender);
+	let (responses_sender, responses) = mpsc::unbounded();
+
+	let requests = async move {
+		while let Some(request) = read_line(&mut reader).await? {
+			if let Ok(Some(response)) = handler.handle_request(&request, metadata.clone())
+				.compat()
+				.await
+			{
+				let _ = responses_sender.unbounded_send(response);
+			}
+		}
+		Ok::<_, io::Error>(())
+	};
+	let notifications = notifications.compat().filter_map(|n| future::ready(n.ok()));
+	let messages = futures::stream::select(responses, notifications);
+	let writes = write_lines(write, messages);
+	pin_mut!(requests, writes);
+
+	match future::select(requests, writes).await {
+		Either::Left((result, _)) | Either::Right((result, _)) => result,
+	}
+}
+
+/// Compares the tokens in a time independent of their content.
+fn tokens_match(given: &[u8], expected: &[u8]) -> bool {
+	given.len() == expected.len() &&
+		given.iter().zip(expected).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Reads a line, without its end. Returns `None` once the worker disconnected.
+async fn read_line<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Option<String>> {
+	let mut line = Vec::new();
+	loop {
+		let mut byte = [0; 1];
+		if reader.read(&mut byte).await? == 0 {
+			return if line.is_empty() {
+				Ok(None)
+			} else {
+				Err(io::ErrorKind::UnexpectedEof.into())
+			};
+		}
+		match byte[0] {
+			b'\n' => break,
+			_ if line.len() == MAX_MESSAGE_SIZE =>
+				return Err(io::Error::new(io::ErrorKind::InvalidData, "Message too large")),
+			byte => line.push(byte),
+		}
+	}
+	if line.last() == Some(&b'\r') {
+		line.pop();
+	}
+	String::from_utf8(line)
+		.map(Some)
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Message is not UTF-8"))
+}
+
+async fn write_lines<W, S>(mut write: W, messages: S) -> io::Result<()> where
+	W: AsyncWrite + Unpin,
+	S: futures::Stream<Item = String>,
+{
+	pin_mut!(messages);
+	while let Some(message) = messages.next().await {
+		write.write_all(message.as_bytes()).await?;
+		write.write_all(b"\n").await?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::{BufRead, Write};
+
+	#[derive(Clone, Default)]
+	struct Metadata(Option<Arc<pubsub::Session>>);
+
+	impl jsonrpc_core::Metadata for Metadata {}
+
+	impl PubSubMetadata for Metadata {
+		fn session(&self) -> Option<Arc<pubsub::Session>> {
+			self.0.clone()
+		}
+	}
+
+	impl From<Sender<String>> for Metadata {
+		fn from(sender: Sender<String>) -> Self {
+			Metadata(Some(Arc::new(pubsub::Session::new(sender))))
+		}
+	}
+
+	fn bridge(path: &Path) -> WorkerBridge {
+		let mut io = RpcHandler::<Metadata>::default();
+		io.add_method("author_submitExtrinsic", |_| Ok(jsonrpc_core::Value::from("0x01")));
+		io.add_method("author_rotateKeys", |_| Ok(jsonrpc_core::Value::from("0x02")));
+		WorkerBridge::start(path, "secret".into(), io).unwrap()
+	}
+
+	fn request(path: &Path, token: &str, method: &str) -> Option<String> {
+		let mut stream = std::os::unix::net::UnixStream::connect(path).unwrap();
+		write!(
+			stream,
+			"{}\n{{\"jsonrpc\":\"2.0\",\"method\":\"{}\",\"params\":[],\"id\":1}}\n",
+			token,
+			method,
+		).unwrap();
+		let mut response = String::new();
+		std::io::BufReader::new(stream).read_line(&mut response).ok()?;
+		Some(response).filter(|response| !response.is_empty())
+	}
+
+	#[test]
+	fn serves_worker_methods_to_authenticated_workers() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("worker.sock");
+		let bridge = bridge(&path);
+
+		let mode = fs::metadata(&path).unwrap().permissions().mode();
+		assert_eq!(mode & 0o777, 0o600);
+		// the private directory the socket was bound in is removed.
+		assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+		let response = request(&path, "secret", "author_submitExtrinsic").unwrap();
+		assert!(response.contains("\"result\":\"0x01\""));
+		let response = request(&path, "secret", "author_rotateKeys").unwrap();
+		assert!(response.contains("Method not found"));
+		assert_eq!(request(&path, "wrong", "author_submitExtrinsic"), None);
+
+		drop(bridge);
+		assert!(!path.exists());
+	}
+
+	#[test]
+	fn lines_are_limited() {
+		let mut runtime = tokio::runtime::Builder::new().basic_scheduler().build().unwrap();
+		let mut reader: &[u8] = b"token\r\nnext";
+		assert_eq!(runtime.block_on(read_line(&mut reader)).unwrap(), Some("token".into()));
+		assert!(runtime.block_on(read_line(&mut reader)).is_err());
+		assert_eq!(runtime.block_on(read_line(&mut reader)).unwrap(), None);
+
+		let long = vec![b'a'; MAX_MESSAGE_SIZE + 1];
+		assert!(runtime.block_on(read_line(&mut &long[..])).is_err());
+	}
+
+	#[test]
+	fn tokens_are_compared() {
+		assert!(tokens_match(b"secret", b"secret"));
+		assert!(!tokens_match(b"secreT", b"secret"));
+		assert!(!tokens_match(b"secret2", b"secret"));
+	}
+}
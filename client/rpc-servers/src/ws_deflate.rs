@@ -0,0 +1,487 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compression of WebSocket messages with the `permessage-deflate` extension (RFC 7692).
+//!
+//! The proxy accepts the extension on behalf of the WS server if the client offers it. The
+//! messages of the server are then compressed for the client, and the compressed messages of
+//! the client are decompressed for the server. Connections without the extension are forwarded
+//! untouched.
+
+use std::io::{self, Cursor};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+use futures::{future::{self, Either}, pin_mut};
+use tokio::{
+	io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+	net::TcpStream,
+};
+
+use crate::proxy::copy_both;
+
+/// Maximal size of the head of the handshake messages.
+const MAX_HEAD: usize = 16 * 1024;
+/// Messages smaller than this are sent uncompressed.
+const MIN_COMPRESSED_SIZE: usize = 64;
+/// Trailer of the compressed messages, removed before sending them.
+const DEFLATE_TRAILER: [u8; 4] = [0, 0, 0xff, 0xff];
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+
+fn invalid_data(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Parameters of the extension accepted for a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DeflateParams {
+	/// Whether each message must be compressed independently of the previous ones.
+	server_no_context_takeover: bool,
+}
+
+impl DeflateParams {
+	/// The `Sec-WebSocket-Extensions` header accepting the extension.
+	fn response_header(&self) -> &'static str {
+		if self.server_no_context_takeover {
+			"Sec-WebSocket-Extensions: permessage-deflate; server_no_context_takeover\r\n"
+		} else {
+			"Sec-WebSocket-Extensions: permessage-deflate\r\n"
+		}
+	}
+}
+
+/// Forwards a connection to the WS server, compressing its messages if the client offers it.
+///
+/// Messages larger than `max_payload`, once decompressed, close the connection.
+pub(crate) async fn forward<C>(
+	mut client: C,
+	mut backend: TcpStream,
+	max_payload: usize,
+) -> io::Result<()> where
+	C: AsyncRead + AsyncWrite + Unpin,
+{
+	let (request, head_len) = match read_head(&mut client).await? {
+		(request, Some(head_len)) => (request, head_len),
+		(request, None) => {
+			backend.write_all(&request).await?;
+			return copy_both(client, backend).await;
+		},
+	};
+	let params = match negotiate(&request[..head_len]) {
+		Some(params) => params,
+		None => {
+			backend.write_all(&request).await?;
+			return copy_both(client, backend).await;
+		},
+	};
+	backend.write_all(&without_extensions(&request[..head_len])).await?;
+
+	let (response, response_head_len) = match read_head(&mut backend).await? {
+		(response, Some(head_len)) if response.starts_with(b"HTTP/1.1 101") => (response, head_len),
+		(response, _) => {
+			client.write_all(&response).await?;
+			return copy_both(client, backend).await;
+		},
+	};
+	// Accept the extension by adding its header before the empty line ending the head.
+	client.write_all(&response[..response_head_len - 2]).await?;
+	client.write_all(params.response_header().as_bytes()).await?;
+	client.write_all(b"\r\n").await?;
+	client.flush().await?;
+
+	// Frames may have been read along with the heads.
+	let (client_read, client_write) = tokio::io::split(client);
+	let (backend_read, backend_write) = tokio::io::split(backend);
+	let client_read = Cursor::new(request[head_len..].to_vec()).chain(client_read);
+	let backend_read = Cursor::new(response[response_head_len..].to_vec()).chain(backend_read);
+
+	let to_backend = inflate_messages(client_read, backend_write, max_payload);
+	let to_client = deflate_messages(backend_read, client_write, params, max_payload);
+	pin_mut!(to_backend, to_client);
+
+	match future::select(to_backend, to_client).await {
+		Either::Left((result, _)) | Either::Right((result, _)) => result,
+	}
+}
+
+/// Reads the head of an HTTP message.
+///
+/// Returns the bytes read, and the length of the head if it was read entirely.
+async fn read_head<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<(Vec<u8>, Option<usize>)> {
+	let mut buffer = Vec::new();
+	let mut chunk = [0; 4096];
+	loop {
+		if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+			return Ok((buffer, Some(end + 4)));
+		}
+		if buffer.len() > MAX_HEAD {
+			return Ok((buffer, None));
+		}
+		match reader.read(&mut chunk).await? {
+			0 => return Ok((buffer, None)),
+			read => buffer.extend_from_slice(&chunk[..read]),
+		}
+	}
+}
+
+/// Lines of an HTTP head, without their line ending.
+fn lines(head: &[u8]) -> impl Iterator<Item = &[u8]> {
+	head.split(|byte| *byte == b'\n').map(|line| match line.last() {
+		Some(b'\r') => &line[..line.len() - 1],
+		_ => line,
+	})
+}
+
+/// Returns the value of `line` if it is a `Sec-WebSocket-Extensions` header.
+fn extensions_header(line: &[u8]) -> Option<&str> {
+	let line = std::str::from_utf8(line).ok()?;
+	let colon = line.find(':')?;
+	if line[..colon].trim().eq_ignore_ascii_case("sec-websocket-extensions") {
+		Some(line[colon + 1..].trim())
+	} else {
+		None
+	}
+}
+
+/// Returns the parameters of the first acceptable `permessage-deflate` offer of a request head.
+fn negotiate(head: &[u8]) -> Option<DeflateParams> {
+	lines(head)
+		.filter_map(extensions_header)
+		.flat_map(|value| value.split(','))
+		.find_map(accept_offer)
+}
+
+fn accept_offer(offer: &str) -> Option<DeflateParams> {
+	let mut params = offer.split(';').map(str::trim);
+	if params.next()? != "permessage-deflate" {
+		return None;
+	}
+
+	let mut server_no_context_takeover = false;
+	for param in params {
+		match param.split('=').next().unwrap_or_default().trim() {
+			"server_no_context_takeover" => server_no_context_takeover = true,
+			// The client may reset its compressor or use a smaller window, that's fine with us.
+			"client_no_context_takeover" | "client_max_window_bits" => {},
+			// The window of our compressor can't be reduced.
+			_ => return None,
+		}
+	}
+	Some(DeflateParams { server_no_context_takeover })
+}
+
+/// Removes the `Sec-WebSocket-Extensions` headers of a request head, which the server doesn't
+/// have to know about.
+fn without_extensions(head: &[u8]) -> Vec<u8> {
+	head.split(|byte| *byte == b'\n')
+		.filter(|line| extensions_header(line).is_none())
+		.collect::<Vec<_>>()
+		.join(&b'\n')
+}
+
+/// A WebSocket frame, or a whole message once reassembled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Frame {
+	fin: bool,
+	/// Marks the first frame of compressed messages.
+	rsv1: bool,
+	opcode: u8,
+	payload: Vec<u8>,
+}
+
+impl Frame {
+	fn is_control(&self) -> bool {
+		self.opcode & 0x8 != 0
+	}
+}
+
+/// Reads a frame, unmasking it. Returns `None` at the end of the stream.
+async fn read_frame<R: AsyncRead + Unpin>(
+	reader: &mut R,
+	max_payload: usize,
+) -> io::Result<Option<Frame>> {
+	let mut head = [0; 2];
+	match reader.read_exact(&mut head).await {
+		Ok(_) => {},
+		Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+		Err(e) => return Err(e),
+	}
+
+	let len = match head[1] & 0x7f {
+		126 => {
+			let mut len = [0; 2];
+			reader.read_exact(&mut len).await?;
+			u64::from(u16::from_be_bytes(len))
+		},
+		127 => {
+			let mut len = [0; 8];
+			reader.read_exact(&mut len).await?;
+			u64::from_be_bytes(len)
+		},
+		len => u64::from(len),
+	};
+	if len > max_payload as u64 {
+		return Err(invalid_data("WebSocket frame too large"));
+	}
+
+	let mut mask = [0; 4];
+	let masked = head[1] & 0x80 != 0;
+	if masked {
+		reader.read_exact(&mut mask).await?;
+	}
+	let mut payload = vec![0; len as usize];
+	reader.read_exact(&mut payload).await?;
+	if masked {
+		for (i, byte) in payload.iter_mut().enumerate() {
+			*byte ^= mask[i % 4];
+		}
+	}
+
+	Ok(Some(Frame {
+		fin: head[0] & 0x80 != 0,
+		rsv1: head[0] & 0x40 != 0,
+		opcode: head[0] & 0x0f,
+		payload,
+	}))
+}
+
+/// Writes a frame, masked as the frames sent by clients must be if `masked`.
+async fn write_frame<W: AsyncWrite + Unpin>(
+	writer: &mut W,
+	frame: &Frame,
+	masked: bool,
+) -> io::Result<()> {
+	let mut head = Vec::with_capacity(14);
+	head.push((frame.fin as u8) << 7 | (frame.rsv1 as u8) << 6 | frame.opcode);
+
+	let mask_bit = if masked { 0x80 } else { 0 };
+	let len = frame.payload.len();
+	if len < 126 {
+		head.push(mask_bit | len as u8);
+	} else if len <= usize::from(u16::max_value()) {
+		head.push(mask_bit | 126);
+		head.extend_from_slice(&(len as u16).to_be_bytes());
+	} else {
+		head.push(mask_bit | 127);
+		head.extend_from_slice(&(len as u64).to_be_bytes());
+	}
+	if masked {
+		// The connection to the server is local, a null mask leaves the payload as is.
+		head.extend_from_slice(&[0; 4]);
+	}
+
+	writer.write_all(&head).await?;
+	writer.write_all(&frame.payload).await?;
+	writer.flush().await
+}
+
+/// Adds `frame` to the message being received, returning the message once complete.
+fn reassemble(
+	message: &mut Option<Frame>,
+	frame: Frame,
+	max_payload: usize,
+) -> io::Result<Option<Frame>> {
+	match message {
+		Some(message) if frame.opcode == OPCODE_CONTINUATION => {
+			if message.payload.len() + frame.payload.len() > max_payload {
+				return Err(invalid_data("WebSocket message too large"));
+			}
+			message.payload.extend_from_slice(&frame.payload);
+			message.fin = frame.fin;
+		},
+		None if frame.opcode != OPCODE_CONTINUATION => *message = Some(frame),
+		_ => return Err(invalid_data("Unexpected WebSocket frame")),
+	}
+
+	if message.as_ref().map_or(false, |message| message.fin) {
+		Ok(message.take())
+	} else {
+		Ok(None)
+	}
+}
+
+/// Forwards the messages of the client to the server, decompressing them.
+async fn inflate_messages<R, W>(
+	mut client: R,
+	mut backend: W,
+	max_payload: usize,
+) -> io::Result<()> where
+	R: AsyncRead + Unpin,
+	W: AsyncWrite + Unpin,
+{
+	let mut inflater = Inflater(Decompress::new(false));
+	let mut message = None;
+	while let Some(frame) = read_frame(&mut client, max_payload).await? {
+		if frame.is_control() {
+			write_frame(&mut backend, &frame, true).await?;
+			continue;
+		}
+		if let Some(mut complete) = reassemble(&mut message, frame, max_payload)? {
+			if complete.rsv1 {
+				complete.payload = inflater.inflate(&complete.payload, max_payload)?;
+				complete.rsv1 = false;
+			}
+			write_frame(&mut backend, &complete, true).await?;
+		}
+	}
+	Ok(())
+}
+
+/// Forwards the messages of the server to the client, compressing them.
+async fn deflate_messages<R, W>(
+	mut backend: R,
+	mut client: W,
+	params: DeflateParams,
+	max_payload: usize,
+) -> io::Result<()> where
+	R: AsyncRead + Unpin,
+	W: AsyncWrite + Unpin,
+{
+	let mut deflater = Deflater {
+		compress: Compress::new(Compression::default(), false),
+		no_context_takeover: params.server_no_context_takeover,
+	};
+	let mut message = None;
+	while let Some(frame) = read_frame(&mut backend, max_payload).await? {
+		if frame.is_control() {
+			write_frame(&mut client, &frame, false).await?;
+			continue;
+		}
+		if let Some(mut complete) = reassemble(&mut message, frame, max_payload)? {
+			if complete.payload.len() >= MIN_COMPRESSED_SIZE {
+				complete.payload = deflater.deflate(&complete.payload)?;
+				complete.rsv1 = true;
+			}
+			write_frame(&mut client, &complete, false).await?;
+		}
+	}
+	Ok(())
+}
+
+/// Compressor of the messages sent to a client.
+struct Deflater {
+	compress: Compress,
+	no_context_takeover: bool,
+}
+
+impl Deflater {
+	fn deflate(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+		let mut output = Vec::with_capacity(data.len() / 2 + 64);
+		let start = self.compress.total_in();
+		loop {
+			let consumed = (self.compress.total_in() - start) as usize;
+			self.compress.compress_vec(&data[consumed..], &mut output, FlushCompress::Sync)
+				.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+			let consumed = (self.compress.total_in() - start) as usize;
+			// Flushed once all the input is consumed without filling the output.
+			if consumed == data.len() && output.len() < output.capacity() {
+				break;
+			}
+			output.reserve(output.capacity());
+		}
+
+		if output.ends_with(&DEFLATE_TRAILER) {
+			output.truncate(output.len() - DEFLATE_TRAILER.len());
+		}
+		if self.no_context_takeover {
+			self.compress.reset();
+		}
+		Ok(output)
+	}
+}
+
+/// Decompressor of the messages received from a client.
+struct Inflater(Decompress);
+
+impl Inflater {
+	fn inflate(&mut self, data: &[u8], max_payload: usize) -> io::Result<Vec<u8>> {
+		let input = [data, &DEFLATE_TRAILER[..]].concat();
+		let mut output = Vec::with_capacity(input.len() * 2 + 64);
+		let start = self.0.total_in();
+		loop {
+			let consumed = (self.0.total_in() - start) as usize;
+			self.0.decompress_vec(&input[consumed..], &mut output, FlushDecompress::Sync)
+				.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+			let consumed = (self.0.total_in() - start) as usize;
+			if consumed == input.len() && output.len() < output.capacity() {
+				return Ok(output);
+			}
+			if output.len() >= max_payload {
+				return Err(invalid_data("WebSocket message too large"));
+			}
+			output.reserve(output.capacity());
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+
+	#[test]
+	fn negotiates_acceptable_offer() {
+		let head = b"GET / HTTP/1.1\r\n\
+			Host: localhost:9944\r\n\
+			Sec-WebSocket-Extensions: permessage-deflate; server_max_window_bits=10, \
+				permessage-deflate; server_no_context_takeover; client_max_window_bits\r\n\
+			\r\n";
+		assert_eq!(negotiate(head), Some(DeflateParams { server_no_context_takeover: true }));
+		assert_eq!(negotiate(b"GET / HTTP/1.1\r\nHost: localhost:9944\r\n\r\n"), None);
+
+		assert_eq!(
+			without_extensions(head),
+			b"GET / HTTP/1.1\r\nHost: localhost:9944\r\n\r\n".to_vec(),
+		);
+	}
+
+	#[test]
+	fn inflates_deflated_messages() {
+		let mut deflater = Deflater {
+			compress: Compress::new(Compression::default(), false),
+			no_context_takeover: false,
+		};
+		let mut inflater = Inflater(Decompress::new(false));
+
+		// The second message refers to the first one.
+		let message = br#"{"jsonrpc":"2.0","method":"state_storage","params":{}}"#.repeat(100);
+		for _ in 0..2 {
+			let compressed = deflater.deflate(&message).unwrap();
+			assert!(compressed.len() < message.len() / 10);
+			assert_eq!(inflater.inflate(&compressed, 1024 * 1024).unwrap(), message);
+		}
+	}
+
+	#[test]
+	fn writes_and_reads_frames() {
+		let frames = vec![
+			Frame { fin: true, rsv1: true, opcode: 0x1, payload: vec![1; 10] },
+			Frame { fin: false, rsv1: false, opcode: 0x2, payload: vec![2; 300] },
+			Frame { fin: true, rsv1: false, opcode: 0x0, payload: vec![3; 70_000] },
+		];
+
+		let mut written = Vec::new();
+		for (i, frame) in frames.iter().enumerate() {
+			block_on(write_frame(&mut written, frame, i % 2 == 0)).unwrap();
+		}
+
+		let mut reader = Cursor::new(written);
+		let mut read = || block_on(read_frame(&mut reader, 1024 * 1024)).unwrap();
+		for frame in frames {
+			assert_eq!(read(), Some(frame));
+		}
+		assert_eq!(read(), None);
+	}
+}
@@ -0,0 +1,346 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Substrate archive API.
+//!
+//! Each query runs on its own thread and stores its results in memory, until its job is removed
+//! or evicted to make room for a newer one.
+
+#[cfg(test)]
+mod tests;
+
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc};
+use log::debug;
+use parking_lot::Mutex;
+use sc_client_api::{Backend, StorageProvider};
+use sc_rpc_api::DenyUnsafe;
+use sp_blockchain::HeaderBackend;
+use sp_core::storage::{StorageKey, StorageChangeSet};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT}};
+
+pub use sc_rpc_api::archive::*;
+use self::error::{Error, Result};
+
+/// Maximum number of jobs kept at once.
+const MAX_JOBS: usize = 16;
+/// Maximum number of results returned by a single `archive_jobResults` call.
+const MAX_PAGE_SIZE: u32 = 1000;
+/// Maximum number of results of a job. A job finding more fails.
+const MAX_JOB_RESULTS: usize = 1_000_000;
+
+/// A query job, shared between its thread and the RPC handlers.
+struct Job<Hash> {
+	state: JobState,
+	processed: u64,
+	total: Option<u64>,
+	results: Vec<JobItem<Hash>>,
+	/// Set when the job is removed, to stop its thread.
+	removed: bool,
+}
+
+type SharedJob<Hash> = Arc<Mutex<Job<Hash>>>;
+
+struct JobsInner<Hash> {
+	next_id: JobId,
+	jobs: BTreeMap<JobId, SharedJob<Hash>>,
+}
+
+/// The query jobs of a node.
+///
+/// The same `Jobs` should be given to all the `Archive` instances of a node, so that jobs started
+/// through one RPC interface can be polled through another.
+pub struct Jobs<Hash> {
+	inner: Arc<Mutex<JobsInner<Hash>>>,
+}
+
+impl<Hash> Clone for Jobs<Hash> {
+	fn clone(&self) -> Self {
+		Jobs { inner: self.inner.clone() }
+	}
+}
+
+impl<Hash> Default for Jobs<Hash> {
+	fn default() -> Self {
+		Jobs {
+			inner: Arc::new(Mutex::new(JobsInner {
+				next_id: 0,
+				jobs: BTreeMap::new(),
+			})),
+		}
+	}
+}
+
+impl<Hash> Jobs<Hash> {
+	/// Add a job, evicting the oldest completed one if there are already `MAX_JOBS` jobs.
+	fn insert(&self, job: SharedJob<Hash>) -> Result<JobId> {
+		let mut inner = self.inner.lock();
+		if inner.jobs.len() >= MAX_JOBS {
+			let completed = inner.jobs.iter()
+				.find(|(_, job)| job.lock().state != JobState::Running)
+				.map(|(id, _)| *id);
+			match completed {
+				Some(id) => {
+					debug!(target: "rpc", "Evicting archive job {}", id);
+					inner.jobs.remove(&id);
+				},
+				None => return Err(Error::TooManyJobs(MAX_JOBS)),
+			}
+		}
+
+		let id = inner.next_id;
+		inner.next_id += 1;
+		inner.jobs.insert(id, job);
+		Ok(id)
+	}
+
+	fn get(&self, id: JobId) -> Result<SharedJob<Hash>> {
+		self.inner.lock().jobs.get(&id).cloned().ok_or(Error::UnknownJob(id))
+	}
+
+	fn remove(&self, id: JobId) -> Option<SharedJob<Hash>> {
+		self.inner.lock().jobs.remove(&id)
+	}
+}
+
+/// Archive API
+pub struct Archive<Block: BlockT, BE, Client> {
+	client: Arc<Client>,
+	jobs: Jobs<Block::Hash>,
+	deny_unsafe: DenyUnsafe,
+	_marker: PhantomData<BE>,
+}
+
+impl<Block: BlockT, BE, Client> Archive<Block, BE, Client> {
+	/// Create new instance of Archive API.
+	pub fn new(client: Arc<Client>, jobs: Jobs<Block::Hash>, deny_unsafe: DenyUnsafe) -> Self {
+		Archive {
+			client,
+			jobs,
+			deny_unsafe,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<Block, BE, Client> ArchiveApi<Block::Hash> for Archive<Block, BE, Client> where
+	Block: BlockT + 'static,
+	BE: Backend<Block> + 'static,
+	Client: StorageProvider<Block, BE> + HeaderBackend<Block> + Send + Sync + 'static,
+{
+	fn start_query(&self, query: Query<Block::Hash>) -> Result<JobId> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let job = Arc::new(Mutex::new(Job {
+			state: JobState::Running,
+			processed: 0,
+			total: None,
+			results: Vec::new(),
+			removed: false,
+		}));
+		let id = self.jobs.insert(job.clone())?;
+
+		let client = self.client.clone();
+		let thread_job = job.clone();
+		let spawned = std::thread::Builder::new()
+			.name(format!("archive-job-{}", id))
+			.spawn(move || {
+				let result = run_query(&*client, query, &thread_job);
+				let mut job = thread_job.lock();
+				job.state = match result {
+					Ok(()) => JobState::Done,
+					Err(e) => JobState::Failed(e),
+				};
+			});
+		if let Err(e) = spawned {
+			job.lock().state = JobState::Failed(format!("Failed to spawn job thread: {}", e));
+		}
+
+		Ok(id)
+	}
+
+	fn job_status(&self, id: JobId) -> Result<JobStatus> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let job = self.jobs.get(id)?;
+		let job = job.lock();
+		Ok(JobStatus {
+			state: job.state.clone(),
+			processed: job.processed,
+			total: job.total,
+			results: job.results.len() as u64,
+		})
+	}
+
+	fn job_results(&self, id: JobId, start: u64, count: u32) -> Result<Vec<JobItem<Block::Hash>>> {
+		self.deny_unsafe.check_if_safe()?;
+
+		if count > MAX_PAGE_SIZE {
+			return Err(Error::InvalidCount {
+				value: count,
+				max: MAX_PAGE_SIZE,
+			});
+		}
+
+		let job = self.jobs.get(id)?;
+		let job = job.lock();
+		let len = job.results.len();
+		let start = std::cmp::min(start, len as u64) as usize;
+		let end = std::cmp::min(start + count as usize, len);
+		Ok(job.results[start..end].to_vec())
+	}
+
+	fn remove_job(&self, id: JobId) -> Result<bool> {
+		self.deny_unsafe.check_if_safe()?;
+
+		Ok(match self.jobs.remove(id) {
+			Some(job) => {
+				job.lock().removed = true;
+				true
+			},
+			None => false,
+		})
+	}
+}
+
+/// Run `query`, recording its progress and results into `job`.
+fn run_query<Block, BE, Client>(
+	client: &Client,
+	query: Query<Block::Hash>,
+	job: &SharedJob<Block::Hash>,
+) -> std::result::Result<(), String> where
+	Block: BlockT,
+	BE: Backend<Block>,
+	Client: StorageProvider<Block, BE> + HeaderBackend<Block>,
+{
+	match query {
+		Query::Keys { prefix, at } => scan_keys(client, &prefix, at, false, job),
+		Query::Pairs { prefix, at } => scan_keys(client, &prefix, at, true, job),
+		Query::Range { keys, from, to } => query_range(client, &keys, from, to, job),
+	}
+}
+
+fn scan_keys<Block, BE, Client>(
+	client: &Client,
+	prefix: &StorageKey,
+	at: Option<Block::Hash>,
+	with_values: bool,
+	job: &SharedJob<Block::Hash>,
+) -> std::result::Result<(), String> where
+	Block: BlockT,
+	BE: Backend<Block>,
+	Client: StorageProvider<Block, BE> + HeaderBackend<Block>,
+{
+	let block = BlockId::Hash(at.unwrap_or_else(|| client.info().best_hash));
+	let keys = client.storage_keys_iter(&block, Some(prefix), None).map_err(client_err)?;
+
+	for key in keys {
+		let item = if with_values {
+			let value = client.storage(&block, &key).map_err(client_err)?;
+			JobItem::Pair(key, value.unwrap_or_default())
+		} else {
+			JobItem::Key(key)
+		};
+		if !record(job, Some(item))? {
+			break;
+		}
+	}
+
+	Ok(())
+}
+
+fn query_range<Block, BE, Client>(
+	client: &Client,
+	keys: &[StorageKey],
+	from: Block::Hash,
+	to: Option<Block::Hash>,
+	job: &SharedJob<Block::Hash>,
+) -> std::result::Result<(), String> where
+	Block: BlockT,
+	BE: Backend<Block>,
+	Client: StorageProvider<Block, BE> + HeaderBackend<Block>,
+{
+	let to = to.unwrap_or_else(|| client.info().best_hash);
+	let header = |hash: Block::Hash| client.header(BlockId::Hash(hash))
+		.map_err(client_err)?
+		.ok_or_else(|| format!("Unknown block {:?}", hash));
+
+	let from_number = *header(from)?.number();
+	let mut hashes = Vec::new();
+	let mut hash = to;
+	loop {
+		let current = header(hash)?;
+		if *current.number() < from_number {
+			return Err(format!("{:?} is not an ancestor of {:?}", from, to));
+		}
+		hashes.push(hash);
+		if hash == from {
+			break;
+		}
+		hash = *current.parent_hash();
+	}
+	hashes.reverse();
+	job.lock().total = Some(hashes.len() as u64);
+
+	let mut last_values = vec![None; keys.len()];
+	for (index, hash) in hashes.into_iter().enumerate() {
+		let block = BlockId::Hash(hash);
+		let mut changes = Vec::new();
+		for (key, last_value) in keys.iter().zip(last_values.iter_mut()) {
+			let value = client.storage(&block, key).map_err(client_err)?;
+			if index == 0 || value != *last_value {
+				changes.push((key.clone(), value.clone()));
+				*last_value = value;
+			}
+		}
+
+		let item = if index == 0 || !changes.is_empty() {
+			Some(JobItem::ChangeSet(StorageChangeSet { block: hash, changes }))
+		} else {
+			None
+		};
+		if !record(job, item)? {
+			break;
+		}
+	}
+
+	Ok(())
+}
+
+/// Count one more processed item of `job`, and add `item` to its results.
+///
+/// Returns `Ok(false)` if the job was removed and should stop.
+fn record<Hash>(
+	job: &SharedJob<Hash>,
+	item: Option<JobItem<Hash>>,
+) -> std::result::Result<bool, String> {
+	let mut job = job.lock();
+	if job.removed {
+		return Ok(false);
+	}
+
+	job.processed += 1;
+	if let Some(item) = item {
+		if job.results.len() >= MAX_JOB_RESULTS {
+			return Err(format!("Query has more than {} results", MAX_JOB_RESULTS));
+		}
+		job.results.push(item);
+	}
+	Ok(true)
+}
+
+fn client_err(err: sp_blockchain::Error) -> String {
+	format!("Client error: {}", err)
+}
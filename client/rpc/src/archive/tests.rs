@@ -0,0 +1,158 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::*;
+
+use std::{thread, time::Duration};
+use assert_matches::assert_matches;
+use sp_core::storage::StorageData;
+use sc_block_builder::BlockBuilderProvider;
+use substrate_test_runtime_client::{
+	prelude::*,
+	sp_consensus::BlockOrigin,
+	runtime,
+	Backend,
+};
+
+type TestArchive = Archive<runtime::Block, Backend, TestClient>;
+
+fn archive(client: TestClient, deny_unsafe: DenyUnsafe) -> TestArchive {
+	Archive::new(Arc::new(client), Jobs::default(), deny_unsafe)
+}
+
+fn wait_for_job(api: &TestArchive, id: JobId) -> JobStatus {
+	for _ in 0..100 {
+		let status = api.job_status(id).unwrap();
+		if status.state != JobState::Running {
+			return status;
+		}
+		thread::sleep(Duration::from_millis(50));
+	}
+	panic!("Job {} did not complete", id);
+}
+
+#[test]
+fn should_query_keys_and_pairs() {
+	let client = TestClientBuilder::new()
+		.add_extra_storage(b":mock".to_vec(), b"hello".to_vec())
+		.build();
+	let api = archive(client, DenyUnsafe::No);
+	let prefix = StorageKey(b":mo".to_vec());
+
+	let id = api.start_query(Query::Keys { prefix: prefix.clone(), at: None }).unwrap();
+	assert_eq!(wait_for_job(&api, id).state, JobState::Done);
+	assert_eq!(
+		api.job_results(id, 0, MAX_PAGE_SIZE).unwrap(),
+		vec![JobItem::Key(StorageKey(b":mock".to_vec()))],
+	);
+
+	let id = api.start_query(Query::Pairs { prefix, at: None }).unwrap();
+	assert_eq!(wait_for_job(&api, id).state, JobState::Done);
+	assert_eq!(
+		api.job_results(id, 0, MAX_PAGE_SIZE).unwrap(),
+		vec![JobItem::Pair(StorageKey(b":mock".to_vec()), StorageData(b"hello".to_vec()))],
+	);
+}
+
+#[test]
+fn should_query_range() {
+	let mut client = substrate_test_runtime_client::new();
+	let mut add_block = |nonce| {
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_storage_change(vec![1], Some(vec![1])).unwrap();
+		builder.push_storage_change(vec![2], Some(vec![nonce])).unwrap();
+		let block = builder.build().unwrap().block;
+		let hash = block.header.hash();
+		client.import(BlockOrigin::Own, block).unwrap();
+		hash
+	};
+	let block1_hash = add_block(0);
+	let block2_hash = add_block(1);
+	let genesis_hash = client.genesis_hash();
+	let api = archive(client, DenyUnsafe::No);
+
+	let keys = vec![StorageKey(vec![1]), StorageKey(vec![2])];
+	let id = api.start_query(Query::Range { keys, from: genesis_hash, to: None }).unwrap();
+	let status = wait_for_job(&api, id);
+	assert_eq!(status.state, JobState::Done);
+	assert_eq!(status.processed, 3);
+	assert_eq!(status.total, Some(3));
+	assert_eq!(status.results, 3);
+
+	assert_eq!(
+		api.job_results(id, 1, 2).unwrap(),
+		vec![
+			JobItem::ChangeSet(StorageChangeSet {
+				block: block1_hash,
+				changes: vec![
+					(StorageKey(vec![1]), Some(StorageData(vec![1]))),
+					(StorageKey(vec![2]), Some(StorageData(vec![0]))),
+				],
+			}),
+			JobItem::ChangeSet(StorageChangeSet {
+				block: block2_hash,
+				changes: vec![(StorageKey(vec![2]), Some(StorageData(vec![1])))],
+			}),
+		],
+	);
+	assert_eq!(api.job_results(id, 3, 10).unwrap(), vec![]);
+
+	// The range must go forward.
+	let keys = vec![StorageKey(vec![1])];
+	let id = api.start_query(Query::Range { keys, from: block2_hash, to: Some(block1_hash) }).unwrap();
+	assert_matches!(wait_for_job(&api, id).state, JobState::Failed(_));
+}
+
+#[test]
+fn should_remove_jobs() {
+	let api = archive(substrate_test_runtime_client::new(), DenyUnsafe::No);
+	let id = api.start_query(Query::Keys { prefix: StorageKey(vec![]), at: None }).unwrap();
+
+	assert_matches!(
+		api.job_results(id, 0, MAX_PAGE_SIZE + 1),
+		Err(Error::InvalidCount { .. })
+	);
+	assert_matches!(api.remove_job(id), Ok(true));
+	assert_matches!(api.remove_job(id), Ok(false));
+	assert_matches!(api.job_status(id), Err(Error::UnknownJob(_)));
+}
+
+#[test]
+fn should_evict_completed_jobs() {
+	let api = archive(substrate_test_runtime_client::new(), DenyUnsafe::No);
+	let first = api.start_query(Query::Keys { prefix: StorageKey(vec![]), at: None }).unwrap();
+	for _ in 1..MAX_JOBS {
+		api.start_query(Query::Keys { prefix: StorageKey(vec![]), at: None }).unwrap();
+	}
+	wait_for_job(&api, first);
+
+	let id = api.start_query(Query::Keys { prefix: StorageKey(vec![]), at: None }).unwrap();
+	assert_matches!(api.job_status(id), Ok(_));
+	assert_matches!(api.job_status(first), Err(Error::UnknownJob(_)));
+}
+
+#[test]
+fn archive_calls_considered_unsafe() {
+	let api = archive(substrate_test_runtime_client::new(), DenyUnsafe::Yes);
+
+	assert_matches!(
+		api.start_query(Query::Keys { prefix: StorageKey(vec![]), at: None }),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+	assert_matches!(api.job_status(0), Err(Error::UnsafeRpcCalled(_)));
+	assert_matches!(api.job_results(0, 0, 1), Err(Error::UnsafeRpcCalled(_)));
+	assert_matches!(api.remove_job(0), Err(Error::UnsafeRpcCalled(_)));
+}
@@ -76,11 +76,17 @@ impl Default for TestSetup {
 
 impl TestSetup {
 	fn author(&self) -> Author<FullTransactionPool, Client<Backend>> {
+		self.author_with_policy(DenyUnsafe::No)
+	}
+
+	fn author_with_policy(&self, deny_unsafe: DenyUnsafe) -> Author<FullTransactionPool, Client<Backend>> {
 		Author {
 			client: self.client.clone(),
 			pool: self.pool.clone(),
 			subscriptions: Subscriptions::new(Arc::new(self.runtime.executor())),
 			keystore: self.keystore.clone(),
+			max_extrinsic_size: None,
+			deny_unsafe,
 		}
 	}
 }
@@ -100,6 +106,19 @@ fn submit_transaction_should_not_cause_error() {
 	);
 }
 
+#[test]
+fn submit_transaction_above_size_limit_should_be_rejected() {
+	let mut p = TestSetup::default().author();
+	let xt = uxt(AccountKeyring::Alice, 1).encode();
+	p.max_extrinsic_size = Some(xt.len() - 1);
+
+	assert_matches!(
+		AuthorApi::submit_extrinsic(&p, xt.clone().into()).wait(),
+		Err(Error::ExtrinsicTooLarge { size, .. }) if size == xt.len()
+	);
+	assert!(p.pool.ready().next().is_none());
+}
+
 #[test]
 fn submit_rich_transaction_should_not_cause_error() {
 	let p = TestSetup::default().author();
@@ -221,6 +240,28 @@ fn should_insert_key() {
 	assert_eq!(key_pair.public(), store_key_pair.public());
 }
 
+#[test]
+fn should_deny_unsafe_calls() {
+	let setup = TestSetup::default();
+	let p = setup.author_with_policy(DenyUnsafe::Yes);
+
+	let suri = "//Alice";
+	let key_pair = ed25519::Pair::from_string(suri, None).expect("Generates keypair");
+	assert_matches!(
+		p.insert_key(
+			String::from_utf8(ED25519.0.to_vec()).expect("Keytype is a valid string"),
+			suri.to_string(),
+			key_pair.public().0.to_vec().into(),
+		),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+	assert_matches!(p.rotate_keys(), Err(Error::UnsafeRpcCalled(_)));
+	assert!(setup.keystore.read().ed25519_key_pair(ED25519, &key_pair.public()).is_none());
+
+	// safe calls are still served.
+	assert!(p.pending_extrinsics().is_ok());
+}
+
 #[test]
 fn should_rotate_keys() {
 	let setup = TestSetup::default();
@@ -17,13 +17,18 @@
 //! Blockchain API backend for full nodes.
 
 use std::sync::Arc;
+use codec::{Decode, Encode};
 use rpc::futures::future::result;
 
 use sc_rpc_api::Subscriptions;
-use sc_client_api::{BlockchainEvents, BlockBackend};
-use sp_runtime::{generic::{BlockId, SignedBlock}, traits::{Block as BlockT}};
+use sc_client_api::{BlockchainEvents, BlockBackend, CallExecutor, ExecutorProvider, ProofProvider};
+use sp_api::RuntimeApiInfo;
+use sp_block_builder::BlockWeight;
+use sp_consensus::BlockStatus;
+use sp_runtime::{generic::{BlockId, SignedBlock}, traits::{Block as BlockT, Header as HeaderT}};
+use sp_state_machine::ExecutionStrategy;
 
-use super::{ChainBackend, client_err, error::FutureResult};
+use super::{BlockStats, ChainBackend, client_err, error::{FutureResult, Result}};
 use std::marker::PhantomData;
 use sp_blockchain::HeaderBackend;
 
@@ -50,7 +55,8 @@ impl<Block: BlockT, Client> FullChain<Block, Client> {
 
 impl<Block, Client> ChainBackend<Client, Block> for FullChain<Block, Client> where
 	Block: BlockT + 'static,
-	Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
+	Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block>
+		+ ExecutorProvider<Block> + ProofProvider<Block> + 'static,
 {
 	fn client(&self) -> &Arc<Client> {
 		&self.client
@@ -75,4 +81,58 @@ impl<Block, Client> ChainBackend<Client, Block> for FullChain<Block, Client> whe
 			.map_err(client_err)
 		))
 	}
+
+	fn block_stats(&self, hash: Block::Hash) -> Result<Option<BlockStats>> {
+		block_stats(&*self.client, hash).map_err(client_err)
+	}
+}
+
+/// Gathers the statistics of a block. The weight and the witness are only measured if the state of
+/// the parent block is available.
+fn block_stats<Block, Client>(
+	client: &Client,
+	hash: Block::Hash,
+) -> sp_blockchain::Result<Option<BlockStats>> where
+	Block: BlockT,
+	Client: BlockBackend<Block> + ExecutorProvider<Block> + ProofProvider<Block>,
+{
+	let block = match client.block(&BlockId::Hash(hash))? {
+		Some(signed) => signed.block,
+		None => return Ok(None),
+	};
+	let mut stats = BlockStats {
+		extrinsics: block.extrinsics().len() as u64,
+		length: block.encode().len() as u64,
+		weight: None,
+		witness_size: None,
+		import_time: client.import_time(&hash).map(|time| time.as_nanos() as u64),
+	};
+
+	let (mut header, extrinsics) = block.deconstruct();
+	let parent = BlockId::Hash(*header.parent_hash());
+	if client.block_status(&parent)? != BlockStatus::InChainWithState {
+		return Ok(Some(stats));
+	}
+
+	let version = client.executor().runtime_version(&parent)?;
+	if version.has_api_with(&<dyn BlockWeight<Block>>::ID, |_| true) {
+		let weight = client.executor().call(
+			&parent,
+			"BlockWeight_extrinsics_weight",
+			&extrinsics.encode(),
+			ExecutionStrategy::NativeElseWasm,
+			None,
+		)?;
+		stats.weight = Some(u64::decode(&mut &weight[..]).map_err(|e|
+			sp_blockchain::Error::Msg(format!("Invalid block weight: {}", e.what()))
+		)?);
+	}
+
+	// blocks are imported without their seal, so it is removed before the execution.
+	header.digest_mut().logs.retain(|item| item.as_seal().is_none());
+	let block = Block::new(header, extrinsics);
+	let (_, proof) = client.execution_proof(&parent, "Core_execute_block", &block.encode())?;
+	stats.witness_size = Some(proof.iter_nodes().map(|node| node.len() as u64).sum());
+
+	Ok(Some(stats))
 }
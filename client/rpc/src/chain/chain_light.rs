@@ -26,12 +26,14 @@ use sc_client::{
 };
 use sp_runtime::{
 	generic::{BlockId, SignedBlock},
-	traits::{Block as BlockT},
+	traits::{Block as BlockT, NumberFor},
 };
 
-use super::{ChainBackend, client_err, error::FutureResult};
+use super::{
+	ChainBackend, BlockStats, ExtrinsicLocation, client_err, error::{Error, FutureResult, Result},
+};
 use sp_blockchain::HeaderBackend;
-use sc_client_api::BlockchainEvents;
+use sc_client_api::{BlockchainEvents, BlockBackend};
 
 /// Blockchain API backend for light nodes. Reads all the data from local
 /// database, if available, or fetches it from remote node otherwise.
@@ -65,7 +67,7 @@ impl<Block: BlockT, Client, F: Fetcher<Block>> LightChain<Block, Client, F> {
 
 impl<Block, Client, F> ChainBackend<Client, Block> for LightChain<Block, Client, F> where
 	Block: BlockT + 'static,
-	Client: BlockchainEvents<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	Client: BlockBackend<Block> + BlockchainEvents<Block> + HeaderBackend<Block> + Send + Sync + 'static,
 	F: Fetcher<Block> + Send + Sync + 'static,
 {
 	fn client(&self) -> &Arc<Client> {
@@ -115,4 +117,16 @@ impl<Block, Client, F> ChainBackend<Client, Block> for LightChain<Block, Client,
 
 		Box::new(block)
 	}
+
+	fn find_extrinsic(
+		&self,
+		_hash: Block::Hash,
+	) -> Result<Vec<ExtrinsicLocation<Block::Hash, NumberFor<Block>>>> {
+		// light clients don't store the block bodies.
+		Err(Error::Other("Searching extrinsics is not supported by light clients".into()))
+	}
+
+	fn block_stats(&self, _hash: Block::Hash) -> Result<Option<BlockStats>> {
+		Err(Error::Other("Block statistics are not supported by light clients".into()))
+	}
 }
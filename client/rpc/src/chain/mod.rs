@@ -22,7 +22,7 @@ mod chain_light;
 #[cfg(test)]
 mod tests;
 
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 use futures::{future, StreamExt, TryStreamExt};
 use log::warn;
 use rpc::{
@@ -30,7 +30,7 @@ use rpc::{
 	futures::{stream, Future, Sink, Stream},
 };
 
-use sc_rpc_api::Subscriptions;
+use sc_rpc_api::{DenyUnsafe, Subscriptions};
 use sc_client::{
 	self, BlockchainEvents,
 	light::{fetcher::Fetcher, blockchain::RemoteBlockchain},
@@ -39,20 +39,20 @@ use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use sp_rpc::{number::NumberOrHex, list::ListOrValue};
 use sp_runtime::{
 	generic::{BlockId, SignedBlock},
-	traits::{Block as BlockT, Header, NumberFor},
+	traits::{Block as BlockT, Header, NumberFor, HashFor, Hash as HashT, One, Zero},
 };
 
 use self::error::{Result, Error, FutureResult};
 
 pub use sc_rpc_api::chain::*;
 use sp_blockchain::HeaderBackend;
-use sc_client_api::BlockBackend;
+use sc_client_api::{BlockBackend, ExecutorProvider, ProofProvider};
 
 /// Blockchain backend API
 trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 	where
 		Block: BlockT + 'static,
-		Client: HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
+		Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
 {
 	/// Get client reference.
 	fn client(&self) -> &Arc<Client>;
@@ -95,6 +95,25 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 		Ok(self.client().info().finalized_hash)
 	}
 
+	/// Locate a block on any known fork.
+	fn find_block(
+		&self,
+		hash: Block::Hash,
+	) -> Result<Option<BlockLocation<Block::Hash, NumberFor<Block>>>> {
+		locate_block(&**self.client(), hash).map_err(client_err)
+	}
+
+	/// Locate the unfinalized blocks containing the given extrinsic on any known fork.
+	fn find_extrinsic(
+		&self,
+		hash: Block::Hash,
+	) -> Result<Vec<ExtrinsicLocation<Block::Hash, NumberFor<Block>>>> {
+		locate_extrinsic(&**self.client(), hash).map_err(client_err)
+	}
+
+	/// Get the statistics of a block.
+	fn block_stats(&self, hash: Block::Hash) -> Result<Option<BlockStats>>;
+
 	/// All new head subscription
 	fn subscribe_all_heads(
 		&self,
@@ -179,13 +198,16 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 pub fn new_full<Block: BlockT, Client>(
 	client: Arc<Client>,
 	subscriptions: Subscriptions,
+	deny_unsafe: DenyUnsafe,
 ) -> Chain<Block, Client>
 	where
 		Block: BlockT + 'static,
-		Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
+		Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block>
+			+ ExecutorProvider<Block> + ProofProvider<Block> + 'static,
 {
 	Chain {
 		backend: Box::new(self::chain_full::FullChain::new(client, subscriptions)),
+		deny_unsafe,
 	}
 }
 
@@ -195,6 +217,7 @@ pub fn new_light<Block: BlockT, Client, F: Fetcher<Block>>(
 	subscriptions: Subscriptions,
 	remote_blockchain: Arc<dyn RemoteBlockchain<Block>>,
 	fetcher: Arc<F>,
+	deny_unsafe: DenyUnsafe,
 ) -> Chain<Block, Client>
 	where
 		Block: BlockT + 'static,
@@ -208,19 +231,22 @@ pub fn new_light<Block: BlockT, Client, F: Fetcher<Block>>(
 			remote_blockchain,
 			fetcher,
 		)),
+		deny_unsafe,
 	}
 }
 
 /// Chain API with subscriptions support.
 pub struct Chain<Block: BlockT, Client> {
 	backend: Box<dyn ChainBackend<Client, Block>>,
+	/// Whether to deny unsafe calls
+	deny_unsafe: DenyUnsafe,
 }
 
 impl<Block, Client> ChainApi<NumberFor<Block>, Block::Hash, Block::Header, SignedBlock<Block>> for
 	Chain<Block, Client>
 		where
 			Block: BlockT + 'static,
-			Client: HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
+			Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
 {
 	type Metadata = crate::metadata::Metadata;
 
@@ -252,6 +278,22 @@ impl<Block, Client> ChainApi<NumberFor<Block>, Block::Hash, Block::Header, Signe
 		self.backend.finalized_head()
 	}
 
+	fn find_block(&self, hash: Block::Hash) -> Result<Option<BlockLocation<Block::Hash, NumberFor<Block>>>> {
+		self.backend.find_block(hash)
+	}
+
+	fn find_extrinsic(
+		&self,
+		hash: Block::Hash,
+	) -> Result<Vec<ExtrinsicLocation<Block::Hash, NumberFor<Block>>>> {
+		self.backend.find_extrinsic(hash)
+	}
+
+	fn block_stats(&self, hash: Block::Hash) -> Result<Option<BlockStats>> {
+		self.deny_unsafe.check_if_safe()?;
+		self.backend.block_stats(hash)
+	}
+
 	fn subscribe_all_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Block::Header>) {
 		self.backend.subscribe_all_heads(metadata, subscriber)
 	}
@@ -317,6 +359,118 @@ fn subscribe_headers<Block, Client, F, G, S, ERR>(
 	});
 }
 
+/// Locate a block within the tree of known blocks.
+fn locate_block<Block, Client>(
+	client: &Client,
+	hash: Block::Hash,
+) -> sp_blockchain::Result<Option<BlockLocation<Block::Hash, NumberFor<Block>>>> where
+	Block: BlockT,
+	Client: BlockBackend<Block> + HeaderBackend<Block>,
+{
+	let number = match client.number(hash)? {
+		Some(number) => number,
+		None => return Ok(None),
+	};
+
+	let info = client.info();
+	let canonical = client.hash(number)? == Some(hash);
+	let finality = if number <= info.finalized_number {
+		if canonical { FinalityStatus::Finalized } else { FinalityStatus::Stale }
+	} else if ancestor_at(client, hash, number, info.finalized_number)? == info.finalized_hash {
+		FinalityStatus::Unfinalized
+	} else {
+		FinalityStatus::Stale
+	};
+
+	let mut forks = Vec::new();
+	for leaf in client.leaves()? {
+		let leaf_number = match client.number(leaf)? {
+			Some(leaf_number) if leaf_number >= number => leaf_number,
+			_ => continue,
+		};
+		if ancestor_at(client, leaf, leaf_number, number)? == hash {
+			forks.push(ForkLocation { leaf, depth: leaf_number - number });
+		}
+	}
+
+	Ok(Some(BlockLocation { hash, number, canonical, finality, forks }))
+}
+
+/// Locate the blocks containing the given extrinsic within the tree of known blocks.
+///
+/// Every block that isn't finalized is searched, starting from the leaves.
+fn locate_extrinsic<Block, Client>(
+	client: &Client,
+	hash: Block::Hash,
+) -> sp_blockchain::Result<Vec<ExtrinsicLocation<Block::Hash, NumberFor<Block>>>> where
+	Block: BlockT,
+	Client: BlockBackend<Block> + HeaderBackend<Block>,
+{
+	let info = client.info();
+	let mut visited = HashSet::new();
+	let mut found = Vec::new();
+
+	for leaf in client.leaves()? {
+		let mut current = leaf;
+		while visited.insert(current) {
+			let header = match client.header(BlockId::Hash(current))? {
+				Some(header) => header,
+				None => break,
+			};
+			let number = *header.number();
+			if number <= info.finalized_number && client.hash(number)? == Some(current) {
+				break
+			}
+
+			let extrinsics = client.block_body(&BlockId::Hash(current))?.unwrap_or_default();
+			for (index, extrinsic) in extrinsics.iter().enumerate() {
+				if HashFor::<Block>::hash_of(extrinsic) == hash {
+					found.push((current, index as u32));
+				}
+			}
+
+			if number.is_zero() {
+				break
+			}
+			current = *header.parent_hash();
+		}
+	}
+
+	let mut locations = Vec::with_capacity(found.len());
+	for (block, index) in found {
+		if let Some(block) = locate_block(client, block)? {
+			locations.push(ExtrinsicLocation { block, index });
+		}
+	}
+	Ok(locations)
+}
+
+/// Returns the hash of the ancestor at `target` of the block `hash` with the given `number`.
+fn ancestor_at<Block, Client>(
+	client: &Client,
+	mut hash: Block::Hash,
+	mut number: NumberFor<Block>,
+	target: NumberFor<Block>,
+) -> sp_blockchain::Result<Block::Hash> where
+	Block: BlockT,
+	Client: HeaderBackend<Block>,
+{
+	while number > target {
+		// the ancestors of a block of the canon chain can be looked up by number.
+		if client.hash(number)? == Some(hash) {
+			return client.hash(target)?
+				.ok_or_else(|| sp_blockchain::Error::UnknownBlock(format!("{}", target)))
+		}
+
+		hash = *client.header(BlockId::Hash(hash))?
+			.ok_or_else(|| sp_blockchain::Error::UnknownBlock(format!("{}", hash)))?
+			.parent_hash();
+		number -= One::one();
+	}
+
+	Ok(hash)
+}
+
 fn client_err(err: sp_blockchain::Error) -> Error {
 	Error::Client(Box::new(err))
 }
@@ -19,9 +19,10 @@ use assert_matches::assert_matches;
 use substrate_test_runtime_client::{
 	prelude::*,
 	sp_consensus::BlockOrigin,
-	runtime::{H256, Block, Header},
+	runtime::{H256, Block, Header, Transfer},
 };
 use sp_rpc::list::ListOrValue;
+use codec::Encode;
 use sc_block_builder::BlockBuilderProvider;
 
 #[test]
@@ -30,7 +31,7 @@ fn should_return_header() {
 	let remote = core.executor();
 
 	let client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)));
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)), DenyUnsafe::No);
 
 	assert_matches!(
 		api.header(Some(client.genesis_hash()).into()).wait(),
@@ -65,7 +66,7 @@ fn should_return_a_block() {
 	let remote = core.executor();
 
 	let mut client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)));
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)), DenyUnsafe::No);
 
 	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
 	let block_hash = block.hash();
@@ -119,7 +120,7 @@ fn should_return_block_hash() {
 	let remote = core.executor();
 
 	let mut client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)));
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)), DenyUnsafe::No);
 
 	assert_matches!(
 		api.block_hash(None.into()),
@@ -166,7 +167,7 @@ fn should_return_finalized_hash() {
 	let remote = core.executor();
 
 	let mut client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)));
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)), DenyUnsafe::No);
 
 	assert_matches!(
 		api.finalized_head(),
@@ -190,6 +191,59 @@ fn should_return_finalized_hash() {
 	);
 }
 
+#[test]
+fn should_find_blocks_and_extrinsics_on_forks() {
+	let core = ::tokio::runtime::Runtime::new().unwrap();
+	let remote = core.executor();
+
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)), DenyUnsafe::No);
+
+	// G -> A1 -> A2
+	//  \
+	//   -> B1
+	let a1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a1.clone()).unwrap();
+	let a2 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a2.clone()).unwrap();
+
+	let mut b1 = client.new_block_at(&BlockId::Number(0), Default::default(), false).unwrap();
+	b1.push_transfer(Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 1,
+		nonce: 0,
+	}).unwrap();
+	let b1 = b1.build().unwrap().block;
+	client.import(BlockOrigin::Own, b1.clone()).unwrap();
+
+	assert_eq!(api.find_block(a1.hash()).unwrap(), Some(BlockLocation {
+		hash: a1.hash(),
+		number: 1,
+		canonical: true,
+		finality: FinalityStatus::Unfinalized,
+		forks: vec![ForkLocation { leaf: a2.hash(), depth: 1 }],
+	}));
+	assert_eq!(api.find_block(b1.hash()).unwrap(), Some(BlockLocation {
+		hash: b1.hash(),
+		number: 1,
+		canonical: false,
+		finality: FinalityStatus::Unfinalized,
+		forks: vec![ForkLocation { leaf: b1.hash(), depth: 0 }],
+	}));
+	assert_eq!(api.find_block(H256::from_low_u64_be(5)).unwrap(), None);
+
+	let extrinsic_hash = HashFor::<Block>::hash_of(&b1.extrinsics[0]);
+	let found = api.find_extrinsic(extrinsic_hash).unwrap();
+	assert_eq!(found.len(), 1);
+	assert_eq!(found[0].block.hash, b1.hash());
+	assert_eq!(found[0].index, 0);
+
+	client.finalize_block(BlockId::Hash(a2.hash()), None).unwrap();
+	assert_eq!(api.find_block(a1.hash()).unwrap().unwrap().finality, FinalityStatus::Finalized);
+	assert_eq!(api.find_block(b1.hash()).unwrap().unwrap().finality, FinalityStatus::Stale);
+}
+
 #[test]
 fn should_notify_about_latest_block() {
 	let mut core = ::tokio::runtime::Runtime::new().unwrap();
@@ -198,7 +252,7 @@ fn should_notify_about_latest_block() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)));
+		let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)), DenyUnsafe::No);
 
 		api.subscribe_all_heads(Default::default(), subscriber);
 
@@ -227,7 +281,7 @@ fn should_notify_about_best_block() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)));
+		let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)), DenyUnsafe::No);
 
 		api.subscribe_new_heads(Default::default(), subscriber);
 
@@ -248,6 +302,37 @@ fn should_notify_about_best_block() {
 	assert_eq!(core.block_on(next.into_future()).unwrap().0, None);
 }
 
+#[test]
+fn should_return_block_stats() {
+	let core = tokio::runtime::Runtime::new().unwrap();
+	let remote = core.executor();
+
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)), DenyUnsafe::No);
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_transfer(Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 42,
+		nonce: 0,
+	}).unwrap();
+	let block = builder.build().unwrap().block;
+	let hash = block.header.hash();
+	client.import(BlockOrigin::Own, block.clone()).unwrap();
+
+	let stats = api.block_stats(hash).unwrap().unwrap();
+	assert_eq!(stats.extrinsics, 1);
+	assert_eq!(stats.length, block.encode().len() as u64);
+	assert!(stats.witness_size.unwrap() > 0);
+	assert!(stats.import_time.is_some());
+	assert_matches!(api.block_stats(H256::from_low_u64_be(5)), Ok(None));
+
+	let core = tokio::runtime::Runtime::new().unwrap();
+	let api = new_full(client, Subscriptions::new(Arc::new(core.executor())), DenyUnsafe::Yes);
+	assert_matches!(api.block_stats(hash), Err(Error::UnsafeRpcCalled(_)));
+}
+
 #[test]
 fn should_notify_about_finalized_block() {
 	let mut core = ::tokio::runtime::Runtime::new().unwrap();
@@ -256,7 +341,7 @@ fn should_notify_about_finalized_block() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)));
+		let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)), DenyUnsafe::No);
 
 		api.subscribe_finalized_heads(Default::default(), subscriber);
 
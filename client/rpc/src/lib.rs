@@ -22,10 +22,11 @@
 
 mod metadata;
 
-pub use sc_rpc_api::Subscriptions;
+pub use sc_rpc_api::{DenyUnsafe, Subscriptions};
 pub use self::metadata::Metadata;
 pub use rpc::IoHandlerExtension as RpcExtension;
 
+pub mod archive;
 pub mod author;
 pub mod chain;
 pub mod offchain;
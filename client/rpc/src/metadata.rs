@@ -15,9 +15,10 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 //! RPC Metadata
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
 
 use jsonrpc_pubsub::{Session, PubSubMetadata};
+use sc_rpc_server::RemoteAddress;
 use rpc::futures::sync::mpsc;
 
 /// RPC Metadata.
@@ -28,6 +29,7 @@ use rpc::futures::sync::mpsc;
 #[derive(Default, Clone)]
 pub struct Metadata {
 	session: Option<Arc<Session>>,
+	remote_address: Option<IpAddr>,
 }
 
 impl rpc::Metadata for Metadata {}
@@ -37,11 +39,18 @@ impl PubSubMetadata for Metadata {
 	}
 }
 
+impl RemoteAddress for Metadata {
+	fn remote_address(&self) -> Option<IpAddr> {
+		self.remote_address
+	}
+}
+
 impl Metadata {
 	/// Create new `Metadata` with session (Pub/Sub) support.
 	pub fn new(transport: mpsc::Sender<String>) -> Self {
 		Metadata {
 			session: Some(Arc::new(Session::new(transport))),
+			remote_address: None,
 		}
 	}
 
@@ -58,3 +67,9 @@ impl From<mpsc::Sender<String>> for Metadata {
 		Self::new(sender)
 	}
 }
+
+impl From<Option<IpAddr>> for Metadata {
+	fn from(remote_address: Option<IpAddr>) -> Self {
+		Metadata { session: None, remote_address }
+	}
+}
@@ -15,13 +15,14 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::*;
+use super::error::Error;
 use assert_matches::assert_matches;
 use sp_core::{Bytes, offchain::storage::InMemOffchainStorage};
 
 #[test]
 fn local_storage_should_work() {
 	let storage = InMemOffchainStorage::default();
-	let offchain = Offchain::new(storage);
+	let offchain = Offchain::new(storage, DenyUnsafe::No);
 	let key = Bytes(b"offchain_storage".to_vec());
 	let value = Bytes(b"offchain_value".to_vec());
 
@@ -30,7 +31,34 @@ fn local_storage_should_work() {
 		Ok(())
 	);
 	assert_matches!(
-		offchain.get_local_storage(StorageKind::PERSISTENT, key),
+		offchain.get_local_storage(StorageKind::PERSISTENT, key.clone()),
 		Ok(Some(ref v)) if *v == value
 	);
+	assert_matches!(offchain.get_local_storage(StorageKind::LOCAL, key.clone()), Ok(None));
+
+	assert_matches!(
+		offchain.set_local_storage(StorageKind::LOCAL, key.clone(), Bytes(b"local".to_vec())),
+		Ok(())
+	);
+	assert_matches!(
+		offchain.get_local_storage(StorageKind::LOCAL, key),
+		Ok(Some(ref v)) if v.0 == b"local".to_vec()
+	);
+}
+
+#[test]
+fn offchain_calls_considered_unsafe() {
+	let storage = InMemOffchainStorage::default();
+	let offchain = Offchain::new(storage, DenyUnsafe::Yes);
+	let key = Bytes(b"offchain_storage".to_vec());
+	let value = Bytes(b"offchain_value".to_vec());
+
+	assert_matches!(
+		offchain.set_local_storage(StorageKind::PERSISTENT, key.clone(), value.clone()),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+	assert_matches!(
+		offchain.get_local_storage(StorageKind::PERSISTENT, key),
+		Err(Error::UnsafeRpcCalled(_))
+	);
 }
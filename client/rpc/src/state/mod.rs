@@ -18,6 +18,7 @@
 
 mod state_full;
 mod state_light;
+mod throttle;
 
 #[cfg(test)]
 mod tests;
@@ -26,9 +27,10 @@ use std::sync::Arc;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use rpc::{Result as RpcResult, futures::{Future, future::result}};
 
-use sc_rpc_api::Subscriptions;
+use sc_rpc_api::{DenyUnsafe, Subscriptions};
 use sc_client::{light::{blockchain::RemoteBlockchain, fetcher::Fetcher}};
 use sp_core::{Bytes, storage::{StorageKey, StorageData, StorageChangeSet}};
+use sp_rpc::tracing::BlockTrace;
 use sp_version::RuntimeVersion;
 use sp_runtime::traits::Block as BlockT;
 
@@ -36,12 +38,19 @@ use sp_api::{Metadata, ProvideRuntimeApi, CallApiAt};
 
 use self::error::{Error, FutureResult};
 
+pub use self::throttle::StorageSubscriptionThrottle;
+
 pub use sc_rpc_api::state::*;
-use sc_client_api::{ExecutorProvider, StorageProvider, BlockchainEvents, Backend};
+pub use sc_rpc_api::child_state::{ChildStateApi, ChildStateClient, ReadProof};
+use sc_client_api::{
+	ExecutorProvider, StorageProvider, BlockchainEvents, Backend, BlockBackend, ProofProvider,
+};
 use sp_blockchain::{HeaderMetadata, HeaderBackend};
 
 const STORAGE_KEYS_PAGED_MAX_COUNT: u32 = 1000;
 
+const CALL_BATCH_MAX_COUNT: usize = 256;
+
 /// State backend API.
 pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 	where
@@ -56,6 +65,13 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		call_data: Bytes,
 	) -> FutureResult<Bytes>;
 
+	/// Call runtime methods in order at given block, on top of the same state.
+	fn call_batch(
+		&self,
+		block: Option<Block::Hash>,
+		calls: Vec<(String, Bytes)>,
+	) -> FutureResult<Vec<Bytes>>;
+
 	/// Returns the keys with prefix, leave empty to get all the keys.
 	fn storage_keys(
 		&self,
@@ -146,6 +162,16 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 			.map(|x| x.map(|x| x.0.len() as u64)))
 	}
 
+	/// Returns a proof of the child storage entries of `keys` at a block's state.
+	fn child_read_proof(
+		&self,
+		block: Option<Block::Hash>,
+		child_storage_key: StorageKey,
+		child_info: StorageKey,
+		child_type: u32,
+		keys: Vec<StorageKey>,
+	) -> FutureResult<ReadProof<Block::Hash>>;
+
 	/// Returns the runtime metadata as an opaque blob.
 	fn metadata(&self, block: Option<Block::Hash>) -> FutureResult<Bytes>;
 
@@ -163,6 +189,13 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		keys: Vec<StorageKey>,
 	) -> FutureResult<Vec<StorageChangeSet<Block::Hash>>>;
 
+	/// Re-executes a block, recording the spans of the given `targets` and the storage accesses.
+	fn trace_block(
+		&self,
+		block: Block::Hash,
+		targets: Option<String>,
+	) -> FutureResult<BlockTrace>;
+
 	/// New runtime version subscription
 	fn subscribe_runtime_version(
 		&self,
@@ -191,34 +224,56 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		_meta: Option<crate::metadata::Metadata>,
 		id: SubscriptionId,
 	) -> RpcResult<bool>;
+
+	/// New storage subscription to the keys starting with one of the `prefixes`.
+	fn subscribe_storage_prefix(
+		&self,
+		_meta: crate::metadata::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		prefixes: Vec<StorageKey>,
+	);
+
+	/// Unsubscribe from storage prefix subscription
+	fn unsubscribe_storage_prefix(
+		&self,
+		_meta: Option<crate::metadata::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool>;
 }
 
-/// Create new state API that works on full node.
+/// Create new state and child state APIs that work on full node.
+///
+/// The notifications of storage subscriptions are throttled if `storage_subscription_throttle`
+/// is set.
 pub fn new_full<BE, Block: BlockT, Client>(
 	client: Arc<Client>,
 	subscriptions: Subscriptions,
-) -> State<Block, Client>
+	storage_subscription_throttle: Option<StorageSubscriptionThrottle>,
+	deny_unsafe: DenyUnsafe,
+) -> (State<Block, Client>, ChildState<Block, Client>)
 	where
 		Block: BlockT + 'static,
 		BE: Backend<Block> + 'static,
 		Client: ExecutorProvider<Block> + StorageProvider<Block, BE> + HeaderBackend<Block>
 			+ HeaderMetadata<Block, Error = sp_blockchain::Error> + BlockchainEvents<Block>
-			+ CallApiAt<Block, Error = sp_blockchain::Error>
-			+ ProvideRuntimeApi<Block> + Send + Sync + 'static,
+			+ CallApiAt<Block, Error = sp_blockchain::Error> + ProofProvider<Block>
+			+ BlockBackend<Block> + ProvideRuntimeApi<Block> + Send + Sync + 'static,
 		Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
 {
-	State {
-		backend: Box::new(self::state_full::FullState::new(client, subscriptions)),
-	}
+	let backend: Arc<dyn StateBackend<Block, Client>> = Arc::new(
+		self::state_full::FullState::new(client, subscriptions, storage_subscription_throttle),
+	);
+	(State { backend: backend.clone(), deny_unsafe }, ChildState { backend })
 }
 
-/// Create new state API that works on light node.
+/// Create new state and child state APIs that work on light node.
 pub fn new_light<BE, Block: BlockT, Client, F: Fetcher<Block>>(
 	client: Arc<Client>,
 	subscriptions: Subscriptions,
 	remote_blockchain: Arc<dyn RemoteBlockchain<Block>>,
 	fetcher: Arc<F>,
-) -> State<Block, Client>
+	deny_unsafe: DenyUnsafe,
+) -> (State<Block, Client>, ChildState<Block, Client>)
 	where
 		Block: BlockT + 'static,
 		BE: Backend<Block> + 'static,
@@ -228,19 +283,17 @@ pub fn new_light<BE, Block: BlockT, Client, F: Fetcher<Block>>(
 			+ Send + Sync + 'static,
 		F: Send + Sync + 'static,
 {
-	State {
-		backend: Box::new(self::state_light::LightState::new(
-			client,
-			subscriptions,
-			remote_blockchain,
-			fetcher,
-		)),
-	}
+	let backend: Arc<dyn StateBackend<Block, Client>> = Arc::new(
+		self::state_light::LightState::new(client, subscriptions, remote_blockchain, fetcher),
+	);
+	(State { backend: backend.clone(), deny_unsafe }, ChildState { backend })
 }
 
 /// State API with subscriptions support.
 pub struct State<Block, Client> {
-	backend: Box<dyn StateBackend<Block, Client>>,
+	backend: Arc<dyn StateBackend<Block, Client>>,
+	/// Whether to deny unsafe calls
+	deny_unsafe: DenyUnsafe,
 }
 
 impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
@@ -254,6 +307,22 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 		self.backend.call(block, method, data)
 	}
 
+	fn call_batch(
+		&self,
+		calls: Vec<(String, Bytes)>,
+		block: Option<Block::Hash>,
+	) -> FutureResult<Vec<Bytes>> {
+		if calls.len() > CALL_BATCH_MAX_COUNT {
+			return Box::new(result(Err(
+				Error::InvalidCount {
+					value: calls.len() as u32,
+					max: CALL_BATCH_MAX_COUNT as u32,
+				}
+			)));
+		}
+		self.backend.call_batch(block, calls)
+	}
+
 	fn storage_keys(
 		&self,
 		key_prefix: StorageKey,
@@ -357,6 +426,13 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 		self.backend.query_storage(from, to, keys)
 	}
 
+	fn trace_block(&self, block: Block::Hash, targets: Option<String>) -> FutureResult<BlockTrace> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())));
+		}
+		self.backend.trace_block(block, targets)
+	}
+
 	fn subscribe_storage(
 		&self,
 		meta: Self::Metadata,
@@ -370,6 +446,23 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 		self.backend.unsubscribe_storage(meta, id)
 	}
 
+	fn subscribe_storage_prefix(
+		&self,
+		meta: Self::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		prefixes: Vec<StorageKey>,
+	) {
+		self.backend.subscribe_storage_prefix(meta, subscriber, prefixes);
+	}
+
+	fn unsubscribe_storage_prefix(
+		&self,
+		meta: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		self.backend.unsubscribe_storage_prefix(meta, id)
+	}
+
 	fn runtime_version(&self, at: Option<Block::Hash>) -> FutureResult<RuntimeVersion> {
 		self.backend.runtime_version(at)
 	}
@@ -387,6 +480,74 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 	}
 }
 
+/// Child state API, sharing the backend of the `State` API.
+pub struct ChildState<Block, Client> {
+	backend: Arc<dyn StateBackend<Block, Client>>,
+}
+
+impl<Block, Client> ChildStateApi<Block::Hash> for ChildState<Block, Client>
+	where
+		Block: BlockT + 'static,
+		Client: Send + Sync + 'static,
+{
+	type Metadata = crate::metadata::Metadata;
+
+	fn storage_keys(
+		&self,
+		child_storage_key: StorageKey,
+		child_info: StorageKey,
+		child_type: u32,
+		key_prefix: StorageKey,
+		block: Option<Block::Hash>
+	) -> FutureResult<Vec<StorageKey>> {
+		self.backend.child_storage_keys(block, child_storage_key, child_info, child_type, key_prefix)
+	}
+
+	fn storage(
+		&self,
+		child_storage_key: StorageKey,
+		child_info: StorageKey,
+		child_type: u32,
+		key: StorageKey,
+		block: Option<Block::Hash>
+	) -> FutureResult<Option<StorageData>> {
+		self.backend.child_storage(block, child_storage_key, child_info, child_type, key)
+	}
+
+	fn storage_hash(
+		&self,
+		child_storage_key: StorageKey,
+		child_info: StorageKey,
+		child_type: u32,
+		key: StorageKey,
+		block: Option<Block::Hash>
+	) -> FutureResult<Option<Block::Hash>> {
+		self.backend.child_storage_hash(block, child_storage_key, child_info, child_type, key)
+	}
+
+	fn storage_size(
+		&self,
+		child_storage_key: StorageKey,
+		child_info: StorageKey,
+		child_type: u32,
+		key: StorageKey,
+		block: Option<Block::Hash>
+	) -> FutureResult<Option<u64>> {
+		self.backend.child_storage_size(block, child_storage_key, child_info, child_type, key)
+	}
+
+	fn read_proof(
+		&self,
+		child_storage_key: StorageKey,
+		child_info: StorageKey,
+		child_type: u32,
+		keys: Vec<StorageKey>,
+		block: Option<Block::Hash>
+	) -> FutureResult<ReadProof<Block::Hash>> {
+		self.backend.child_read_proof(block, child_storage_key, child_info, child_type, keys)
+	}
+}
+
 fn client_err(err: sp_blockchain::Error) -> Error {
 	Error::Client(Box::new(err))
 }
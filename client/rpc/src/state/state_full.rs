@@ -16,9 +16,11 @@
 
 //! State API backend for full nodes.
 
+use std::cell::RefCell;
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::ops::Range;
+use codec::Encode;
 use futures::{future, StreamExt as _, TryStreamExt as _};
 use log::warn;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
@@ -29,18 +31,25 @@ use sc_client_api::backend::Backend;
 use sp_blockchain::{Result as ClientResult, Error as ClientError, HeaderMetadata, CachedHeaderMetadata, HeaderBackend};
 use sc_client::BlockchainEvents;
 use sp_core::{
-	Bytes, storage::{well_known_keys, StorageKey, StorageData, StorageChangeSet, ChildInfo},
+	Bytes, NeverNativeValue,
+	storage::{well_known_keys, StorageKey, StorageData, StorageChangeSet, ChildInfo},
 };
+use sp_rpc::tracing::BlockTrace;
+use sp_state_machine::{ExecutionStrategy, OverlayedChanges};
 use sp_version::RuntimeVersion;
 use sp_runtime::{
-	generic::BlockId, traits::{Block as BlockT, NumberFor, SaturatedConversion},
+	generic::BlockId, traits::{Block as BlockT, Header as HeaderT, NumberFor, SaturatedConversion},
 };
 
-use sp_api::{Metadata, ProvideRuntimeApi, CallApiAt};
+use sp_api::{Metadata, ProvideRuntimeApi, CallApiAt, InitializeBlock};
 
-use super::{StateBackend, error::{FutureResult, Error, Result}, client_err, child_resolution_error};
+use super::{
+	StateBackend, ReadProof, error::{FutureResult, Error, Result}, client_err,
+	child_resolution_error,
+};
+use super::throttle::{StorageChanges, StorageSubscriptionThrottle, Throttled};
 use std::marker::PhantomData;
-use sc_client_api::{CallExecutor, StorageProvider, ExecutorProvider};
+use sc_client_api::{BlockBackend, CallExecutor, StorageProvider, ExecutorProvider, ProofProvider};
 
 /// Ranges to query in state_queryStorage.
 struct QueryStorageRange<Block: BlockT> {
@@ -60,6 +69,7 @@ struct QueryStorageRange<Block: BlockT> {
 pub struct FullState<BE, Block: BlockT, Client> {
 	client: Arc<Client>,
 	subscriptions: Subscriptions,
+	storage_subscription_throttle: Option<StorageSubscriptionThrottle>,
 	_phantom: PhantomData<(BE, Block)>
 }
 
@@ -71,8 +81,12 @@ impl<BE, Block: BlockT, Client> FullState<BE, Block, Client>
 		Block: BlockT + 'static,
 {
 	/// Create new state API backend for full nodes.
-	pub fn new(client: Arc<Client>, subscriptions: Subscriptions) -> Self {
-		Self { client, subscriptions, _phantom: PhantomData }
+	pub fn new(
+		client: Arc<Client>,
+		subscriptions: Subscriptions,
+		storage_subscription_throttle: Option<StorageSubscriptionThrottle>,
+	) -> Self {
+		Self { client, subscriptions, storage_subscription_throttle, _phantom: PhantomData }
 	}
 
 	/// Returns given block hash or best block hash if None is passed.
@@ -218,7 +232,7 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 	Client: ExecutorProvider<Block> + StorageProvider<Block, BE> + HeaderBackend<Block>
 		+ HeaderMetadata<Block, Error = sp_blockchain::Error> + BlockchainEvents<Block>
 		+ CallApiAt<Block, Error = sp_blockchain::Error> + ProvideRuntimeApi<Block>
-		+ Send + Sync + 'static,
+		+ ProofProvider<Block> + BlockBackend<Block> + Send + Sync + 'static,
 	Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
 {
 	fn call(
@@ -243,6 +257,40 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		Box::new(result(r))
 	}
 
+	fn call_batch(
+		&self,
+		block: Option<Block::Hash>,
+		calls: Vec<(String, Bytes)>,
+	) -> FutureResult<Vec<Bytes>> {
+		let r = self.block_or_best(block)
+			.and_then(|block| {
+				let at = BlockId::Hash(block);
+				let strategy = self.client.execution_extensions().strategies().other;
+				let changes = RefCell::new(OverlayedChanges::default());
+				calls.into_iter()
+					.map(|(method, call_data)| self
+						.client
+						.executor()
+						.contextual_call::<_, _, NeverNativeValue, fn() -> _>(
+							|| Ok(()),
+							&at,
+							&method,
+							&*call_data,
+							&changes,
+							None,
+							InitializeBlock::Skip,
+							strategy.get_manager(),
+							None,
+							&None,
+							None,
+						)
+						.map(|result| result.into_encoded().into())
+					)
+					.collect()
+			}).map_err(client_err);
+		Box::new(result(r))
+	}
+
 	fn storage_keys(
 		&self,
 		block: Option<Block::Hash>,
@@ -365,6 +413,29 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 				.map_err(client_err)))
 	}
 
+	fn child_read_proof(
+		&self,
+		block: Option<Block::Hash>,
+		child_storage_key: StorageKey,
+		child_info: StorageKey,
+		child_type: u32,
+		keys: Vec<StorageKey>,
+	) -> FutureResult<ReadProof<Block::Hash>> {
+		Box::new(result(
+			self.block_or_best(block)
+				.and_then(|block| self.client.read_child_proof(
+					&BlockId::Hash(block),
+					&child_storage_key.0,
+					ChildInfo::resolve_child_info(child_type, &child_info.0[..])
+						.ok_or_else(child_resolution_error)?,
+					&mut keys.iter().map(|key| &key.0[..]),
+				).map(|proof| ReadProof {
+					at: block,
+					proof: proof.iter_nodes().map(Into::into).collect(),
+				}))
+				.map_err(client_err)))
+	}
+
 	fn metadata(&self, block: Option<Block::Hash>) -> FutureResult<Bytes> {
 		Box::new(result(
 			self.block_or_best(block)
@@ -398,6 +469,38 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		Box::new(result(call_fn()))
 	}
 
+	fn trace_block(
+		&self,
+		block: Block::Hash,
+		targets: Option<String>,
+	) -> FutureResult<BlockTrace> {
+		let r = self.client.block(&BlockId::Hash(block))
+			.and_then(|signed| signed.ok_or_else(||
+				ClientError::UnknownBlock(format!("{}", block))
+			))
+			.and_then(|signed| {
+				let (mut header, extrinsics) = signed.block.deconstruct();
+				let parent = *header.parent_hash();
+				// blocks are imported without their seal, so it is removed before the execution.
+				header.digest_mut().logs.retain(|item| item.as_seal().is_none());
+				let block = Block::new(header, extrinsics);
+				// spans are only emitted by the native runtime.
+				let (executed, trace) = sc_tracing::block::trace_block(
+					targets.as_ref().map(String::as_str),
+					|| self.client.executor().call(
+						&BlockId::Hash(parent),
+						"Core_execute_block",
+						&block.encode(),
+						ExecutionStrategy::NativeWhenPossible,
+						None,
+					),
+				);
+				executed.map(|_| trace)
+			})
+			.map_err(client_err);
+		Box::new(result(r))
+	}
+
 	fn subscribe_runtime_version(
 		&self,
 		_meta: crate::metadata::Metadata,
@@ -490,15 +593,18 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 				vec![Ok(Ok(StorageChangeSet { block, changes }))]
 			}).unwrap_or_default());
 
+		let changes = stream.map(|(block, changes)| (
+			block,
+			changes.iter()
+				.filter_map(|(o_sk, k, v)| if o_sk.is_none() {
+					Some((k.clone(),v.cloned()))
+				} else { None }).collect::<Vec<_>>(),
+		));
+		let changes = throttle_changes(&*self.client, self.storage_subscription_throttle, changes);
+
 		self.subscriptions.add(subscriber, |sink| {
-			let stream = stream
-				.map(|(block, changes)| Ok::<_, ()>(Ok(StorageChangeSet {
-					block,
-					changes: changes.iter()
-						.filter_map(|(o_sk, k, v)| if o_sk.is_none() {
-							Some((k.clone(),v.cloned()))
-						} else { None }).collect(),
-				})))
+			let stream = changes
+				.map(|(block, changes)| Ok::<_, ()>(Ok(StorageChangeSet { block, changes })))
 				.compat();
 
 			sink
@@ -516,6 +622,74 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 	) -> RpcResult<bool> {
 		Ok(self.subscriptions.cancel(id))
 	}
+
+	fn subscribe_storage_prefix(
+		&self,
+		_meta: crate::metadata::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		prefixes: Vec<StorageKey>,
+	) {
+		let stream = match self.client.storage_changes_notification_stream(None, None) {
+			Ok(stream) => stream,
+			Err(err) => {
+				let _ = subscriber.reject(client_err(err).into());
+				return;
+			},
+		};
+
+		let changes = stream
+			.map(move |(block, changes)| (
+				block,
+				changes.iter()
+					.filter(|(o_sk, k, _)| {
+						o_sk.is_none() && prefixes.iter().any(|prefix| k.0.starts_with(&prefix.0))
+					})
+					.map(|(_, k, v)| (k.clone(), v.cloned()))
+					.collect::<Vec<_>>(),
+			))
+			.filter(|(_, changes)| future::ready(!changes.is_empty()));
+		let changes = throttle_changes(&*self.client, self.storage_subscription_throttle, changes);
+
+		self.subscriptions.add(subscriber, |sink| {
+			let stream = changes
+				.map(|(block, changes)| Ok::<_, ()>(Ok(StorageChangeSet { block, changes })))
+				.compat();
+
+			sink
+				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+				.send_all(stream)
+				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
+				.map(|_| ())
+		});
+	}
+
+	fn unsubscribe_storage_prefix(
+		&self,
+		_meta: Option<crate::metadata::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
+}
+
+/// Throttles the storage `changes` notified to a subscriber, if `throttle` is set.
+fn throttle_changes<Block, Client, S>(
+	client: &Client,
+	throttle: Option<StorageSubscriptionThrottle>,
+	changes: S,
+) -> Box<dyn futures::Stream<Item = StorageChanges<Block::Hash>> + Send + Unpin> where
+	Block: BlockT,
+	Client: BlockchainEvents<Block>,
+	S: futures::Stream<Item = StorageChanges<Block::Hash>> + Send + Unpin + 'static,
+{
+	match throttle {
+		Some(throttle) => {
+			let best_blocks = client.import_notification_stream()
+				.filter(|notification| future::ready(notification.is_new_best));
+			Box::new(Throttled::new(throttle, changes, best_blocks))
+		},
+		None => Box::new(changes),
+	}
 }
 
 /// Splits passed range into two subranges where:
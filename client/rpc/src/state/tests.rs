@@ -46,7 +46,12 @@ fn should_return_storage() {
 		.add_extra_child_storage(STORAGE_KEY.to_vec(), CHILD_INFO, KEY.to_vec(), CHILD_VALUE.to_vec())
 		.build();
 	let genesis_hash = client.genesis_hash();
-	let client = new_full(Arc::new(client), Subscriptions::new(Arc::new(core.executor())));
+	let client = new_full(
+		Arc::new(client),
+		Subscriptions::new(Arc::new(core.executor())),
+		None,
+		DenyUnsafe::No,
+	).0;
 	let key = StorageKey(KEY.to_vec());
 	let storage_key = StorageKey(STORAGE_KEY.to_vec());
 	let (child_info, child_type) = CHILD_INFO.info();
@@ -85,7 +90,12 @@ fn should_return_child_storage() {
 		.add_child_storage("test", "key", CHILD_INFO, vec![42_u8])
 		.build());
 	let genesis_hash = client.genesis_hash();
-	let client = new_full(client, Subscriptions::new(Arc::new(core.executor())));
+	let client = new_full(
+		client,
+		Subscriptions::new(Arc::new(core.executor())),
+		None,
+		DenyUnsafe::No,
+	).0;
 	let child_key = StorageKey(
 		well_known_keys::CHILD_STORAGE_KEY_PREFIX.iter().chain(b"test").cloned().collect()
 	);
@@ -124,12 +134,73 @@ fn should_return_child_storage() {
 	);
 }
 
+#[test]
+fn should_return_child_state() {
+	let (child_info, child_type) = CHILD_INFO.info();
+	let child_info = StorageKey(child_info.to_vec());
+	let core = tokio::runtime::Runtime::new().unwrap();
+	let client = Arc::new(substrate_test_runtime_client::TestClientBuilder::new()
+		.add_child_storage("test", "key", CHILD_INFO, vec![42_u8])
+		.build());
+	let genesis_hash = client.genesis_hash();
+	let child_state = new_full(
+		client,
+		Subscriptions::new(Arc::new(core.executor())),
+		None,
+		DenyUnsafe::No,
+	).1;
+	let child_key = StorageKey(
+		well_known_keys::CHILD_STORAGE_KEY_PREFIX.iter().chain(b"test").cloned().collect()
+	);
+	let key = StorageKey(b"key".to_vec());
+
+	assert_eq!(
+		child_state.storage_keys(
+			child_key.clone(),
+			child_info.clone(),
+			child_type,
+			StorageKey(Vec::new()),
+			None,
+		).wait().unwrap(),
+		vec![key.clone()],
+	);
+	assert_matches!(
+		child_state.storage(
+			child_key.clone(),
+			child_info.clone(),
+			child_type,
+			key.clone(),
+			Some(genesis_hash).into(),
+		).wait(),
+		Ok(Some(StorageData(ref d))) if d[0] == 42 && d.len() == 1
+	);
+	assert_matches!(
+		child_state.storage_hash(
+			child_key.clone(),
+			child_info.clone(),
+			child_type,
+			key.clone(),
+			None,
+		).wait().map(|x| x.is_some()),
+		Ok(true)
+	);
+	assert_matches!(
+		child_state.read_proof(child_key, child_info, child_type, vec![key], None).wait(),
+		Ok(ref proof) if proof.at == genesis_hash && !proof.proof.is_empty()
+	);
+}
+
 #[test]
 fn should_call_contract() {
 	let core = tokio::runtime::Runtime::new().unwrap();
 	let client = Arc::new(substrate_test_runtime_client::new());
 	let genesis_hash = client.genesis_hash();
-	let client = new_full(client, Subscriptions::new(Arc::new(core.executor())));
+	let client = new_full(
+		client,
+		Subscriptions::new(Arc::new(core.executor())),
+		None,
+		DenyUnsafe::No,
+	).0;
 
 	assert_matches!(
 		client.call("balanceOf".into(), Bytes(vec![1,2,3]), Some(genesis_hash).into()).wait(),
@@ -137,6 +208,64 @@ fn should_call_contract() {
 	)
 }
 
+#[test]
+fn should_call_batch_at_the_same_block() {
+	let core = tokio::runtime::Runtime::new().unwrap();
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let client = new_full(
+		client,
+		Subscriptions::new(Arc::new(core.executor())),
+		None,
+		DenyUnsafe::No,
+	).0;
+
+	let version = client.call("Core_version".into(), Bytes(Vec::new()), None).wait().unwrap();
+	let calls = vec![("Core_version".to_string(), Bytes(Vec::new())); 2];
+	assert_eq!(
+		client.call_batch(calls, Some(genesis_hash).into()).wait().unwrap(),
+		vec![version.clone(), version],
+	);
+
+	let calls = vec![
+		("Core_version".to_string(), Bytes(Vec::new())),
+		("balanceOf".to_string(), Bytes(vec![1,2,3])),
+	];
+	assert_matches!(client.call_batch(calls, None).wait(), Err(Error::Client(_)));
+
+	let calls = vec![("Core_version".to_string(), Bytes(Vec::new())); CALL_BATCH_MAX_COUNT + 1];
+	assert_matches!(client.call_batch(calls, None).wait(), Err(Error::InvalidCount { .. }));
+}
+
+#[test]
+fn should_trace_block_storage_accesses() {
+	let core = tokio::runtime::Runtime::new().unwrap();
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_transfer(runtime::Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 42,
+		nonce: 0,
+	}).unwrap();
+	let block = builder.build().unwrap().block;
+	let hash = block.header.hash();
+	client.import(BlockOrigin::Own, block).unwrap();
+
+	let subscriptions = Subscriptions::new(Arc::new(core.executor()));
+	let api = new_full(client.clone(), subscriptions.clone(), None, DenyUnsafe::No).0;
+	let trace = api.trace_block(hash, None).wait().unwrap();
+	assert!(trace.storage.iter().any(|access| access.method == "Get"));
+	assert!(trace.storage.iter().any(|access| access.method == "Put"));
+	assert_matches!(
+		api.trace_block(H256::repeat_byte(1), None).wait(),
+		Err(Error::Client(_))
+	);
+
+	let api = new_full(client, subscriptions, None, DenyUnsafe::Yes).0;
+	assert_matches!(api.trace_block(hash, None).wait(), Err(Error::UnsafeRpcCalled(_)));
+}
+
 #[test]
 fn should_notify_about_storage_changes() {
 	let mut core = tokio::runtime::Runtime::new().unwrap();
@@ -145,7 +274,12 @@ fn should_notify_about_storage_changes() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)));
+		let api = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(remote)),
+			None,
+			DenyUnsafe::No,
+		).0;
 
 		api.subscribe_storage(Default::default(), subscriber, None.into());
 
@@ -178,7 +312,12 @@ fn should_send_initial_storage_changes_and_notifications() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(remote)));
+		let api = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(remote)),
+			None,
+			DenyUnsafe::No,
+		).0;
 
 		let alice_balance_key = blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()));
 
@@ -210,11 +349,58 @@ fn should_send_initial_storage_changes_and_notifications() {
 	assert_eq!(core.block_on(next.into_future()).unwrap().0, None);
 }
 
+#[test]
+fn should_notify_about_storage_changes_under_prefixes() {
+	let mut core = tokio::runtime::Runtime::new().unwrap();
+	let remote = core.executor();
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	{
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let api = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(remote)),
+			None,
+			DenyUnsafe::No,
+		).0;
+
+		api.subscribe_storage_prefix(Default::default(), subscriber, vec![StorageKey(vec![0xaa])]);
+
+		// assert id assigned
+		assert_eq!(core.block_on(id), Ok(Ok(SubscriptionId::Number(1))));
+
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_storage_change(vec![0xaa, 1], Some(vec![1])).unwrap();
+		builder.push_storage_change(vec![0xbb], Some(vec![2])).unwrap();
+		let block = builder.build().unwrap().block;
+		client.import(BlockOrigin::Own, block).unwrap();
+
+		// no change under the prefix
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_storage_change(vec![0xbb], Some(vec![3])).unwrap();
+		let block = builder.build().unwrap().block;
+		client.import(BlockOrigin::Own, block).unwrap();
+	}
+
+	// no initial values, only the changes under the prefix
+	let (notification, next) = core.block_on(transport.into_future()).unwrap();
+	let notification = notification.unwrap();
+	assert!(notification.contains("[\"0xaa01\",\"0x01\"]"));
+	assert!(!notification.contains("0xbb"));
+	// no more notifications on this channel
+	assert_eq!(core.block_on(next.into_future()).unwrap().0, None);
+}
+
 #[test]
 fn should_query_storage() {
 	fn run_tests(mut client: Arc<TestClient>) {
 		let core = tokio::runtime::Runtime::new().unwrap();
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(core.executor())));
+		let api = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(core.executor())),
+			None,
+			DenyUnsafe::No,
+		).0;
 
 		let mut add_block = |nonce| {
 			let mut builder = client.new_block(Default::default()).unwrap();
@@ -399,7 +585,12 @@ fn should_return_runtime_version() {
 	let core = tokio::runtime::Runtime::new().unwrap();
 
 	let client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(core.executor())));
+	let api = new_full(
+		client.clone(),
+		Subscriptions::new(Arc::new(core.executor())),
+		None,
+		DenyUnsafe::No,
+	).0;
 
 	let result = "{\"specName\":\"test\",\"implName\":\"parity-test\",\"authoringVersion\":1,\
 		\"specVersion\":2,\"implVersion\":2,\"apis\":[[\"0xdf6acb689907609b\",2],\
@@ -422,7 +613,12 @@ fn should_notify_on_runtime_version_initially() {
 
 	{
 		let client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(core.executor())));
+		let api = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(core.executor())),
+			None,
+			DenyUnsafe::No,
+		).0;
 
 		api.subscribe_runtime_version(Default::default(), subscriber);
 
@@ -0,0 +1,183 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Throttling of the notifications of storage subscriptions.
+//!
+//! A subscription to keys changing every block gets a notification per block. When throttled,
+//! a notification is only sent once enough blocks were imported and enough time elapsed since
+//! the previous one. The changes made in between are merged: each key is notified once, with
+//! its latest value, at the latest block.
+
+use std::{
+	collections::BTreeMap, pin::Pin, task::{Context, Poll}, time::{Duration, Instant},
+};
+use futures::{prelude::*, stream::Fuse};
+use futures_timer::Delay;
+use sp_core::storage::{StorageKey, StorageData};
+
+/// Changes of the values of storage keys at a block.
+pub(crate) type StorageChanges<Hash> = (Hash, Vec<(StorageKey, Option<StorageData>)>);
+
+/// Minimum spacing of the notifications of a storage subscription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageSubscriptionThrottle {
+	/// Number of best blocks imported between two notifications.
+	pub min_blocks: u32,
+	/// Time between two notifications.
+	pub min_interval: Duration,
+}
+
+/// Stream of storage changes, throttled and merged according to a `StorageSubscriptionThrottle`.
+pub(crate) struct Throttled<Hash, C, B> {
+	throttle: StorageSubscriptionThrottle,
+	changes: Fuse<C>,
+	best_blocks: Fuse<B>,
+	/// Merged changes not notified yet, at the block of the latest ones.
+	pending: Option<(Hash, BTreeMap<StorageKey, Option<StorageData>>)>,
+	/// Best blocks imported since the last notification.
+	blocks: u32,
+	/// When the last notification was sent.
+	last_notification: Option<Instant>,
+	/// Fires when `min_interval` has elapsed since the last notification.
+	delay: Option<Delay>,
+}
+
+// The wrapped streams are only polled as `Unpin`.
+impl<Hash, C: Unpin, B: Unpin> Unpin for Throttled<Hash, C, B> {}
+
+impl<Hash, C, B> Throttled<Hash, C, B> where
+	C: Stream<Item = StorageChanges<Hash>> + Unpin,
+	B: Stream + Unpin,
+{
+	/// Throttles `changes`, counting blocks with the items of `best_blocks`.
+	pub(crate) fn new(throttle: StorageSubscriptionThrottle, changes: C, best_blocks: B) -> Self {
+		Throttled {
+			throttle,
+			changes: changes.fuse(),
+			best_blocks: best_blocks.fuse(),
+			pending: None,
+			blocks: throttle.min_blocks,
+			last_notification: None,
+			delay: None,
+		}
+	}
+
+	/// Returns the time left before a notification can be sent.
+	fn interval_left(&self, now: Instant) -> Option<Duration> {
+		let elapsed = now.duration_since(self.last_notification?);
+		self.throttle.min_interval.checked_sub(elapsed)
+			.filter(|left| *left > Duration::from_secs(0))
+	}
+}
+
+impl<Hash, C, B> Stream for Throttled<Hash, C, B> where
+	C: Stream<Item = StorageChanges<Hash>> + Unpin,
+	B: Stream + Unpin,
+{
+	type Item = StorageChanges<Hash>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let this = self.get_mut();
+
+		while let Poll::Ready(Some((hash, changes))) = this.changes.poll_next_unpin(cx) {
+			match &mut this.pending {
+				Some((block, merged)) => {
+					*block = hash;
+					merged.extend(changes);
+				},
+				None => this.pending = Some((hash, changes.into_iter().collect())),
+			}
+		}
+		while let Poll::Ready(Some(_)) = this.best_blocks.poll_next_unpin(cx) {
+			this.blocks = this.blocks.saturating_add(1);
+		}
+
+		if this.pending.is_none() {
+			return if this.changes.is_done() { Poll::Ready(None) } else { Poll::Pending };
+		}
+		// Without more blocks to come, the pending changes are notified regardless.
+		if this.blocks < this.throttle.min_blocks && !this.best_blocks.is_done() {
+			return Poll::Pending;
+		}
+		let now = Instant::now();
+		if let Some(left) = this.interval_left(now) {
+			let delay = this.delay.get_or_insert_with(|| Delay::new(left));
+			if delay.poll_unpin(cx).is_pending() {
+				return Poll::Pending;
+			}
+		}
+
+		this.blocks = 0;
+		this.last_notification = Some(now);
+		this.delay = None;
+		Poll::Ready(this.pending.take().map(|(block, changes)| {
+			(block, changes.into_iter().collect())
+		}))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{channel::mpsc, executor::block_on};
+
+	fn change(value: u8) -> Vec<(StorageKey, Option<StorageData>)> {
+		vec![(StorageKey(vec![1]), Some(StorageData(vec![value])))]
+	}
+
+	#[test]
+	fn merges_changes_until_enough_blocks_were_imported() {
+		let (changes_tx, changes) = mpsc::unbounded();
+		let (best_tx, best_blocks) = mpsc::unbounded();
+		let throttle = StorageSubscriptionThrottle {
+			min_blocks: 2,
+			min_interval: Duration::from_secs(0),
+		};
+		let mut throttled = Throttled::new(throttle, changes, best_blocks);
+
+		// The first changes are notified right away.
+		changes_tx.unbounded_send((1, change(1))).unwrap();
+		assert_eq!(block_on(throttled.next()), Some((1, change(1))));
+
+		changes_tx.unbounded_send((2, change(2))).unwrap();
+		best_tx.unbounded_send(()).unwrap();
+		changes_tx.unbounded_send((3, change(3))).unwrap();
+		best_tx.unbounded_send(()).unwrap();
+		assert_eq!(block_on(throttled.next()), Some((3, change(3))));
+
+		drop(changes_tx);
+		assert_eq!(block_on(throttled.next()), None);
+	}
+
+	#[test]
+	fn waits_for_interval_between_notifications() {
+		let (changes_tx, changes) = mpsc::unbounded();
+		let throttle = StorageSubscriptionThrottle {
+			min_blocks: 0,
+			min_interval: Duration::from_millis(50),
+		};
+		let mut throttled = Throttled::new(throttle, changes, stream::pending::<()>());
+
+		changes_tx.unbounded_send((1, change(1))).unwrap();
+		assert_eq!(block_on(throttled.next()), Some((1, change(1))));
+
+		let start = Instant::now();
+		changes_tx.unbounded_send((2, change(2))).unwrap();
+		changes_tx.unbounded_send((3, change(3))).unwrap();
+		assert_eq!(block_on(throttled.next()), Some((3, change(3))));
+		assert!(start.elapsed() >= Duration::from_millis(40));
+	}
+}
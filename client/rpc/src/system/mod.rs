@@ -21,19 +21,24 @@ mod tests;
 
 use futures::{future::BoxFuture, FutureExt, TryFutureExt};
 use futures::{channel::{mpsc, oneshot}, compat::Compat};
-use sc_rpc_api::Receiver;
+use sc_rpc_api::{DenyUnsafe, Receiver};
+use sp_core::Bytes;
 use sp_runtime::traits::{self, Header as HeaderT};
 
 use self::error::Result;
 
 pub use sc_rpc_api::system::*;
-pub use self::helpers::{Properties, SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{
+	Properties, SystemInfo, Health, PeerInfo, PeerDirection, NodeRole, IdentityAttestation,
+	SyncState, IDENTITY_ATTESTATION_PREFIX, identity_attestation_message,
+};
 pub use self::gen_client::Client as SystemClient;
 
 /// System API implementation
 pub struct System<B: traits::Block> {
 	info: SystemInfo,
 	send_back: mpsc::UnboundedSender<Request<B>>,
+	deny_unsafe: DenyUnsafe,
 }
 
 /// Request to be processed.
@@ -48,8 +53,28 @@ pub enum Request<B: traits::Block> {
 	NetworkAddReservedPeer(String, oneshot::Sender<Result<()>>),
 	/// Must return any potential parse error.
 	NetworkRemoveReservedPeer(String, oneshot::Sender<Result<()>>),
+	/// Must update the peers the connections are accepted or refused with, and return any
+	/// potential parse error.
+	NetworkUpdatePeerFilter(PeerFilterUpdate, oneshot::Sender<Result<()>>),
 	/// Must return the node role.
-	NodeRoles(oneshot::Sender<Vec<NodeRole>>)
+	NodeRoles(oneshot::Sender<Vec<NodeRole>>),
+	/// Must sign `identity_attestation_message` of the challenge with the network key.
+	AttestIdentity(Vec<u8>, oneshot::Sender<Result<IdentityAttestation>>),
+	/// Must return the progress of the sync of the chain.
+	SyncState(oneshot::Sender<SyncState<<B::Header as HeaderT>::Number>>),
+}
+
+/// Update of the peers the connections are accepted or refused with. The peers are given by
+/// PeerId or by IP address range.
+pub enum PeerFilterUpdate {
+	/// Refuse the connections with the peer.
+	AddDenied(String),
+	/// Stop refusing the connections with the peer.
+	RemoveDenied(String),
+	/// Accept the connections with the peer, and only with the allowed ones.
+	AddAllowed(String),
+	/// Stop accepting the connections with the peer.
+	RemoveAllowed(String),
 }
 
 impl<B: traits::Block> System<B> {
@@ -60,12 +85,33 @@ impl<B: traits::Block> System<B> {
 	pub fn new(
 		info: SystemInfo,
 		send_back: mpsc::UnboundedSender<Request<B>>,
+		deny_unsafe: DenyUnsafe,
 	) -> Self {
 		System {
 			info,
 			send_back,
+			deny_unsafe,
 		}
 	}
+
+	/// Sends a peer filter update, if unsafe calls are allowed.
+	fn update_peer_filter(&self, update: PeerFilterUpdate)
+		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
+	{
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return async move { Err(rpc::Error::from(err)) }.boxed().compat();
+		}
+
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NetworkUpdatePeerFilter(update, tx));
+		async move {
+			match rx.await {
+				Ok(Ok(())) => Ok(()),
+				Ok(Err(e)) => Err(rpc::Error::from(e)),
+				Err(_) => Err(rpc::Error::internal_error()),
+			}
+		}.boxed().compat()
+	}
 }
 
 impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for System<B> {
@@ -106,6 +152,10 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 	fn system_add_reserved_peer(&self, peer: String)
 		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
 	{
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return async move { Err(rpc::Error::from(err)) }.boxed().compat();
+		}
+
 		let (tx, rx) = oneshot::channel();
 		let _ = self.send_back.unbounded_send(Request::NetworkAddReservedPeer(peer, tx));
 		async move {
@@ -120,6 +170,10 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 	fn system_remove_reserved_peer(&self, peer: String)
 		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
 	{
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return async move { Err(rpc::Error::from(err)) }.boxed().compat();
+		}
+
 		let (tx, rx) = oneshot::channel();
 		let _ = self.send_back.unbounded_send(Request::NetworkRemoveReservedPeer(peer, tx));
 		async move {
@@ -131,9 +185,53 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 		}.boxed().compat()
 	}
 
+	fn system_add_denied_peer(&self, peer: String)
+		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
+	{
+		self.update_peer_filter(PeerFilterUpdate::AddDenied(peer))
+	}
+
+	fn system_remove_denied_peer(&self, peer: String)
+		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
+	{
+		self.update_peer_filter(PeerFilterUpdate::RemoveDenied(peer))
+	}
+
+	fn system_add_allowed_peer(&self, peer: String)
+		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
+	{
+		self.update_peer_filter(PeerFilterUpdate::AddAllowed(peer))
+	}
+
+	fn system_remove_allowed_peer(&self, peer: String)
+		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
+	{
+		self.update_peer_filter(PeerFilterUpdate::RemoveAllowed(peer))
+	}
+
 	fn system_node_roles(&self) -> Receiver<Vec<NodeRole>> {
 		let (tx, rx) = oneshot::channel();
 		let _ = self.send_back.unbounded_send(Request::NodeRoles(tx));
 		Receiver(Compat::new(rx))
 	}
+
+	fn system_attest_identity(&self, challenge: Bytes)
+		-> Compat<BoxFuture<'static, std::result::Result<IdentityAttestation, rpc::Error>>>
+	{
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::AttestIdentity(challenge.0, tx));
+		async move {
+			match rx.await {
+				Ok(Ok(attestation)) => Ok(attestation),
+				Ok(Err(e)) => Err(rpc::Error::from(e)),
+				Err(_) => Err(rpc::Error::internal_error()),
+			}
+		}.boxed().compat()
+	}
+
+	fn system_sync_state(&self) -> Receiver<SyncState<<B::Header as HeaderT>::Number>> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::SyncState(tx));
+		Receiver(Compat::new(rx))
+	}
 }
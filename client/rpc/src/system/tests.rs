@@ -22,6 +22,7 @@ use substrate_test_runtime_client::runtime::Block;
 use assert_matches::assert_matches;
 use futures::{prelude::*, channel::mpsc};
 use std::thread;
+use sc_rpc_api::UnsafeRpcError;
 
 struct Status {
 	pub peers: usize,
@@ -64,6 +65,7 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 							protocol_version: 1,
 							best_hash: Default::default(),
 							best_number: 1,
+							direction: Some(PeerDirection::Outbound),
 						});
 					}
 					let _ = sender.send(peers);
@@ -92,9 +94,37 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 						Err(s) => sender.send(Err(error::Error::MalformattedPeerArg(s.to_string()))),
 					};
 				}
+				Request::NetworkUpdatePeerFilter(update, sender) => {
+					let peer = match update {
+						PeerFilterUpdate::AddDenied(peer) |
+						PeerFilterUpdate::RemoveDenied(peer) |
+						PeerFilterUpdate::AddAllowed(peer) |
+						PeerFilterUpdate::RemoveAllowed(peer) => peer,
+					};
+					let _ = match peer.parse::<sc_network::config::PeerFilterEntry>() {
+						Ok(_) => sender.send(Ok(())),
+						Err(s) => sender.send(Err(error::Error::MalformattedPeerArg(s))),
+					};
+				}
 				Request::NodeRoles(sender) => {
 					let _ = sender.send(vec![NodeRole::Authority]);
 				}
+				Request::AttestIdentity(challenge, sender) => {
+					// Not an actual signature, which is produced by the network service.
+					let _ = sender.send(Ok(IdentityAttestation {
+						peer_id: status.peer_id.to_base58(),
+						public_key: Vec::new().into(),
+						signature: identity_attestation_message(&challenge).into(),
+					}));
+				}
+				Request::SyncState(sender) => {
+					let _ = sender.send(SyncState {
+						starting_block: 1,
+						current_block: 2,
+						highest_block: Some(3),
+						eta_secs: if status.is_syncing { Some(10) } else { None },
+					});
+				}
 			};
 
 			future::ready(())
@@ -105,7 +135,7 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 		impl_version: "0.2.0".into(),
 		chain_name: "testchain".into(),
 		properties: Default::default(),
-	}, tx)
+	}, tx, DenyUnsafe::No)
 }
 
 fn wait_receiver<T>(rx: Receiver<T>) -> T {
@@ -215,6 +245,7 @@ fn system_peers() {
 			protocol_version: 1,
 			best_hash: Default::default(),
 			best_number: 1u64,
+			direction: Some(PeerDirection::Outbound),
 		}]
 	);
 }
@@ -268,3 +299,78 @@ fn system_network_remove_reserved() {
 	assert_eq!(runtime.block_on(good_fut), Ok(()));
 	assert!(runtime.block_on(bad_fut).is_err());
 }
+
+#[test]
+fn system_network_reserved_peers_are_unsafe() {
+	let (tx, _rx) = mpsc::unbounded();
+	let api = System::<Block>::new(SystemInfo {
+		impl_name: "testclient".into(),
+		impl_version: "0.2.0".into(),
+		chain_name: "testchain".into(),
+		properties: Default::default(),
+	}, tx, DenyUnsafe::Yes);
+	let peer_id = "QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV";
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+	let add_fut = api.system_add_reserved_peer(format!("/ip4/198.51.100.19/tcp/30333/p2p/{}", peer_id));
+	let remove_fut = api.system_remove_reserved_peer(peer_id.into());
+	assert_eq!(runtime.block_on(add_fut), Err(UnsafeRpcError.into()));
+	assert_eq!(runtime.block_on(remove_fut), Err(UnsafeRpcError.into()));
+}
+
+#[test]
+fn system_network_peer_filter() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+	let peer_id_fut = api(None).system_add_denied_peer(
+		"QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".into()
+	);
+	let range_fut = api(None).system_add_allowed_peer("192.0.2.0/24".into());
+	let remove_fut = api(None).system_remove_allowed_peer("2001:db8::/32".into());
+	let bad_fut = api(None).system_remove_denied_peer("192.0.2.0/33".into());
+	assert_eq!(runtime.block_on(peer_id_fut), Ok(()));
+	assert_eq!(runtime.block_on(range_fut), Ok(()));
+	assert_eq!(runtime.block_on(remove_fut), Ok(()));
+	assert!(runtime.block_on(bad_fut).is_err());
+}
+
+#[test]
+fn system_network_peer_filter_is_unsafe() {
+	let (tx, _rx) = mpsc::unbounded();
+	let api = System::<Block>::new(SystemInfo {
+		impl_name: "testclient".into(),
+		impl_version: "0.2.0".into(),
+		chain_name: "testchain".into(),
+		properties: Default::default(),
+	}, tx, DenyUnsafe::Yes);
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+	let deny_fut = api.system_add_denied_peer("192.0.2.0/24".into());
+	let allow_fut = api.system_add_allowed_peer("192.0.2.0/24".into());
+	assert_eq!(runtime.block_on(deny_fut), Err(UnsafeRpcError.into()));
+	assert_eq!(runtime.block_on(allow_fut), Err(UnsafeRpcError.into()));
+}
+
+#[test]
+fn system_attest_identity_signs_prefixed_challenge() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+	let attestation = runtime.block_on(api(None).system_attest_identity(vec![1, 2, 3].into()))
+		.unwrap();
+	assert_eq!(attestation.signature.0, [IDENTITY_ATTESTATION_PREFIX, &[1, 2, 3]].concat());
+}
+
+#[test]
+fn system_sync_state() {
+	assert_eq!(
+		wait_receiver(api(Status {
+			is_syncing: true,
+			..Default::default()
+		}).system_sync_state()),
+		SyncState {
+			starting_block: 1,
+			current_block: 2,
+			highest_block: Some(3),
+			eta_secs: Some(10),
+		}
+	);
+}
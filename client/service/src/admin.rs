@@ -0,0 +1,243 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Administration of the node over the p2p network.
+//!
+//! Nodes listed as admin peers can send JSON-RPC requests to this node over a dedicated
+//! notifications protocol, and receive the responses on the same protocol. Admin peers are
+//! authenticated by their network identity, so no RPC port needs to be exposed for them.
+//!
+//! Every RPC method of the node is available (including the unsafe ones, such as
+//! `system_addReservedPeer`), plus `admin_setLogLevel` which changes the maximum log level of the
+//! node. Subscriptions are not supported.
+//!
+//! The requests are sent by the `admin` subcommand of the CLI, through
+//! `ServiceBuilderCommand::admin_request`.
+
+use std::{collections::HashSet, str::FromStr, sync::Arc};
+
+use futures::{prelude::*, compat::*};
+use jsonrpc_core as rpc;
+use log::{debug, trace};
+use sc_network::{Event, ExHashT, NetworkService, PeerId};
+use sp_runtime::{ConsensusEngineId, traits::Block as BlockT};
+
+/// Engine id of the admin notifications protocol.
+pub const ADMIN_ENGINE_ID: ConsensusEngineId = *b"admn";
+
+/// Name of the admin notifications protocol.
+pub const ADMIN_PROTOCOL_NAME: &[u8] = b"/substrate/admin/1";
+
+/// Builds the RPC handler of the admin peers with `gen_handler`, allowing the unsafe methods and
+/// adding the admin-only ones.
+pub(crate) fn admin_handler(
+	gen_handler: impl FnOnce(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>,
+) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata> {
+	let mut handler = gen_handler(sc_rpc::DenyUnsafe::No);
+	extend_handler(&mut handler);
+	handler
+}
+
+/// Add the admin-only RPC methods to the given handler.
+fn extend_handler(handler: &mut sc_rpc_server::RpcHandler<sc_rpc::Metadata>) {
+	handler.add_method("admin_setLogLevel", |params: rpc::Params| {
+		let (level,): (String,) = params.parse()?;
+		let level = log::LevelFilter::from_str(&level)
+			.map_err(|_| rpc::Error::invalid_params(format!("Invalid log level: {}", level)))?;
+		log::set_max_level(level);
+		Ok(rpc::Value::Null)
+	});
+}
+
+/// Builds the future that answers the requests of the admin peers.
+///
+/// Notifications received from any other peer on the admin protocol are ignored.
+pub(crate) fn build_admin_future<B: BlockT, H: ExHashT>(
+	network: Arc<NetworkService<B, H>>,
+	rpc_handlers: sc_rpc_server::RpcHandler<sc_rpc::Metadata>,
+	admin_peers: HashSet<PeerId>,
+) -> impl Future<Output = ()> {
+	// the event stream must be requested before registering the protocol.
+	let events = network.event_stream();
+	network.register_notifications_protocol(ADMIN_ENGINE_ID, ADMIN_PROTOCOL_NAME);
+
+	events
+		.filter_map(move |event| future::ready(
+			admin_requests(&admin_peers, event).map(stream::iter)
+		))
+		.flatten()
+		.for_each_concurrent(None, move |(remote, message)| {
+			let network = network.clone();
+			let request = String::from_utf8_lossy(&message).into_owned();
+			trace!(target: "admin", "Admin request from {}: {}", remote, request);
+
+			rpc_handlers.handle_request(&request, Default::default())
+				.compat()
+				.map(move |response| {
+					if let Ok(Some(response)) = response {
+						network.write_notification(remote, ADMIN_ENGINE_ID, response.into_bytes());
+					}
+				})
+		})
+}
+
+/// The admin requests carried by `event`, if it comes from one of `admin_peers`.
+fn admin_requests(admin_peers: &HashSet<PeerId>, event: Event) -> Option<Vec<(PeerId, Vec<u8>)>> {
+	match event {
+		Event::NotificationsReceived { remote, messages } => {
+			let requests = messages.into_iter()
+				.filter(|(engine_id, _)| *engine_id == ADMIN_ENGINE_ID)
+				.map(|(_, message)| (remote.clone(), message.to_vec()))
+				.collect::<Vec<_>>();
+			if requests.is_empty() {
+				None
+			} else if admin_peers.contains(&remote) {
+				Some(requests)
+			} else {
+				debug!(target: "admin", "Ignoring admin request from unknown peer {}", remote);
+				None
+			}
+		},
+		_ => None,
+	}
+}
+
+/// Sends the JSON-RPC `request` to the node `node` once the admin protocol is open with it, and
+/// returns its response. Returns `None` if the network stops first.
+pub(crate) fn send_request<B: BlockT, H: ExHashT>(
+	network: Arc<NetworkService<B, H>>,
+	node: PeerId,
+	request: String,
+) -> impl Future<Output = Option<String>> {
+	// the event stream must be requested before registering the protocol.
+	let events = network.event_stream();
+	network.register_notifications_protocol(ADMIN_ENGINE_ID, ADMIN_PROTOCOL_NAME);
+
+	events
+		.filter_map(move |event| future::ready(match event {
+			Event::NotificationStreamOpened { remote, engine_id, .. }
+				if remote == node && engine_id == ADMIN_ENGINE_ID =>
+			{
+				trace!(target: "admin", "Admin request to {}: {}", remote, request);
+				network.write_notification(remote, ADMIN_ENGINE_ID, request.clone().into_bytes());
+				None
+			},
+			Event::NotificationsReceived { remote, messages } if remote == node => messages
+				.into_iter()
+				.find(|(engine_id, _)| *engine_id == ADMIN_ENGINE_ID)
+				.map(|(_, message)| String::from_utf8_lossy(&message).into_owned()),
+			_ => None,
+		}))
+		.into_future()
+		.map(|(response, _)| response)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::thread;
+	use sc_rpc::system::{System, SystemApi, SystemInfo, Request};
+	use substrate_test_runtime_client::runtime::Block;
+
+	/// Builds a handler of the system RPC methods, answering the requests to the network from
+	/// another thread.
+	fn system_handler(
+		deny_unsafe: sc_rpc::DenyUnsafe,
+	) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata> {
+		let (tx, mut rx) = futures::channel::mpsc::unbounded::<Request<Block>>();
+		thread::spawn(move || futures::executor::block_on(async move {
+			while let Some(request) = rx.next().await {
+				match request {
+					Request::NetworkAddReservedPeer(_, sender) => {
+						let _ = sender.send(Ok(()));
+					},
+					_ => panic!("Unexpected request"),
+				}
+			}
+		}));
+
+		let info = SystemInfo {
+			impl_name: "testclient".into(),
+			impl_version: "0.2.0".into(),
+			chain_name: "testchain".into(),
+			properties: Default::default(),
+		};
+		sc_rpc_server::rpc_handler(SystemApi::to_delegate(System::new(info, tx, deny_unsafe)))
+	}
+
+	fn add_reserved_peer_request() -> String {
+		let peer = format!("/ip4/198.51.100.19/tcp/30333/p2p/{}", PeerId::random());
+		serde_json::json!({
+			"jsonrpc": "2.0",
+			"method": "system_addReservedPeer",
+			"params": [peer],
+			"id": 1,
+		}).to_string()
+	}
+
+	#[test]
+	fn admin_peers_can_call_unsafe_methods() {
+		let handler = admin_handler(system_handler);
+		let request = add_reserved_peer_request();
+		let response = handler.handle_request_sync(&request, Default::default());
+		assert_eq!(response, Some(r#"{"jsonrpc":"2.0","result":null,"id":1}"#.to_owned()));
+
+		// the same request is rejected by the handler of the public RPC interfaces.
+		let handler = system_handler(sc_rpc::DenyUnsafe::Yes);
+		let response = handler.handle_request_sync(&request, Default::default()).unwrap();
+		assert!(response.contains(r#""error""#));
+	}
+
+	#[test]
+	fn admin_peers_can_set_the_log_level() {
+		let handler = admin_handler(system_handler);
+		let request = |level: &str| serde_json::json!({
+			"jsonrpc": "2.0",
+			"method": "admin_setLogLevel",
+			"params": [level],
+			"id": 1,
+		}).to_string();
+
+		let previous = log::max_level();
+		let response = handler.handle_request_sync(&request("trace"), Default::default());
+		assert_eq!(response, Some(r#"{"jsonrpc":"2.0","result":null,"id":1}"#.to_owned()));
+		assert_eq!(log::max_level(), log::LevelFilter::Trace);
+		log::set_max_level(previous);
+
+		let response = handler.handle_request_sync(&request("loud"), Default::default()).unwrap();
+		assert!(response.contains("Invalid log level: loud"));
+	}
+
+	#[test]
+	fn requests_of_unknown_peers_are_ignored() {
+		let admin = PeerId::random();
+		let stranger = PeerId::random();
+		let event = |remote: &PeerId| Event::NotificationsReceived {
+			remote: remote.clone(),
+			messages: vec![
+				(ADMIN_ENGINE_ID, b"request".to_vec().into()),
+				(*b"othr", b"gossip".to_vec().into()),
+			],
+		};
+		let admin_peers = vec![admin.clone()].into_iter().collect();
+
+		assert_eq!(
+			admin_requests(&admin_peers, event(&admin)),
+			Some(vec![(admin.clone(), b"request".to_vec())]),
+		);
+		assert_eq!(admin_requests(&admin_peers, event(&stranger)), None);
+	}
+}
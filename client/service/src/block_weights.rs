@@ -0,0 +1,175 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reconciliation of the execution time of the imported blocks with the weight declared by their
+//! extrinsics, through the `BlockWeight` runtime api.
+//!
+//! Extrinsics declaring less weight than the time they take to execute let their senders fill
+//! blocks that take longer than expected to import, so the ratio of the actual to the expected
+//! execution time is exported to Prometheus, and egregious mismatches are logged.
+//!
+//! The weight is queried once the blocks are imported, so that the import itself doesn't wait
+//! for it.
+
+use std::{fmt, sync::Arc, time::Duration};
+use codec::{Decode, Encode};
+use futures::{prelude::*, future::ready};
+use log::{debug, warn};
+use prometheus_endpoint::{register, Histogram, HistogramOpts, PrometheusError, Registry};
+use sc_client_api::{BlockBackend, BlockchainEvents, CallExecutor, ExecutorProvider};
+use sp_api::RuntimeApiInfo;
+use sp_block_builder::BlockWeight as BlockWeightApi;
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT}};
+use sp_state_machine::ExecutionStrategy;
+
+/// Nanoseconds of execution a unit of weight is expected to take. The maximal weight of a block
+/// is one second of execution at this rate.
+const NANOS_PER_WEIGHT: f64 = 1.0;
+
+/// Ratio of the actual to the expected execution time over which a warning is logged.
+const WARN_RATIO: f64 = 10.0;
+
+/// Execution time under which no warning is logged, as fixed costs dominate the execution of
+/// small blocks.
+const WARN_MIN_EXECUTION_TIME: Duration = Duration::from_millis(50);
+
+/// Ratio of the actual execution time of a block to the one expected from its `weight`, or
+/// `None` if it declares no weight.
+fn execution_ratio(execution_time: Duration, weight: u64) -> Option<f64> {
+	if weight == 0 {
+		return None;
+	}
+	Some(execution_time.as_nanos() as f64 / (weight as f64 * NANOS_PER_WEIGHT))
+}
+
+/// Prometheus metrics comparing the execution time of the imported blocks with their weight.
+pub(crate) struct BlockWeightMetrics {
+	execution_ratio: Histogram,
+}
+
+impl BlockWeightMetrics {
+	pub(crate) fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(BlockWeightMetrics {
+			execution_ratio: register(Histogram::with_opts(
+				HistogramOpts::new(
+					"block_execution_weight_ratio",
+					"Ratio of the execution time of the imported blocks to the time expected from \
+					the weight declared by their extrinsics",
+				).buckets(vec![0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 10.0, 100.0]),
+			)?, registry)?,
+		})
+	}
+
+	/// Reports the execution of the block `number` with the given hash.
+	fn report(
+		&self,
+		number: impl fmt::Display,
+		hash: impl fmt::Display,
+		execution_time: Duration,
+		weight: u64,
+	) {
+		let ratio = match execution_ratio(execution_time, weight) {
+			Some(ratio) => ratio,
+			None => return,
+		};
+		self.execution_ratio.observe(ratio);
+
+		if ratio > WARN_RATIO && execution_time > WARN_MIN_EXECUTION_TIME {
+			warn!(
+				"Block #{} ({}) took {:?} to execute, {:.1} times longer than expected from the \
+				weight of its extrinsics ({}). Some extrinsics may be under-weighted.",
+				number,
+				hash,
+				execution_time,
+				ratio,
+				weight,
+			);
+		}
+	}
+}
+
+/// Sum of the weights declared by the extrinsics of the block `hash`, or `None` if its runtime
+/// doesn't report it.
+fn extrinsics_weight<Block, Client>(
+	client: &Client,
+	hash: Block::Hash,
+	parent_hash: Block::Hash,
+) -> sp_blockchain::Result<Option<u64>> where
+	Block: BlockT,
+	Client: BlockBackend<Block> + ExecutorProvider<Block>,
+{
+	let at = BlockId::Hash(parent_hash);
+	let has_api = client.executor().runtime_version(&at)?
+		.has_api_with(&<dyn BlockWeightApi<Block>>::ID, |_| true);
+	let body = match client.block_body(&BlockId::Hash(hash))? {
+		Some(body) if has_api => body,
+		_ => return Ok(None),
+	};
+
+	let weight = client.executor().call(
+		&at,
+		"BlockWeight_extrinsics_weight",
+		&body.encode(),
+		ExecutionStrategy::NativeElseWasm,
+		None,
+	)?;
+	u64::decode(&mut &weight[..])
+		.map(Some)
+		.map_err(|e| sp_blockchain::Error::Msg(format!("Invalid block weight: {}", e.what())))
+}
+
+/// Builds the future reporting the execution of the blocks imported by `client` to `metrics`.
+pub(crate) fn build_block_weights_future<Block, Client>(
+	client: Arc<Client>,
+	metrics: BlockWeightMetrics,
+) -> impl Future<Output = ()> where
+	Block: BlockT,
+	Client: BlockchainEvents<Block> + BlockBackend<Block> + ExecutorProvider<Block>,
+{
+	client.import_notification_stream().for_each(move |notification| {
+		// The blocks authored locally, or imported with their storage changes, weren't executed.
+		let execution_time = match client.import_time(&notification.hash) {
+			Some(execution_time) => execution_time,
+			None => return ready(()),
+		};
+
+		let parent_hash = *notification.header.parent_hash();
+		match extrinsics_weight(&*client, notification.hash, parent_hash) {
+			Ok(Some(weight)) => metrics.report(
+				notification.header.number(),
+				notification.hash,
+				execution_time,
+				weight,
+			),
+			Ok(None) => {},
+			Err(e) => debug!("Failed to get the weight of imported block: {}", e),
+		}
+		ready(())
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ratio_compares_time_with_weight() {
+		assert_eq!(execution_ratio(Duration::from_millis(1), 0), None);
+		assert_eq!(execution_ratio(Duration::from_millis(1), 1_000_000), Some(1.0));
+		assert_eq!(execution_ratio(Duration::from_millis(20), 1_000_000), Some(20.0));
+		assert_eq!(execution_ratio(Duration::from_millis(1), 4_000_000), Some(0.25));
+	}
+}
@@ -15,7 +15,10 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{Service, NetworkStatus, NetworkState, error::Error, DEFAULT_PROTOCOL_ID, MallocSizeOfWasm};
-use crate::{TaskManagerBuilder, start_rpc_servers, build_network_future, TransactionPoolAdapter};
+use crate::{
+	TaskManagerBuilder, RpcServerHandlers, RpcServersConfig, start_rpc_servers, build_network_future,
+	TransactionPoolAdapter,
+};
 use crate::status_sinks;
 use crate::config::{Configuration, DatabaseConfig, KeystoreConfig, PrometheusConfig};
 use sc_client_api::{
@@ -28,6 +31,7 @@ use sc_client_api::{
 use sc_client::Client;
 use sc_chain_spec::get_extension;
 use sp_consensus::import_queue::ImportQueue;
+use sp_consensus::block_validation::{BlockAnnounceValidator, DefaultBlockAnnounceValidator};
 use futures::{
 	Future, FutureExt, StreamExt,
 	channel::mpsc,
@@ -43,10 +47,11 @@ use sp_runtime::traits::{
 	Block as BlockT, NumberFor, SaturatedConversion, HashFor, UniqueSaturatedInto,
 };
 use sp_api::ProvideRuntimeApi;
+use sp_core::storage::Storage;
 use sc_executor::{NativeExecutor, NativeExecutionDispatch};
 use std::{
 	io::{Read, Write, Seek},
-	marker::PhantomData, sync::Arc, pin::Pin
+	marker::PhantomData, sync::{Arc, atomic::AtomicBool}, pin::Pin
 };
 use wasm_timer::SystemTime;
 use sysinfo::{get_current_pid, ProcessExt, System, SystemExt};
@@ -122,9 +127,9 @@ pub type BackgroundTask = Pin<Box<dyn Future<Output=()> + Send>>;
 pub struct ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
 	TExPool, TRpc, Backend>
 {
-	config: Configuration,
+	pub (crate) config: Configuration,
 	pub (crate) client: Arc<TCl>,
-	backend: Arc<Backend>,
+	pub (crate) backend: Arc<Backend>,
 	tasks_builder: TaskManagerBuilder,
 	keystore: Arc<RwLock<Keystore>>,
 	fetcher: Option<TFchr>,
@@ -133,10 +138,84 @@ pub struct ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
 	finality_proof_request_builder: Option<TFprb>,
 	finality_proof_provider: Option<TFpp>,
 	transaction_pool: Arc<TExPool>,
-	rpc_extensions: TRpc,
+	rpc_extensions_builder: Box<dyn RpcExtensionBuilder<Output = TRpc> + Send>,
 	remote_backend: Option<Arc<dyn RemoteBlockchain<TBl>>>,
 	marker: PhantomData<(TBl, TRtApi)>,
 	background_tasks: Vec<(&'static str, BackgroundTask)>,
+	block_announce_validator_builder: Option<Box<
+		dyn FnOnce(Arc<TCl>) -> Box<dyn BlockAnnounceValidator<TBl> + Send> + Send
+	>>,
+}
+
+/// Builds the RPC extensions for a given `DenyUnsafe` value.
+///
+/// When the service is defined, it is not known yet whether the interfaces the RPC extensions
+/// are served on allow unsafe methods, so they are built once per interface.
+pub trait RpcExtensionBuilder {
+	/// The type of the RPC extensions that are built.
+	type Output: sc_rpc::RpcExtension<sc_rpc::Metadata>;
+
+	/// Build the RPC extensions, denying unsafe methods according to `deny_unsafe`.
+	fn build(&self, deny_unsafe: sc_rpc::DenyUnsafe) -> Self::Output;
+}
+
+impl<F, R> RpcExtensionBuilder for F where
+	F: Fn(sc_rpc::DenyUnsafe) -> R,
+	R: sc_rpc::RpcExtension<sc_rpc::Metadata>,
+{
+	type Output = R;
+
+	fn build(&self, deny_unsafe: sc_rpc::DenyUnsafe) -> R {
+		(*self)(deny_unsafe)
+	}
+}
+
+/// An [`RpcExtensionBuilder`] returning clones of the same RPC extensions, regardless of
+/// `DenyUnsafe`.
+pub struct NoopRpcExtensionBuilder<R>(pub R);
+
+impl<R> RpcExtensionBuilder for NoopRpcExtensionBuilder<R> where
+	R: sc_rpc::RpcExtension<sc_rpc::Metadata> + Clone,
+{
+	type Output = R;
+
+	fn build(&self, _: sc_rpc::DenyUnsafe) -> R {
+		self.0.clone()
+	}
+}
+
+/// An [`RpcExtensionBuilder`] building the extensions defined so far, then the ones added by a
+/// call to [`ServiceBuilder::with_rpc_extensions_builder`].
+struct ChainedRpcExtensionBuilder<R, B> {
+	first: Box<dyn RpcExtensionBuilder<Output = R> + Send>,
+	second: B,
+}
+
+impl<R, B> RpcExtensionBuilder for ChainedRpcExtensionBuilder<R, B> where
+	R: sc_rpc::RpcExtension<sc_rpc::Metadata>,
+	B: RpcExtensionBuilder,
+{
+	type Output = (R, B::Output);
+
+	fn build(&self, deny_unsafe: sc_rpc::DenyUnsafe) -> Self::Output {
+		(self.first.build(deny_unsafe), self.second.build(deny_unsafe))
+	}
+}
+
+/// Prefix of the messages signed to attest the identity of the node to the telemetry servers.
+pub const TELEMETRY_ATTESTATION_PREFIX: &str = "substrate-telemetry-identity-attestation:";
+
+/// Returns the message signed with the network key in the `system.connected` telemetry message.
+///
+/// The arguments are the `network_id`, `attestation_timestamp`, `chain` and `name` fields of the
+/// telemetry message, which the telemetry servers can check the `attestation_signature` against.
+pub fn telemetry_attestation_message(
+	network_id: &str,
+	timestamp: u128,
+	chain: &str,
+	name: &str,
+) -> String {
+	format!("{}{}:{}:{}:{}", TELEMETRY_ATTESTATION_PREFIX, network_id, timestamp, chain, name)
 }
 
 /// Full client type.
@@ -246,6 +325,8 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 						path: path.clone(),
 						cache_size: cache_size.clone().map(|u| u as usize),
 					},
+				DatabaseConfig::ReadOnlyPath(path) =>
+					sc_client_db::DatabaseSettingsSrc::ReadOnlyPath(path.clone()),
 				DatabaseConfig::Custom(db) =>
 					sc_client_db::DatabaseSettingsSrc::Custom(db.clone()),
 			},
@@ -304,9 +385,10 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 			finality_proof_request_builder: None,
 			finality_proof_provider: None,
 			transaction_pool: Arc::new(()),
-			rpc_extensions: Default::default(),
+			rpc_extensions_builder: Box::new(|_: sc_rpc::DenyUnsafe| ()),
 			remote_backend: None,
 			background_tasks: Default::default(),
+			block_announce_validator_builder: None,
 			marker: PhantomData,
 		})
 	}
@@ -356,6 +438,8 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 							path: path.clone(),
 							cache_size: cache_size.clone().map(|u| u as usize),
 						},
+					DatabaseConfig::ReadOnlyPath(path) =>
+						sc_client_db::DatabaseSettingsSrc::ReadOnlyPath(path.clone()),
 					DatabaseConfig::Custom(db) =>
 						sc_client_db::DatabaseSettingsSrc::Custom(db.clone()),
 				},
@@ -381,6 +465,10 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 			config.prometheus_config.as_ref().map(|config| config.registry.clone()),
 		)?);
 
+		if let Some(epoch_snapshot) = &config.epoch_snapshot {
+			crate::chain_ops::import_epoch_snapshot::<TBl, _>(&*client, epoch_snapshot)?;
+		}
+
 		Ok(ServiceBuilder {
 			config,
 			client,
@@ -393,9 +481,10 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 			finality_proof_request_builder: None,
 			finality_proof_provider: None,
 			transaction_pool: Arc::new(()),
-			rpc_extensions: Default::default(),
+			rpc_extensions_builder: Box::new(|_: sc_rpc::DenyUnsafe| ()),
 			remote_backend: Some(remote_blockchain),
 			background_tasks: Default::default(),
+			block_announce_validator_builder: None,
 			marker: PhantomData,
 		})
 	}
@@ -430,6 +519,20 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 		self.transaction_pool.clone()
 	}
 
+	/// Returns a proposer factory using the client and transaction pool of this builder, whose
+	/// blocks only depend on their parent, inherent data and the ready transactions of the pool.
+	///
+	/// Transactions are pushed in `TransactionOrdering::Deterministic` order, and the deadline of
+	/// block building is measured with `clock`. Meant for tests needing reproducible blocks.
+	pub fn deterministic_proposer_factory(
+		&self,
+		clock: sc_basic_authorship::Clock,
+	) -> sc_basic_authorship::ProposerFactory<TExPool, Backend, TCl> {
+		sc_basic_authorship::ProposerFactory::new(self.client.clone(), self.transaction_pool.clone())
+			.with_ordering(sc_basic_authorship::TransactionOrdering::Deterministic)
+			.with_clock(clock)
+	}
+
 	/// Returns a reference to the fetcher, only available if builder
 	/// was created with `new_light`.
 	pub fn fetcher(&self) -> Option<TFchr>
@@ -466,9 +569,10 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			finality_proof_request_builder: self.finality_proof_request_builder,
 			finality_proof_provider: self.finality_proof_provider,
 			transaction_pool: self.transaction_pool,
-			rpc_extensions: self.rpc_extensions,
+			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			background_tasks: self.background_tasks,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
@@ -509,9 +613,10 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			finality_proof_request_builder: self.finality_proof_request_builder,
 			finality_proof_provider: self.finality_proof_provider,
 			transaction_pool: self.transaction_pool,
-			rpc_extensions: self.rpc_extensions,
+			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			background_tasks: self.background_tasks,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
@@ -547,9 +652,10 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			finality_proof_request_builder: self.finality_proof_request_builder,
 			finality_proof_provider,
 			transaction_pool: self.transaction_pool,
-			rpc_extensions: self.rpc_extensions,
+			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			background_tasks: self.background_tasks,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
@@ -609,9 +715,10 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			finality_proof_request_builder: fprb,
 			finality_proof_provider: self.finality_proof_provider,
 			transaction_pool: self.transaction_pool,
-			rpc_extensions: self.rpc_extensions,
+			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			background_tasks: self.background_tasks,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
@@ -669,22 +776,57 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			finality_proof_request_builder: self.finality_proof_request_builder,
 			finality_proof_provider: self.finality_proof_provider,
 			transaction_pool: Arc::new(transaction_pool),
-			rpc_extensions: self.rpc_extensions,
+			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			background_tasks: self.background_tasks,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
 
-	/// Defines the RPC extensions to use.
+	/// Adds RPC extensions, built from the components of the builder such as its client,
+	/// transaction pool and keystore.
+	///
+	/// The extensions added by successive calls are all served, so that independent modules can
+	/// each register their own namespace. A method defined twice is served by the last
+	/// extensions defining it.
 	pub fn with_rpc_extensions<URpc>(
 		self,
 		rpc_ext_builder: impl FnOnce(&Self) -> Result<URpc, Error>,
 	) -> Result<ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
-		TExPool, URpc, Backend>, Error>
-	where TSc: Clone, TFchr: Clone {
+		TExPool, (TRpc, URpc), Backend>, Error>
+	where
+		TSc: Clone,
+		TFchr: Clone,
+		TRpc: sc_rpc::RpcExtension<sc_rpc::Metadata> + 'static,
+		URpc: sc_rpc::RpcExtension<sc_rpc::Metadata> + Clone + Send + 'static,
+	{
 		let rpc_extensions = rpc_ext_builder(&self)?;
 
+		self.with_rpc_extensions_builder(|_| Ok(NoopRpcExtensionBuilder(rpc_extensions)))
+	}
+
+	/// Adds a builder of RPC extensions.
+	///
+	/// Unlike [`with_rpc_extensions`](ServiceBuilder::with_rpc_extensions), the extensions are
+	/// built for every interface the RPC is served on, and can deny unsafe methods on the
+	/// interfaces that don't allow them.
+	pub fn with_rpc_extensions_builder<URpcBuilder>(
+		self,
+		rpc_ext_builder: impl FnOnce(&Self) -> Result<URpcBuilder, Error>,
+	) -> Result<ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
+		TExPool, (TRpc, URpcBuilder::Output), Backend>, Error>
+	where
+		TSc: Clone,
+		TFchr: Clone,
+		TRpc: sc_rpc::RpcExtension<sc_rpc::Metadata> + 'static,
+		URpcBuilder: RpcExtensionBuilder + Send + 'static,
+	{
+		let rpc_extensions_builder = ChainedRpcExtensionBuilder {
+			second: rpc_ext_builder(&self)?,
+			first: self.rpc_extensions_builder,
+		};
+
 		Ok(ServiceBuilder {
 			config: self.config,
 			client: self.client,
@@ -697,9 +839,10 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			finality_proof_request_builder: self.finality_proof_request_builder,
 			finality_proof_provider: self.finality_proof_provider,
 			transaction_pool: self.transaction_pool,
-			rpc_extensions,
+			rpc_extensions_builder: Box::new(rpc_extensions_builder),
 			remote_backend: self.remote_backend,
 			background_tasks: self.background_tasks,
+			block_announce_validator_builder: self.block_announce_validator_builder,
 			marker: self.marker,
 		})
 	}
@@ -739,6 +882,60 @@ pub trait ServiceBuilderCommand {
 		self,
 		block: BlockId<Self::Block>
 	) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+
+	/// Extract the runtime code of a known block, along with its version.
+	fn export_runtime(
+		&self,
+		block: BlockId<Self::Block>
+	) -> Result<(Vec<u8>, sp_api::RuntimeVersion), Error>;
+
+	/// Extract the SCALE-encoded metadata of the runtime at the given `block`, by executing its
+	/// Wasm code. If `block` is `None`, the best block will be used.
+	fn export_metadata(
+		&self,
+		block: Option<BlockId<Self::Block>>,
+	) -> Result<Vec<u8>, Error>;
+
+	/// Export the raw state at the given `block`. If `block` is `None`, the
+	/// best block will be used.
+	fn export_raw_state(
+		&self,
+		block: Option<BlockId<Self::Block>>,
+	) -> Result<Storage, Error>;
+
+	/// Returns information about the chain stored in the database.
+	///
+	/// This reads the whole database to compute the size of its columns.
+	fn database_info(&self) -> Result<crate::chain_ops::DatabaseInfo<Self::Block>, Error>;
+
+	/// Returns the peers of the network address book stored in the database.
+	fn address_book(&self) -> Result<Vec<sc_network::PersistedPeer>, Error>;
+
+	/// Re-execute the last `count` finalized blocks with the Wasm runtime on top of the state of
+	/// their parent, checking that the execution results in their stored roots.
+	fn audit_blocks(
+		&self,
+		count: NumberFor<Self::Block>,
+	) -> Result<Vec<crate::chain_ops::BlockAudit<Self::Block>>, Error>;
+
+	/// Collect the finalized headers enacting epoch or authority set changes, along with their
+	/// justifications, and the last finalized header.
+	///
+	/// This reads every finalized header from genesis.
+	fn export_epochs(&self) -> Result<crate::chain_ops::EpochSnapshotFor<Self::Block>, Error>;
+
+	/// Send the JSON-RPC `request` to the node `peer_id` listening on `address`, over the admin
+	/// protocol, and return its response.
+	///
+	/// The request is sent with the network identity of the configuration, which must be one of
+	/// the admin peers of the node. Fails if no response is received within `timeout`.
+	fn admin_request(
+		self,
+		peer_id: sc_network::PeerId,
+		address: sc_network::Multiaddr,
+		request: String,
+		timeout: std::time::Duration,
+	) -> Pin<Box<dyn Future<Output = Result<String, Error>> + Send>>;
 }
 
 impl<TBl, TRtApi, TBackend, TExec, TSc, TImpQu, TExPool, TRpc>
@@ -770,7 +967,7 @@ ServiceBuilder<
 	TSc: Clone,
 	TImpQu: 'static + ImportQueue<TBl>,
 	TExPool: MaintainedTransactionPool<Block=TBl, Hash = <TBl as BlockT>::Hash> + MallocSizeOfWasm + 'static,
-	TRpc: sc_rpc::RpcExtension<sc_rpc::Metadata> + Clone,
+	TRpc: sc_rpc::RpcExtension<sc_rpc::Metadata>,
 {
 
 	/// Set an ExecutionExtensionsFactory
@@ -779,6 +976,20 @@ ServiceBuilder<
 		Ok(self)
 	}
 
+	/// Defines the validator of the block announcements received from the peers, e.g. checking
+	/// a proof attached to their data. The peers sending invalid announcements are penalized,
+	/// before the announced blocks are requested.
+	///
+	/// By default, every announcement is valid.
+	pub fn with_block_announce_validator(
+		mut self,
+		block_announce_validator_builder: impl FnOnce(Arc<Client<TBackend, TExec, TBl, TRtApi>>)
+			-> Box<dyn BlockAnnounceValidator<TBl> + Send> + Send + 'static
+	) -> Result<Self, Error> {
+		self.block_announce_validator_builder = Some(Box::new(block_announce_validator_builder));
+		Ok(self)
+	}
+
 	/// Builds the service.
 	pub fn build(self) -> Result<Service<
 		TBl,
@@ -808,11 +1019,17 @@ ServiceBuilder<
 			finality_proof_request_builder,
 			finality_proof_provider,
 			transaction_pool,
-			rpc_extensions,
+			rpc_extensions_builder,
 			remote_backend,
 			background_tasks,
+			block_announce_validator_builder,
 		} = self;
 
+		let consensus_recorder = match config.consensus_recorder.clone() {
+			Some(recorder_config) => Some(sc_consensus_recorder::init(recorder_config)?),
+			None => None,
+		};
+
 		sp_session::generate_initial_session_keys(
 			client.clone(),
 			&BlockId::Hash(client.chain_info().best_hash),
@@ -839,8 +1056,10 @@ ServiceBuilder<
 		client.execution_extensions()
 			.register_transaction_pool(Arc::downgrade(&transaction_pool) as _);
 
+		let disk_space_low = Arc::new(AtomicBool::new(false));
 		let transaction_pool_adapter = Arc::new(TransactionPoolAdapter {
 			imports_external_transactions: !config.roles.is_light(),
+			disk_space_low: disk_space_low.clone(),
 			pool: transaction_pool.clone(),
 			client: client.clone(),
 			executor: tasks_builder.spawn_handle(),
@@ -859,8 +1078,10 @@ ServiceBuilder<
 			sc_network::config::ProtocolId::from(protocol_id_full)
 		};
 
-		let block_announce_validator =
-			Box::new(sp_consensus::block_validation::DefaultBlockAnnounceValidator::new(client.clone()));
+		let block_announce_validator = match block_announce_validator_builder {
+			Some(builder) => builder(client.clone()),
+			None => Box::new(DefaultBlockAnnounceValidator::new(client.clone())),
+		};
 
 		let network_params = sc_network::config::Params {
 			roles: config.roles,
@@ -870,7 +1091,10 @@ ServiceBuilder<
 					spawn_handle.spawn("libp2p-node", fut);
 				}))
 			},
-			network_config: config.network.clone(),
+			network_config: sc_network::config::NetworkConfiguration {
+				max_extrinsic_size: config.max_extrinsic_size,
+				..config.network.clone()
+			},
 			chain: client.clone(),
 			finality_proof_provider,
 			finality_proof_request_builder,
@@ -907,6 +1131,10 @@ ServiceBuilder<
 			spawn_handle.spawn(title, background_task);
 		}
 
+		if let Some(consensus_recorder) = consensus_recorder {
+			spawn_handle.spawn("consensus-recorder", consensus_recorder);
+		}
+
 		{
 			// block notifications
 			let txpool = Arc::downgrade(&transaction_pool);
@@ -992,6 +1220,7 @@ ServiceBuilder<
 		}
 
 		// Prometheus metrics.
+		let mut health_sink = None;
 		let metrics = if let Some(PrometheusConfig { port, registry }) = config.prometheus_config.clone() {
 			// Set static metrics.
 			register(Gauge::<U64>::with_opts(
@@ -1009,6 +1238,31 @@ ServiceBuilder<
 
 			let metrics = ServiceMetrics::register(&registry)?;
 
+			let block_weight_metrics =
+				crate::block_weights::BlockWeightMetrics::register(&registry)?;
+			spawn_handle.spawn(
+				"block-weights",
+				crate::block_weights::build_block_weights_future(
+					client.clone(),
+					block_weight_metrics,
+				),
+			);
+
+			let health_metrics = crate::health::HealthMetrics::register(&registry)?;
+			let (health_tx, health_rx) = mpsc::unbounded::<(NetworkStatus<_>, NetworkState)>();
+			network_status_sinks.lock().push(std::time::Duration::from_millis(5000), health_tx);
+			let (consensus_events_tx, consensus_events_rx) = mpsc::unbounded();
+			health_sink = Some(consensus_events_tx);
+			spawn_handle.spawn(
+				"consensus-health",
+				crate::health::build_health_future(
+					client.clone(),
+					health_rx,
+					consensus_events_rx,
+					health_metrics,
+				),
+			);
+
 			spawn_handle.spawn(
 				"prometheus-endpoint",
 				prometheus_endpoint::init_prometheus(port, registry).map(drop)
@@ -1019,6 +1273,37 @@ ServiceBuilder<
 			None
 		};
 
+		if let Some(export_config) = config.export_finalized.clone() {
+			spawn_handle.spawn(
+				"export-finalized",
+				crate::finalized_export::build_finalized_export_future(
+					client.clone(),
+					export_config,
+				),
+			);
+		}
+
+		// The free space of the disk can't be queried in the browser.
+		#[cfg(not(target_os = "unknown"))]
+		{
+			if let (Some(watchdog_config), DatabaseConfig::Path { path, .. }) =
+				(config.disk_watchdog.clone(), config.expect_database())
+			{
+				let mut essential_failed = essential_failed_tx.clone();
+				spawn_handle.spawn(
+					"disk-watchdog",
+					crate::disk_watchdog::build_disk_watchdog_future(
+						path.clone(),
+						watchdog_config,
+						network.clone(),
+						disk_space_low,
+					).map(move |()| {
+						let _ = essential_failed.unbounded_send(());
+					}),
+				);
+			}
+		}
+
 		// Periodically notify the telemetry.
 		let transaction_pool_ = transaction_pool.clone();
 		let client_ = client.clone();
@@ -1126,8 +1411,10 @@ ServiceBuilder<
 
 		// RPC
 		let (system_rpc_tx, system_rpc_rx) = mpsc::unbounded();
-		let gen_handler = || {
-			use sc_rpc::{chain, state, author, system, offchain};
+		// Shared by all the handlers, so that archive jobs can be polled through any RPC interface.
+		let archive_jobs = sc_rpc::archive::Jobs::default();
+		let gen_handler = |deny_unsafe: sc_rpc::DenyUnsafe| {
+			use sc_rpc::{chain, state, author, system, offchain, archive};
 
 			let system_info = sc_rpc::system::SystemInfo {
 				chain_name: chain_spec.name().into(),
@@ -1138,28 +1425,46 @@ ServiceBuilder<
 
 			let subscriptions = sc_rpc::Subscriptions::new(Arc::new(tasks_builder.spawn_handle()));
 
-			let (chain, state) = if let (Some(remote_backend), Some(on_demand)) =
-				(remote_backend.as_ref(), on_demand.as_ref()) {
+			let (chain, state, child_state, archive) =
+				if let (Some(remote_backend), Some(on_demand)) =
+					(remote_backend.as_ref(), on_demand.as_ref())
+			{
 				// Light clients
 				let chain = sc_rpc::chain::new_light(
 					client.clone(),
 					subscriptions.clone(),
 					remote_backend.clone(),
-					on_demand.clone()
+					on_demand.clone(),
+					deny_unsafe,
 				);
-				let state = sc_rpc::state::new_light(
+				let (state, child_state) = sc_rpc::state::new_light(
 					client.clone(),
 					subscriptions.clone(),
 					remote_backend.clone(),
-					on_demand.clone()
+					on_demand.clone(),
+					deny_unsafe,
 				);
-				(chain, state)
+				(chain, state, child_state, None)
 
 			} else {
 				// Full nodes
-				let chain = sc_rpc::chain::new_full(client.clone(), subscriptions.clone());
-				let state = sc_rpc::state::new_full(client.clone(), subscriptions.clone());
-				(chain, state)
+				let chain = sc_rpc::chain::new_full(
+					client.clone(),
+					subscriptions.clone(),
+					deny_unsafe,
+				);
+				let (state, child_state) = sc_rpc::state::new_full(
+					client.clone(),
+					subscriptions.clone(),
+					config.rpc_storage_subscription_throttle,
+					deny_unsafe,
+				);
+				let archive = sc_rpc::archive::Archive::<TBl, TBackend, _>::new(
+					client.clone(),
+					archive_jobs.clone(),
+					deny_unsafe,
+				);
+				(chain, state, child_state, Some(archive))
 			};
 
 			let author = sc_rpc::author::Author::new(
@@ -1167,32 +1472,59 @@ ServiceBuilder<
 				transaction_pool.clone(),
 				subscriptions,
 				keystore.clone(),
+				config.max_extrinsic_size,
+				deny_unsafe,
 			);
-			let system = system::System::new(system_info, system_rpc_tx.clone());
+			let system = system::System::new(system_info, system_rpc_tx.clone(), deny_unsafe);
 
 			match offchain_storage.clone() {
 				Some(storage) => {
-					let offchain = sc_rpc::offchain::Offchain::new(storage);
+					let offchain = sc_rpc::offchain::Offchain::new(storage, deny_unsafe);
 					sc_rpc_server::rpc_handler((
 						state::StateApi::to_delegate(state),
+						state::ChildStateApi::to_delegate(child_state),
 						chain::ChainApi::to_delegate(chain),
 						offchain::OffchainApi::to_delegate(offchain),
 						author::AuthorApi::to_delegate(author),
 						system::SystemApi::to_delegate(system),
-						rpc_extensions.clone(),
+						archive.map(archive::ArchiveApi::to_delegate),
+						rpc_extensions_builder.build(deny_unsafe),
 					))
 				},
 				None => sc_rpc_server::rpc_handler((
 					state::StateApi::to_delegate(state),
+					state::ChildStateApi::to_delegate(child_state),
 					chain::ChainApi::to_delegate(chain),
 					author::AuthorApi::to_delegate(author),
 					system::SystemApi::to_delegate(system),
-					rpc_extensions.clone(),
+					archive.map(archive::ArchiveApi::to_delegate),
+					rpc_extensions_builder.build(deny_unsafe),
 				))
 			}
 		};
-		let rpc_handlers = gen_handler();
-		let rpc = start_rpc_servers(&config, gen_handler)?;
+		// RPC queries that don't go through the HTTP or WebSockets servers are always trusted.
+		let rpc_handlers = gen_handler(sc_rpc::DenyUnsafe::No);
+
+		if !config.admin_peers.is_empty() {
+			spawn_handle.spawn(
+				"admin-protocol",
+				crate::admin::build_admin_future(
+					network.clone(),
+					crate::admin::admin_handler(&gen_handler),
+					config.admin_peers.iter().cloned().collect(),
+				),
+			);
+		}
+
+		let rpc_server_handlers = RpcServerHandlers {
+			safe: gen_handler(sc_rpc::DenyUnsafe::Yes),
+			all: gen_handler(sc_rpc::DenyUnsafe::No),
+		};
+		let rpc_metrics = config.prometheus_config.as_ref()
+			.map(|config| sc_rpc_server::RpcMetrics::register(&config.registry))
+			.transpose()?;
+		let rpc_config = RpcServersConfig::new(&config, rpc_metrics);
+		let rpc = start_rpc_servers(&rpc_config, &rpc_server_handlers)?;
 
 		spawn_handle.spawn(
 			"network-worker",
@@ -1205,6 +1537,7 @@ ServiceBuilder<
 				has_bootnodes,
 			),
 		);
+		let (reload_tx, reload_rx) = mpsc::unbounded();
 
 		let telemetry_connection_sinks: Arc<Mutex<Vec<futures::channel::mpsc::UnboundedSender<()>>>> = Default::default();
 
@@ -1217,6 +1550,7 @@ ServiceBuilder<
 			let version = version.clone();
 			let chain_name = config.expect_chain_spec().name().to_owned();
 			let telemetry_connection_sinks_ = telemetry_connection_sinks.clone();
+			let network_ = network.clone();
 			let telemetry = sc_telemetry::init_telemetry(sc_telemetry::TelemetryConfig {
 				endpoints,
 				wasm_external_transport: config.telemetry_external_transport.take(),
@@ -1229,6 +1563,30 @@ ServiceBuilder<
 					// Safe-guard in case we add more events in the future.
 					let sc_telemetry::TelemetryEvent::Connected = event;
 
+					// Signed anew for each connection, so that the timestamp tells how fresh it is.
+					let timestamp = SystemTime::UNIX_EPOCH.elapsed()
+						.map(|dur| dur.as_millis())
+						.unwrap_or(0);
+					let message = telemetry_attestation_message(
+						&network_id,
+						timestamp,
+						&chain_name,
+						&name,
+					);
+					let (public_key, signature) = match network_.sign_with_local_identity(message) {
+						Ok(signature) => {
+							let public_key = signature.public_key.into_protobuf_encoding();
+							(
+								sp_core::bytes::to_hex(&public_key, false),
+								sp_core::bytes::to_hex(&signature.bytes, false),
+							)
+						},
+						Err(e) => {
+							warn!("Failed to sign the telemetry attestation: {}", e);
+							(String::new(), String::new())
+						},
+					};
+
 					telemetry!(SUBSTRATE_INFO; "system.connected";
 						"name" => name.clone(),
 						"implementation" => impl_name.clone(),
@@ -1237,7 +1595,10 @@ ServiceBuilder<
 						"chain" => chain_name.clone(),
 						"authority" => is_authority,
 						"startup_time" => startup_time,
-						"network_id" => network_id.clone()
+						"network_id" => network_id.clone(),
+						"attestation_timestamp" => timestamp,
+						"attestation_public_key" => public_key,
+						"attestation_signature" => signature
 					);
 
 					telemetry_connection_sinks_.lock().retain(|sink| {
@@ -1274,14 +1635,22 @@ ServiceBuilder<
 			transaction_pool,
 			essential_failed_tx,
 			essential_failed_rx,
+			reload_tx,
+			reload_rx,
+			system_rpc_tx,
+			reserved_nodes: config.network.reserved_nodes.clone(),
 			rpc_handlers,
+			rpc_server_handlers,
+			rpc_config,
 			_rpc: rpc,
 			_telemetry: telemetry,
+			telemetry_endpoints: config.telemetry_endpoints.clone(),
 			_offchain_workers: offchain_workers,
 			_telemetry_on_connect_sinks: telemetry_connection_sinks.clone(),
 			keystore,
 			marker: PhantomData::<TBl>,
-			prometheus_registry: config.prometheus_config.map(|config| config.registry)
+			prometheus_registry: config.prometheus_config.map(|config| config.registry),
+			health_sink,
 		})
 	}
 }
@@ -19,6 +19,7 @@
 use crate::error;
 use crate::builder::{ServiceBuilderCommand, ServiceBuilder};
 use crate::error::Error;
+use crate::config::EpochSnapshotConfig;
 use sc_chain_spec::ChainSpec;
 use log::{warn, info};
 use futures::{future, prelude::*};
@@ -30,18 +31,99 @@ use codec::{Decode, Encode, IoReader};
 use sc_client::{Client, LocalCallExecutor};
 use sp_consensus::{
 	BlockOrigin,
+	block_validation::DefaultBlockAnnounceValidator,
 	import_queue::{IncomingBlock, Link, BlockImportError, BlockImportResult, ImportQueue},
 };
-use sc_executor::{NativeExecutor, NativeExecutionDispatch};
+use sc_network::{
+	Multiaddr, NetworkWorker, PeerId,
+	config::{EmptyTransactionPool, NetworkConfiguration, NonReservedPeerMode},
+};
+use sc_executor::{
+	NativeExecutor, NativeExecutionDispatch, WasmExecutor, WasmExecutionMethod, CallInWasm,
+	sp_wasm_interface::HostFunctions,
+};
+
+use std::{io::{Read, Write, Seek}, pin::Pin, collections::BTreeMap, sync::Arc, time::Duration};
+use sc_client_api::{
+	BlockBackend, StorageProvider, DatabaseColumnInfo, ExecutorProvider, CallExecutor,
+	ExecutionStrategy, backend::{AuxStore, Backend as _},
+};
+use sp_blockchain::HeaderBackend;
+use sc_consensus_epochs::snapshot::{
+	Checkpoints, EpochSnapshot, Transition, write_checkpoints,
+};
+use sp_core::{
+	storage::{StorageKey, Storage, well_known_keys}, hexdisplay::HexDisplay, OpaqueMetadata,
+};
 
-use std::{io::{Read, Write, Seek}, pin::Pin};
-use sc_client_api::BlockBackend;
+pub use sc_consensus_epochs::snapshot::{EpochSnapshotFor, SignedEpochSnapshot};
 
 /// Build a chain spec json
 pub fn build_spec(spec: &dyn ChainSpec, raw: bool) -> error::Result<String> {
 	Ok(spec.as_json(raw)?)
 }
 
+/// Build the genesis storage of the Wasm runtime `code` from the JSON genesis `config`, with the
+/// `GenesisBuilder` runtime api. No native runtime is involved.
+///
+/// The runtime `code` is part of the storage, unless the runtime overwrites it.
+pub fn build_genesis_state(
+	code: &[u8],
+	config: &[u8],
+	wasm_method: WasmExecutionMethod,
+) -> error::Result<Storage> {
+	let executor = WasmExecutor::new(
+		wasm_method,
+		None,
+		sp_io::SubstrateHostFunctions::host_functions(),
+		false,
+		1,
+	);
+	let mut top = BTreeMap::new();
+	top.insert(well_known_keys::CODE.to_vec(), code.to_vec());
+	let mut ext = sp_state_machine::BasicExternalities::new(
+		Storage { top, children: Default::default() },
+	);
+
+	let result = executor.call_in_wasm(
+		code,
+		None,
+		sp_genesis_builder::BUILD_CONFIG,
+		&config.to_vec().encode(),
+		&mut ext,
+	).map_err(|e| format!("Failed to build the genesis state: {}", e))?;
+	sp_genesis_builder::BuildResult::decode(&mut &result[..])
+		.map_err(|e| format!("Invalid result of {}: {:?}", sp_genesis_builder::BUILD_CONFIG, e))?
+		.map_err(|e| format!("Invalid genesis config: {}", String::from_utf8_lossy(&e)))?;
+
+	Ok(ext.into_storages())
+}
+
+/// Information about the chain stored in a database.
+#[derive(Debug)]
+pub struct DatabaseInfo<Block: BlockT> {
+	/// Best, finalized and genesis blocks.
+	pub chain: sp_blockchain::Info<Block>,
+	/// Version of the runtime at the best block.
+	pub runtime_version: sp_api::RuntimeVersion,
+	/// Size of each column of the database, if the backend is backed by one.
+	pub columns: Option<Vec<DatabaseColumnInfo>>,
+}
+
+/// Result of the re-execution of a block, as returned by
+/// [`ServiceBuilderCommand::audit_blocks`].
+#[derive(Debug)]
+pub struct BlockAudit<Block: BlockT> {
+	/// Number of the block.
+	pub number: NumberFor<Block>,
+	/// Hash of the block.
+	pub hash: Block::Hash,
+	/// State root stored in the header of the block.
+	pub state_root: Block::Hash,
+	/// Error of the execution, if it failed or didn't result in the stored roots.
+	pub error: Option<String>,
+}
+
 impl<
 	TBl, TRtApi, TBackend,
 	TExecDisp, TFchr, TSc, TImpQu, TFprb, TFpp,
@@ -56,6 +138,7 @@ impl<
 	TExecDisp: 'static + NativeExecutionDispatch,
 	TImpQu: 'static + ImportQueue<TBl>,
 	TRtApi: 'static + Send + Sync,
+	Backend: sc_client_api::backend::Backend<TBl>,
 {
 	type Block = TBl;
 	type NativeDispatch = TExecDisp;
@@ -67,14 +150,14 @@ impl<
 	) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
 		struct WaitLink {
 			imported_blocks: u64,
-			has_error: bool,
+			error: Option<String>,
 		}
 
 		impl WaitLink {
 			fn new() -> WaitLink {
 				WaitLink {
 					imported_blocks: 0,
-					has_error: false,
+					error: None,
 				}
 			}
 		}
@@ -91,7 +174,9 @@ impl<
 				for result in results {
 					if let (Err(err), hash) = result {
 						warn!("There was an error importing block with hash {:?}: {:?}", hash, err);
-						self.has_error = true;
+						self.error = Some(
+							format!("Block {:?} was rejected. {}", hash, describe_import_error(&err))
+						);
 						break;
 					}
 				}
@@ -168,12 +253,12 @@ impl<
 			let blocks_before = link.imported_blocks;
 			queue.poll_actions(cx, &mut link);
 
-			if link.has_error {
+			if let Some(error) = link.error.take() {
 				info!(
 					"Stopping after #{} blocks because of an error",
 					link.imported_blocks,
 				);
-				return std::task::Poll::Ready(Ok(()));
+				return std::task::Poll::Ready(Err(error.into()));
 			}
 
 			if link.imported_blocks / 1000 != blocks_before / 1000 {
@@ -296,4 +381,232 @@ impl<
 			Err(e) => Box::pin(future::err(format!("Error reading block: {:?}", e).into())),
 		}
 	}
+
+	fn export_runtime(
+		&self,
+		block_id: BlockId<TBl>
+	) -> Result<(Vec<u8>, sp_api::RuntimeVersion), Error> {
+		let code = self.client.storage(&block_id, &StorageKey(well_known_keys::CODE.to_vec()))?
+			.ok_or_else(|| format!("No runtime code at block {}", block_id))?;
+		let version = self.client.runtime_version_at(&block_id)?;
+
+		Ok((code.0, version))
+	}
+
+	fn export_metadata(
+		&self,
+		block: Option<BlockId<TBl>>,
+	) -> Result<Vec<u8>, Error> {
+		let block = block.unwrap_or_else(|| BlockId::Hash(self.client.chain_info().best_hash));
+		let metadata = self.client.executor().call(
+			&block,
+			"Metadata_metadata",
+			&[],
+			ExecutionStrategy::AlwaysWasm,
+			None,
+		)?;
+		let metadata = OpaqueMetadata::decode(&mut &metadata[..])
+			.map_err(|e| format!("Invalid metadata at block {}: {:?}", block, e))?;
+
+		Ok(metadata.to_vec())
+	}
+
+	fn export_raw_state(
+		&self,
+		block: Option<BlockId<TBl>>,
+	) -> Result<Storage, Error> {
+		let block = block.unwrap_or_else(|| BlockId::Hash(self.client.chain_info().best_hash));
+
+		let mut top = BTreeMap::new();
+		for (key, value) in self.client.storage_pairs(&block, &StorageKey(Vec::new()))? {
+			// The child tries can't be walked without their unique id, which isn't part of the
+			// state. Their roots are left out, as they are not valid genesis storage.
+			if well_known_keys::is_child_storage_key(&key.0) {
+				warn!("Child trie at {} is not exported", HexDisplay::from(&key.0));
+				continue;
+			}
+			top.insert(key.0, value.0);
+		}
+
+		Ok(Storage { top, children: Default::default() })
+	}
+
+	fn database_info(&self) -> Result<DatabaseInfo<TBl>, Error> {
+		let chain = self.client.chain_info();
+		let runtime_version = self.client.runtime_version_at(&BlockId::Hash(chain.best_hash))?;
+		let columns = self.backend.database_columns();
+
+		Ok(DatabaseInfo { chain, runtime_version, columns })
+	}
+
+	fn address_book(&self) -> Result<Vec<sc_network::PersistedPeer>, Error> {
+		Ok(sc_network::load_address_book(&*self.client))
+	}
+
+	fn audit_blocks(&self, count: NumberFor<TBl>) -> Result<Vec<BlockAudit<TBl>>, Error> {
+		let last = self.client.chain_info().finalized_number;
+		let mut number = if last > count { last - count + One::one() } else { One::one() };
+
+		let mut audits = Vec::new();
+		while number <= last {
+			let block = self.client.block(&BlockId::Number(number))?
+				.ok_or_else(|| format!("Block #{} not found", number))?
+				.block;
+			let (mut header, extrinsics) = block.deconstruct();
+			let hash = header.hash();
+			let state_root = *header.state_root();
+			let parent_hash = *header.parent_hash();
+
+			// The seal is added to the header after the execution of the block.
+			if header.digest().logs().last().map_or(false, |item| item.as_seal().is_some()) {
+				header.digest_mut().pop();
+			}
+
+			// Executing the block checks that it results in the roots of its header.
+			let error = self.client.executor().call(
+				&BlockId::Hash(parent_hash),
+				"Core_execute_block",
+				&TBl::new(header, extrinsics).encode(),
+				ExecutionStrategy::AlwaysWasm,
+				None,
+			).err().map(|e| e.to_string());
+			if let Some(error) = &error {
+				warn!("Re-execution of block #{} ({}) failed: {}", number, hash, error);
+			}
+
+			audits.push(BlockAudit { number, hash, state_root, error });
+			number += One::one();
+		}
+
+		info!("Audited {} blocks", audits.len());
+		Ok(audits)
+	}
+
+	fn export_epochs(&self) -> Result<EpochSnapshotFor<TBl>, Error> {
+		let chain = self.client.chain_info();
+
+		let mut transitions = Vec::new();
+		let mut number: NumberFor<TBl> = One::one();
+		while number <= chain.finalized_number {
+			let id = BlockId::Number(number);
+			let header = self.client.header(id)?
+				.ok_or_else(|| format!("Header #{} not found", number))?;
+			// Epoch and authority set changes are signaled with consensus digests.
+			let enacts_change = header.digest().logs().iter()
+				.any(|item| item.as_consensus().is_some());
+			if enacts_change || number == chain.finalized_number {
+				let justification = self.client.justification(&id)?;
+				transitions.push(Transition { header, justification });
+			}
+			number += One::one();
+		}
+
+		info!("Exported {} epoch transitions", transitions.len());
+		Ok(EpochSnapshot { genesis_hash: chain.genesis_hash, transitions })
+	}
+
+	fn admin_request(
+		self,
+		peer_id: PeerId,
+		address: Multiaddr,
+		request: String,
+		timeout: Duration,
+	) -> Pin<Box<dyn Future<Output = Result<String, Error>> + Send>> {
+		let protocol_id = self.config.expect_chain_spec().protocol_id()
+			.unwrap_or(crate::DEFAULT_PROTOCOL_ID)
+			.as_bytes()
+			.into();
+
+		// the only peer of the network is the administrated node.
+		let network_params = sc_network::config::Params {
+			roles: self.config.roles,
+			executor: None,
+			network_config: NetworkConfiguration {
+				listen_addresses: Vec::new(),
+				boot_nodes: Vec::new(),
+				reserved_nodes: vec![format!("{}/p2p/{}", address, peer_id)],
+				non_reserved_mode: NonReservedPeerMode::Deny,
+				..self.config.network.clone()
+			},
+			chain: self.client.clone(),
+			finality_proof_provider: None,
+			finality_proof_request_builder: None,
+			on_demand: None,
+			transaction_pool: Arc::new(EmptyTransactionPool),
+			protocol_id,
+			import_queue: Box::new(self.import_queue),
+			block_announce_validator: Box::new(DefaultBlockAnnounceValidator::new(self.client)),
+			metrics_registry: None,
+		};
+
+		let worker = match NetworkWorker::<TBl, TBl::Hash>::new(network_params) {
+			Ok(worker) => worker,
+			Err(err) => return Box::pin(future::err(err.into())),
+		};
+		let response = crate::admin::send_request(worker.service().clone(), peer_id, request);
+
+		// the network worker must be polled until the response is received.
+		let response = future::select(Box::pin(response), worker.map(|_| None));
+		let timeout = futures_timer::Delay::new(timeout).map(|_| None);
+		Box::pin(future::select(response, timeout).map(|either| {
+			let response = match either {
+				future::Either::Left((future::Either::Left((response, _)), _)) => response,
+				future::Either::Left((future::Either::Right((response, _)), _)) => response,
+				future::Either::Right((response, _)) => response,
+			};
+			response.ok_or_else(|| Error::Other("No response from the node".into()))
+		}))
+	}
+}
+
+/// Imports the checkpoints of the signed epoch snapshot of `config` into the auxiliary storage
+/// of `client`, so that the checkpointed headers are imported without verifying their seals.
+pub fn import_epoch_snapshot<Block, C>(
+	client: &C,
+	config: &EpochSnapshotConfig,
+) -> error::Result<()> where
+	Block: BlockT,
+	C: AuxStore + HeaderBackend<Block>,
+{
+	let encoded = std::fs::read(&config.path)
+		.map_err(|e| format!("Failed to read epoch snapshot {}: {}", config.path.display(), e))?;
+	let signed = SignedEpochSnapshot::decode(&mut &encoded[..])
+		.map_err(|e| format!("Invalid epoch snapshot: {}", e.what()))?;
+	let snapshot: EpochSnapshotFor<Block> = signed.verify(&config.signer)?;
+
+	if snapshot.genesis_hash != client.info().genesis_hash {
+		return Err("Epoch snapshot is for another chain".into());
+	}
+
+	let checkpoints = Checkpoints::from_snapshot(&snapshot)?;
+	write_checkpoints::<Block, _>(client, &checkpoints)?;
+
+	let last = snapshot.transitions.last()
+		.map_or(Zero::zero(), |transition| *transition.header.number());
+	info!(
+		"Imported epoch snapshot with {} transitions, up to #{}",
+		snapshot.transitions.len(),
+		last,
+	);
+	Ok(())
+}
+
+/// Describe the stage of the import pipeline at which a block was rejected.
+fn describe_import_error(err: &BlockImportError) -> String {
+	match err {
+		BlockImportError::IncompleteHeader(_) =>
+			"Header check failed: the block has no header.".into(),
+		BlockImportError::UnknownParent =>
+			"Pre-import check failed: the parent block is unknown.".into(),
+		BlockImportError::MissingState =>
+			"Pre-import check failed: the state of the parent block is missing.".into(),
+		BlockImportError::BadBlock(_) =>
+			"Pre-import check failed: the block is known to be bad.".into(),
+		BlockImportError::VerificationFailed(_, e) =>
+			format!("Consensus verification failed: {}", e),
+		BlockImportError::Other(e) =>
+			format!("Import into the client failed: {}", e),
+		BlockImportError::Cancelled =>
+			"Import was cancelled.".into(),
+	}
 }
@@ -17,12 +17,19 @@
 //! Service configuration.
 
 pub use sc_client::ExecutionStrategies;
-pub use sc_client_db::{kvdb::KeyValueDB, PruningMode};
+pub use sc_client_db::{kvdb::KeyValueDB, PruningMode, StoredPruning};
 pub use sc_network::config::{ExtTransport, NetworkConfiguration, Roles};
+pub use sc_network::PeerId;
 pub use sc_executor::WasmExecutionMethod;
 
-use std::{future::Future, path::{PathBuf, Path}, pin::Pin, net::SocketAddr, sync::Arc};
+use std::{future::Future, path::{PathBuf, Path}, pin::Pin, net::SocketAddr, sync::Arc, time::Duration};
 pub use sc_transaction_pool::txpool::Options as TransactionPoolOptions;
+pub use sc_consensus_recorder::RecorderConfig as ConsensusRecorderConfig;
+pub use sc_rpc_server::RateLimit as RpcRateLimit;
+pub use sc_rpc_server::RpcLimits;
+pub use sc_rpc_server::TlsConfig as RpcTlsConfig;
+pub use sc_rpc::state::StorageSubscriptionThrottle;
+pub use sp_consensus_babe::SecondarySlots;
 use sc_chain_spec::ChainSpec;
 use sp_core::crypto::Protected;
 use target_info::Target;
@@ -64,6 +71,9 @@ pub struct Configuration {
 	pub task_executor: Option<Arc<dyn Fn(Pin<Box<dyn Future<Output = ()> + Send>>) + Send + Sync>>,
 	/// Extrinsic pool configuration.
 	pub transaction_pool: TransactionPoolOptions,
+	/// Maximum encoded size of an extrinsic submitted over RPC or received from the network.
+	/// Larger ones are rejected before reaching the transaction pool. `None` if unlimited.
+	pub max_extrinsic_size: Option<usize>,
 	/// Network configuration.
 	pub network: NetworkConfiguration,
 	/// Path to the base configuration directory.
@@ -94,6 +104,23 @@ pub struct Configuration {
 	pub rpc_ws_max_connections: Option<usize>,
 	/// CORS settings for HTTP & WS servers. `None` if all origins are allowed.
 	pub rpc_cors: Option<Vec<String>>,
+	/// RPC methods to expose (by default only a safe subset or all of them).
+	pub rpc_methods: RpcMethods,
+	/// Limits on the payloads, batches and subscriptions of the HTTP & WS servers.
+	pub rpc_limits: RpcLimits,
+	/// Limit on the rate of calls to the HTTP & WS servers. `None` if unlimited.
+	pub rpc_rate_limit: Option<RpcRateLimit>,
+	/// Certificate and key the HTTP & WS servers terminate TLS with. `None` if disabled.
+	pub rpc_tls: Option<RpcTlsConfig>,
+	/// Whether the WS server compresses messages for the clients supporting it.
+	pub rpc_ws_compression: bool,
+	/// Minimum spacing of the notifications of storage subscriptions. `None` if unthrottled.
+	pub rpc_storage_subscription_throttle: Option<StorageSubscriptionThrottle>,
+	/// Local socket the external workers connect to. `None` if disabled.
+	pub worker_bridge: Option<WorkerBridgeConfig>,
+	/// Peers allowed to administrate the node over the network. The admin protocol is disabled
+	/// if empty.
+	pub admin_peers: Vec<PeerId>,
 	/// Prometheus endpoint configuration. `None` if disabled.
 	pub prometheus_config: Option<PrometheusConfig>,
 	/// Telemetry service URL. `None` if disabled.
@@ -109,8 +136,15 @@ pub struct Configuration {
 	/// actively participate in consensus (i.e. no keystores should be passed to
 	/// consensus modules).
 	pub sentry_mode: bool,
-	/// Enable authoring even when offline.
+	/// Enable authoring even when offline, or when no block is received from the peers.
 	pub force_authoring: bool,
+	/// Whether the secondary slots are claimed. `None` leaves it to the chain spec.
+	pub secondary_slots: Option<SecondarySlots>,
+	/// Skip authoring while the best block is more than this number of blocks ahead of the
+	/// finalized block. `None` means no limit.
+	pub max_unfinalized_blocks: Option<u32>,
+	/// Proportion of the slot given to build a block. `None` leaves it to the chain spec.
+	pub proposal_slot_proportion: Option<f32>,
 	/// Disable GRANDPA when running in validator mode
 	pub disable_grandpa: bool,
 	/// Development key seed.
@@ -123,10 +157,20 @@ pub struct Configuration {
 	pub tracing_targets: Option<String>,
 	/// Tracing receiver
 	pub tracing_receiver: sc_tracing::TracingReceiver,
+	/// Where and how to record consensus artifacts. `None` if disabled.
+	pub consensus_recorder: Option<ConsensusRecorderConfig>,
+	/// Where to export the last finalized block. `None` if disabled.
+	pub export_finalized: Option<FinalizedExportConfig>,
+	/// Trusted epoch snapshot imported by light clients on start. `None` if disabled.
+	pub epoch_snapshot: Option<EpochSnapshotConfig>,
+	/// Thresholds of free space on the disk of the database. `None` if not checked.
+	pub disk_watchdog: Option<DiskWatchdogConfig>,
 	/// The size of the instances cache.
 	///
 	/// The default value is 8.
 	pub max_runtime_instances: usize,
+	/// Maximum time given to the node to shut down gracefully once an exit signal was received.
+	pub shutdown_timeout: Duration,
 }
 
 /// Configuration of the client keystore.
@@ -155,6 +199,24 @@ impl KeystoreConfig {
 	}
 }
 
+/// Available RPC methods.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RpcMethods {
+	/// Expose every RPC method only when the RPC server is listening on localhost,
+	/// otherwise only the safe subset.
+	Auto,
+	/// Allow only a safe subset of RPC methods.
+	Safe,
+	/// Expose every RPC method (even potentially unsafe ones).
+	Unsafe,
+}
+
+impl Default for RpcMethods {
+	fn default() -> Self {
+		RpcMethods::Auto
+	}
+}
+
 /// Configuration of the database of the client.
 #[derive(Clone)]
 pub enum DatabaseConfig {
@@ -166,6 +228,9 @@ pub enum DatabaseConfig {
 		cache_size: Option<u32>,
 	},
 
+	/// Full node database file at a specific path, opened for reading only.
+	ReadOnlyPath(PathBuf),
+
 	/// A custom implementation of an already-open database.
 	Custom(Arc<dyn KeyValueDB>),
 }
@@ -192,6 +257,62 @@ impl PrometheusConfig {
 	}
 }
 
+/// Configuration of the export of the last finalized block to a file.
+#[derive(Debug, Clone)]
+pub struct FinalizedExportConfig {
+	/// File the number and hash of the last finalized block are written to, as JSON.
+	pub path: PathBuf,
+	/// Shell command run after each write, with the block in the `FINALIZED_NUMBER` and
+	/// `FINALIZED_HASH` environment variables and the file in `FINALIZED_FILE`.
+	pub hook: Option<String>,
+}
+
+/// Configuration of the import of a signed epoch snapshot, see `sc_consensus_epochs::snapshot`.
+#[derive(Debug, Clone)]
+pub struct EpochSnapshotConfig {
+	/// File containing the SCALE-encoded `SignedEpochSnapshot`.
+	pub path: PathBuf,
+	/// Key the snapshot must be signed with.
+	pub signer: sp_core::sr25519::Public,
+}
+
+/// Thresholds of free space on the disk of the database, in bytes. The disk is checked
+/// periodically, and the node degrades gracefully as it fills up.
+#[derive(Debug, Clone)]
+pub struct DiskWatchdogConfig {
+	/// Free space under which a warning is logged.
+	pub warn: u64,
+	/// Free space under which the gossip messages and transactions received from the network
+	/// are dropped.
+	pub degrade: u64,
+	/// Free space under which the node shuts down, before the database gets corrupted.
+	pub shutdown: u64,
+	/// Interval between two checks.
+	pub interval: Duration,
+}
+
+/// Unix socket through which the external workers, such as oracles or provers, follow the chain
+/// and submit transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerBridgeConfig {
+	/// Path of the socket, only accessible to the user running the node.
+	pub path: PathBuf,
+	/// Token the workers authenticate with.
+	pub token: String,
+}
+
+/// The part of the configuration that can be changed while the service is running, see
+/// `AbstractService::reload_handle`.
+#[derive(Debug, Clone, Default)]
+pub struct ReloadableConfig {
+	/// Telemetry service URL. `None` if disabled.
+	pub telemetry_endpoints: Option<TelemetryEndpoints>,
+	/// List of reserved node addresses.
+	pub reserved_nodes: Vec<String>,
+	/// CORS settings for HTTP & WS servers. `None` if all origins are allowed.
+	pub rpc_cors: Option<Vec<String>>,
+}
+
 impl Default for Configuration {
 	/// Create a default config
 	fn default() -> Self {
@@ -205,6 +326,7 @@ impl Default for Configuration {
 			roles: Roles::FULL,
 			task_executor: None,
 			transaction_pool: Default::default(),
+			max_extrinsic_size: None,
 			network: Default::default(),
 			keystore: KeystoreConfig::None,
 			database: None,
@@ -217,6 +339,14 @@ impl Default for Configuration {
 			rpc_ws: None,
 			rpc_ws_max_connections: None,
 			rpc_cors: Some(vec![]),
+			rpc_methods: Default::default(),
+			rpc_limits: Default::default(),
+			rpc_rate_limit: None,
+			rpc_tls: None,
+			rpc_ws_compression: true,
+			rpc_storage_subscription_throttle: None,
+			worker_bridge: None,
+			admin_peers: Vec::new(),
 			prometheus_config: None,
 			telemetry_endpoints: None,
 			telemetry_external_transport: None,
@@ -224,11 +354,19 @@ impl Default for Configuration {
 			offchain_worker: Default::default(),
 			sentry_mode: false,
 			force_authoring: false,
+			secondary_slots: None,
+			max_unfinalized_blocks: None,
+			proposal_slot_proportion: None,
 			disable_grandpa: false,
 			dev_key_seed: None,
 			tracing_targets: Default::default(),
 			tracing_receiver: Default::default(),
+			consensus_recorder: None,
+			export_finalized: None,
+			epoch_snapshot: None,
+			disk_watchdog: None,
 			max_runtime_instances: 8,
+			shutdown_timeout: Duration::from_secs(60),
 		}
 	}
 }
@@ -244,6 +382,15 @@ impl Configuration {
 		config
 	}
 
+	/// Returns the part of this configuration that can be changed while the service is running.
+	pub fn reloadable(&self) -> ReloadableConfig {
+		ReloadableConfig {
+			telemetry_endpoints: self.telemetry_endpoints.clone(),
+			reserved_nodes: self.network.reserved_nodes.clone(),
+			rpc_cors: self.rpc_cors.clone(),
+		}
+	}
+
 	/// Returns full version string of this configuration.
 	pub fn full_version(&self) -> String {
 		full_version_from_strs(self.impl_version, self.impl_commit)
@@ -294,6 +441,28 @@ impl Configuration {
 		}
 	}
 
+	/// Open the database at the configured path for reading only, with the pruning mode stored in
+	/// it rather than the configured one. Returns the pruning stored in the database.
+	///
+	/// This function returns an error if the database isn't given by a path, or if it doesn't
+	/// exist or can't be opened.
+	pub fn use_read_only_database(&mut self) -> Result<StoredPruning, String> {
+		let path = match self.expect_database() {
+			DatabaseConfig::Path { path, .. } | DatabaseConfig::ReadOnlyPath(path) => path.clone(),
+			DatabaseConfig::Custom(_) => return Err("Database config isn't a path".into()),
+		};
+		let pruning = {
+			let db = sc_client_db::open_read_only_database(&path).map_err(|e| e.to_string())?;
+			sc_client_db::stored_pruning(&*db)
+				.map_err(|e| e.to_string())?
+				.ok_or_else(|| "No pruning mode is stored in the database".to_string())?
+		};
+
+		self.pruning = pruning.mode.clone();
+		self.database = Some(DatabaseConfig::ReadOnlyPath(path));
+		Ok(pruning)
+	}
+
 	/// Use in memory keystore config when it is not required at all.
 	///
 	/// This function returns an error if the keystore is already set to something different than
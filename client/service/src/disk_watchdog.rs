@@ -0,0 +1,137 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Periodic check of the free space on the disk of the database.
+//!
+//! A database running out of space in the middle of a commit may be left corrupted, so the node
+//! degrades gracefully as the disk fills: it first warns, then drops the gossip messages and
+//! transactions received from the network, and finally shuts down while it still can.
+
+use std::{
+	path::PathBuf,
+	sync::{Arc, atomic::{AtomicBool, Ordering}},
+};
+
+use futures_timer::Delay;
+use log::{error, info, warn};
+use sc_network::{ExHashT, NetworkService};
+use sp_runtime::traits::Block as BlockT;
+
+use crate::config::DiskWatchdogConfig;
+
+/// How the node behaves given the free space on the disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+	Normal,
+	Warn,
+	Degraded,
+	Shutdown,
+}
+
+fn level(available: u64, config: &DiskWatchdogConfig) -> Level {
+	if available < config.shutdown {
+		Level::Shutdown
+	} else if available < config.degrade {
+		Level::Degraded
+	} else if available < config.warn {
+		Level::Warn
+	} else {
+		Level::Normal
+	}
+}
+
+/// Builds the task checking the free space on the disk of `path`. The task ends when the free
+/// space falls under the shutdown threshold, and is meant to be spawned as an essential task.
+///
+/// While degraded, `disk_space_low` is set and `network` drops the notifications it receives.
+pub(crate) async fn build_disk_watchdog_future<B: BlockT, H: ExHashT>(
+	path: PathBuf,
+	config: DiskWatchdogConfig,
+	network: Arc<NetworkService<B, H>>,
+	disk_space_low: Arc<AtomicBool>,
+) {
+	let mut current = Level::Normal;
+	loop {
+		let available = match fs2::available_space(&path) {
+			Ok(available) => available,
+			Err(e) => {
+				warn!("Failed to get the free disk space of {}: {}", path.display(), e);
+				Delay::new(config.interval).await;
+				continue;
+			},
+		};
+
+		let new = level(available, &config);
+		let available_mib = available / 1024 / 1024;
+		match new {
+			Level::Shutdown => {
+				error!(
+					"Only {} MiB left on the disk of the database, shutting down to avoid \
+					corrupting it",
+					available_mib,
+				);
+				return;
+			},
+			Level::Degraded if current < Level::Degraded => warn!(
+				"Only {} MiB left on the disk of the database, no longer accepting gossip messages \
+				and transactions from the network",
+				available_mib,
+			),
+			Level::Warn if current != Level::Warn => warn!(
+				"Only {} MiB left on the disk of the database",
+				available_mib,
+			),
+			Level::Normal if current != Level::Normal =>
+				info!("{} MiB left on the disk of the database", available_mib),
+			_ => {},
+		}
+
+		if (new == Level::Degraded) != (current == Level::Degraded) {
+			let degraded = new == Level::Degraded;
+			if !degraded {
+				info!("Accepting gossip messages and transactions from the network again");
+			}
+			disk_space_low.store(degraded, Ordering::Relaxed);
+			network.set_accepts_notifications(!degraded);
+		}
+		current = new;
+
+		Delay::new(config.interval).await;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	#[test]
+	fn levels_follow_thresholds() {
+		let config = DiskWatchdogConfig {
+			warn: 300,
+			degrade: 200,
+			shutdown: 100,
+			interval: Duration::from_secs(1),
+		};
+
+		assert_eq!(level(400, &config), Level::Normal);
+		assert_eq!(level(300, &config), Level::Normal);
+		assert_eq!(level(299, &config), Level::Warn);
+		assert_eq!(level(199, &config), Level::Degraded);
+		assert_eq!(level(99, &config), Level::Shutdown);
+		assert_eq!(level(0, &config), Level::Shutdown);
+	}
+}
@@ -59,6 +59,21 @@ impl From<prometheus_endpoint::PrometheusError> for Error {
 	}
 }
 
+impl Error {
+	/// The category of the error.
+	pub fn kind(&self) -> sp_blockchain::ErrorKind {
+		use sp_blockchain::ErrorKind;
+		match self {
+			Error::Client(e) => e.kind(),
+			Error::Consensus(_) => ErrorKind::Consensus,
+			Error::Network(_) => ErrorKind::Network,
+			Error::Keystore(_) => ErrorKind::Keystore,
+			Error::SelectChainRequired | Error::TaskExecutorRequired => ErrorKind::Config,
+			Error::Io(_) | Error::Other(_) => ErrorKind::Other,
+		}
+	}
+}
+
 impl std::error::Error for Error {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
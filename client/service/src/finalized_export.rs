@@ -0,0 +1,137 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Export of the last finalized block to a file, for external systems following finality
+//! without speaking RPC.
+//!
+//! The file is replaced atomically, so readers never see it half-written. After each write the
+//! optional hook command is run through the shell, with the block in its environment. A hook
+//! still running when the next block is finalized is not run again for that block.
+
+use std::{
+	fs, io, path::{Path, PathBuf}, process::Command, thread,
+	sync::{Arc, atomic::{AtomicBool, Ordering}},
+};
+
+use futures::{prelude::*, future::ready, stream};
+use log::{debug, warn};
+use sc_client_api::BlockchainEvents;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, SaturatedConversion};
+
+use crate::config::FinalizedExportConfig;
+
+/// Builds the task that writes every newly finalized block to the configured file.
+pub(crate) fn build_finalized_export_future<Block, Client>(
+	client: Arc<Client>,
+	config: FinalizedExportConfig,
+) -> impl Future<Output = ()> where
+	Block: BlockT,
+	Client: HeaderBackend<Block> + BlockchainEvents<Block>,
+{
+	let info = client.info();
+	let current = stream::iter(Some((info.finalized_number, info.finalized_hash)));
+	let finalized = client.finality_notification_stream()
+		.map(|notification| (*notification.header.number(), notification.hash));
+
+	let hook_running = Arc::new(AtomicBool::new(false));
+	current.chain(finalized).for_each(move |(number, hash)| {
+		let number = number.saturated_into::<u64>();
+		let json = serde_json::json!({
+			"number": number,
+			"hash": hash,
+		});
+		if let Err(e) = write_atomically(&config.path, json.to_string().as_bytes()) {
+			warn!("Failed to export finalized block to {}: {}", config.path.display(), e);
+		} else if let Some(hook) = &config.hook {
+			run_hook(hook, &config.path, number, &format!("{:?}", hash), &hook_running);
+		}
+		ready(())
+	})
+}
+
+/// Writes `data` to a temporary file next to `path`, then renames it to `path`.
+fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+	let mut tmp = path.as_os_str().to_owned();
+	tmp.push(".tmp");
+	let tmp = PathBuf::from(tmp);
+
+	fs::write(&tmp, data)?;
+	fs::rename(&tmp, path)
+}
+
+/// Spawns the hook command, unless the previous one is still running.
+fn run_hook(hook: &str, path: &Path, number: u64, hash: &str, running: &Arc<AtomicBool>) {
+	if running.swap(true, Ordering::SeqCst) {
+		debug!("Finalized block hook still running, skipping block #{}", number);
+		return;
+	}
+
+	let spawned = shell(hook)
+		.env("FINALIZED_NUMBER", number.to_string())
+		.env("FINALIZED_HASH", hash)
+		.env("FINALIZED_FILE", path)
+		.spawn();
+	let mut child = match spawned {
+		Ok(child) => child,
+		Err(e) => {
+			warn!("Failed to run finalized block hook: {}", e);
+			running.store(false, Ordering::SeqCst);
+			return;
+		},
+	};
+
+	let running = running.clone();
+	thread::spawn(move || {
+		match child.wait() {
+			Ok(status) if !status.success() => warn!("Finalized block hook failed: {}", status),
+			Err(e) => warn!("Failed to wait for finalized block hook: {}", e),
+			Ok(_) => {},
+		}
+		running.store(false, Ordering::SeqCst);
+	});
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+	let mut shell = Command::new("sh");
+	shell.arg("-c").arg(command);
+	shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+	let mut shell = Command::new("cmd");
+	shell.arg("/C").arg(command);
+	shell
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn replaces_file_without_leaving_temporary_file() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("finalized.json");
+
+		write_atomically(&path, b"first").unwrap();
+		write_atomically(&path, b"second").unwrap();
+
+		assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+		assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+	}
+}
@@ -0,0 +1,249 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Consensus health gauges, meant to be used in alerting rules.
+//!
+//! They are all computed by the task of this module. The lags come from the network status and
+//! the chain, and the blocks imported as `BlockOrigin::Own` are counted as authored by the node.
+//! The slots it claimed and the GRANDPA rounds are reported by the consensus engines through the
+//! `HealthSink` of the service.
+
+use std::{sync::Arc, time::Instant};
+
+use futures::{prelude::*, channel::mpsc, future::ready, stream};
+use prometheus_endpoint::{register, Gauge, GaugeVec, Opts, PrometheusError, Registry, F64, U64};
+use sc_client_api::{
+	health::HealthEvent, BlockchainEvents, BlockImportNotification, FinalityNotification,
+};
+use sc_network::network_state::NetworkState;
+use sp_blockchain::HeaderBackend;
+use sp_consensus::BlockOrigin;
+use sp_runtime::traits::{Block as BlockT, SaturatedConversion};
+
+use crate::NetworkStatus;
+
+/// Prometheus gauges describing the health of the node's consensus.
+#[derive(Clone)]
+pub(crate) struct HealthMetrics {
+	best_block_lag_from_peers: Gauge<U64>,
+	finality_lag_blocks: Gauge<U64>,
+	authored_blocks: GaugeVec<U64>,
+	grandpa_round_duration: Gauge<F64>,
+}
+
+impl HealthMetrics {
+	pub(crate) fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			best_block_lag_from_peers: register(Gauge::new(
+				"best_block_lag_from_peers",
+				"Number of blocks between our best block and the best block announced by our peers",
+			)?, registry)?,
+			finality_lag_blocks: register(Gauge::new(
+				"finality_lag_blocks",
+				"Number of blocks between our best block and our last finalized block",
+			)?, registry)?,
+			authored_blocks: register(GaugeVec::new(
+				Opts::new(
+					"authored_blocks_expected_vs_actual",
+					"Slots claimed by this node (expected) and blocks it authored (actual)",
+				),
+				&["kind"],
+			)?, registry)?,
+			grandpa_round_duration: register(Gauge::new(
+				"grandpa_round_duration_seconds",
+				"Time it took to complete the last GRANDPA round",
+			)?, registry)?,
+		})
+	}
+}
+
+/// Keeps the health gauges of the node up to date.
+struct HealthAggregator<Block: BlockT, Client> {
+	client: Arc<Client>,
+	metrics: HealthMetrics,
+	claimed: u64,
+	authored: u64,
+	/// The last round we started voting in, and when.
+	round_started: Option<(u64, Instant)>,
+	_marker: std::marker::PhantomData<Block>,
+}
+
+impl<Block, Client> HealthAggregator<Block, Client> where
+	Block: BlockT,
+	Client: HeaderBackend<Block>,
+{
+	fn new(client: Arc<Client>, metrics: HealthMetrics) -> Self {
+		HealthAggregator {
+			client,
+			metrics,
+			claimed: 0,
+			authored: 0,
+			round_started: None,
+			_marker: Default::default(),
+		}
+	}
+
+	fn on_network_status(&mut self, status: &NetworkStatus<Block>) {
+		let best_number = self.client.info().best_number;
+		let lag = status.best_seen_block
+			.map(|best_seen| best_seen.saturated_into::<u64>()
+				.saturating_sub(best_number.saturated_into::<u64>())
+			)
+			.unwrap_or(0);
+		self.metrics.best_block_lag_from_peers.set(lag);
+		self.update_finality_lag();
+	}
+
+	fn on_block_imported(&mut self, notification: &BlockImportNotification<Block>) {
+		if notification.origin == BlockOrigin::Own {
+			self.authored += 1;
+			self.metrics.authored_blocks.with_label_values(&["actual"]).set(self.authored);
+		}
+		self.update_finality_lag();
+	}
+
+	fn on_block_finalized(&mut self, _notification: &FinalityNotification<Block>) {
+		self.update_finality_lag();
+	}
+
+	fn on_consensus_event(&mut self, event: HealthEvent) {
+		match event {
+			HealthEvent::SlotClaimed => {
+				self.claimed += 1;
+				self.metrics.authored_blocks.with_label_values(&["expected"]).set(self.claimed);
+			},
+			HealthEvent::RoundStarted(round) => self.round_started = Some((round, Instant::now())),
+			HealthEvent::RoundCompleted(round) => match self.round_started {
+				Some((started, at)) if started == round =>
+					self.metrics.grandpa_round_duration.set(at.elapsed().as_secs_f64()),
+				_ => {},
+			},
+		}
+	}
+
+	fn update_finality_lag(&self) {
+		let info = self.client.info();
+		let lag = info.best_number.saturated_into::<u64>()
+			.saturating_sub(info.finalized_number.saturated_into::<u64>());
+		self.metrics.finality_lag_blocks.set(lag);
+	}
+}
+
+enum Event<Block: BlockT> {
+	NetworkStatus(NetworkStatus<Block>),
+	Imported(BlockImportNotification<Block>),
+	Finalized(FinalityNotification<Block>),
+	Consensus(HealthEvent),
+}
+
+/// Builds the task that keeps the health gauges up to date.
+pub(crate) fn build_health_future<Block, Client>(
+	client: Arc<Client>,
+	network_status: mpsc::UnboundedReceiver<(NetworkStatus<Block>, NetworkState)>,
+	consensus_events: mpsc::UnboundedReceiver<HealthEvent>,
+	metrics: HealthMetrics,
+) -> impl Future<Output = ()> where
+	Block: BlockT,
+	Client: HeaderBackend<Block> + BlockchainEvents<Block>,
+{
+	let imported = client.import_notification_stream().map(Event::Imported);
+	let finalized = client.finality_notification_stream().map(Event::Finalized);
+	let network_status = network_status.map(|(status, _)| Event::NetworkStatus(status));
+	let consensus_events = consensus_events.map(Event::Consensus);
+
+	let mut aggregator = HealthAggregator::new(client, metrics);
+	let chain_events = stream::select(imported, finalized);
+	stream::select(chain_events, stream::select(network_status, consensus_events))
+		.for_each(move |event| {
+			match event {
+				Event::NetworkStatus(status) => aggregator.on_network_status(&status),
+				Event::Imported(notification) => aggregator.on_block_imported(&notification),
+				Event::Finalized(notification) => aggregator.on_block_finalized(&notification),
+				Event::Consensus(event) => aggregator.on_consensus_event(event),
+			}
+			ready(())
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::generic::BlockId;
+	use substrate_test_runtime_client::{
+		prelude::*,
+		runtime::Block,
+		client_ext::ClientExt,
+	};
+
+	fn imported(block: &Block) -> BlockImportNotification<Block> {
+		BlockImportNotification {
+			hash: block.hash(),
+			origin: BlockOrigin::Own,
+			header: block.header().clone(),
+			is_new_best: true,
+			retracted: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn finality_lag_follows_the_finalized_block() {
+		let mut client = substrate_test_runtime_client::new();
+		let metrics = HealthMetrics::register(&Registry::new()).unwrap();
+
+		let a1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, a1.clone()).unwrap();
+		let a2 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, a2.clone()).unwrap();
+
+		let client = Arc::new(client);
+		let mut aggregator = HealthAggregator::new(client.clone(), metrics.clone());
+		for block in &[&a1, &a2] {
+			aggregator.on_block_imported(&imported(block));
+		}
+		assert_eq!(metrics.finality_lag_blocks.get(), 2);
+
+		ClientExt::finalize_block(&*client, BlockId::Hash(a2.hash()), None).unwrap();
+		aggregator.on_block_finalized(&FinalityNotification {
+			hash: a2.hash(),
+			header: a2.header().clone(),
+		});
+
+		assert_eq!(metrics.finality_lag_blocks.get(), 0);
+	}
+
+	#[test]
+	fn authored_blocks_are_compared_with_claimed_slots() {
+		let mut client = substrate_test_runtime_client::new();
+		let metrics = HealthMetrics::register(&Registry::new()).unwrap();
+
+		let a1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, a1.clone()).unwrap();
+		let a2 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::NetworkBroadcast, a2.clone()).unwrap();
+
+		let mut aggregator = HealthAggregator::new(Arc::new(client), metrics.clone());
+		aggregator.on_consensus_event(HealthEvent::SlotClaimed);
+		aggregator.on_block_imported(&imported(&a1));
+		aggregator.on_consensus_event(HealthEvent::SlotClaimed);
+		aggregator.on_block_imported(&BlockImportNotification {
+			origin: BlockOrigin::NetworkBroadcast,
+			..imported(&a2)
+		});
+
+		assert_eq!(metrics.authored_blocks.with_label_values(&["expected"]).get(), 2);
+		assert_eq!(metrics.authored_blocks.with_label_values(&["actual"]).get(), 1);
+	}
+}
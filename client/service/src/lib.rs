@@ -24,7 +24,13 @@ pub mod config;
 pub mod chain_ops;
 pub mod error;
 
+mod admin;
+mod block_weights;
 mod builder;
+#[cfg(not(target_os = "unknown"))]
+mod disk_watchdog;
+mod finalized_export;
+mod health;
 mod status_sinks;
 mod task_manager;
 
@@ -32,12 +38,14 @@ use std::{borrow::Cow, io, pin::Pin};
 use std::marker::PhantomData;
 use std::net::SocketAddr;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use wasm_timer::Instant;
 use std::task::{Poll, Context};
 use parking_lot::Mutex;
 
 use sc_client::Client;
+use sc_client_api::health::HealthSink;
 use futures::{
 	Future, FutureExt, Stream, StreamExt,
 	channel::mpsc,
@@ -45,20 +53,24 @@ use futures::{
 	sink::SinkExt,
 	task::{Spawn, FutureObj, SpawnError},
 };
-use sc_network::{NetworkService, network_state::NetworkState, PeerId, ReportHandle};
-use log::{log, warn, debug, error, Level};
+use sc_network::{
+	NetworkService, network_state::{NetworkState, PeerEndpoint}, PeerId, ReportHandle,
+};
+use log::{log, info, warn, debug, error, Level};
 use codec::{Encode, Decode};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{NumberFor, Block as BlockT};
 use parity_util_mem::MallocSizeOf;
 
 pub use self::error::Error;
+pub use self::admin::{ADMIN_ENGINE_ID, ADMIN_PROTOCOL_NAME};
 pub use self::builder::{
 	new_full_client,
 	ServiceBuilder, ServiceBuilderCommand, TFullClient, TLightClient, TFullBackend, TLightBackend,
-	TFullCallExecutor, TLightCallExecutor,
+	TFullCallExecutor, TLightCallExecutor, RpcExtensionBuilder, NoopRpcExtensionBuilder,
+	TELEMETRY_ATTESTATION_PREFIX, telemetry_attestation_message,
 };
-pub use config::{Configuration, Roles, PruningMode};
+pub use config::{Configuration, ReloadableConfig, Roles, PruningMode, RpcMethods};
 pub use sc_chain_spec::{
 	ChainSpec, GenericChainSpec, Properties, RuntimeGenesis, Extension as ChainSpecExtension
 };
@@ -76,6 +88,9 @@ use task_manager::TaskManager;
 
 const DEFAULT_PROTOCOL_ID: &str = "sup";
 
+/// Maximum time to wait for the connections to our peers to close when shutting down.
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// A type that implements `MallocSizeOf` on native but not wasm.
 #[cfg(not(target_os = "unknown"))]
 pub trait MallocSizeOfWasm: MallocSizeOf {}
@@ -87,7 +102,7 @@ impl<T: MallocSizeOf> MallocSizeOfWasm for T {}
 impl<T> MallocSizeOfWasm for T {}
 
 /// Substrate service.
-pub struct Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> {
+pub struct Service<TBl: BlockT, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> {
 	client: Arc<TCl>,
 	task_manager: TaskManager,
 	select_chain: Option<TSc>,
@@ -101,17 +116,32 @@ pub struct Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> {
 	essential_failed_tx: mpsc::UnboundedSender<()>,
 	/// A receiver for spawned essential-tasks concluding.
 	essential_failed_rx: mpsc::UnboundedReceiver<()>,
+	/// Send a new configuration to apply. The next time the service future is polled it applies
+	/// it.
+	reload_tx: mpsc::UnboundedSender<ReloadableConfig>,
+	/// A receiver for the configurations to apply.
+	reload_rx: mpsc::UnboundedReceiver<ReloadableConfig>,
+	/// Sender of the requests to the network worker, used to update the reserved peers.
+	system_rpc_tx: mpsc::UnboundedSender<sc_rpc::system::Request<TBl>>,
+	/// Reserved nodes given in the configuration.
+	reserved_nodes: Vec<String>,
 	rpc_handlers: sc_rpc_server::RpcHandler<sc_rpc::Metadata>,
+	rpc_server_handlers: RpcServerHandlers,
+	rpc_config: RpcServersConfig,
 	_rpc: Box<dyn std::any::Any + Send + Sync>,
 	_telemetry: Option<sc_telemetry::Telemetry>,
+	/// Telemetry endpoints given in the configuration.
+	telemetry_endpoints: Option<sc_telemetry::TelemetryEndpoints>,
 	_telemetry_on_connect_sinks: Arc<Mutex<Vec<futures::channel::mpsc::UnboundedSender<()>>>>,
 	_offchain_workers: Option<Arc<TOc>>,
 	keystore: sc_keystore::KeyStorePtr,
 	marker: PhantomData<TBl>,
 	prometheus_registry: Option<prometheus_endpoint::Registry>,
+	/// Sender of the consensus events to the health gauges, if Prometheus is enabled.
+	health_sink: Option<HealthSink>,
 }
 
-impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Unpin for Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> {}
+impl<TBl: BlockT, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Unpin for Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> {}
 
 /// Abstraction over a Substrate service.
 pub trait AbstractService: 'static + Future<Output = Result<(), Error>> +
@@ -182,6 +212,23 @@ pub trait AbstractService: 'static + Future<Output = Result<(), Error>> +
 
 	/// Get the prometheus metrics registry, if available.
 	fn prometheus_registry(&self) -> Option<prometheus_endpoint::Registry>;
+
+	/// Returns the sink the consensus engines report their events to, from which the health
+	/// gauges are computed. `None` if Prometheus isn't enabled.
+	fn health_sink(&self) -> Option<HealthSink>;
+
+	/// Returns a handle for changing the telemetry endpoints, the reserved peers and the CORS
+	/// origins of the RPC servers while the service is running.
+	fn reload_handle(&self) -> ReloadHandle;
+
+	/// Shut the service down gracefully.
+	///
+	/// The RPC servers stop accepting connections first. We then disconnect from our peers, waiting
+	/// at most a few seconds for the connections to close, before the background tasks are
+	/// signaled to stop. The transactions still pending in the pool are left untouched, so that
+	/// their watchers aren't told they're invalid. The database is flushed last, and the client is
+	/// released so that the database is closed once the tasks still holding it are gone.
+	fn shutdown(self) where Self: Sized;
 }
 
 impl<TBl, TBackend, TExec, TRtApi, TSc, TExPool, TOc> AbstractService for
@@ -276,9 +323,71 @@ where
 	fn prometheus_registry(&self) -> Option<prometheus_endpoint::Registry> {
 		self.prometheus_registry.clone()
 	}
+
+	fn health_sink(&self) -> Option<HealthSink> {
+		self.health_sink.clone()
+	}
+
+	fn reload_handle(&self) -> ReloadHandle {
+		ReloadHandle(self.reload_tx.clone())
+	}
+
+	fn shutdown(self) {
+		let Service {
+			client,
+			task_manager,
+			network,
+			transaction_pool,
+			system_rpc_tx,
+			rpc_handlers,
+			_rpc,
+			_offchain_workers,
+			..
+		} = self;
+
+		debug!(target: "service", "Stopping RPC servers");
+		drop(_rpc);
+		drop(rpc_handlers);
+
+		debug!(target: "service", "Disconnecting from peers");
+		let (peers_tx, peers_rx) = futures::channel::oneshot::channel();
+		if system_rpc_tx.unbounded_send(sc_rpc::system::Request::Peers(peers_tx)).is_ok() {
+			let peers = futures::executor::block_on(peers_rx).unwrap_or_default();
+			for peer in peers {
+				if let Ok(peer_id) = peer.peer_id.parse::<PeerId>() {
+					network.disconnect_peer(peer_id);
+				}
+			}
+		}
+		let deadline = Instant::now() + DISCONNECT_TIMEOUT;
+		while network.num_connected() > 0 && Instant::now() < deadline {
+			std::thread::sleep(Duration::from_millis(50));
+		}
+
+		// Neither the RPC servers nor our peers can submit transactions anymore.
+		let status = transaction_pool.status();
+		debug!(
+			target: "service",
+			"Leaving {} ready and {} future transactions in the pool",
+			status.ready,
+			status.future,
+		);
+
+		debug!(target: "service", "Stopping background tasks and network");
+		drop(task_manager);
+		drop(network);
+
+		debug!(target: "service", "Flushing the database, releasing transaction pool and client");
+		drop(_offchain_workers);
+		drop(transaction_pool);
+		if let Err(err) = client.flush() {
+			warn!(target: "service", "Failed to flush the database: {:?}", err);
+		}
+		drop(client);
+	}
 }
 
-impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Future for
+impl<TBl: BlockT, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Future for
 	Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc>
 {
 	type Output = Result<(), Error>;
@@ -295,6 +404,13 @@ impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Future for
 			}
 		}
 
+		while let Poll::Ready(Some(config)) = Pin::new(&mut this.reload_rx).poll_next(cx) {
+			match this.reload(config) {
+				Ok(()) => info!("Configuration reloaded"),
+				Err(err) => error!("Failed to reload the configuration: {}", err),
+			}
+		}
+
 		this.task_manager.process_receiver(cx);
 
 		// The service future never ends.
@@ -302,7 +418,67 @@ impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Future for
 	}
 }
 
-impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Spawn for
+impl<TBl: BlockT, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> {
+	/// Apply a new configuration.
+	///
+	/// Every reserved node address is checked before anything is changed. The RPC servers are
+	/// only restarted if the CORS origins changed, and are left stopped if they fail to restart.
+	fn reload(&mut self, config: ReloadableConfig) -> Result<(), Error> {
+		let parse_addr = |addr: &String| sc_network::config::parse_str_addr(addr)
+			.map_err(|e| Error::Other(format!("Invalid reserved node {}: {:?}", addr, e)));
+
+		let mut added = Vec::new();
+		for addr in config.reserved_nodes.iter().filter(|addr| !self.reserved_nodes.contains(addr)) {
+			parse_addr(addr)?;
+			added.push(addr.clone());
+		}
+		let mut removed = Vec::new();
+		for addr in self.reserved_nodes.iter().filter(|addr| !config.reserved_nodes.contains(addr)) {
+			let (peer_id, _) = parse_addr(addr)?;
+			removed.push(peer_id.to_base58());
+		}
+
+		// The network worker reports failures to the RPC callers only, and we already checked
+		// the addresses.
+		for peer_id in removed {
+			let (tx, _) = futures::channel::oneshot::channel();
+			let _ = self.system_rpc_tx.unbounded_send(
+				sc_rpc::system::Request::NetworkRemoveReservedPeer(peer_id, tx)
+			);
+		}
+		for addr in added {
+			let (tx, _) = futures::channel::oneshot::channel();
+			let _ = self.system_rpc_tx.unbounded_send(
+				sc_rpc::system::Request::NetworkAddReservedPeer(addr, tx)
+			);
+		}
+		self.reserved_nodes = config.reserved_nodes;
+
+		if config.telemetry_endpoints != self.telemetry_endpoints {
+			match (&self._telemetry, config.telemetry_endpoints.clone()) {
+				(Some(telemetry), endpoints) => telemetry.set_endpoints(
+					endpoints.unwrap_or_else(|| sc_telemetry::TelemetryEndpoints::new(Vec::new()))
+				),
+				(None, Some(_)) => warn!(
+					"Telemetry was disabled when the node started, restart the node to enable it"
+				),
+				(None, None) => {},
+			}
+			self.telemetry_endpoints = config.telemetry_endpoints;
+		}
+
+		if config.rpc_cors != self.rpc_config.cors {
+			self.rpc_config.cors = config.rpc_cors;
+			// The previous servers must release their addresses before we bind them again.
+			self._rpc = Box::new(());
+			self._rpc = start_rpc_servers(&self.rpc_config, &self.rpc_server_handlers)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl<TBl: BlockT, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Spawn for
 	Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc>
 {
 	fn spawn_obj(
@@ -319,7 +495,7 @@ impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Spawn for
 /// The `status_sink` contain a list of senders to send a periodic network status to.
 fn build_network_future<
 	B: BlockT,
-	C: sc_client::BlockchainEvents<B>,
+	C: sc_client::BlockchainEvents<B> + sp_blockchain::HeaderBackend<B>,
 	H: sc_network::ExHashT
 > (
 	roles: Roles,
@@ -360,8 +536,15 @@ fn build_network_future<
 					});
 				},
 				sc_rpc::system::Request::Peers(sender) => {
+					use sc_rpc::system::PeerDirection;
+
+					let direction = |endpoint| match endpoint {
+						PeerEndpoint::Dialing(_) => PeerDirection::Outbound,
+						PeerEndpoint::Listening { .. } => PeerDirection::Inbound,
+					};
 					let _ = sender.send(network.peers_debug_info().into_iter().map(|(peer_id, p)|
 						sc_rpc::system::PeerInfo {
+							direction: network.peer_endpoint(&peer_id).map(direction),
 							peer_id: peer_id.to_base58(),
 							roles: format!("{:?}", p.roles),
 							protocol_version: p.protocol_version,
@@ -391,6 +574,18 @@ fn build_network_future<
 						))),
 					};
 				}
+				sc_rpc::system::Request::NetworkUpdatePeerFilter(update, sender) => {
+					use sc_rpc::system::PeerFilterUpdate;
+					let result = match update {
+						PeerFilterUpdate::AddDenied(peer) => network.add_denied_peer(peer),
+						PeerFilterUpdate::RemoveDenied(peer) => network.remove_denied_peer(peer),
+						PeerFilterUpdate::AddAllowed(peer) => network.add_allowed_peer(peer),
+						PeerFilterUpdate::RemoveAllowed(peer) => network.remove_allowed_peer(peer),
+					};
+					let _ = sender.send(
+						result.map_err(sc_rpc::system::error::Error::MalformattedPeerArg)
+					);
+				}
 				sc_rpc::system::Request::NodeRoles(sender) => {
 					use sc_rpc::system::NodeRole;
 
@@ -406,6 +601,25 @@ fn build_network_future<
 
 					let _ = sender.send(node_roles);
 				}
+				sc_rpc::system::Request::AttestIdentity(challenge, sender) => {
+					let message = sc_rpc::system::identity_attestation_message(&challenge);
+					let attestation = network.service().sign_with_local_identity(message)
+						.map(|signature| sc_rpc::system::IdentityAttestation {
+							peer_id: signature.public_key.clone().into_peer_id().to_base58(),
+							public_key: signature.public_key.into_protobuf_encoding().into(),
+							signature: signature.bytes.into(),
+						})
+						.map_err(|e| sc_rpc::system::error::Error::SigningFailed(e.to_string()));
+					let _ = sender.send(attestation);
+				}
+				sc_rpc::system::Request::SyncState(sender) => {
+					let _ = sender.send(sc_rpc::system::SyncState {
+						starting_block: network.sync_starting_block(),
+						current_block: client.info().best_number,
+						highest_block: network.best_seen_block(),
+						eta_secs: network.sync_eta().map(|eta| eta.as_secs()),
+					});
+				}
 			};
 		}
 
@@ -487,11 +701,76 @@ mod waiting {
 	}
 }
 
+/// Handle used to change part of the configuration of a running service.
+#[derive(Clone)]
+pub struct ReloadHandle(mpsc::UnboundedSender<ReloadableConfig>);
+
+impl ReloadHandle {
+	/// Ask the service to apply the given configuration.
+	///
+	/// The configuration is applied the next time the service is polled. Failures are logged.
+	pub fn reload(&self, config: ReloadableConfig) {
+		let _ = self.0.unbounded_send(config);
+	}
+}
+
+/// Settings of the RPC servers, kept around so that they can be restarted.
+#[derive(Clone)]
+struct RpcServersConfig {
+	http: Option<SocketAddr>,
+	ws: Option<SocketAddr>,
+	ws_max_connections: Option<usize>,
+	cors: Option<Vec<String>>,
+	methods: config::RpcMethods,
+	limits: config::RpcLimits,
+	rate_limit: Option<config::RpcRateLimit>,
+	tls: Option<config::RpcTlsConfig>,
+	ws_compression: bool,
+	worker_bridge: Option<config::WorkerBridgeConfig>,
+	metrics: Option<sc_rpc_server::RpcMetrics>,
+}
+
+impl RpcServersConfig {
+	fn new(config: &Configuration, metrics: Option<sc_rpc_server::RpcMetrics>) -> Self {
+		RpcServersConfig {
+			http: config.rpc_http,
+			ws: config.rpc_ws,
+			ws_max_connections: config.rpc_ws_max_connections,
+			cors: config.rpc_cors.clone(),
+			methods: config.rpc_methods,
+			limits: config.rpc_limits,
+			rate_limit: config.rpc_rate_limit,
+			tls: config.rpc_tls.clone(),
+			ws_compression: config.rpc_ws_compression,
+			worker_bridge: config.worker_bridge.clone(),
+			metrics,
+		}
+	}
+}
+
+/// RPC handlers served by the RPC servers.
+#[derive(Clone)]
+struct RpcServerHandlers {
+	/// Handler denying the unsafe methods.
+	safe: sc_rpc_server::RpcHandler<sc_rpc::Metadata>,
+	/// Handler serving every method.
+	all: sc_rpc_server::RpcHandler<sc_rpc::Metadata>,
+}
+
+impl RpcServerHandlers {
+	fn get(&self, deny_unsafe: sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata> {
+		match deny_unsafe {
+			sc_rpc::DenyUnsafe::Yes => self.safe.clone(),
+			sc_rpc::DenyUnsafe::No => self.all.clone(),
+		}
+	}
+}
+
 /// Starts RPC servers that run in their own thread, and returns an opaque object that keeps them alive.
 #[cfg(not(target_os = "unknown"))]
-fn start_rpc_servers<H: FnMut() -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>>(
-	config: &Configuration,
-	mut gen_handler: H
+fn start_rpc_servers(
+	config: &RpcServersConfig,
+	handlers: &RpcServerHandlers,
 ) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error> {
 	fn maybe_start_server<T, F>(address: Option<SocketAddr>, mut start: F) -> Result<Option<T>, io::Error>
 		where F: FnMut(&SocketAddr) -> Result<T, io::Error>,
@@ -511,28 +790,74 @@ fn start_rpc_servers<H: FnMut() -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>>(
 		})
 	}
 
+	#[cfg(unix)]
+	fn start_worker_bridge(
+		config: Option<&config::WorkerBridgeConfig>,
+		handlers: &RpcServerHandlers,
+	) -> Result<Option<sc_rpc_server::WorkerBridge>, io::Error> {
+		// The workers are local processes of the user running the node.
+		config.map(|config| sc_rpc_server::WorkerBridge::start(
+			&config.path,
+			config.token.clone(),
+			handlers.get(sc_rpc::DenyUnsafe::No),
+		)).transpose()
+	}
+
+	#[cfg(not(unix))]
+	fn start_worker_bridge(
+		config: Option<&config::WorkerBridgeConfig>,
+		_: &RpcServerHandlers,
+	) -> Result<Option<()>, io::Error> {
+		if config.is_some() {
+			warn!("The worker bridge is only available on unix platforms");
+		}
+		Ok(None)
+	}
+
+	fn deny_unsafe(addr: &SocketAddr, methods: &config::RpcMethods) -> sc_rpc::DenyUnsafe {
+		let is_exposed_addr = !addr.ip().is_loopback();
+		match (is_exposed_addr, methods) {
+			(_, config::RpcMethods::Unsafe) | (false, config::RpcMethods::Auto) => sc_rpc::DenyUnsafe::No,
+			_ => sc_rpc::DenyUnsafe::Yes,
+		}
+	}
+
 	Ok(Box::new((
 		maybe_start_server(
-			config.rpc_http,
-			|address| sc_rpc_server::start_http(address, config.rpc_cors.as_ref(), gen_handler()),
+			config.http,
+			|address| sc_rpc_server::start_http(
+				address,
+				config.cors.as_ref(),
+				config.limits,
+				config.rate_limit,
+				config.tls.as_ref(),
+				config.metrics.clone(),
+				handlers.get(deny_unsafe(address, &config.methods)),
+			),
 		)?.map(|s| waiting::HttpServer(Some(s))),
 		maybe_start_server(
-			config.rpc_ws,
+			config.ws,
 			|address| sc_rpc_server::start_ws(
 				address,
-				config.rpc_ws_max_connections,
-				config.rpc_cors.as_ref(),
-				gen_handler(),
+				config.ws_max_connections,
+				config.cors.as_ref(),
+				config.limits,
+				config.rate_limit,
+				config.tls.as_ref(),
+				config.ws_compression,
+				config.metrics.clone(),
+				handlers.get(deny_unsafe(address, &config.methods)),
 			),
 		)?.map(|s| waiting::WsServer(Some(s))).map(Mutex::new),
+		start_worker_bridge(config.worker_bridge.as_ref(), handlers)?,
 	)))
 }
 
 /// Starts RPC servers that run in their own thread, and returns an opaque object that keeps them alive.
 #[cfg(target_os = "unknown")]
-fn start_rpc_servers<H: FnMut() -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>>(
-	_: &Configuration,
-	_: H
+fn start_rpc_servers(
+	_: &RpcServersConfig,
+	_: &RpcServerHandlers,
 ) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error> {
 	Ok(Box::new(()))
 }
@@ -561,6 +886,8 @@ impl RpcSession {
 /// Transaction pool adapter.
 pub struct TransactionPoolAdapter<C, P> {
 	imports_external_transactions: bool,
+	/// Set while the disk of the database is almost full.
+	disk_space_low: Arc<AtomicBool>,
 	pool: Arc<P>,
 	client: Arc<C>,
 	executor: SpawnTaskHandle,
@@ -616,6 +943,10 @@ where
 			debug!("Transaction rejected");
 			return;
 		}
+		if self.disk_space_low.load(Ordering::Relaxed) {
+			debug!("Transaction rejected, low on disk space");
+			return;
+		}
 
 		let encoded = transaction.encode();
 		match Decode::decode(&mut &encoded[..]) {
@@ -38,7 +38,10 @@ use sc_service::{
 	Error,
 };
 use sc_network::{multiaddr, Multiaddr, NetworkStateInfo};
-use sc_network::config::{NetworkConfiguration, TransportConfig, NodeKeyConfig, Secret, NonReservedPeerMode};
+use sc_network::config::{
+	NetworkConfiguration, TransportConfig, NodeKeyConfig, Secret, NonReservedPeerMode, NatTraversal,
+	DiscoveryConfig,
+};
 use sp_runtime::{generic::BlockId, traits::Block as BlockT};
 use sp_transaction_pool::TransactionPool;
 
@@ -155,22 +158,38 @@ fn node_config<G: RuntimeGenesis + 'static, E: ChainSpecExtension + Clone + 'sta
 				.collect()
 		],
 		public_addresses: vec![],
+		observed_address_confirmations: NetworkConfiguration::default().observed_address_confirmations,
 		boot_nodes: vec![],
 		node_key: NodeKeyConfig::Ed25519(Secret::New),
+		nat: NatTraversal::None,
+		discovery: DiscoveryConfig::default(),
 		in_peers: 50,
 		out_peers: 450,
+		max_light_peers: None,
 		reserved_nodes: vec![],
 		non_reserved_mode: NonReservedPeerMode::Accept,
+		allowed_peers: vec![],
+		denied_peers: vec![],
 		sentry_nodes: vec![],
+		sentry_validators: vec![],
 		client_version: "network/test/0.1".to_owned(),
 		node_name: "unknown".to_owned(),
 		transport: TransportConfig::Normal {
 			enable_mdns: false,
 			allow_private_ipv4: true,
+			prefer_ipv6: false,
 			wasm_external_transport: None,
 			use_yamux_flow_control: true,
+			proxy: None,
+			websocket_tls: None,
 		},
 		max_parallel_downloads: NetworkConfiguration::default().max_parallel_downloads,
+		max_extrinsic_size: None,
+		max_upload_rate: None,
+		max_download_rate: None,
+		pre_shared_key: None,
+		request_response_protocols: Vec::new(),
+		notifications_protocols: Vec::new(),
 	};
 
 	Configuration {
@@ -180,6 +199,7 @@ fn node_config<G: RuntimeGenesis + 'static, E: ChainSpecExtension + Clone + 'sta
 		roles: role,
 		task_executor: Some(task_executor),
 		transaction_pool: Default::default(),
+		max_extrinsic_size: None,
 		network: network_config,
 		keystore: KeystoreConfig::Path {
 			path: root.join("key"),
@@ -201,6 +221,14 @@ fn node_config<G: RuntimeGenesis + 'static, E: ChainSpecExtension + Clone + 'sta
 		rpc_ws: None,
 		rpc_ws_max_connections: None,
 		rpc_cors: None,
+		rpc_methods: Default::default(),
+		rpc_limits: Default::default(),
+		rpc_rate_limit: None,
+		rpc_tls: None,
+		rpc_ws_compression: true,
+		rpc_storage_subscription_throttle: None,
+		worker_bridge: None,
+		admin_peers: Vec::new(),
 		prometheus_config: None,
 		telemetry_endpoints: None,
 		telemetry_external_transport: None,
@@ -208,11 +236,19 @@ fn node_config<G: RuntimeGenesis + 'static, E: ChainSpecExtension + Clone + 'sta
 		offchain_worker: false,
 		sentry_mode: false,
 		force_authoring: false,
+		secondary_slots: None,
+		max_unfinalized_blocks: None,
+		proposal_slot_proportion: None,
 		disable_grandpa: false,
 		dev_key_seed: key_seed,
 		tracing_targets: None,
 		tracing_receiver: Default::default(),
+		consensus_recorder: None,
+		export_finalized: None,
+		epoch_snapshot: None,
+		disk_watchdog: None,
 		max_runtime_instances: 8,
+		shutdown_timeout: Duration::from_secs(60),
 	}
 }
 
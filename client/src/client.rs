@@ -17,8 +17,8 @@
 //! Substrate Client
 
 use std::{
-	marker::PhantomData, collections::{HashSet, BTreeMap, HashMap}, sync::Arc, panic::UnwindSafe,
-	result,
+	marker::PhantomData, collections::{HashSet, BTreeMap, HashMap, VecDeque}, sync::Arc,
+	panic::UnwindSafe, result, time::Duration,
 };
 use log::{info, trace, warn};
 use futures::channel::mpsc;
@@ -99,9 +99,14 @@ pub struct Client<B, E, Block, RA> where Block: BlockT {
 	importing_block: RwLock<Option<Block::Hash>>,
 	block_rules: BlockRules<Block>,
 	execution_extensions: ExecutionExtensions<Block>,
+	// execution time of the most recently imported blocks, oldest first.
+	import_times: Mutex<VecDeque<(Block::Hash, Duration)>>,
 	_phantom: PhantomData<RA>,
 }
 
+/// Number of imported blocks whose execution time is kept.
+const IMPORT_TIMES_KEPT: usize = 1024;
+
 // used in importing a block, where additional changes are made after the runtime
 // executed.
 enum PrePostHeader<H> {
@@ -282,6 +287,7 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			importing_block: Default::default(),
 			block_rules: BlockRules::new(fork_blocks, bad_blocks),
 			execution_extensions,
+			import_times: Default::default(),
 			_phantom: Default::default(),
 		})
 	}
@@ -793,10 +799,19 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			(true, ref mut storage_changes @ None, Some(ref body)) => {
 				let runtime_api = self.runtime_api();
 
+				let started = std::time::Instant::now();
 				runtime_api.execute_block(
 					&at,
 					Block::new(import_block.header.clone(), body.clone()),
 				)?;
+				let execution_time = started.elapsed();
+				{
+					let mut import_times = self.import_times.lock();
+					if import_times.len() == IMPORT_TIMES_KEPT {
+						import_times.pop_front();
+					}
+					import_times.push_back((import_block.header.hash(), execution_time));
+				}
 
 				let state = self.backend.state_at(at)?;
 				let changes_trie_state = changes_tries_state_at_block(
@@ -999,6 +1014,21 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		}
 	}
 
+	/// Query the state the network keeps across restarts.
+	pub fn network_state(&self, key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+		self.backend.get_network_state(key)
+	}
+
+	/// Store a value of the network state, replacing the previous one.
+	pub fn insert_network_state(&self, key: &[u8], value: &[u8]) -> sp_blockchain::Result<()> {
+		self.backend.insert_network_state(key, value)
+	}
+
+	/// Write the buffered changes of the database to disk.
+	pub fn flush(&self) -> sp_blockchain::Result<()> {
+		self.backend.flush()
+	}
+
 	/// Get blockchain info.
 	pub fn chain_info(&self) -> blockchain::Info<Block> {
 		self.backend.blockchain().info()
@@ -1785,18 +1815,39 @@ where
 	}
 }
 
+/// Policy used by `LongestChain` to pick the block to build new blocks upon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainSelectionPolicy<N> {
+	/// Build on top of the best block seen, regardless of how far it is ahead of finality.
+	BestSeen,
+	/// Build on top of the best chain containing the last finalized block, at most
+	/// `max_lookahead` blocks past it. The lookahead is unbounded if `None`.
+	FinalityRestricted {
+		/// Maximum number of non-finalized blocks to build upon.
+		max_lookahead: Option<N>,
+	},
+}
+
+impl<N> Default for ChainSelectionPolicy<N> {
+	fn default() -> Self {
+		ChainSelectionPolicy::BestSeen
+	}
+}
+
 /// Implement Longest Chain Select implementation
 /// where 'longest' is defined as the highest number of blocks
-pub struct LongestChain<B, Block> {
+pub struct LongestChain<B, Block: BlockT> {
 	backend: Arc<B>,
+	policy: ChainSelectionPolicy<NumberFor<Block>>,
 	_phantom: PhantomData<Block>
 }
 
-impl<B, Block> Clone for LongestChain<B, Block> {
+impl<B, Block: BlockT> Clone for LongestChain<B, Block> {
 	fn clone(&self) -> Self {
 		let backend = self.backend.clone();
 		LongestChain {
 			backend,
+			policy: self.policy,
 			_phantom: Default::default()
 		}
 	}
@@ -1809,19 +1860,39 @@ where
 {
 	/// Instantiate a new LongestChain for Backend B
 	pub fn new(backend: Arc<B>) -> Self {
+		Self::with_policy(backend, Default::default())
+	}
+
+	/// Instantiate a new LongestChain for Backend B using the given selection policy.
+	pub fn with_policy(backend: Arc<B>, policy: ChainSelectionPolicy<NumberFor<Block>>) -> Self {
 		LongestChain {
 			backend,
+			policy,
 			_phantom: Default::default()
 		}
 	}
 
+	/// The selection policy in use.
+	pub fn policy(&self) -> ChainSelectionPolicy<NumberFor<Block>> {
+		self.policy
+	}
+
 	fn best_block_header(&self) -> sp_blockchain::Result<<Block as BlockT>::Header> {
 		let info = self.backend.blockchain().info();
 		let import_lock = self.backend.get_import_lock();
-		let best_hash = self.backend
-			.blockchain()
-			.best_containing(info.best_hash, None, import_lock)?
-			.unwrap_or(info.best_hash);
+		let best_hash = match self.policy {
+			ChainSelectionPolicy::BestSeen => self.backend
+				.blockchain()
+				.best_containing(info.best_hash, None, import_lock)?
+				.unwrap_or(info.best_hash),
+			ChainSelectionPolicy::FinalityRestricted { max_lookahead } => {
+				let max_number = max_lookahead.map(|lookahead| info.finalized_number + lookahead);
+				self.backend
+					.blockchain()
+					.best_containing(info.finalized_hash, max_number, import_lock)?
+					.unwrap_or(info.finalized_hash)
+			},
+		};
 
 		Ok(self.backend.blockchain().header(BlockId::Hash(best_hash))?
 			.expect("given block hash was fetched from block in db; qed"))
@@ -1909,6 +1980,16 @@ impl<B, E, Block, RA> BlockBackend<Block> for Client<B, E, Block, RA>
 	fn justification(&self, id: &BlockId<Block>) -> sp_blockchain::Result<Option<Justification>> {
 		self.backend.blockchain().justification(*id)
 	}
+
+	fn leaves(&self) -> sp_blockchain::Result<Vec<Block::Hash>> {
+		self.backend.blockchain().leaves()
+	}
+
+	fn import_time(&self, hash: &Block::Hash) -> Option<Duration> {
+		self.import_times.lock().iter()
+			.find(|(imported, _)| imported == hash)
+			.map(|(_, time)| *time)
+	}
 }
 
 impl<B, E, Block, RA> backend::AuxStore for Client<B, E, Block, RA>
@@ -2940,6 +3021,102 @@ pub(crate) mod tests {
 		);
 	}
 
+	#[test]
+	fn finality_restricted_policy_builds_within_lookahead() {
+		let builder = TestClientBuilder::new();
+		let backend = builder.backend();
+		let mut client = builder.build();
+
+		let best_seen = LongestChain::new(backend.clone());
+		let restricted = LongestChain::with_policy(
+			backend.clone(),
+			ChainSelectionPolicy::FinalityRestricted { max_lookahead: Some(2) },
+		);
+
+		// G -> A1 -> A2 -> A3 -> A4
+		let mut blocks = Vec::new();
+		for _ in 0..4 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			client.import(BlockOrigin::Own, block.clone()).unwrap();
+			blocks.push(block);
+		}
+
+		assert_eq!(best_seen.best_chain().unwrap().hash(), blocks[3].hash());
+		assert_eq!(restricted.best_chain().unwrap().hash(), blocks[1].hash());
+
+		// finalizing A1 moves the lookahead window forward.
+		ClientExt::finalize_block(&client, BlockId::Hash(blocks[0].hash()), None).unwrap();
+		assert_eq!(restricted.best_chain().unwrap().hash(), blocks[2].hash());
+
+		// an unbounded lookahead builds on top of the best chain containing the finalized block.
+		let unbounded = LongestChain::with_policy(
+			backend,
+			ChainSelectionPolicy::FinalityRestricted { max_lookahead: None },
+		);
+		assert_eq!(unbounded.best_chain().unwrap().hash(), blocks[3].hash());
+	}
+
+	#[test]
+	fn finality_restricted_policy_ignores_forks_diverging_from_finalized_chain() {
+		let builder = TestClientBuilder::new();
+		let backend = builder.backend();
+		let mut client = builder.build();
+
+		let best_seen = LongestChain::new(backend.clone());
+		let restricted = LongestChain::with_policy(
+			backend,
+			ChainSelectionPolicy::FinalityRestricted { max_lookahead: Some(1) },
+		);
+
+		// G -> A1
+		//   \
+		//    -> B1 -> B2 -> B3
+		let a1 = client.new_block_at(
+			&BlockId::Number(0),
+			Default::default(),
+			false,
+		).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, a1.clone()).unwrap();
+
+		let mut b1 = client.new_block_at(
+			&BlockId::Number(0),
+			Default::default(),
+			false,
+		).unwrap();
+		// needed to make sure B1 gets a different hash from A1
+		b1.push_transfer(Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Ferdie.into(),
+			amount: 1,
+			nonce: 0,
+		}).unwrap();
+		let b1 = b1.build().unwrap().block;
+		client.import(BlockOrigin::Own, b1.clone()).unwrap();
+
+		let b2 = client.new_block_at(
+			&BlockId::Hash(b1.hash()),
+			Default::default(),
+			false,
+		).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, b2.clone()).unwrap();
+
+		let b3 = client.new_block_at(
+			&BlockId::Hash(b2.hash()),
+			Default::default(),
+			false,
+		).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, b3.clone()).unwrap();
+
+		// nothing is finalized yet, so the restricted policy stays close to genesis.
+		assert_eq!(best_seen.best_chain().unwrap().hash(), b3.hash());
+		assert_eq!(restricted.best_chain().unwrap().hash(), b1.hash());
+
+		// finalizing A1 leaves the B fork behind: both policies author on top of A1.
+		ClientExt::finalize_block(&client, BlockId::Hash(a1.hash()), None).unwrap();
+		assert_eq!(best_seen.best_chain().unwrap().hash(), a1.hash());
+		assert_eq!(restricted.best_chain().unwrap().hash(), a1.hash());
+	}
+
 	#[test]
 	fn get_header_by_block_number_doesnt_panic() {
 		let client = substrate_test_runtime_client::new();
@@ -36,7 +36,7 @@ mod test;
 
 use std::fmt;
 use parking_lot::RwLock;
-use codec::Codec;
+use codec::{Codec, Decode};
 use std::collections::{HashMap, hash_map::Entry};
 use noncanonical::NonCanonicalOverlay;
 use pruning::RefWindow;
@@ -185,6 +185,37 @@ impl Default for PruningMode {
 	}
 }
 
+/// Pruning mode and progress stored in a database.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StoredPruning {
+	/// The pruning mode the database was created with. The number of blocks kept by a
+	/// `Constrained` mode isn't stored, so its constraints are left unspecified.
+	pub mode: PruningMode,
+	/// Number of the last block whose state was pruned, if any.
+	pub last_pruned: Option<u64>,
+}
+
+/// Read the pruning mode and progress stored in `db`, or `None` if no block was inserted yet.
+pub fn read_pruning<D: MetaDb>(db: &D) -> Result<Option<StoredPruning>, Error<D::Error>> {
+	let mode = match db.get_meta(&to_meta_key(PRUNING_MODE, &())).map_err(Error::Db)? {
+		Some(mode) => mode,
+		None => return Ok(None),
+	};
+	let mode = match mode.as_slice() {
+		PRUNING_MODE_ARCHIVE => PruningMode::ArchiveAll,
+		PRUNING_MODE_ARCHIVE_CANON => PruningMode::ArchiveCanonical,
+		PRUNING_MODE_CONSTRAINED => PruningMode::Constrained(Default::default()),
+		_ => return Err(Error::InvalidPruningMode(String::from_utf8_lossy(&mode).into())),
+	};
+	let last_pruned = db.get_meta(&to_meta_key(pruning::LAST_PRUNED, &())).map_err(Error::Db)?;
+	let last_pruned = match last_pruned {
+		Some(buffer) => Some(u64::decode(&mut buffer.as_slice())?),
+		None => None,
+	};
+
+	Ok(Some(StoredPruning { mode, last_pruned }))
+}
+
 fn to_meta_key<S: Codec>(suffix: &[u8], data: &S) -> Vec<u8> {
 	let mut buffer = data.encode();
 	buffer.extend(suffix);
@@ -518,7 +549,7 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDb<BlockHash
 mod tests {
 	use std::io;
 	use sp_core::H256;
-	use crate::{StateDb, PruningMode, Constraints};
+	use crate::{StateDb, PruningMode, Constraints, StoredPruning, read_pruning};
 	use crate::test::{make_db, make_changeset, TestDb};
 
 	fn make_test_db(settings: PruningMode) -> (TestDb, StateDb<H256, H256>) {
@@ -635,6 +666,26 @@ mod tests {
 		assert!(db.data_eq(&make_db(&[1, 21, 3, 921, 922, 93, 94])));
 	}
 
+	#[test]
+	fn reads_the_stored_pruning() {
+		assert_eq!(read_pruning(&make_db(&[])).unwrap(), None);
+
+		let (db, _) = make_test_db(PruningMode::ArchiveAll);
+		assert_eq!(
+			read_pruning(&db).unwrap(),
+			Some(StoredPruning { mode: PruningMode::ArchiveAll, last_pruned: None }),
+		);
+
+		let (db, _) = make_test_db(PruningMode::keep_blocks(1));
+		assert_eq!(
+			read_pruning(&db).unwrap(),
+			Some(StoredPruning {
+				mode: PruningMode::Constrained(Default::default()),
+				last_pruned: Some(1),
+			}),
+		);
+	}
+
 	#[test]
 	fn detects_incompatible_mode() {
 		let mut db = make_db(&[]);
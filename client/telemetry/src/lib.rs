@@ -135,6 +135,8 @@ struct TelemetryInner {
 	worker: Option<worker::TelemetryWorker>,
 	/// Receives log entries for them to be dispatched to the worker.
 	receiver: mpsc::Receiver<async_record::AsyncRecord>,
+	/// Transport given at initialization, used again when the endpoints are replaced.
+	wasm_external_transport: Option<wasm_ext::ExtTransport>,
 }
 
 /// Implements `slog::Drain`.
@@ -148,15 +150,6 @@ struct TelemetryDrain {
 /// Please be careful to not call this function twice in the same program. The `slog` crate
 /// doesn't provide any way of knowing whether a global logger has already been registered.
 pub fn init_telemetry(config: TelemetryConfig) -> Telemetry {
-	// Build the list of telemetry endpoints.
-	let mut endpoints = Vec::new();
-	for &(ref url, verbosity) in &config.endpoints.0 {
-		match url_to_multiaddr(url) {
-			Ok(addr) => endpoints.push((addr, verbosity)),
-			Err(err) => warn!(target: "telemetry", "Invalid telemetry URL {}: {}", url, err),
-		}
-	}
-
 	let (sender, receiver) = mpsc::channel(16);
 	let guard = {
 		let logger = TelemetryDrain { sender: std::panic::AssertUnwindSafe(sender) };
@@ -164,23 +157,52 @@ pub fn init_telemetry(config: TelemetryConfig) -> Telemetry {
 		slog_scope::set_global_logger(root)
 	};
 
-	let worker = match worker::TelemetryWorker::new(endpoints, config.wasm_external_transport) {
-		Ok(w) => Some(w),
-		Err(err) => {
-			error!(target: "telemetry", "Failed to initialize telemetry worker: {:?}", err);
-			None
-		}
-	};
+	let worker = new_worker(&config.endpoints, config.wasm_external_transport.clone());
 
 	Telemetry {
 		inner: Arc::new(Mutex::new(TelemetryInner {
 			worker,
 			receiver,
+			wasm_external_transport: config.wasm_external_transport,
 		})),
 		_guard: Arc::new(guard),
 	}
 }
 
+impl Telemetry {
+	/// Replaces the telemetry servers we send the log entries to.
+	///
+	/// The connections to the previous servers are closed, and the new servers are connected to
+	/// the next time the telemetry is polled.
+	pub fn set_endpoints(&self, endpoints: TelemetryEndpoints) {
+		let mut inner = self.inner.lock();
+		inner.worker = new_worker(&endpoints, inner.wasm_external_transport.clone());
+	}
+}
+
+/// Builds a worker connecting to the given endpoints. Returns `None` if it failed to initialize.
+fn new_worker(
+	endpoints: &TelemetryEndpoints,
+	wasm_external_transport: Option<wasm_ext::ExtTransport>,
+) -> Option<worker::TelemetryWorker> {
+	// Build the list of telemetry endpoints.
+	let mut addrs = Vec::new();
+	for &(ref url, verbosity) in &endpoints.0 {
+		match url_to_multiaddr(url) {
+			Ok(addr) => addrs.push((addr, verbosity)),
+			Err(err) => warn!(target: "telemetry", "Invalid telemetry URL {}: {}", url, err),
+		}
+	}
+
+	match worker::TelemetryWorker::new(addrs, wasm_external_transport) {
+		Ok(w) => Some(w),
+		Err(err) => {
+			error!(target: "telemetry", "Failed to initialize telemetry worker: {:?}", err);
+			None
+		}
+	}
+}
+
 /// Event generated when polling the worker.
 #[derive(Debug)]
 pub enum TelemetryEvent {
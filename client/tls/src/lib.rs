@@ -0,0 +1,63 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Loading of the PEM encoded certificates and private keys Substrate servers terminate TLS
+//! with.
+
+use rustls::internal::pemfile;
+use std::{fs::File, io::{self, BufReader}, path::Path};
+
+/// A certificate chain and the private key of its first certificate, DER encoded.
+pub struct Identity {
+	/// The certificates of the chain, starting with the end-entity certificate.
+	pub certs: Vec<Vec<u8>>,
+	/// The PKCS#8 or RSA private key.
+	pub key: Vec<u8>,
+}
+
+/// Builds an `InvalidData` error, as returned for an invalid certificate or private key.
+pub fn invalid_data(message: String) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn open(path: &Path) -> io::Result<BufReader<File>> {
+	File::open(path)
+		.map(BufReader::new)
+		.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+fn load_private_key(path: &Path) -> io::Result<Vec<u8>> {
+	let pkcs8_keys = pemfile::pkcs8_private_keys(&mut open(path)?)
+		.map_err(|()| invalid_data(format!("{}: invalid PKCS#8 private key", path.display())))?;
+	let rsa_keys = pemfile::rsa_private_keys(&mut open(path)?)
+		.map_err(|()| invalid_data(format!("{}: invalid RSA private key", path.display())))?;
+
+	pkcs8_keys.into_iter().chain(rsa_keys).next()
+		.map(|key| key.0)
+		.ok_or_else(|| invalid_data(format!("{}: no private key found", path.display())))
+}
+
+/// Loads the certificate chain of the `cert` file and the first private key of the `key` file.
+pub fn load(cert: &Path, key: &Path) -> io::Result<Identity> {
+	let certs = pemfile::certs(&mut open(cert)?)
+		.map_err(|()| invalid_data(format!("{}: invalid certificate", cert.display())))?;
+	if certs.is_empty() {
+		return Err(invalid_data(format!("{}: no certificate found", cert.display())));
+	}
+	let key = load_private_key(key)?;
+
+	Ok(Identity { certs: certs.into_iter().map(|cert| cert.0).collect(), key })
+}
@@ -0,0 +1,254 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tracing of the execution of a block.
+//!
+//! The block is executed with a `BlockSubscriber` as the default subscriber of the thread, which
+//! records the spans entered and the storage accesses reported by the state machine.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use sp_rpc::tracing::{BlockTrace, Span, StorageAccess};
+use sp_state_machine::STORAGE_ACCESS_TARGET;
+use tracing_core::{
+	dispatcher::{self, Dispatch},
+	event::Event,
+	field::{Visit, Field},
+	Level,
+	metadata::Metadata,
+	span::{Attributes, Id, Record},
+	subscriber::Subscriber,
+};
+
+use crate::parse_target;
+
+/// Runs `f` and records the spans of the given comma separated `targets` entered by it, along
+/// with its storage accesses. The spans of all the targets are recorded if `targets` is `None`.
+pub fn trace_block<R>(targets: Option<&str>, f: impl FnOnce() -> R) -> (R, BlockTrace) {
+	let trace = Arc::new(Mutex::new(TraceState::default()));
+	let subscriber = BlockSubscriber {
+		next_id: AtomicU64::new(1),
+		targets: targets.map(|targets| targets.split(',').map(parse_target).collect()),
+		trace: trace.clone(),
+	};
+	let result = dispatcher::with_default(&Dispatch::new(subscriber), f);
+	let mut trace = trace.lock();
+	// spans still open, e.g. when the execution failed, are reported as they are.
+	let mut open: Vec<_> = trace.open.drain().map(|(_, span)| span).collect();
+	open.sort_by_key(|span| span.span.id);
+	for span in open {
+		trace.close(span);
+	}
+	(result, std::mem::replace(&mut trace.block, BlockTrace::default()))
+}
+
+/// A span not closed yet.
+struct OpenSpan {
+	span: Span,
+	entered: Option<Instant>,
+	time: Duration,
+}
+
+#[derive(Default)]
+struct TraceState {
+	/// Spans entered by the thread, innermost last.
+	stack: Vec<u64>,
+	open: HashMap<u64, OpenSpan>,
+	block: BlockTrace,
+}
+
+impl TraceState {
+	fn close(&mut self, mut open: OpenSpan) {
+		if let Some(entered) = open.entered {
+			open.time += entered.elapsed();
+		}
+		open.span.time = open.time.as_nanos() as u64;
+		self.block.spans.push(open.span);
+	}
+}
+
+/// Records the spans and storage accesses of the execution of a block.
+struct BlockSubscriber {
+	next_id: AtomicU64,
+	targets: Option<Vec<(String, Level)>>,
+	trace: Arc<Mutex<TraceState>>,
+}
+
+impl Subscriber for BlockSubscriber {
+	fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+		if metadata.target() == STORAGE_ACCESS_TARGET {
+			return metadata.is_event();
+		}
+		match self.targets {
+			Some(ref targets) => targets.iter().any(|(target, level)|
+				metadata.target().starts_with(target.as_str()) && metadata.level() <= level
+			),
+			None => true,
+		}
+	}
+
+	fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+		let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+		let mut values = Values::default();
+		attrs.record(&mut values);
+		let mut trace = self.trace.lock();
+		let parent_id = match attrs.parent() {
+			Some(parent) => Some(parent.into_u64()),
+			None if attrs.is_contextual() => trace.stack.last().cloned(),
+			None => None,
+		};
+		let metadata = attrs.metadata();
+		trace.open.insert(id, OpenSpan {
+			span: Span {
+				id,
+				parent_id,
+				name: metadata.name().to_string(),
+				target: metadata.target().to_string(),
+				line: metadata.line().unwrap_or(0),
+				time: 0,
+				values: values.0,
+			},
+			entered: None,
+			time: Duration::from_nanos(0),
+		});
+		Id::from_u64(id)
+	}
+
+	fn record(&self, span: &Id, values: &Record<'_>) {
+		if let Some(open) = self.trace.lock().open.get_mut(&span.into_u64()) {
+			let mut recorded = Values(std::mem::replace(&mut open.span.values, BTreeMap::new()));
+			values.record(&mut recorded);
+			open.span.values = recorded.0;
+		}
+	}
+
+	fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+	fn event(&self, event: &Event<'_>) {
+		if event.metadata().target() != STORAGE_ACCESS_TARGET {
+			return;
+		}
+		let mut values = Values::default();
+		event.record(&mut values);
+		let mut trace = self.trace.lock();
+		let span_id = match event.parent() {
+			Some(parent) => Some(parent.into_u64()),
+			None if event.is_contextual() => trace.stack.last().cloned(),
+			None => None,
+		};
+		let mut field = |name: &str| values.0.remove(name).filter(|value| !value.is_empty());
+		trace.block.storage.push(StorageAccess {
+			span_id,
+			method: field("method").unwrap_or_default(),
+			child: field("child"),
+			key: field("key").unwrap_or_default(),
+			value: field("value"),
+		});
+	}
+
+	fn enter(&self, span: &Id) {
+		let mut trace = self.trace.lock();
+		trace.stack.push(span.into_u64());
+		if let Some(open) = trace.open.get_mut(&span.into_u64()) {
+			open.entered = Some(Instant::now());
+		}
+	}
+
+	fn exit(&self, span: &Id) {
+		let mut trace = self.trace.lock();
+		if let Some(position) = trace.stack.iter().rposition(|id| *id == span.into_u64()) {
+			trace.stack.remove(position);
+		}
+		if let Some(open) = trace.open.get_mut(&span.into_u64()) {
+			if let Some(entered) = open.entered.take() {
+				open.time += entered.elapsed();
+			}
+		}
+	}
+
+	fn try_close(&self, span: Id) -> bool {
+		let mut trace = self.trace.lock();
+		if let Some(open) = trace.open.remove(&span.into_u64()) {
+			trace.close(open);
+		}
+		true
+	}
+}
+
+/// Values recorded with a span or an event, formatted with `Display` for strings and `Debug`
+/// otherwise.
+#[derive(Default)]
+struct Values(BTreeMap<String, String>);
+
+impl Visit for Values {
+	fn record_str(&mut self, field: &Field, value: &str) {
+		self.0.insert(field.name().to_string(), value.to_string());
+	}
+
+	fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+		self.0.insert(field.name().to_string(), format!("{:?}", value));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn records_spans_and_storage_accesses() {
+		let (result, trace) = trace_block(Some("pallet"), || {
+			let outer = tracing::span!(target: "pallet", tracing::Level::INFO, "outer", call = 1);
+			let _outer = outer.enter();
+			tracing::trace!(
+				target: "state",
+				method = "Get",
+				child = "",
+				key = "0x01",
+				value = "0x02",
+			);
+			let other = tracing::span!(target: "other", tracing::Level::INFO, "ignored");
+			let _other = other.enter();
+			tracing::trace!(target: "state", method = "Put", child = "", key = "0x01", value = "");
+			42
+		});
+
+		assert_eq!(result, 42);
+		assert_eq!(trace.spans.len(), 1);
+		let span = &trace.spans[0];
+		assert_eq!((span.name.as_str(), span.parent_id), ("outer", None));
+		assert_eq!(span.values.get("call").map(String::as_str), Some("1"));
+		assert_eq!(trace.storage, vec![
+			StorageAccess {
+				span_id: Some(span.id),
+				method: "Get".into(),
+				child: None,
+				key: "0x01".into(),
+				value: Some("0x02".into()),
+			},
+			StorageAccess {
+				span_id: Some(span.id),
+				method: "Put".into(),
+				child: None,
+				key: "0x01".into(),
+				value: None,
+			},
+		]);
+	}
+}
@@ -36,6 +36,8 @@
 //! ```
 //! Currently we provide `Log` (default), `Telemetry` variants for `Receiver`
 
+pub mod block;
+
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::atomic::{AtomicU64, Ordering};
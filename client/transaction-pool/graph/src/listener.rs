@@ -20,6 +20,7 @@ use std::{
 };
 use linked_hash_map::LinkedHashMap;
 use serde::Serialize;
+use sp_transaction_pool::DropReason;
 use crate::{watcher, ChainApi, BlockHash};
 use log::{debug, trace, warn};
 use sp_runtime::traits;
@@ -85,13 +86,16 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 		self.fire(tx, |watcher| watcher.future());
 	}
 
-	/// Transaction was dropped from the pool because of the limit.
-	pub fn dropped(&mut self, tx: &H, by: Option<&H>) {
-		trace!(target: "txpool", "[{:?}] Dropped (replaced by {:?})", tx, by);
-		self.fire(tx, |watcher| match by {
-			Some(t) => watcher.usurped(t.clone()),
-			None => watcher.dropped(),
-		})
+	/// Transaction was replaced by another transaction providing the same tags.
+	pub fn usurped(&mut self, tx: &H, by: &H) {
+		trace!(target: "txpool", "[{:?}] Usurped by {:?}", tx, by);
+		self.fire(tx, |watcher| watcher.usurped(by.clone()))
+	}
+
+	/// Transaction was dropped from the pool for the given reason.
+	pub fn dropped(&mut self, tx: &H, reason: DropReason) {
+		trace!(target: "txpool", "[{:?}] Dropped ({:?})", tx, reason);
+		self.fire(tx, |watcher| watcher.dropped(reason))
 	}
 
 	/// Transaction was removed as invalid.
@@ -34,7 +34,7 @@ use sp_runtime::{
 	traits::{self, SaturatedConversion},
 	transaction_validity::{TransactionTag as Tag, ValidTransaction},
 };
-use sp_transaction_pool::{error, PoolStatus};
+use sp_transaction_pool::{error, DropReason, PoolStatus};
 use wasm_timer::Instant;
 
 use crate::base_pool::PruneStatus;
@@ -209,7 +209,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 			debug!(target: "txpool", "Enforcing limits: {} dropped", removed.len());
 			let mut listener = self.listener.write();
 			for h in &removed {
-				listener.dropped(h, None);
+				listener.dropped(h, DropReason::LimitReached);
 			}
 
 			removed
@@ -246,7 +246,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 	/// Transactions that are missing from the pool are not submitted.
 	pub fn resubmit(&self, mut updated_transactions: HashMap<ExHash<B>, ValidatedTransactionFor<B>>) {
 		#[derive(Debug, Clone, Copy, PartialEq)]
-		enum Status { Future, Ready, Failed, Dropped };
+		enum Status { Future, Ready, Failed, Dropped(DropReason) };
 
 		let (mut initial_statuses, final_statuses) = {
 			let mut pool = self.pool.write();
@@ -309,7 +309,10 @@ impl<B: ChainApi> ValidatedPool<B> {
 										final_statuses.insert(hash, Status::Failed);
 									}
 									for tx in removed {
-										final_statuses.insert(tx.hash.clone(), Status::Dropped);
+										final_statuses.insert(
+											tx.hash.clone(),
+											Status::Dropped(DropReason::Conflict),
+										);
 									}
 								},
 								base::Imported::Future { .. } => {
@@ -339,7 +342,10 @@ impl<B: ChainApi> ValidatedPool<B> {
 				// queue, updating final statuses as required
 				if reject_future_transactions {
 					for future_tx in pool.clear_future() {
-						final_statuses.insert(future_tx.hash.clone(), Status::Dropped);
+						final_statuses.insert(
+							future_tx.hash.clone(),
+							Status::Dropped(DropReason::FutureRejected),
+						);
 					}
 				}
 
@@ -355,7 +361,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 				match final_status {
 					Status::Future => listener.future(&hash),
 					Status::Ready => listener.ready(&hash, None),
-					Status::Dropped => listener.dropped(&hash, None),
+					Status::Dropped(reason) => listener.dropped(&hash, reason),
 					Status::Failed => listener.invalid(&hash, initial_status.is_some()),
 				}
 			}
@@ -391,7 +397,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 				fire_events(&mut *listener, promoted);
 			}
 			for f in &status.failed {
-				listener.dropped(f, None);
+				listener.dropped(f, DropReason::ReimportFailed);
 			}
 		}
 
@@ -589,7 +595,7 @@ fn fire_events<H, B, Ex>(
 				listener.invalid(f, true);
 			}
 			for r in removed {
-				listener.dropped(&r.hash, Some(hash));
+				listener.usurped(&r.hash, hash);
 			}
 			for p in promoted {
 				listener.ready(p, None);
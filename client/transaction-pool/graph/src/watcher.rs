@@ -20,7 +20,7 @@ use futures::{
 	Stream,
 	channel::mpsc,
 };
-use sp_transaction_pool::TransactionStatus;
+use sp_transaction_pool::{DropReason, TransactionStatus};
 
 /// Extrinsic watcher.
 ///
@@ -117,9 +117,9 @@ impl<H: Clone, BH: Clone> Sender<H, BH> {
 		self.is_finalized = true;
 	}
 
-	/// Transaction has been dropped from the pool because of the limit.
-	pub fn dropped(&mut self) {
-		self.send(TransactionStatus::Dropped);
+	/// Transaction has been dropped from the pool for the given reason.
+	pub fn dropped(&mut self, reason: DropReason) {
+		self.send(TransactionStatus::Dropped(reason));
 		self.is_finalized = true;
 	}
 
@@ -0,0 +1,191 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reporting of the authorities authoring two blocks in the same slot.
+//!
+//! The nodes detecting an equivocation submit its `EquivocationProof` as an unsigned
+//! `report_equivocation` extrinsic, built by the `report_equivocation_extrinsic` runtime API.
+//! The proof is checked against the authorities of the current epoch, and the offender is then
+//! handed to the `HandleEquivocation` of the runtime, e.g. `EquivocationHandler` to report a
+//! `BabeEquivocationOffence` to the offences module.
+
+use sp_std::prelude::*;
+use codec::Decode;
+use sp_consensus_babe::{
+	AuthorityIndex, AuthoritySignature, BABE_ENGINE_ID, EquivocationProof, SlotNumber,
+	digests::RawPreDigest,
+};
+use sp_runtime::{Perbill, PerThing, RuntimeAppPublic, traits::{Convert, Header}};
+use sp_staking::{
+	SessionIndex,
+	offence::{Kind, Offence, OffenceError, ReportOffence},
+};
+use pallet_session::historical::IdentificationTuple;
+
+use crate::{Authorities, Module, Trait};
+
+/// Handles the authorities proven to have equivocated.
+pub trait HandleEquivocation<T: Trait> {
+	/// Reports that the authority at `authority_index` of the current authorities authored two
+	/// blocks in `slot_number`.
+	fn report_offence(authority_index: AuthorityIndex, slot_number: SlotNumber)
+		-> Result<(), OffenceError>;
+}
+
+/// Equivocations are ignored.
+impl<T: Trait> HandleEquivocation<T> for () {
+	fn report_offence(_: AuthorityIndex, _: SlotNumber) -> Result<(), OffenceError> {
+		Ok(())
+	}
+}
+
+/// Reports the equivocations as `BabeEquivocationOffence`s to `R`, e.g. the offences module.
+///
+/// The authorities are the keys of the validators of the current session, in the same order.
+pub struct EquivocationHandler<R>(sp_std::marker::PhantomData<R>);
+
+impl<T, R> HandleEquivocation<T> for EquivocationHandler<R> where
+	T: Trait + pallet_session::historical::Trait,
+	R: ReportOffence<
+		T::AccountId,
+		IdentificationTuple<T>,
+		BabeEquivocationOffence<IdentificationTuple<T>>,
+	>,
+{
+	fn report_offence(authority_index: AuthorityIndex, slot_number: SlotNumber)
+		-> Result<(), OffenceError>
+	{
+		let validators = <pallet_session::Module<T>>::validators();
+		let offender = validators.get(authority_index as usize).and_then(|id|
+			T::FullIdentificationOf::convert(id.clone()).map(|full| (id.clone(), full))
+		);
+		let offender = match offender {
+			Some(offender) => offender,
+			// the offender is no longer a validator, and can't be punished.
+			None => return Ok(()),
+		};
+
+		let offence = BabeEquivocationOffence {
+			slot: slot_number,
+			session_index: <pallet_session::Module<T>>::current_index(),
+			validator_set_count: validators.len() as u32,
+			offender,
+		};
+		R::report_offence(Vec::new(), offence)
+	}
+}
+
+/// A BABE equivocation offence report.
+///
+/// When a validator released two or more blocks at the same slot.
+pub struct BabeEquivocationOffence<FullIdentification> {
+	/// A babe slot number in which this incident happened.
+	pub slot: u64,
+	/// The session index in which the incident happened.
+	pub session_index: SessionIndex,
+	/// The size of the validator set at the time of the offence.
+	pub validator_set_count: u32,
+	/// The authority that produced the equivocation.
+	pub offender: FullIdentification,
+}
+
+impl<FullIdentification: Clone> Offence<FullIdentification>
+	for BabeEquivocationOffence<FullIdentification>
+{
+	const ID: Kind = *b"babe:equivocatio";
+	type TimeSlot = u64;
+
+	fn offenders(&self) -> Vec<FullIdentification> {
+		vec![self.offender.clone()]
+	}
+
+	fn session_index(&self) -> SessionIndex {
+		self.session_index
+	}
+
+	fn validator_set_count(&self) -> u32 {
+		self.validator_set_count
+	}
+
+	fn time_slot(&self) -> Self::TimeSlot {
+		self.slot
+	}
+
+	fn slash_fraction(
+		offenders_count: u32,
+		validator_set_count: u32,
+	) -> Perbill {
+		// the formula is min((3k / n)^2, 1)
+		let x = Perbill::from_rational_approximation(3 * offenders_count, validator_set_count);
+		// _ ^ 2
+		x.square()
+	}
+}
+
+/// Checks that the two headers of `proof` were authored and sealed by its offender, in its slot
+/// of the current epoch. Returns the index of the offender in the current authorities.
+pub(crate) fn check_proof<T: Trait>(
+	proof: &EquivocationProof<T::Header>,
+) -> Option<AuthorityIndex> {
+	let epoch_start = <Module<T>>::current_epoch_start();
+	let epoch_end = epoch_start.saturating_add(T::EpochDuration::get());
+	if proof.slot_number < epoch_start || proof.slot_number >= epoch_end {
+		return None;
+	}
+	if proof.first_header.hash() == proof.second_header.hash() {
+		return None;
+	}
+
+	let first = check_header::<T>(&proof.first_header, proof)?;
+	let second = check_header::<T>(&proof.second_header, proof)?;
+	if first != second {
+		return None;
+	}
+
+	match Authorities::get().get(first as usize) {
+		Some((authority, _)) if authority == &proof.offender => Some(first),
+		_ => None,
+	}
+}
+
+/// Checks that `header` claims the slot of `proof`, and is sealed by its offender. Returns the
+/// authority index claiming the slot.
+fn check_header<T: Trait>(
+	header: &T::Header,
+	proof: &EquivocationProof<T::Header>,
+) -> Option<AuthorityIndex> {
+	let mut header = header.clone();
+	let seal = header.digest_mut().pop()?;
+	let signature = match seal.as_seal() {
+		Some((id, mut signature)) if id == BABE_ENGINE_ID =>
+			AuthoritySignature::decode(&mut signature).ok()?,
+		_ => return None,
+	};
+
+	let pre_digest = header.digest().logs().iter()
+		.filter_map(|log| log.as_pre_runtime())
+		.find(|(id, _)| *id == BABE_ENGINE_ID)
+		.and_then(|(_, mut data)| RawPreDigest::decode(&mut data).ok())?;
+	if pre_digest.slot_number() != proof.slot_number {
+		return None;
+	}
+
+	let pre_hash = header.hash();
+	if !proof.offender.verify(&pre_hash, &signature) {
+		return None;
+	}
+	Some(pre_digest.authority_index())
+}
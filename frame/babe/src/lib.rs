@@ -25,22 +25,29 @@ pub use pallet_timestamp;
 use sp_std::{result, prelude::*};
 use frame_support::{decl_storage, decl_module, traits::{FindAuthor, Get, Randomness as RandomnessT}};
 use sp_timestamp::OnTimestampSet;
-use sp_runtime::{generic::DigestItem, ConsensusEngineId, Perbill, PerThing};
-use sp_runtime::traits::{IsMember, SaturatedConversion, Saturating, Hash};
-use sp_staking::{
-	SessionIndex,
-	offence::{Offence, Kind},
+use sp_runtime::{
+	generic::DigestItem, ConsensusEngineId,
+	transaction_validity::{
+		TransactionValidity, ValidTransaction, InvalidTransaction, TransactionPriority,
+	},
 };
+use sp_runtime::traits::{IsMember, SaturatedConversion, Saturating, Hash};
+use frame_system::ensure_none;
+use sp_staking::offence::OffenceError;
 
 use codec::{Encode, Decode};
 use sp_inherents::{InherentIdentifier, InherentData, ProvideInherent, MakeFatalError};
 use sp_consensus_babe::{
-	BABE_ENGINE_ID, ConsensusLog, BabeAuthorityWeight, SlotNumber,
+	BABE_ENGINE_ID, ConsensusLog, BabeAuthorityWeight, EquivocationProof, SlotNumber,
 	inherents::{INHERENT_IDENTIFIER, BabeInherentData},
-	digests::{NextEpochDescriptor, RawPreDigest},
+	digests::{NextEpochDescriptor, NextConfigDescriptor, RawPreDigest},
 };
 pub use sp_consensus_babe::{AuthorityId, VRF_OUTPUT_LENGTH, PUBLIC_KEY_LENGTH};
 
+mod equivocation;
+
+pub use equivocation::{BabeEquivocationOffence, EquivocationHandler, HandleEquivocation};
+
 #[cfg(all(feature = "std", test))]
 mod tests;
 
@@ -64,6 +71,10 @@ pub trait Trait: pallet_timestamp::Trait {
 	/// Typically, the `ExternalTrigger` type should be used. An internal trigger should only be used
 	/// when no other module is responsible for changing authority set.
 	type EpochChangeTrigger: EpochChangeTrigger;
+
+	/// Handles the authorities proven to have authored two blocks in the same slot, see
+	/// `EquivocationHandler`. Use `()` to ignore equivocations.
+	type HandleEquivocation: HandleEquivocation<Self>;
 }
 
 /// Trigger an epoch change, if any should take place.
@@ -151,6 +162,10 @@ decl_storage! {
 		/// Temporary value (cleared at block finalization) which is `Some`
 		/// if per-block initialization has already been called for current block.
 		Initialized get(fn initialized): Option<MaybeVrf>;
+
+		/// Pending epoch configuration change that will be signaled together
+		/// with the next epoch change.
+		PendingEpochConfigChange: Option<NextConfigDescriptor>;
 	}
 	add_extra_genesis {
 		config(authorities): Vec<(AuthorityId, BabeAuthorityWeight)>;
@@ -188,6 +203,22 @@ decl_module! {
 				Self::deposit_vrf_output(&vrf_output);
 			}
 		}
+
+		/// Report an authority for authoring two blocks in the same slot of the current epoch.
+		///
+		/// The proof is checked in `validate_unsigned`, and again here since it can only be
+		/// checked against the current authorities.
+		fn report_equivocation(origin, equivocation_proof: EquivocationProof<T::Header>) {
+			ensure_none(origin)?;
+
+			let authority_index = equivocation::check_proof::<T>(&equivocation_proof)
+				.ok_or("Invalid equivocation proof")?;
+			T::HandleEquivocation::report_offence(authority_index, equivocation_proof.slot_number)
+				.map_err(|e| match e {
+					OffenceError::DuplicateReport => "Equivocation already reported",
+					OffenceError::Other(_) => "Unable to report the equivocation",
+				})?;
+		}
 	}
 }
 
@@ -244,52 +275,6 @@ impl<T: Trait> pallet_session::ShouldEndSession<T::BlockNumber> for Module<T> {
 	}
 }
 
-// TODO [slashing]: @marcio use this, remove the dead_code annotation.
-/// A BABE equivocation offence report.
-///
-/// When a validator released two or more blocks at the same slot.
-struct BabeEquivocationOffence<FullIdentification> {
-	/// A babe slot number in which this incident happened.
-	slot: u64,
-	/// The session index in which the incident happened.
-	session_index: SessionIndex,
-	/// The size of the validator set at the time of the offence.
-	validator_set_count: u32,
-	/// The authority that produced the equivocation.
-	offender: FullIdentification,
-}
-
-impl<FullIdentification: Clone> Offence<FullIdentification> for BabeEquivocationOffence<FullIdentification> {
-	const ID: Kind = *b"babe:equivocatio";
-	type TimeSlot = u64;
-
-	fn offenders(&self) -> Vec<FullIdentification> {
-		vec![self.offender.clone()]
-	}
-
-	fn session_index(&self) -> SessionIndex {
-		self.session_index
-	}
-
-	fn validator_set_count(&self) -> u32 {
-		self.validator_set_count
-	}
-
-	fn time_slot(&self) -> Self::TimeSlot {
-		self.slot
-	}
-
-	fn slash_fraction(
-		offenders_count: u32,
-		validator_set_count: u32,
-	) -> Perbill {
-		// the formula is min((3k / n)^2, 1)
-		let x = Perbill::from_rational_approximation(3 * offenders_count, validator_set_count);
-		// _ ^ 2
-		x.square()
-	}
-}
-
 impl<T: Trait> Module<T> {
 	/// Determine the BABE slot duration based on the Timestamp module configuration.
 	pub fn slot_duration() -> T::Moment {
@@ -332,10 +317,20 @@ impl<T: Trait> Module<T> {
 		}
 
 		// Update epoch index
-		let epoch_index = EpochIndex::get()
+		let mut epoch_index = EpochIndex::get()
 			.checked_add(1)
 			.expect("epoch indices will never reach 2^64 before the death of the universe; qed");
 
+		// if the chain was stalled for longer than an epoch, the current slot
+		// is past the end of the epoch we'd be entering. in that case we jump
+		// straight to the epoch that contains the current slot.
+		let slot_idx = CurrentSlot::get().saturating_sub(Self::epoch_start(epoch_index));
+		if slot_idx >= T::EpochDuration::get() {
+			epoch_index = epoch_index
+				.checked_add(slot_idx / T::EpochDuration::get())
+				.expect("epoch indices will never reach 2^64 before the death of the universe; qed");
+		}
+
 		EpochIndex::put(epoch_index);
 		Authorities::put(authorities);
 
@@ -358,14 +353,30 @@ impl<T: Trait> Module<T> {
 			randomness: next_randomness,
 		};
 
-		Self::deposit_consensus(ConsensusLog::NextEpochData(next))
+		Self::deposit_consensus(ConsensusLog::NextEpochData(next));
+
+		if let Some(next_config) = PendingEpochConfigChange::take() {
+			Self::deposit_consensus(ConsensusLog::NextConfigData(next_config));
+		}
+	}
+
+	/// Plan an epoch config change. The epoch config change is recorded and will be enacted on
+	/// the next call to `enact_epoch_change`. The config will be activated one epoch after.
+	/// Multiple calls to this method will replace any existing planned config change that had
+	/// not been enacted yet.
+	pub fn plan_config_change(config: NextConfigDescriptor) {
+		PendingEpochConfigChange::put(config);
 	}
 
 	// finds the start slot of the current epoch. only guaranteed to
 	// give correct results after `do_initialize` of the first block
 	// in the chain (as its result is based off of `GenesisSlot`).
 	pub fn current_epoch_start() -> SlotNumber {
-		(EpochIndex::get() * T::EpochDuration::get()) + GenesisSlot::get()
+		Self::epoch_start(EpochIndex::get())
+	}
+
+	fn epoch_start(epoch_index: u64) -> SlotNumber {
+		(epoch_index * T::EpochDuration::get()) + GenesisSlot::get()
 	}
 
 	fn deposit_consensus<U: Encode>(new: U) {
@@ -529,6 +540,30 @@ fn compute_randomness(
 	sp_io::hashing::blake2_256(&s)
 }
 
+impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
+	type Call = Call<T>;
+
+	fn validate_unsigned(call: &Self::Call) -> TransactionValidity {
+		if let Call::report_equivocation(equivocation_proof) = call {
+			if equivocation::check_proof::<T>(equivocation_proof).is_none() {
+				return InvalidTransaction::BadProof.into();
+			}
+
+			Ok(ValidTransaction {
+				priority: TransactionPriority::max_value(),
+				requires: vec![],
+				provides: vec![
+					(&equivocation_proof.offender, equivocation_proof.slot_number).encode(),
+				],
+				longevity: T::EpochDuration::get(),
+				propagate: true,
+			})
+		} else {
+			InvalidTransaction::Call.into()
+		}
+	}
+}
+
 impl<T: Trait> ProvideInherent for Module<T> {
 	type Call = pallet_timestamp::Call<T>;
 	type Error = MakeFatalError<sp_inherents::Error>;
@@ -16,7 +16,8 @@
 
 //! Test utilities
 
-use super::{Trait, Module, GenesisConfig};
+use super::{Trait, Module, GenesisConfig, HandleEquivocation};
+use std::cell::RefCell;
 use sp_runtime::{
 	traits::IdentityLookup, Perbill, testing::{Header, UintAuthorityId}, impl_opaque_keys,
 };
@@ -24,6 +25,8 @@ use sp_version::RuntimeVersion;
 use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
 use sp_io;
 use sp_core::H256;
+use sp_consensus_babe::{AuthorityIndex, SlotNumber};
+use sp_staking::offence::OffenceError;
 
 impl_outer_origin!{
 	pub enum Origin for Test  where system = frame_system {}
@@ -96,6 +99,23 @@ impl Trait for Test {
 	type EpochDuration = EpochDuration;
 	type ExpectedBlockTime = ExpectedBlockTime;
 	type EpochChangeTrigger = crate::ExternalTrigger;
+	type HandleEquivocation = TestEquivocationHandler;
+}
+
+thread_local! {
+	pub static EQUIVOCATIONS: RefCell<Vec<(AuthorityIndex, SlotNumber)>> = RefCell::new(Vec::new());
+}
+
+/// Records the reported equivocations in `EQUIVOCATIONS`.
+pub struct TestEquivocationHandler;
+
+impl HandleEquivocation<Test> for TestEquivocationHandler {
+	fn report_offence(authority_index: AuthorityIndex, slot_number: SlotNumber)
+		-> Result<(), OffenceError>
+	{
+		EQUIVOCATIONS.with(|l| l.borrow_mut().push((authority_index, slot_number)));
+		Ok(())
+	}
 }
 
 pub fn new_test_ext(authorities: Vec<DummyValidatorId>) -> sp_io::TestExternalities {
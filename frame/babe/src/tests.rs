@@ -17,8 +17,13 @@
 //! Consensus extension module tests for BABE consensus.
 
 use super::*;
-use mock::{new_test_ext, Babe, System};
-use sp_runtime::{traits::OnFinalize, testing::{Digest, DigestItem}};
+use mock::{new_test_ext, Babe, System, EpochDuration, Origin, EQUIVOCATIONS};
+use sp_runtime::{
+	traits::{Header as HeaderT, OnFinalize},
+	testing::{Digest, DigestItem, Header},
+};
+use sp_core::{H256, Pair, sr25519};
+use frame_support::unsigned::ValidateUnsigned;
 use pallet_session::ShouldEndSession;
 
 const EMPTY_RANDOMNESS: [u8; 32] = [
@@ -128,3 +133,177 @@ fn authority_index() {
 			"Trivially invalid authorities are ignored")
 	})
 }
+
+#[test]
+fn can_enact_next_config() {
+	new_test_ext(vec![0, 1, 2, 3]).execute_with(|| {
+		let pre_digest = make_pre_digest(0, 100, [1; 32], [0xff; 64]);
+
+		System::initialize(
+			&1,
+			&Default::default(),
+			&Default::default(),
+			&pre_digest,
+			Default::default(),
+		);
+		Babe::do_initialize(1);
+
+		Babe::plan_config_change(NextConfigDescriptor::V1 {
+			c: (1, 4),
+			secondary_slots: false,
+		});
+
+		let authorities = Babe::authorities();
+		Babe::enact_epoch_change(authorities.clone(), authorities);
+
+		let header = System::finalize();
+		let consensus_log = sp_consensus_babe::ConsensusLog::NextConfigData(
+			NextConfigDescriptor::V1 {
+				c: (1, 4),
+				secondary_slots: false,
+			}
+		);
+		let consensus_digest = DigestItem::Consensus(BABE_ENGINE_ID, consensus_log.encode());
+
+		assert_eq!(header.digest.logs.last(), Some(&consensus_digest));
+		assert!(PendingEpochConfigChange::get().is_none());
+	})
+}
+
+#[test]
+fn can_recover_from_skipped_epochs() {
+	new_test_ext(vec![0, 1, 2, 3]).execute_with(|| {
+		let genesis_slot = 100;
+
+		System::initialize(
+			&1,
+			&Default::default(),
+			&Default::default(),
+			&make_pre_digest(0, genesis_slot, [1; 32], [0xff; 64]),
+			Default::default(),
+		);
+		Babe::do_initialize(1);
+		Babe::on_finalize(1);
+		System::finalize();
+
+		// the chain stalls and resumes four epochs later.
+		let slot = genesis_slot + 4 * EpochDuration::get();
+		System::initialize(
+			&2,
+			&Default::default(),
+			&Default::default(),
+			&make_pre_digest(0, slot, [1; 32], [0xff; 64]),
+			Default::default(),
+		);
+
+		assert!(Babe::should_end_session(2));
+
+		let authorities = Babe::authorities();
+		Babe::enact_epoch_change(authorities.clone(), authorities);
+
+		assert_eq!(Babe::epoch_index(), 4);
+		assert_eq!(Babe::current_epoch_start(), slot);
+	})
+}
+
+fn make_sealed_header(
+	pair: &sr25519::Pair,
+	authority_index: sp_consensus_babe::AuthorityIndex,
+	slot_number: sp_consensus_babe::SlotNumber,
+	state_root: H256,
+) -> Header {
+	let pre_digest = sp_consensus_babe::digests::RawPreDigest::Secondary {
+		authority_index,
+		slot_number,
+	};
+	let mut header = Header {
+		parent_hash: Default::default(),
+		number: 1,
+		state_root,
+		extrinsics_root: Default::default(),
+		digest: Digest {
+			logs: vec![DigestItem::PreRuntime(BABE_ENGINE_ID, pre_digest.encode())],
+		},
+	};
+
+	let pre_hash = header.hash();
+	let signature: sp_consensus_babe::AuthoritySignature = pair.sign(pre_hash.as_ref()).into();
+	header.digest.push(DigestItem::Seal(BABE_ENGINE_ID, signature.encode()));
+	header
+}
+
+fn make_equivocation_proof(
+	pair: &sr25519::Pair,
+	slot_number: sp_consensus_babe::SlotNumber,
+) -> EquivocationProof<Header> {
+	EquivocationProof {
+		offender: pair.public().into(),
+		slot_number,
+		first_header: make_sealed_header(pair, 1, slot_number, H256::repeat_byte(1)),
+		second_header: make_sealed_header(pair, 1, slot_number, H256::repeat_byte(2)),
+	}
+}
+
+#[test]
+fn equivocation_is_reported() {
+	new_test_ext(vec![0, 1, 2]).execute_with(|| {
+		let pair = sr25519::Pair::from_seed(&[1; 32]);
+		let mut authorities = Babe::authorities();
+		authorities[1].0 = pair.public().into();
+		Authorities::put(authorities);
+
+		let proof = make_equivocation_proof(&pair, 1);
+		let call = Call::report_equivocation(proof.clone());
+		assert!(Babe::validate_unsigned(&call).is_ok());
+
+		Babe::report_equivocation(Origin::system(frame_system::RawOrigin::None), proof).unwrap();
+		assert_eq!(EQUIVOCATIONS.with(|l| l.borrow().clone()), vec![(1, 1)]);
+	})
+}
+
+#[test]
+fn invalid_equivocation_proofs_are_rejected() {
+	new_test_ext(vec![0, 1, 2]).execute_with(|| {
+		let pair = sr25519::Pair::from_seed(&[1; 32]);
+		let mut authorities = Babe::authorities();
+		authorities[1].0 = pair.public().into();
+		Authorities::put(authorities);
+
+		let invalid = |proof: EquivocationProof<Header>| {
+			let bad_proof: TransactionValidity = InvalidTransaction::BadProof.into();
+			let call = Call::report_equivocation(proof.clone());
+			assert_eq!(Babe::validate_unsigned(&call), bad_proof);
+			assert!(Babe::report_equivocation(
+				Origin::system(frame_system::RawOrigin::None),
+				proof,
+			).is_err());
+		};
+
+		// the same header twice.
+		let mut proof = make_equivocation_proof(&pair, 1);
+		proof.second_header = proof.first_header.clone();
+		invalid(proof);
+
+		// a slot outside of the current epoch.
+		invalid(make_equivocation_proof(&pair, EpochDuration::get()));
+
+		// headers claiming another slot than the proof.
+		let mut proof = make_equivocation_proof(&pair, 1);
+		proof.slot_number = 2;
+		invalid(proof);
+
+		// headers sealed by another key than the offender's.
+		let other = sr25519::Pair::from_seed(&[2; 32]);
+		let mut proof = make_equivocation_proof(&other, 1);
+		proof.offender = pair.public().into();
+		invalid(proof);
+
+		// an offender that isn't the authority claiming the slot.
+		let mut authorities = Babe::authorities();
+		authorities[1].0 = other.public().into();
+		Authorities::put(authorities);
+		invalid(make_equivocation_proof(&pair, 1));
+
+		assert!(EQUIVOCATIONS.with(|l| l.borrow().is_empty()));
+	})
+}
@@ -465,6 +465,9 @@ macro_rules! impl_benchmark {
 							// This will enable worst case scenario for reading from the database.
 							$crate::benchmarking::commit_db();
 
+							// Only count the reads and writes of the extrinsic logic.
+							$crate::benchmarking::reset_read_write_count();
+
 							// Time the extrinsic logic.
 							let start_extrinsic = $crate::benchmarking::current_time();
 							closure_to_benchmark()?;
@@ -477,7 +480,10 @@ macro_rules! impl_benchmark {
 							let finish_storage_root = $crate::benchmarking::current_time();
 							let elapsed_storage_root = finish_storage_root - start_storage_root;
 
-							results.push((c.clone(), elapsed_extrinsic, elapsed_storage_root));
+							// The writes are counted while calculating the storage root.
+							let (reads, writes) = $crate::benchmarking::read_write_count();
+
+							results.push((c.clone(), elapsed_extrinsic, elapsed_storage_root, reads, writes));
 
 							// Wipe the DB back to the genesis state.
 							$crate::benchmarking::wipe_db();
@@ -30,9 +30,10 @@ pub enum BenchmarkParameter {
 }
 
 /// Results from running benchmarks on a FRAME pallet.
-/// Contains duration of the function call in nanoseconds along with the benchmark parameters
-/// used for that benchmark result.
-pub type BenchmarkResults = (Vec<(BenchmarkParameter, u32)>, u128, u128);
+/// Contains duration of the function call and of the storage root calculation in nanoseconds,
+/// and the number of storage reads and writes, along with the benchmark parameters used for that
+/// benchmark result.
+pub type BenchmarkResults = (Vec<(BenchmarkParameter, u32)>, u128, u128, u32, u32);
 
 sp_api::decl_runtime_apis! {
 	/// Runtime api for benchmarking a FRAME runtime.
@@ -71,6 +72,16 @@ pub trait Benchmarking {
 	fn commit_db(&mut self) {
 		self.commit()
 	}
+
+	/// Get the number of storage reads and writes since the last reset.
+	fn read_write_count(&self) -> (u32, u32) {
+		self.read_write_count()
+	}
+
+	/// Reset the number of storage reads and writes.
+	fn reset_read_write_count(&mut self) {
+		self.reset_read_write_count()
+	}
 }
 
 /// The pallet benchmarking trait.
@@ -75,7 +75,9 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use sp_std::{prelude::*, marker::PhantomData};
-use frame_support::{storage::StorageValue, weights::{GetDispatchInfo, WeighBlock, DispatchInfo}};
+use frame_support::{
+	storage::StorageValue, weights::{GetDispatchInfo, WeighBlock, DispatchInfo, Weight},
+};
 use sp_runtime::{
 	generic::Digest, ApplyExtrinsicResult,
 	traits::{
@@ -242,9 +244,13 @@ where
 		// any initial checks
 		Self::initial_checks(&block);
 
-		// execute extrinsics
+		// execute extrinsics, verifying their signatures in parallel
 		let (header, extrinsics) = block.deconstruct();
+		let signature_batching = sp_runtime::SignatureBatching::start();
 		Self::execute_extrinsics_with_book_keeping(extrinsics, *header.number());
+		if !signature_batching.verify() {
+			panic!("Signature verification failed.");
+		}
 
 		// any final checks
 		Self::final_checks(&header);
@@ -353,6 +359,14 @@ where
 		xt.validate::<UnsignedValidator>(dispatch_info, encoded_len)
 	}
 
+	/// Sum of the weights declared by `extrinsics`, without executing them.
+	pub fn extrinsics_weight(extrinsics: &[Block::Extrinsic]) -> Weight where
+		Block::Extrinsic: GetDispatchInfo,
+	{
+		extrinsics.iter()
+			.fold(0, |weight: Weight, xt| weight.saturating_add(xt.get_dispatch_info().weight))
+	}
+
 	/// Start an offchain worker and generate extrinsics.
 	pub fn offchain_worker(header: &System::Header) {
 		// We need to keep events available for offchain workers,
@@ -726,6 +740,16 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn extrinsics_weight_sums_declared_weights() {
+		let xt = TestXt::new(Call::Balances(BalancesCall::transfer(33, 0)), sign_extra(1, 0, 0));
+		let x1 = TestXt::new(Call::Balances(BalancesCall::transfer(33, 0)), sign_extra(1, 1, 0));
+		let weight = xt.get_dispatch_info().weight + x1.get_dispatch_info().weight;
+
+		assert_eq!(Executive::extrinsics_weight(&[xt, x1]), weight);
+		assert_eq!(Executive::extrinsics_weight(&[]), 0);
+	}
+
 	#[test]
 	fn validate_unsigned() {
 		let xt = TestXt::new(Call::Balances(BalancesCall::set_balance(33, 69, 69)), None);
@@ -0,0 +1,177 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC interface returning the transactions a block built on top of the best block would contain.
+
+use std::{sync::Arc, collections::HashSet};
+use codec::{Codec, Decode, Encode};
+use serde::{Serialize, Deserialize};
+use sp_blockchain::HeaderBackend;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_runtime::{
+	generic::BlockId, traits::{MaybeDisplay, MaybeFromStr},
+	transaction_validity::TransactionPriority,
+};
+use sp_api::ProvideRuntimeApi;
+use sp_core::Bytes;
+use sp_transaction_pool::{TransactionPool, InPoolTransaction, BlockHash, TxHash};
+use sc_rpc_api::DenyUnsafe;
+use pallet_transaction_payment_rpc_runtime_api::RuntimeDispatchInfo;
+use crate::{Error, TransactionPaymentRuntimeApi};
+
+pub use self::gen_client::Client as BlockTemplateClient;
+
+#[rpc]
+pub trait BlockTemplateApi<ResponseType> {
+	/// Returns the ready transactions of the pool that fit into a block built on top of the best
+	/// block, in the order they would be included, with their weight and fee.
+	///
+	/// This RPC is unsafe.
+	#[rpc(name = "payment_blockTemplate")]
+	fn block_template(&self) -> Result<ResponseType>;
+}
+
+/// Limits of the block template.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockLimits {
+	/// Maximum total weight of the transactions.
+	pub max_weight: u64,
+	/// Maximum total encoded length of the transactions.
+	pub max_length: u32,
+}
+
+/// The transactions a block built on top of `parent_hash` would contain.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(bound(
+	serialize = "Hash: Serialize, TxHash: Serialize, Balance: std::fmt::Display",
+	deserialize = "Hash: Deserialize<'de>, TxHash: Deserialize<'de>, Balance: std::str::FromStr",
+))]
+pub struct Template<Hash, TxHash, Balance> {
+	/// Hash of the block the template is built on.
+	pub parent_hash: Hash,
+	/// Transactions of the template, in the order they would be included.
+	pub transactions: Vec<TemplateTransaction<TxHash, Balance>>,
+	/// Total weight of the transactions.
+	pub total_weight: u64,
+	/// Total encoded length of the transactions.
+	pub total_length: u32,
+}
+
+/// A transaction of a [`Template`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(bound(
+	serialize = "TxHash: Serialize, Balance: std::fmt::Display",
+	deserialize = "TxHash: Deserialize<'de>, Balance: std::str::FromStr",
+))]
+pub struct TemplateTransaction<TxHash, Balance> {
+	/// Hash of the transaction.
+	pub hash: TxHash,
+	/// The encoded transaction.
+	pub extrinsic: Bytes,
+	/// Priority of the transaction in the pool.
+	pub priority: TransactionPriority,
+	/// Encoded length of the transaction.
+	pub length: u32,
+	/// Weight, class and inclusion fee of the transaction.
+	#[serde(flatten)]
+	pub info: RuntimeDispatchInfo<Balance>,
+}
+
+/// A struct that implements the [`BlockTemplateApi`].
+pub struct BlockTemplate<C, P, Extrinsic> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	limits: BlockLimits,
+	deny_unsafe: DenyUnsafe,
+	_marker: std::marker::PhantomData<Extrinsic>,
+}
+
+impl<C, P, Extrinsic> BlockTemplate<C, P, Extrinsic> {
+	/// Create new `BlockTemplate` with the given reference to the client and the pool.
+	pub fn new(client: Arc<C>, pool: Arc<P>, limits: BlockLimits, deny_unsafe: DenyUnsafe) -> Self {
+		BlockTemplate { client, pool, limits, deny_unsafe, _marker: Default::default() }
+	}
+}
+
+impl<C, P, Balance, Extrinsic> BlockTemplateApi<Template<BlockHash<P>, TxHash<P>, Balance>>
+	for BlockTemplate<C, P, Extrinsic>
+where
+	P: TransactionPool + 'static,
+	C: Send + Sync + 'static + ProvideRuntimeApi<P::Block> + HeaderBackend<P::Block>,
+	C::Api: TransactionPaymentRuntimeApi<P::Block, Balance, Extrinsic>,
+	Balance: Codec + MaybeDisplay + MaybeFromStr,
+	Extrinsic: Codec + Send + Sync + 'static,
+{
+	fn block_template(&self) -> Result<Template<BlockHash<P>, TxHash<P>, Balance>> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let api = self.client.runtime_api();
+		let parent_hash = self.client.info().best_hash;
+		let at = BlockId::hash(parent_hash);
+
+		let mut template = Template {
+			parent_hash,
+			transactions: Vec::new(),
+			total_weight: 0,
+			total_length: 0,
+		};
+		// Tags provided by the transactions left out of the template. The transactions requiring
+		// any of them can't be included either.
+		let mut skipped_tags = HashSet::new();
+
+		for tx in self.pool.ready() {
+			if tx.requires().iter().any(|tag| skipped_tags.contains(tag)) {
+				skipped_tags.extend(tx.provides().iter().cloned());
+				continue;
+			}
+
+			let encoded = tx.data().encode();
+			let length = encoded.len() as u32;
+			let uxt: Extrinsic = Decode::decode(&mut &*encoded).map_err(|e| RpcError {
+				code: ErrorCode::ServerError(Error::DecodeError.into()),
+				message: "Unable to build block template.".into(),
+				data: Some(format!("{:?}", e).into()),
+			})?;
+			let info = api.query_info(&at, uxt, length).map_err(|e| RpcError {
+				code: ErrorCode::ServerError(Error::RuntimeError.into()),
+				message: "Unable to build block template.".into(),
+				data: Some(format!("{:?}", e).into()),
+			})?;
+
+			let total_weight = template.total_weight.saturating_add(info.weight);
+			let total_length = template.total_length.saturating_add(length);
+			if total_weight > self.limits.max_weight || total_length > self.limits.max_length {
+				skipped_tags.extend(tx.provides().iter().cloned());
+				continue;
+			}
+
+			template.total_weight = total_weight;
+			template.total_length = total_length;
+			template.transactions.push(TemplateTransaction {
+				hash: tx.hash().clone(),
+				extrinsic: encoded.into(),
+				priority: *tx.priority(),
+				length,
+				info,
+			});
+		}
+
+		Ok(template)
+	}
+}
@@ -0,0 +1,218 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC interface returning statistics about the priorities of recent and pending transactions.
+//!
+//! The priority of a signed transaction is set by `ChargeTransactionPayment` from its fee,
+//! including the tip, so the statistics tell which tip a transaction needs to compete.
+
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use sp_blockchain::HeaderBackend;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_runtime::{
+	generic::BlockId, traits::{Block as BlockT, Header as HeaderT, Zero},
+	transaction_validity::TransactionPriority,
+};
+use sp_api::ProvideRuntimeApi;
+use sp_transaction_pool::{
+	TransactionPool, InPoolTransaction, BlockHash, runtime_api::TaggedTransactionQueue,
+};
+use sc_client_api::BlockBackend;
+use sc_rpc_api::DenyUnsafe;
+use crate::Error;
+
+pub use self::gen_client::Client as FeeStatisticsClient;
+
+/// Number of blocks the statistics are computed over by default.
+const DEFAULT_BLOCKS: u32 = 10;
+/// Maximum number of blocks the statistics can be computed over.
+const MAX_BLOCKS: u32 = 100;
+
+#[rpc]
+pub trait FeeStatisticsApi<BlockHash, ResponseType> {
+	/// Returns percentiles of the priorities of the transactions included in the last `blocks`
+	/// blocks up to `at`, and of the ready transactions of the pool.
+	///
+	/// `blocks` defaults to 10 and is capped to 100. `at` defaults to the best block.
+	///
+	/// This RPC is unsafe, since it executes the runtime for every transaction of the blocks.
+	#[rpc(name = "payment_feeStatistics")]
+	fn fee_statistics(&self, blocks: Option<u32>, at: Option<BlockHash>) -> Result<ResponseType>;
+}
+
+/// Percentiles of a set of priorities.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Percentiles {
+	/// Number of transactions.
+	pub count: u32,
+	/// Lowest priority.
+	pub min: TransactionPriority,
+	/// 25th percentile.
+	pub p25: TransactionPriority,
+	/// 50th percentile.
+	pub median: TransactionPriority,
+	/// 75th percentile.
+	pub p75: TransactionPriority,
+	/// 90th percentile.
+	pub p90: TransactionPriority,
+	/// Highest priority.
+	pub max: TransactionPriority,
+}
+
+impl Percentiles {
+	/// Compute the percentiles of `values`, or `None` if there are none.
+	pub fn new(mut values: Vec<TransactionPriority>) -> Option<Self> {
+		if values.is_empty() {
+			return None;
+		}
+
+		values.sort();
+		let percentile = |p: usize| values[(values.len() - 1) * p / 100];
+		Some(Percentiles {
+			count: values.len() as u32,
+			min: values[0],
+			p25: percentile(25),
+			median: percentile(50),
+			p75: percentile(75),
+			p90: percentile(90),
+			max: values[values.len() - 1],
+		})
+	}
+}
+
+/// Statistics returned by `payment_feeStatistics`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Statistics<Hash> {
+	/// Hash of the last block of the range.
+	pub at: Hash,
+	/// Number of blocks the included transactions were collected from.
+	pub blocks: u32,
+	/// Priorities of the transactions included in the blocks. Inherents and transactions that
+	/// are no longer valid at the parent of their block are left out.
+	pub included: Option<Percentiles>,
+	/// Priorities of the ready transactions of the pool.
+	pub pending: Option<Percentiles>,
+}
+
+/// A struct that implements the [`FeeStatisticsApi`].
+pub struct FeeStatistics<C, P> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<C, P> FeeStatistics<C, P> {
+	/// Create new `FeeStatistics` with the given reference to the client and the pool.
+	pub fn new(client: Arc<C>, pool: Arc<P>, deny_unsafe: DenyUnsafe) -> Self {
+		FeeStatistics { client, pool, deny_unsafe }
+	}
+}
+
+impl<C, P> FeeStatisticsApi<BlockHash<P>, Statistics<BlockHash<P>>> for FeeStatistics<C, P>
+where
+	P: TransactionPool + 'static,
+	C: Send + Sync + 'static + ProvideRuntimeApi<P::Block> + HeaderBackend<P::Block>
+		+ BlockBackend<P::Block>,
+	C::Api: TaggedTransactionQueue<P::Block>,
+{
+	fn fee_statistics(
+		&self,
+		blocks: Option<u32>,
+		at: Option<BlockHash<P>>,
+	) -> Result<Statistics<BlockHash<P>>> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let max_blocks = std::cmp::min(blocks.unwrap_or(DEFAULT_BLOCKS), MAX_BLOCKS);
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		let mut included = Vec::new();
+		let mut walked = 0;
+		let mut hash = at;
+		while walked < max_blocks {
+			let id = BlockId::Hash(hash);
+			let header = match self.client.header(id).map_err(client_err)? {
+				Some(header) => header,
+				None => break,
+			};
+			if header.number().is_zero() {
+				break;
+			}
+			// The body is missing if it was pruned.
+			let body = match self.client.block_body(&id).map_err(client_err)? {
+				Some(body) => body,
+				None => break,
+			};
+
+			let parent = BlockId::Hash(*header.parent_hash());
+			for xt in body {
+				// Validation may write to the overlay of the runtime API, so each transaction
+				// gets a fresh one, like in the pool.
+				let validity = self.client.runtime_api()
+					.validate_transaction(&parent, xt)
+					.map_err(|e| RpcError {
+						code: ErrorCode::ServerError(Error::RuntimeError.into()),
+						message: "Unable to compute fee statistics.".into(),
+						data: Some(format!("{:?}", e).into()),
+					})?;
+				if let Ok(valid) = validity {
+					included.push(valid.priority);
+				}
+			}
+
+			walked += 1;
+			hash = *header.parent_hash();
+		}
+
+		let pending = self.pool.ready().map(|tx| *tx.priority()).collect();
+
+		Ok(Statistics {
+			at,
+			blocks: walked,
+			included: Percentiles::new(included),
+			pending: Percentiles::new(pending),
+		})
+	}
+}
+
+fn client_err(e: sp_blockchain::Error) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: "Unable to compute fee statistics.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn percentiles_of_priorities() {
+		assert_eq!(Percentiles::new(Vec::new()), None);
+		assert_eq!(
+			Percentiles::new((1..=10).rev().collect()),
+			Some(Percentiles { count: 10, min: 1, p25: 3, median: 5, p75: 7, p90: 9, max: 10 }),
+		);
+		assert_eq!(
+			Percentiles::new(vec![42]),
+			Some(Percentiles { count: 1, min: 42, p25: 42, median: 42, p75: 42, p90: 42, max: 42 }),
+		);
+	}
+}
@@ -27,6 +27,15 @@ use sp_core::Bytes;
 use pallet_transaction_payment_rpc_runtime_api::RuntimeDispatchInfo;
 pub use pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi as TransactionPaymentRuntimeApi;
 pub use self::gen_client::Client as TransactionPaymentClient;
+pub use self::block_template::{
+	BlockTemplateApi, BlockTemplateClient, BlockTemplate, BlockLimits, Template, TemplateTransaction,
+};
+pub use self::fee_statistics::{
+	FeeStatisticsApi, FeeStatisticsClient, FeeStatistics, Statistics, Percentiles,
+};
+
+mod block_template;
+mod fee_statistics;
 
 #[rpc]
 pub trait TransactionPaymentApi<BlockHash, ResponseType> {
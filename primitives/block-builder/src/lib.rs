@@ -43,4 +43,12 @@ sp_api::decl_runtime_apis! {
 		/// Generate a random seed.
 		fn random_seed() -> <Block as BlockT>::Hash;
 	}
+
+	/// The `BlockWeight` api trait that reports the weight declared by extrinsics, which nodes
+	/// compare with the time the blocks containing them take to execute.
+	pub trait BlockWeight {
+		/// Sum of the weights declared by the given extrinsics. Weight consumed outside of the
+		/// extrinsics, such as by the block initialization, isn't included.
+		fn extrinsics_weight(extrinsics: sp_std::vec::Vec<<Block as BlockT>::Extrinsic>) -> u64;
+	}
 }
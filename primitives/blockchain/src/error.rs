@@ -42,6 +42,48 @@ pub enum ApplyExtrinsicFailed {
 	Msg(String),
 }
 
+/// Category of an error, identified by a stable code.
+///
+/// The code is used as the exit code of the node and in the code of the RPC errors, so that
+/// automation can react to specific failures without parsing the messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+	/// Uncategorized error.
+	Other = 1,
+	/// Invalid configuration, e.g. command line or chain spec.
+	Config = 10,
+	/// Networking error, including the failed requests of light clients.
+	Network = 11,
+	/// Consensus error, e.g. invalid justification or authorities.
+	Consensus = 12,
+	/// Keystore error.
+	Keystore = 13,
+	/// Database error, or data missing from the database.
+	Database = 14,
+	/// Error executing the runtime.
+	Runtime = 15,
+}
+
+impl ErrorKind {
+	/// The code identifying the category.
+	pub fn code(self) -> u8 {
+		self as u8
+	}
+
+	/// The name of the category.
+	pub fn name(self) -> &'static str {
+		match self {
+			ErrorKind::Other => "other",
+			ErrorKind::Config => "config",
+			ErrorKind::Network => "network",
+			ErrorKind::Consensus => "consensus",
+			ErrorKind::Keystore => "keystore",
+			ErrorKind::Database => "database",
+			ErrorKind::Runtime => "runtime",
+		}
+	}
+}
+
 /// Substrate Client error
 #[derive(Debug, Display, From)]
 pub enum Error {
@@ -160,4 +202,37 @@ impl Error {
 	pub fn from_state(e: Box<dyn sp_state_machine::Error>) -> Self {
 		Error::Execution(e)
 	}
+
+	/// The category of the error.
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			Error::Blockchain(e) => e.kind(),
+			Error::Consensus(_) |
+			Error::InvalidAuthoritiesSet |
+			Error::JustificationDecode |
+			Error::BadJustification(_) |
+			Error::NonSequentialFinalization(_) |
+			Error::NotInFinalizedChain |
+			Error::IncompletePipeline => ErrorKind::Consensus,
+			Error::Backend(_) |
+			Error::UnknownBlock(_) |
+			Error::ErrorReadingChangesTriesConfig |
+			Error::ChangesTrieAccessFailed(_) |
+			Error::MissingHashRequiredForCHT => ErrorKind::Database,
+			Error::ApplyExtrinsicFailed(_) |
+			Error::Execution(_) |
+			Error::VersionInvalid(_) |
+			Error::CallResultDecode(..) |
+			Error::RuntimeParamConversion(_) |
+			Error::ChangesTriesNotSupported |
+			Error::InvalidStateRoot => ErrorKind::Runtime,
+			Error::NotAvailableOnLightClient |
+			Error::InvalidCHTProof |
+			Error::RemoteFetchCancelled |
+			Error::RemoteFetchFailed => ErrorKind::Network,
+			Error::GenesisInvalid => ErrorKind::Config,
+			Error::TransactionPoolNotReady |
+			Error::Msg(_) => ErrorKind::Other,
+		}
+	}
 }
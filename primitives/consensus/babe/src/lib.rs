@@ -24,8 +24,8 @@ pub mod inherents;
 
 use codec::{Encode, Decode};
 use sp_std::vec::Vec;
-use sp_runtime::{ConsensusEngineId, RuntimeDebug};
-use crate::digests::NextEpochDescriptor;
+use sp_runtime::{ConsensusEngineId, RuntimeDebug, traits::Block as BlockT};
+use crate::digests::{NextEpochDescriptor, NextConfigDescriptor};
 
 mod app {
 	use sp_application_crypto::{app_crypto, key_types::BABE, sr25519};
@@ -78,6 +78,19 @@ pub type BabeAuthorityWeight = u64;
 /// The weight of a BABE block.
 pub type BabeBlockWeight = u32;
 
+/// Proof that an authority equivocated, i.e. authored two different blocks in the same slot.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug)]
+pub struct EquivocationProof<Header> {
+	/// The authority that equivocated.
+	pub offender: AuthorityId,
+	/// The slot in which both blocks were authored.
+	pub slot_number: SlotNumber,
+	/// The header of the first block seen.
+	pub first_header: Header,
+	/// The header of the second block seen.
+	pub second_header: Header,
+}
+
 /// An consensus log item for BABE.
 #[derive(Decode, Encode, Clone, PartialEq, Eq)]
 pub enum ConsensusLog {
@@ -89,6 +102,10 @@ pub enum ConsensusLog {
 	/// Disable the authority with given index.
 	#[codec(index = "2")]
 	OnDisabled(AuthorityIndex),
+	/// The epoch has changed, and the epoch after the current one will
+	/// enact different epoch configurations.
+	#[codec(index = "3")]
+	NextConfigData(NextConfigDescriptor),
 }
 
 /// Configuration data used by the BABE consensus engine.
@@ -122,6 +139,33 @@ pub struct BabeConfiguration {
 	pub secondary_slots: bool,
 }
 
+impl BabeConfiguration {
+	/// The epoch configuration that is in effect at genesis.
+	pub fn genesis_epoch_configuration(&self) -> BabeEpochConfiguration {
+		BabeEpochConfiguration {
+			c: self.c,
+			secondary_slots: self.secondary_slots,
+		}
+	}
+}
+
+/// Configuration data used by the BABE consensus engine that may change
+/// with epoch changes.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug)]
+pub struct BabeEpochConfiguration {
+	/// A constant value that is used in the threshold calculation formula.
+	/// Expressed as a rational where the first member of the tuple is the
+	/// numerator and the second is the denominator. The rational should
+	/// represent a value between 0 and 1.
+	/// In the threshold formula calculation, `1 - c` represents the probability
+	/// of a slot being empty.
+	pub c: (u64, u64),
+
+	/// Whether this epoch should run with secondary slots, which are assigned
+	/// in round-robin manner.
+	pub secondary_slots: bool,
+}
+
 #[cfg(feature = "std")]
 impl sp_consensus::SlotData for BabeConfiguration {
 	fn slot_duration(&self) -> u64 {
@@ -131,8 +175,53 @@ impl sp_consensus::SlotData for BabeConfiguration {
 	const SLOT_KEY: &'static [u8] = b"babe_configuration";
 }
 
+/// Whether the authoring worker claims the secondary slots, in the epochs that allow them.
+///
+/// Skipping them lets a chain be authored on the primary slots only, e.g. while staging the
+/// transition to a runtime configuration without secondary slots.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SecondarySlots {
+	/// Claim the secondary slot when the primary slot can't be claimed.
+	Claim,
+	/// Only claim the primary slots.
+	Skip,
+}
+
+#[cfg(feature = "std")]
+impl SecondarySlots {
+	/// The names of the policies, as parsed by `from_str`.
+	pub const VARIANTS: [&'static str; 2] = ["claim", "skip"];
+}
+
+#[cfg(feature = "std")]
+impl Default for SecondarySlots {
+	fn default() -> Self {
+		SecondarySlots::Claim
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::str::FromStr for SecondarySlots {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"claim" => Ok(SecondarySlots::Claim),
+			"skip" => Ok(SecondarySlots::Skip),
+			_ => Err(format!(
+				"Invalid secondary slots policy {}, expected one of: {}",
+				s,
+				Self::VARIANTS.join(", "),
+			)),
+		}
+	}
+}
+
 sp_api::decl_runtime_apis! {
 	/// API necessary for block authorship with BABE.
+	#[api_version(2)]
 	pub trait BabeApi {
 		/// Return the configuration for BABE. Currently,
 		/// only the value provided by this type at genesis will be used.
@@ -142,5 +231,12 @@ sp_api::decl_runtime_apis! {
 
 		/// Returns the slot number that started the current epoch.
 		fn current_epoch_start() -> SlotNumber;
+
+		/// Returns the unsigned extrinsic reporting the equivocation proven by
+		/// `equivocation_proof`, to be submitted to the transaction pool. `None` if the runtime
+		/// doesn't punish equivocations.
+		fn report_equivocation_extrinsic(
+			equivocation_proof: EquivocationProof<<Block as BlockT>::Header>,
+		) -> Option<<Block as BlockT>::Extrinsic>;
 	}
 }
@@ -37,13 +37,20 @@ impl<T: Chain<B>, B: Block> Chain<B> for Arc<T> {
 pub enum Validation {
 	/// Valid block announcement.
 	Success,
-	/// Invalid block announcement.
-	Failure,
+	/// Invalid block announcement. The block isn't requested, and the reputation of the peer
+	/// that announced it is lowered, so that it is disconnected from if it keeps sending invalid
+	/// announcements.
+	Failure {
+		/// If true, the peer is also disconnected from right away.
+		disconnect: bool,
+	},
 }
 
 /// Type which checks incoming block announcements.
 pub trait BlockAnnounceValidator<B: Block> {
 	/// Validate the announced header and its associated data.
+	///
+	/// The blocks we already know of aren't validated again.
 	fn validate(&mut self, header: &B::Header, data: &[u8]) -> Result<Validation, Box<dyn Error + Send>>;
 }
 
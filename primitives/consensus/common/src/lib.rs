@@ -173,6 +173,11 @@ pub trait SyncOracle {
 	/// Whether the synchronization service is offline.
 	/// Returns true if so.
 	fn is_offline(&mut self) -> bool;
+	/// Whether the node is connected to peers, but no longer receives blocks from them, and
+	/// likely builds a fork of its own. Returns true if so.
+	fn is_isolated(&mut self) -> bool {
+		false
+	}
 }
 
 /// A synchronization oracle for when there is no network.
@@ -192,6 +197,10 @@ impl<T> SyncOracle for Arc<T> where T: ?Sized, for<'r> &'r T: SyncOracle {
 	fn is_offline(&mut self) -> bool {
 		<&T>::is_offline(&mut &**self)
 	}
+
+	fn is_isolated(&mut self) -> bool {
+		<&T>::is_isolated(&mut &**self)
+	}
 }
 
 /// Checks if the current active native block authoring implementation can author with the runtime
@@ -425,6 +425,41 @@ impl AsRef<schnorrkel::Keypair> for Pair {
 	}
 }
 
+/// Verify a batch of signatures.
+///
+/// All signatures must be valid for `true` to be returned. Signatures in the deprecated
+/// pre-audit format can't be batched, and make the whole batch verified one by one.
+#[cfg(feature = "std")]
+pub fn verify_batch(
+	messages: Vec<&[u8]>,
+	signatures: Vec<&Signature>,
+	pub_keys: Vec<&Public>,
+) -> bool {
+	if messages.len() != signatures.len() || messages.len() != pub_keys.len() {
+		return false;
+	}
+	if messages.is_empty() {
+		return true;
+	}
+
+	let sr_signatures = signatures.iter()
+		.map(|signature| schnorrkel::Signature::from_bytes(signature.as_ref()))
+		.collect::<Result<Vec<_>, _>>();
+	let sr_pub_keys = pub_keys.iter()
+		.map(|pub_key| PublicKey::from_bytes(pub_key.as_ref()))
+		.collect::<Result<Vec<_>, _>>();
+
+	match (sr_signatures, sr_pub_keys) {
+		(Ok(sr_signatures), Ok(sr_pub_keys)) => {
+			let transcripts = messages.iter()
+				.map(|message| signing_context(SIGNING_CTX).bytes(message));
+			schnorrkel::verify_batch(transcripts, &sr_signatures, &sr_pub_keys, true).is_ok()
+		},
+		_ => messages.iter().zip(signatures).zip(pub_keys)
+			.all(|((message, signature), pub_key)| Pair::verify(signature, message, pub_key)),
+	}
+}
+
 /// Derive a single hard junction.
 #[cfg(feature = "full_crypto")]
 fn derive_hard_junction(secret: &SecretKey, cc: &[u8; CHAIN_CODE_LENGTH]) -> MiniSecretKey {
@@ -790,6 +825,25 @@ mod test {
 		assert!(Pair::verify(&js_signature, b"SUBSTRATE", &public));
 	}
 
+	#[test]
+	fn verify_batch_works() {
+		let pair = Pair::from_seed(b"12345678901234567890123456789012");
+		let public = pair.public();
+		let messages = vec![&b"first"[..], &b"second"[..], &b"third"[..]];
+		let signatures: Vec<_> = messages.iter().map(|message| pair.sign(message)).collect();
+
+		assert!(verify_batch(
+			messages.clone(),
+			signatures.iter().collect(),
+			vec![&public; 3],
+		));
+		assert!(!verify_batch(
+			vec![&b"first"[..], &b"second"[..], &b"forged"[..]],
+			signatures.iter().collect(),
+			vec![&public; 3],
+		));
+	}
+
 	#[test]
 	fn signature_serialization_works() {
 		let pair = Pair::from_seed(b"12345678901234567890123456789012");
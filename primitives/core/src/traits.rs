@@ -72,6 +72,26 @@ pub trait BareCryptoStore: Send + Sync {
 	///
 	/// Returns `true` iff all private keys could be found.
 	fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool;
+
+	/// Start a batch of key insertions on the current thread.
+	///
+	/// The keys generated or inserted by the current thread until the batch is committed or
+	/// aborted are either all placed into the file system store, or none of them is, even if the
+	/// node crashes in between. Only the current thread can commit or abort the batch. The
+	/// default implementation does nothing.
+	fn begin_batch(&mut self) -> Result<(), String> {
+		Ok(())
+	}
+
+	/// Place the keys of the current batch into the file system store.
+	fn commit_batch(&mut self) -> Result<(), String> {
+		Ok(())
+	}
+
+	/// Discard the keys of the current batch.
+	fn abort_batch(&mut self) -> Result<(), String> {
+		Ok(())
+	}
 }
 
 /// A pointer to the key store.
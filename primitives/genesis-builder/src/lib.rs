@@ -0,0 +1,40 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The genesis builder runtime api primitives.
+//!
+//! A runtime implementing `GenesisBuilder` builds its genesis storage from a JSON config, so that
+//! the genesis of a modified runtime can be built from its Wasm code alone, without a node
+//! compiled with the matching native runtime (see the `build-genesis-state` command).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(missing_docs)]
+
+use sp_std::vec::Vec;
+
+/// Name of the runtime function building the genesis storage.
+pub const BUILD_CONFIG: &str = "GenesisBuilder_build_config";
+
+/// Result of building the genesis storage, with the UTF-8 error message on failure.
+pub type BuildResult = sp_std::result::Result<(), Vec<u8>>;
+
+sp_api::decl_runtime_apis! {
+	/// The genesis builder api.
+	pub trait GenesisBuilder {
+		/// Writes the genesis storage built from the JSON genesis `config` to the storage.
+		fn build_config(config: Vec<u8>) -> BuildResult;
+	}
+}
@@ -0,0 +1,206 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Batch verification of signatures, in parallel on the tasks executor of the host.
+
+use sp_core::{crypto::Pair, ed25519, sr25519, traits::CloneableSpawn};
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use futures::{channel::oneshot, future::{self, join_all}, task::SpawnExt};
+
+/// Number of signatures of the same scheme verified by a single task.
+const BATCH_SIZE: usize = 128;
+
+struct Ed25519Item {
+	signature: ed25519::Signature,
+	pub_key: ed25519::Public,
+	message: Vec<u8>,
+}
+
+struct Sr25519Item {
+	signature: sr25519::Signature,
+	pub_key: sr25519::Public,
+	message: Vec<u8>,
+}
+
+/// Signatures verified by a single task.
+enum Batch {
+	Ed25519(Vec<Ed25519Item>),
+	Sr25519(Vec<Sr25519Item>),
+}
+
+impl Batch {
+	fn verify(&self) -> bool {
+		match self {
+			Batch::Ed25519(items) => items.iter().all(|item|
+				ed25519::Pair::verify(&item.signature, &item.message, &item.pub_key)
+			),
+			Batch::Sr25519(items) => sr25519::verify_batch(
+				items.iter().map(|item| &item.message[..]).collect(),
+				items.iter().map(|item| &item.signature).collect(),
+				items.iter().map(|item| &item.pub_key).collect(),
+			),
+		}
+	}
+}
+
+/// Accumulates signatures to verify, and verifies them in batches.
+///
+/// Every `BATCH_SIZE` signatures of a scheme, a task verifying them is spawned. The result is
+/// only known after [`verify_and_clear`](BatchVerifier::verify_and_clear), which verifies the
+/// remaining signatures and waits for all the tasks.
+pub struct BatchVerifier {
+	scheduler: Box<dyn CloneableSpawn>,
+	ed25519_items: Vec<Ed25519Item>,
+	sr25519_items: Vec<Sr25519Item>,
+	/// Set once any signature is known to be invalid.
+	invalid: Arc<AtomicBool>,
+	/// Completion of the spawned tasks.
+	pending_tasks: Vec<oneshot::Receiver<()>>,
+}
+
+impl BatchVerifier {
+	/// Creates a new batch verifier spawning its tasks on `scheduler`.
+	pub fn new(scheduler: Box<dyn CloneableSpawn>) -> Self {
+		BatchVerifier {
+			scheduler,
+			ed25519_items: Vec::new(),
+			sr25519_items: Vec::new(),
+			invalid: Arc::new(AtomicBool::new(false)),
+			pending_tasks: Vec::new(),
+		}
+	}
+
+	/// Adds an `ed25519` signature to verify.
+	///
+	/// Returns `false` if a signature of the batch is already known to be invalid.
+	pub fn push_ed25519(
+		&mut self,
+		signature: ed25519::Signature,
+		pub_key: ed25519::Public,
+		message: Vec<u8>,
+	) -> bool {
+		if self.invalid.load(Ordering::Relaxed) {
+			return false;
+		}
+
+		self.ed25519_items.push(Ed25519Item { signature, pub_key, message });
+		if self.ed25519_items.len() >= BATCH_SIZE {
+			let items = std::mem::replace(&mut self.ed25519_items, Vec::new());
+			self.spawn_verification(Batch::Ed25519(items));
+		}
+		true
+	}
+
+	/// Adds an `sr25519` signature to verify.
+	///
+	/// Returns `false` if a signature of the batch is already known to be invalid.
+	pub fn push_sr25519(
+		&mut self,
+		signature: sr25519::Signature,
+		pub_key: sr25519::Public,
+		message: Vec<u8>,
+	) -> bool {
+		if self.invalid.load(Ordering::Relaxed) {
+			return false;
+		}
+
+		self.sr25519_items.push(Sr25519Item { signature, pub_key, message });
+		if self.sr25519_items.len() >= BATCH_SIZE {
+			let items = std::mem::replace(&mut self.sr25519_items, Vec::new());
+			self.spawn_verification(Batch::Sr25519(items));
+		}
+		true
+	}
+
+	/// Spawns a task verifying `batch`, which is verified right away if spawning fails.
+	fn spawn_verification(&mut self, batch: Batch) {
+		let batch = Arc::new(batch);
+		let (sender, receiver) = oneshot::channel();
+		let invalid = self.invalid.clone();
+		let task_batch = batch.clone();
+		let task = future::lazy(move |_| {
+			if !task_batch.verify() {
+				invalid.store(true, Ordering::Relaxed);
+			}
+			let _ = sender.send(());
+		});
+
+		match self.scheduler.spawn(task) {
+			Ok(()) => self.pending_tasks.push(receiver),
+			Err(e) => {
+				log::debug!(
+					target: "runtime",
+					"Failed to spawn batch verification task, verifying in place: {:?}",
+					e,
+				);
+				if !batch.verify() {
+					self.invalid.store(true, Ordering::Relaxed);
+				}
+			},
+		}
+	}
+
+	/// Verifies all the signatures pushed since the last call, and clears the verifier.
+	///
+	/// Returns `true` if all of them are valid.
+	pub fn verify_and_clear(&mut self) -> bool {
+		let ed25519_items = std::mem::replace(&mut self.ed25519_items, Vec::new());
+		let sr25519_items = std::mem::replace(&mut self.sr25519_items, Vec::new());
+		let pending = std::mem::replace(&mut self.pending_tasks, Vec::new());
+		let invalid = std::mem::replace(&mut self.invalid, Arc::new(AtomicBool::new(false)));
+
+		let remaining_valid = !invalid.load(Ordering::Relaxed)
+			&& Batch::Ed25519(ed25519_items).verify()
+			&& Batch::Sr25519(sr25519_items).verify();
+
+		// A task dropped by the executor before completion verified nothing.
+		let tasks_completed = futures::executor::block_on(join_all(pending))
+			.into_iter()
+			.all(|result| result.is_ok());
+
+		remaining_valid && tasks_completed && !invalid.load(Ordering::Relaxed)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn verifies_batches_and_detects_invalid_signature() {
+		let mut verifier = BatchVerifier::new(sp_core::tasks::executor());
+		let ed_pair = ed25519::Pair::from_seed(&[1; 32]);
+		let sr_pair = sr25519::Pair::from_seed(&[2; 32]);
+
+		for i in 0..BATCH_SIZE as u32 * 2 + 3 {
+			let message = i.to_le_bytes().to_vec();
+			let (ed_signature, sr_signature) = (ed_pair.sign(&message), sr_pair.sign(&message));
+			assert!(verifier.push_ed25519(ed_signature, ed_pair.public(), message.clone()));
+			assert!(verifier.push_sr25519(sr_signature, sr_pair.public(), message));
+		}
+		assert!(verifier.verify_and_clear());
+
+		for i in 0..BATCH_SIZE as u32 + 1 {
+			let message = i.to_le_bytes().to_vec();
+			verifier.push_sr25519(sr_pair.sign(&message), sr_pair.public(), message);
+		}
+		verifier.push_sr25519(sr_pair.sign(b"signed"), sr_pair.public(), b"not signed".to_vec());
+		assert!(!verifier.verify_and_clear());
+
+		// Cleared after verification.
+		assert!(verifier.verify_and_clear());
+	}
+}
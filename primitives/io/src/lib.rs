@@ -34,7 +34,7 @@ use sp_std::ops::Deref;
 #[cfg(feature = "std")]
 use sp_core::{
 	crypto::Pair,
-	traits::{KeystoreExt, CallInWasmExt},
+	traits::{KeystoreExt, CallInWasmExt, TaskExecutorExt},
 	offchain::{OffchainExt, TransactionPoolExt},
 	hexdisplay::HexDisplay,
 	storage::{ChildStorageKey, ChildInfo},
@@ -57,6 +57,12 @@ use codec::{Encode, Decode};
 #[cfg(feature = "std")]
 use sp_externalities::{ExternalitiesExt, Externalities};
 
+#[cfg(feature = "std")]
+mod batch_verifier;
+
+#[cfg(feature = "std")]
+use batch_verifier::BatchVerifier;
+
 /// Error verifying ECDSA signature
 #[derive(Encode, Decode)]
 pub enum EcdsaVerifyError {
@@ -371,6 +377,12 @@ pub trait Misc {
 	}
 }
 
+#[cfg(feature = "std")]
+sp_externalities::decl_extension! {
+	/// Batch verification extension to register/retrieve from the externalities.
+	pub struct VerificationExt(BatchVerifier);
+}
+
 /// Interfaces for working with crypto related types from within the runtime.
 #[runtime_interface]
 pub trait Crypto {
@@ -426,6 +438,26 @@ pub trait Crypto {
 		ed25519::Pair::verify(sig, msg, pub_key)
 	}
 
+	/// Register an `ed25519` signature for batch verification.
+	///
+	/// Batch verification must be enabled by calling [`start_batch_verify`].
+	/// If batch verification is not enabled, the signature will be verified immediately.
+	/// To get the result of the batch verification, [`finish_batch_verify`]
+	/// needs to be called.
+	///
+	/// Returns `true` when the verification is either successful or batched.
+	fn ed25519_batch_verify(
+		&mut self,
+		sig: &ed25519::Signature,
+		msg: &[u8],
+		pub_key: &ed25519::Public,
+	) -> bool {
+		match self.extension::<VerificationExt>() {
+			Some(verifier) => verifier.push_ed25519(sig.clone(), pub_key.clone(), msg.to_vec()),
+			None => ed25519::Pair::verify(sig, msg, pub_key),
+		}
+	}
+
 	/// Returns all `sr25519` public keys for the given key id from the keystore.
 	fn sr25519_public_keys(&mut self, id: KeyTypeId) -> Vec<sr25519::Public> {
 		self.extension::<KeystoreExt>()
@@ -473,6 +505,60 @@ pub trait Crypto {
 		sr25519::Pair::verify(sig, msg, pubkey)
 	}
 
+	/// Register an `sr25519` signature for batch verification.
+	///
+	/// Batch verification must be enabled by calling [`start_batch_verify`].
+	/// If batch verification is not enabled, the signature will be verified immediately.
+	/// To get the result of the batch verification, [`finish_batch_verify`]
+	/// needs to be called.
+	///
+	/// Returns `true` when the verification is either successful or batched.
+	fn sr25519_batch_verify(
+		&mut self,
+		sig: &sr25519::Signature,
+		msg: &[u8],
+		pub_key: &sr25519::Public,
+	) -> bool {
+		match self.extension::<VerificationExt>() {
+			Some(verifier) => verifier.push_sr25519(sig.clone(), pub_key.clone(), msg.to_vec()),
+			None => sr25519::Pair::verify(sig, msg, pub_key),
+		}
+	}
+
+	/// Start verification extension.
+	///
+	/// Signatures registered with [`ed25519_batch_verify`] and [`sr25519_batch_verify`] are
+	/// then verified in parallel, on the tasks executor of the host. Without a tasks executor,
+	/// they keep being verified immediately.
+	fn start_batch_verify(&mut self) {
+		let scheduler = match self.extension::<TaskExecutorExt>() {
+			Some(executor) => sp_core::traits::CloneableSpawn::clone(&*executor.0),
+			None => return,
+		};
+
+		self.register_extension(VerificationExt(BatchVerifier::new(scheduler)))
+			.expect("Failed to register required extension: `VerificationExt`");
+	}
+
+	/// Finish batch-verification of signatures.
+	///
+	/// Verify or wait for verification to finish for all signatures which were previously
+	/// deferred by `sr25519_batch_verify`/`ed25519_batch_verify`.
+	///
+	/// Returns `true` if all of them are valid, or if batch verification wasn't started.
+	fn finish_batch_verify(&mut self) -> bool {
+		let result = match self.extension::<VerificationExt>() {
+			Some(verifier) => verifier.verify_and_clear(),
+			// Batch verification is not available, all the signatures were verified already.
+			None => return true,
+		};
+
+		self.deregister_extension::<VerificationExt>()
+			.expect("No verification extension in current context!");
+
+		result
+	}
+
 	/// Verify and recover a SECP256k1 ECDSA signature.
 	///
 	/// - `sig` is passed in RSV format. V should be either `0/1` or `27/28`.
@@ -19,9 +19,24 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
 
+use sp_runtime::offchain::StorageKind;
+
 /// Local Storage Prefix used by the Offchain Worker API to
 pub const STORAGE_PREFIX: &[u8] = b"storage";
 
+/// Prefix of the values of the `LOCAL` storage kind in the offchain database.
+///
+/// NOTE the values aren't fork-aware yet: they are kept when the block that set them is reverted.
+pub const LOCAL_STORAGE_PREFIX: &[u8] = b"local-storage";
+
+/// Returns the prefix of the values of the given storage kind in the offchain database.
+pub fn storage_prefix(kind: StorageKind) -> &'static [u8] {
+	match kind {
+		StorageKind::PERSISTENT => STORAGE_PREFIX,
+		StorageKind::LOCAL => LOCAL_STORAGE_PREFIX,
+	}
+}
+
 sp_api::decl_runtime_apis! {
 	/// The offchain worker api.
 	#[api_version(2)]
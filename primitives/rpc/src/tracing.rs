@@ -0,0 +1,86 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Types of the traces of block executions.
+
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+
+/// Trace of the execution of a block.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTrace {
+	/// The spans entered during the execution, in the order they were closed.
+	pub spans: Vec<Span>,
+	/// The storage accesses, in the order they were made.
+	pub storage: Vec<StorageAccess>,
+}
+
+/// A span entered during the execution of a block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Span {
+	/// Id of the span, unique within the trace.
+	pub id: u64,
+	/// Id of the parent span, if any.
+	pub parent_id: Option<u64>,
+	/// Name of the span.
+	pub name: String,
+	/// Target of the span, usually the module it was created in.
+	pub target: String,
+	/// Line of the span in its source file.
+	pub line: u32,
+	/// Time spent in the span, in nanoseconds.
+	pub time: u64,
+	/// Values recorded with the span.
+	pub values: BTreeMap<String, String>,
+}
+
+/// A storage access made during the execution of a block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageAccess {
+	/// Id of the innermost span the access was made in, if any.
+	pub span_id: Option<u64>,
+	/// The kind of access, e.g. `Get` or `Put`.
+	pub method: String,
+	/// The hex-encoded storage key of the child trie, if any.
+	pub child: Option<String>,
+	/// The hex-encoded key, or prefix for `ClearPrefix`.
+	pub key: String,
+	/// The hex-encoded value read or written, `None` if missing or removed.
+	pub value: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::assert_deser;
+
+	#[test]
+	fn should_serialize_and_deserialize() {
+		assert_deser(
+			r#"{"spanId":1,"method":"Get","child":null,"key":"0x01","value":"0x"}"#,
+			StorageAccess {
+				span_id: Some(1),
+				method: "Get".into(),
+				child: None,
+				key: "0x01".into(),
+				value: Some("0x".into()),
+			},
+		);
+	}
+}
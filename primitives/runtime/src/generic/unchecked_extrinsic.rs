@@ -126,7 +126,7 @@ where
 				let signed = lookup.lookup(signed)?;
 				let raw_payload = SignedPayload::new(self.function, extra)?;
 				if !raw_payload.using_encoded(|payload| {
-					signature.verify(payload, &signed)
+					signature.batch_verify(payload, &signed)
 				}) {
 					return Err(InvalidTransaction::BadProof.into())
 				}
@@ -311,6 +311,17 @@ impl Verify for MultiSignature {
 			}
 		}
 	}
+
+	fn batch_verify<L: Lazy<[u8]>>(&self, msg: L, signer: &AccountId32) -> bool {
+		use sp_core::crypto::Public;
+		match self {
+			MultiSignature::Ed25519(ref sig) =>
+				sig.batch_verify(msg, &ed25519::Public::from_slice(signer.as_ref())),
+			MultiSignature::Sr25519(ref sig) =>
+				sig.batch_verify(msg, &sr25519::Public::from_slice(signer.as_ref())),
+			MultiSignature::Ecdsa(_) => self.verify(msg, signer),
+		}
+	}
 }
 
 /// Signature verify that can work with any known signature types..
@@ -685,6 +696,29 @@ pub fn print(print: impl traits::Printable) {
 	print.print();
 }
 
+/// Batching session of the signatures verified with [`Verify::batch_verify`].
+///
+/// While the session lasts, `ed25519` and `sr25519` signatures are verified in parallel by the
+/// host, if it has a tasks executor. [`verify`](SignatureBatching::verify) ends the session and
+/// tells whether all of them were valid.
+#[must_use = "`verify` must be called to know whether the deferred signatures are valid"]
+pub struct SignatureBatching(());
+
+impl SignatureBatching {
+	/// Starts a batching session.
+	pub fn start() -> Self {
+		sp_io::crypto::start_batch_verify();
+		SignatureBatching(())
+	}
+
+	/// Ends the batching session, returning `true` if all the signatures deferred by it are
+	/// valid.
+	#[must_use]
+	pub fn verify(self) -> bool {
+		sp_io::crypto::finish_batch_verify()
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
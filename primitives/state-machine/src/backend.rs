@@ -223,6 +223,16 @@ pub trait Backend<H: Hasher>: std::fmt::Debug {
 	fn commit(&self, _storage_root: H::Out, _transaction: Self::Transaction) -> Result<(), Self::Error> {
 		unimplemented!()
 	}
+
+	/// Get the number of storage reads and writes since the last reset.
+	///
+	/// Backends which don't count them return `(0, 0)`.
+	fn read_write_count(&self) -> (u32, u32) {
+		(0, 0)
+	}
+
+	/// Reset the number of storage reads and writes.
+	fn reset_read_write_count(&self) {}
 }
 
 impl<'a, T: Backend<H>, H: Hasher> Backend<H> for &'a T {
@@ -311,6 +321,14 @@ impl<'a, T: Backend<H>, H: Hasher> Backend<H> for &'a T {
 	fn usage_info(&self) -> UsageInfo {
 		(*self).usage_info()
 	}
+
+	fn read_write_count(&self) -> (u32, u32) {
+		(*self).read_write_count()
+	}
+
+	fn reset_read_write_count(&self) {
+		(*self).reset_read_write_count()
+	}
  }
 
 /// Trait that allows consolidate two transactions together.
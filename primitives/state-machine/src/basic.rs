@@ -276,6 +276,12 @@ impl Externalities for BasicExternalities {
 	fn wipe(&mut self) {}
 
 	fn commit(&mut self) {}
+
+	fn read_write_count(&self) -> (u32, u32) {
+		(0, 0)
+	}
+
+	fn reset_read_write_count(&mut self) {}
 }
 
 impl sp_externalities::ExtensionStore for BasicExternalities {
@@ -283,6 +289,22 @@ impl sp_externalities::ExtensionStore for BasicExternalities {
 		warn!("Extensions are not supported by `BasicExternalities`.");
 		None
 	}
+
+	fn register_extension_with_type_id(
+		&mut self,
+		_: TypeId,
+		_: Box<dyn sp_externalities::Extension>,
+	) -> Result<(), sp_externalities::Error> {
+		warn!("Extensions are not supported by `BasicExternalities`.");
+		Err(sp_externalities::Error::ExtensionsAreNotSupported)
+	}
+
+	fn deregister_extension_by_type_id(
+		&mut self,
+		_: TypeId,
+	) -> Result<(), sp_externalities::Error> {
+		Err(sp_externalities::Error::ExtensionsAreNotSupported)
+	}
 }
 
 #[cfg(test)]
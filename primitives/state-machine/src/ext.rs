@@ -36,6 +36,34 @@ use log::{warn, trace};
 
 const EXT_NOT_ALLOWED_TO_FAIL: &str = "Externalities not allowed to fail within runtime";
 
+/// Target of the `tracing` events reporting the storage accesses, recorded when tracing the
+/// execution of a block.
+pub const STORAGE_ACCESS_TARGET: &str = "state";
+
+/// Displays optional bytes as `0x`-prefixed hex, and `None` as nothing.
+struct OptionalHex<'a>(Option<&'a [u8]>);
+
+impl<'a> fmt::Display for OptionalHex<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.0 {
+			Some(bytes) => write!(f, "0x{}", HexDisplay::from(&bytes)),
+			None => Ok(()),
+		}
+	}
+}
+
+/// Reports a storage access to the `tracing` subscriber. The values are only formatted when the
+/// subscriber records the event.
+fn trace_access(method: &'static str, child: Option<&[u8]>, key: &[u8], value: Option<&[u8]>) {
+	tracing::trace!(
+		target: STORAGE_ACCESS_TARGET,
+		method,
+		child = %OptionalHex(child),
+		key = %OptionalHex(Some(key)),
+		value = %OptionalHex(value),
+	);
+}
+
 /// Errors that can occur when interacting with the externalities.
 #[derive(Debug, Copy, Clone)]
 pub enum Error<B, E> {
@@ -161,6 +189,7 @@ where
 			HexDisplay::from(&key),
 			result.as_ref().map(HexDisplay::from)
 		);
+		trace_access("Get", None, key, result.as_ref().map(Vec::as_slice));
 		result
 	}
 
@@ -200,6 +229,8 @@ where
 			HexDisplay::from(&key),
 			result.as_ref().map(HexDisplay::from)
 		);
+		let value = result.as_ref().map(Vec::as_slice);
+		trace_access("GetChild", Some(storage_key.as_ref()), key, value);
 
 		result
 	}
@@ -319,6 +350,7 @@ where
 			HexDisplay::from(&key),
 			value.as_ref().map(HexDisplay::from)
 		);
+		trace_access("Put", None, &key, value.as_ref().map(Vec::as_slice));
 		let _guard = sp_panic_handler::AbortGuard::force_abort();
 		if is_child_storage_key(&key) {
 			warn!(target: "trie", "Refuse to directly set child storage key");
@@ -342,6 +374,8 @@ where
 			HexDisplay::from(&key),
 			value.as_ref().map(HexDisplay::from)
 		);
+		let traced_value = value.as_ref().map(Vec::as_slice);
+		trace_access("PutChild", Some(storage_key.as_ref()), &key, traced_value);
 		let _guard = sp_panic_handler::AbortGuard::force_abort();
 
 		self.mark_dirty();
@@ -357,6 +391,7 @@ where
 			self.id,
 			HexDisplay::from(&storage_key.as_ref()),
 		);
+		trace_access("KillChild", Some(storage_key.as_ref()), &[], None);
 		let _guard = sp_panic_handler::AbortGuard::force_abort();
 
 		self.mark_dirty();
@@ -371,6 +406,7 @@ where
 			self.id,
 			HexDisplay::from(&prefix),
 		);
+		trace_access("ClearPrefix", None, prefix, None);
 		let _guard = sp_panic_handler::AbortGuard::force_abort();
 		if is_child_storage_key(prefix) {
 			warn!(target: "trie", "Refuse to directly clear prefix that is part of child storage key");
@@ -395,6 +431,7 @@ where
 			HexDisplay::from(&storage_key.as_ref()),
 			HexDisplay::from(&prefix),
 		);
+		trace_access("ClearChildPrefix", Some(storage_key.as_ref()), prefix, None);
 		let _guard = sp_panic_handler::AbortGuard::force_abort();
 
 		self.mark_dirty();
@@ -537,6 +574,14 @@ where
 		).expect(EXT_NOT_ALLOWED_TO_FAIL);
 		self.storage_transaction_cache.reset();
 	}
+
+	fn read_write_count(&self) -> (u32, u32) {
+		self.backend.read_write_count()
+	}
+
+	fn reset_read_write_count(&mut self) {
+		self.backend.reset_read_write_count()
+	}
 }
 
 impl<'a, H, B, N> sp_externalities::ExtensionStore for Ext<'a, H, N, B>
@@ -548,6 +593,32 @@ where
 	fn extension_by_type_id(&mut self, type_id: TypeId) -> Option<&mut dyn Any> {
 		self.extensions.as_mut().and_then(|exts| exts.get_mut(type_id))
 	}
+
+	fn register_extension_with_type_id(
+		&mut self,
+		type_id: TypeId,
+		extension: Box<dyn sp_externalities::Extension>,
+	) -> Result<(), sp_externalities::Error> {
+		if let Some(ref mut extensions) = self.extensions {
+			extensions.register_with_type_id(type_id, extension)
+		} else {
+			Err(sp_externalities::Error::ExtensionsAreNotSupported)
+		}
+	}
+
+	fn deregister_extension_by_type_id(
+		&mut self,
+		type_id: TypeId,
+	) -> Result<(), sp_externalities::Error> {
+		if let Some(ref mut extensions) = self.extensions {
+			extensions
+				.deregister(type_id)
+				.map(drop)
+				.ok_or(sp_externalities::Error::ExtensionIsNotRegistered(type_id))
+		} else {
+			Err(sp_externalities::Error::ExtensionsAreNotSupported)
+		}
+	}
 }
 
 #[cfg(test)]
@@ -45,7 +45,7 @@ mod stats;
 pub use sp_trie::{trie_types::{Layout, TrieDBMut}, StorageProof, TrieMut, DBValue, MemoryDB};
 pub use testing::TestExternalities;
 pub use basic::BasicExternalities;
-pub use ext::Ext;
+pub use ext::{Ext, STORAGE_ACCESS_TARGET};
 pub use backend::Backend;
 pub use changes_trie::{
 	AnchorBlockId as ChangesTrieAnchorBlockId,
@@ -122,12 +122,26 @@ pub enum TransactionStatus<Hash, BlockHash> {
 	/// Transaction has been replaced in the pool, by another transaction
 	/// that provides the same tags. (e.g. same (sender, nonce)).
 	Usurped(Hash),
-	/// Transaction has been dropped from the pool because of the limit.
-	Dropped,
+	/// Transaction has been dropped from the pool for the given reason.
+	Dropped(DropReason),
 	/// Transaction is no longer valid in the current state.
 	Invalid,
 }
 
+/// Why a transaction was dropped from the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DropReason {
+	/// The pool reached its limits, and the transaction had the lowest priority.
+	LimitReached,
+	/// The transaction was removed to import a conflicting transaction of higher priority.
+	Conflict,
+	/// The transaction was in the future queue, which the pool doesn't keep at the moment.
+	FutureRejected,
+	/// The transaction couldn't be re-imported after the transactions it depends on were pruned.
+	ReimportFailed,
+}
+
 /// The stream of transaction events.
 pub type TransactionStatusStream<Hash, BlockHash> = dyn Stream<Item=TransactionStatus<Hash, BlockHash>> + Send + Unpin;
 
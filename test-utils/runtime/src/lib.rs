@@ -414,6 +414,7 @@ impl pallet_babe::Trait for Runtime {
 	// are manually adding the digests. normally in this situation you'd use
 	// pallet_babe::SameAuthoritiesForever.
 	type EpochChangeTrigger = pallet_babe::ExternalTrigger;
+	type HandleEquivocation = ();
 }
 
 /// Adds one to the given input and returns the final result.
@@ -629,6 +630,12 @@ cfg_if! {
 				fn current_epoch_start() -> SlotNumber {
 					<pallet_babe::Module<Runtime>>::current_epoch_start()
 				}
+
+				fn report_equivocation_extrinsic(
+					equivocation_proof: sp_consensus_babe::EquivocationProof<Header>,
+				) -> Option<<Block as BlockT>::Extrinsic> {
+					Some(Extrinsic::IncludeData(equivocation_proof.encode()))
+				}
 			}
 
 			impl sp_offchain::OffchainWorkerApi<Block> for Runtime {
@@ -655,6 +662,12 @@ cfg_if! {
 					0
 				}
 			}
+
+			impl frame_system_rpc_runtime_api::EventsApi<Block> for Runtime {
+				fn events() -> Vec<u8> {
+					Vec::<()>::new().encode()
+				}
+			}
 		}
 	} else {
 		impl_runtime_apis! {
@@ -820,6 +833,12 @@ cfg_if! {
 				fn current_epoch_start() -> SlotNumber {
 					<pallet_babe::Module<Runtime>>::current_epoch_start()
 				}
+
+				fn report_equivocation_extrinsic(
+					equivocation_proof: sp_consensus_babe::EquivocationProof<Header>,
+				) -> Option<<Block as BlockT>::Extrinsic> {
+					Some(Extrinsic::IncludeData(equivocation_proof.encode()))
+				}
 			}
 
 			impl sp_offchain::OffchainWorkerApi<Block> for Runtime {
@@ -846,6 +865,12 @@ cfg_if! {
 					0
 				}
 			}
+
+			impl frame_system_rpc_runtime_api::EventsApi<Block> for Runtime {
+				fn events() -> Vec<u8> {
+					Vec::<()>::new().encode()
+				}
+			}
 		}
 	}
 }
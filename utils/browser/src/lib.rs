@@ -14,6 +14,12 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
+//! Utilities to run a light client of a Substrate chain in the browser.
+//!
+//! The light client only connects to its peers over WebSocket, through the transport of the web
+//! page: libp2p 0.16, which this crate uses, has no WebRTC transport. The boot nodes of the chain
+//! spec must then listen on `/ws` (or `/wss`) addresses.
+
 use futures01::sync::mpsc as mpsc01;
 use log::{debug, info};
 use std::sync::Arc;
@@ -50,8 +56,11 @@ where
 	config.network.transport = sc_network::config::TransportConfig::Normal {
 		wasm_external_transport: Some(transport.clone()),
 		allow_private_ipv4: true,
+		prefer_ipv6: false,
 		enable_mdns: false,
 		use_yamux_flow_control: true,
+		proxy: None,
+		websocket_tls: None,
 	};
 	config.task_executor = Some(Arc::new(move |fut| {
 		wasm_bindgen_futures::spawn_local(fut)
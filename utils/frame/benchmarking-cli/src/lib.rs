@@ -154,13 +154,39 @@ impl BenchmarkCmd {
 				// Print the table header
 				results[0].0.iter().for_each(|param| print!("{:?},", param.0));
 
-				print!("extrinsic_time,storage_root_time\n");
+				print!("extrinsic_time,storage_root_time,reads,writes\n");
 				// Print the values
 				results.iter().for_each(|result| {
 					let parameters = &result.0;
 					parameters.iter().for_each(|param| print!("{:?},", param.1));
-					// Print extrinsic time and storage root time
-					print!("{:?},{:?}\n", result.1, result.2);
+					// Print extrinsic time, storage root time and storage reads and writes
+					print!("{:?},{:?},{:?},{:?}\n", result.1, result.2, result.3, result.4);
+				});
+
+				// Print the summary of the repetitions of each set of parameters, which are
+				// consecutive in the results.
+				println!();
+				results[0].0.iter().for_each(|param| print!("{:?},", param.0));
+				print!("median_extrinsic_time,worst_extrinsic_time,");
+				print!("median_storage_root_time,worst_storage_root_time,reads,writes\n");
+				results.chunks(self.repeat.max(1) as usize).for_each(|repetitions| {
+					let parameters = &repetitions[0].0;
+					parameters.iter().for_each(|param| print!("{:?},", param.1));
+					let (median_extrinsic, worst_extrinsic) =
+						median_and_max(repetitions.iter().map(|result| result.1).collect());
+					let (median_storage_root, worst_storage_root) =
+						median_and_max(repetitions.iter().map(|result| result.2).collect());
+					let reads = repetitions.iter().map(|result| result.3).max().unwrap_or(0);
+					let writes = repetitions.iter().map(|result| result.4).max().unwrap_or(0);
+					print!(
+						"{:?},{:?},{:?},{:?},{:?},{:?}\n",
+						median_extrinsic,
+						worst_extrinsic,
+						median_storage_root,
+						worst_storage_root,
+						reads,
+						writes,
+					);
 				});
 
 				eprintln!("Done.");
@@ -190,3 +216,9 @@ impl BenchmarkCmd {
 		Ok(())
 	}
 }
+
+/// Returns the median and the maximum of `values`, which must not be empty.
+fn median_and_max(mut values: Vec<u128>) -> (u128, u128) {
+	values.sort();
+	(values[values.len() / 2], values[values.len() - 1])
+}
@@ -18,7 +18,7 @@
 
 use std::sync::Arc;
 
-use codec::{self, Codec, Decode, Encode};
+use codec::{self, Codec, Compact, Decode, Encode};
 use sc_client::{
 	light::blockchain::{future_header, RemoteBlockchain},
 	light::fetcher::{Fetcher, RemoteCallRequest},
@@ -29,6 +29,9 @@ use jsonrpc_core::{
 };
 use jsonrpc_derive::rpc;
 use futures::future::{ready, TryFutureExt};
+use serde::{Deserialize, Serialize};
+use sp_api::{ApiExt, Core};
+use sp_block_builder::BlockBuilder;
 use sp_blockchain::{
 	HeaderBackend,
 	Error as ClientError
@@ -37,10 +40,11 @@ use sp_runtime::{
 	generic::BlockId,
 	traits,
 };
-use sp_core::hexdisplay::HexDisplay;
+use sp_core::{Bytes, ExecutionContext, hexdisplay::HexDisplay};
 use sp_transaction_pool::{TransactionPool, InPoolTransaction};
+use sc_rpc_api::DenyUnsafe;
 
-pub use frame_system_rpc_runtime_api::AccountNonceApi;
+pub use frame_system_rpc_runtime_api::{AccountNonceApi, EventsApi};
 pub use self::gen_client::Client as SystemClient;
 
 /// Future that resolves to account nonce.
@@ -48,7 +52,7 @@ pub type FutureResult<T> = Box<dyn Future<Item = T, Error = Error> + Send>;
 
 /// System RPC methods.
 #[rpc]
-pub trait SystemApi<AccountId, Index> {
+pub trait SystemApi<BlockHash, AccountId, Index> {
 	/// Returns the next valid index (aka nonce) for given account.
 	///
 	/// This method takes into consideration all pending transactions
@@ -56,34 +60,58 @@ pub trait SystemApi<AccountId, Index> {
 	/// it fallbacks to query the index from the runtime (aka. state nonce).
 	#[rpc(name = "system_accountNextIndex", alias("account_nextIndex"))]
 	fn nonce(&self, account: AccountId) -> FutureResult<Index>;
+
+	/// Applies the SCALE-encoded extrinsic on top of the given block, or the best block, without
+	/// importing anything, to predict the outcome of its inclusion in the next block.
+	///
+	/// This RPC is unsafe.
+	#[rpc(name = "system_dryRun")]
+	fn dry_run(&self, extrinsic: Bytes, at: Option<BlockHash>) -> FutureResult<DryRunResult>;
+}
+
+/// The outcome of applying an extrinsic in a dry run.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunResult {
+	/// The SCALE-encoded `ApplyExtrinsicResult`: whether the extrinsic is valid, and if it is,
+	/// whether its dispatch succeeded.
+	pub outcome: Bytes,
+	/// The SCALE-encoded `Vec<EventRecord>` of the events deposited by the extrinsic, or `None`
+	/// if the runtime doesn't expose them.
+	pub events: Option<Bytes>,
 }
 
 const RUNTIME_ERROR: i64 = 1;
+const DECODE_ERROR: i64 = 2;
 
 /// An implementation of System-specific RPC methods on full client.
 pub struct FullSystem<P: TransactionPool, C, B> {
 	client: Arc<C>,
 	pool: Arc<P>,
+	/// Whether to deny unsafe calls
+	deny_unsafe: DenyUnsafe,
 	_marker: std::marker::PhantomData<B>,
 }
 
 impl<P: TransactionPool, C, B> FullSystem<P, C, B> {
 	/// Create new `FullSystem` given client and transaction pool.
-	pub fn new(client: Arc<C>, pool: Arc<P>) -> Self {
+	pub fn new(client: Arc<C>, pool: Arc<P>, deny_unsafe: DenyUnsafe) -> Self {
 		FullSystem {
 			client,
 			pool,
+			deny_unsafe,
 			_marker: Default::default(),
 		}
 	}
 }
 
-impl<P, C, Block, AccountId, Index> SystemApi<AccountId, Index> for FullSystem<P, C, Block>
+impl<P, C, Block, AccountId, Index> SystemApi<Block::Hash, AccountId, Index>
+	for FullSystem<P, C, Block>
 where
 	C: sp_api::ProvideRuntimeApi<Block>,
 	C: HeaderBackend<Block>,
 	C: Send + Sync + 'static,
-	C::Api: AccountNonceApi<Block, AccountId, Index>,
+	C::Api: AccountNonceApi<Block, AccountId, Index> + EventsApi<Block> + BlockBuilder<Block>,
 	P: TransactionPool + 'static,
 	Block: traits::Block,
 	AccountId: Clone + std::fmt::Display + Codec,
@@ -106,6 +134,58 @@ where
 
 		Box::new(result(get_nonce()))
 	}
+
+	fn dry_run(&self, extrinsic: Bytes, at: Option<Block::Hash>) -> FutureResult<DryRunResult> {
+		let dry_run = || -> Result<DryRunResult, Error> {
+			self.deny_unsafe.check_if_safe()?;
+
+			let uxt: Block::Extrinsic = Decode::decode(&mut &*extrinsic)
+				.map_err(|e| dry_run_error(DECODE_ERROR, e))?;
+			let parent_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+			let parent = BlockId::hash(parent_hash);
+			let parent_number = self.client.number(parent_hash)
+				.map_err(|e| dry_run_error(RUNTIME_ERROR, e))?
+				.ok_or_else(|| dry_run_error(
+					RUNTIME_ERROR,
+					ClientError::UnknownBlock(format!("{}", parent_hash)),
+				))?;
+
+			// the extrinsic is applied as the first one of the next block, after its
+			// initialization, as it would be by the block builder.
+			let header = <Block::Header as traits::Header>::new(
+				parent_number + traits::One::one(),
+				Default::default(),
+				Default::default(),
+				parent_hash,
+				Default::default(),
+			);
+			let api = self.client.runtime_api();
+			let with_events = api.has_api::<dyn EventsApi<Block>>(&parent)
+				.map_err(|e| dry_run_error(RUNTIME_ERROR, e))?;
+			api.initialize_block_with_context(&parent, ExecutionContext::BlockConstruction, &header)
+				.map_err(|e| dry_run_error(RUNTIME_ERROR, e))?;
+			let events = || api.events_with_context(&parent, ExecutionContext::BlockConstruction)
+				.map_err(|e| dry_run_error(RUNTIME_ERROR, e));
+			let events_before = if with_events { Some(events()?) } else { None };
+			let outcome = api.apply_extrinsic_with_context(
+				&parent,
+				ExecutionContext::BlockConstruction,
+				uxt,
+			).map_err(|e| dry_run_error(RUNTIME_ERROR, e))?;
+			let events = match events_before {
+				Some(before) => {
+					let events = new_events(&before, &events()?)
+						.ok_or_else(|| dry_run_error(DECODE_ERROR, "Invalid events"))?;
+					Some(events.into())
+				},
+				None => None,
+			};
+
+			Ok(DryRunResult { outcome: outcome.encode().into(), events })
+		};
+
+		Box::new(result(dry_run()))
+	}
 }
 
 /// An implementation of System-specific RPC methods on light client.
@@ -133,7 +213,8 @@ impl<P: TransactionPool, C, F, Block> LightSystem<P, C, F, Block> {
 	}
 }
 
-impl<P, C, F, Block, AccountId, Index> SystemApi<AccountId, Index> for LightSystem<P, C, F, Block>
+impl<P, C, F, Block, AccountId, Index> SystemApi<Block::Hash, AccountId, Index>
+	for LightSystem<P, C, F, Block>
 where
 	P: TransactionPool + 'static,
 	C: HeaderBackend<Block>,
@@ -178,6 +259,37 @@ where
 
 		Box::new(future_nonce)
 	}
+
+	fn dry_run(&self, _extrinsic: Bytes, _at: Option<Block::Hash>) -> FutureResult<DryRunResult> {
+		Box::new(result(Err(Error {
+			code: ErrorCode::MethodNotFound,
+			message: "Dry run is not supported by light clients.".into(),
+			data: None,
+		})))
+	}
+}
+
+fn dry_run_error(code: i64, e: impl std::fmt::Debug) -> Error {
+	Error {
+		code: ErrorCode::ServerError(code),
+		message: "Unable to dry run extrinsic.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+/// Returns the encoded events of `after` that are not in `before`, both being encoded `Vec`s of
+/// the events deposited in the block, before and after applying an extrinsic.
+fn new_events(before: &[u8], after: &[u8]) -> Option<Vec<u8>> {
+	let mut before_records = before;
+	let before_len = <Compact<u32>>::decode(&mut before_records).ok()?.0;
+	let mut after_records = after;
+	let after_len = <Compact<u32>>::decode(&mut after_records).ok()?.0;
+	if !after_records.starts_with(before_records) {
+		return None;
+	}
+	let mut events = Compact(after_len.checked_sub(before_len)?).encode();
+	events.extend_from_slice(&after_records[before_records.len()..]);
+	Some(events)
 }
 
 /// Adjust account nonce from state, so that tx with the nonce will be
@@ -225,10 +337,11 @@ mod tests {
 
 	use futures::executor::block_on;
 	use substrate_test_runtime_client::{
-		runtime::Transfer,
+		runtime::{AccountId, Index, Transfer},
 		AccountKeyring,
 	};
 	use sc_transaction_pool::{BasicPool, FullChainApi};
+	use sp_runtime::{ApplyExtrinsicResult, transaction_validity::InvalidTransaction};
 
 	#[test]
 	fn should_return_next_nonce_for_some_account() {
@@ -254,7 +367,7 @@ mod tests {
 		let ext1 = new_transaction(1);
 		block_on(pool.submit_one(&BlockId::number(0), ext1)).unwrap();
 
-		let accounts = FullSystem::new(client, pool);
+		let accounts = FullSystem::new(client, pool, DenyUnsafe::No);
 
 		// when
 		let nonce = accounts.nonce(AccountKeyring::Alice.into());
@@ -262,4 +375,66 @@ mod tests {
 		// then
 		assert_eq!(nonce.wait().unwrap(), 2);
 	}
+
+	#[test]
+	fn dry_run_should_not_import_the_extrinsic() {
+		// given
+		let _ = env_logger::try_init();
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let pool = Arc::new(
+			BasicPool::new(Default::default(), Arc::new(FullChainApi::new(client.clone()))).0
+		);
+		let accounts = FullSystem::new(client, pool, DenyUnsafe::No);
+
+		let transfer = |nonce: u64| Bytes(Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Bob.into(),
+			amount: 5,
+			nonce,
+		}.into_signed_tx().encode());
+
+		let dry_run = |extrinsic: Bytes| {
+			SystemApi::<_, AccountId, Index>::dry_run(&accounts, extrinsic, None).wait()
+		};
+
+		// when
+		let first = dry_run(transfer(0)).unwrap();
+		let second = dry_run(transfer(0)).unwrap();
+		let stale = dry_run(transfer(1)).unwrap();
+
+		// then
+		let success: ApplyExtrinsicResult = Ok(Ok(()));
+		assert_eq!(first.outcome, Bytes(success.encode()));
+		assert_eq!(first.events, Some(Bytes(Vec::<()>::new().encode())));
+		assert_eq!(second, first);
+		let stale_outcome: ApplyExtrinsicResult = Err(InvalidTransaction::Stale.into());
+		assert_eq!(stale.outcome, Bytes(stale_outcome.encode()));
+		assert!(dry_run(Bytes(vec![1, 2, 3])).is_err());
+	}
+
+	#[test]
+	fn dry_run_should_be_unsafe() {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let pool = Arc::new(
+			BasicPool::new(Default::default(), Arc::new(FullChainApi::new(client.clone()))).0
+		);
+		let accounts = FullSystem::new(client, pool, DenyUnsafe::Yes);
+
+		let result = SystemApi::<_, AccountId, Index>::dry_run(&accounts, Bytes(Vec::new()), None);
+		assert_eq!(
+			result.wait().unwrap_err().message,
+			"RPC call is unsafe to be called externally",
+		);
+	}
+
+	#[test]
+	fn should_extract_new_events() {
+		let before = vec![1u16, 2].encode();
+		let after = vec![1u16, 2, 3, 4].encode();
+
+		assert_eq!(new_events(&before, &after), Some(vec![3u16, 4].encode()));
+		assert_eq!(new_events(&before, &before), Some(Vec::<u16>::new().encode()));
+		assert_eq!(new_events(&after, &before), None);
+		assert_eq!(new_events(&before, &vec![5u16, 2, 3].encode()), None);
+	}
 }
@@ -21,7 +21,8 @@ pub use prometheus::{
 		GenericGauge as Gauge, GenericCounter as Counter,
 		GenericGaugeVec as GaugeVec, GenericCounterVec as CounterVec,
 		AtomicF64 as F64, AtomicI64 as I64, AtomicU64 as U64,
-	}
+	},
+	Histogram, HistogramOpts, HistogramVec,
 };
 use prometheus::{Encoder, TextEncoder, core::Collector};
 use std::net::SocketAddr;